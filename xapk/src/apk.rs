@@ -0,0 +1,188 @@
+use anyhow::Result;
+use std::path::Path;
+use xperformance::utils;
+
+// APK 内的 dex 文件
+#[derive(Debug, Clone)]
+pub struct DexEntry {
+    pub name: String,
+    pub compressed: u64,
+    pub uncompressed: u64,
+}
+
+// 安装后的设备端占用（KB）
+#[derive(Debug, Clone, Default)]
+pub struct Footprint {
+    pub code_kb: u64,
+    pub odex_kb: u64,
+    pub vdex_kb: u64,
+    pub art_kb: u64,
+    pub app_kb: Option<u64>,
+    pub data_kb: Option<u64>,
+    pub cache_kb: Option<u64>,
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u64> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]) as u64)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u64> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as u64)
+}
+
+// 读取 zip 中央目录，列出 classes*.dex 条目
+pub fn list_dex_entries(apk: &Path) -> Result<Vec<DexEntry>> {
+    let data = std::fs::read(apk)?;
+
+    // 从文件末尾查找 End of Central Directory 记录
+    let search_start = data.len().saturating_sub(65_557);
+    let eocd = (search_start..data.len().saturating_sub(22))
+        .rev()
+        .find(|&i| data[i..i + 4] == [0x50, 0x4b, 0x05, 0x06])
+        .ok_or_else(|| anyhow::format_err!("{} is not a valid APK", apk.display()))?;
+
+    let entry_count = read_u16(&data, eocd + 10).unwrap_or(0);
+    let mut offset = read_u32(&data, eocd + 16).unwrap_or(0) as usize;
+    let mut entries = Vec::new();
+
+    for _ in 0..entry_count {
+        if data.get(offset..offset + 4) != Some(&[0x50, 0x4b, 0x01, 0x02]) {
+            break;
+        }
+        let (
+            Some(compressed),
+            Some(uncompressed),
+            Some(name_len),
+            Some(extra_len),
+            Some(comment_len),
+        ) = (
+            read_u32(&data, offset + 20),
+            read_u32(&data, offset + 24),
+            read_u16(&data, offset + 28),
+            read_u16(&data, offset + 30),
+            read_u16(&data, offset + 32),
+        )
+        else {
+            break;
+        };
+        let name_start = offset + 46;
+        let name = data
+            .get(name_start..name_start + name_len as usize)
+            .map(|b| String::from_utf8_lossy(b).to_string())
+            .unwrap_or_default();
+
+        if name.starts_with("classes") && name.ends_with(".dex") {
+            entries.push(DexEntry {
+                name,
+                compressed,
+                uncompressed,
+            });
+        }
+        offset = name_start + (name_len + extra_len + comment_len) as usize;
+    }
+
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(entries)
+}
+
+// 尝试通过 aapt2 / aapt 从 APK 中读取包名
+pub fn read_package_name(apk: &Path) -> Option<String> {
+    let apk = apk.to_string_lossy().to_string();
+    let aapt2 = std::process::Command::new("aapt2")
+        .args(["dump", "packagename", &apk])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string());
+    if let Some(name) = aapt2.filter(|n| !n.is_empty()) {
+        return Some(name);
+    }
+
+    let output = std::process::Command::new("aapt")
+        .args(["dump", "badging", &apk])
+        .output()
+        .ok()?;
+    let badging = String::from_utf8_lossy(&output.stdout);
+    let line = badging.lines().find(|l| l.starts_with("package:"))?;
+    let start = line.find("name='")? + 6;
+    let end = line[start..].find('\'')? + start;
+    Some(line[start..end].to_string())
+}
+
+fn du_kb(serial: Option<&str>, path: &str) -> u64 {
+    utils::run_adb_command_on(serial, &["shell", "du", "-k", "-s", path])
+        .ok()
+        .and_then(|o| o.split_whitespace().next().and_then(|v| v.parse().ok()))
+        .unwrap_or(0)
+}
+
+// 解析 diskstats 中形如 `App Sizes: [1,2,3]` 的数组
+fn parse_diskstats_array(output: &str, key: &str) -> Vec<String> {
+    output
+        .lines()
+        .find_map(|line| line.trim().strip_prefix(key))
+        .map(|rest| {
+            rest.trim()
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .split(',')
+                .map(|v| v.trim().trim_matches('"').to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub fn measure_footprint(serial: Option<&str>, package: &str) -> Result<Footprint> {
+    let mut footprint = Footprint::default();
+
+    let paths = utils::run_adb_command_on(serial, &["shell", "pm", "path", package])?;
+    let base_apk = paths
+        .lines()
+        .find_map(|l| l.trim().strip_prefix("package:"))
+        .ok_or_else(|| anyhow::format_err!("Package {} is not installed", package))?;
+    let code_dir = base_apk
+        .rsplit_once('/')
+        .map(|(dir, _)| dir.to_string())
+        .unwrap_or_default();
+
+    footprint.code_kb = du_kb(serial, &code_dir);
+
+    // oat 目录下的 odex/vdex/art 文件
+    let oat_dir = format!("{}/oat", code_dir);
+    if let Ok(output) = utils::run_adb_command_on(serial, &["shell", "du", "-k", "-a", &oat_dir]) {
+        for line in output.lines() {
+            let mut fields = line.split_whitespace();
+            let (Some(kb), Some(path)) = (fields.next(), fields.next()) else {
+                continue;
+            };
+            let kb: u64 = kb.parse().unwrap_or(0);
+            if path.ends_with(".odex") {
+                footprint.odex_kb += kb;
+            } else if path.ends_with(".vdex") {
+                footprint.vdex_kb += kb;
+            } else if path.ends_with(".art") {
+                footprint.art_kb += kb;
+            }
+        }
+    }
+
+    // diskstats 中的大小单位为字节，且由系统定期刷新
+    if let Ok(output) = utils::run_adb_command_on(serial, &["shell", "dumpsys", "diskstats"]) {
+        let names = parse_diskstats_array(&output, "Package Names:");
+        if let Some(idx) = names.iter().position(|n| n == package) {
+            let value_at = |key: &str| {
+                parse_diskstats_array(&output, key)
+                    .get(idx)
+                    .and_then(|v| v.parse::<u64>().ok())
+                    .map(|bytes| bytes / 1024)
+            };
+            footprint.app_kb = value_at("App Sizes:");
+            footprint.data_kb = value_at("App Data Sizes:");
+            footprint.cache_kb = value_at("Cache Sizes:");
+        }
+    }
+
+    Ok(footprint)
+}
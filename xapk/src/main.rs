@@ -0,0 +1,201 @@
+#![deny(warnings)]
+use anyhow::Result;
+use chrono::Local;
+use clap::Parser;
+use colored::*;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Instant;
+use xperformance::layout::{Category, OutputLayout};
+use xperformance::package;
+use xperformance::stats::Summary;
+use xperformance::utils;
+
+mod apk;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// APK file to install
+    apk: PathBuf,
+
+    /// Package name of the APK (default: read with aapt2/aapt)
+    #[arg(short, long)]
+    package: Option<String>,
+
+    /// Number of timed installs
+    #[arg(short = 'n', long, default_value_t = 1)]
+    runs: usize,
+
+    /// Uninstall the package before each install
+    #[arg(long)]
+    fresh: bool,
+}
+
+fn kb_to_mb(kb: u64) -> f64 {
+    kb as f64 / 1024.0
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    println!("{}", "XApk Profiler".green().bold());
+    println!("APK: {}", args.apk.display().to_string().cyan());
+
+    if !args.apk.is_file() {
+        anyhow::bail!("APK not found: {}", args.apk.display());
+    }
+    if !utils::check_adb_connection() {
+        anyhow::bail!("No Android devices connected");
+    }
+
+    let package_name = match args
+        .package
+        .clone()
+        .or_else(|| apk::read_package_name(&args.apk))
+    {
+        Some(name) => name,
+        None => anyhow::bail!("Could not determine package name, use --package"),
+    };
+    println!("Package: {}", package_name.cyan());
+
+    let apk_kb = std::fs::metadata(&args.apk)?.len() / 1024;
+    let dex_entries = apk::list_dex_entries(&args.apk)?;
+    let dex_kb: u64 = dex_entries.iter().map(|d| d.uncompressed).sum::<u64>() / 1024;
+
+    // 计时安装
+    let apk_path = args.apk.to_string_lossy().to_string();
+    let mut install_times = Vec::new();
+    for run in 1..=args.runs.max(1) {
+        if args.fresh {
+            let _ = utils::run_adb_command(&["uninstall", &package_name]);
+        }
+        let start = Instant::now();
+        let output = utils::run_adb_command(&["install", "-r", &apk_path])?;
+        let elapsed = start.elapsed().as_secs_f64() * 1000.0;
+        if !output.contains("Success") {
+            anyhow::bail!("Install failed: {}", output.trim());
+        }
+        println!(
+            "[{}/{}] Install time: {}",
+            run,
+            args.runs.max(1),
+            format!("{:.0} ms", elapsed).blue()
+        );
+        install_times.push(elapsed);
+    }
+
+    let footprint = apk::measure_footprint(None, &package_name)?;
+    let info = package::query_package_info(None, &package_name).unwrap_or_default();
+    let install = Summary::from_values(&install_times).unwrap_or_default();
+
+    let optional =
+        |v: Option<u64>| v.map_or("-".to_string(), |kb| format!("{:.2} MB", kb_to_mb(kb)));
+    println!();
+    println!(
+        "Version:          {} ({})",
+        info.version_name, info.version_code
+    );
+    println!(
+        "Build:            {} (targetSdk {})",
+        info.build_type(),
+        info.target_sdk
+    );
+    println!("APK size:         {:.2} MB", kb_to_mb(apk_kb));
+    println!(
+        "Dex (in APK):     {:.2} MB in {} files",
+        kb_to_mb(dex_kb),
+        dex_entries.len()
+    );
+    for dex in &dex_entries {
+        println!(
+            "  {:<16} {:.2} MB ({:.2} MB compressed)",
+            dex.name,
+            kb_to_mb(dex.uncompressed / 1024),
+            kb_to_mb(dex.compressed / 1024)
+        );
+    }
+    println!(
+        "Install time:     {:.0} ms (median of {})",
+        install.median, install.count
+    );
+    println!("Code on device:   {:.2} MB", kb_to_mb(footprint.code_kb));
+    println!(
+        "  odex / vdex / art: {:.2} / {:.2} / {:.2} MB",
+        kb_to_mb(footprint.odex_kb),
+        kb_to_mb(footprint.vdex_kb),
+        kb_to_mb(footprint.art_kb)
+    );
+    println!("App (diskstats):  {}", optional(footprint.app_kb));
+    println!("Data:             {}", optional(footprint.data_kb));
+    println!("Cache:            {}", optional(footprint.cache_kb));
+
+    // 本次结果
+    let apk_dir = OutputLayout::session(&package_name)?.dir(Category::Apk);
+    std::fs::create_dir_all(&apk_dir)?;
+
+    let report_path = apk_dir.join(format!("{}_apk_report.csv", package_name));
+    let mut report = std::fs::File::create(&report_path)?;
+    let cell = |v: Option<u64>| v.map(|v| v.to_string()).unwrap_or_default();
+    writeln!(report, "Metric,Value")?;
+    writeln!(report, "APK,{}", args.apk.display())?;
+    writeln!(report, "Version Name,{}", info.version_name)?;
+    writeln!(report, "Version Code,{}", info.version_code)?;
+    writeln!(report, "Build Type,{}", info.build_type())?;
+    writeln!(report, "Target SDK,{}", info.target_sdk)?;
+    writeln!(report, "APK Size (KB),{}", apk_kb)?;
+    writeln!(report, "Dex Size (KB),{}", dex_kb)?;
+    writeln!(report, "Install Time Median (ms),{:.0}", install.median)?;
+    writeln!(report, "Code (KB),{}", footprint.code_kb)?;
+    writeln!(report, "Odex (KB),{}", footprint.odex_kb)?;
+    writeln!(report, "Vdex (KB),{}", footprint.vdex_kb)?;
+    writeln!(report, "Art (KB),{}", footprint.art_kb)?;
+    writeln!(report, "App (KB),{}", cell(footprint.app_kb))?;
+    writeln!(report, "Data (KB),{}", cell(footprint.data_kb))?;
+    writeln!(report, "Cache (KB),{}", cell(footprint.cache_kb))?;
+    for dex in &dex_entries {
+        writeln!(report, "{} (KB),{}", dex.name, dex.uncompressed / 1024)?;
+    }
+    report.flush()?;
+    println!("\n✓ APK report exported to CSV: {}", report_path.display());
+
+    let times_path = apk_dir.join(format!("{}_install_times.csv", package_name));
+    let mut times = std::fs::File::create(&times_path)?;
+    writeln!(times, "Run,Install Time (ms)")?;
+    for (i, t) in install_times.iter().enumerate() {
+        writeln!(times, "{},{:.0}", i + 1, t)?;
+    }
+    times.flush()?;
+    println!("✓ Install times exported to CSV: {}", times_path.display());
+
+    // 跨构建对比的历史记录，每次运行追加一行
+    let history_path = utils::create_log_dir_if_needed(&package_name)?.join("apk_history.csv");
+    let new_file = !history_path.exists();
+    let mut history = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&history_path)?;
+    if new_file {
+        writeln!(history, "Timestamp,Version Name,Version Code,APK (KB),Dex (KB),Install (ms),Code (KB),Odex (KB),Vdex (KB),Art (KB),Data (KB),Cache (KB)")?;
+    }
+    writeln!(
+        history,
+        "{},{},{},{},{},{:.0},{},{},{},{},{},{}",
+        Local::now().format("%Y-%m-%d %H:%M:%S"),
+        info.version_name,
+        info.version_code,
+        apk_kb,
+        dex_kb,
+        install.median,
+        footprint.code_kb,
+        footprint.odex_kb,
+        footprint.vdex_kb,
+        footprint.art_kb,
+        cell(footprint.data_kb),
+        cell(footprint.cache_kb)
+    )?;
+    history.flush()?;
+    println!("✓ Build history updated: {}", history_path.display());
+
+    Ok(())
+}
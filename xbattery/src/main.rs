@@ -0,0 +1,250 @@
+#![deny(warnings)]
+use anyhow::Result;
+use clap::Parser;
+use colored::*;
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::time::{sleep, Duration, Instant};
+use xperformance::chart::{self, TimeSeriesData};
+use xperformance::layout::{Category, OutputLayout};
+use xperformance::power::{self, BatterySample, UidPowerUse};
+use xperformance::utils;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Test duration, e.g. 30m or 8h (default: run until Ctrl-C)
+    #[arg(short, long, value_parser = utils::parse_duration)]
+    duration: Option<std::time::Duration>,
+
+    /// Sampling interval in seconds (default: 60)
+    #[arg(short, long, default_value_t = 60)]
+    interval: u64,
+
+    /// Do not reset batterystats at the start of the test
+    #[arg(long)]
+    no_reset: bool,
+
+    /// Simulate unplugged power so the device discharges while connected over USB
+    #[arg(long)]
+    unplug: bool,
+
+    /// Number of UIDs to show in the drain report
+    #[arg(long, default_value_t = 10)]
+    top: usize,
+}
+
+async fn collect_samples(args: &Args, running: &AtomicBool) -> Vec<BatterySample> {
+    let mut samples = Vec::new();
+    let start = Instant::now();
+    let interval = Duration::from_secs(args.interval.max(1));
+
+    while running.load(Ordering::SeqCst) {
+        match power::sample_battery(None) {
+            Ok(sample) => {
+                println!(
+                    "[{}] Battery: {}%, {} mV, {:.1}°C, current: {}",
+                    sample.timestamp.format("%H:%M:%S"),
+                    sample.level.to_string().green(),
+                    sample.voltage_mv,
+                    sample.temperature_c,
+                    sample
+                        .current_ma
+                        .map_or("-".to_string(), |c| format!("{:.0} mA", c))
+                        .yellow()
+                );
+                samples.push(sample);
+            }
+            Err(e) => {
+                if !utils::is_being_interrupted() {
+                    println!("{}: {}", "Failed to sample battery".red(), e);
+                }
+            }
+        }
+
+        if let Some(duration) = args.duration {
+            if start.elapsed() >= duration {
+                break;
+            }
+        }
+
+        // 分段休眠，以便及时响应 Ctrl-C
+        let next = Instant::now() + interval;
+        while running.load(Ordering::SeqCst) && Instant::now() < next {
+            sleep(Duration::from_millis(500)).await;
+        }
+    }
+
+    samples
+}
+
+fn export_drain_to_csv(path: &Path, items: &[UidPowerUse], hours: f64) -> Result<()> {
+    let total: f64 = items.iter().map(|i| i.mah).sum();
+    let mut file = std::fs::File::create(path)?;
+    writeln!(file, "UID,Label,Packages,mAh,mAh/hour,Share (%)")?;
+    for item in items {
+        writeln!(
+            file,
+            "{},{},{},{:.2},{:.2},{:.1}",
+            item.uid,
+            item.label,
+            item.packages.join(" "),
+            item.mah,
+            item.mah / hours,
+            item.mah * 100.0 / total.max(f64::EPSILON)
+        )?;
+    }
+    file.flush()?;
+    Ok(())
+}
+
+fn write_report(args: &Args, samples: &[BatterySample], items: &[UidPowerUse]) -> Result<()> {
+    let battery_dir = OutputLayout::session("xbattery")?.dir(Category::Battery);
+    std::fs::create_dir_all(&battery_dir)?;
+
+    let (first, last) = (&samples[0], &samples[samples.len() - 1]);
+    let hours =
+        ((last.timestamp - first.timestamp).num_seconds() as f64 / 3600.0).max(1.0 / 3600.0);
+    let drop = first.level as f64 - last.level as f64;
+    let currents: Vec<f64> = samples
+        .iter()
+        .filter_map(|s| s.current_ma.map(|c| c.abs() as f64))
+        .collect();
+
+    println!();
+    println!("Duration:        {:.2} h", hours);
+    println!(
+        "Battery level:   {}% -> {}% ({})",
+        first.level,
+        last.level,
+        format!("{:.2}%/h", drop / hours).red()
+    );
+    if !currents.is_empty() {
+        println!(
+            "Average current: {:.0} mA",
+            currents.iter().sum::<f64>() / currents.len() as f64
+        );
+    }
+
+    let samples_csv = battery_dir.join("battery_samples.csv");
+    power::write_samples_csv(&samples_csv, samples)?;
+    println!(
+        "✓ Battery samples exported to CSV: {}",
+        samples_csv.display()
+    );
+
+    if !items.is_empty() {
+        println!("\nDrain by UID (mAh/hour):");
+        for item in items.iter().take(args.top) {
+            let name = item
+                .packages
+                .first()
+                .cloned()
+                .unwrap_or_else(|| item.label.clone());
+            println!(
+                "  {:>8} {:<40} {:>8.2} mAh  {:>7.2} mAh/h",
+                item.uid.yellow(),
+                name,
+                item.mah,
+                item.mah / hours
+            );
+        }
+        let drain_csv = battery_dir.join("drain_by_uid.csv");
+        export_drain_to_csv(&drain_csv, items, hours)?;
+        println!("✓ Drain report exported to CSV: {}", drain_csv.display());
+    }
+
+    if samples.len() > 1 {
+        let level_chart = battery_dir.join("battery_level_chart.png");
+        chart::draw_time_series_chart(
+            &level_chart,
+            "Battery Level / Temperature",
+            "Level (%) / Temperature (°C)",
+            &[
+                TimeSeriesData::new(
+                    "Level (%)",
+                    samples
+                        .iter()
+                        .map(|s| (s.timestamp, s.level as f64))
+                        .collect(),
+                ),
+                TimeSeriesData::new(
+                    "Temperature (°C)",
+                    samples
+                        .iter()
+                        .map(|s| (s.timestamp, s.temperature_c as f64))
+                        .collect(),
+                ),
+            ],
+        )?;
+        println!("✓ Battery chart generated: {}", level_chart.display());
+
+        if !currents.is_empty() {
+            let current_chart = battery_dir.join("battery_current_chart.png");
+            chart::draw_time_series_chart(
+                &current_chart,
+                "Battery Current",
+                "Current |mA|",
+                &[TimeSeriesData::new(
+                    "Current |mA|",
+                    samples
+                        .iter()
+                        .filter_map(|s| s.current_ma.map(|c| (s.timestamp, c.abs() as f64)))
+                        .collect(),
+                )],
+            )?;
+            println!("✓ Current chart generated: {}", current_chart.display());
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+
+    println!("{}", "XBattery Drain Test".green().bold());
+    println!("Sampling interval: {} seconds", args.interval);
+
+    if !utils::check_adb_connection() {
+        anyhow::bail!("No Android devices connected");
+    }
+
+    if !args.no_reset {
+        power::reset_batterystats(None)?;
+        println!("Batterystats reset");
+    }
+    if args.unplug {
+        power::set_unplugged(None, true)?;
+        println!("Simulating unplugged power");
+    }
+
+    let running = Arc::new(AtomicBool::new(true));
+    let r = running.clone();
+    ctrlc::set_handler(move || {
+        r.store(false, Ordering::SeqCst);
+        utils::set_interrupt_flag();
+        println!("\n程序正在退出...");
+    })?;
+
+    let samples = collect_samples(&args, &running).await;
+
+    if args.unplug {
+        let _ = power::set_unplugged(None, false);
+    }
+
+    if samples.is_empty() {
+        println!("No battery samples collected");
+        return Ok(());
+    }
+
+    let items = power::query_power_use(None).unwrap_or_else(|e| {
+        println!("Failed to read batterystats: {}", e);
+        Vec::new()
+    });
+
+    write_report(&args, &samples, &items)
+}
@@ -0,0 +1,306 @@
+#![deny(warnings)]
+use anyhow::Result;
+use clap::{Parser, ValueEnum};
+use colored::*;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use xperformance::layout::{Category, OutputLayout};
+use xperformance::regenerate;
+use xperformance::report::HtmlReport;
+use xperformance::session::SessionInfo;
+use xperformance::stats::Summary;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Session directories to compare (e.g. log/<package>/<timestamp>)
+    #[arg(required = true, num_args = 2..)]
+    sessions: Vec<PathBuf>,
+
+    /// Statistic used for each metric
+    #[arg(short, long, value_enum, default_value_t = Stat::Mean)]
+    stat: Stat,
+
+    /// Relative difference (%) from the class baseline that is highlighted
+    #[arg(short, long, default_value_t = 10.0)]
+    threshold: f64,
+
+    /// Output directory (default: log/xcompare/<timestamp>/compare)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Stat {
+    Mean,
+    Median,
+    P95,
+    Max,
+}
+
+impl Stat {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Stat::Mean => "mean",
+            Stat::Median => "median",
+            Stat::P95 => "p95",
+            Stat::Max => "max",
+        }
+    }
+
+    fn value(&self, summary: &Summary) -> f64 {
+        match self {
+            Stat::Mean => summary.mean,
+            Stat::Median => summary.median,
+            Stat::P95 => summary.p95,
+            Stat::Max => summary.max,
+        }
+    }
+}
+
+// 一个待对比的会话
+struct Session {
+    path: PathBuf,
+    label: String,
+    class: String,
+    info: Option<SessionInfo>,
+    metrics: HashMap<String, f64>,
+}
+
+impl Session {
+    fn load(path: &Path, stat: Stat) -> Result<Self> {
+        if !path.is_dir() {
+            anyhow::bail!("Session directory not found: {}", path.display());
+        }
+        let info = SessionInfo::load(path)?;
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.display().to_string());
+        let label = match &info {
+            Some(info) if !info.version_name.is_empty() => {
+                format!("{} {} ({})", info.device.model, info.version_name, name)
+            }
+            Some(info) => format!("{} ({})", info.device.model, name),
+            None => name,
+        };
+        let class = info
+            .as_ref()
+            .map(|i| i.device.device_class().to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+
+        let metrics = regenerate::session_metrics(path)?
+            .into_iter()
+            .map(|(source, metric, summary)| {
+                (format!("{}: {}", source, metric), stat.value(&summary))
+            })
+            .collect();
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            label,
+            class,
+            info,
+            metrics,
+        })
+    }
+}
+
+// 相对同档位基准会话的变化百分比，基准自身或缺失数据时为 None
+fn delta_percent(
+    sessions: &[Session],
+    baselines: &HashMap<String, usize>,
+    index: usize,
+    key: &str,
+) -> Option<f64> {
+    let session = &sessions[index];
+    let baseline_index = *baselines.get(&session.class)?;
+    if baseline_index == index {
+        return None;
+    }
+    let value = session.metrics.get(key)?;
+    let base = sessions[baseline_index].metrics.get(key)?;
+    if base.abs() < f64::EPSILON {
+        return None;
+    }
+    Some((value - base) * 100.0 / base.abs())
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    println!("{}", "XCompare".green().bold());
+
+    let sessions = args
+        .sessions
+        .iter()
+        .map(|p| Session::load(p, args.stat))
+        .collect::<Result<Vec<_>>>()?;
+
+    // 每个设备档位的第一个会话作为该档位的基准
+    let mut baselines: HashMap<String, usize> = HashMap::new();
+    for (i, session) in sessions.iter().enumerate() {
+        baselines.entry(session.class.clone()).or_insert(i);
+    }
+
+    // 所有会话中出现过的指标，按首次出现顺序排列
+    let mut keys: Vec<String> = Vec::new();
+    for session in &sessions {
+        let mut session_keys: Vec<&String> = session.metrics.keys().collect();
+        session_keys.sort();
+        for key in session_keys {
+            if !keys.contains(key) {
+                keys.push(key.clone());
+            }
+        }
+    }
+    if keys.is_empty() {
+        anyhow::bail!("No metrics found in the given sessions");
+    }
+
+    for (i, session) in sessions.iter().enumerate() {
+        let role = if baselines.get(&session.class) == Some(&i) {
+            " [baseline]".blue().to_string()
+        } else {
+            String::new()
+        };
+        println!(
+            "  {} {} ({}){}",
+            format!("#{}", i + 1).yellow(),
+            session.label,
+            session.class.cyan(),
+            role
+        );
+    }
+
+    let output_dir = match &args.output {
+        Some(dir) => dir.clone(),
+        None => OutputLayout::session("xcompare")?.dir(Category::Compare),
+    };
+    std::fs::create_dir_all(&output_dir)?;
+
+    // 矩阵：每行一个指标，每个会话一列数值，非基准会话附带变化百分比
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    let mut highlights: Vec<Vec<bool>> = Vec::new();
+    let mut significant: Vec<(String, usize, f64)> = Vec::new();
+    let matrix_path = output_dir.join("compare_matrix.csv");
+    let mut matrix = std::fs::File::create(&matrix_path)?;
+    let mut header = vec!["Metric".to_string()];
+    for (i, session) in sessions.iter().enumerate() {
+        header.push(format!("#{} {}", i + 1, session.label));
+        header.push(format!("#{} Delta (%)", i + 1));
+    }
+    writeln!(matrix, "{}", header.join(","))?;
+
+    for key in &keys {
+        let mut row = vec![key.clone()];
+        let mut highlight = vec![false];
+        let mut csv_row = vec![key.clone()];
+        for (i, session) in sessions.iter().enumerate() {
+            let value = session.metrics.get(key);
+            let delta = delta_percent(&sessions, &baselines, i, key);
+            let cell = match (value, delta) {
+                (Some(v), Some(d)) => format!("{:.2} ({:+.1}%)", v, d),
+                (Some(v), None) => format!("{:.2}", v),
+                (None, _) => "-".to_string(),
+            };
+            let is_significant = delta.is_some_and(|d| d.abs() >= args.threshold);
+            if let (true, Some(d)) = (is_significant, delta) {
+                significant.push((key.clone(), i, d));
+            }
+            row.push(cell);
+            highlight.push(is_significant);
+            csv_row.push(value.map(|v| format!("{:.2}", v)).unwrap_or_default());
+            csv_row.push(delta.map(|d| format!("{:.1}", d)).unwrap_or_default());
+        }
+        writeln!(matrix, "{}", csv_row.join(","))?;
+        rows.push(row);
+        highlights.push(highlight);
+    }
+    matrix.flush()?;
+
+    if significant.is_empty() {
+        println!(
+            "\nNo differences above {:.0}% within the same device class",
+            args.threshold
+        );
+    } else {
+        println!(
+            "\nSignificant differences (>= {:.0}% vs class baseline):",
+            args.threshold
+        );
+        for (key, i, delta) in &significant {
+            let text = format!("{:+.1}%", delta);
+            println!(
+                "  {} {:<60} {}",
+                format!("#{}", i + 1).yellow(),
+                key,
+                if *delta > 0.0 {
+                    text.red()
+                } else {
+                    text.green()
+                }
+            );
+        }
+    }
+    println!(
+        "\n✓ Comparison matrix exported to CSV: {}",
+        matrix_path.display()
+    );
+
+    // HTML 报告
+    let mut report = HtmlReport::new("Session Comparison");
+    report.add_paragraph(&format!(
+        "Metric statistic: {}. Differences are computed against the first session of each device class; cells at or above {:.0}% are highlighted.",
+        args.stat.as_str(),
+        args.threshold
+    ));
+    report.add_heading("Sessions");
+    let session_rows: Vec<Vec<String>> = sessions
+        .iter()
+        .enumerate()
+        .map(|(i, s)| {
+            let device = s.info.as_ref().map(|info| &info.device);
+            vec![
+                format!("#{}", i + 1),
+                s.path.display().to_string(),
+                device
+                    .map(|d| format!("{} {}", d.manufacturer, d.model))
+                    .unwrap_or_else(|| "-".to_string()),
+                s.class.clone(),
+                device
+                    .map(|d| d.android_version.clone())
+                    .unwrap_or_else(|| "-".to_string()),
+                s.info
+                    .as_ref()
+                    .map(|info| info.version_label())
+                    .unwrap_or_else(|| "-".to_string()),
+                if baselines.get(&s.class) == Some(&i) {
+                    "yes".to_string()
+                } else {
+                    String::new()
+                },
+            ]
+        })
+        .collect();
+    report.add_table(
+        &[
+            "#", "Path", "Device", "Class", "Android", "Version", "Baseline",
+        ],
+        &session_rows,
+    );
+
+    report.add_heading("Metrics");
+    let headers: Vec<String> = std::iter::once("Metric".to_string())
+        .chain((1..=sessions.len()).map(|i| format!("#{}", i)))
+        .collect();
+    let headers: Vec<&str> = headers.iter().map(String::as_str).collect();
+    report.add_highlighted_table(&headers, &rows, &highlights);
+
+    let report_path = output_dir.join("report.html");
+    report.write(&report_path)?;
+    println!("✓ Comparison report generated: {}", report_path.display());
+
+    Ok(())
+}
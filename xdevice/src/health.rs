@@ -0,0 +1,120 @@
+use xperformance::utils;
+
+// 电池状态
+#[derive(Debug, Clone, Default)]
+pub struct BatteryStatus {
+    pub level: Option<u32>,
+    pub temperature_c: Option<f32>,
+    pub powered: bool,
+}
+
+// /data 分区使用情况（KB）
+#[derive(Debug, Clone, Default)]
+pub struct StorageStatus {
+    pub total_kb: u64,
+    pub used_kb: u64,
+    pub available_kb: u64,
+}
+
+// top 输出中的一个进程
+#[derive(Debug, Clone)]
+pub struct ProcessCpu {
+    pub pid: String,
+    pub cpu: f32,
+    pub name: String,
+}
+
+pub fn query_battery(serial: &str) -> BatteryStatus {
+    let output = utils::run_adb_command_on(Some(serial), &["shell", "dumpsys", "battery"])
+        .unwrap_or_default();
+    let mut status = BatteryStatus::default();
+
+    for line in output.lines() {
+        let Some((key, value)) = line.trim().split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "level" => status.level = value.parse().ok(),
+            // 温度单位为 0.1 摄氏度
+            "temperature" => status.temperature_c = value.parse::<f32>().ok().map(|t| t / 10.0),
+            "AC powered" | "USB powered" | "Wireless powered" if value == "true" => {
+                status.powered = true
+            }
+            _ => {}
+        }
+    }
+
+    status
+}
+
+pub fn query_storage(serial: &str) -> Option<StorageStatus> {
+    let output = utils::run_adb_command_on(Some(serial), &["shell", "df", "-k", "/data"]).ok()?;
+
+    // Filesystem 1K-blocks Used Available Use% Mounted on
+    let fields: Vec<&str> = output.lines().nth(1)?.split_whitespace().collect();
+    if fields.len() < 4 {
+        return None;
+    }
+    Some(StorageStatus {
+        total_kb: fields[1].parse().ok()?,
+        used_kb: fields[2].parse().ok()?,
+        available_kb: fields[3].parse().ok()?,
+    })
+}
+
+// 通过 top 获取当前CPU占用最高的进程
+pub fn query_top_processes(serial: &str, count: usize) -> Vec<ProcessCpu> {
+    let count_arg = count.to_string();
+    let output = utils::run_adb_command_on(
+        Some(serial),
+        &["shell", "top", "-b", "-n", "1", "-m", &count_arg],
+    )
+    .unwrap_or_default();
+
+    let mut columns: Option<(usize, usize, usize)> = None;
+    let mut processes = Vec::new();
+
+    for line in output.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.contains(&"PID") {
+            // toybox top 的表头把状态和CPU合并为 "S[%CPU]"，拆开以对齐数据列
+            let header: Vec<&str> = fields
+                .iter()
+                .flat_map(|f| f.split('['))
+                .map(|f| f.trim_end_matches(']'))
+                .collect();
+            let find = |name: &str| header.iter().position(|f| *f == name);
+            let name_idx = find("ARGS")
+                .or_else(|| find("NAME"))
+                .or_else(|| find("CMD"));
+            columns = match (find("PID"), find("%CPU"), name_idx) {
+                (Some(pid), Some(cpu), Some(name)) => Some((pid, cpu, name)),
+                _ => None,
+            };
+            continue;
+        }
+
+        let Some((pid_idx, cpu_idx, name_idx)) = columns else {
+            continue;
+        };
+        if fields.len() <= name_idx {
+            continue;
+        }
+        if let Ok(cpu) = fields[cpu_idx].trim_end_matches('%').parse::<f32>() {
+            processes.push(ProcessCpu {
+                pid: fields[pid_idx].to_string(),
+                cpu,
+                name: fields[name_idx..].join(" "),
+            });
+        }
+    }
+
+    processes.sort_by(|a, b| {
+        b.cpu
+            .partial_cmp(&a.cpu)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    processes.truncate(count);
+    processes
+}
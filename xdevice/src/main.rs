@@ -0,0 +1,136 @@
+#![deny(warnings)]
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use colored::*;
+use xperformance::device::{self, DeviceTags};
+use xperformance::utils;
+
+mod health;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// List connected devices with health information (default)
+    List {
+        /// Only show devices carrying this tag
+        #[arg(short, long)]
+        tag: Option<String>,
+
+        /// Number of top CPU processes to show per device
+        #[arg(long, default_value_t = 3)]
+        top: usize,
+    },
+    /// Add tags to a device
+    Tag { serial: String, tags: Vec<String> },
+    /// Remove tags from a device
+    Untag { serial: String, tags: Vec<String> },
+}
+
+fn list_devices(tag: Option<&str>, top: usize) -> Result<()> {
+    let tags = DeviceTags::load()?;
+    let mut serials = utils::list_devices()?;
+    if let Some(tag) = tag {
+        let tagged = tags.serials_with_tag(tag);
+        serials.retain(|s| tagged.contains(s));
+    }
+
+    if serials.is_empty() {
+        println!("No Android devices connected");
+        return Ok(());
+    }
+
+    for serial in &serials {
+        let info = device::query_device_info(Some(serial));
+        let battery = health::query_battery(serial);
+        let storage = health::query_storage(serial);
+
+        println!(
+            "{} {} {} (Android {}, SDK {})",
+            serial.yellow().bold(),
+            info.manufacturer,
+            info.model.cyan(),
+            info.android_version,
+            info.sdk
+        );
+
+        let device_tags = tags.tags_for(serial);
+        if !device_tags.is_empty() {
+            println!("  Tags:        {}", device_tags.join(", ").green());
+        }
+
+        println!(
+            "  Battery:     {}{}, {}",
+            battery.level.map_or("-".to_string(), |l| format!("{}%", l)),
+            if battery.powered { " (charging)" } else { "" },
+            battery
+                .temperature_c
+                .map_or("-".to_string(), |t| format!("{:.1}°C", t))
+        );
+
+        match storage {
+            Some(storage) => println!(
+                "  Storage:     {:.1} GB free of {:.1} GB ({:.0}% used)",
+                storage.available_kb as f64 / 1024.0 / 1024.0,
+                storage.total_kb as f64 / 1024.0 / 1024.0,
+                storage.used_kb as f64 * 100.0 / storage.total_kb.max(1) as f64
+            ),
+            None => println!("  Storage:     -"),
+        }
+
+        let processes = health::query_top_processes(serial, top);
+        if !processes.is_empty() {
+            println!("  Top CPU:");
+            for process in processes {
+                println!(
+                    "    {:>6} {:>6.1}%  {}",
+                    process.pid.yellow(),
+                    process.cpu,
+                    process.name
+                );
+            }
+        }
+        println!();
+    }
+
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    match args.command.unwrap_or(Commands::List { tag: None, top: 3 }) {
+        Commands::List { tag, top } => list_devices(tag.as_deref(), top),
+        Commands::Tag { serial, tags } => {
+            let mut store = DeviceTags::load()?;
+            for tag in &tags {
+                store.add(&serial, tag);
+            }
+            store.save()?;
+            println!(
+                "{} tags: {}",
+                serial.yellow(),
+                store.tags_for(&serial).join(", ").green()
+            );
+            Ok(())
+        }
+        Commands::Untag { serial, tags } => {
+            let mut store = DeviceTags::load()?;
+            for tag in &tags {
+                store.remove(&serial, tag);
+            }
+            store.save()?;
+            println!(
+                "{} tags: {}",
+                serial.yellow(),
+                store.tags_for(&serial).join(", ").green()
+            );
+            Ok(())
+        }
+    }
+}
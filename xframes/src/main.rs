@@ -0,0 +1,470 @@
+#![deny(warnings)]
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use clap::Parser;
+use colored::*;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::time::{sleep, Duration, Instant};
+use xperformance::chart::{self, TimeSeriesData};
+use xperformance::cpu::{self, ThreadRole};
+use xperformance::framestats::{self, FrameStats, FrameTiming, FrameTracker, JankType};
+use xperformance::layout::{Category, OutputLayout};
+use xperformance::report::HtmlReport;
+use xperformance::session::SessionInfo;
+use xperformance::stats::Summary;
+use xperformance::utils;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Package name to monitor
+    #[arg(short, long)]
+    package: String,
+
+    /// Sampling interval in seconds (default: 1)
+    #[arg(short, long, default_value_t = 1)]
+    interval: u64,
+
+    /// Stop after this many seconds (default: run until Ctrl-C)
+    #[arg(short, long)]
+    duration: Option<u64>,
+
+    /// Also sample main thread, RenderThread and GPU completion CPU with pidstat
+    #[arg(long)]
+    threads: bool,
+}
+
+// 每个采样周期的帧统计
+struct IntervalStats {
+    timestamp: DateTime<Local>,
+    frames: usize,
+    fps: f64,
+    jank_percent: f64,
+    p90_ms: f64,
+    // 渲染相关线程的CPU，未开启 --threads 时为空
+    thread_cpu: HashMap<ThreadRole, f32>,
+}
+
+#[derive(Default)]
+struct FrameSession {
+    frames: Vec<(DateTime<Local>, FrameTiming)>,
+    intervals: Vec<IntervalStats>,
+    tracker: FrameTracker,
+}
+
+impl FrameSession {
+    // 只保留上次采样之后的新帧
+    fn add_sample(
+        &mut self,
+        timestamp: DateTime<Local>,
+        frames: Vec<FrameTiming>,
+        secs: f64,
+        thread_cpu: HashMap<ThreadRole, f32>,
+    ) {
+        let new_frames = self.tracker.take_new(frames);
+        let frame_stats = FrameStats::from_frames(&new_frames, secs);
+        let stats = IntervalStats {
+            timestamp,
+            frames: frame_stats.frames,
+            fps: frame_stats.fps,
+            jank_percent: frame_stats.jank_percent,
+            p90_ms: frame_stats.p90_ms,
+            thread_cpu,
+        };
+
+        let threads = ThreadRole::RENDERING
+            .iter()
+            .filter_map(|role| {
+                let cpu = stats.thread_cpu.get(role)?;
+                Some(format!("{} {:.1}%", role.as_str(), cpu))
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        println!(
+            "[{}] FPS: {}, frames: {}, janky: {}, p90: {:.1} ms{}",
+            timestamp.format("%H:%M:%S"),
+            format!("{:.1}", stats.fps).blue(),
+            stats.frames,
+            format!("{:.1}%", stats.jank_percent).red(),
+            stats.p90_ms,
+            if threads.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", threads).magenta().to_string()
+            }
+        );
+
+        self.intervals.push(stats);
+        self.frames
+            .extend(new_frames.into_iter().map(|f| (timestamp, f)));
+    }
+
+    fn jank_counts(&self) -> HashMap<JankType, usize> {
+        let mut counts = HashMap::new();
+        for (_, frame) in &self.frames {
+            *counts.entry(frame.classify()).or_insert(0) += 1;
+        }
+        counts
+    }
+}
+
+async fn collect_frames(args: &Args, running: &AtomicBool) -> FrameSession {
+    let mut session = FrameSession::default();
+    let interval = Duration::from_secs(args.interval.max(1));
+    let start = Instant::now();
+
+    // 丢弃启动前已存在的帧
+    let _ = utils::run_adb_command(&["shell", "dumpsys", "gfxinfo", &args.package, "reset"]);
+    let mut last_read = Instant::now();
+
+    while running.load(Ordering::SeqCst) {
+        // pidstat 本身会阻塞约 1 秒，与周期等待并行以免拉长采样周期
+        let thread_cpu = if args.threads {
+            let (_, result) = tokio::join!(sleep(interval), cpu::sample_cpu(&args.package));
+            match (result, utils::get_process_info(&args.package)) {
+                (Ok((_, _, threads)), Ok(info)) => cpu::cpu_by_role(&threads, &info.pid),
+                _ => HashMap::new(),
+            }
+        } else {
+            sleep(interval).await;
+            HashMap::new()
+        };
+        if let Some(duration) = args.duration {
+            if start.elapsed() >= Duration::from_secs(duration) {
+                break;
+            }
+        }
+
+        let output = match utils::run_adb_command(&[
+            "shell",
+            "dumpsys",
+            "gfxinfo",
+            &args.package,
+            "framestats",
+        ]) {
+            Ok(output) => output,
+            Err(e) => {
+                if !utils::is_being_interrupted() {
+                    println!("\n{}: {}", "Failed to read gfxinfo".red(), e);
+                }
+                break;
+            }
+        };
+
+        // adb 往返会拉长每轮采样，FPS 按两次读取之间实际经过的时间计算
+        let now = Instant::now();
+        let elapsed = now.duration_since(last_read).as_secs_f64();
+        last_read = now;
+
+        let frames = framestats::parse_framestats(&output);
+        session.add_sample(Local::now(), frames, elapsed, thread_cpu);
+    }
+
+    session
+}
+
+fn export_frames_to_csv(path: &Path, session: &FrameSession) -> Result<()> {
+    let mut file = std::fs::File::create(path)?;
+    writeln!(
+        file,
+        "Timestamp,IntendedVsync,TotalMs,UiThreadMs,RenderMs,VsyncDelayMs,JankType"
+    )?;
+    for (timestamp, frame) in &session.frames {
+        writeln!(
+            file,
+            "{},{},{:.2},{:.2},{:.2},{:.2},{}",
+            timestamp.format("%Y-%m-%d %H:%M:%S"),
+            frame.intended_vsync,
+            frame.total_ms(),
+            frame.ui_thread_ms(),
+            frame.render_ms(),
+            frame.vsync_delay_ms(),
+            frame.classify().as_str()
+        )?;
+    }
+    file.flush()?;
+    Ok(())
+}
+
+fn export_intervals_to_csv(path: &Path, session: &FrameSession, threads: bool) -> Result<()> {
+    let mut file = std::fs::File::create(path)?;
+    let mut header = "Timestamp,Frames,FPS,Jank (%),P90 (ms)".to_string();
+    if threads {
+        for role in ThreadRole::RENDERING {
+            header.push_str(&format!(",{} CPU (%)", role.as_str()));
+        }
+    }
+    writeln!(file, "{}", header)?;
+    for stats in &session.intervals {
+        write!(
+            file,
+            "{},{},{:.2},{:.2},{:.2}",
+            stats.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            stats.frames,
+            stats.fps,
+            stats.jank_percent,
+            stats.p90_ms
+        )?;
+        if threads {
+            for role in ThreadRole::RENDERING {
+                match stats.thread_cpu.get(&role) {
+                    Some(cpu) => write!(file, ",{:.2}", cpu)?,
+                    None => write!(file, ",")?,
+                }
+            }
+        }
+        writeln!(file)?;
+    }
+    file.flush()?;
+    Ok(())
+}
+
+fn write_report(args: &Args, session: &FrameSession) -> Result<()> {
+    let frames_dir = OutputLayout::session(&args.package)?.dir(Category::Frames);
+    std::fs::create_dir_all(&frames_dir)?;
+
+    let frames_csv = frames_dir.join(format!("{}_frames.csv", args.package));
+    export_frames_to_csv(&frames_csv, session)?;
+    println!("✓ Frame data exported to CSV: {}", frames_csv.display());
+
+    let intervals_csv = frames_dir.join(format!("{}_fps_data.csv", args.package));
+    export_intervals_to_csv(&intervals_csv, session, args.threads)?;
+    println!("✓ FPS data exported to CSV: {}", intervals_csv.display());
+
+    let mut report = HtmlReport::new(&format!("Frame Report - {}", args.package));
+
+    // 帧耗时分布
+    let totals: Vec<f64> = session.frames.iter().map(|(_, f)| f.total_ms()).collect();
+    let counts = session.jank_counts();
+    let janky: usize = JankType::ALL
+        .iter()
+        .map(|t| counts.get(t).copied().unwrap_or(0))
+        .sum();
+    let total = session.frames.len();
+    let percent = |n: usize| {
+        if total == 0 {
+            0.0
+        } else {
+            n as f64 * 100.0 / total as f64
+        }
+    };
+
+    println!(
+        "Total frames: {}, janky: {} ({})",
+        total,
+        janky,
+        format!("{:.1}%", percent(janky)).red()
+    );
+    report.add_paragraph(&format!(
+        "Total frames: {}, janky frames: {} ({:.1}%)",
+        total,
+        janky,
+        percent(janky)
+    ));
+
+    if let Some(summary) = Summary::from_values(&totals) {
+        println!(
+            "Frame time: p50 {:.1} ms, p90 {:.1} ms, p95 {:.1} ms, p99 {:.1} ms",
+            summary.median, summary.p90, summary.p95, summary.p99
+        );
+        report.add_heading("Frame Time (ms)");
+        report.add_table(
+            &["Metric", "Min", "Mean", "P50", "P90", "P95", "P99", "Max"],
+            &[vec![
+                "Total frame time".to_string(),
+                format!("{:.2}", summary.min),
+                format!("{:.2}", summary.mean),
+                format!("{:.2}", summary.median),
+                format!("{:.2}", summary.p90),
+                format!("{:.2}", summary.p95),
+                format!("{:.2}", summary.p99),
+                format!("{:.2}", summary.max),
+            ]],
+        );
+    }
+
+    // 卡顿分类
+    let rows: Vec<Vec<String>> = JankType::ALL
+        .iter()
+        .map(|t| {
+            let n = counts.get(t).copied().unwrap_or(0);
+            println!("  {:<16} {:>6} ({:.1}%)", t.as_str(), n, percent(n));
+            vec![
+                t.as_str().to_string(),
+                n.to_string(),
+                format!("{:.1}", percent(n)),
+            ]
+        })
+        .collect();
+    report.add_heading("Jank Classification");
+    report.add_table(&["Type", "Frames", "% of all frames"], &rows);
+
+    if args.threads {
+        add_thread_attribution(&mut report, session, &counts, total);
+    }
+
+    if session.intervals.len() > 1 {
+        let fps_chart = frames_dir.join(format!("{}_fps_chart.png", args.package));
+        let series = [
+            TimeSeriesData::new(
+                "FPS",
+                session
+                    .intervals
+                    .iter()
+                    .map(|s| (s.timestamp, s.fps))
+                    .collect(),
+            ),
+            TimeSeriesData::new(
+                "Jank (%)",
+                session
+                    .intervals
+                    .iter()
+                    .map(|s| (s.timestamp, s.jank_percent))
+                    .collect(),
+            ),
+        ];
+        chart::draw_time_series_chart(
+            &fps_chart,
+            &format!("FPS / Jank - {}", args.package),
+            "FPS / Jank (%)",
+            &series,
+        )?;
+        println!("✓ FPS chart generated: {}", fps_chart.display());
+        report.add_heading("FPS over time");
+        report.add_image(&file_name(&fps_chart), "FPS and janky frame percentage");
+    }
+
+    if !totals.is_empty() {
+        let histogram = frames_dir.join(format!("{}_frame_time_histogram.png", args.package));
+        chart::draw_histogram(
+            &histogram,
+            &format!("Frame Time Distribution - {}", args.package),
+            "Frame time (ms)",
+            &totals,
+            20,
+        )?;
+        println!("✓ Frame time histogram generated: {}", histogram.display());
+        report.add_heading("Frame Time Distribution");
+        report.add_image(&file_name(&histogram), "Frame time histogram");
+    }
+
+    let report_path = frames_dir.join("report.html");
+    report.write(&report_path)?;
+    println!("✓ HTML report generated: {}", report_path.display());
+
+    Ok(())
+}
+
+// 对比卡顿周期和流畅周期中各渲染线程的CPU，区分逻辑瓶颈和渲染瓶颈
+fn add_thread_attribution(
+    report: &mut HtmlReport,
+    session: &FrameSession,
+    counts: &HashMap<JankType, usize>,
+    total: usize,
+) {
+    let logic = counts.get(&JankType::SlowUiThread).copied().unwrap_or(0);
+    let rendering = counts.get(&JankType::SlowDraw).copied().unwrap_or(0);
+    let percent = |n: usize| n as f64 * 100.0 / total.max(1) as f64;
+    let bound = format!(
+        "Logic-bound jank (slow_ui_thread): {} frames ({:.1}%), rendering-bound jank (slow_draw): {} frames ({:.1}%)",
+        logic,
+        percent(logic),
+        rendering,
+        percent(rendering)
+    );
+    println!("{}", bound);
+
+    let mean_cpu = |role: ThreadRole, janky: Option<bool>| {
+        let values: Vec<f64> = session
+            .intervals
+            .iter()
+            .filter(|s| janky.is_none_or(|j| (s.jank_percent > 0.0) == j))
+            .filter_map(|s| s.thread_cpu.get(&role).map(|&c| c as f64))
+            .collect();
+        Summary::from_values(&values).map(|s| s.mean)
+    };
+    let cell = |v: Option<f64>| v.map_or("-".to_string(), |v| format!("{:.1}", v));
+
+    println!("Rendering thread CPU (%): all / janky intervals / smooth intervals");
+    let rows: Vec<Vec<String>> = ThreadRole::RENDERING
+        .iter()
+        .map(|&role| {
+            let (all, janky, smooth) = (
+                mean_cpu(role, None),
+                mean_cpu(role, Some(true)),
+                mean_cpu(role, Some(false)),
+            );
+            println!(
+                "  {:<16} {:>6} / {:>6} / {:>6}",
+                role.as_str(),
+                cell(all),
+                cell(janky),
+                cell(smooth)
+            );
+            vec![
+                role.as_str().to_string(),
+                cell(all),
+                cell(janky),
+                cell(smooth),
+            ]
+        })
+        .collect();
+
+    report.add_heading("Rendering Threads");
+    report.add_paragraph(&bound);
+    report.add_table(
+        &[
+            "Thread",
+            "Mean CPU (%)",
+            "Janky intervals (%)",
+            "Smooth intervals (%)",
+        ],
+        &rows,
+    );
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+
+    println!("{}", "XFrames Monitor".green().bold());
+    println!("Monitoring package: {}", args.package.cyan());
+    println!("Sampling interval: {} seconds", args.interval);
+
+    if !utils::check_adb_connection() {
+        anyhow::bail!("No Android devices connected");
+    }
+    utils::get_process_info(&args.package)?;
+
+    // 图表标题附带设备和构建信息
+    chart::set_stamp(
+        &SessionInfo::capture(None, &args.package).stamp_label(),
+        false,
+    );
+
+    let running = Arc::new(AtomicBool::new(true));
+    let r = running.clone();
+    ctrlc::set_handler(move || {
+        r.store(false, Ordering::SeqCst);
+        utils::set_interrupt_flag();
+        println!("\n程序正在退出...");
+    })?;
+
+    let session = collect_frames(&args, &running).await;
+    if session.frames.is_empty() {
+        println!("No frames collected");
+        return Ok(());
+    }
+
+    write_report(&args, &session)
+}
@@ -0,0 +1,493 @@
+#![deny(warnings)]
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use clap::Parser;
+use colored::*;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::time::{sleep, Duration, Instant};
+use xperformance::chart::{self, TimeSeriesData};
+use xperformance::layout::{Category, OutputLayout};
+use xperformance::monitor::{Monitor, MonitorConfig, MonitorSample};
+use xperformance::report::HtmlReport;
+use xperformance::session::SessionInfo;
+use xperformance::stats::Summary;
+use xperformance::utils;
+
+mod script;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Package name of the app under test
+    package: String,
+
+    /// UI script to run instead of monkey (see README for the format)
+    #[arg(long)]
+    script: Option<PathBuf>,
+
+    /// Total number of monkey events
+    #[arg(short, long, default_value_t = 5000)]
+    events: usize,
+
+    /// Number of monkey events per burst
+    #[arg(short, long, default_value_t = 500)]
+    burst: usize,
+
+    /// Delay between monkey events in milliseconds
+    #[arg(long, default_value_t = 0)]
+    throttle: u64,
+
+    /// Monkey random seed (default: derived from the current time)
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Number of times to run the UI script
+    #[arg(long, default_value_t = 1)]
+    repeat: usize,
+
+    /// Idle time in seconds before the first burst and after each burst
+    #[arg(long, default_value_t = 5)]
+    pause: u64,
+
+    /// Sampling interval in seconds
+    #[arg(short, long, default_value_t = 1)]
+    interval: u64,
+}
+
+// 一次资源采样，burst 为采样开始时正在执行的批次（0 表示空闲）
+#[derive(Debug, Clone)]
+struct Sample {
+    timestamp: DateTime<Local>,
+    burst: usize,
+    cpu: Option<f32>,
+    pss_kb: Option<u64>,
+}
+
+// 一个事件批次的执行结果
+#[derive(Debug, Clone)]
+struct BurstResult {
+    name: String,
+    start: DateTime<Local>,
+    end: DateTime<Local>,
+    events: usize,
+    crashes: usize,
+    anrs: usize,
+}
+
+// 订阅 Monitor 的采样，按收到时正在执行的批次归类，与事件注入并行运行
+async fn collect_samples(
+    mut receiver: broadcast::Receiver<MonitorSample>,
+    current_burst: Arc<AtomicUsize>,
+    samples: Arc<Mutex<Vec<Sample>>>,
+) {
+    loop {
+        let sample = match receiver.recv().await {
+            Ok(sample) => sample,
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => break,
+        };
+        let burst = current_burst.load(Ordering::SeqCst);
+        if let Ok(mut samples) = samples.lock() {
+            samples.push(Sample {
+                timestamp: sample.tick.timestamp,
+                burst,
+                cpu: sample.cpu,
+                pss_kb: sample.memory.map(|details| details.total_pss),
+            });
+        }
+    }
+}
+
+// 解析 monkey 输出中的注入事件数、崩溃和 ANR 次数
+fn parse_monkey_output(output: &str) -> (usize, usize, usize) {
+    let mut events = 0;
+    let mut crashes = 0;
+    let mut anrs = 0;
+    for line in output.lines() {
+        let line = line.trim();
+        if let Some(rest) = line.strip_prefix("Events injected:") {
+            events = rest.trim().parse().unwrap_or(0);
+        } else if line.starts_with("// CRASH:") {
+            crashes += 1;
+        } else if line.starts_with("// NOT RESPONDING:") {
+            anrs += 1;
+        }
+    }
+    (events, crashes, anrs)
+}
+
+async fn run_monkey_burst(
+    package: &str,
+    seed: u64,
+    throttle: u64,
+    count: usize,
+) -> Result<(usize, usize, usize)> {
+    let args = vec![
+        "shell".to_string(),
+        "monkey".to_string(),
+        "-p".to_string(),
+        package.to_string(),
+        "-s".to_string(),
+        seed.to_string(),
+        "--throttle".to_string(),
+        throttle.to_string(),
+        "--ignore-crashes".to_string(),
+        "--ignore-timeouts".to_string(),
+        "--ignore-security-exceptions".to_string(),
+        "-v".to_string(),
+        count.to_string(),
+    ];
+    // monkey 会阻塞直到所有事件注入完成，放到阻塞线程中执行以免影响采样
+    let output = tokio::task::spawn_blocking(move || {
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        utils::run_adb_command(&args)
+    })
+    .await??;
+    Ok(parse_monkey_output(&output))
+}
+
+async fn pause(running: &AtomicBool, seconds: u64) {
+    let deadline = Instant::now() + Duration::from_secs(seconds);
+    while running.load(Ordering::SeqCst) && Instant::now() < deadline {
+        sleep(Duration::from_millis(200)).await;
+    }
+}
+
+fn burst_row(index: usize, burst: &BurstResult, samples: &[Sample]) -> Vec<String> {
+    let in_burst: Vec<&Sample> = samples.iter().filter(|s| s.burst == index).collect();
+    let cpu_values: Vec<f64> = in_burst
+        .iter()
+        .filter_map(|s| s.cpu.map(|c| c as f64))
+        .collect();
+    let cpu = Summary::from_values(&cpu_values);
+
+    // 批次开始前的最后一次采样作为内存基线
+    let pss_start = samples
+        .iter()
+        .rev()
+        .find(|s| s.timestamp < burst.start && s.burst != index)
+        .and_then(|s| s.pss_kb)
+        .or_else(|| in_burst.iter().find_map(|s| s.pss_kb));
+    let pss_end = in_burst.iter().rev().find_map(|s| s.pss_kb);
+    let pss_max = in_burst.iter().filter_map(|s| s.pss_kb).max();
+    let cell = |v: Option<u64>| v.map(|v| v.to_string()).unwrap_or_default();
+
+    vec![
+        index.to_string(),
+        burst.name.clone(),
+        burst.start.format("%H:%M:%S").to_string(),
+        burst.end.format("%H:%M:%S").to_string(),
+        format!(
+            "{:.1}",
+            (burst.end - burst.start).num_milliseconds() as f64 / 1000.0
+        ),
+        burst.events.to_string(),
+        burst.crashes.to_string(),
+        burst.anrs.to_string(),
+        cpu.as_ref()
+            .map(|c| format!("{:.1}", c.mean))
+            .unwrap_or_default(),
+        cpu.as_ref()
+            .map(|c| format!("{:.1}", c.max))
+            .unwrap_or_default(),
+        cell(pss_start),
+        cell(pss_end),
+        cell(pss_max),
+        match (pss_start, pss_end) {
+            (Some(start), Some(end)) => (end as i64 - start as i64).to_string(),
+            _ => String::new(),
+        },
+    ]
+}
+
+const BURST_HEADERS: [&str; 14] = [
+    "Burst",
+    "Name",
+    "Start",
+    "End",
+    "Duration (s)",
+    "Events",
+    "Crashes",
+    "ANRs",
+    "CPU Mean (%)",
+    "CPU Max (%)",
+    "PSS Before (KB)",
+    "PSS After (KB)",
+    "PSS Max (KB)",
+    "PSS Delta (KB)",
+];
+
+fn write_report(
+    args: &Args,
+    mode: &str,
+    bursts: &[BurstResult],
+    samples: &[Sample],
+) -> Result<PathBuf> {
+    let monkey_dir = OutputLayout::session(&args.package)?.dir(Category::Monkey);
+    std::fs::create_dir_all(&monkey_dir)?;
+
+    let samples_path = monkey_dir.join("monkey_samples.csv");
+    let mut file = std::fs::File::create(&samples_path)?;
+    writeln!(file, "Timestamp,Burst,CPU Usage (%),PSS (KB)")?;
+    for s in samples {
+        writeln!(
+            file,
+            "{},{},{},{}",
+            s.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            s.burst,
+            s.cpu.map(|c| format!("{:.1}", c)).unwrap_or_default(),
+            s.pss_kb.map(|p| p.to_string()).unwrap_or_default()
+        )?;
+    }
+    file.flush()?;
+    println!("✓ Samples exported to CSV: {}", samples_path.display());
+
+    let rows: Vec<Vec<String>> = bursts
+        .iter()
+        .enumerate()
+        .map(|(i, b)| burst_row(i + 1, b, samples))
+        .collect();
+    let bursts_path = monkey_dir.join("monkey_bursts.csv");
+    let mut file = std::fs::File::create(&bursts_path)?;
+    writeln!(file, "{}", BURST_HEADERS.join(","))?;
+    for row in &rows {
+        writeln!(file, "{}", row.join(","))?;
+    }
+    file.flush()?;
+    println!("✓ Burst summary exported to CSV: {}", bursts_path.display());
+
+    let mut report = HtmlReport::new(&format!("Stress Test - {}", args.package));
+    report.add_paragraph(&format!(
+        "Mode: {}. {} bursts, {} events, {} crashes, {} ANRs. Burst 0 in the samples marks idle periods.",
+        mode,
+        bursts.len(),
+        bursts.iter().map(|b| b.events).sum::<usize>(),
+        bursts.iter().map(|b| b.crashes).sum::<usize>(),
+        bursts.iter().map(|b| b.anrs).sum::<usize>()
+    ));
+    report.add_heading("Bursts");
+    let highlights: Vec<Vec<bool>> = bursts
+        .iter()
+        .map(|b| {
+            (0..BURST_HEADERS.len())
+                .map(|c| (c == 6 && b.crashes > 0) || (c == 7 && b.anrs > 0))
+                .collect()
+        })
+        .collect();
+    report.add_highlighted_table(&BURST_HEADERS, &rows, &highlights);
+
+    let charts = [
+        (
+            "monkey_cpu_chart.png",
+            "Process CPU",
+            TimeSeriesData::new(
+                "CPU Usage (%)",
+                samples
+                    .iter()
+                    .filter_map(|s| Some((s.timestamp, s.cpu? as f64)))
+                    .collect(),
+            ),
+        ),
+        (
+            "monkey_memory_chart.png",
+            "Process PSS",
+            TimeSeriesData::new(
+                "PSS (MB)",
+                samples
+                    .iter()
+                    .filter_map(|s| Some((s.timestamp, s.pss_kb? as f64 / 1024.0)))
+                    .collect(),
+            ),
+        ),
+    ];
+    for (file_name, title, series) in charts {
+        if series.points.len() < 2 {
+            continue;
+        }
+        let path = monkey_dir.join(file_name);
+        let y_desc = series.label.clone();
+        match chart::draw_time_series_chart(&path, title, &y_desc, &[series]) {
+            Ok(()) => {
+                report.add_heading(title);
+                report.add_image(file_name, title);
+                println!("✓ Chart generated: {}", path.display());
+            }
+            Err(e) => println!("Failed to generate chart {}: {}", file_name, e),
+        }
+    }
+
+    let report_path = monkey_dir.join("monkey_report.html");
+    report.write(&report_path)?;
+    Ok(report_path)
+}
+
+fn print_burst(index: usize, burst: &BurstResult) {
+    let status = if burst.crashes + burst.anrs > 0 {
+        format!("{} crashes, {} ANRs", burst.crashes, burst.anrs).red()
+    } else {
+        "ok".green()
+    };
+    println!(
+        "{} {} finished: {} events in {:.1}s ({})",
+        format!("[burst {}]", index).yellow(),
+        burst.name,
+        burst.events,
+        (burst.end - burst.start).num_milliseconds() as f64 / 1000.0,
+        status
+    );
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+
+    println!("{}", "XMonkey Stress Test".green().bold());
+    println!("Package: {}", args.package.cyan());
+
+    if !utils::check_adb_connection() {
+        anyhow::bail!("No Android devices connected");
+    }
+
+    // 图表标题附带设备和构建信息
+    chart::set_stamp(
+        &SessionInfo::capture(None, &args.package).stamp_label(),
+        false,
+    );
+
+    let script = match &args.script {
+        Some(path) => Some(script::parse_script(&std::fs::read_to_string(path)?)?),
+        None => None,
+    };
+    let seed = args
+        .seed
+        .unwrap_or_else(|| Local::now().timestamp() as u64 % 1_000_000);
+    let mode = match &args.script {
+        Some(path) => format!("script {} x{}", path.display(), args.repeat.max(1)),
+        None => format!(
+            "monkey, {} events in bursts of {}, seed {}, throttle {} ms",
+            args.events, args.burst, seed, args.throttle
+        ),
+    };
+    println!("Mode: {}", mode);
+
+    let running = Arc::new(AtomicBool::new(true));
+    let r = running.clone();
+    ctrlc::set_handler(move || {
+        r.store(false, Ordering::SeqCst);
+        utils::set_interrupt_flag();
+        println!("\n程序正在退出...");
+    })?;
+
+    // 启动后台采样
+    let mut config = MonitorConfig::new(&args.package);
+    config.interval = Duration::from_secs(args.interval.max(1));
+    let monitor = Monitor::start(config).with_context(|| {
+        format!(
+            "{} is not running; launch the app before the stress test",
+            args.package
+        )
+    })?;
+    let current_burst = Arc::new(AtomicUsize::new(0));
+    let samples = Arc::new(Mutex::new(Vec::new()));
+    let collector = tokio::spawn(collect_samples(
+        monitor.subscribe(),
+        current_burst.clone(),
+        samples.clone(),
+    ));
+
+    // 第一个批次前的空闲基线
+    pause(&running, args.pause).await;
+
+    let mut bursts: Vec<BurstResult> = Vec::new();
+    match &script {
+        Some(script_bursts) => {
+            'outer: for _ in 0..args.repeat.max(1) {
+                for script_burst in script_bursts {
+                    if !running.load(Ordering::SeqCst) {
+                        break 'outer;
+                    }
+                    current_burst.store(bursts.len() + 1, Ordering::SeqCst);
+                    let start = Local::now();
+                    for step in &script_burst.steps {
+                        if !running.load(Ordering::SeqCst) {
+                            break;
+                        }
+                        if let Err(e) = script::run_step(step).await {
+                            println!("Failed to run {:?}: {}", step, e);
+                        }
+                    }
+                    let burst = BurstResult {
+                        name: script_burst.name.clone(),
+                        start,
+                        end: Local::now(),
+                        events: script_burst.event_count(),
+                        crashes: 0,
+                        anrs: 0,
+                    };
+                    current_burst.store(0, Ordering::SeqCst);
+                    print_burst(bursts.len() + 1, &burst);
+                    bursts.push(burst);
+                    pause(&running, args.pause).await;
+                }
+            }
+        }
+        None => {
+            let burst_size = args.burst.max(1);
+            let mut remaining = args.events;
+            while remaining > 0 && running.load(Ordering::SeqCst) {
+                let count = remaining.min(burst_size);
+                let index = bursts.len() + 1;
+                current_burst.store(index, Ordering::SeqCst);
+                let start = Local::now();
+                // 每个批次使用不同的种子，结果仍可通过初始种子复现
+                let (events, crashes, anrs) = match run_monkey_burst(
+                    &args.package,
+                    seed + index as u64,
+                    args.throttle,
+                    count,
+                )
+                .await
+                {
+                    Ok(result) => result,
+                    Err(e) => {
+                        if !utils::is_being_interrupted() {
+                            println!("{}: {}", "Monkey failed".red(), e);
+                        }
+                        (0, 0, 0)
+                    }
+                };
+                let burst = BurstResult {
+                    name: format!("monkey seed {}", seed + index as u64),
+                    start,
+                    end: Local::now(),
+                    events,
+                    crashes,
+                    anrs,
+                };
+                current_burst.store(0, Ordering::SeqCst);
+                print_burst(index, &burst);
+                bursts.push(burst);
+                remaining -= count;
+                pause(&running, args.pause).await;
+            }
+        }
+    }
+
+    monitor.stop().await?;
+    let _ = collector.await;
+
+    if bursts.is_empty() {
+        println!("No bursts were run");
+        return Ok(());
+    }
+
+    let samples = samples.lock().map(|s| s.clone()).unwrap_or_default();
+    let report_path = write_report(&args, &mode, &bursts, &samples)?;
+    println!("✓ Stress test report generated: {}", report_path.display());
+
+    Ok(())
+}
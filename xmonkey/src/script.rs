@@ -0,0 +1,139 @@
+use anyhow::Result;
+use std::time::Duration;
+use xperformance::utils;
+
+// UI 脚本中的一个步骤，通过 `adb shell input` 执行
+#[derive(Debug, Clone)]
+pub enum ScriptStep {
+    Tap(u32, u32),
+    Swipe(u32, u32, u32, u32, u32),
+    Text(String),
+    Key(String),
+    Sleep(Duration),
+}
+
+// 以 `burst <name>` 分隔的一组步骤
+#[derive(Debug, Clone)]
+pub struct ScriptBurst {
+    pub name: String,
+    pub steps: Vec<ScriptStep>,
+}
+
+impl ScriptBurst {
+    // 注入的输入事件数（不含 sleep）
+    pub fn event_count(&self) -> usize {
+        self.steps
+            .iter()
+            .filter(|s| !matches!(s, ScriptStep::Sleep(_)))
+            .count()
+    }
+}
+
+fn parse_numbers(args: &[&str], line_no: usize) -> Result<Vec<u32>> {
+    args.iter()
+        .map(|a| {
+            a.parse::<u32>()
+                .map_err(|_| anyhow::format_err!("Line {}: invalid number '{}'", line_no, a))
+        })
+        .collect()
+}
+
+// 脚本格式，每行一条命令，# 开头为注释：
+//   burst <name>                 开始新的事件批次
+//   tap <x> <y>
+//   swipe <x1> <y1> <x2> <y2> [ms]
+//   text <string>
+//   key <keycode>                如 KEYCODE_BACK
+//   sleep <ms>
+pub fn parse_script(content: &str) -> Result<Vec<ScriptBurst>> {
+    let mut bursts: Vec<ScriptBurst> = Vec::new();
+
+    for (i, line) in content.lines().enumerate() {
+        let line_no = i + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        let args: Vec<&str> = rest.split_whitespace().collect();
+
+        if command == "burst" {
+            let name = if rest.is_empty() {
+                format!("burst {}", bursts.len() + 1)
+            } else {
+                rest.to_string()
+            };
+            bursts.push(ScriptBurst {
+                name,
+                steps: Vec::new(),
+            });
+            continue;
+        }
+
+        let step = match command {
+            "tap" => match parse_numbers(&args, line_no)?.as_slice() {
+                [x, y] => ScriptStep::Tap(*x, *y),
+                _ => anyhow::bail!("Line {}: usage: tap <x> <y>", line_no),
+            },
+            "swipe" => match parse_numbers(&args, line_no)?.as_slice() {
+                [x1, y1, x2, y2] => ScriptStep::Swipe(*x1, *y1, *x2, *y2, 300),
+                [x1, y1, x2, y2, ms] => ScriptStep::Swipe(*x1, *y1, *x2, *y2, *ms),
+                _ => anyhow::bail!("Line {}: usage: swipe <x1> <y1> <x2> <y2> [ms]", line_no),
+            },
+            "text" if !rest.is_empty() => ScriptStep::Text(rest.to_string()),
+            "key" if args.len() == 1 => ScriptStep::Key(args[0].to_string()),
+            "sleep" => match parse_numbers(&args, line_no)?.as_slice() {
+                [ms] => ScriptStep::Sleep(Duration::from_millis(*ms as u64)),
+                _ => anyhow::bail!("Line {}: usage: sleep <ms>", line_no),
+            },
+            _ => anyhow::bail!("Line {}: unknown command '{}'", line_no, line),
+        };
+
+        // 第一个 burst 之前的步骤归入隐式批次
+        if bursts.is_empty() {
+            bursts.push(ScriptBurst {
+                name: "burst 1".to_string(),
+                steps: Vec::new(),
+            });
+        }
+        if let Some(burst) = bursts.last_mut() {
+            burst.steps.push(step);
+        }
+    }
+
+    bursts.retain(|b| !b.steps.is_empty());
+    if bursts.is_empty() {
+        anyhow::bail!("Script contains no steps");
+    }
+    Ok(bursts)
+}
+
+pub async fn run_step(step: &ScriptStep) -> Result<()> {
+    match step {
+        ScriptStep::Tap(x, y) => {
+            utils::run_adb_command(&["shell", "input", "tap", &x.to_string(), &y.to_string()])?;
+        }
+        ScriptStep::Swipe(x1, y1, x2, y2, ms) => {
+            utils::run_adb_command(&[
+                "shell",
+                "input",
+                "swipe",
+                &x1.to_string(),
+                &y1.to_string(),
+                &x2.to_string(),
+                &y2.to_string(),
+                &ms.to_string(),
+            ])?;
+        }
+        ScriptStep::Text(text) => {
+            // input text 中空格需转义为 %s
+            utils::run_adb_command(&["shell", "input", "text", &text.replace(' ', "%s")])?;
+        }
+        ScriptStep::Key(key) => {
+            utils::run_adb_command(&["shell", "input", "keyevent", key])?;
+        }
+        ScriptStep::Sleep(duration) => tokio::time::sleep(*duration).await,
+    }
+    Ok(())
+}
@@ -0,0 +1,1025 @@
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use plotters::chart::{MeshStyle, SeriesLabelStyle};
+use plotters::coord::CoordTranslate;
+use plotters::element::PathElement;
+use plotters::prelude::*;
+use plotters::style::RGBColor;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+// 所有图表统一使用的尺寸，可由配置修改
+static CHART_SIZE: Mutex<(u32, u32)> = Mutex::new((1920, 1080));
+
+pub fn set_chart_size(size: (u32, u32)) {
+    *CHART_SIZE.lock().unwrap() = size;
+}
+
+pub fn chart_size() -> (u32, u32) {
+    *CHART_SIZE.lock().unwrap()
+}
+
+// 每条曲线最多绘制的点数，超过时降采样；0 表示不降采样
+pub const DEFAULT_MAX_POINTS: usize = 2000;
+static CHART_MAX_POINTS: Mutex<usize> = Mutex::new(DEFAULT_MAX_POINTS);
+
+pub fn set_max_points(max_points: usize) {
+    *CHART_MAX_POINTS.lock().unwrap() = max_points;
+}
+
+pub fn max_points() -> usize {
+    *CHART_MAX_POINTS.lock().unwrap()
+}
+
+// 按时间顺序分桶，每桶保留最小值和最大值，长时间会话的尖峰和低谷不会被平滑掉
+pub fn downsample<X: Copy, Y: Copy + PartialOrd>(
+    points: &[(X, Y)],
+    max_points: usize,
+) -> Vec<(X, Y)> {
+    if max_points < 4 || points.len() <= max_points {
+        return points.to_vec();
+    }
+    // 首尾两点单独保留，中间每桶贡献两个点
+    let inner = &points[1..points.len() - 1];
+    let buckets = (max_points - 2) / 2;
+    let bucket_size = inner.len().div_ceil(buckets);
+    let mut sampled = Vec::with_capacity(max_points);
+    sampled.push(points[0]);
+    for bucket in inner.chunks(bucket_size) {
+        let (mut min, mut max) = (0, 0);
+        for (i, point) in bucket.iter().enumerate() {
+            if point.1 < bucket[min].1 {
+                min = i;
+            }
+            if point.1 > bucket[max].1 {
+                max = i;
+            }
+        }
+        sampled.push(bucket[min.min(max)]);
+        if min != max {
+            sampled.push(bucket[min.max(max)]);
+        }
+    }
+    sampled.push(points[points.len() - 1]);
+    sampled
+}
+
+// "1280x720" 形式的图表尺寸
+pub fn parse_chart_size(text: &str) -> Result<(u32, u32)> {
+    let (width, height) = text.trim().split_once(['x', 'X']).ok_or_else(|| {
+        anyhow::format_err!("Invalid chart size '{}', expected WIDTHxHEIGHT", text)
+    })?;
+    let size = (width.trim().parse::<u32>()?, height.trim().parse::<u32>()?);
+    if size.0 < 320 || size.1 < 240 {
+        anyhow::bail!("Chart size '{}' is too small (minimum 320x240)", text);
+    }
+    Ok(size)
+}
+
+// 多条曲线共用的调色板
+pub const SERIES_COLORS: [RGBColor; 12] = [
+    RGBColor(255, 0, 0),
+    RGBColor(0, 0, 255),
+    RGBColor(0, 255, 0),
+    RGBColor(255, 255, 0),
+    RGBColor(255, 0, 255),
+    RGBColor(0, 255, 255),
+    RGBColor(128, 0, 0),   // Dark Red
+    RGBColor(0, 128, 0),   // Dark Green
+    RGBColor(0, 0, 128),   // Dark Blue
+    RGBColor(128, 128, 0), // Olive
+    RGBColor(128, 0, 128), // Purple
+    RGBColor(0, 128, 128), // Teal
+];
+
+// 图表配色：背景、文字与坐标轴、网格、底纹、标记线和曲线调色板
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChartTheme {
+    pub background: RGBColor,
+    pub foreground: RGBColor,
+    pub grid: RGBColor,
+    pub shade: RGBColor,
+    pub marker: RGBColor,
+    pub series: Vec<RGBColor>,
+}
+
+impl ChartTheme {
+    pub fn light() -> Self {
+        Self {
+            background: WHITE,
+            foreground: BLACK,
+            grid: BLACK,
+            shade: RGBColor(200, 200, 200),
+            marker: RED,
+            series: SERIES_COLORS.to_vec(),
+        }
+    }
+
+    // 深色背景下去掉深蓝、深红等看不清的颜色
+    pub fn dark() -> Self {
+        Self {
+            background: RGBColor(30, 30, 30),
+            foreground: RGBColor(220, 220, 220),
+            grid: WHITE,
+            shade: RGBColor(90, 90, 90),
+            marker: RGBColor(255, 85, 85),
+            series: vec![
+                RGBColor(255, 99, 71),   // Tomato
+                RGBColor(100, 149, 237), // Cornflower Blue
+                RGBColor(50, 205, 50),   // Lime Green
+                RGBColor(255, 215, 0),   // Gold
+                RGBColor(238, 130, 238), // Violet
+                RGBColor(0, 206, 209),   // Turquoise
+                RGBColor(255, 165, 0),   // Orange
+                RGBColor(144, 238, 144), // Light Green
+                RGBColor(135, 206, 250), // Light Sky Blue
+                RGBColor(240, 230, 140), // Khaki
+                RGBColor(221, 160, 221), // Plum
+                RGBColor(127, 255, 212), // Aquamarine
+            ],
+        }
+    }
+
+    pub fn series_color(&self, index: usize) -> RGBColor {
+        self.series[index % self.series.len()]
+    }
+}
+
+// 自定义主题文件，未指定的颜色沿用 base（light 或 dark）
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ThemeFile {
+    base: Option<String>,
+    background: Option<String>,
+    foreground: Option<String>,
+    grid: Option<String>,
+    shade: Option<String>,
+    marker: Option<String>,
+    series: Option<Vec<String>>,
+}
+
+// "#RRGGBB" 形式的颜色
+fn parse_color(text: &str) -> Result<RGBColor> {
+    let hex = text.trim().trim_start_matches('#');
+    let channel = |i: usize| {
+        hex.get(i..i + 2)
+            .and_then(|h| u8::from_str_radix(h, 16).ok())
+            .ok_or_else(|| anyhow::format_err!("Invalid color '{}', expected #RRGGBB", text))
+    };
+    if hex.len() != 6 {
+        anyhow::bail!("Invalid color '{}', expected #RRGGBB", text);
+    }
+    Ok(RGBColor(channel(0)?, channel(2)?, channel(4)?))
+}
+
+// light、dark 或自定义主题的 TOML 文件路径
+pub fn parse_chart_theme(text: &str) -> Result<ChartTheme> {
+    match text.trim() {
+        "light" => return Ok(ChartTheme::light()),
+        "dark" => return Ok(ChartTheme::dark()),
+        _ => {}
+    }
+    let content = std::fs::read_to_string(text).map_err(|e| {
+        anyhow::format_err!(
+            "Invalid chart theme '{}' (expected light, dark or a .toml file): {}",
+            text,
+            e
+        )
+    })?;
+    let file: ThemeFile = toml::from_str(&content)?;
+    let mut theme = match file.base.as_deref() {
+        None | Some("light") => ChartTheme::light(),
+        Some("dark") => ChartTheme::dark(),
+        Some(other) => anyhow::bail!("Unknown base theme '{}', expected light or dark", other),
+    };
+    for (value, slot) in [
+        (&file.background, &mut theme.background),
+        (&file.foreground, &mut theme.foreground),
+        (&file.grid, &mut theme.grid),
+        (&file.shade, &mut theme.shade),
+        (&file.marker, &mut theme.marker),
+    ] {
+        if let Some(value) = value {
+            *slot = parse_color(value)?;
+        }
+    }
+    if let Some(series) = &file.series {
+        theme.series = series
+            .iter()
+            .map(|c| parse_color(c))
+            .collect::<Result<_>>()?;
+        if theme.series.is_empty() {
+            anyhow::bail!("Chart theme series must list at least one color");
+        }
+    }
+    Ok(theme)
+}
+
+// 所有图表统一使用的配色，可由 --chart-theme 修改
+static CHART_THEME: Mutex<Option<ChartTheme>> = Mutex::new(None);
+
+pub fn set_theme(theme: ChartTheme) {
+    *CHART_THEME.lock().unwrap() = Some(theme);
+}
+
+pub fn theme() -> ChartTheme {
+    CHART_THEME
+        .lock()
+        .unwrap()
+        .clone()
+        .unwrap_or_else(ChartTheme::light)
+}
+
+// 按主题前景色绘制的文字，用于标题、坐标轴和图例
+pub fn text_style(size: u32) -> TextStyle<'static> {
+    ("sans-serif", size).into_font().color(&theme().foreground)
+}
+
+// 坐标轴、网格线和刻度标签按主题着色，网格透明度与 plotters 默认一致
+pub fn themed_mesh<'m, 'a, 'b, X: Ranged, Y: Ranged, DB: DrawingBackend>(
+    mesh: &'m mut MeshStyle<'a, 'b, X, Y, DB>,
+) -> &'m mut MeshStyle<'a, 'b, X, Y, DB> {
+    let theme = theme();
+    mesh.axis_style(theme.foreground)
+        .bold_line_style(theme.grid.mix(0.2))
+        .light_line_style(theme.grid.mix(0.1))
+        .label_style(text_style(12))
+}
+
+// 图例的背景、边框和文字按主题着色
+pub fn themed_legend<'m, 'a, 'b, DB: DrawingBackend + 'a, CT: CoordTranslate>(
+    legend: &'m mut SeriesLabelStyle<'a, 'b, DB, CT>,
+) -> &'m mut SeriesLabelStyle<'a, 'b, DB, CT> {
+    let theme = theme();
+    legend
+        .background_style(theme.background.mix(0.8))
+        .border_style(theme.foreground)
+        .label_font(text_style(15))
+}
+
+// 图表附带的设备和构建信息，使单独转发的 PNG 也能自描述
+#[derive(Clone)]
+struct ChartStamp {
+    label: String,
+    in_file_names: bool,
+}
+
+// 按设备保存，多设备并行监控时各自使用自己的标签
+static CHART_STAMP: Mutex<Option<HashMap<Option<String>, ChartStamp>>> = Mutex::new(None);
+
+// 设置后所有图表标题附带该标签，in_file_names 为 true 时文件名也附带
+pub fn set_stamp(label: &str, in_file_names: bool) {
+    if let Ok(mut stamps) = CHART_STAMP.lock() {
+        stamps.get_or_insert_with(HashMap::new).insert(
+            crate::utils::adb_serial(),
+            ChartStamp {
+                label: label.to_string(),
+                in_file_names,
+            },
+        );
+    }
+}
+
+// 当前设备的标签，没有时使用未指定设备时设置的标签
+fn current_stamp() -> Option<ChartStamp> {
+    let stamps = CHART_STAMP.lock().ok()?;
+    let stamps = stamps.as_ref()?;
+    stamps
+        .get(&crate::utils::adb_serial())
+        .or_else(|| stamps.get(&None))
+        .cloned()
+}
+
+pub fn stamped_title(title: &str) -> String {
+    match current_stamp() {
+        Some(stamp) if !stamp.label.is_empty() => format!("{} [{}]", title, stamp.label),
+        _ => title.to_string(),
+    }
+}
+
+// 在扩展名前插入标签，如 com.foo_cpu_chart_Pixel_7_Android_14_1.2.3.png
+pub fn stamped_file_name(file_name: &str) -> String {
+    let Some(stamp) = current_stamp() else {
+        return file_name.to_string();
+    };
+    if !stamp.in_file_names || stamp.label.is_empty() {
+        return file_name.to_string();
+    }
+
+    let mut slug = String::new();
+    for c in stamp.label.chars() {
+        if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+            slug.push(c);
+        } else if !slug.ends_with('_') {
+            slug.push('_');
+        }
+    }
+    let slug = slug.trim_matches('_');
+
+    match file_name.rsplit_once('.') {
+        Some((stem, ext)) => format!("{}_{}.{}", stem, slug, ext),
+        None => format!("{}_{}", file_name, slug),
+    }
+}
+
+// 预热结束时间，时间序列图表中预热区间加灰色底纹；与标签一样按设备保存
+static WARMUP_END: Mutex<Option<HashMap<Option<String>, DateTime<Local>>>> = Mutex::new(None);
+
+pub fn set_warmup_end(end: Option<DateTime<Local>>) {
+    if let Ok(mut warmup_ends) = WARMUP_END.lock() {
+        let warmup_ends = warmup_ends.get_or_insert_with(HashMap::new);
+        match end {
+            Some(end) => warmup_ends.insert(crate::utils::adb_serial(), end),
+            None => warmup_ends.remove(&crate::utils::adb_serial()),
+        };
+    }
+}
+
+pub fn warmup_end() -> Option<DateTime<Local>> {
+    let warmup_ends = WARMUP_END.lock().ok()?;
+    warmup_ends
+        .as_ref()?
+        .get(&crate::utils::adb_serial())
+        .copied()
+}
+
+pub const WARMUP_LABEL: &str = "Warmup (excluded from stats)";
+
+// 预热区间的底纹矩形；图表时间范围不含预热区间时返回 None
+pub fn warmup_shade<Y: Copy + 'static>(
+    min_time: DateTime<Local>,
+    max_time: DateTime<Local>,
+    y_min: Y,
+    y_max: Y,
+) -> Option<Rectangle<(DateTime<Local>, Y)>> {
+    let end = warmup_end()?.min(max_time);
+    if end <= min_time {
+        return None;
+    }
+    Some(Rectangle::new(
+        [(min_time, y_min), (end, y_max)],
+        theme().shade.mix(0.4).filled(),
+    ))
+}
+
+// 用户在监控过程中插入的事件标记，所有时间序列图表中画竖线和标签
+static USER_MARKERS: Mutex<Vec<(DateTime<Local>, String)>> = Mutex::new(Vec::new());
+
+pub fn add_user_marker(time: DateTime<Local>, label: &str) {
+    if let Ok(mut markers) = USER_MARKERS.lock() {
+        markers.push((time, label.to_string()));
+    }
+}
+
+pub fn set_user_markers(markers: Vec<(DateTime<Local>, String)>) {
+    if let Ok(mut current) = USER_MARKERS.lock() {
+        *current = markers;
+    }
+}
+
+pub fn user_markers() -> Vec<(DateTime<Local>, String)> {
+    USER_MARKERS
+        .lock()
+        .map(|markers| markers.clone())
+        .unwrap_or_default()
+}
+
+// 图表时间范围内的用户标记竖线
+pub fn user_marker_lines<Y: Copy + 'static>(
+    min_time: DateTime<Local>,
+    max_time: DateTime<Local>,
+    y_min: Y,
+    y_max: Y,
+) -> Vec<PathElement<(DateTime<Local>, Y)>> {
+    let color = theme().marker;
+    user_markers()
+        .into_iter()
+        .filter(|(time, _)| *time >= min_time && *time <= max_time)
+        .map(|(time, _)| {
+            PathElement::new(vec![(time, y_min), (time, y_max)], color.stroke_width(2))
+        })
+        .collect()
+}
+
+// 用户标记的标签文字，画在竖线右侧 label_y 的高度
+pub fn user_marker_labels<Y: Copy + 'static>(
+    min_time: DateTime<Local>,
+    max_time: DateTime<Local>,
+    label_y: Y,
+) -> Vec<Text<'static, (DateTime<Local>, Y), String>> {
+    let style = ("sans-serif", 14).into_font().color(&theme().marker);
+    user_markers()
+        .into_iter()
+        .filter(|(time, _)| *time >= min_time && *time <= max_time)
+        .map(|(time, label)| Text::new(format!(" {}", label), (time, label_y), style.clone()))
+        .collect()
+}
+
+// 时间区间底纹，如前台 Activity；muted 的区间（如后台）使用底纹色
+#[derive(Debug, Clone)]
+pub struct Band {
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    pub label: String,
+    pub muted: bool,
+}
+
+// 一组同标签的底纹：标签、颜色和矩形
+pub type BandGroup<Y> = (String, RGBAColor, Vec<Rectangle<(DateTime<Local>, Y)>>);
+
+// 按标签分组的底纹矩形，同一标签同色、图例只出现一次
+pub fn band_groups<Y: Copy + 'static>(
+    bands: &[Band],
+    min_time: DateTime<Local>,
+    max_time: DateTime<Local>,
+    y_min: Y,
+    y_max: Y,
+) -> Vec<BandGroup<Y>> {
+    let theme = theme();
+    let mut groups: Vec<BandGroup<Y>> = Vec::new();
+    let mut colored = 0;
+    for band in bands
+        .iter()
+        .filter(|b| b.end > min_time && b.start < max_time)
+    {
+        let index = match groups.iter().position(|(label, _, _)| *label == band.label) {
+            Some(index) => index,
+            None => {
+                let color = if band.muted {
+                    theme.shade.mix(0.3)
+                } else {
+                    colored += 1;
+                    theme.series_color(colored - 1).mix(0.12)
+                };
+                groups.push((band.label.clone(), color, Vec::new()));
+                groups.len() - 1
+            }
+        };
+        let color = groups[index].1;
+        groups[index].2.push(Rectangle::new(
+            [
+                (band.start.max(min_time), y_min),
+                (band.end.min(max_time), y_max),
+            ],
+            color.filled(),
+        ));
+    }
+    groups
+}
+
+// 一条以数值为X轴的曲线
+#[derive(Debug, Clone)]
+pub struct LineSeriesData {
+    pub label: String,
+    pub points: Vec<(f64, f64)>,
+}
+
+impl LineSeriesData {
+    pub fn new(label: impl Into<String>, points: Vec<(f64, f64)>) -> Self {
+        Self {
+            label: label.into(),
+            points,
+        }
+    }
+}
+
+// 绘制以数值为X轴的多曲线图表（例如按启动序号、按经过秒数）
+pub fn draw_line_chart(
+    path: &Path,
+    title: &str,
+    x_desc: &str,
+    y_desc: &str,
+    series: &[LineSeriesData],
+) -> Result<()> {
+    let all_points = series.iter().flat_map(|s| s.points.iter());
+    let (mut min_x, mut max_x, mut max_y) = (f64::MAX, f64::MIN, 0.1f64);
+    for &(x, y) in all_points {
+        min_x = min_x.min(x);
+        max_x = max_x.max(x);
+        max_y = max_y.max(y);
+    }
+    if min_x > max_x {
+        return Err(anyhow::format_err!("No data to chart"));
+    }
+    if max_x <= min_x {
+        max_x = min_x + 1.0;
+    }
+
+    let theme = theme();
+    let root = BitMapBackend::new(path, chart_size()).into_drawing_area();
+    root.fill(&theme.background)?;
+    let (title_area, chart_area) = root.split_vertically(50);
+    title_area.titled(&stamped_title(title), text_style(20))?;
+
+    let mut chart = ChartBuilder::on(&chart_area)
+        .margin(10)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d(min_x..max_x, 0f64..max_y * 1.1)?;
+
+    themed_mesh(&mut chart.configure_mesh())
+        .x_desc(x_desc)
+        .y_desc(y_desc)
+        .draw()?;
+
+    for (idx, s) in series.iter().enumerate() {
+        let color = theme.series_color(idx);
+        chart
+            .draw_series(LineSeries::new(
+                downsample(&s.points, max_points()),
+                color.stroke_width(2),
+            ))?
+            .label(s.label.clone())
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+        chart.draw_series(
+            s.points
+                .iter()
+                .map(|&(x, y)| Circle::new((x, y), 3, color.filled())),
+        )?;
+    }
+
+    themed_legend(&mut chart.configure_series_labels())
+        .position(SeriesLabelPosition::UpperRight)
+        .margin(10)
+        .draw()?;
+
+    root.present()?;
+    Ok(())
+}
+
+// 绘制数值分布直方图
+pub fn draw_histogram(
+    path: &Path,
+    title: &str,
+    x_desc: &str,
+    values: &[f64],
+    bucket_count: usize,
+) -> Result<()> {
+    if values.is_empty() {
+        return Err(anyhow::format_err!("No data to chart"));
+    }
+
+    let min = values.iter().cloned().fold(f64::MAX, f64::min);
+    let mut max = values.iter().cloned().fold(f64::MIN, f64::max);
+    if max <= min {
+        max = min + 1.0;
+    }
+    let bucket_count = bucket_count.max(1);
+    let width = (max - min) / bucket_count as f64;
+
+    let mut counts = vec![0u32; bucket_count];
+    for &v in values {
+        let idx = (((v - min) / width) as usize).min(bucket_count - 1);
+        counts[idx] += 1;
+    }
+    let max_count = counts.iter().cloned().max().unwrap_or(1).max(1);
+
+    let theme = theme();
+    let root = BitMapBackend::new(path, chart_size()).into_drawing_area();
+    root.fill(&theme.background)?;
+    let (title_area, chart_area) = root.split_vertically(50);
+    title_area.titled(&stamped_title(title), text_style(20))?;
+
+    let mut chart = ChartBuilder::on(&chart_area)
+        .margin(10)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d(min..max, 0u32..max_count + 1)?;
+
+    themed_mesh(&mut chart.configure_mesh())
+        .x_desc(x_desc)
+        .y_desc("Count")
+        .draw()?;
+
+    chart.draw_series(counts.iter().enumerate().map(|(i, &count)| {
+        let x0 = min + width * i as f64;
+        Rectangle::new(
+            [(x0, 0), (x0 + width, count)],
+            theme.series_color(1).mix(0.6).filled(),
+        )
+    }))?;
+
+    root.present()?;
+    Ok(())
+}
+
+// 一条以时间为X轴的曲线
+#[derive(Debug, Clone)]
+pub struct TimeSeriesData {
+    pub label: String,
+    pub points: Vec<(DateTime<Local>, f64)>,
+}
+
+impl TimeSeriesData {
+    pub fn new(label: impl Into<String>, points: Vec<(DateTime<Local>, f64)>) -> Self {
+        Self {
+            label: label.into(),
+            points,
+        }
+    }
+}
+
+// 时间线图中的一行，如某个 wakelock 的各次持有区间
+#[derive(Debug, Clone)]
+pub struct TimelineLane {
+    pub label: String,
+    pub intervals: Vec<(DateTime<Local>, DateTime<Local>)>,
+}
+
+impl TimelineLane {
+    pub fn new(
+        label: impl Into<String>,
+        intervals: Vec<(DateTime<Local>, DateTime<Local>)>,
+    ) -> Self {
+        Self {
+            label: label.into(),
+            intervals,
+        }
+    }
+}
+
+// 绘制甘特式时间线：每行一个泳道，区间画成横条
+pub fn draw_timeline_chart(path: &Path, title: &str, lanes: &[TimelineLane]) -> Result<()> {
+    let mut range: Option<(DateTime<Local>, DateTime<Local>)> = None;
+    for &(start, end) in lanes.iter().flat_map(|lane| lane.intervals.iter()) {
+        range = Some(match range {
+            Some((min, max)) => (min.min(start), max.max(end)),
+            None => (start, end),
+        });
+    }
+    let Some((min_time, mut max_time)) = range else {
+        return Err(anyhow::format_err!("No data to chart"));
+    };
+    if max_time <= min_time {
+        max_time = min_time + chrono::Duration::seconds(1);
+    }
+
+    let theme = theme();
+    let root = BitMapBackend::new(path, chart_size()).into_drawing_area();
+    root.fill(&theme.background)?;
+    let (title_area, chart_area) = root.split_vertically(50);
+    title_area.titled(&stamped_title(title), text_style(20))?;
+
+    let mut chart = ChartBuilder::on(&chart_area)
+        .margin(10)
+        .x_label_area_size(40)
+        .y_label_area_size(260)
+        .build_cartesian_2d(min_time..max_time, (0..lanes.len() as i32).into_segmented())?;
+
+    themed_mesh(&mut chart.configure_mesh())
+        .x_labels(8)
+        .x_label_formatter(&|x| x.format("%H:%M:%S").to_string())
+        .y_labels(lanes.len())
+        .y_label_formatter(&|y| match y {
+            SegmentValue::CenterOf(i) => lanes
+                .get(*i as usize)
+                .map(|lane| lane.label.clone())
+                .unwrap_or_default(),
+            _ => String::new(),
+        })
+        .x_desc("Time")
+        .draw()?;
+
+    for (idx, lane) in lanes.iter().enumerate() {
+        let color = theme.series_color(idx);
+        chart.draw_series(lane.intervals.iter().map(|&(start, end)| {
+            Rectangle::new(
+                [
+                    (start, SegmentValue::Exact(idx as i32)),
+                    (end, SegmentValue::Exact(idx as i32 + 1)),
+                ],
+                color.mix(0.7).filled(),
+            )
+        }))?;
+    }
+
+    root.present()?;
+    Ok(())
+}
+
+// 绘制以时间为X轴的多曲线图表
+pub fn draw_time_series_chart(
+    path: &Path,
+    title: &str,
+    y_desc: &str,
+    series: &[TimeSeriesData],
+) -> Result<()> {
+    draw_banded_time_series_chart(path, title, y_desc, series, &[])
+}
+
+// 同 draw_time_series_chart，曲线下方加时间区间底纹（如后台区间）
+pub fn draw_banded_time_series_chart(
+    path: &Path,
+    title: &str,
+    y_desc: &str,
+    series: &[TimeSeriesData],
+    bands: &[Band],
+) -> Result<()> {
+    let all_points = series.iter().flat_map(|s| s.points.iter());
+    let mut range: Option<(DateTime<Local>, DateTime<Local>)> = None;
+    let mut max_y = 0.1f64;
+    // 一般从 0 开始，有负值时（如实时线程的内核优先级）向下扩展
+    let mut min_y = 0.0f64;
+    for &(t, y) in all_points {
+        range = Some(match range {
+            Some((min, max)) => (min.min(t), max.max(t)),
+            None => (t, t),
+        });
+        max_y = max_y.max(y);
+        min_y = min_y.min(y);
+    }
+    let Some((min_time, mut max_time)) = range else {
+        return Err(anyhow::format_err!("No data to chart"));
+    };
+    if max_time <= min_time {
+        max_time = min_time + chrono::Duration::seconds(1);
+    }
+    if min_y < 0.0 {
+        min_y *= 1.1;
+    }
+
+    let theme = theme();
+    let root = BitMapBackend::new(path, chart_size()).into_drawing_area();
+    root.fill(&theme.background)?;
+    let (title_area, chart_area) = root.split_vertically(50);
+    title_area.titled(&stamped_title(title), text_style(20))?;
+
+    let mut chart = ChartBuilder::on(&chart_area)
+        .margin(10)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d(min_time..max_time, min_y..max_y * 1.1)?;
+
+    themed_mesh(&mut chart.configure_mesh())
+        .x_labels(8)
+        .x_label_formatter(&|x| x.format("%H:%M:%S").to_string())
+        .y_desc(y_desc)
+        .x_desc("Time")
+        .draw()?;
+
+    if let Some(shade) = warmup_shade(min_time, max_time, min_y, max_y * 1.1) {
+        chart
+            .draw_series(std::iter::once(shade))?
+            .label(WARMUP_LABEL)
+            .legend(move |(x, y)| {
+                Rectangle::new([(x, y - 5), (x + 20, y + 5)], theme.shade.filled())
+            });
+    }
+
+    for (label, color, rects) in band_groups(bands, min_time, max_time, min_y, max_y * 1.1) {
+        chart
+            .draw_series(rects)?
+            .label(label)
+            .legend(move |(x, y)| Rectangle::new([(x, y - 5), (x + 20, y + 5)], color.filled()));
+    }
+
+    chart.draw_series(user_marker_lines(min_time, max_time, min_y, max_y * 1.1))?;
+    chart.draw_series(user_marker_labels(min_time, max_time, max_y * 1.05))?;
+
+    for (idx, s) in series.iter().enumerate() {
+        let color = theme.series_color(idx);
+        chart
+            .draw_series(LineSeries::new(
+                downsample(&s.points, max_points()),
+                color.stroke_width(2),
+            ))?
+            .label(s.label.clone())
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+    }
+
+    themed_legend(&mut chart.configure_series_labels())
+        .position(SeriesLabelPosition::UpperRight)
+        .margin(10)
+        .draw()?;
+
+    root.present()?;
+    Ok(())
+}
+
+// 双纵轴时间序列图：series 用左轴，secondary 用右轴，用于量纲不同但需要对照的指标（如 GPU 利用率和频率）
+pub fn draw_dual_axis_chart(
+    path: &Path,
+    title: &str,
+    y_desc: &str,
+    series: &[TimeSeriesData],
+    secondary_desc: &str,
+    secondary: &[TimeSeriesData],
+) -> Result<()> {
+    let max_of = |series: &[TimeSeriesData]| {
+        series
+            .iter()
+            .flat_map(|s| s.points.iter().map(|(_, y)| *y))
+            .fold(0.1f64, f64::max)
+    };
+    let mut range: Option<(DateTime<Local>, DateTime<Local>)> = None;
+    for &(t, _) in series.iter().chain(secondary).flat_map(|s| s.points.iter()) {
+        range = Some(match range {
+            Some((min, max)) => (min.min(t), max.max(t)),
+            None => (t, t),
+        });
+    }
+    let Some((min_time, mut max_time)) = range else {
+        return Err(anyhow::format_err!("No data to chart"));
+    };
+    if max_time <= min_time {
+        max_time = min_time + chrono::Duration::seconds(1);
+    }
+    let max_y = max_of(series) * 1.1;
+    let max_secondary = max_of(secondary) * 1.1;
+
+    let theme = theme();
+    let root = BitMapBackend::new(path, chart_size()).into_drawing_area();
+    root.fill(&theme.background)?;
+    let (title_area, chart_area) = root.split_vertically(50);
+    title_area.titled(&stamped_title(title), text_style(20))?;
+
+    let mut chart = ChartBuilder::on(&chart_area)
+        .margin(10)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .right_y_label_area_size(60)
+        .build_cartesian_2d(min_time..max_time, 0f64..max_y)?
+        .set_secondary_coord(min_time..max_time, 0f64..max_secondary);
+
+    themed_mesh(&mut chart.configure_mesh())
+        .x_labels(8)
+        .x_label_formatter(&|x| x.format("%H:%M:%S").to_string())
+        .y_desc(y_desc)
+        .x_desc("Time")
+        .draw()?;
+    chart
+        .configure_secondary_axes()
+        .axis_style(theme.foreground)
+        .label_style(text_style(12))
+        .axis_desc_style(text_style(12))
+        .y_desc(secondary_desc)
+        .draw()?;
+
+    if let Some(shade) = warmup_shade(min_time, max_time, 0.0, max_y) {
+        chart
+            .draw_series(std::iter::once(shade))?
+            .label(WARMUP_LABEL)
+            .legend(move |(x, y)| {
+                Rectangle::new([(x, y - 5), (x + 20, y + 5)], theme.shade.filled())
+            });
+    }
+
+    chart.draw_series(user_marker_lines(min_time, max_time, 0.0, max_y))?;
+    chart.draw_series(user_marker_labels(min_time, max_time, max_y * 0.95))?;
+
+    for (idx, s) in series.iter().enumerate() {
+        let color = theme.series_color(idx);
+        chart
+            .draw_series(LineSeries::new(
+                downsample(&s.points, max_points()),
+                color.stroke_width(2),
+            ))?
+            .label(s.label.clone())
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+    }
+    // 右轴曲线接着左轴的颜色，细线绘制
+    for (idx, s) in secondary.iter().enumerate() {
+        let color = theme.series_color(series.len() + idx);
+        chart
+            .draw_secondary_series(LineSeries::new(
+                downsample(&s.points, max_points()),
+                color.stroke_width(1),
+            ))?
+            .label(format!("{} (right axis)", s.label))
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+    }
+
+    themed_legend(&mut chart.configure_series_labels())
+        .position(SeriesLabelPosition::UpperRight)
+        .margin(10)
+        .draw()?;
+
+    root.present()?;
+    Ok(())
+}
+
+// 堆叠面积图，series 按自下而上的顺序累加，各时间点的值需一一对应
+pub fn draw_stacked_area_chart(
+    path: &Path,
+    title: &str,
+    y_desc: &str,
+    series: &[TimeSeriesData],
+) -> Result<()> {
+    let Some(base) = series.first().filter(|s| !s.points.is_empty()) else {
+        return Err(anyhow::format_err!("No data to chart"));
+    };
+    let min_time = base.points[0].0;
+    let mut max_time = base.points[base.points.len() - 1].0;
+    if max_time <= min_time {
+        max_time = min_time + chrono::Duration::seconds(1);
+    }
+
+    // 逐层累加得到每层的上边界
+    let mut stacked: Vec<Vec<(DateTime<Local>, f64)>> = Vec::new();
+    for s in series {
+        let layer = s
+            .points
+            .iter()
+            .enumerate()
+            .map(|(i, &(t, v))| {
+                let below = stacked.last().and_then(|l| l.get(i)).map_or(0.0, |p| p.1);
+                (t, below + v)
+            })
+            .collect();
+        stacked.push(layer);
+    }
+    let max_y = stacked
+        .last()
+        .into_iter()
+        .flatten()
+        .map(|&(_, v)| v)
+        .fold(0.1f64, f64::max);
+
+    let theme = theme();
+    let root = BitMapBackend::new(path, chart_size()).into_drawing_area();
+    root.fill(&theme.background)?;
+    let (title_area, chart_area) = root.split_vertically(50);
+    title_area.titled(&stamped_title(title), text_style(20))?;
+
+    let mut chart = ChartBuilder::on(&chart_area)
+        .margin(10)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d(min_time..max_time, 0f64..max_y * 1.1)?;
+
+    themed_mesh(&mut chart.configure_mesh())
+        .x_labels(8)
+        .x_label_formatter(&|x| x.format("%H:%M:%S").to_string())
+        .y_desc(y_desc)
+        .x_desc("Time")
+        .draw()?;
+
+    if let Some(shade) = warmup_shade(min_time, max_time, 0.0, max_y * 1.1) {
+        chart
+            .draw_series(std::iter::once(shade))?
+            .label(WARMUP_LABEL)
+            .legend(move |(x, y)| {
+                Rectangle::new([(x, y - 5), (x + 20, y + 5)], theme.shade.filled())
+            });
+    }
+
+    // 从最上层开始画，下层覆盖在上面，露出的部分即各层的厚度
+    for (idx, (s, layer)) in series.iter().zip(&stacked).enumerate().rev() {
+        let color = theme.series_color(idx);
+        chart
+            .draw_series(AreaSeries::new(
+                layer.iter().copied(),
+                0.0,
+                color.mix(0.8).filled(),
+            ))?
+            .label(s.label.clone())
+            .legend(move |(x, y)| Rectangle::new([(x, y - 5), (x + 20, y + 5)], color.filled()));
+    }
+
+    chart.draw_series(user_marker_lines(min_time, max_time, 0.0, max_y * 1.1))?;
+    chart.draw_series(user_marker_labels(min_time, max_time, max_y * 1.05))?;
+
+    themed_legend(&mut chart.configure_series_labels())
+        .position(SeriesLabelPosition::UpperRight)
+        .margin(10)
+        .draw()?;
+
+    root.present()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn downsample_keeps_short_series() {
+        let points: Vec<(u32, f64)> = (0..10).map(|i| (i, f64::from(i))).collect();
+        assert_eq!(downsample(&points, 10), points);
+        assert_eq!(downsample(&points, 3), points);
+    }
+
+    #[test]
+    fn downsample_keeps_endpoints_and_spikes() {
+        let mut points: Vec<(u32, f64)> = (0..1000).map(|i| (i, 10.0)).collect();
+        points[500].1 = 95.0;
+        points[700].1 = 1.0;
+        let sampled = downsample(&points, 100);
+        assert!(sampled.len() <= 100);
+        assert_eq!(sampled.first(), points.first());
+        assert_eq!(sampled.last(), points.last());
+        assert!(sampled.contains(&(500, 95.0)));
+        assert!(sampled.contains(&(700, 1.0)));
+        // 保持时间顺序
+        assert!(sampled.windows(2).all(|w| w[0].0 < w[1].0));
+    }
+
+    #[test]
+    fn parse_chart_size_rejects_small_sizes() {
+        assert_eq!(parse_chart_size("1280x720").unwrap(), (1280, 720));
+        assert_eq!(parse_chart_size(" 1920X1080 ").unwrap(), (1920, 1080));
+        assert!(parse_chart_size("200x100").is_err());
+        assert!(parse_chart_size("1280").is_err());
+    }
+}
@@ -1,8 +1,51 @@
 use crate::utils;
 use anyhow::Result;
 use chrono::{DateTime, Local};
+use clap::ValueEnum;
 use colored::*;
 use std::cmp::Ordering;
+use std::collections::HashMap;
+
+// CPU 使用率的归一化方式；pidstat 以单核为 100%，多核设备上进程可超过 100%
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum CpuScale {
+    /// 所有核心合计为 100%
+    Total,
+    /// 单个核心为 100%（pidstat 原始值）
+    #[default]
+    PerCore,
+}
+
+impl CpuScale {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CpuScale::Total => "total",
+            CpuScale::PerCore => "per-core",
+        }
+    }
+
+    pub fn normalize(&self, per_core_value: f32, cores: u32) -> f32 {
+        match self {
+            CpuScale::Total => per_core_value / cores.max(1) as f32,
+            CpuScale::PerCore => per_core_value,
+        }
+    }
+
+    // 理论最大值，用于限制图表Y轴
+    pub fn max_value(&self, cores: u32) -> f32 {
+        match self {
+            CpuScale::Total => 100.0,
+            CpuScale::PerCore => 100.0 * cores.max(1) as f32,
+        }
+    }
+
+    pub fn unit(&self) -> &'static str {
+        match self {
+            CpuScale::Total => "% of all cores",
+            CpuScale::PerCore => "% of one core",
+        }
+    }
+}
 
 // 定义线程CPU使用信息结构体
 #[derive(Debug, Clone)]
@@ -11,6 +54,8 @@ pub struct ThreadCpuInfo {
     pub cpu_usage: f32,
     pub name: String,
     pub timestamp: Option<DateTime<Local>>,
+    // pidstat 的 CPU 列：采样时线程最近运行的核心
+    pub core: Option<u32>,
 }
 
 // 实现比较特性以便在最大堆中使用
@@ -38,6 +83,62 @@ impl Ord for ThreadCpuInfo {
     }
 }
 
+// 与渲染相关的线程角色，用于区分逻辑瓶颈（主线程）和渲染瓶颈（RenderThread/GPU）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ThreadRole {
+    Main,
+    RenderThread,
+    GpuCompletion,
+    HwuiTask,
+    Other,
+}
+
+impl ThreadRole {
+    pub const RENDERING: [ThreadRole; 4] = [
+        ThreadRole::Main,
+        ThreadRole::RenderThread,
+        ThreadRole::GpuCompletion,
+        ThreadRole::HwuiTask,
+    ];
+
+    // 主线程 TID 与进程 PID 相同
+    pub fn classify(thread: &ThreadCpuInfo, pid: &str) -> Self {
+        if thread.tid == pid {
+            ThreadRole::Main
+        } else if thread.name == "RenderThread" {
+            ThreadRole::RenderThread
+        } else if thread.name == "GPU completion" {
+            ThreadRole::GpuCompletion
+        } else if thread.name.starts_with("hwuiTask") {
+            ThreadRole::HwuiTask
+        } else {
+            ThreadRole::Other
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ThreadRole::Main => "Main thread",
+            ThreadRole::RenderThread => "RenderThread",
+            ThreadRole::GpuCompletion => "GPU completion",
+            ThreadRole::HwuiTask => "hwuiTask",
+            ThreadRole::Other => "Other",
+        }
+    }
+}
+
+// 按角色汇总线程CPU（hwuiTask 可能有多个，累加）
+pub fn cpu_by_role(threads: &[ThreadCpuInfo], pid: &str) -> HashMap<ThreadRole, f32> {
+    let mut by_role = HashMap::new();
+    for thread in threads {
+        let role = ThreadRole::classify(thread, pid);
+        if role != ThreadRole::Other {
+            *by_role.entry(role).or_insert(0.0) += thread.cpu_usage;
+        }
+    }
+    by_role
+}
+
 // Helper function to clean thread names
 fn clean_thread_name(name: &str) -> String {
     // Remove common prefixes like "1 |__", "2 |__", etc.
@@ -130,11 +231,18 @@ async fn collect_pidstat_data(pid: &str) -> Result<(f32, Vec<ThreadCpuInfo>)> {
                                 format!("Thread-{}", tid)
                             };
 
+                            // CPU 列之后还有命令名时才是核心编号
+                            let core = fields
+                                .get(cpu_idx + 1)
+                                .filter(|_| fields.len() > cpu_idx + 2)
+                                .and_then(|core| core.parse().ok());
+
                             threads.push(ThreadCpuInfo {
                                 tid: tid.to_string(),
                                 cpu_usage,
                                 name: thread_name,
                                 timestamp: None,
+                                core,
                             });
                         }
                     }
@@ -197,7 +305,7 @@ pub async fn sample_cpu(package: &str) -> Result<(f32, DateTime<Local>, Vec<Thre
             }
 
             // 打印进程CPU使用情况
-            println!(
+            console!(
                 "[{}] Process CPU: {}% (pid: {})",
                 timestamp.format("%H:%M:%S"),
                 format!("{:.1}", pidstat_process_cpu).blue(),
@@ -0,0 +1,143 @@
+use crate::utils;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+// 设备基本信息
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DeviceInfo {
+    pub serial: String,
+    pub manufacturer: String,
+    pub model: String,
+    pub android_version: String,
+    pub sdk: String,
+    #[serde(default)]
+    pub ram_mb: u64,
+    #[serde(default)]
+    pub cpu_cores: u32,
+}
+
+fn getprop(serial: Option<&str>, name: &str) -> String {
+    utils::run_adb_command_on(serial, &["shell", "getprop", name])
+        .map(|v| v.trim().to_string())
+        .unwrap_or_default()
+}
+
+pub fn query_device_info(serial: Option<&str>) -> DeviceInfo {
+    let serial_prop = getprop(serial, "ro.serialno");
+    DeviceInfo {
+        serial: serial.map(str::to_string).unwrap_or(serial_prop),
+        manufacturer: getprop(serial, "ro.product.manufacturer"),
+        model: getprop(serial, "ro.product.model"),
+        android_version: getprop(serial, "ro.build.version.release"),
+        sdk: getprop(serial, "ro.build.version.sdk"),
+        ram_mb: query_ram_mb(serial),
+        cpu_cores: query_cpu_cores(serial),
+    }
+}
+
+// 解析 /sys/devices/system/cpu/possible（如 "0-7" 或 "0-3,6"），包含当前离线的核心
+pub fn query_cpu_cores(serial: Option<&str>) -> u32 {
+    let output = utils::run_adb_command_on(
+        serial,
+        &["shell", "cat", "/sys/devices/system/cpu/possible"],
+    )
+    .unwrap_or_default();
+    let cores: u32 = output
+        .trim()
+        .split(',')
+        .filter_map(|range| match range.split_once('-') {
+            Some((start, end)) => Some(end.parse::<u32>().ok()? - start.parse::<u32>().ok()? + 1),
+            None => range.parse::<u32>().ok().map(|_| 1),
+        })
+        .sum();
+    cores.max(1)
+}
+
+// /proc/meminfo 中的 MemTotal（KB）
+fn query_ram_mb(serial: Option<&str>) -> u64 {
+    utils::run_adb_command_on(serial, &["shell", "cat", "/proc/meminfo"])
+        .ok()
+        .and_then(|output| {
+            output
+                .lines()
+                .find_map(|l| l.strip_prefix("MemTotal:"))
+                .and_then(|v| v.split_whitespace().next()?.parse::<u64>().ok())
+        })
+        .map(|kb| kb / 1024)
+        .unwrap_or(0)
+}
+
+impl DeviceInfo {
+    // 按内存容量划分的设备档位，跨设备对比时只在同档位内计算差异
+    pub fn device_class(&self) -> &'static str {
+        match self.ram_mb {
+            0 => "unknown",
+            mb if mb <= 4096 => "low-end",
+            mb if mb <= 8192 => "mid-range",
+            _ => "high-end",
+        }
+    }
+}
+
+// 设备标签，保存在 log/device_tags.json，供多设备监控按标签选择设备
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DeviceTags {
+    pub tags: BTreeMap<String, Vec<String>>,
+}
+
+impl DeviceTags {
+    pub fn path() -> PathBuf {
+        PathBuf::from("log").join("device_tags.json")
+    }
+
+    pub fn load() -> Result<Self> {
+        let path = Self::path();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path)?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn tags_for(&self, serial: &str) -> &[String] {
+        self.tags.get(serial).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    pub fn add(&mut self, serial: &str, tag: &str) {
+        let tags = self.tags.entry(serial.to_string()).or_default();
+        if !tags.iter().any(|t| t == tag) {
+            tags.push(tag.to_string());
+            tags.sort();
+        }
+    }
+
+    pub fn remove(&mut self, serial: &str, tag: &str) {
+        if let Some(tags) = self.tags.get_mut(serial) {
+            tags.retain(|t| t != tag);
+            if tags.is_empty() {
+                self.tags.remove(serial);
+            }
+        }
+    }
+
+    // 带有指定标签的设备序列号
+    pub fn serials_with_tag(&self, tag: &str) -> Vec<String> {
+        self.tags
+            .iter()
+            .filter(|(_, tags)| tags.iter().any(|t| t == tag))
+            .map(|(serial, _)| serial.clone())
+            .collect()
+    }
+}
@@ -0,0 +1,154 @@
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// 少于该样本数时不做趋势判断
+pub const MIN_SAMPLES: usize = 10;
+// 增长低于该速度（MB/小时）时不视为泄漏
+pub const MIN_LEAK_MB_PER_HOUR: f64 = 1.0;
+// 拟合优度低于该值时增长可能只是波动
+pub const MIN_R_SQUARED: f64 = 0.5;
+
+// 双侧 95% 置信区间的 t 分布临界值，自由度 1..=30
+const T_CRITICAL_95: [f64; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+    2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+    2.052, 2.048, 2.045, 2.042,
+];
+
+fn t_critical(df: usize) -> f64 {
+    df.checked_sub(1)
+        .and_then(|i| T_CRITICAL_95.get(i).copied())
+        .unwrap_or(1.96)
+}
+
+// 对内存序列做最小二乘线性回归得到的增长趋势，单位为 KB
+#[derive(Debug, Clone, Copy)]
+pub struct Trend {
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    pub samples: usize,
+    // 起始时刻的拟合值（KB）
+    pub intercept_kb: f64,
+    // 斜率及其 95% 置信区间半宽（KB/小时）
+    pub slope_kb_per_hour: f64,
+    pub margin_kb_per_hour: f64,
+    pub r_squared: f64,
+}
+
+impl Trend {
+    // 以小时为 X 轴拟合，样本过少或时间跨度为零时返回 None
+    pub fn fit(points: &[(DateTime<Local>, f64)]) -> Option<Self> {
+        if points.len() < MIN_SAMPLES {
+            return None;
+        }
+        let start = points.iter().map(|(t, _)| *t).min()?;
+        let end = points.iter().map(|(t, _)| *t).max()?;
+        let hours = |t: DateTime<Local>| (t - start).num_milliseconds() as f64 / 3_600_000.0;
+
+        let n = points.len() as f64;
+        let mean_x = points.iter().map(|(t, _)| hours(*t)).sum::<f64>() / n;
+        let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+        let sxx = points
+            .iter()
+            .map(|(t, _)| (hours(*t) - mean_x).powi(2))
+            .sum::<f64>();
+        if sxx <= 0.0 {
+            return None;
+        }
+        let sxy = points
+            .iter()
+            .map(|(t, y)| (hours(*t) - mean_x) * (y - mean_y))
+            .sum::<f64>();
+        let slope = sxy / sxx;
+        let intercept = mean_y - slope * mean_x;
+
+        let ss_res = points
+            .iter()
+            .map(|(t, y)| (y - (intercept + slope * hours(*t))).powi(2))
+            .sum::<f64>();
+        let ss_tot = points
+            .iter()
+            .map(|(_, y)| (y - mean_y).powi(2))
+            .sum::<f64>();
+        let r_squared = if ss_tot > 0.0 {
+            1.0 - ss_res / ss_tot
+        } else {
+            0.0
+        };
+        let df = points.len() - 2;
+        let std_error = (ss_res / df as f64 / sxx).sqrt();
+
+        Some(Self {
+            start,
+            end,
+            samples: points.len(),
+            intercept_kb: intercept,
+            slope_kb_per_hour: slope,
+            margin_kb_per_hour: t_critical(df) * std_error,
+            r_squared,
+        })
+    }
+
+    pub fn mb_per_hour(&self) -> f64 {
+        self.slope_kb_per_hour / 1024.0
+    }
+
+    pub fn margin_mb_per_hour(&self) -> f64 {
+        self.margin_kb_per_hour / 1024.0
+    }
+
+    // 置信区间整体高于零、增长足够快且线性拟合良好时判定为疑似泄漏
+    pub fn is_probable_leak(&self) -> bool {
+        self.slope_kb_per_hour - self.margin_kb_per_hour > 0.0
+            && self.mb_per_hour() >= MIN_LEAK_MB_PER_HOUR
+            && self.r_squared >= MIN_R_SQUARED
+    }
+
+    pub fn value_at(&self, t: DateTime<Local>) -> f64 {
+        self.intercept_kb
+            + self.slope_kb_per_hour * (t - self.start).num_milliseconds() as f64 / 3_600_000.0
+    }
+
+    // 用于在图表中绘制趋势线的两个端点
+    pub fn line(&self, from: DateTime<Local>, to: DateTime<Local>) -> Vec<(DateTime<Local>, f64)> {
+        vec![(from, self.value_at(from)), (to, self.value_at(to))]
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "{:+.2} ± {:.2} MB/h (95% CI, R² {:.2}, {} samples)",
+            self.mb_per_hour(),
+            self.margin_mb_per_hour(),
+            self.r_squared,
+            self.samples
+        )
+    }
+}
+
+// 导出各序列的趋势
+pub fn write_trends_csv(dir: &Path, package: &str, trends: &[(&str, Trend)]) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{}_leak_trend.csv", package));
+    let mut file = std::fs::File::create(&path)?;
+    writeln!(
+        file,
+        "Metric,Start,End,Samples,Growth (MB/h),CI95 (MB/h),R2,Probable Leak"
+    )?;
+    for (metric, trend) in trends {
+        writeln!(
+            file,
+            "{},{},{},{},{:.3},{:.3},{:.3},{}",
+            metric,
+            trend.start.format("%Y-%m-%d %H:%M:%S"),
+            trend.end.format("%Y-%m-%d %H:%M:%S"),
+            trend.samples,
+            trend.mb_per_hour(),
+            trend.margin_mb_per_hour(),
+            trend.r_squared,
+            trend.is_probable_leak()
+        )?;
+    }
+    Ok(path)
+}
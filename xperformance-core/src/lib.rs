@@ -0,0 +1,21 @@
+// 终端输出统一经过这里，由使用方决定写到哪里
+#[macro_export]
+macro_rules! console {
+    () => {
+        $crate::output::line(String::new())
+    };
+    ($($arg:tt)*) => {
+        $crate::output::line(format!($($arg)*))
+    };
+}
+
+pub mod chart;
+pub mod cpu;
+pub mod device;
+pub mod leak;
+pub mod memory;
+pub mod monitor;
+pub mod output;
+pub mod replay;
+pub mod tick;
+pub mod utils;
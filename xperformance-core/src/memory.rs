@@ -0,0 +1,1309 @@
+use crate::chart::{self, Band, TimeSeriesData};
+use crate::leak::Trend;
+use crate::utils;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use colored::*;
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// 堆的已提交大小、已分配和空闲（KB），来自 meminfo 明细表最后三列
+// 空闲部分是已提交但未使用的内存（碎片或尚未 trim），与存活对象分开观察
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeapDetails {
+    pub size: u64,
+    pub alloc: u64,
+    pub free: u64,
+}
+
+impl HeapDetails {
+    // 空闲占已提交大小的比例
+    pub fn free_ratio(&self) -> Option<f64> {
+        (self.size > 0).then(|| self.free as f64 / self.size as f64)
+    }
+}
+
+// 定义内存详细类别结构
+#[derive(Debug, Clone, Default)]
+pub struct MemoryDetails {
+    pub java_heap: u64,
+    pub native_heap: u64,
+    pub code: u64,
+    pub stack: u64,
+    pub graphics: u64,
+    pub private_other: u64,
+    pub system: u64,
+    pub total_pss: u64,
+    pub dalvik_heap_detail: HeapDetails,
+    pub native_heap_detail: HeapDetails,
+    // Dalvik Details 中的大对象空间（.LOS）PSS
+    pub dalvik_los: u64,
+    // /proc/<pid>/smaps_rollup，无权限读取时为 None
+    pub smaps: Option<SmapsRollup>,
+    // Objects 段，旧版本或输出不完整时为 None
+    pub objects: Option<ObjectCounts>,
+}
+
+// dumpsys meminfo 的 Objects 段中的对象计数
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ObjectCounts {
+    pub views: u64,
+    pub view_roots: u64,
+    pub app_contexts: u64,
+    pub activities: u64,
+    pub assets: u64,
+    pub asset_managers: u64,
+    pub local_binders: u64,
+    pub proxy_binders: u64,
+    pub parcel_memory_kb: u64,
+    pub parcel_count: u64,
+    pub death_recipients: u64,
+    pub webviews: u64,
+}
+
+impl ObjectCounts {
+    // 页面关闭后应回落的计数，一直高于基线通常说明 Activity 或 Context 泄漏
+    pub const LEAK_INDICATORS: [&'static str; 4] =
+        ["Activities", "AppContexts", "ViewRootImpl", "WebViews"];
+
+    // (列名, 数量)，用于合并 CSV 和图表
+    pub fn columns(&self) -> [(&'static str, u64); 12] {
+        [
+            ("Activities", self.activities),
+            ("AppContexts", self.app_contexts),
+            ("ViewRootImpl", self.view_roots),
+            ("WebViews", self.webviews),
+            ("Views", self.views),
+            ("Assets", self.assets),
+            ("AssetManagers", self.asset_managers),
+            ("Local Binders", self.local_binders),
+            ("Proxy Binders", self.proxy_binders),
+            ("Death Recipients", self.death_recipients),
+            ("Parcel Count", self.parcel_count),
+            ("Parcel Memory (KB)", self.parcel_memory_kb),
+        ]
+    }
+
+    pub fn get(&self, name: &str) -> Option<u64> {
+        self.columns()
+            .into_iter()
+            .find(|(column, _)| *column == name)
+            .map(|(_, value)| value)
+    }
+}
+
+// 解析 Objects 段，每行两组 "名称: 数量"：
+//   Objects
+//                Views:       12         ViewRootImpl:        1
+//          AppContexts:        3           Activities:        1
+pub fn parse_objects(output: &str) -> Option<ObjectCounts> {
+    let mut counts = ObjectCounts::default();
+    let mut in_section = false;
+    let mut found = false;
+    for line in output.lines() {
+        let line = line.trim();
+        if line == "Objects" {
+            in_section = true;
+            continue;
+        }
+        if !in_section {
+            continue;
+        }
+        // 段后为空行或下一个段落（SQL、DATABASES）
+        if line.is_empty() || !line.contains(':') {
+            if found {
+                break;
+            }
+            continue;
+        }
+        let mut name: Vec<&str> = Vec::new();
+        let mut tokens = line.split_whitespace();
+        while let Some(token) = tokens.next() {
+            let Some(last) = token.strip_suffix(':') else {
+                name.push(token);
+                continue;
+            };
+            name.push(last);
+            let value = tokens.next().and_then(|v| v.parse::<u64>().ok());
+            let field = match name.join(" ").as_str() {
+                "Views" => &mut counts.views,
+                "ViewRootImpl" => &mut counts.view_roots,
+                "AppContexts" => &mut counts.app_contexts,
+                "Activities" => &mut counts.activities,
+                "Assets" => &mut counts.assets,
+                "AssetManagers" => &mut counts.asset_managers,
+                "Local Binders" => &mut counts.local_binders,
+                "Proxy Binders" => &mut counts.proxy_binders,
+                "Parcel memory" => &mut counts.parcel_memory_kb,
+                "Parcel count" => &mut counts.parcel_count,
+                "Death Recipients" => &mut counts.death_recipients,
+                "WebViews" => &mut counts.webviews,
+                _ => {
+                    name.clear();
+                    continue;
+                }
+            };
+            if let Some(value) = value {
+                *field = value;
+                found = true;
+            }
+            name.clear();
+        }
+    }
+    found.then_some(counts)
+}
+
+// 一直没有回到基线的对象计数
+#[derive(Debug, Clone)]
+pub struct ObjectLeak {
+    pub name: &'static str,
+    // 首次（预热后）采样的值
+    pub baseline: u64,
+    // 后半段的最小值
+    pub floor: u64,
+    pub last: u64,
+}
+
+// 预热后至少 4 次采样，后半段的最小值仍高于首次采样时视为可能泄漏
+pub fn object_leaks(
+    history: &[(DateTime<Local>, MemoryDetails)],
+    warmup_end: Option<DateTime<Local>>,
+) -> Vec<ObjectLeak> {
+    let counts: Vec<ObjectCounts> = history
+        .iter()
+        .filter(|(t, _)| warmup_end.is_none_or(|end| *t >= end))
+        .filter_map(|(_, details)| details.objects)
+        .collect();
+    if counts.len() < 4 {
+        return Vec::new();
+    }
+    ObjectCounts::LEAK_INDICATORS
+        .iter()
+        .filter_map(|name| {
+            let values: Vec<u64> = counts.iter().filter_map(|c| c.get(name)).collect();
+            let baseline = *values.first()?;
+            let floor = *values[values.len() / 2..].iter().min()?;
+            let last = *values.last()?;
+            (floor > baseline).then_some(ObjectLeak {
+                name,
+                baseline,
+                floor,
+                last,
+            })
+        })
+        .collect()
+}
+
+// smaps_rollup 中的汇总（KB）。USS 为进程独占的页（Private_Clean + Private_Dirty），
+// 即杀死进程后实际可回收的内存；Swap 为已换出（含 zram）的匿名页
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SmapsRollup {
+    pub rss: u64,
+    pub pss: u64,
+    pub uss: u64,
+    pub swap: u64,
+    pub swap_pss: u64,
+}
+
+// 解析形如 "Private_Dirty:     12345 kB" 的行
+pub fn parse_smaps_rollup(output: &str) -> Option<SmapsRollup> {
+    let mut rollup = SmapsRollup::default();
+    let mut found = false;
+    for line in output.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let Some(kb) = value
+            .split_whitespace()
+            .next()
+            .and_then(|v| v.parse::<u64>().ok())
+        else {
+            continue;
+        };
+        match key.trim() {
+            "Rss" => rollup.rss = kb,
+            "Pss" => rollup.pss = kb,
+            "Private_Clean" | "Private_Dirty" => rollup.uss += kb,
+            "Swap" => rollup.swap = kb,
+            "SwapPss" => rollup.swap_pss = kb,
+            _ => continue,
+        }
+        found = true;
+    }
+    found.then_some(rollup)
+}
+
+// 应用进程的 smaps_rollup 对 shell 用户不可读；依次尝试直接读取（adb root）、run-as、su，
+// 首次成功后固定使用该方式，全部失败后不再尝试
+pub struct SmapsReader {
+    package: String,
+    prefix: Option<Vec<String>>,
+    unavailable: bool,
+}
+
+impl SmapsReader {
+    pub fn new(package: &str) -> Self {
+        Self {
+            package: package.to_string(),
+            prefix: None,
+            unavailable: false,
+        }
+    }
+
+    pub fn is_unavailable(&self) -> bool {
+        self.unavailable
+    }
+
+    pub fn sample(&mut self, pid: &str) -> Option<SmapsRollup> {
+        if self.unavailable {
+            return None;
+        }
+        let path = format!("/proc/{}/smaps_rollup", pid);
+        let prefixes = match &self.prefix {
+            Some(prefix) => vec![prefix.clone()],
+            None => vec![
+                Vec::new(),
+                vec!["run-as".to_string(), self.package.clone()],
+                vec!["su".to_string(), "0".to_string()],
+            ],
+        };
+        for prefix in prefixes {
+            let mut args: Vec<&str> = vec!["shell"];
+            args.extend(prefix.iter().map(String::as_str));
+            args.extend(["cat", path.as_str()]);
+            let Some(rollup) = utils::run_adb_command(&args)
+                .ok()
+                .and_then(|output| parse_smaps_rollup(&output))
+            else {
+                continue;
+            };
+            self.prefix = Some(prefix);
+            return Some(rollup);
+        }
+        // 已确定的方式失败可能只是进程刚退出，下次重试
+        if self.prefix.is_none() {
+            self.unavailable = true;
+        }
+        None
+    }
+}
+
+// 写入合并表的内存列（KB）
+pub fn detail_columns(details: &MemoryDetails) -> [(&'static str, u64); 15] {
+    [
+        ("Total PSS (KB)", details.total_pss),
+        ("Java Heap (KB)", details.java_heap),
+        ("Native Heap (KB)", details.native_heap),
+        ("Code (KB)", details.code),
+        ("Stack (KB)", details.stack),
+        ("Graphics (KB)", details.graphics),
+        ("Private Other (KB)", details.private_other),
+        ("System (KB)", details.system),
+        ("Dalvik Heap Size (KB)", details.dalvik_heap_detail.size),
+        ("Dalvik Heap Alloc (KB)", details.dalvik_heap_detail.alloc),
+        ("Dalvik Heap Free (KB)", details.dalvik_heap_detail.free),
+        ("Dalvik LOS (KB)", details.dalvik_los),
+        ("Native Heap Size (KB)", details.native_heap_detail.size),
+        ("Native Heap Alloc (KB)", details.native_heap_detail.alloc),
+        ("Native Heap Free (KB)", details.native_heap_detail.free),
+    ]
+}
+
+// 明细表中 "Native Heap"/"Dalvik Heap" 行的最后三列为 Heap Size/Alloc/Free
+fn parse_heap_row(line: &str) -> Option<HeapDetails> {
+    let numbers: Vec<u64> = line
+        .split_whitespace()
+        .filter_map(|v| v.parse().ok())
+        .collect();
+    if numbers.len() < 7 {
+        return None;
+    }
+    let heap = &numbers[numbers.len() - 3..];
+    Some(HeapDetails {
+        size: heap[0],
+        alloc: heap[1],
+        free: heap[2],
+    })
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MemoryTimeSeriesData {
+    pub timestamps: VecDeque<DateTime<Local>>,
+    pub memory_details: VecDeque<MemoryDetails>,
+}
+
+impl MemoryTimeSeriesData {
+    pub fn add_data_point(&mut self, timestamp: DateTime<Local>, details: MemoryDetails) {
+        // 添加新数据点
+        self.timestamps.push_back(timestamp);
+        self.memory_details.push_back(details);
+
+        // 保持最多300个数据点
+        while self.timestamps.len() > 300 {
+            self.timestamps.pop_front();
+            self.memory_details.pop_front();
+        }
+    }
+}
+
+pub async fn sample_memory(
+    package: &str,
+    verbose: bool,
+) -> Result<(u64, DateTime<Local>, MemoryDetails)> {
+    let timestamp = Local::now();
+    let process_info = utils::get_process_info(package)?;
+    let pid = &process_info.pid;
+    // -d 附带 Dalvik Details（.Heap、.LOS 等）
+    let output = utils::run_adb_command(&["shell", "dumpsys", "meminfo", "-d", pid])?;
+
+    let mut total_pss = 0;
+    let mut memory_details = MemoryDetails::default();
+    let mut in_app_summary = false;
+    let mut header_passed = false; // 用于跳过标题行
+
+    // 解析App Summary部分
+    for line in output.lines() {
+        let line = line.trim();
+
+        // App Summary 之前的明细表：堆大小和大对象空间
+        if !in_app_summary {
+            if line.starts_with("Native Heap") {
+                if let Some(heap) = parse_heap_row(line) {
+                    memory_details.native_heap_detail = heap;
+                }
+            } else if line.starts_with("Dalvik Heap") {
+                if let Some(heap) = parse_heap_row(line) {
+                    memory_details.dalvik_heap_detail = heap;
+                }
+            } else if line.starts_with(".LOS") {
+                if let Some(kb) = line.split_whitespace().nth(1).and_then(|v| v.parse().ok()) {
+                    memory_details.dalvik_los = kb;
+                }
+            }
+        }
+
+        // 检测App Summary部分开始
+        if line.contains("App Summary") {
+            in_app_summary = true;
+            continue;
+        }
+
+        // 跳过PSS/RSS标题行
+        if in_app_summary && (line.contains("Pss(KB)") || line.contains("------")) {
+            header_passed = line.contains("------");
+            continue;
+        }
+
+        // 如果已经过了App Summary部分，则退出解析
+        if in_app_summary && line.is_empty() {
+            in_app_summary = false;
+            continue;
+        }
+
+        // 解析App Summary部分的内存信息
+        if in_app_summary && header_passed {
+            let parts: Vec<&str> = line.split(':').collect();
+            if parts.len() >= 2 {
+                let category = parts[0].trim();
+                let values: Vec<&str> = parts[1].split_whitespace().collect();
+
+                if !values.is_empty() {
+                    if let Ok(kb) = values[0].parse::<u64>() {
+                        match category {
+                            "Java Heap" => memory_details.java_heap = kb,
+                            "Native Heap" => memory_details.native_heap = kb,
+                            "Code" => memory_details.code = kb,
+                            "Stack" => memory_details.stack = kb,
+                            "Graphics" => memory_details.graphics = kb,
+                            "Private Other" => memory_details.private_other = kb,
+                            "System" => memory_details.system = kb,
+                            "TOTAL" | "TOTAL PSS" => {
+                                memory_details.total_pss = kb;
+                                total_pss = kb;
+                            }
+                            _ => {} // 忽略其他类别
+                        }
+                    }
+                }
+            }
+        }
+
+        // 如果不在App Summary中，仍然需要查找TOTAL PSS作为备用
+        if !in_app_summary && line.starts_with("TOTAL PSS:") {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() >= 3 {
+                if let Ok(kb) = parts[2].parse::<u64>() {
+                    total_pss = kb;
+                    if memory_details.total_pss == 0 {
+                        memory_details.total_pss = kb;
+                    }
+                }
+            }
+        }
+    }
+
+    memory_details.objects = parse_objects(&output);
+
+    if verbose {
+        let mut details = String::new();
+        let mut current_section = String::new();
+
+        // Add section header
+        details.push_str("Memory Usage Details\n");
+        details.push_str(&"=".repeat(80));
+        details.push_str("\n\n");
+
+        // Add process information
+        details.push_str(&format!("Process ID: {}\n", pid));
+        details.push_str(&format!("Package Name: {}\n", package));
+        details.push_str(&format!("Start Time: {}\n", process_info.start_time));
+        details.push('\n');
+
+        // 添加App Summary详细信息
+        details.push_str("App Summary\n");
+        details.push_str(&"-".repeat(80));
+        details.push('\n');
+        details.push_str(&format!(
+            "{:<25} {:>15}\n",
+            "Java Heap:",
+            format!("{} KB", memory_details.java_heap)
+        ));
+        details.push_str(&format!(
+            "{:<25} {:>15}\n",
+            "Native Heap:",
+            format!("{} KB", memory_details.native_heap)
+        ));
+        details.push_str(&format!(
+            "{:<25} {:>15}\n",
+            "Code:",
+            format!("{} KB", memory_details.code)
+        ));
+        details.push_str(&format!(
+            "{:<25} {:>15}\n",
+            "Stack:",
+            format!("{} KB", memory_details.stack)
+        ));
+        details.push_str(&format!(
+            "{:<25} {:>15}\n",
+            "Graphics:",
+            format!("{} KB", memory_details.graphics)
+        ));
+        details.push_str(&format!(
+            "{:<25} {:>15}\n",
+            "Private Other:",
+            format!("{} KB", memory_details.private_other)
+        ));
+        details.push_str(&format!(
+            "{:<25} {:>15}\n",
+            "System:",
+            format!("{} KB", memory_details.system)
+        ));
+        details.push_str(&format!(
+            "{:<25} {:>15}\n",
+            "TOTAL PSS:",
+            format!("{} KB", memory_details.total_pss)
+        ));
+        details.push_str("\n\n");
+
+        // Parse memory info for full details
+        for line in output.lines() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            // Start of a new section
+            if line.ends_with(':') || line.contains("TOTAL") {
+                if !current_section.is_empty() {
+                    details.push_str(&current_section);
+                    details.push('\n');
+                }
+                current_section = format!("\n{}\n{}\n", line, "-".repeat(80));
+                continue;
+            }
+
+            // Parse memory values and format them
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() >= 2 {
+                // Try to parse the second column as a number (memory value)
+                if let Ok(kb) = parts[1].parse::<u64>() {
+                    // 直接显示KB单位，不转换为字节
+                    let formatted_size = format!("{} KB", kb);
+                    // Left-align name (40 chars), right-align memory value (15 chars)
+                    current_section.push_str(&format!("{:<40} {:>15}\n", parts[0], formatted_size));
+                } else {
+                    // If not a memory line, just add it with indentation
+                    current_section.push_str(&format!("    {}\n", line));
+                }
+            } else {
+                // Lines that don't match the pattern
+                current_section.push_str(&format!("    {}\n", line));
+            }
+        }
+
+        // Add the last section if any
+        if !current_section.is_empty() {
+            details.push_str(&current_section);
+        }
+
+        // Add summary section
+        details.push_str("\nMemory Summary\n");
+        details.push_str(&"=".repeat(80));
+        details.push('\n');
+        details.push_str(&format!(
+            "{:<40} {:>15}\n",
+            "Total PSS",
+            format!("{} KB", total_pss)
+        ));
+        details.push_str(&"=".repeat(80));
+        details.push('\n');
+
+        // Write to log file
+        utils::append_to_log(&details)?;
+    }
+
+    // Print detailed summary to console
+    console!(
+        "[{}] Memory Usage: {} KB (Java: {}, Native: {}, Code: {}, Graphics: {})",
+        timestamp.format("%H:%M:%S"),
+        memory_details.total_pss.to_string().blue(),
+        memory_details.java_heap.to_string().green(),
+        memory_details.native_heap.to_string().yellow(),
+        memory_details.code.to_string().cyan(),
+        memory_details.graphics.to_string().magenta()
+    );
+
+    Ok((total_pss, timestamp, memory_details))
+}
+
+// 堆大小与已分配量的对比图，两者之间的差距即已提交但未使用的堆
+pub fn write_heap_charts(
+    dir: &Path,
+    package: &str,
+    history: &[(DateTime<Local>, MemoryDetails)],
+) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let mut paths = Vec::new();
+    let series = |value: fn(&MemoryDetails) -> u64| -> Vec<(DateTime<Local>, f64)> {
+        history
+            .iter()
+            .map(|(t, details)| (*t, value(details) as f64))
+            .collect()
+    };
+
+    for (name, file_name, size, alloc, extra) in [
+        (
+            "Dalvik Heap",
+            "dalvik_heap.png",
+            series(|d| d.dalvik_heap_detail.size),
+            series(|d| d.dalvik_heap_detail.alloc),
+            Some(("LOS (PSS)", series(|d| d.dalvik_los))),
+        ),
+        (
+            "Native Heap",
+            "native_heap.png",
+            series(|d| d.native_heap_detail.size),
+            series(|d| d.native_heap_detail.alloc),
+            None,
+        ),
+    ] {
+        // 设备不提供堆明细时跳过
+        if size.iter().all(|(_, v)| *v == 0.0) {
+            continue;
+        }
+        let mut lines = vec![
+            TimeSeriesData::new("Heap Size", size),
+            TimeSeriesData::new("Heap Alloc", alloc),
+        ];
+        if let Some((label, points)) = extra.filter(|(_, p)| p.iter().any(|(_, v)| *v > 0.0)) {
+            lines.push(TimeSeriesData::new(label, points));
+        }
+        let path = dir.join(chart::stamped_file_name(&format!(
+            "{}_{}",
+            package, file_name
+        )));
+        chart::draw_time_series_chart(
+            &path,
+            &format!("{} Size vs Alloc - {}", name, package),
+            "Memory (KB)",
+            &lines,
+        )?;
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
+
+// PSS 与 USS、RSS、Swap 的对比图，没有 smaps_rollup 数据时返回 None
+pub fn write_smaps_chart(
+    dir: &Path,
+    package: &str,
+    history: &[(DateTime<Local>, MemoryDetails)],
+) -> Result<Option<PathBuf>> {
+    let series = |value: fn(&SmapsRollup) -> u64| -> Vec<(DateTime<Local>, f64)> {
+        history
+            .iter()
+            .filter_map(|(t, details)| details.smaps.as_ref().map(|s| (*t, value(s) as f64)))
+            .collect()
+    };
+    let pss = series(|s| s.pss);
+    if pss.len() < 2 {
+        return Ok(None);
+    }
+    let mut lines = vec![
+        TimeSeriesData::new("RSS", series(|s| s.rss)),
+        TimeSeriesData::new("PSS", pss),
+        TimeSeriesData::new("USS", series(|s| s.uss)),
+    ];
+    let swap = series(|s| s.swap);
+    if swap.iter().any(|(_, v)| *v > 0.0) {
+        lines.push(TimeSeriesData::new("Swap", swap));
+    }
+
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(chart::stamped_file_name(&format!(
+        "{}_rss_pss_uss.png",
+        package
+    )));
+    chart::draw_time_series_chart(
+        &path,
+        &format!("RSS / PSS / USS / Swap (smaps_rollup) - {}", package),
+        "Memory (KB)",
+        &lines,
+    )?;
+    Ok(Some(path))
+}
+
+// 内存图表上的叠加内容：主缺页风暴区间、GC 时间点和前台 Activity 底纹
+#[derive(Debug, Default)]
+pub struct MemoryChartOverlay {
+    pub fault_storms: Vec<(DateTime<Local>, DateTime<Local>)>,
+    pub gc_times: Vec<DateTime<Local>>,
+    pub bands: Vec<chart::Band>,
+}
+
+// 生成内存图表的函数
+pub fn generate_memory_charts(
+    output_dir: &Path,
+    package: &str,
+    memory_data: &MemoryTimeSeriesData,
+    write_csv: bool,
+    trends: &[(&str, Trend)],
+    overlay: &MemoryChartOverlay,
+) -> Result<Vec<PathBuf>> {
+    use plotters::prelude::*;
+
+    // 创建一个单一的内存图表文件
+    let mut chart_paths = Vec::new();
+    let file_name = chart::stamped_file_name(&format!("{}_memory_chart.png", package));
+    let path = output_dir.join(file_name);
+
+    // 检查数据是否足够
+    if memory_data.timestamps.is_empty() || memory_data.memory_details.is_empty() {
+        return Err(anyhow::format_err!("No memory data to chart"));
+    }
+
+    // 创建图表
+    let theme = chart::theme();
+    let root = BitMapBackend::new(&path, chart::chart_size()).into_drawing_area();
+    root.fill(&theme.background)?;
+
+    // 创建图表标题
+    let title = chart::stamped_title(&format!("Memory Usage - {}", package));
+
+    // 分割绘图区域为标题、图表和图例
+    let (title_area, rest_area) = root.split_vertically(50);
+
+    // 绘制标题
+    title_area.titled(&title, chart::text_style(20))?;
+
+    // 查找最大内存使用量以设置Y轴范围
+    let mut max_memory = 0.1f32;
+    for detail in &memory_data.memory_details {
+        max_memory = max_memory.max(detail.total_pss as f32);
+        max_memory = max_memory.max(detail.java_heap as f32);
+        max_memory = max_memory.max(detail.native_heap as f32);
+        max_memory = max_memory.max(detail.code as f32);
+        max_memory = max_memory.max(detail.stack as f32);
+        max_memory = max_memory.max(detail.graphics as f32);
+        max_memory = max_memory.max(detail.private_other as f32);
+        max_memory = max_memory.max(detail.system as f32);
+    }
+
+    // 添加一些填充到最大内存使用量
+    max_memory *= 1.1;
+
+    // 获取时间范围
+    let min_time = *memory_data.timestamps.front().unwrap();
+    let max_time = *memory_data.timestamps.back().unwrap();
+
+    // 定义内存类型和对应的名称
+    let memory_types = [
+        "Total PSS",
+        "Java Heap",
+        "Native Heap",
+        "Code",
+        "Stack",
+        "Graphics",
+        "Private Other",
+        "System",
+    ];
+
+    // 创建图表上下文
+    let mut chart = ChartBuilder::on(&rest_area)
+        .margin(10)
+        .margin_right(35) // 增加右侧边距为图例留出空间
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d(min_time..max_time, 0f32..max_memory)?;
+
+    // 配置网格
+    chart::themed_mesh(&mut chart.configure_mesh())
+        .x_labels(8)
+        .x_label_formatter(&|x| x.format("%H:%M:%S").to_string())
+        .y_desc("Memory Usage (KB)")
+        .x_desc("Time")
+        .draw()?;
+
+    // 预热区间底纹
+    if let Some(shade) = chart::warmup_shade(min_time, max_time, 0f32, max_memory) {
+        chart.draw_series(std::iter::once(shade))?;
+    }
+
+    // Activity 底纹
+    for (label, color, rects) in
+        chart::band_groups(&overlay.bands, min_time, max_time, 0f32, max_memory)
+    {
+        chart
+            .draw_series(rects)?
+            .label(label)
+            .legend(move |(x, y)| Rectangle::new([(x, y - 5), (x + 20, y + 5)], color.filled()));
+    }
+
+    // 主缺页风暴区间底纹
+    let storms: Vec<_> = overlay
+        .fault_storms
+        .iter()
+        .filter(|(start, end)| *end > min_time && *start < max_time)
+        .map(|(start, end)| {
+            Rectangle::new(
+                [
+                    ((*start).max(min_time), 0f32),
+                    ((*end).min(max_time), max_memory),
+                ],
+                theme.marker.mix(0.12).filled(),
+            )
+        })
+        .collect();
+    if !storms.is_empty() {
+        let marker = theme.marker;
+        chart
+            .draw_series(storms)?
+            .label("Major fault storm")
+            .legend(move |(x, y)| {
+                Rectangle::new([(x, y - 5), (x + 20, y + 5)], marker.mix(0.3).filled())
+            });
+    }
+
+    // 为每种内存类型绘制数据线
+    for (i, &memory_type) in memory_types.iter().enumerate() {
+        let color = theme.series_color(i);
+
+        // 根据内存类型获取对应的数据
+        let values: Vec<(DateTime<Local>, f32)> = memory_data
+            .timestamps
+            .iter()
+            .zip(memory_data.memory_details.iter())
+            .map(|(t, d)| {
+                let value = match i {
+                    0 => d.total_pss as f32,
+                    1 => d.java_heap as f32,
+                    2 => d.native_heap as f32,
+                    3 => d.code as f32,
+                    4 => d.stack as f32,
+                    5 => d.graphics as f32,
+                    6 => d.private_other as f32,
+                    7 => d.system as f32,
+                    _ => 0.0,
+                };
+                (t.to_owned(), value)
+            })
+            .collect();
+
+        // 绘制数据线
+        chart
+            .draw_series(LineSeries::new(
+                chart::downsample(&values, chart::max_points()),
+                color,
+            ))?
+            .label(memory_type.to_string())
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+    }
+
+    // 用户插入的事件标记
+    chart.draw_series(chart::user_marker_lines(
+        min_time, max_time, 0f32, max_memory,
+    ))?;
+    chart.draw_series(chart::user_marker_labels(
+        min_time,
+        max_time,
+        max_memory * 0.95,
+    ))?;
+
+    // GC 标记：底部的短竖线
+    let gc_ticks: Vec<_> = overlay
+        .gc_times
+        .iter()
+        .filter(|t| **t >= min_time && **t <= max_time)
+        .map(|t| PathElement::new(vec![(*t, 0f32), (*t, max_memory * 0.04)], theme.marker))
+        .collect();
+    if !gc_ticks.is_empty() {
+        let marker = theme.marker;
+        chart
+            .draw_series(gc_ticks)?
+            .label(format!("GC ({})", overlay.gc_times.len()))
+            .legend(move |(x, y)| PathElement::new(vec![(x + 10, y - 5), (x + 10, y + 5)], marker));
+    }
+
+    // 回归趋势线（虚线），颜色与对应的曲线一致
+    for (metric, trend) in trends {
+        let Some(i) = memory_types.iter().position(|t| t == metric) else {
+            continue;
+        };
+        let color = theme.series_color(i);
+        let from = trend.start.max(min_time);
+        let label = format!(
+            "{} trend {:+.2} MB/h{}",
+            metric,
+            trend.mb_per_hour(),
+            if trend.is_probable_leak() {
+                " (probable leak)"
+            } else {
+                ""
+            }
+        );
+        chart
+            .draw_series(DashedLineSeries::new(
+                trend
+                    .line(from, max_time)
+                    .into_iter()
+                    .map(|(t, v)| (t, v as f32)),
+                10,
+                6,
+                color.stroke_width(2),
+            ))?
+            .label(label)
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+    }
+
+    // 添加图例配置
+    chart::themed_legend(&mut chart.configure_series_labels())
+        .position(SeriesLabelPosition::UpperRight)
+        .margin(10)
+        .legend_area_size(35) // 增加图例区域大小
+        .draw()?;
+
+    // 保存图表
+    root.present()?;
+
+    chart_paths.push(path.clone());
+    // 移除输出，由调用者处理输出
+    // console!("✓ Memory chart generated: {}", path.display());
+
+    // 导出内存数据到CSV
+    let csv_path = output_dir.join(format!("{}_memory_data.csv", package));
+    if !write_csv {
+        return Ok(chart_paths);
+    }
+    if let Ok(file) = std::fs::File::create(&csv_path) {
+        let mut writer = std::io::BufWriter::new(file);
+
+        // 写入CSV头
+        writeln!(
+            &mut writer,
+            "Timestamp,Total PSS,Java Heap,Native Heap,Code,Stack,Graphics,Private Other,System"
+        )?;
+
+        // 写入每个数据点
+        for i in 0..memory_data.timestamps.len() {
+            let timestamp = &memory_data.timestamps[i];
+            let details = &memory_data.memory_details[i];
+
+            writeln!(
+                &mut writer,
+                "{},{},{},{},{},{},{},{},{}",
+                timestamp.format("%Y-%m-%d %H:%M:%S"),
+                details.total_pss,
+                details.java_heap,
+                details.native_heap,
+                details.code,
+                details.stack,
+                details.graphics,
+                details.private_other,
+                details.system
+            )?;
+        }
+
+        // 添加CSV文件路径到返回结果
+        chart_paths.push(csv_path.clone());
+        // 移除输出，由调用者处理输出
+        // console!("✓ Memory data exported to CSV: {}", csv_path.display());
+    }
+
+    Ok(chart_paths)
+}
+
+// 保留原始的单个内存指标图表函数，但它不会被直接调用
+pub fn generate_single_memory_chart(
+    output_dir: &Path,
+    package: &str,
+    metric_name: &str,
+    timestamps: &VecDeque<DateTime<Local>>,
+    values: &[f32],
+) -> Result<PathBuf> {
+    use plotters::prelude::*;
+
+    // 创建文件名，用下划线替换空格
+    let file_name = chart::stamped_file_name(&format!(
+        "{}_{}.png",
+        package,
+        metric_name.replace(" ", "_")
+    ));
+    let path = output_dir.join(file_name);
+    let path_copy = path.clone();
+
+    // 创建图表
+    let theme = chart::theme();
+    let root = BitMapBackend::new(&path, chart::chart_size()).into_drawing_area();
+    root.fill(&theme.background)?;
+
+    // 找到最大值
+    let max_value = values.iter().fold(0.0f32, |a, &b| a.max(b)) * 1.1;
+
+    // 获取开始和结束时间
+    let first_timestamp = timestamps.front().unwrap();
+    let last_timestamp = timestamps.back().unwrap();
+
+    // 定义图表区域
+    let mut chart = ChartBuilder::on(&root)
+        .caption(
+            chart::stamped_title(&format!("{} - {}", package, metric_name)),
+            chart::text_style(22),
+        )
+        .margin(10)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d(
+            first_timestamp.to_owned()..last_timestamp.to_owned(),
+            0.0..max_value,
+        )?;
+
+    // 配置网格和标签
+    chart::themed_mesh(&mut chart.configure_mesh())
+        .x_labels(10)
+        .x_label_formatter(&|x| x.format("%H:%M:%S").to_string())
+        .y_desc(format!("{} (KB)", metric_name))
+        .draw()?;
+
+    chart.draw_series(chart::user_marker_lines(
+        *first_timestamp,
+        *last_timestamp,
+        0.0,
+        max_value,
+    ))?;
+    chart.draw_series(chart::user_marker_labels(
+        *first_timestamp,
+        *last_timestamp,
+        max_value * 0.95,
+    ))?;
+
+    // 绘制折线
+    let points: Vec<(DateTime<Local>, f32)> = timestamps
+        .iter()
+        .zip(values.iter())
+        .map(|(t, &v)| (t.to_owned(), v))
+        .collect();
+    chart.draw_series(LineSeries::new(
+        chart::downsample(&points, chart::max_points()),
+        theme.series_color(0),
+    ))?;
+
+    // 保存图表
+    root.present()?;
+
+    Ok(path_copy)
+}
+
+// 回到桌面后持续增长的计数
+#[derive(Debug, Clone)]
+pub struct BackgroundGrowth {
+    pub name: &'static str,
+    // 连续增长的第一次采样
+    pub start: DateTime<Local>,
+    pub timestamp: DateTime<Local>,
+    pub from: u64,
+    pub to: u64,
+}
+
+impl BackgroundGrowth {
+    pub fn band(&self) -> Band {
+        Band {
+            start: self.start,
+            end: self.timestamp,
+            label: format!("{} growing in background", self.name),
+            muted: false,
+        }
+    }
+}
+
+// 应用退到后台（回到桌面）后 Activities 和 ViewRootImpl 应保持不变或随 GC 回落；
+// 连续 samples 次采样单调不减且有增长时告警，每个后台区间每项只告警一次
+pub struct BackgroundGrowthDetector {
+    samples: usize,
+    background: Vec<(DateTime<Local>, ObjectCounts)>,
+    alerted: Vec<&'static str>,
+    pub alerts: Vec<BackgroundGrowth>,
+}
+
+impl BackgroundGrowthDetector {
+    pub const INDICATORS: [&'static str; 2] = ["Activities", "ViewRootImpl"];
+
+    pub fn new(samples: usize) -> Self {
+        Self {
+            samples: samples.max(2),
+            background: Vec::new(),
+            alerted: Vec::new(),
+            alerts: Vec::new(),
+        }
+    }
+
+    // 返回本次采样新触发的告警；回到前台时重新开始
+    pub fn record(
+        &mut self,
+        timestamp: DateTime<Local>,
+        objects: ObjectCounts,
+        foreground: bool,
+    ) -> Vec<BackgroundGrowth> {
+        if foreground {
+            self.background.clear();
+            self.alerted.clear();
+            return Vec::new();
+        }
+        self.background.push((timestamp, objects));
+        let Some(window) = self
+            .background
+            .len()
+            .checked_sub(self.samples)
+            .map(|start| &self.background[start..])
+        else {
+            return Vec::new();
+        };
+        let mut alerts = Vec::new();
+        for name in Self::INDICATORS {
+            if self.alerted.contains(&name) {
+                continue;
+            }
+            let values: Vec<u64> = window.iter().filter_map(|(_, o)| o.get(name)).collect();
+            let (Some(&from), Some(&to)) = (values.first(), values.last()) else {
+                continue;
+            };
+            if to > from && values.windows(2).all(|pair| pair[1] >= pair[0]) {
+                self.alerted.push(name);
+                alerts.push(BackgroundGrowth {
+                    name,
+                    start: window[0].0,
+                    timestamp,
+                    from,
+                    to,
+                });
+            }
+        }
+        self.alerts.extend(alerts.iter().cloned());
+        alerts
+    }
+}
+
+// 对象计数图：泄漏指标（Activities 等，数量小）和其余对象（Views、Binder 等）分两张图
+pub fn write_object_charts(
+    dir: &Path,
+    package: &str,
+    history: &[(DateTime<Local>, MemoryDetails)],
+) -> Result<Vec<PathBuf>> {
+    let samples: Vec<(DateTime<Local>, ObjectCounts)> = history
+        .iter()
+        .filter_map(|(t, details)| details.objects.map(|o| (*t, o)))
+        .collect();
+    let mut paths = Vec::new();
+    if samples.len() < 2 {
+        return Ok(paths);
+    }
+    std::fs::create_dir_all(dir)?;
+    let series = |leak_indicators: bool| -> Vec<TimeSeriesData> {
+        ObjectCounts::default()
+            .columns()
+            .iter()
+            .enumerate()
+            .filter(|(_, (name, _))| {
+                ObjectCounts::LEAK_INDICATORS.contains(name) == leak_indicators
+            })
+            .map(|(i, (name, _))| {
+                TimeSeriesData::new(
+                    *name,
+                    samples
+                        .iter()
+                        .map(|(t, o)| (*t, o.columns()[i].1 as f64))
+                        .collect(),
+                )
+            })
+            .collect()
+    };
+    for (file_name, title, lines) in [
+        (
+            "objects_leak.png",
+            "Leak Indicators (Objects)",
+            series(true),
+        ),
+        ("objects.png", "Objects", series(false)),
+    ] {
+        let path = dir.join(chart::stamped_file_name(&format!(
+            "{}_{}",
+            package, file_name
+        )));
+        chart::draw_time_series_chart(&path, &format!("{} - {}", title, package), "Count", &lines)?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+// Activities 和 ViewRootImpl 的专用泄漏图：后台区间加灰色底纹，回到桌面后持续增长的区间加彩色底纹
+pub fn write_activity_leak_chart(
+    dir: &Path,
+    package: &str,
+    samples: &[(DateTime<Local>, ObjectCounts)],
+    bands: &[Band],
+) -> Result<Option<PathBuf>> {
+    if samples.len() < 2 {
+        return Ok(None);
+    }
+    std::fs::create_dir_all(dir)?;
+    let series: Vec<TimeSeriesData> = BackgroundGrowthDetector::INDICATORS
+        .iter()
+        .map(|name| {
+            TimeSeriesData::new(
+                *name,
+                samples
+                    .iter()
+                    .filter_map(|(t, o)| Some((*t, o.get(name)? as f64)))
+                    .collect(),
+            )
+        })
+        .collect();
+    let path = dir.join(chart::stamped_file_name(&format!(
+        "{}_activity_leak.png",
+        package
+    )));
+    chart::draw_banded_time_series_chart(
+        &path,
+        &format!("Activities / ViewRootImpl - {}", package),
+        "Count",
+        &series,
+        bands,
+    )?;
+    Ok(Some(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MEMINFO: &str = include_str!("../testdata/meminfo_d.txt");
+
+    #[test]
+    fn parse_objects_section() {
+        let objects = parse_objects(MEMINFO).unwrap();
+        assert_eq!(
+            objects,
+            ObjectCounts {
+                views: 245,
+                view_roots: 2,
+                app_contexts: 6,
+                activities: 2,
+                assets: 22,
+                asset_managers: 0,
+                local_binders: 31,
+                proxy_binders: 45,
+                parcel_memory_kb: 12,
+                parcel_count: 48,
+                death_recipients: 3,
+                webviews: 1,
+            }
+        );
+        assert_eq!(objects.get("ViewRootImpl"), Some(2));
+    }
+
+    #[test]
+    fn parse_objects_missing_section() {
+        let truncated = MEMINFO.split(" Objects").next().unwrap();
+        assert!(parse_objects(truncated).is_none());
+    }
+
+    #[test]
+    fn parse_heap_rows() {
+        let row = |name: &str| MEMINFO.lines().map(str::trim).find(|l| l.starts_with(name));
+        let native = parse_heap_row(row("Native Heap").unwrap()).unwrap();
+        assert_eq!(
+            (native.size, native.alloc, native.free),
+            (32768, 21534, 11233)
+        );
+        // 没有 Heap 列的行
+        assert!(parse_heap_row(row("Dalvik Other").unwrap()).is_none());
+    }
+
+    #[test]
+    fn parse_smaps_rollup_sums_private_pages() {
+        let output = "\
+7c1e2b3000-7fffdd4000 ---p 00000000 00:00 0                              [rollup]
+Rss:              156556 kB
+Pss:               65063 kB
+Pss_Anon:          45210 kB
+Shared_Clean:      84316 kB
+Shared_Dirty:       6420 kB
+Private_Clean:     14352 kB
+Private_Dirty:     44268 kB
+Swap:                184 kB
+SwapPss:              92 kB
+Locked:                0 kB
+";
+        let rollup = parse_smaps_rollup(output).unwrap();
+        assert_eq!(rollup.rss, 156556);
+        assert_eq!(rollup.pss, 65063);
+        assert_eq!(rollup.uss, 58620);
+        assert_eq!((rollup.swap, rollup.swap_pss), (184, 92));
+        assert!(parse_smaps_rollup("cat: /proc/1/smaps_rollup: Permission denied").is_none());
+    }
+
+    #[test]
+    fn background_growth_alerts_once_per_background_stay() {
+        let base = parse_objects(MEMINFO).unwrap();
+        let counts = |activities: u64| ObjectCounts { activities, ..base };
+        let start = Local::now();
+        let at = |i: i64| start + chrono::Duration::seconds(i);
+        let mut detector = BackgroundGrowthDetector::new(3);
+        assert!(detector.record(at(0), counts(5), true).is_empty());
+        assert!(detector.record(at(1), counts(2), false).is_empty());
+        assert!(detector.record(at(2), counts(3), false).is_empty());
+        let alerts = detector.record(at(3), counts(3), false);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].name, "Activities");
+        assert_eq!((alerts[0].from, alerts[0].to), (2, 3));
+        assert_eq!(alerts[0].start, at(1));
+        // 同一后台区间不再告警，回到前台后重新开始
+        assert!(detector.record(at(4), counts(4), false).is_empty());
+        assert!(detector.record(at(5), counts(4), true).is_empty());
+        assert!(detector.record(at(6), counts(4), false).is_empty());
+        assert!(detector.record(at(7), counts(4), false).is_empty());
+        assert!(detector.record(at(8), counts(4), false).is_empty());
+        assert_eq!(detector.alerts.len(), 1);
+    }
+}
@@ -0,0 +1,246 @@
+use crate::cpu::{self, CpuScale, ThreadCpuInfo};
+use crate::device;
+use crate::memory::{self, MemoryDetails, SmapsReader};
+use crate::tick::Tick;
+use crate::utils;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+// 供其他工具和测试以代码方式驱动监控：只采样 CPU 和内存，不写文件
+#[derive(Debug, Clone)]
+pub struct MonitorConfig {
+    pub package: String,
+    pub interval: Duration,
+    pub cpu: bool,
+    pub memory: bool,
+    pub cpu_scale: CpuScale,
+    pub verbose: bool,
+}
+
+impl MonitorConfig {
+    pub fn new(package: &str) -> Self {
+        Self {
+            package: package.to_string(),
+            interval: Duration::from_secs(1),
+            cpu: true,
+            memory: true,
+            cpu_scale: CpuScale::PerCore,
+            verbose: false,
+        }
+    }
+}
+
+// 一个 Tick 的采样结果，未启用或采样失败的指标为 None
+#[derive(Debug, Clone)]
+pub struct MonitorSample {
+    pub tick: Tick,
+    pub pid: String,
+    pub restarted: bool,
+    pub cpu: Option<f32>,
+    pub threads: Vec<ThreadCpuInfo>,
+    pub memory: Option<MemoryDetails>,
+}
+
+// 峰值及其时间，以及重启次数
+#[derive(Debug, Clone, Default)]
+pub struct MonitorPeaks {
+    pub cpu: f32,
+    pub cpu_time: Option<DateTime<Local>>,
+    pub memory_kb: u64,
+    pub memory_time: Option<DateTime<Local>>,
+    pub restarts: u32,
+    pub samples: u64,
+}
+
+impl MonitorPeaks {
+    pub fn record(&mut self, sample: &MonitorSample) {
+        self.samples += 1;
+        if sample.restarted {
+            self.restarts += 1;
+        }
+        if let Some(cpu) = sample.cpu.filter(|cpu| *cpu > self.cpu) {
+            self.cpu = cpu;
+            self.cpu_time = Some(sample.tick.timestamp);
+        }
+        if let Some(memory) = sample
+            .memory
+            .as_ref()
+            .filter(|m| m.total_pss > self.memory_kb)
+        {
+            self.memory_kb = memory.total_pss;
+            self.memory_time = Some(sample.tick.timestamp);
+        }
+    }
+}
+
+// 一个 Tick 的进程、CPU 和内存采样，Monitor 和命令行的监控会话共用
+pub struct Sampler {
+    config: MonitorConfig,
+    process: utils::ProcessInfo,
+    cores: u32,
+    // smaps_rollup 中的 USS、RSS 和 Swap，需要 root 或 debuggable 应用
+    smaps: SmapsReader,
+}
+
+impl Sampler {
+    // 进程不存在时返回错误
+    pub fn new(config: MonitorConfig) -> Result<Self> {
+        let process = utils::get_process_info(&config.package)?;
+        Ok(Self {
+            cores: device::query_cpu_cores(None),
+            smaps: SmapsReader::new(&config.package),
+            process,
+            config,
+        })
+    }
+
+    pub fn config(&self) -> &MonitorConfig {
+        &self.config
+    }
+
+    // 最近一次采样时的进程
+    pub fn process(&self) -> &utils::ProcessInfo {
+        &self.process
+    }
+
+    // 用于 CPU 归一化的核心数
+    pub fn cores(&self) -> u32 {
+        self.cores
+    }
+
+    // smaps_rollup 不可读时不再尝试
+    pub fn smaps_unavailable(&self) -> bool {
+        self.smaps.is_unavailable()
+    }
+
+    // PID 变化视为重启；进程不存在时返回错误
+    pub async fn sample(&mut self, tick: Tick) -> Result<MonitorSample> {
+        let current = utils::get_process_info(&self.config.package)?;
+        let restarted = current.pid != self.process.pid;
+        self.process = current;
+
+        let (cpu, threads) = if self.config.cpu {
+            match cpu::sample_cpu(&self.config.package).await {
+                Ok((usage, _, mut threads)) => {
+                    let scale = self.config.cpu_scale;
+                    for thread in &mut threads {
+                        thread.timestamp = Some(tick.timestamp);
+                        thread.cpu_usage = scale.normalize(thread.cpu_usage, self.cores);
+                    }
+                    (Some(scale.normalize(usage, self.cores)), threads)
+                }
+                Err(_) => (None, Vec::new()),
+            }
+        } else {
+            (None, Vec::new())
+        };
+        let memory = if self.config.memory {
+            memory::sample_memory(&self.config.package, self.config.verbose)
+                .await
+                .ok()
+                .map(|(_, _, mut details)| {
+                    details.smaps = self.smaps.sample(&self.process.pid);
+                    details
+                })
+        } else {
+            None
+        };
+
+        Ok(MonitorSample {
+            tick,
+            pid: self.process.pid.clone(),
+            restarted,
+            cpu,
+            threads,
+            memory,
+        })
+    }
+}
+
+// 后台任务按间隔采样，样本通过 subscribe 获取；stop 结束采样并返回峰值
+pub struct Monitor {
+    running: Arc<AtomicBool>,
+    peaks: Arc<Mutex<MonitorPeaks>>,
+    sender: broadcast::Sender<MonitorSample>,
+    task: JoinHandle<Result<()>>,
+}
+
+impl Monitor {
+    // 进程不存在时返回错误；在 utils::with_device 作用域内调用时使用该设备
+    pub fn start(config: MonitorConfig) -> Result<Self> {
+        let sampler = Sampler::new(config)?;
+        let running = Arc::new(AtomicBool::new(true));
+        let peaks = Arc::new(Mutex::new(MonitorPeaks::default()));
+        let (sender, _) = broadcast::channel(256);
+
+        let task = {
+            let running = running.clone();
+            let peaks = peaks.clone();
+            let sender = sender.clone();
+            let device = utils::current_task_device();
+            tokio::spawn(utils::with_device(device, async move {
+                run(sampler, running, peaks, sender).await
+            }))
+        };
+
+        Ok(Self {
+            running,
+            peaks,
+            sender,
+            task,
+        })
+    }
+
+    // 只能收到订阅之后的样本
+    pub fn subscribe(&self) -> broadcast::Receiver<MonitorSample> {
+        self.sender.subscribe()
+    }
+
+    pub fn peaks(&self) -> MonitorPeaks {
+        self.peaks.lock().unwrap().clone()
+    }
+
+    pub fn is_running(&self) -> bool {
+        !self.task.is_finished()
+    }
+
+    pub async fn stop(self) -> Result<MonitorPeaks> {
+        self.running.store(false, Ordering::SeqCst);
+        self.task.await??;
+        let peaks = self.peaks.lock().unwrap().clone();
+        Ok(peaks)
+    }
+}
+
+async fn run(
+    mut sampler: Sampler,
+    running: Arc<AtomicBool>,
+    peaks: Arc<Mutex<MonitorPeaks>>,
+    sender: broadcast::Sender<MonitorSample>,
+) -> Result<()> {
+    let mut interval = tokio::time::interval(sampler.config.interval);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    let mut id = 0;
+
+    while running.load(Ordering::SeqCst) {
+        interval.tick().await;
+        if !running.load(Ordering::SeqCst) {
+            break;
+        }
+        id += 1;
+
+        // 进程暂时不存在时跳过本次采样
+        let Ok(sample) = sampler.sample(Tick::new(id)).await else {
+            continue;
+        };
+        peaks.lock().unwrap().record(&sample);
+        // 没有订阅者时发送失败，忽略
+        let _ = sender.send(sample);
+    }
+    Ok(())
+}
@@ -0,0 +1,15 @@
+use std::sync::OnceLock;
+
+// 工具可以接管终端输出（xperformance 的 --tui 日志区和 --ci 的 stderr），未设置时直接打印
+static SINK: OnceLock<fn(String)> = OnceLock::new();
+
+pub fn set_sink(sink: fn(String)) {
+    let _ = SINK.set(sink);
+}
+
+pub fn line(text: String) {
+    match SINK.get() {
+        Some(sink) => sink(text),
+        None => println!("{}", text),
+    }
+}
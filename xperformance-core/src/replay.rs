@@ -0,0 +1,230 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// 录制文件名，每行一条 adb 调用记录
+pub const RAW_FILE: &str = "raw_adb.jsonl";
+
+// 一次 adb 调用的原始输出，失败时 output 为错误信息；serial 为调用所针对的设备
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RawRecord {
+    timestamp: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    serial: Option<String>,
+    args: Vec<String>,
+    ok: bool,
+    output: String,
+}
+
+// 回放时按设备和参数分组，多台设备的录制不会互相混用
+type RecordKey = (Option<String>, Vec<String>);
+
+enum Mode {
+    Record(std::fs::File),
+    // 同一设备的同一命令按录制顺序依次返回；serial 为回放的设备，clock 为最近一次返回记录的录制时间
+    Replay {
+        records: HashMap<RecordKey, VecDeque<RawRecord>>,
+        serial: Option<String>,
+        clock: Option<DateTime<Local>>,
+    },
+}
+
+static MODE: Mutex<Option<Mode>> = Mutex::new(None);
+
+// 开始录制，之后所有 adb 调用的输出追加到 dir/raw_adb.jsonl
+pub fn start_recording(dir: &Path) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(RAW_FILE);
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)?;
+    *MODE.lock().unwrap() = Some(Mode::Record(file));
+    Ok(path)
+}
+
+// 加载录制文件（可传目录或文件），之后 adb 调用改为返回录制的输出，返回该设备的记录数
+// 录制中有多台设备（--all-devices）时用 serial 选择回放哪一台
+pub fn start_replay(path: &Path, serial: Option<&str>) -> Result<usize> {
+    let path = if path.is_dir() {
+        path.join(RAW_FILE)
+    } else {
+        path.to_path_buf()
+    };
+    let file = std::fs::File::open(&path)
+        .with_context(|| format!("Failed to open capture {}", path.display()))?;
+
+    let mut records: HashMap<RecordKey, VecDeque<RawRecord>> = HashMap::new();
+    let mut counts: HashMap<Option<String>, usize> = HashMap::new();
+    for line in std::io::BufReader::new(file).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: RawRecord = serde_json::from_str(&line)
+            .with_context(|| format!("Invalid record in {}", path.display()))?;
+        *counts.entry(record.serial.clone()).or_default() += 1;
+        records
+            .entry((record.serial.clone(), record.args.clone()))
+            .or_default()
+            .push_back(record);
+    }
+
+    let serial = match serial {
+        Some(serial) => Some(serial.to_string()),
+        None if counts.len() > 1 => {
+            let mut serials: Vec<String> = counts.keys().flatten().cloned().collect();
+            serials.sort();
+            anyhow::bail!(
+                "{} contains several devices ({}), choose one with --serial",
+                path.display(),
+                serials.join(", ")
+            );
+        }
+        None => counts.keys().next().cloned().flatten(),
+    };
+    let count = counts.get(&serial).copied().unwrap_or(0);
+    if count == 0 {
+        anyhow::bail!(
+            "No recorded adb output{} in {}",
+            serial
+                .as_deref()
+                .map(|s| format!(" for {}", s))
+                .unwrap_or_default(),
+            path.display()
+        );
+    }
+
+    *MODE.lock().unwrap() = Some(Mode::Replay {
+        records,
+        serial,
+        clock: None,
+    });
+    Ok(count)
+}
+
+pub fn is_replaying() -> bool {
+    matches!(*MODE.lock().unwrap(), Some(Mode::Replay { .. }))
+}
+
+// 当前时间：回放时为最近一次回放记录的录制时间，使时间戳与录制时一致
+pub fn now() -> DateTime<Local> {
+    match &*MODE.lock().unwrap() {
+        Some(Mode::Replay {
+            clock: Some(clock), ..
+        }) => *clock,
+        _ => Local::now(),
+    }
+}
+
+// 回放模式下返回录制的输出；同一命令的录制用完后返回错误，监控随之结束
+pub(crate) fn replay(args: &[&str]) -> Option<Result<String>> {
+    let mut mode = MODE.lock().unwrap();
+    let Some(Mode::Replay {
+        records,
+        serial,
+        clock,
+    }) = mode.as_mut()
+    else {
+        return None;
+    };
+    let key = (serial.clone(), args.iter().map(|a| a.to_string()).collect());
+    let Some(record) = records.get_mut(&key).and_then(VecDeque::pop_front) else {
+        return Some(Err(anyhow::format_err!(
+            "Replay exhausted for: adb {}",
+            args.join(" ")
+        )));
+    };
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(&record.timestamp) {
+        *clock = Some(timestamp.with_timezone(&Local));
+    }
+    Some(if record.ok {
+        Ok(record.output)
+    } else {
+        Err(anyhow::format_err!("{}", record.output))
+    })
+}
+
+// 录制模式下追加一条记录
+pub(crate) fn record(args: &[&str], result: &Result<String>) {
+    let mut mode = MODE.lock().unwrap();
+    let Some(Mode::Record(file)) = mode.as_mut() else {
+        return;
+    };
+    let record = RawRecord {
+        timestamp: Local::now().to_rfc3339(),
+        serial: crate::utils::adb_serial(),
+        args: args.iter().map(|a| a.to_string()).collect(),
+        ok: result.is_ok(),
+        output: match result {
+            Ok(output) => output.clone(),
+            Err(e) => e.to_string(),
+        },
+    };
+    if let Ok(line) = serde_json::to_string(&record) {
+        let _ = writeln!(file, "{}", line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils;
+
+    // 录制的 adb 输出；回放状态是全局的，所有回放相关断言放在同一个测试中
+    const CAPTURE: &str = r#"{"timestamp":"2026-10-15T14:00:01+08:00","args":["shell","ls /proc/12345/task"],"ok":true,"output":"12345\n12351\n12360\n12388\n12402\n"}
+{"timestamp":"2026-10-15T14:00:02+08:00","args":["shell","ls /proc/12345/task"],"ok":true,"output":"12345\n12351\n12360\n"}
+{"timestamp":"2026-10-15T14:00:03+08:00","args":["shell","ls /proc/12345/task"],"ok":false,"output":"ADB command failed: error: device offline"}
+"#;
+
+    #[test]
+    fn replay_returns_recorded_output_in_order() {
+        let dir = std::env::temp_dir().join(format!("xperformance_replay_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(RAW_FILE), CAPTURE).unwrap();
+        let list_tasks = || utils::run_adb_command(&["shell", "ls /proc/12345/task"]);
+
+        assert_eq!(start_replay(&dir, None).unwrap(), 3);
+        assert!(is_replaying());
+        assert_eq!(list_tasks().unwrap().lines().count(), 5);
+        assert_eq!(
+            now(),
+            DateTime::parse_from_rfc3339("2026-10-15T14:00:01+08:00").unwrap()
+        );
+        assert_eq!(list_tasks().unwrap().lines().count(), 3);
+        let error = list_tasks().unwrap_err();
+        assert!(error.to_string().contains("device offline"));
+        // 录制用完后不再访问设备
+        let error = list_tasks().unwrap_err();
+        assert!(error.to_string().contains("Replay exhausted"));
+
+        // --all-devices 的录制按设备回放，需要用 serial 选择设备
+        let two_devices = [
+            r#"{"timestamp":"2026-10-15T14:00:01+08:00","serial":"A","args":["shell","getprop ro.product.model"],"ok":true,"output":"Pixel 7"}"#,
+            r#"{"timestamp":"2026-10-15T14:00:01+08:00","serial":"B","args":["shell","getprop ro.product.model"],"ok":true,"output":"Pixel 8"}"#,
+        ];
+        std::fs::write(dir.join(RAW_FILE), two_devices.join("\n")).unwrap();
+        let error = start_replay(&dir, None).unwrap_err();
+        assert!(error.to_string().contains("several devices (A, B)"));
+        assert_eq!(start_replay(&dir, Some("B")).unwrap(), 1);
+        let model = utils::run_adb_command(&["shell", "getprop ro.product.model"]).unwrap();
+        assert_eq!(model, "Pixel 8");
+
+        *MODE.lock().unwrap() = None;
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn start_replay_rejects_empty_capture() {
+        let dir = std::env::temp_dir().join(format!("xperformance_empty_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(RAW_FILE);
+        std::fs::write(&path, "\n").unwrap();
+        assert!(start_replay(&path, None).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,90 @@
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+
+// 会话 CSV 中统一使用的时间戳格式
+pub const CSV_TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+// 一次采样循环的标识，本轮所有指标共用同一个 ID 和时间戳，使各 CSV 的行可以对齐
+#[derive(Debug, Clone, Copy)]
+pub struct Tick {
+    pub id: u64,
+    pub timestamp: DateTime<Local>,
+}
+
+impl Tick {
+    pub fn new(id: u64) -> Self {
+        Self {
+            id,
+            timestamp: crate::replay::now(),
+        }
+    }
+}
+
+// 按 Tick 汇总所有指标的宽表，每个 Tick 一行、每个指标一列
+#[derive(Debug, Clone, Default)]
+pub struct TickTable {
+    columns: Vec<String>,
+    rows: BTreeMap<u64, (DateTime<Local>, BTreeMap<usize, f64>)>,
+}
+
+impl TickTable {
+    pub fn record(&mut self, tick: &Tick, column: &str, value: f64) {
+        let index = match self.columns.iter().position(|c| c == column) {
+            Some(index) => index,
+            None => {
+                self.columns.push(column.to_string());
+                self.columns.len() - 1
+            }
+        };
+        self.rows
+            .entry(tick.id)
+            .or_insert_with(|| (tick.timestamp, BTreeMap::new()))
+            .1
+            .insert(index, value);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    // 某个 Tick 已记录的 (列名, 值)，按列顺序
+    pub fn row(&self, id: u64) -> Vec<(&str, f64)> {
+        self.rows
+            .get(&id)
+            .map(|(_, values)| {
+                values
+                    .iter()
+                    .map(|(i, v)| (self.columns[*i].as_str(), *v))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    // 列顺序按指标首次出现的顺序，缺失的值留空
+    pub fn write_csv(&self, path: &Path) -> Result<()> {
+        let mut file = std::fs::File::create(path)?;
+        writeln!(file, "Timestamp,Tick,{}", self.columns.join(","))?;
+        for (id, (timestamp, values)) in &self.rows {
+            let cells: Vec<String> = (0..self.columns.len())
+                .map(|i| {
+                    values
+                        .get(&i)
+                        .map(|v| format!("{:.2}", v))
+                        .unwrap_or_default()
+                })
+                .collect();
+            writeln!(
+                file,
+                "{},{},{}",
+                timestamp.format(CSV_TIMESTAMP_FORMAT),
+                id,
+                cells.join(",")
+            )?;
+        }
+        file.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,1014 @@
+use crate::cpu::{CpuScale, ThreadCpuInfo};
+use crate::tick::{Tick, TickTable};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use plotters::element::PathElement;
+use plotters::prelude::*;
+use plotters::style::Color;
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::Mutex;
+
+// 全局静态变量，用于跟踪中断状态
+static INTERRUPT_FLAG: AtomicBool = AtomicBool::new(false);
+static mut LOG_FILE_PATH: Option<PathBuf> = None;
+
+// 存储当前执行期间的timestamp目录路径
+static mut TIMESTAMP_DIR: Option<PathBuf> = None;
+static TIMESTAMP_DIR_MUTEX: Mutex<()> = Mutex::new(());
+// 目标设备序列号，设置后未显式指定设备的 adb 调用都使用 adb -s <serial>
+static ADB_SERIAL: Mutex<Option<String>> = Mutex::new(None);
+// 输出根目录，未设置时为 log
+static OUTPUT_ROOT: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+tokio::task_local! {
+    // 多设备并行监控时当前任务对应的设备，优先于全局设置
+    static TASK_DEVICE: String;
+}
+
+pub fn set_adb_serial(serial: Option<&str>) {
+    *ADB_SERIAL.lock().unwrap() = serial.map(str::to_string);
+}
+
+pub fn adb_serial() -> Option<String> {
+    current_task_device().or_else(|| ADB_SERIAL.lock().unwrap().clone())
+}
+
+pub fn current_task_device() -> Option<String> {
+    TASK_DEVICE.try_with(|serial| serial.clone()).ok()
+}
+
+// 在指定设备的作用域内运行，其中的 adb 调用、输出目录和图表标签都对应该设备
+pub async fn with_device<F: std::future::Future>(serial: Option<String>, future: F) -> F::Output {
+    match serial {
+        Some(serial) => TASK_DEVICE.scope(serial, future).await,
+        None => future.await,
+    }
+}
+
+// 序列号用作目录名，如 192.168.1.5:5555 -> 192.168.1.5_5555
+pub fn device_dir_name(serial: &str) -> String {
+    serial
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+pub struct ProcessInfo {
+    pub pid: String,
+    pub start_time: String,
+}
+
+pub fn check_adb_connection() -> bool {
+    if crate::replay::is_replaying() {
+        return true;
+    }
+    if let Ok(output) = Command::new("adb").arg("devices").output() {
+        if output.status.success() {
+            let devices = String::from_utf8_lossy(&output.stdout);
+            let target = adb_serial();
+            // offline / unauthorized 的设备不算已连接；指定了设备时只看该设备
+            return devices.lines().skip(1).any(|line| {
+                let mut fields = line.split_whitespace();
+                match (fields.next(), fields.next()) {
+                    (Some(serial), Some("device")) => {
+                        target.as_deref().is_none_or(|target| target == serial)
+                    }
+                    _ => false,
+                }
+            });
+        }
+    }
+    false
+}
+
+pub fn get_process_info(package: &str) -> Result<ProcessInfo> {
+    let pid = {
+        let output = run_adb_command(&["shell", "pidof", package])?;
+        let pid = output.trim();
+        if pid.is_empty() {
+            anyhow::bail!("Process not found for package: {}", package);
+        }
+        pid.to_string()
+    };
+
+    let start_time = {
+        let output = run_adb_command(&[
+            "shell",
+            "stat",
+            "-c",
+            "%y",
+            format!("/proc/{}/cmdline", pid).as_str(),
+        ])?;
+        output.trim().to_string()
+    };
+
+    Ok(ProcessInfo { pid, start_time })
+}
+
+pub fn run_adb_command(args: &[&str]) -> Result<String> {
+    run_adb_command_on(None, args)
+}
+
+// 在指定设备上执行adb命令，serial为None时使用adb默认设备
+pub fn run_adb_command_on(serial: Option<&str>, args: &[&str]) -> Result<String> {
+    // 回放模式不访问设备
+    if let Some(result) = crate::replay::replay(args) {
+        return result;
+    }
+    let serial = serial.map(str::to_string).or_else(adb_serial);
+    let result = execute_adb_command(serial.as_deref(), args);
+    crate::replay::record(args, &result);
+    result
+}
+
+fn execute_adb_command(serial: Option<&str>, args: &[&str]) -> Result<String> {
+    let mut command = Command::new("adb");
+    if let Some(serial) = serial {
+        command.args(["-s", serial]);
+    }
+    let output = command
+        .args(args)
+        .env("TERM", "dumb")
+        .output()
+        .context("Failed to execute adb command")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("ADB command failed: {}", stderr);
+    }
+
+    let raw_output = String::from_utf8_lossy(&output.stdout).to_string();
+    Ok(clean_control_chars(&raw_output))
+}
+
+// 列出所有处于 device 状态的设备序列号
+pub fn list_devices() -> Result<Vec<String>> {
+    let output = run_adb_command(&["devices"])?;
+    Ok(output
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            match (fields.next(), fields.next()) {
+                (Some(serial), Some("device")) => Some(serial.to_string()),
+                _ => None,
+            }
+        })
+        .collect())
+}
+
+// adb connect 连接 ADB over TCP 设备（ip:port），失败时 adb 仍返回 0，需检查输出
+pub fn adb_connect(address: &str) -> Result<()> {
+    let output = Command::new("adb")
+        .args(["connect", address])
+        .output()
+        .context("Failed to execute adb command")?;
+    let text = String::from_utf8_lossy(&output.stdout).to_string()
+        + &String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() || !text.contains("connected to") {
+        anyhow::bail!("adb connect {} failed: {}", address, text.trim());
+    }
+    // 连接后设备可能仍为 unauthorized 或 offline
+    if !list_devices()?.iter().any(|serial| serial == address) {
+        anyhow::bail!(
+            "{} is connected but not ready, check the authorization prompt on the device",
+            address
+        );
+    }
+    Ok(())
+}
+
+// TCP 链路断开后设备常停留在 offline，先断开再重新连接
+pub fn adb_reconnect(address: &str) -> Result<()> {
+    let _ = Command::new("adb").args(["disconnect", address]).output();
+    adb_connect(address)
+}
+
+// 选择目标设备：指定了序列号时确认其已连接，未指定且连接了多台设备时报错并列出
+pub fn select_device(serial: Option<&str>) -> Result<String> {
+    let devices = list_devices()?;
+    match (serial, devices.as_slice()) {
+        (Some(serial), _) if devices.iter().any(|d| d == serial) => Ok(serial.to_string()),
+        (Some(serial), []) => anyhow::bail!("Device {} not found, no devices connected", serial),
+        (Some(serial), _) => anyhow::bail!(
+            "Device {} not found. Connected devices: {}",
+            serial,
+            devices.join(", ")
+        ),
+        (None, []) => anyhow::bail!("No Android devices connected"),
+        (None, [only]) => Ok(only.clone()),
+        (None, _) => anyhow::bail!(
+            "Multiple devices connected, use --serial to pick one: {}",
+            devices.join(", ")
+        ),
+    }
+}
+
+fn clean_control_chars(input: &str) -> String {
+    let mut result = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == '\x1B' && chars.peek() == Some(&'[') {
+            chars.next();
+            while let Some(&next) = chars.peek() {
+                if next.is_ascii_alphabetic() {
+                    chars.next();
+                    break;
+                }
+                chars.next();
+            }
+            continue;
+        }
+        result.push(c);
+    }
+    result
+}
+
+pub fn set_output_root(dir: Option<&Path>) {
+    *OUTPUT_ROOT.lock().unwrap() = dir.map(Path::to_path_buf);
+}
+
+pub fn output_root() -> PathBuf {
+    OUTPUT_ROOT
+        .lock()
+        .unwrap()
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("log"))
+}
+
+pub fn create_log_dir_if_needed(package: &str) -> Result<PathBuf> {
+    let log_dir = output_root().join(package);
+    if !log_dir.exists() {
+        fs::create_dir_all(&log_dir)?;
+        console!("Created log directory: {}", log_dir.display());
+
+        // Try to log the directory creation if logging is already initialized
+        // This may fail if this is the first call to ensure_log_dir
+        let _ = append_to_log(&format!("Created log directory: {}", log_dir.display()));
+    }
+    Ok(log_dir)
+}
+
+pub fn append_to_log(content: &str) -> Result<()> {
+    let path = unsafe {
+        if let Some(ref path) = LOG_FILE_PATH {
+            path
+        } else {
+            anyhow::bail!("Log file not initialized")
+        }
+    };
+
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+
+    let timestamp = Local::now().format("%Y-%m-%d %H:%M:%S");
+    writeln!(file, "\n[{}]", timestamp)?;
+    writeln!(file, "{}", content)?;
+    file.flush()?;
+
+    Ok(())
+}
+
+// CPU 图表上的叠加内容：事件标记（如温控状态变化、抓取窗口）、各簇的归一化频率、限频区间和前台 Activity 底纹；
+// per_core 非空时在下方增加每核心占用面板
+#[derive(Debug, Default)]
+pub struct CpuChartOverlay {
+    pub markers: Vec<(DateTime<Local>, String)>,
+    pub frequency: Vec<crate::chart::TimeSeriesData>,
+    pub throttled: Vec<(DateTime<Local>, DateTime<Local>)>,
+    pub bands: Vec<crate::chart::Band>,
+    pub per_core: Vec<crate::chart::TimeSeriesData>,
+}
+
+// 图表直接写入当前设备会话目录下的 cpu 目录，多设备并行时不会互相覆盖
+pub fn generate_cpu_chart(
+    package: &str,
+    timestamps: &VecDeque<DateTime<Local>>,
+    process_cpu: &VecDeque<f32>,
+    pid: &str,
+    scale: CpuScale,
+    cores: u32,
+    overlay: &CpuChartOverlay,
+) -> Result<PathBuf> {
+    if timestamps.is_empty() || process_cpu.is_empty() {
+        return Err(anyhow::format_err!("No CPU data to chart"));
+    }
+
+    let cpu_dir = create_timestamp_subdir(package)?.join("cpu");
+    fs::create_dir_all(&cpu_dir)?;
+    let output_file = cpu_dir.join(crate::chart::stamped_file_name(&format!(
+        "{}_cpu_chart.png",
+        package
+    )));
+    let points: Vec<(DateTime<Local>, f32)> = timestamps
+        .iter()
+        .copied()
+        .zip(process_cpu.iter().copied())
+        .collect();
+    draw_cpu_chart(&output_file, package, &points, pid, scale, cores, overlay)?;
+
+    // 导出数据到CSV (保留这个功能)
+    let csv_path = output_file.with_extension("csv");
+    export_cpu_data_to_csv(&csv_path, timestamps, process_cpu)?;
+
+    Ok(output_file)
+}
+
+// 绘制进程 CPU 图表到指定文件；pid 为空时（如根据 CSV 重新生成）图例不显示 PID
+pub fn draw_cpu_chart(
+    output_file: &Path,
+    package: &str,
+    points: &[(DateTime<Local>, f32)],
+    pid: &str,
+    scale: CpuScale,
+    cores: u32,
+    overlay: &CpuChartOverlay,
+) -> Result<()> {
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return Err(anyhow::format_err!("No CPU data to chart"));
+    };
+
+    // Create X-axis range (timestamps)
+    let x_range = first.0..last.0;
+
+    // Create root drawing area
+    let theme = crate::chart::theme();
+    let root = BitMapBackend::new(output_file, crate::chart::chart_size()).into_drawing_area();
+    root.fill(&theme.background)?;
+
+    // 进程 CPU，有每核心数据时下方再加一个面板
+    let chart_count = if overlay.per_core.is_empty() { 1 } else { 2 };
+
+    // Split the drawing area into subplots
+    let areas = root.split_evenly((chart_count, 1));
+    let area_index = 0;
+
+    // Y轴按实际数据自动缩放，但不超过该归一化方式下的理论最大值
+    let observed_max = points.iter().map(|(_, v)| *v).fold(0.0f32, f32::max);
+    let y_max = (observed_max * 1.1).max(10.0).min(scale.max_value(cores));
+    let y_desc = format!("Process CPU ({})", scale.unit());
+
+    // Process CPU (always shown)
+    let mut process_chart = ChartBuilder::on(&areas[area_index])
+        .caption(
+            crate::chart::stamped_title(&format!("CPU Usage - {}", package)),
+            crate::chart::text_style(20),
+        )
+        .margin(15)
+        .x_label_area_size(40) // Always show X-axis labels
+        .y_label_area_size(60)
+        // 频率叠加时右侧为副坐标轴（% of max）
+        .right_y_label_area_size(if overlay.frequency.is_empty() { 0 } else { 60 })
+        .build_cartesian_2d(x_range.clone(), 0f32..y_max)?
+        .set_secondary_coord(x_range.clone(), 0f64..110f64);
+
+    // 创建持久的mesh配置
+    let mut mesh_config = process_chart.configure_mesh();
+    crate::chart::themed_mesh(&mut mesh_config)
+        .y_desc(y_desc.as_str())
+        .y_label_formatter(&|v| format!("{:.1}", v))
+        .x_desc("Time")
+        .x_labels(10)
+        .x_label_formatter(&|x| x.format("%H:%M:%S").to_string());
+
+    mesh_config.draw()?;
+
+    if !overlay.frequency.is_empty() {
+        process_chart
+            .configure_secondary_axes()
+            .axis_style(theme.foreground)
+            .label_style(crate::chart::text_style(12))
+            .axis_desc_style(crate::chart::text_style(12))
+            .y_desc("CPU frequency (% of max)")
+            .y_label_formatter(&|v| format!("{:.0}", v))
+            .draw()?;
+    }
+
+    // 预热区间底纹
+    if let Some(shade) = crate::chart::warmup_shade(x_range.start, x_range.end, 0f32, y_max) {
+        process_chart.draw_series(std::iter::once(shade))?;
+    }
+
+    // Activity 底纹
+    for (label, color, rects) in
+        crate::chart::band_groups(&overlay.bands, x_range.start, x_range.end, 0f32, y_max)
+    {
+        process_chart
+            .draw_series(rects)?
+            .label(label)
+            .legend(move |(x, y)| Rectangle::new([(x, y - 5), (x + 20, y + 5)], color.filled()));
+    }
+
+    // 限频区间底纹
+    let throttled: Vec<_> = overlay
+        .throttled
+        .iter()
+        .filter(|(start, end)| *end > x_range.start && *start < x_range.end)
+        .map(|(start, end)| {
+            Rectangle::new(
+                [
+                    ((*start).max(x_range.start), 0f32),
+                    ((*end).min(x_range.end), y_max),
+                ],
+                theme.marker.mix(0.12).filled(),
+            )
+        })
+        .collect();
+    if !throttled.is_empty() {
+        let marker = theme.marker;
+        process_chart
+            .draw_series(throttled)?
+            .label("Throttled")
+            .legend(move |(x, y)| {
+                Rectangle::new([(x, y - 5), (x + 20, y + 5)], marker.mix(0.3).filled())
+            });
+    }
+
+    // 绘制进程CPU线
+    let color = theme.series_color(1);
+    process_chart
+        .draw_series(LineSeries::new(
+            crate::chart::downsample(points, crate::chart::max_points()),
+            color.stroke_width(2),
+        ))?
+        .label(if pid.is_empty() {
+            "Process CPU".to_string()
+        } else {
+            format!("Process CPU (PID: {})", pid)
+        })
+        .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(2)));
+
+    // 各簇的归一化频率用细线绘制，区分 CPU 升高与降频
+    for (i, series) in overlay.frequency.iter().enumerate() {
+        let color = theme.series_color(i + 2).mix(0.7);
+        process_chart
+            .draw_secondary_series(LineSeries::new(
+                crate::chart::downsample(&series.points, crate::chart::max_points()),
+                color.stroke_width(1),
+            ))?
+            .label(series.label.clone())
+            .legend(move |(x, y)| {
+                PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(2))
+            });
+    }
+
+    // 事件标记（如温控限频状态变化）：竖线加说明文字
+    for (time, label) in overlay
+        .markers
+        .iter()
+        .filter(|(time, _)| x_range.contains(time) || *time == x_range.end)
+    {
+        process_chart.draw_series(std::iter::once(PathElement::new(
+            vec![(*time, 0f32), (*time, y_max)],
+            theme.marker.mix(0.6).stroke_width(1),
+        )))?;
+        process_chart.draw_series(std::iter::once(Text::new(
+            label.clone(),
+            (*time, y_max * 0.95),
+            ("sans-serif", 14).into_font().color(&theme.marker),
+        )))?;
+    }
+
+    // 用户插入的事件标记
+    process_chart.draw_series(crate::chart::user_marker_lines(
+        x_range.start,
+        x_range.end,
+        0f32,
+        y_max,
+    ))?;
+    process_chart.draw_series(crate::chart::user_marker_labels(
+        x_range.start,
+        x_range.end,
+        y_max * 0.9,
+    ))?;
+
+    // 添加图例
+    crate::chart::themed_legend(&mut process_chart.configure_series_labels()).draw()?;
+
+    // 每核心占用：看出进程是被压在小核上还是分布在大核簇上
+    if let Some(area) = areas.get(1) {
+        let mut core_chart = ChartBuilder::on(area)
+            .margin(15)
+            .x_label_area_size(40)
+            .y_label_area_size(60)
+            // 与上方面板的绘图区对齐
+            .right_y_label_area_size(if overlay.frequency.is_empty() { 0 } else { 60 })
+            .build_cartesian_2d(x_range.clone(), 0f64..105f64)?;
+
+        let mut mesh_config = core_chart.configure_mesh();
+        crate::chart::themed_mesh(&mut mesh_config)
+            .y_desc("Core busy (%)")
+            .y_label_formatter(&|v| format!("{:.0}", v))
+            .x_desc("Time")
+            .x_labels(10)
+            .x_label_formatter(&|x| x.format("%H:%M:%S").to_string());
+        mesh_config.draw()?;
+
+        if let Some(shade) = crate::chart::warmup_shade(x_range.start, x_range.end, 0f64, 105f64) {
+            core_chart.draw_series(std::iter::once(shade))?;
+        }
+
+        for (i, series) in overlay.per_core.iter().enumerate() {
+            let points: Vec<(DateTime<Local>, f64)> = series
+                .points
+                .iter()
+                .filter(|(time, _)| x_range.contains(time) || *time == x_range.end)
+                .copied()
+                .collect();
+            let color = theme.series_color(i);
+            core_chart
+                .draw_series(LineSeries::new(
+                    crate::chart::downsample(&points, crate::chart::max_points()),
+                    color.stroke_width(1),
+                ))?
+                .label(series.label.clone())
+                .legend(move |(x, y)| {
+                    PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(2))
+                });
+        }
+
+        core_chart.draw_series(crate::chart::user_marker_lines(
+            x_range.start,
+            x_range.end,
+            0f64,
+            105f64,
+        ))?;
+
+        crate::chart::themed_legend(&mut core_chart.configure_series_labels()).draw()?;
+    }
+
+    Ok(())
+}
+
+// 添加一个新函数用于导出CSV数据
+pub fn export_cpu_data_to_csv(
+    path: &PathBuf,
+    timestamps: &VecDeque<DateTime<Local>>,
+    process_cpu: &VecDeque<f32>,
+) -> Result<()> {
+    let mut file = fs::File::create(path)?;
+
+    // 写入CSV头
+    writeln!(file, "Timestamp,Process CPU (%)")?;
+
+    // 写入数据行
+    for i in 0..timestamps.len() {
+        writeln!(
+            file,
+            "{},{:.2}",
+            timestamps[i].format("%Y-%m-%d %H:%M:%S"),
+            process_cpu[i]
+        )?;
+    }
+
+    file.flush()?;
+    Ok(())
+}
+
+// Function to create timestamp subdirectory within the log directory
+// 多设备并行监控时每台设备使用时间戳目录下以序列号命名的子目录
+pub fn create_timestamp_subdir(package: &str) -> Result<PathBuf> {
+    let timestamp_dir = create_session_dir(package)?;
+    match current_task_device() {
+        Some(serial) => {
+            let device_dir = timestamp_dir.join(device_dir_name(&serial));
+            std::fs::create_dir_all(&device_dir)?;
+            Ok(device_dir)
+        }
+        None => Ok(timestamp_dir),
+    }
+}
+
+fn create_session_dir(package: &str) -> Result<PathBuf> {
+    // 使用互斥锁保护静态变量的访问
+    let _lock = TIMESTAMP_DIR_MUTEX.lock().unwrap();
+
+    // 检查缓存中是否已存在timestamp目录
+    unsafe {
+        if let Some(ref dir) = TIMESTAMP_DIR {
+            return Ok(dir.clone());
+        }
+    }
+
+    // 如果没有，创建新的timestamp目录
+    let log_dir = create_log_dir_if_needed(package)?;
+    let timestamp_str = Local::now().format("%Y%m%d_%H%M%S").to_string();
+    let timestamp_dir = log_dir.join(&timestamp_str);
+
+    if !timestamp_dir.exists() {
+        std::fs::create_dir_all(&timestamp_dir)?;
+        let msg = format!("Created timestamp directory: {}", timestamp_dir.display());
+        console!("{}", msg);
+
+        // Log directory creation
+        let _ = append_to_log(&msg);
+    }
+
+    // 缓存目录路径
+    unsafe {
+        TIMESTAMP_DIR = Some(timestamp_dir.clone());
+    }
+
+    Ok(timestamp_dir)
+}
+
+// 清除缓存的timestamp目录，下次调用 create_timestamp_subdir 时创建新目录
+pub fn reset_timestamp_subdir() {
+    let _lock = TIMESTAMP_DIR_MUTEX.lock().unwrap();
+    unsafe {
+        TIMESTAMP_DIR = None;
+    }
+}
+
+// 导出合并的线程数据：宽表（每个 Tick 一行，CPU 均值最高的 top_n 个线程各一列）
+// 和长表（每个 Tick 每个线程一行），代替大量零散的逐线程 CSV
+pub fn export_thread_tables(
+    path: &Path,
+    package: &str,
+    samples: &[(Tick, Vec<ThreadCpuInfo>)],
+    top_n: usize,
+) -> Result<(PathBuf, PathBuf)> {
+    fs::create_dir_all(path)?;
+
+    // 按 TID 累计 CPU，选出均值最高的线程
+    let mut totals: HashMap<&str, (f32, &str)> = HashMap::new();
+    for (_, threads) in samples {
+        for thread in threads {
+            let entry = totals.entry(&thread.tid).or_insert((0.0, &thread.name));
+            entry.0 += thread.cpu_usage;
+            entry.1 = &thread.name;
+        }
+    }
+    let mut ranked: Vec<(&str, f32, &str)> = totals
+        .into_iter()
+        .map(|(tid, (total, name))| (tid, total, name))
+        .collect();
+    ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+    let columns: HashMap<&str, String> = ranked
+        .into_iter()
+        .take(top_n)
+        .map(|(tid, _, name)| (tid, format!("{} ({})", name.replace(',', " "), tid)))
+        .collect();
+
+    let mut wide = TickTable::default();
+    for (tick, threads) in samples {
+        for thread in threads {
+            if let Some(column) = columns.get(thread.tid.as_str()) {
+                wide.record(tick, column, thread.cpu_usage as f64);
+            }
+        }
+    }
+    let wide_path = path.join(format!("{}_threads_wide.csv", package));
+    wide.write_csv(&wide_path)?;
+
+    let long_path = path.join(format!("{}_threads_long.csv", package));
+    let mut writer = std::io::BufWriter::new(fs::File::create(&long_path)?);
+    writeln!(writer, "Timestamp,Tick,TID,Thread,CPU (%)")?;
+    for (tick, threads) in samples {
+        for thread in threads {
+            writeln!(
+                writer,
+                "{},{},{},\"{}\",{:.2}",
+                tick.timestamp.format("%Y-%m-%d %H:%M:%S"),
+                tick.id,
+                thread.tid,
+                thread.name.replace('"', "\"\""),
+                thread.cpu_usage
+            )?;
+        }
+    }
+    writer.flush()?;
+
+    Ok((wide_path, long_path))
+}
+
+// Function to export thread data to individual CSV files by thread ID
+pub fn export_thread_data_to_csv(
+    path: PathBuf,
+    pid: &str,
+    threads: &[ThreadCpuInfo],
+    append: bool,
+) -> Result<Vec<String>> {
+    let mut created_files = Vec::new();
+
+    // Filter out threads with zero CPU usage
+    let active_threads: Vec<&ThreadCpuInfo> = threads
+        .iter()
+        .filter(|thread| thread.cpu_usage > 0.0)
+        .collect();
+
+    if active_threads.is_empty() {
+        console!("No threads with CPU usage > 0 found, skipping thread data export");
+        return Ok(created_files);
+    }
+
+    // Group threads by TID
+    let mut thread_map: std::collections::HashMap<String, Vec<&ThreadCpuInfo>> =
+        std::collections::HashMap::new();
+    for thread in active_threads {
+        if let Some(_timestamp) = thread.timestamp {
+            thread_map
+                .entry(thread.tid.clone())
+                .or_default()
+                .push(thread);
+        }
+    }
+
+    // Create/update a CSV file for each thread
+    for (tid, thread_data) in thread_map {
+        if thread_data.is_empty() {
+            continue;
+        }
+
+        // Use the thread name from the latest data point
+        let thread_name = thread_data.last().unwrap().name.clone();
+        let sanitized_name = thread_name.replace(" ", "_").replace("/", "-");
+        let filename = format!("thread_{}_{}_{}.csv", sanitized_name, tid, pid);
+        let filepath = path.join(&filename);
+
+        let file_exists = filepath.exists();
+        let file = if append && file_exists {
+            std::fs::OpenOptions::new().append(true).open(&filepath)?
+        } else {
+            std::fs::File::create(&filepath)?
+        };
+
+        let mut writer = std::io::BufWriter::new(file);
+
+        // Write header if new file
+        if !append || !file_exists {
+            writeln!(writer, "Timestamp,CPUUsage")?;
+        }
+
+        // Write data, ordered by timestamp
+        let mut sorted_data = thread_data.clone();
+        sorted_data.sort_by_key(|a| a.timestamp.unwrap());
+
+        for thread in sorted_data {
+            if let Some(timestamp) = thread.timestamp {
+                writeln!(
+                    writer,
+                    "{},{}",
+                    timestamp.format("%Y-%m-%d %H:%M:%S"),
+                    thread.cpu_usage
+                )?;
+            }
+        }
+
+        writer.flush()?;
+        created_files.push(filename.clone());
+
+        // Log CSV file creation or update
+        let action = if append && file_exists {
+            "Updated"
+        } else {
+            "Created"
+        };
+        let message = format!("{} thread data CSV: {}", action, filepath.display());
+        console!("{}", message);
+        let _ = append_to_log(&message);
+    }
+
+    Ok(created_files)
+}
+
+// Function to generate a time-series chart for thread data
+pub fn generate_thread_time_series_chart(
+    path: PathBuf,
+    package: &str,
+    pid: &str,
+    thread_data: &std::collections::HashMap<String, Vec<ThreadCpuInfo>>,
+) -> Result<Vec<String>> {
+    // If there's no thread data, return early
+    if thread_data.is_empty() {
+        let message = "No thread data available for chart generation";
+        console!("{}", message);
+        return Ok(Vec::new());
+    }
+
+    // Filter for active threads
+    let mut active_threads: Vec<(&String, Vec<ThreadCpuInfo>)> = thread_data
+        .iter()
+        .filter_map(|(tid, threads)| {
+            // Check if this thread has any readings with CPU > 0
+            let active_points: Vec<ThreadCpuInfo> = threads
+                .iter()
+                .filter(|thread| thread.cpu_usage > 0.0 && thread.timestamp.is_some())
+                .cloned()
+                .collect();
+
+            if !active_points.is_empty() {
+                Some((tid, active_points))
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    if active_threads.is_empty() {
+        let message = "No active threads (CPU > 0) found for chart generation";
+        console!("{}", message);
+        return Ok(Vec::new());
+    }
+
+    // 按总 CPU 降序排列，每页最多 THREADS_PER_PAGE 个线程，保证高占用线程不会被遗漏
+    let total_cpu = |points: &[ThreadCpuInfo]| points.iter().map(|p| p.cpu_usage).sum::<f32>();
+    active_threads.sort_by(|a, b| {
+        total_cpu(&b.1)
+            .partial_cmp(&total_cpu(&a.1))
+            .unwrap_or(Ordering::Equal)
+    });
+    let pages: Vec<_> = active_threads.chunks(THREADS_PER_PAGE).collect();
+
+    // Create a timestamp for the chart filename
+    let timestamp_str = Local::now().format("%Y%m%d_%H%M%S").to_string();
+    let mut chart_filenames = Vec::new();
+
+    for (page_idx, page) in pages.iter().enumerate() {
+        let (file_name, title) = if pages.len() == 1 {
+            (
+                format!("thread_time_series_{}_pid{}.png", timestamp_str, pid),
+                format!("Thread CPU Time Series - {} (PID: {})", package, pid),
+            )
+        } else {
+            let first = page_idx * THREADS_PER_PAGE + 1;
+            (
+                format!(
+                    "thread_time_series_{}_pid{}_page{}.png",
+                    timestamp_str,
+                    pid,
+                    page_idx + 1
+                ),
+                format!(
+                    "Thread CPU Time Series - {} (PID: {}) - page {}/{}, threads {}-{} by total CPU",
+                    package,
+                    pid,
+                    page_idx + 1,
+                    pages.len(),
+                    first,
+                    first + page.len() - 1
+                ),
+            )
+        };
+        let chart_filename = crate::chart::stamped_file_name(&file_name);
+        let filepath = path.join(&chart_filename);
+        draw_thread_chart_page(&filepath, &crate::chart::stamped_title(&title), page)?;
+
+        let message = format!("Thread time series chart saved to: {}", filepath.display());
+        console!("{}", message);
+        // Log chart creation
+        let _ = append_to_log(&message);
+        chart_filenames.push(chart_filename);
+    }
+
+    Ok(chart_filenames)
+}
+
+// 每张线程图表最多显示的线程数，与调色板颜色数一致
+const THREADS_PER_PAGE: usize = 12;
+
+fn draw_thread_chart_page(
+    filepath: &Path,
+    title: &str,
+    threads: &[(&String, Vec<ThreadCpuInfo>)],
+) -> Result<()> {
+    let theme = crate::chart::theme();
+    let root = BitMapBackend::new(filepath, crate::chart::chart_size()).into_drawing_area();
+    root.fill(&theme.background)?;
+
+    // Split the drawing area for title and chart
+    let (title_area, chart_area) = root.split_vertically(50);
+
+    // Draw the title
+    title_area.titled(title, crate::chart::text_style(20))?;
+
+    // Find the min and max timestamps from all thread data
+    let mut min_time = chrono::Local::now();
+    let mut max_time = chrono::Local::now() - chrono::Duration::hours(1);
+    let mut max_cpu = 0.1f32;
+
+    for (_, thread_points) in threads {
+        for point in thread_points {
+            if let Some(timestamp) = point.timestamp {
+                if timestamp < min_time {
+                    min_time = timestamp;
+                }
+                if timestamp > max_time {
+                    max_time = timestamp;
+                }
+                if point.cpu_usage > max_cpu {
+                    max_cpu = point.cpu_usage;
+                }
+            }
+        }
+    }
+
+    // Ensure we have a reasonable range
+    if max_time <= min_time {
+        max_time = min_time + chrono::Duration::minutes(5);
+    }
+
+    // Add some padding to the max CPU usage
+    max_cpu *= 1.1;
+    if max_cpu < 1.0 {
+        max_cpu = 1.0;
+    }
+
+    // Create the chart context
+    let mut chart = ChartBuilder::on(&chart_area)
+        .margin(10)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d(min_time..max_time, 0f32..max_cpu)?;
+
+    // Configure the mesh
+    crate::chart::themed_mesh(&mut chart.configure_mesh())
+        .x_labels(8)
+        .x_label_formatter(&|x| x.format("%H:%M:%S").to_string())
+        .y_desc("CPU Usage (%)")
+        .x_desc("Time")
+        .draw()?;
+
+    chart.draw_series(crate::chart::user_marker_lines(
+        min_time, max_time, 0f32, max_cpu,
+    ))?;
+    chart.draw_series(crate::chart::user_marker_labels(
+        min_time,
+        max_time,
+        max_cpu * 0.95,
+    ))?;
+
+    // Draw a line series for each thread
+    for (idx, (tid, thread_points)) in threads.iter().enumerate() {
+        // Use thread name and tid for legend
+        let legend_name = format!("{} ({})", thread_points[0].name, tid);
+        let color = theme.series_color(idx);
+
+        // Convert data to the format expected by the chart
+        let line_data: Vec<(DateTime<Local>, f32)> = thread_points
+            .iter()
+            .filter_map(|point| point.timestamp.map(|ts| (ts, point.cpu_usage)))
+            .collect();
+
+        // Plot the data for this thread with label
+        chart
+            .draw_series(LineSeries::new(
+                crate::chart::downsample(&line_data, crate::chart::max_points()),
+                color,
+            ))?
+            .label(legend_name)
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+    }
+
+    // Add a legend with better positioning and size
+    crate::chart::themed_legend(&mut chart.configure_series_labels())
+        .position(SeriesLabelPosition::UpperRight)
+        .margin(10)
+        .draw()?;
+
+    // Present the chart
+    root.present()?;
+    Ok(())
+}
+
+// 设置中断标志
+pub fn set_interrupt_flag() {
+    INTERRUPT_FLAG.store(true, AtomicOrdering::SeqCst);
+}
+
+// 检查程序是否正在被中断
+pub fn is_being_interrupted() -> bool {
+    INTERRUPT_FLAG.load(AtomicOrdering::SeqCst)
+}
+
+// 解析 "90"、"30s"、"15m"、"8h" 形式的时长，无后缀时按秒计算
+pub fn parse_duration(text: &str) -> Result<std::time::Duration> {
+    let text = text.trim();
+    let (number, unit) = match text.find(|c: char| !c.is_ascii_digit() && c != '.') {
+        Some(pos) => text.split_at(pos),
+        None => (text, "s"),
+    };
+    let value: f64 = number
+        .parse()
+        .with_context(|| format!("Invalid duration: {}", text))?;
+    let seconds = match unit {
+        "s" => value,
+        "m" => value * 60.0,
+        "h" => value * 3600.0,
+        _ => anyhow::bail!("Invalid duration unit in {} (use s, m or h)", text),
+    };
+    Ok(std::time::Duration::from_secs_f64(seconds))
+}
@@ -0,0 +1,278 @@
+use crate::chart::{self, LineSeriesData};
+use crate::regression::{Run, Statistic};
+use crate::report::HtmlReport;
+use crate::stats::{self, Summary};
+use crate::utils;
+use anyhow::Result;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// p 值低于该值的差异视为显著
+pub const ALPHA: f64 = 0.05;
+
+// 覆盖安装 APK，-d 允许 B 的版本号低于 A
+pub fn install_apk(apk: &Path) -> Result<()> {
+    let path = apk.to_string_lossy();
+    let output = utils::run_adb_command(&["install", "-r", "-d", &path])?;
+    if !output.contains("Success") {
+        anyhow::bail!("Install of {} failed: {}", apk.display(), output.trim());
+    }
+    Ok(())
+}
+
+// 一个 APK 及其各次运行
+#[derive(Debug, Clone)]
+pub struct Variant {
+    // A 或 B
+    pub name: String,
+    pub apk: PathBuf,
+    // 安装后读取的 versionName (versionCode)
+    pub version: String,
+    pub runs: Vec<Run>,
+}
+
+impl Variant {
+    pub fn label(&self) -> String {
+        if self.version.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{} {}", self.name, self.version)
+        }
+    }
+}
+
+// A/B 对比表的一行；A、B 为各次运行统计量的均值，数值越大越差
+#[derive(Debug, Clone)]
+pub struct AbDelta {
+    pub metric: &'static str,
+    pub statistic: Statistic,
+    pub a: f64,
+    pub b: f64,
+    pub percent: Option<f64>,
+    pub p_value: Option<f64>,
+    pub regression: bool,
+}
+
+impl AbDelta {
+    pub fn is_significant(&self) -> bool {
+        self.p_value.is_some_and(|p| p < ALPHA)
+    }
+}
+
+// 每组至少两次运行时，对各次运行的统计量做 Welch t 检验；只有一次运行时，
+// 均值改为对逐个样本检验（样本间有自相关，结果偏乐观），峰值和 p95 不检验。
+// 增幅超过阈值且差异显著（或无法检验）即为回归
+pub fn compare_variants(a: &[Run], b: &[Run], threshold: f64) -> Vec<AbDelta> {
+    // 只对比所有运行都覆盖的时长
+    let overlap = a
+        .iter()
+        .chain(b)
+        .map(Run::duration)
+        .fold(f64::INFINITY, f64::min);
+    let within = |points: &[(f64, f64)]| -> Vec<f64> {
+        points
+            .iter()
+            .filter(|(elapsed, _)| *elapsed <= overlap)
+            .map(|(_, value)| *value)
+            .collect()
+    };
+    type Metric = fn(&Run) -> &Vec<(f64, f64)>;
+    let metrics: [(&'static str, Metric); 2] =
+        [("CPU (%)", |run| &run.cpu), ("PSS (KB)", |run| &run.pss_kb)];
+
+    let mut deltas = Vec::new();
+    for (metric, points) in metrics {
+        let samples = |runs: &[Run]| -> Vec<Vec<f64>> {
+            runs.iter()
+                .map(|run| within(points(run)))
+                .filter(|values| !values.is_empty())
+                .collect()
+        };
+        let (a_samples, b_samples) = (samples(a), samples(b));
+        if a_samples.is_empty() || b_samples.is_empty() {
+            continue;
+        }
+        let repeated = a_samples.len() > 1 && b_samples.len() > 1;
+        for statistic in Statistic::ALL {
+            let per_run = |samples: &[Vec<f64>]| -> Vec<f64> {
+                samples
+                    .iter()
+                    .filter_map(|values| Summary::from_values(values))
+                    .map(|summary| statistic.value(&summary))
+                    .collect()
+            };
+            let (a_values, b_values) = (per_run(&a_samples), per_run(&b_samples));
+            let mean = |values: &[f64]| values.iter().sum::<f64>() / values.len().max(1) as f64;
+            let (a_mean, b_mean) = (mean(&a_values), mean(&b_values));
+            let p_value = if repeated {
+                stats::welch_t_test(&a_values, &b_values)
+            } else if statistic == Statistic::Mean {
+                stats::welch_t_test(&a_samples.concat(), &b_samples.concat())
+            } else {
+                None
+            };
+            let percent =
+                (a_mean.abs() > f64::EPSILON).then(|| (b_mean - a_mean) * 100.0 / a_mean.abs());
+            deltas.push(AbDelta {
+                metric,
+                statistic,
+                a: a_mean,
+                b: b_mean,
+                percent,
+                p_value,
+                regression: percent.is_some_and(|p| p > threshold)
+                    && p_value.is_none_or(|p| p < ALPHA),
+            });
+        }
+    }
+    deltas
+}
+
+// 导出 A/B 对比 CSV、各次运行的 CPU/PSS 叠加图和 HTML 报告
+pub fn export_ab_test(
+    dir: &Path,
+    a: &Variant,
+    b: &Variant,
+    deltas: &[AbDelta],
+    threshold: f64,
+) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let mut paths = Vec::new();
+
+    let csv_path = dir.join("ab_comparison.csv");
+    let mut file = std::fs::File::create(&csv_path)?;
+    writeln!(
+        file,
+        "Metric,Statistic,A,B,Delta (%),P Value,Significant,Regression"
+    )?;
+    for delta in deltas {
+        writeln!(
+            file,
+            "{},{},{:.2},{:.2},{},{},{},{}",
+            delta.metric,
+            delta.statistic.as_str(),
+            delta.a,
+            delta.b,
+            delta
+                .percent
+                .map(|p| format!("{:.1}", p))
+                .unwrap_or_default(),
+            delta
+                .p_value
+                .map(|p| format!("{:.4}", p))
+                .unwrap_or_default(),
+            delta.is_significant(),
+            delta.regression
+        )?;
+    }
+    paths.push(csv_path);
+
+    let mut charts = Vec::new();
+    for (file_name, title, y_desc) in [
+        ("cpu_overlay.png", "CPU Usage", "CPU (%)"),
+        ("pss_overlay.png", "Total PSS", "PSS (MB)"),
+    ] {
+        let to_mb = file_name.starts_with("pss");
+        // 每次运行一条线，如 A #1、B #2
+        let series: Vec<LineSeriesData> = [a, b]
+            .into_iter()
+            .flat_map(|variant| {
+                variant.runs.iter().enumerate().map(move |(i, run)| {
+                    let points = if to_mb { &run.pss_kb } else { &run.cpu };
+                    (format!("{} #{}", variant.name, i + 1), points)
+                })
+            })
+            .filter(|(_, points)| !points.is_empty())
+            .map(|(label, points)| {
+                LineSeriesData::new(
+                    label,
+                    points
+                        .iter()
+                        .map(|&(x, y)| (x, if to_mb { y / 1024.0 } else { y }))
+                        .collect(),
+                )
+            })
+            .collect();
+        if series.len() < 2 {
+            continue;
+        }
+        let path = dir.join(file_name);
+        chart::draw_line_chart(
+            &path,
+            &format!("{} - {} vs {}", title, a.label(), b.label()),
+            "Elapsed (s)",
+            y_desc,
+            &series,
+        )?;
+        charts.push((file_name, title));
+        paths.push(path);
+    }
+
+    let mut report = HtmlReport::new("A/B Test");
+    report.add_paragraph(&format!(
+        "A: {}, {} run(s). B: {}, {} run(s).",
+        a.apk.display(),
+        a.runs.len(),
+        b.apk.display(),
+        b.runs.len()
+    ));
+    report.add_paragraph(&format!(
+        "Runs are aligned by elapsed time and compared over the duration all runs cover. A and B are the means of each run's statistic. P values come from Welch's t-test over the per-run values (over individual samples for the mean when there is only one run per APK). Increases above {:.0}% that are significant at p < {} are flagged as regressions.",
+        threshold, ALPHA
+    ));
+    // 每次运行的会话目录，可用 report 子命令查看单次运行的完整图表
+    let sessions: Vec<Vec<String>> = [a, b]
+        .into_iter()
+        .flat_map(|variant| {
+            variant.runs.iter().enumerate().map(move |(i, run)| {
+                vec![format!("{} #{}", variant.name, i + 1), run.label.clone()]
+            })
+        })
+        .collect();
+    report.add_table(&["Run", "Session"], &sessions);
+    let rows: Vec<Vec<String>> = deltas
+        .iter()
+        .map(|delta| {
+            vec![
+                delta.metric.to_string(),
+                delta.statistic.as_str().to_string(),
+                format!("{:.2}", delta.a),
+                format!("{:.2}", delta.b),
+                delta
+                    .percent
+                    .map(|p| format!("{:+.1}%", p))
+                    .unwrap_or_else(|| "-".to_string()),
+                delta
+                    .p_value
+                    .map(|p| format!("{:.4}", p))
+                    .unwrap_or_else(|| "-".to_string()),
+            ]
+        })
+        .collect();
+    let highlights: Vec<Vec<bool>> = deltas
+        .iter()
+        .map(|delta| {
+            vec![
+                false,
+                false,
+                false,
+                false,
+                delta.regression,
+                delta.is_significant(),
+            ]
+        })
+        .collect();
+    report.add_highlighted_table(
+        &["Metric", "Statistic", "A", "B", "Delta", "P Value"],
+        &rows,
+        &highlights,
+    );
+    for (file_name, title) in charts {
+        report.add_image(file_name, title);
+    }
+    let report_path = dir.join("report.html");
+    report.write(&report_path)?;
+    paths.push(report_path);
+
+    Ok(paths)
+}
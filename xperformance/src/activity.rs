@@ -0,0 +1,386 @@
+use crate::chart::{self, Band};
+use crate::layout::Category;
+use crate::logcat::{EventKind, LogcatEvent};
+use crate::memory::{self, BackgroundGrowthDetector, MemoryChartOverlay, ObjectCounts};
+use crate::recorder::{Collector, FinishContext, TickContext};
+use crate::regenerate;
+use crate::utils::{self, CpuChartOverlay};
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use colored::*;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+pub const BACKGROUND_LABEL: &str = "Background";
+
+// 一次采样时位于顶部（resumed）的 Activity，以及目标应用是否在前台
+#[derive(Debug, Clone)]
+pub struct ActivitySample {
+    pub timestamp: DateTime<Local>,
+    // 组件名，如 com.example/.MainActivity；锁屏等没有 resumed Activity 时为 None
+    pub activity: Option<String>,
+    pub foreground: bool,
+}
+
+// Android 10 起为 "topResumedActivity=ActivityRecord{a1b2c3 u0 com.example/.MainActivity t12}"，
+// 更早为 "mResumedActivity: ActivityRecord{...}"
+pub fn parse_resumed_activity(output: &str) -> Option<String> {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.contains("ResumedActivity"))
+        .find_map(|line| {
+            let record = line.split_once("ActivityRecord{")?.1;
+            record
+                .split_whitespace()
+                .map(|token| token.trim_end_matches('}'))
+                .find(|token| token.contains('/'))
+                .map(str::to_string)
+        })
+}
+
+// 组件名中的类名：com.example/.ui.MainActivity -> MainActivity
+pub fn short_name(component: &str) -> &str {
+    let class = component
+        .split_once('/')
+        .map_or(component, |(_, class)| class);
+    class.rsplit('.').next().unwrap_or(class)
+}
+
+pub fn sample(package: &str, timestamp: DateTime<Local>) -> Result<ActivitySample> {
+    let output = utils::run_adb_command(&[
+        "shell",
+        "dumpsys activity activities | grep ResumedActivity",
+    ])?;
+    let activity = parse_resumed_activity(&output);
+    let foreground = activity
+        .as_deref()
+        .and_then(|component| component.split_once('/'))
+        .is_some_and(|(owner, _)| owner == package);
+    Ok(ActivitySample {
+        timestamp,
+        activity,
+        foreground,
+    })
+}
+
+// 顶部 Activity 不变的一段时间，end 为 None 表示仍在持续
+#[derive(Debug, Clone)]
+pub struct ActivitySpan {
+    pub start: DateTime<Local>,
+    pub end: Option<DateTime<Local>>,
+    pub activity: Option<String>,
+    pub foreground: bool,
+}
+
+impl ActivitySpan {
+    pub fn duration_secs(&self, now: DateTime<Local>) -> f64 {
+        (self.end.unwrap_or(now) - self.start).num_milliseconds() as f64 / 1000.0
+    }
+
+    // 前台时为 Activity 类名，否则为 Background
+    pub fn label(&self) -> String {
+        match (&self.activity, self.foreground) {
+            (Some(activity), true) => short_name(activity).to_string(),
+            _ => BACKGROUND_LABEL.to_string(),
+        }
+    }
+}
+
+// 把逐次采样合并为连续的区间
+#[derive(Default)]
+pub struct ActivityTracker {
+    spans: Vec<ActivitySpan>,
+}
+
+impl ActivityTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 从 CSV 读回的区间重建，用于重新生成图表
+    pub fn from_spans(spans: Vec<ActivitySpan>) -> Self {
+        Self { spans }
+    }
+
+    pub fn spans(&self) -> &[ActivitySpan] {
+        &self.spans
+    }
+
+    // 顶部 Activity 或前后台状态变化时开始新区间并返回
+    pub fn update(&mut self, sample: &ActivitySample) -> Option<&ActivitySpan> {
+        if let Some(last) = self.spans.last_mut() {
+            if last.activity == sample.activity && last.foreground == sample.foreground {
+                return None;
+            }
+            last.end = Some(sample.timestamp);
+        }
+        self.spans.push(ActivitySpan {
+            start: sample.timestamp,
+            end: None,
+            activity: sample.activity.clone(),
+            foreground: sample.foreground,
+        });
+        self.spans.last()
+    }
+
+    pub fn finish(&mut self, timestamp: DateTime<Local>) {
+        if let Some(span) = self.spans.last_mut().filter(|s| s.end.is_none()) {
+            span.end = Some(timestamp);
+        }
+    }
+
+    // 图表底纹：前台按 Activity 着色，后台为灰色
+    pub fn bands(&self, now: DateTime<Local>) -> Vec<Band> {
+        self.spans
+            .iter()
+            .map(|span| Band {
+                start: span.start,
+                end: span.end.unwrap_or(now),
+                label: span.label(),
+                muted: !span.foreground,
+            })
+            .collect()
+    }
+
+    // 前台总时长和后台总时长（秒）
+    pub fn foreground_split(&self, now: DateTime<Local>) -> (f64, f64) {
+        self.spans.iter().fold((0.0, 0.0), |(fg, bg), span| {
+            if span.foreground {
+                (fg + span.duration_secs(now), bg)
+            } else {
+                (fg, bg + span.duration_secs(now))
+            }
+        })
+    }
+}
+
+pub fn write_spans_csv(dir: &Path, package: &str, spans: &[ActivitySpan]) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{}_activities.csv", package));
+    let mut file = std::fs::File::create(&path)?;
+    writeln!(file, "Start,End,Duration (s),State,Activity")?;
+    let now = Local::now();
+    for span in spans {
+        writeln!(
+            file,
+            "{},{},{:.0},{},{}",
+            span.start.format("%Y-%m-%d %H:%M:%S"),
+            span.end
+                .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_default(),
+            span.duration_secs(now),
+            if span.foreground {
+                "foreground"
+            } else {
+                "background"
+            },
+            span.activity.as_deref().unwrap_or_default()
+        )?;
+    }
+    Ok(path)
+}
+
+// 读取 write_spans_csv 写出的文件，用于重新生成图表
+pub fn read_spans_csv(path: &Path) -> Result<Vec<ActivitySpan>> {
+    let table = regenerate::read_csv(path)?;
+    Ok(table
+        .rows
+        .iter()
+        .filter_map(|row| {
+            Some(ActivitySpan {
+                start: regenerate::parse_timestamp(row.first()?)?,
+                end: row.get(1).and_then(|t| regenerate::parse_timestamp(t)),
+                activity: row.get(4).filter(|a| !a.is_empty()).cloned(),
+                foreground: row.get(3)? == "foreground",
+            })
+        })
+        .collect())
+}
+
+// --object-growth：回到桌面后 Activities 和 ViewRootImpl 的持续增长，计数来自 --memory 的 Objects 段
+struct ObjectGrowth {
+    samples: usize,
+    detector: BackgroundGrowthDetector,
+    history: Vec<(DateTime<Local>, ObjectCounts)>,
+    events: Vec<LogcatEvent>,
+}
+
+impl ObjectGrowth {
+    fn record(&mut self, ctx: &TickContext, foreground: bool) {
+        let Some(objects) = ctx.sample.memory.as_ref().and_then(|m| m.objects) else {
+            return;
+        };
+        let timestamp = ctx.tick.timestamp;
+        self.history.push((timestamp, objects));
+        for growth in self.detector.record(timestamp, objects, foreground) {
+            console!(
+                "[{}] {} {} grew from {} to {} over {} samples after returning to the home screen",
+                timestamp.format("%H:%M:%S").to_string().blue(),
+                "ALERT:".red().bold(),
+                growth.name,
+                growth.from,
+                growth.to.to_string().red().bold(),
+                self.samples
+            );
+            chart::add_user_marker(growth.start, &format!("{} growth", growth.name));
+            self.events.push(LogcatEvent {
+                timestamp: growth.start,
+                kind: EventKind::ObjectGrowth,
+                pid: Some(ctx.sample.pid.clone()).filter(|pid| !pid.is_empty()),
+                message: format!(
+                    "{} grew from {} to {} in the background",
+                    growth.name, growth.from, growth.to
+                ),
+            });
+        }
+    }
+}
+
+// 逐 Tick 读取顶部 Activity，记录前后台状态，结束时导出时间线；区间作为 CPU 和内存图表的底纹
+pub struct ActivityCollector {
+    tracker: ActivityTracker,
+    object_growth: Option<ObjectGrowth>,
+}
+
+impl ActivityCollector {
+    pub fn new(object_growth: Option<usize>) -> Self {
+        Self {
+            tracker: ActivityTracker::new(),
+            object_growth: object_growth.map(|samples| ObjectGrowth {
+                samples,
+                detector: BackgroundGrowthDetector::new(samples),
+                history: Vec::new(),
+                events: Vec::new(),
+            }),
+        }
+    }
+}
+
+impl Collector for ActivityCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        let tick = ctx.tick;
+        match sample(ctx.package, tick.timestamp) {
+            Ok(sample) => {
+                ctx.table.record(
+                    &tick,
+                    "Foreground",
+                    if sample.foreground { 1.0 } else { 0.0 },
+                );
+                if let Some(span) = self.tracker.update(&sample) {
+                    console!(
+                        "[{}] {} {}",
+                        tick.timestamp.format("%H:%M:%S").to_string().blue(),
+                        if span.foreground {
+                            "Foreground:".green()
+                        } else {
+                            "Background:".yellow()
+                        },
+                        span.activity.as_deref().unwrap_or("no resumed activity")
+                    );
+                }
+            }
+            Err(e) => {
+                if ctx.verbose {
+                    console!("Failed to read the top activity: {}", e);
+                }
+            }
+        }
+        // 读取失败时沿用上一次的前后台状态
+        if let Some(growth) = &mut self.object_growth {
+            let foreground = self.tracker.spans().last().is_none_or(|s| s.foreground);
+            growth.record(ctx, foreground);
+        }
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        self.tracker.finish(Local::now());
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        let spans = self.tracker.spans();
+        if spans.is_empty() {
+            return;
+        }
+        let (foreground, background) = self.tracker.foreground_split(Local::now());
+        console!(
+            "Foreground: {:.0}s, background: {:.0}s, activity changes: {}",
+            foreground,
+            background,
+            spans.len() - 1
+        );
+        match write_spans_csv(&ctx.layout.dir(Category::Process), ctx.package, spans) {
+            Ok(path) => console!("✓ Activity timeline exported to CSV: {}", path.display()),
+            Err(e) => console!("Failed to export activity timeline: {}", e),
+        }
+
+        let Some(growth) = &self.object_growth else {
+            return;
+        };
+        for alert in &growth.detector.alerts {
+            console!(
+                "{} {} grew from {} to {} in the background ({} - {})",
+                "ALERT:".red().bold(),
+                alert.name,
+                alert.from,
+                alert.to,
+                alert.start.format("%H:%M:%S"),
+                alert.timestamp.format("%H:%M:%S")
+            );
+        }
+        // 只画后台区间，前台各 Activity 的底纹会盖住增长区间
+        let mut bands: Vec<Band> = self
+            .tracker
+            .bands(Local::now())
+            .into_iter()
+            .filter(|band| band.muted)
+            .collect();
+        bands.extend(growth.detector.alerts.iter().map(|alert| alert.band()));
+        match memory::write_activity_leak_chart(
+            &ctx.layout.dir(Category::Memory),
+            ctx.package,
+            &growth.history,
+            &bands,
+        ) {
+            Ok(Some(path)) => console!("✓ Activity leak chart generated: {}", path.display()),
+            Ok(None) => {}
+            Err(e) => console!("Failed to generate activity leak chart: {}", e),
+        }
+    }
+
+    fn events(&self, events: &mut Vec<LogcatEvent>) {
+        if let Some(growth) = &self.object_growth {
+            events.extend(growth.events.iter().cloned());
+        }
+    }
+
+    fn cpu_overlay(&self, overlay: &mut CpuChartOverlay) {
+        overlay.bands.extend(self.tracker.bands(Local::now()));
+    }
+
+    fn memory_overlay(&self, overlay: &mut MemoryChartOverlay) {
+        overlay.bands.extend(self.tracker.bands(Local::now()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_resumed_activity_reads_component() {
+        let output = "    topResumedActivity=ActivityRecord{a1b2c3 u0 com.example/.ui.MainActivity t12}\n  ResumedActivity: ActivityRecord{a1b2c3 u0 com.example/.ui.MainActivity t12}\n";
+        let activity = parse_resumed_activity(output).unwrap();
+        assert_eq!(activity, "com.example/.ui.MainActivity");
+        assert_eq!(short_name(&activity), "MainActivity");
+        assert_eq!(
+            parse_resumed_activity(
+                "mResumedActivity: ActivityRecord{d4e5 u0 com.android.launcher3/.Launcher t1}"
+            )
+            .as_deref(),
+            Some("com.android.launcher3/.Launcher")
+        );
+        assert!(parse_resumed_activity("").is_none());
+    }
+}
@@ -0,0 +1,433 @@
+use crate::chart::{self, TimeSeriesData};
+use crate::layout::Category;
+use crate::recorder::{Collector, FinishContext, TickContext};
+use crate::regenerate::CSV_TIMESTAMP_FORMAT;
+use crate::utils;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use colored::*;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// 一个闹钟标签的累计次数，如 "*walarm*:com.foo.SYNC"（*walarm* 为唤醒闹钟，*alarm* 为非唤醒闹钟）
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AlarmTagStats {
+    pub tag: String,
+    pub wakeups: u64,
+    pub alarms: u64,
+}
+
+// dumpsys alarm 中应用的闹钟统计，计数自开机（或统计重置）起累计
+#[derive(Debug, Clone, Default)]
+pub struct AlarmStats {
+    pub wakeups: u64,
+    pub tags: BTreeMap<String, AlarmTagStats>,
+    // 当前排队中的唤醒闹钟（RTC_WAKEUP、ELAPSED_WAKEUP）
+    pub pending_wakeups: u32,
+}
+
+impl AlarmStats {
+    pub fn alarms(&self) -> u64 {
+        self.tags.values().map(|t| t.alarms).sum()
+    }
+}
+
+// 行中紧挨在 word 之前的数字，如 "5 wakeups:" 中的 5
+fn number_before(line: &str, word: &str) -> Option<u64> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let i = tokens
+        .iter()
+        .position(|t| t.trim_end_matches([',', ':']) == word)?;
+    tokens.get(i.checked_sub(1)?)?.parse().ok()
+}
+
+// 排队中的闹钟："RTC_WAKEUP #3: Alarm{8c2d0a1 type 0 origWhen 1697000000000 ... com.foo}"，最后一项为包名
+fn is_pending_wakeup(line: &str, package: &str) -> bool {
+    if !line.contains("_WAKEUP #") {
+        return false;
+    }
+    let Some(start) = line.find("Alarm{") else {
+        return false;
+    };
+    let body = &line[start + 6..];
+    let body = body.split('}').next().unwrap_or(body);
+    body.split_whitespace().last() == Some(package)
+}
+
+// 解析 dumpsys alarm 的 "Alarm Stats:" 段：
+//   u0a123:com.foo +1s234ms running, 5 wakeups:
+//     +1s200ms 3 wakes 3 alarms, last -2m3s456ms:
+//       *walarm*:com.foo.SYNC
+// 同一包名在多个用户下的统计合并
+pub fn parse_alarm_stats(output: &str, package: &str) -> AlarmStats {
+    let mut stats = AlarmStats::default();
+    let mut in_section = false;
+    let mut in_package = false;
+    // 上一行过滤器行的 (唤醒次数, 闹钟次数)，下一行为其标签
+    let mut pending_filter: Option<(u64, u64)> = None;
+    for line in output.lines() {
+        if is_pending_wakeup(line, package) {
+            stats.pending_wakeups += 1;
+        }
+        let trimmed = line.trim();
+        if trimmed == "Alarm Stats:" {
+            in_section = true;
+            continue;
+        }
+        if !in_section {
+            continue;
+        }
+        // 没有缩进的行是下一个服务段落
+        if !trimmed.is_empty() && !line.starts_with(' ') {
+            break;
+        }
+        let content = trimmed.trim_start_matches("*ACTIVE* ");
+        if let Some((wakeups, alarms)) = pending_filter.take() {
+            if in_package {
+                let entry =
+                    stats
+                        .tags
+                        .entry(trimmed.to_string())
+                        .or_insert_with(|| AlarmTagStats {
+                            tag: trimmed.to_string(),
+                            ..Default::default()
+                        });
+                entry.wakeups += wakeups;
+                entry.alarms += alarms;
+            }
+            continue;
+        }
+        if content.contains(" running, ") && content.ends_with(" wakeups:") {
+            let owner = content.split_whitespace().next().unwrap_or_default();
+            in_package = owner.split_once(':').map(|(_, p)| p) == Some(package);
+            if in_package {
+                stats.wakeups += number_before(content, "wakeups").unwrap_or(0);
+            }
+        } else if content.contains(" wakes ") && content.contains(" alarms, last ") {
+            pending_filter = Some((
+                number_before(content, "wakes").unwrap_or(0),
+                number_before(content, "alarms").unwrap_or(0),
+            ));
+        }
+    }
+    stats
+}
+
+pub fn sample_alarm_stats(package: &str) -> Result<AlarmStats> {
+    let output = utils::run_adb_command(&["shell", "dumpsys", "alarm"])?;
+    if !output.contains("Alarm Stats:") {
+        anyhow::bail!("No 'Alarm Stats' section in dumpsys alarm");
+    }
+    Ok(parse_alarm_stats(&output, package))
+}
+
+// 一次采样：自监控开始以来的唤醒和闹钟次数，以及当前排队的唤醒闹钟数
+#[derive(Debug, Clone, Copy)]
+pub struct AlarmSample {
+    pub timestamp: DateTime<Local>,
+    pub wakeups: u64,
+    pub alarms: u64,
+    pub pending_wakeups: u32,
+}
+
+// 以首次采样为基准计算会话期间的增量；dumpsys alarm 输出较大，按 interval 间隔采样
+pub struct AlarmTracker {
+    interval: chrono::Duration,
+    baseline: Option<AlarmStats>,
+    latest: Option<AlarmStats>,
+    samples: Vec<AlarmSample>,
+}
+
+impl AlarmTracker {
+    pub fn new(interval: std::time::Duration) -> Self {
+        Self {
+            interval: chrono::Duration::from_std(interval).unwrap_or_default(),
+            baseline: None,
+            latest: None,
+            samples: Vec::new(),
+        }
+    }
+
+    pub fn samples(&self) -> &[AlarmSample] {
+        &self.samples
+    }
+
+    pub fn is_due(&self, timestamp: DateTime<Local>) -> bool {
+        self.samples
+            .last()
+            .is_none_or(|last| timestamp - last.timestamp >= self.interval)
+    }
+
+    // 返回自上次采样以来新增的唤醒次数
+    pub fn record(&mut self, timestamp: DateTime<Local>, stats: AlarmStats) -> u64 {
+        let baseline = self.baseline.get_or_insert_with(|| stats.clone());
+        let sample = AlarmSample {
+            timestamp,
+            wakeups: stats.wakeups.saturating_sub(baseline.wakeups),
+            alarms: stats.alarms().saturating_sub(baseline.alarms()),
+            pending_wakeups: stats.pending_wakeups,
+        };
+        let new_wakeups = sample
+            .wakeups
+            .saturating_sub(self.samples.last().map_or(0, |s| s.wakeups));
+        self.samples.push(sample);
+        self.latest = Some(stats);
+        new_wakeups
+    }
+
+    // 会话期间各标签的次数，按唤醒次数、闹钟次数降序，没有触发的标签不列出
+    pub fn tag_deltas(&self) -> Vec<AlarmTagStats> {
+        let (Some(baseline), Some(latest)) = (&self.baseline, &self.latest) else {
+            return Vec::new();
+        };
+        let mut tags: Vec<AlarmTagStats> = latest
+            .tags
+            .values()
+            .map(|tag| {
+                let before = baseline.tags.get(&tag.tag);
+                AlarmTagStats {
+                    tag: tag.tag.clone(),
+                    wakeups: tag.wakeups.saturating_sub(before.map_or(0, |b| b.wakeups)),
+                    alarms: tag.alarms.saturating_sub(before.map_or(0, |b| b.alarms)),
+                }
+            })
+            .filter(|tag| tag.alarms > 0 || tag.wakeups > 0)
+            .collect();
+        tags.sort_by(|a, b| b.wakeups.cmp(&a.wakeups).then(b.alarms.cmp(&a.alarms)));
+        tags
+    }
+
+    // 每小时唤醒次数，采样跨度不足一分钟时为 None
+    pub fn wakeups_per_hour(&self) -> Option<f64> {
+        let (first, last) = (self.samples.first()?, self.samples.last()?);
+        let secs = (last.timestamp - first.timestamp).num_seconds();
+        (secs >= 60).then(|| last.wakeups as f64 * 3600.0 / secs as f64)
+    }
+}
+
+// 导出逐次采样 CSV、各标签次数 CSV 和累计唤醒次数图
+pub fn export_alarm_data(
+    dir: &Path,
+    package: &str,
+    tracker: &AlarmTracker,
+) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let mut paths = Vec::new();
+
+    let csv_path = dir.join(format!("{}_alarm_data.csv", package));
+    let mut file = std::fs::File::create(&csv_path)?;
+    writeln!(file, "Timestamp,Wakeups,Alarms,Pending Wakeup Alarms")?;
+    for sample in &tracker.samples {
+        writeln!(
+            file,
+            "{},{},{},{}",
+            sample.timestamp.format(CSV_TIMESTAMP_FORMAT),
+            sample.wakeups,
+            sample.alarms,
+            sample.pending_wakeups
+        )?;
+    }
+    paths.push(csv_path);
+
+    let tags_path = dir.join(format!("{}_alarm_tags.csv", package));
+    let mut file = std::fs::File::create(&tags_path)?;
+    writeln!(file, "Tag,Wakeups,Alarms")?;
+    for tag in tracker.tag_deltas() {
+        writeln!(
+            file,
+            "{},{},{}",
+            tag.tag.replace(',', ";"),
+            tag.wakeups,
+            tag.alarms
+        )?;
+    }
+    paths.push(tags_path);
+
+    if tracker.samples.len() > 1 {
+        let path = dir.join(chart::stamped_file_name(&format!(
+            "{}_alarm_wakeups.png",
+            package
+        )));
+        let series = |f: fn(&AlarmSample) -> f64| -> Vec<(DateTime<Local>, f64)> {
+            tracker
+                .samples
+                .iter()
+                .map(|s| (s.timestamp, f(s)))
+                .collect()
+        };
+        chart::draw_time_series_chart(
+            &path,
+            &format!("Alarm Wakeups - {}", package),
+            "Count",
+            &[
+                TimeSeriesData::new("Wakeups", series(|s| s.wakeups as f64)),
+                TimeSeriesData::new("Alarms", series(|s| s.alarms as f64)),
+            ],
+        )?;
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
+
+// --alarms：按 --alarm-interval 读取 dumpsys alarm，统计会话期间应用的闹钟唤醒
+pub struct AlarmCollector {
+    tracker: AlarmTracker,
+}
+
+impl AlarmCollector {
+    pub fn new(interval: std::time::Duration) -> Self {
+        Self {
+            tracker: AlarmTracker::new(interval),
+        }
+    }
+}
+
+impl Collector for AlarmCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        let tick = ctx.tick;
+        if !self.tracker.is_due(tick.timestamp) {
+            return Ok(());
+        }
+        let stats = match sample_alarm_stats(ctx.package) {
+            Ok(stats) => stats,
+            Err(e) => {
+                if !utils::is_being_interrupted() {
+                    console!("Failed to sample alarms: {}", e);
+                }
+                return Ok(());
+            }
+        };
+        let new_wakeups = self.tracker.record(tick.timestamp, stats);
+        let Some(sample) = self.tracker.samples().last() else {
+            return Ok(());
+        };
+        ctx.table
+            .record(&tick, "Alarm Wakeups", sample.wakeups as f64);
+        ctx.table.record(
+            &tick,
+            "Pending Wakeup Alarms",
+            sample.pending_wakeups as f64,
+        );
+        if new_wakeups > 0 {
+            console!(
+                "[{}] {} +{} (total {})",
+                tick.timestamp.format("%H:%M:%S").to_string().blue(),
+                "Alarm wakeups:".yellow(),
+                new_wakeups,
+                sample.wakeups
+            );
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        if self.tracker.samples().is_empty() {
+            return;
+        }
+        // 结束时再读一次，最后一个间隔内的闹钟也计入
+        match sample_alarm_stats(ctx.package) {
+            Ok(stats) => {
+                self.tracker.record(Local::now(), stats);
+            }
+            Err(e) => console!("Failed to sample alarms: {}", e),
+        }
+        let Some(last) = self.tracker.samples().last() else {
+            return;
+        };
+        console!(
+            "Alarms: {} wakeups, {} alarms{}",
+            last.wakeups.to_string().red(),
+            last.alarms,
+            self.tracker
+                .wakeups_per_hour()
+                .map(|rate| format!(" ({:.1} wakeups/hour)", rate))
+                .unwrap_or_default()
+        );
+        for tag in self.tracker.tag_deltas().iter().take(5) {
+            console!(
+                "  {}: {} wakeups, {} alarms",
+                tag.tag.cyan(),
+                tag.wakeups,
+                tag.alarms
+            );
+        }
+        match export_alarm_data(
+            &ctx.layout.dir(Category::Wakelocks),
+            ctx.package,
+            &self.tracker,
+        ) {
+            Ok(paths) => {
+                for path in paths {
+                    console!("✓ Alarm data exported: {}", path.display());
+                }
+            }
+            Err(e) => console!("Failed to export alarm data: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DUMPSYS: &str = "\
+Current Alarm Manager state:
+  Pending alarm batches: 2
+    RTC_WAKEUP #0: Alarm{8c2d0a1 type 0 origWhen 1697000000000 com.foo}
+    ELAPSED #1: Alarm{8c2d0a2 type 3 origWhen 1697000000000 com.foo}
+    RTC_WAKEUP #2: Alarm{8c2d0a3 type 0 origWhen 1697000000000 com.bar}
+  Alarm Stats:
+  u0a123:com.foo +1s234ms running, 5 wakeups:
+    +1s200ms 3 wakes 3 alarms, last -2m3s456ms:
+      *walarm*:com.foo.SYNC
+    +34ms 0 wakes 4 alarms, last -10s:
+      *alarm*:com.foo.TICK
+  u0a124:com.bar +2s running, 9 wakeups:
+    +2s 9 wakes 9 alarms, last -1s:
+      *walarm*:com.bar.POLL
+
+DUMP OF SERVICE next:
+";
+
+    #[test]
+    fn parse_alarm_stats_for_package() {
+        let stats = parse_alarm_stats(DUMPSYS, "com.foo");
+        assert_eq!(stats.wakeups, 5);
+        assert_eq!(stats.alarms(), 7);
+        assert_eq!(stats.pending_wakeups, 1);
+        assert_eq!(
+            stats.tags["*walarm*:com.foo.SYNC"],
+            AlarmTagStats {
+                tag: "*walarm*:com.foo.SYNC".into(),
+                wakeups: 3,
+                alarms: 3,
+            }
+        );
+        assert!(!stats.tags.contains_key("*walarm*:com.bar.POLL"));
+    }
+
+    #[test]
+    fn tracker_reports_deltas_from_first_sample() {
+        let mut tracker = AlarmTracker::new(std::time::Duration::from_secs(60));
+        let start = Local::now();
+        assert!(tracker.is_due(start));
+        assert_eq!(
+            tracker.record(start, parse_alarm_stats(DUMPSYS, "com.foo")),
+            0
+        );
+        assert!(!tracker.is_due(start + chrono::Duration::seconds(30)));
+
+        let later = DUMPSYS
+            .replace("5 wakeups:", "7 wakeups:")
+            .replace("3 wakes 3 alarms", "5 wakes 5 alarms");
+        let end = start + chrono::Duration::seconds(3600);
+        assert_eq!(tracker.record(end, parse_alarm_stats(&later, "com.foo")), 2);
+        assert_eq!(tracker.wakeups_per_hour(), Some(2.0));
+        let tags = tracker.tag_deltas();
+        assert_eq!(tags.len(), 1);
+        assert_eq!(tags[0].tag, "*walarm*:com.foo.SYNC");
+        assert_eq!(tags[0].wakeups, 2);
+    }
+}
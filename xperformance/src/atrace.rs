@@ -0,0 +1,256 @@
+use crate::capture::{self, CaptureWindow};
+use crate::layout::{Category, OutputLayout};
+use crate::recorder::{Collector, FinishContext, TickContext};
+use crate::replay;
+use crate::tui;
+use crate::utils::{self, CpuChartOverlay};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use colored::*;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+
+// 设备端临时目录
+const REMOTE_DIR: &str = "/data/local/tmp";
+
+pub const DEFAULT_CATEGORIES: &[&str] = &["gfx", "view", "sched"];
+
+// 后台运行的 atrace，由 adb 子进程承载，到达时长后自行结束
+pub struct Capture {
+    child: Child,
+    pub remote_path: String,
+    pub start: DateTime<Local>,
+    pub trigger: String,
+}
+
+// 抓取指定应用的 atrace，类别如 gfx、view、sched
+pub fn start_capture(
+    package: &str,
+    categories: &[String],
+    duration: Duration,
+    trigger: &str,
+) -> Result<Capture> {
+    let start = Local::now();
+    let remote_path = format!(
+        "{}/{}_{}.atrace",
+        REMOTE_DIR,
+        package,
+        start.format("%Y%m%d_%H%M%S")
+    );
+
+    let seconds = duration.as_secs().max(1).to_string();
+    let mut command = Command::new("adb");
+    if let Some(serial) = utils::adb_serial() {
+        command.args(["-s", &serial]);
+    }
+    let child = command
+        .args([
+            "shell",
+            "atrace",
+            "-a",
+            package,
+            "-t",
+            &seconds,
+            "-o",
+            &remote_path,
+        ])
+        .args(categories)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to execute adb command")?;
+
+    Ok(Capture {
+        child,
+        remote_path,
+        start,
+        trigger: trigger.to_string(),
+    })
+}
+
+impl Capture {
+    // 已结束时检查退出状态，失败时返回 atrace 的错误输出
+    pub fn is_finished(&mut self) -> Result<bool> {
+        let Some(status) = self.child.try_wait()? else {
+            return Ok(false);
+        };
+        if !status.success() {
+            let mut stderr = String::new();
+            if let Some(mut pipe) = self.child.stderr.take() {
+                let _ = pipe.read_to_string(&mut stderr);
+            }
+            anyhow::bail!("atrace failed: {}", stderr.trim());
+        }
+        Ok(true)
+    }
+
+    // 提前结束时发送 SIGINT，atrace 收到后停止抓取并写出已有数据
+    pub fn stop(&mut self) -> Result<()> {
+        if self.child.try_wait()?.is_none() {
+            let _ = utils::run_adb_command(&["shell", "pkill", "-INT", "-x", "atrace"]);
+            self.child.wait()?;
+        }
+        Ok(())
+    }
+}
+
+// 将 atrace 文本包装为 systrace 格式的 HTML：trace 数据放在 trace-data 脚本块中，
+// 可直接拖入 ui.perfetto.dev 打开
+pub fn to_html(trace: &str, title: &str) -> String {
+    let data = trace
+        .trim_start()
+        .strip_prefix("TRACE:")
+        .unwrap_or(trace)
+        .trim_start();
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n<p>{} &mdash; open this file in <a href=\"https://ui.perfetto.dev\">ui.perfetto.dev</a>.</p>\n<script class=\"trace-data\" type=\"application/text\">\n{}\n</script>\n</body>\n</html>\n",
+        title,
+        title,
+        data.replace("</script>", "<\\/script>")
+    )
+}
+
+// 拉取 trace 并转换为 HTML 保存到本地目录，删除设备端文件
+pub fn pull(capture: &Capture, output_dir: &Path) -> Result<PathBuf> {
+    std::fs::create_dir_all(output_dir)?;
+    let stem = capture
+        .remote_path
+        .rsplit('/')
+        .next()
+        .unwrap_or("trace.atrace")
+        .trim_end_matches(".atrace")
+        .to_string();
+    let raw_path = output_dir.join(format!("{}.atrace", stem));
+    utils::run_adb_command(&["pull", &capture.remote_path, &raw_path.to_string_lossy()])?;
+    let _ = utils::run_adb_command(&["shell", "rm", "-f", &capture.remote_path]);
+
+    let trace = std::fs::read_to_string(&raw_path)
+        .with_context(|| format!("Failed to read {}", raw_path.display()))?;
+    let html_path = output_dir.join(format!("{}.html", stem));
+    std::fs::write(&html_path, to_html(&trace, &stem))?;
+    std::fs::remove_file(&raw_path)?;
+    Ok(html_path)
+}
+
+// 拉取 atrace 并保存为 HTML，返回其时间窗口
+fn finish_capture(package: &str, capture: &Capture, end: DateTime<Local>) -> Result<CaptureWindow> {
+    let dir = OutputLayout::session(package)?.dir(Category::Atrace);
+    let path = match pull(capture, &dir) {
+        Ok(path) => {
+            console!("✓ atrace saved: {}", path.display());
+            Some(path)
+        }
+        Err(e) => {
+            console!("Failed to pull atrace: {}", e);
+            None
+        }
+    };
+    Ok(CaptureWindow {
+        kind: "atrace",
+        start: capture.start,
+        end,
+        trigger: capture.trigger.clone(),
+        path,
+    })
+}
+
+// --atrace 在会话开始时抓取一段，--tui 中按 t 再抓取；同一时间最多一个
+pub struct AtraceCollector {
+    package: String,
+    categories: Vec<String>,
+    duration: Duration,
+    at_start: bool,
+    capture: Option<Capture>,
+    requested: bool,
+    windows: Vec<CaptureWindow>,
+}
+
+impl AtraceCollector {
+    // 未指定类别时使用 DEFAULT_CATEGORIES
+    pub fn new(package: &str, categories: &[String], duration: Duration, at_start: bool) -> Self {
+        let categories = if categories.is_empty() {
+            DEFAULT_CATEGORIES.iter().map(|c| c.to_string()).collect()
+        } else {
+            categories.to_vec()
+        };
+        Self {
+            package: package.to_string(),
+            categories,
+            duration,
+            at_start,
+            capture: None,
+            requested: at_start,
+            windows: Vec::new(),
+        }
+    }
+}
+
+impl Collector for AtraceCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        let tick = ctx.tick;
+        let finished = match self.capture.as_mut().map(Capture::is_finished) {
+            Some(Ok(finished)) => finished,
+            Some(Err(e)) => {
+                console!("{}", e);
+                self.capture = None;
+                false
+            }
+            None => false,
+        };
+        if let Some(capture) = self.capture.take_if(|_| finished) {
+            let end = tick
+                .timestamp
+                .min(capture.start + chrono::Duration::from_std(self.duration).unwrap_or_default());
+            self.windows
+                .push(finish_capture(&self.package, &capture, end)?);
+        }
+
+        self.requested |= tui::take_atrace_request();
+        if self.requested && self.capture.is_none() && !replay::is_replaying() {
+            self.requested = false;
+            let trigger = if self.windows.is_empty() && self.at_start {
+                "Session start"
+            } else {
+                "On demand"
+            };
+            match start_capture(&self.package, &self.categories, self.duration, trigger) {
+                Ok(capture) => {
+                    console!(
+                        "[{}] atrace capture started ({}s): {}",
+                        tick.timestamp.format("%H:%M:%S").to_string().blue(),
+                        self.duration.as_secs(),
+                        self.categories.join(" ")
+                    );
+                    self.capture = Some(capture);
+                }
+                Err(e) => console!("Failed to start atrace: {}", e),
+            }
+        }
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        let Some(mut capture) = self.capture.take() else {
+            return;
+        };
+        if let Err(e) = capture.stop() {
+            console!("Failed to stop atrace: {}", e);
+            return;
+        }
+        match finish_capture(&self.package, &capture, Local::now()) {
+            Ok(window) => self.windows.push(window),
+            Err(e) => console!("Failed to pull atrace: {}", e),
+        }
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        capture::report_windows("atrace captures:", Category::Atrace, &self.windows, ctx);
+    }
+
+    fn cpu_overlay(&self, overlay: &mut CpuChartOverlay) {
+        overlay.markers.extend(capture::markers(&self.windows));
+    }
+}
@@ -0,0 +1,366 @@
+use crate::chart::{self, TimeSeriesData};
+use crate::layout::Category;
+use crate::recorder::{Collector, FinishContext, TickContext};
+use crate::utils;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use colored::*;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// binder 驱动的统计文件，新内核使用 binderfs，旧内核使用 debugfs（都需要 root）
+const STATS_PATHS: [&str; 2] = [
+    "/dev/binderfs/binder_logs/stats",
+    "/sys/kernel/debug/binder/stats",
+];
+
+// 数据来源：驱动统计可得到线程池状态；binder_calls_stats 只有该 UID 发往 system_server 的调用次数
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BinderSource {
+    DriverStats(String),
+    CallsStats,
+}
+
+impl BinderSource {
+    pub fn describe(&self) -> String {
+        match self {
+            BinderSource::DriverStats(path) => path.clone(),
+            BinderSource::CallsStats => "dumpsys binder_calls_stats".to_string(),
+        }
+    }
+}
+
+// 驱动统计中某个进程（binder 上下文）的累计值
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ProcBinderStats {
+    pub threads: u32,
+    pub max_threads: u32,
+    pub ready_threads: u32,
+    // 收到的事务（BR_TRANSACTION）和发出的事务（BC_TRANSACTION）
+    pub incoming: u64,
+    pub outgoing: u64,
+}
+
+// 解析 stats 中 "proc <pid>" 段，只统计 "context binder"（不含 hwbinder、vndbinder）
+// 形如 "  threads: 5"、"  requested threads: 0+1/15"、"  ready threads 2"、"  BR_TRANSACTION: 120"
+pub fn parse_driver_stats(output: &str, pid: &str) -> Option<ProcBinderStats> {
+    let mut stats: Option<ProcBinderStats> = None;
+    let mut in_proc = false;
+    for line in output.lines() {
+        let line = line.trim();
+        if let Some(proc_pid) = line.strip_prefix("proc ") {
+            in_proc = proc_pid.trim() == pid;
+            continue;
+        }
+        if !in_proc {
+            continue;
+        }
+        if let Some(context) = line.strip_prefix("context ") {
+            in_proc = context.trim() == "binder";
+            if in_proc {
+                stats.get_or_insert_with(ProcBinderStats::default);
+            }
+            continue;
+        }
+        let Some(stats) = stats.as_mut() else {
+            continue;
+        };
+        let number = |value: &str| value.trim().parse::<u64>().ok();
+        if let Some(value) = line.strip_prefix("threads:") {
+            stats.threads = number(value).unwrap_or(0) as u32;
+        } else if let Some(value) = line.strip_prefix("requested threads:") {
+            stats.max_threads = value
+                .rsplit_once('/')
+                .and_then(|(_, max)| number(max))
+                .unwrap_or(0) as u32;
+        } else if let Some(value) = line.strip_prefix("ready threads") {
+            stats.ready_threads = number(value).unwrap_or(0) as u32;
+        } else if let Some(value) = line.strip_prefix("BR_TRANSACTION:") {
+            stats.incoming = number(value).unwrap_or(0);
+        } else if let Some(value) = line.strip_prefix("BC_TRANSACTION:") {
+            stats.outgoing = number(value).unwrap_or(0);
+        }
+    }
+    stats
+}
+
+// 解析 binder_calls_stats 的 Raw data 段，累加该 UID 的 call_count（最后一列）
+// 行形如 "10123,android.app.IActivityManager#getRunningAppProcesses,false,...,42"
+pub fn parse_calls_stats(output: &str, uid: &str) -> Option<u64> {
+    let mut in_raw = false;
+    let mut total = None;
+    for line in output.lines() {
+        let line = line.trim();
+        if line.starts_with("Raw data") {
+            in_raw = true;
+            continue;
+        }
+        if !in_raw {
+            continue;
+        }
+        if line.is_empty() {
+            break;
+        }
+        let fields: Vec<&str> = line.split(',').collect();
+        if fields.first() == Some(&uid) {
+            if let Some(count) = fields.last().and_then(|v| v.trim().parse::<u64>().ok()) {
+                *total.get_or_insert(0) += count;
+            }
+        }
+    }
+    total
+}
+
+// 一个采样周期的 binder 活动
+#[derive(Debug, Clone, Copy)]
+pub struct BinderSample {
+    pub timestamp: DateTime<Local>,
+    pub incoming: Option<f64>,
+    pub outgoing: Option<f64>,
+    pub threads: Option<u32>,
+    pub busy_threads: Option<u32>,
+    pub max_threads: Option<u32>,
+}
+
+impl BinderSample {
+    // 线程池饱和度：忙碌线程占线程池上限的比例，上限为 max_threads 加上主动加入的主 binder 线程
+    pub fn saturation(&self) -> Option<f64> {
+        let busy = self.busy_threads?;
+        let max = self.max_threads.filter(|max| *max > 0)? + 1;
+        Some(busy as f64 * 100.0 / max as f64)
+    }
+}
+
+pub struct BinderSampler {
+    pub source: BinderSource,
+    uid: String,
+    last: Option<(String, u64, u64)>,
+}
+
+impl BinderSampler {
+    // 优先使用可读的驱动统计，否则回退到 binder_calls_stats
+    pub fn new(pid: &str, uid: &str) -> Self {
+        let source = STATS_PATHS
+            .iter()
+            .find(|path| {
+                utils::run_adb_command(&["shell", "cat", path])
+                    .ok()
+                    .and_then(|output| parse_driver_stats(&output, pid))
+                    .is_some()
+            })
+            .map(|path| BinderSource::DriverStats(path.to_string()))
+            .unwrap_or(BinderSource::CallsStats);
+        Self {
+            source,
+            uid: uid.to_string(),
+            last: None,
+        }
+    }
+
+    // 计数器为累计值，首次采样或进程重启后只建立基线
+    pub fn sample(&mut self, pid: &str, timestamp: DateTime<Local>) -> Result<BinderSample> {
+        let (stats, incoming, outgoing) = match &self.source {
+            BinderSource::DriverStats(path) => {
+                let output = utils::run_adb_command(&["shell", "cat", path])?;
+                let stats = parse_driver_stats(&output, pid)
+                    .ok_or_else(|| anyhow::format_err!("PID {} not found in {}", pid, path))?;
+                (Some(stats), stats.incoming, stats.outgoing)
+            }
+            BinderSource::CallsStats => {
+                let output = utils::run_adb_command(&["shell", "dumpsys", "binder_calls_stats"])?;
+                let calls = parse_calls_stats(&output, &self.uid).unwrap_or(0);
+                (None, 0, calls)
+            }
+        };
+
+        let delta = |now: u64, before: u64| (now >= before).then(|| (now - before) as f64);
+        let (incoming_delta, outgoing_delta) = match &self.last {
+            Some((last_pid, last_in, last_out)) if last_pid == pid => (
+                delta(incoming, *last_in).filter(|_| stats.is_some()),
+                delta(outgoing, *last_out),
+            ),
+            _ => (None, None),
+        };
+        self.last = Some((pid.to_string(), incoming, outgoing));
+
+        Ok(BinderSample {
+            timestamp,
+            incoming: incoming_delta,
+            outgoing: outgoing_delta,
+            threads: stats.map(|s| s.threads),
+            busy_threads: stats.map(|s| s.threads.saturating_sub(s.ready_threads)),
+            max_threads: stats.map(|s| s.max_threads),
+        })
+    }
+}
+
+// 导出 binder 事务数、线程池 CSV，以及事务数和线程池饱和度图表
+pub fn export_binder_data(
+    dir: &Path,
+    package: &str,
+    samples: &[BinderSample],
+) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let mut paths = Vec::new();
+
+    let csv_path = dir.join(format!("{}_binder.csv", package));
+    let mut file = std::fs::File::create(&csv_path)?;
+    writeln!(
+        file,
+        "Timestamp,Incoming Transactions,Outgoing Transactions,Threads,Busy Threads,Max Threads,Saturation (%)"
+    )?;
+    let format = |value: Option<f64>| value.map(|v| format!("{:.0}", v)).unwrap_or_default();
+    for sample in samples {
+        writeln!(
+            file,
+            "{},{},{},{},{},{},{}",
+            sample.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            format(sample.incoming),
+            format(sample.outgoing),
+            format(sample.threads.map(f64::from)),
+            format(sample.busy_threads.map(f64::from)),
+            format(sample.max_threads.map(f64::from)),
+            sample
+                .saturation()
+                .map(|v| format!("{:.1}", v))
+                .unwrap_or_default()
+        )?;
+    }
+    paths.push(csv_path);
+
+    let series = |f: fn(&BinderSample) -> Option<f64>| -> Vec<(DateTime<Local>, f64)> {
+        samples
+            .iter()
+            .filter_map(|s| f(s).map(|v| (s.timestamp, v)))
+            .collect()
+    };
+    for (file_name, title, y_desc, data) in [
+        (
+            "binder_transactions.png",
+            "Binder Transactions",
+            "Transactions per sample",
+            vec![
+                TimeSeriesData::new("Incoming", series(|s| s.incoming)),
+                TimeSeriesData::new("Outgoing", series(|s| s.outgoing)),
+            ],
+        ),
+        (
+            "binder_saturation.png",
+            "Binder Thread Pool Saturation",
+            "Busy threads (% of pool)",
+            vec![TimeSeriesData::new(
+                "Saturation (%)",
+                series(BinderSample::saturation),
+            )],
+        ),
+    ] {
+        let data: Vec<TimeSeriesData> = data
+            .into_iter()
+            .filter(|series| series.points.len() > 1)
+            .collect();
+        if data.is_empty() {
+            continue;
+        }
+        let path = dir.join(chart::stamped_file_name(&format!(
+            "{}_{}",
+            package, file_name
+        )));
+        chart::draw_time_series_chart(&path, &format!("{} - {}", title, package), y_desc, &data)?;
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
+
+// binder 调用量和线程池占用；数据来源需要 root 才能读取驱动统计，首次采样时按当时的 PID 探测
+pub struct BinderCollector {
+    uid: String,
+    sampler: Option<BinderSampler>,
+    samples: Vec<BinderSample>,
+}
+
+impl BinderCollector {
+    pub fn new(uid: &str) -> Self {
+        Self {
+            uid: uid.to_string(),
+            sampler: None,
+            samples: Vec::new(),
+        }
+    }
+}
+
+impl Collector for BinderCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        let tick = ctx.tick;
+        let pid = ctx.sample.pid.as_str();
+        let sampler = self.sampler.get_or_insert_with(|| {
+            let sampler = BinderSampler::new(pid, &self.uid);
+            console!("Binder source: {}", sampler.source.describe().cyan());
+            sampler
+        });
+        let sample = match sampler.sample(pid, tick.timestamp) {
+            Ok(sample) => sample,
+            Err(e) => {
+                if !utils::is_being_interrupted() {
+                    console!("Failed to sample binder stats: {}", e);
+                }
+                return Ok(());
+            }
+        };
+        let format = |value: Option<f64>| value.map_or("-".to_string(), |v| format!("{:.0}", v));
+        for (column, value) in [
+            ("Binder Incoming", sample.incoming),
+            ("Binder Outgoing", sample.outgoing),
+            ("Binder Busy Threads", sample.busy_threads.map(f64::from)),
+            ("Binder Saturation (%)", sample.saturation()),
+        ] {
+            if let Some(value) = value {
+                ctx.table.record(&tick, column, value);
+            }
+        }
+        console!(
+            "[{}] Binder: in {}, out {}, busy threads {}/{}{}",
+            tick.timestamp.format("%H:%M:%S"),
+            format(sample.incoming),
+            format(sample.outgoing),
+            format(sample.busy_threads.map(f64::from)),
+            format(sample.threads.map(f64::from)),
+            sample
+                .saturation()
+                .map(|v| format!(" ({:.0}% of pool)", v).yellow().to_string())
+                .unwrap_or_default()
+        );
+        self.samples.push(sample);
+        Ok(())
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        if self.samples.is_empty() {
+            return;
+        }
+        if let Some(peak) = self
+            .samples
+            .iter()
+            .filter_map(|s| s.saturation().map(|v| (s.timestamp, v)))
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+        {
+            console!(
+                "Peak binder thread pool saturation: {} at {}",
+                format!("{:.0}%", peak.1).red(),
+                peak.0.format("%Y-%m-%d %H:%M:%S")
+            );
+        }
+        match export_binder_data(
+            &ctx.layout.dir(Category::Binder),
+            ctx.package,
+            &self.samples,
+        ) {
+            Ok(paths) => {
+                for path in paths {
+                    console!("✓ Binder data exported: {}", path.display());
+                }
+            }
+            Err(e) => console!("Failed to export binder data: {}", e),
+        }
+    }
+}
@@ -0,0 +1,304 @@
+use crate::layout::{Category, OutputLayout};
+use crate::recorder::{Collector, FinishContext, TickContext};
+use crate::trace::{self, ActiveTrace, TraceOptions};
+use crate::utils::CpuChartOverlay;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use clap::ValueEnum;
+use colored::*;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// 触发式抓取的 trace 数量上限，避免持续异常时占满设备存储
+pub const MAX_TRIGGERED_TRACES: usize = 5;
+
+// 监控期间抓取的 trace 的时间窗口，记录下来以便与 CPU 图表对照
+#[derive(Debug, Clone)]
+pub struct CaptureWindow {
+    pub kind: &'static str,
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    // 触发原因，如 "session start" 或告警内容
+    pub trigger: String,
+    pub path: Option<PathBuf>,
+}
+
+impl CaptureWindow {
+    pub fn duration_secs(&self) -> f64 {
+        (self.end - self.start).num_milliseconds() as f64 / 1000.0
+    }
+}
+
+// 图表中每个窗口的开始和结束各一条竖线
+pub fn markers(windows: &[CaptureWindow]) -> Vec<(DateTime<Local>, String)> {
+    windows
+        .iter()
+        .flat_map(|w| {
+            [
+                (w.start, format!("{} start", w.kind)),
+                (w.end, format!("{} end", w.kind)),
+            ]
+        })
+        .collect()
+}
+
+// 拉取到本地的文件路径，拉取失败时为空
+pub fn window_path(window: &CaptureWindow) -> String {
+    window
+        .path
+        .as_ref()
+        .map(|p| p.display().to_string())
+        .unwrap_or_default()
+}
+
+pub fn write_windows_csv(path: &Path, windows: &[CaptureWindow]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut file = std::fs::File::create(path)?;
+    writeln!(file, "Kind,Start,End,Duration (s),Trigger,File")?;
+    for window in windows {
+        writeln!(
+            file,
+            "{},{},{},{:.1},\"{}\",{}",
+            window.kind,
+            window.start.format("%Y-%m-%d %H:%M:%S"),
+            window.end.format("%Y-%m-%d %H:%M:%S"),
+            window.duration_secs(),
+            window.trigger.replace('"', "\"\""),
+            window
+                .path
+                .as_ref()
+                .and_then(|p| p.file_name())
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default()
+        )?;
+    }
+    Ok(())
+}
+
+// 会话结束时列出抓取的时间窗口，与 CPU 图表上的标记对应，并导出到 <subdir> 目录
+pub fn report_windows(
+    title: &str,
+    category: Category,
+    windows: &[CaptureWindow],
+    ctx: &FinishContext,
+) {
+    if windows.is_empty() {
+        return;
+    }
+    console!("\n{}", title.cyan().bold());
+    for window in windows {
+        console!(
+            "  {} - {} ({}) {}",
+            window.start.format("%H:%M:%S"),
+            window.end.format("%H:%M:%S"),
+            window.trigger,
+            window_path(window)
+        );
+    }
+    let path = ctx.layout.category_file(
+        category,
+        &format!("{}_{}_windows.csv", ctx.package, category.dir_name()),
+    );
+    match write_windows_csv(&path, windows) {
+        Ok(()) => console!("✓ Capture windows exported to CSV: {}", path.display()),
+        Err(e) => console!("Failed to export capture windows: {}", e),
+    }
+}
+
+// 监控期间的 perfetto 抓取，同一时间最多一个 trace，结束后拉取到会话目录
+pub struct PerfettoRecorder {
+    options: TraceOptions,
+    output_dir: PathBuf,
+    active: Option<(ActiveTrace, DateTime<Local>, String)>,
+    attempts: usize,
+    windows: Vec<CaptureWindow>,
+}
+
+impl PerfettoRecorder {
+    // 启动前先生成一次配置，尽早报告无效的配置名或文件
+    pub fn new(options: TraceOptions, output_dir: PathBuf) -> Result<Self> {
+        trace::build_config(&options)?;
+        Ok(Self {
+            options,
+            output_dir,
+            active: None,
+            attempts: 0,
+            windows: Vec::new(),
+        })
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active.is_some()
+    }
+
+    // 启动失败也计入，避免每个 Tick 重试
+    pub fn attempts(&self) -> usize {
+        self.attempts
+    }
+
+    pub fn windows(&self) -> &[CaptureWindow] {
+        &self.windows
+    }
+
+    // 已有 trace 在进行时不重复启动
+    pub fn start(&mut self, trigger: &str) -> Result<bool> {
+        if self.active.is_some() {
+            return Ok(false);
+        }
+        self.attempts += 1;
+        let active = trace::start_trace(None, &self.options)?;
+        self.active = Some((active, Local::now(), trigger.to_string()));
+        Ok(true)
+    }
+
+    // perfetto 到达 duration 后自行退出，此时拉取文件并记录窗口
+    pub fn poll(&mut self) -> Result<Option<&CaptureWindow>> {
+        let finished = self
+            .active
+            .as_ref()
+            .is_some_and(|(active, _, _)| !trace::is_running(active));
+        if !finished {
+            return Ok(None);
+        }
+        self.finish()?;
+        Ok(self.windows.last())
+    }
+
+    // 会话结束时停止仍在进行的 trace
+    pub fn stop(&mut self) -> Result<Option<&CaptureWindow>> {
+        let Some((active, _, _)) = &self.active else {
+            return Ok(None);
+        };
+        trace::stop_trace(active)?;
+        self.finish()?;
+        Ok(self.windows.last())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        let Some((active, start, trigger)) = self.active.take() else {
+            return Ok(());
+        };
+        // 结束时间不晚于配置的 duration，轮询间隔带来的延迟不计入窗口
+        let mut end = Local::now();
+        if let Some(duration) = self
+            .options
+            .duration
+            .and_then(|d| chrono::Duration::from_std(d).ok())
+        {
+            end = end.min(start + duration);
+        }
+        let path = trace::pull_trace(&active, &self.output_dir)?;
+        self.windows.push(CaptureWindow {
+            kind: "Perfetto",
+            start,
+            end,
+            trigger,
+            path: Some(path),
+        });
+        Ok(())
+    }
+}
+
+// perfetto trace 的启动时机
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PerfettoTrigger {
+    /// 监控开始时抓取一次
+    Start,
+    /// 出现异常时抓取，最多 5 次
+    Anomaly,
+}
+
+// --perfetto：按触发方式抓取 trace，窗口标记在 CPU 图表上
+pub struct PerfettoCollector {
+    recorder: PerfettoRecorder,
+    trigger: PerfettoTrigger,
+    config: String,
+    duration: Duration,
+}
+
+impl PerfettoCollector {
+    pub fn start(
+        package: &str,
+        config: &str,
+        duration: Duration,
+        trigger: PerfettoTrigger,
+    ) -> Result<Self> {
+        let options = TraceOptions {
+            config: config.to_string(),
+            duration: Some(duration),
+            buffer_mb: 64,
+            ..Default::default()
+        };
+        let dir = OutputLayout::session(package)?.dir(Category::Perfetto);
+        Ok(Self {
+            recorder: PerfettoRecorder::new(options, dir)?,
+            trigger,
+            config: config.to_string(),
+            duration,
+        })
+    }
+}
+
+impl Collector for PerfettoCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        let tick = ctx.tick;
+        match self.recorder.poll() {
+            Ok(Some(window)) => console!(
+                "[{}] Perfetto trace pulled: {}",
+                tick.timestamp.format("%H:%M:%S").to_string().blue(),
+                window_path(window)
+            ),
+            Ok(None) => {}
+            Err(e) => console!("Failed to pull Perfetto trace: {}", e),
+        }
+        let trigger = match self.trigger {
+            PerfettoTrigger::Start if self.recorder.attempts() == 0 => {
+                Some("Session start".to_string())
+            }
+            PerfettoTrigger::Anomaly if self.recorder.attempts() < MAX_TRIGGERED_TRACES => {
+                ctx.anomaly.take()
+            }
+            _ => None,
+        };
+        if let Some(trigger) = trigger.filter(|_| !self.recorder.is_active()) {
+            match self.recorder.start(&trigger) {
+                Ok(_) => console!(
+                    "[{}] Perfetto trace started ({}, {}s): {}",
+                    tick.timestamp.format("%H:%M:%S").to_string().blue(),
+                    self.config,
+                    self.duration.as_secs(),
+                    trigger.yellow()
+                ),
+                Err(e) => console!("Failed to start Perfetto trace: {}", e),
+            }
+        }
+        Ok(())
+    }
+
+    // 停止仍在进行的 trace 并拉取
+    fn stop(&mut self) {
+        match self.recorder.stop() {
+            Ok(Some(window)) => {
+                console!("Perfetto trace stopped and pulled: {}", window_path(window))
+            }
+            Ok(None) => {}
+            Err(e) => console!("Failed to stop Perfetto trace: {}", e),
+        }
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        report_windows(
+            "Perfetto traces:",
+            Category::Perfetto,
+            self.recorder.windows(),
+            ctx,
+        );
+    }
+
+    fn cpu_overlay(&self, overlay: &mut CpuChartOverlay) {
+        overlay.markers.extend(markers(self.recorder.windows()));
+    }
+}
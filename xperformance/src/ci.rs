@@ -0,0 +1,143 @@
+use crate::stats::Summary;
+use crate::threshold::Violation;
+use crate::utils;
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+// 会话期间的 CPU 和 PSS 峰值，没有对应数据时为 None
+#[derive(Debug, Default, Serialize)]
+pub struct Peaks {
+    pub cpu_percent: Option<f64>,
+    pub cpu_at: Option<String>,
+    pub pss_kb: Option<u64>,
+    pub pss_at: Option<String>,
+}
+
+// 一项 --max-* 超限
+#[derive(Debug, Serialize)]
+pub struct CiViolation {
+    pub metric: &'static str,
+    pub flag: &'static str,
+    pub limit: f64,
+    pub first_at: String,
+    pub worst: f64,
+    pub worst_at: String,
+    pub samples: u32,
+    pub exit_code: i32,
+}
+
+impl From<&Violation> for CiViolation {
+    fn from(v: &Violation) -> Self {
+        Self {
+            metric: v.limit.as_str(),
+            flag: v.limit.flag(),
+            limit: v.max,
+            first_at: v.first_at.to_rfc3339(),
+            worst: v.worst,
+            worst_at: v.worst_at.to_rfc3339(),
+            samples: v.samples,
+            exit_code: v.limit.exit_code(),
+        }
+    }
+}
+
+// --ci 退出时输出到 stdout 的唯一一个 JSON 对象，error 不为空时其余字段可能不完整
+#[derive(Debug, Default, Serialize)]
+pub struct CiReport {
+    pub package: String,
+    pub device: Option<String>,
+    pub started: Option<String>,
+    pub ended: String,
+    pub duration_secs: f64,
+    pub samples: u64,
+    pub peaks: Peaks,
+    // 预热之后的统计摘要（均值、中位数、p90/p95/p99 等），键为指标名
+    pub metrics: BTreeMap<String, Summary>,
+    pub restart_count: u32,
+    pub violations: Vec<CiViolation>,
+    pub exit_code: i32,
+    pub session_dir: Option<String>,
+    // 会话目录下的所有输出文件（CSV、图表、归档）
+    pub artifacts: Vec<String>,
+    pub error: Option<String>,
+}
+
+impl CiReport {
+    pub fn new(package: &str, started: Option<DateTime<Local>>) -> Self {
+        let ended = Local::now();
+        Self {
+            package: package.to_string(),
+            started: started.map(|t| t.to_rfc3339()),
+            ended: ended.to_rfc3339(),
+            duration_secs: started.map_or(0.0, |t| (ended - t).num_milliseconds() as f64 / 1000.0),
+            ..Default::default()
+        }
+    }
+
+    // 记录会话目录并列出其中的文件
+    pub fn set_session_dir(&mut self, dir: &Path) {
+        let mut artifacts = Vec::new();
+        collect_files(dir, &mut artifacts);
+        artifacts.sort();
+        self.artifacts = artifacts;
+        self.session_dir = Some(dir.display().to_string());
+    }
+
+    // 单行 JSON，直接写到 stdout，不经过 console_line
+    pub fn print(&self) {
+        match serde_json::to_string(self) {
+            Ok(json) => println!("{}", json),
+            Err(e) => eprintln!("Failed to serialize CI report: {}", e),
+        }
+    }
+}
+
+// 监控未能完成时也输出一个 JSON 对象，并以退出码 1 结束
+pub fn report_error(package: &str, error: &str) -> ! {
+    let mut report = CiReport::new(package, None);
+    report.device = utils::adb_serial();
+    report.exit_code = 1;
+    report.error = Some(error.to_string());
+    report.print();
+    std::process::exit(1);
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<String>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_files(&path, files);
+        } else {
+            files.push(path.display().to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_lists_artifacts() {
+        let dir = std::env::temp_dir().join(format!("xperformance_ci_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("cpu")).unwrap();
+        std::fs::write(dir.join("session.json"), "{}").unwrap();
+        std::fs::write(dir.join("cpu").join("app_cpu_data.csv"), "").unwrap();
+
+        let mut report = CiReport::new("com.example.app", Some(Local::now()));
+        report.set_session_dir(&dir);
+        let json: serde_json::Value =
+            serde_json::from_str(&serde_json::to_string(&report).unwrap()).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(json["package"], "com.example.app");
+        assert_eq!(json["artifacts"].as_array().unwrap().len(), 2);
+        assert!(json["error"].is_null());
+        assert!(json["peaks"]["cpu_percent"].is_null());
+    }
+}
@@ -0,0 +1,406 @@
+use crate::chart::{self, TimeSeriesData};
+use crate::cpu::ThreadCpuInfo;
+use crate::layout::Category;
+use crate::percore::HOT_THREADS;
+use crate::recorder::{Collector, FinishContext, TickContext};
+use crate::utils;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use colored::*;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// 每个核心一行：编号、cpu_capacity（调度器使用的相对算力，最大 1024）、硬件最大频率（kHz），读不到时为 -
+const TOPOLOGY_COMMAND: &str = "cd /sys/devices/system/cpu && for c in cpu[0-9]*; do echo \"${c#cpu} $(cat $c/cpu_capacity 2>/dev/null || echo -) $(cat $c/cpufreq/cpuinfo_max_freq 2>/dev/null || echo -)\"; done";
+
+// 算力相同的一组核心
+#[derive(Debug, Clone)]
+pub struct Cluster {
+    pub name: String,
+    pub cpus: Vec<u32>,
+}
+
+impl Cluster {
+    // 图例和 CSV 列名，如 big (cpu4-7)
+    pub fn label(&self) -> String {
+        let contiguous = self.cpus.windows(2).all(|pair| pair[1] == pair[0] + 1);
+        match self.cpus.as_slice() {
+            [] => self.name.clone(),
+            [only] => format!("{} (cpu{})", self.name, only),
+            [first, .., last] if contiguous => format!("{} (cpu{}-{})", self.name, first, last),
+            cpus => {
+                let cpus: Vec<String> = cpus.iter().map(|c| c.to_string()).collect();
+                // 用 / 分隔，标签也用作 CSV 列名
+                format!("{} (cpu{})", self.name, cpus.join("/"))
+            }
+        }
+    }
+}
+
+// 按算力从低到高排列的簇
+#[derive(Debug, Clone, Default)]
+pub struct ClusterTopology {
+    pub clusters: Vec<Cluster>,
+}
+
+impl ClusterTopology {
+    pub fn cluster_of(&self, core: u32) -> Option<usize> {
+        self.clusters.iter().position(|c| c.cpus.contains(&core))
+    }
+
+    // 只有一种核心时没有大小核之分
+    pub fn is_heterogeneous(&self) -> bool {
+        self.clusters.len() > 1
+    }
+
+    pub fn describe(&self) -> String {
+        let labels: Vec<String> = self.clusters.iter().map(Cluster::label).collect();
+        labels.join(", ")
+    }
+}
+
+// 从低到高的簇名：两簇为 little/big，三簇为 little/mid/big，更多时最高一簇为 prime
+fn cluster_names(count: usize) -> Vec<String> {
+    match count {
+        0 => Vec::new(),
+        1 => vec!["all".to_string()],
+        2 => vec!["little".to_string(), "big".to_string()],
+        3 => vec!["little".to_string(), "mid".to_string(), "big".to_string()],
+        _ => {
+            let mids = count - 3;
+            let mut names = vec!["little".to_string()];
+            if mids == 1 {
+                names.push("mid".to_string());
+            } else {
+                names.extend((1..=mids).map(|i| format!("mid{}", i)));
+            }
+            names.push("big".to_string());
+            names.push("prime".to_string());
+            names
+        }
+    }
+}
+
+// 优先按 cpu_capacity 分组，有核心读不到时按最大频率分组
+pub fn parse_topology(output: &str) -> ClusterTopology {
+    let cores: Vec<(u32, Option<u64>, Option<u64>)> = output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let core = fields.next()?.parse().ok()?;
+            let capacity = fields.next().and_then(|v| v.parse().ok());
+            let max_freq = fields.next().and_then(|v| v.parse().ok());
+            Some((core, capacity, max_freq))
+        })
+        .collect();
+    let by_capacity = cores.iter().all(|(_, capacity, _)| capacity.is_some());
+
+    let mut groups: BTreeMap<u64, Vec<u32>> = BTreeMap::new();
+    for (core, capacity, max_freq) in &cores {
+        let key = if by_capacity { *capacity } else { *max_freq };
+        groups.entry(key.unwrap_or(0)).or_default().push(*core);
+    }
+    let names = cluster_names(groups.len());
+    let clusters = groups
+        .into_values()
+        .zip(names)
+        .map(|(mut cpus, name)| {
+            cpus.sort_unstable();
+            Cluster { name, cpus }
+        })
+        .collect();
+    ClusterTopology { clusters }
+}
+
+pub fn query_topology() -> Result<ClusterTopology> {
+    let output = utils::run_adb_command(&["shell", TOPOLOGY_COMMAND])?;
+    let topology = parse_topology(&output);
+    if topology.clusters.is_empty() {
+        anyhow::bail!("No CPU topology readable: {}", output.trim());
+    }
+    Ok(topology)
+}
+
+// 一次采样中进程在各簇上的 CPU，按线程最近运行的核心归属
+#[derive(Debug, Clone)]
+pub struct ClusterSample {
+    pub timestamp: DateTime<Local>,
+    pub cpu: Vec<f64>,
+}
+
+// 累计每个线程在各簇上的 CPU；pidstat 只给出采样时所在的核心，整段间隔的 CPU 都记到该核心所在的簇
+pub struct ClusterResidency {
+    topology: ClusterTopology,
+    samples: Vec<ClusterSample>,
+    // (TID, 线程名) -> 各簇累计 CPU
+    threads: BTreeMap<(String, String), Vec<f64>>,
+}
+
+impl ClusterResidency {
+    pub fn new(topology: ClusterTopology) -> Self {
+        Self {
+            topology,
+            samples: Vec::new(),
+            threads: BTreeMap::new(),
+        }
+    }
+
+    pub fn topology(&self) -> &ClusterTopology {
+        &self.topology
+    }
+
+    pub fn samples(&self) -> &[ClusterSample] {
+        &self.samples
+    }
+
+    pub fn record(
+        &mut self,
+        timestamp: DateTime<Local>,
+        threads: &[ThreadCpuInfo],
+    ) -> Option<&ClusterSample> {
+        let mut cpu = vec![0.0; self.topology.clusters.len()];
+        let mut attributed = false;
+        for thread in threads.iter().filter(|t| t.cpu_usage > 0.0) {
+            let Some(cluster) = thread.core.and_then(|core| self.topology.cluster_of(core)) else {
+                continue;
+            };
+            let usage = thread.cpu_usage as f64;
+            cpu[cluster] += usage;
+            self.threads
+                .entry((thread.tid.clone(), thread.name.clone()))
+                .or_insert_with(|| vec![0.0; cpu.len()])[cluster] += usage;
+            attributed = true;
+        }
+        // pidstat 没有 CPU 列时不记录
+        if !attributed {
+            return None;
+        }
+        self.samples.push(ClusterSample { timestamp, cpu });
+        self.samples.last()
+    }
+
+    // 按累计 CPU 降序的线程，以及各簇所占比例（%）
+    pub fn thread_shares(&self) -> Vec<(String, String, Vec<f64>)> {
+        let mut shares: Vec<(String, String, f64, Vec<f64>)> = self
+            .threads
+            .iter()
+            .map(|((tid, name), cpu)| {
+                let total: f64 = cpu.iter().sum();
+                let shares = cpu
+                    .iter()
+                    .map(|v| v * 100.0 / total.max(f64::EPSILON))
+                    .collect();
+                (tid.clone(), name.clone(), total, shares)
+            })
+            .collect();
+        shares.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap_or(std::cmp::Ordering::Equal));
+        shares
+            .into_iter()
+            .map(|(tid, name, _, shares)| (tid, name, shares))
+            .collect()
+    }
+}
+
+// 导出各簇 CPU 的逐次采样 CSV 和堆叠面积图，以及每个线程的簇分布 CSV
+pub fn export_cluster_data(
+    dir: &Path,
+    package: &str,
+    residency: &ClusterResidency,
+) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let mut paths = Vec::new();
+    let labels: Vec<String> = residency
+        .topology
+        .clusters
+        .iter()
+        .map(Cluster::label)
+        .collect();
+
+    let csv_path = dir.join(format!("{}_cluster_residency.csv", package));
+    let mut file = std::fs::File::create(&csv_path)?;
+    let header: Vec<String> = labels.iter().map(|l| format!("{} (%)", l)).collect();
+    writeln!(file, "Timestamp,{}", header.join(","))?;
+    for sample in &residency.samples {
+        let values: Vec<String> = sample.cpu.iter().map(|v| format!("{:.1}", v)).collect();
+        writeln!(
+            file,
+            "{},{}",
+            sample.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            values.join(",")
+        )?;
+    }
+    paths.push(csv_path);
+
+    let thread_path = dir.join(format!("{}_thread_clusters.csv", package));
+    let mut file = std::fs::File::create(&thread_path)?;
+    let header: Vec<String> = labels.iter().map(|l| format!("{} Share (%)", l)).collect();
+    writeln!(file, "TID,Thread,{}", header.join(","))?;
+    for (tid, name, shares) in residency.thread_shares() {
+        let values: Vec<String> = shares.iter().map(|v| format!("{:.1}", v)).collect();
+        writeln!(
+            file,
+            "{},{},{}",
+            tid,
+            name.replace(',', " "),
+            values.join(",")
+        )?;
+    }
+    paths.push(thread_path);
+
+    if residency.samples.len() > 1 {
+        // 自下而上从小核到大核
+        let series: Vec<TimeSeriesData> = labels
+            .iter()
+            .enumerate()
+            .map(|(i, label)| {
+                TimeSeriesData::new(
+                    label.as_str(),
+                    residency
+                        .samples
+                        .iter()
+                        .map(|s| (s.timestamp, s.cpu[i]))
+                        .collect(),
+                )
+            })
+            .collect();
+        let path = dir.join(chart::stamped_file_name(&format!(
+            "{}_cluster_residency.png",
+            package
+        )));
+        chart::draw_stacked_area_chart(
+            &path,
+            &format!("CPU Cluster Residency - {}", package),
+            "Process CPU (%)",
+            &series,
+        )?;
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
+
+// --per-core：把热点线程的 CPU 按运行的核心归属到大小核簇
+pub struct ClusterCollector {
+    residency: ClusterResidency,
+}
+
+impl ClusterCollector {
+    // 读不到拓扑或所有核心算力相同时不启用
+    pub fn start(verbose: bool) -> Option<Self> {
+        match query_topology() {
+            Ok(topology) if topology.is_heterogeneous() => {
+                console!("CPU clusters: {}", topology.describe().cyan());
+                Some(Self {
+                    residency: ClusterResidency::new(topology),
+                })
+            }
+            Ok(_) => {
+                console!("All cores have the same capacity; skipping cluster residency");
+                None
+            }
+            Err(e) => {
+                if verbose {
+                    console!("Failed to read CPU cluster topology: {}", e);
+                }
+                None
+            }
+        }
+    }
+}
+
+impl Collector for ClusterCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        let tick = ctx.tick;
+        let Some(sample) = self
+            .residency
+            .record(tick.timestamp, &ctx.sample.threads)
+            .cloned()
+        else {
+            return Ok(());
+        };
+        for (cluster, cpu) in self.residency.topology().clusters.iter().zip(&sample.cpu) {
+            ctx.table
+                .record(&tick, &format!("CPU on {} (%)", cluster.name), *cpu);
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        if self.residency.samples().is_empty() {
+            return;
+        }
+        console!("Hot thread cluster residency (share of CPU time):");
+        let clusters = &self.residency.topology().clusters;
+        for (tid, name, shares) in self.residency.thread_shares().iter().take(HOT_THREADS) {
+            let shares: Vec<String> = clusters
+                .iter()
+                .zip(shares)
+                .filter(|(_, share)| **share > 0.0)
+                .map(|(cluster, share)| format!("{} {:.0}%", cluster.name, share))
+                .collect();
+            console!(
+                "  {} (TID: {}): {}",
+                name.cyan(),
+                tid.yellow(),
+                shares.join(", ")
+            );
+        }
+        match export_cluster_data(&ctx.layout.dir(Category::Cpu), ctx.package, &self.residency) {
+            Ok(paths) => {
+                for path in paths {
+                    console!("✓ Cluster residency exported: {}", path.display());
+                }
+            }
+            Err(e) => console!("Failed to export cluster residency: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_topology_groups_by_capacity() {
+        let topology = parse_topology("0 325 1785600\n1 325 1785600\n2 325 1785600\n3 325 1785600\n4 825 2419200\n5 825 2419200\n6 825 2419200\n7 1024 3187200\n");
+        assert!(topology.is_heterogeneous());
+        assert_eq!(
+            topology.describe(),
+            "little (cpu0-3), mid (cpu4-6), big (cpu7)"
+        );
+        assert_eq!(topology.cluster_of(5), Some(1));
+        assert_eq!(topology.cluster_of(8), None);
+
+        // 读不到 cpu_capacity 时按最大频率分组
+        let topology = parse_topology("0 - 1800000\n1 - 1800000\n2 325 2400000\n3 - 2400000\n");
+        assert_eq!(topology.describe(), "little (cpu0-1), big (cpu2-3)");
+    }
+
+    #[test]
+    fn residency_attributes_cpu_to_clusters() {
+        let mut residency =
+            ClusterResidency::new(parse_topology("0 325 -\n1 325 -\n2 1024 -\n3 1024 -\n"));
+        let thread = |tid: &str, cpu_usage: f32, core: Option<u32>| ThreadCpuInfo {
+            tid: tid.to_string(),
+            cpu_usage,
+            name: format!("Thread-{}", tid),
+            timestamp: None,
+            core,
+        };
+        let now = Local::now();
+        // pidstat 没有 CPU 列
+        assert!(residency.record(now, &[thread("1", 40.0, None)]).is_none());
+        let sample = residency
+            .record(
+                now,
+                &[thread("1", 40.0, Some(3)), thread("2", 10.0, Some(0))],
+            )
+            .unwrap();
+        assert_eq!(sample.cpu, [10.0, 40.0]);
+        residency.record(now, &[thread("1", 40.0, Some(1))]);
+        let shares = residency.thread_shares();
+        assert_eq!(shares[0].0, "1");
+        assert_eq!(shares[0].2, [50.0, 50.0]);
+    }
+}
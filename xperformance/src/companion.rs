@@ -0,0 +1,257 @@
+use crate::chart::{self, TimeSeriesData};
+use crate::cpu::CpuScale;
+use crate::layout::Category;
+use crate::process;
+use crate::recorder::{Collector, FinishContext, TickContext};
+use crate::utils;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use colored::*;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// 与应用一同监控的系统进程（如 system_server、surfaceflinger），应用引起的负载常体现在这些进程上
+pub struct Companion {
+    pub name: String,
+    // 上次读数的 PID、CPU ticks 和时间，PID 变化时重新建立基线
+    last: Option<(String, u64, DateTime<Local>)>,
+    pub cpu: Vec<(DateTime<Local>, f64)>,
+    pub memory: Vec<(DateTime<Local>, f64)>,
+}
+
+impl Companion {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            last: None,
+            cpu: Vec::new(),
+            memory: Vec::new(),
+        }
+    }
+
+    // 一次 adb 调用读取 stat，返回 CPU（首次采样或重启后为 None）和 RSS（KB）
+    pub fn sample(
+        &mut self,
+        timestamp: DateTime<Local>,
+        scale: CpuScale,
+        cores: u32,
+    ) -> Result<(Option<f64>, f64)> {
+        let output = utils::run_adb_command(&[
+            "shell",
+            &format!("cat /proc/$(pidof -s {})/stat", self.name),
+        ])?;
+        let now = crate::replay::now();
+        let stat = output
+            .lines()
+            .find_map(process::parse_proc_stat)
+            .ok_or_else(|| anyhow::format_err!("Process not found: {}", self.name))?;
+
+        let cpu = match &self.last {
+            Some((pid, ticks, at)) if *pid == stat.pid => process::cpu_percent(
+                *ticks,
+                stat.cpu_ticks,
+                (now - *at).num_milliseconds() as f64 / 1000.0,
+            )
+            .map(|per_core| scale.normalize(per_core as f32, cores) as f64),
+            _ => None,
+        };
+        self.last = Some((stat.pid, stat.cpu_ticks, now));
+
+        if let Some(cpu) = cpu {
+            self.cpu.push((timestamp, cpu));
+        }
+        self.memory.push((timestamp, stat.rss_kb));
+        Ok((cpu, stat.rss_kb))
+    }
+
+    pub fn cpu_column(&self) -> String {
+        format!("{} CPU (%)", self.name)
+    }
+
+    pub fn memory_column(&self) -> String {
+        format!("{} RSS (KB)", self.name)
+    }
+}
+
+fn mean(points: &[(DateTime<Local>, f64)]) -> Option<f64> {
+    if points.is_empty() {
+        return None;
+    }
+    Some(points.iter().map(|(_, v)| v).sum::<f64>() / points.len() as f64)
+}
+
+fn peak(points: &[(DateTime<Local>, f64)]) -> Option<f64> {
+    points.iter().map(|(_, v)| *v).reduce(f64::max)
+}
+
+// 应用与系统进程叠加的 CPU、内存图表及汇总 CSV
+pub fn write_overlay(
+    dir: &Path,
+    package: &str,
+    app_cpu: &[(DateTime<Local>, f64)],
+    app_memory: &[(DateTime<Local>, f64)],
+    companions: &[Companion],
+    scale: CpuScale,
+) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let mut paths = Vec::new();
+
+    let summary_path = dir.join(format!("{}_companions_summary.csv", package));
+    let mut file = std::fs::File::create(&summary_path)?;
+    writeln!(
+        file,
+        "Process,Mean CPU (%),Peak CPU (%),Mean Memory (KB),Peak Memory (KB),Memory Metric"
+    )?;
+    let format = |value: Option<f64>| value.map(|v| format!("{:.1}", v)).unwrap_or_default();
+    writeln!(
+        file,
+        "{},{},{},{},{},PSS",
+        package,
+        format(mean(app_cpu)),
+        format(peak(app_cpu)),
+        format(mean(app_memory)),
+        format(peak(app_memory))
+    )?;
+    for companion in companions {
+        writeln!(
+            file,
+            "{},{},{},{},{},RSS",
+            companion.name,
+            format(mean(&companion.cpu)),
+            format(peak(&companion.cpu)),
+            format(mean(&companion.memory)),
+            format(peak(&companion.memory))
+        )?;
+    }
+    paths.push(summary_path);
+
+    let cpu_y_desc = format!("CPU Usage ({})", scale.unit());
+    for (app_points, companion_points, app_label, file_name, title, y_desc) in [
+        (
+            app_cpu,
+            companions
+                .iter()
+                .map(|c| (c.name.clone(), c.cpu.clone()))
+                .collect::<Vec<_>>(),
+            package.to_string(),
+            format!("{}_companions_cpu.png", package),
+            "CPU Usage: App vs System Processes",
+            cpu_y_desc.as_str(),
+        ),
+        (
+            app_memory,
+            companions
+                .iter()
+                .map(|c| (format!("{} (RSS)", c.name), c.memory.clone()))
+                .collect::<Vec<_>>(),
+            format!("{} (PSS)", package),
+            format!("{}_companions_memory.png", package),
+            "Memory: App vs System Processes",
+            "Memory (KB)",
+        ),
+    ] {
+        let series: Vec<TimeSeriesData> =
+            std::iter::once(TimeSeriesData::new(app_label, app_points.to_vec()))
+                .chain(
+                    companion_points
+                        .into_iter()
+                        .map(|(label, points)| TimeSeriesData::new(label, points)),
+                )
+                .filter(|series| series.points.len() > 1)
+                .collect();
+        if series.is_empty() {
+            continue;
+        }
+        let path = dir.join(chart::stamped_file_name(&file_name));
+        chart::draw_time_series_chart(&path, &format!("{} - {}", title, package), y_desc, &series)?;
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
+
+// 同时监控的系统进程，与应用数据写入同一 Tick，结束时输出叠加图表
+pub struct CompanionCollector {
+    companions: Vec<Companion>,
+    scale: CpuScale,
+    cores: u32,
+    // 应用自身的 CPU 和 PSS，用于叠加图表
+    app_cpu: Vec<(DateTime<Local>, f64)>,
+    app_memory: Vec<(DateTime<Local>, f64)>,
+}
+
+impl CompanionCollector {
+    pub fn new(names: &[String], scale: CpuScale, cores: u32) -> Self {
+        console!("Also monitoring: {}", names.join(", ").cyan());
+        Self {
+            companions: names.iter().map(|name| Companion::new(name)).collect(),
+            scale,
+            cores,
+            app_cpu: Vec::new(),
+            app_memory: Vec::new(),
+        }
+    }
+}
+
+impl Collector for CompanionCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        let tick = ctx.tick;
+        if let Some(cpu) = ctx.sample.cpu {
+            self.app_cpu.push((tick.timestamp, cpu as f64));
+        }
+        if let Some(details) = &ctx.sample.memory {
+            self.app_memory
+                .push((tick.timestamp, details.total_pss as f64));
+        }
+
+        let mut notes = Vec::new();
+        for companion in &mut self.companions {
+            match companion.sample(tick.timestamp, self.scale, self.cores) {
+                Ok((cpu, rss_kb)) => {
+                    if let Some(cpu) = cpu {
+                        ctx.table.record(&tick, &companion.cpu_column(), cpu);
+                    }
+                    ctx.table.record(&tick, &companion.memory_column(), rss_kb);
+                    notes.push(format!(
+                        "{} {}, {:.0} MB",
+                        companion.name.cyan(),
+                        cpu.map(|c| format!("{:.1}%", c))
+                            .unwrap_or_else(|| "-".to_string()),
+                        rss_kb / 1024.0
+                    ));
+                }
+                Err(e) => {
+                    if ctx.verbose {
+                        console!("Failed to sample {}: {}", companion.name, e);
+                    }
+                }
+            }
+        }
+        if !notes.is_empty() {
+            console!(
+                "[{}] {}",
+                tick.timestamp.format("%H:%M:%S").to_string().blue(),
+                notes.join(" | ")
+            );
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        match write_overlay(
+            &ctx.layout.dir(Category::Companions),
+            ctx.package,
+            &self.app_cpu,
+            &self.app_memory,
+            &self.companions,
+            self.scale,
+        ) {
+            Ok(paths) => {
+                for path in paths {
+                    console!("✓ Companion data exported: {}", path.display());
+                }
+            }
+            Err(e) => console!("Failed to export companion data: {}", e),
+        }
+    }
+}
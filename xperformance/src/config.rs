@@ -0,0 +1,80 @@
+use anyhow::{Context, Result};
+use clap::Command;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+// 未指定 --config 时在当前目录查找
+pub const DEFAULT_CONFIG: &str = "xperf.toml";
+
+// 命令行中的 --config <文件> 或 --config=<文件>，否则为当前目录下存在的 xperf.toml
+pub fn locate(args: &[OsString]) -> Option<PathBuf> {
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        let arg = arg.to_string_lossy();
+        if arg == "--config" {
+            return iter.next().map(PathBuf::from);
+        }
+        if let Some(path) = arg.strip_prefix("--config=") {
+            return Some(PathBuf::from(path));
+        }
+    }
+    Path::new(DEFAULT_CONFIG)
+        .exists()
+        .then(|| PathBuf::from(DEFAULT_CONFIG))
+}
+
+// 配置文件中的键即长参数名（下划线和连字符均可），转换为放在命令行参数之前的参数，
+// 这样命令行中重复指定的参数会覆盖配置文件的值
+pub fn to_args(path: &Path, command: &Command) -> Result<Vec<OsString>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config {}", path.display()))?;
+    let table: toml::Table = text
+        .parse()
+        .with_context(|| format!("Invalid config {}", path.display()))?;
+    table_to_args(table, path, command)
+}
+
+// 已解析的选项表转换为参数，path 只用于错误信息；场景脚本的 [monitor] 表也用这种格式
+pub fn table_to_args(table: toml::Table, path: &Path, command: &Command) -> Result<Vec<OsString>> {
+    let mut args = Vec::new();
+    for (key, value) in table {
+        let name = key.replace('_', "-");
+        let arg = command
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(name.as_str()) && name != "config")
+            .ok_or_else(|| anyhow::format_err!("Unknown option '{}' in {}", key, path.display()))?;
+        let flag = format!("--{}", name);
+        let takes_value = arg.get_action().takes_values();
+        let values = match value {
+            toml::Value::Array(values) => values,
+            value => vec![value],
+        };
+        for value in values {
+            let text = match value {
+                toml::Value::Boolean(enabled) if !takes_value => {
+                    if enabled {
+                        args.push(OsString::from(&flag));
+                    }
+                    continue;
+                }
+                toml::Value::String(text) if takes_value => text,
+                toml::Value::Integer(number) if takes_value => number.to_string(),
+                toml::Value::Float(number) if takes_value => number.to_string(),
+                value => anyhow::bail!(
+                    "Option '{}' in {} expects {}, got {}",
+                    key,
+                    path.display(),
+                    if takes_value {
+                        "a value"
+                    } else {
+                        "true or false"
+                    },
+                    value
+                ),
+            };
+            args.push(OsString::from(&flag));
+            args.push(OsString::from(text));
+        }
+    }
+    Ok(args)
+}
@@ -0,0 +1,634 @@
+use crate::chart::{self, TimeSeriesData};
+use crate::layout::Category;
+use crate::recorder::{Collector, FinishContext, TickContext};
+use crate::utils;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use colored::*;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// 累计计数器，ADB 短暂断开后可根据前后两次读数补回断开期间的总量
+// /proc/<pid>/io 通常需要 root 或 debuggable 应用，读取失败时为 None
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CumulativeCounters {
+    pub read_bytes: Option<u64>,
+    pub write_bytes: Option<u64>,
+    pub syscr: Option<u64>,
+    pub syscw: Option<u64>,
+    pub rx_bytes: Option<u64>,
+    pub tx_bytes: Option<u64>,
+    // /proc/<pid>/status 中的 voluntary_ctxt_switches 和 nonvoluntary_ctxt_switches
+    pub voluntary_switches: Option<u64>,
+    pub involuntary_switches: Option<u64>,
+    // /proc/<pid>/stat 中的 minflt 和 majflt
+    pub minor_faults: Option<u64>,
+    pub major_faults: Option<u64>,
+}
+
+// 两次读数之间的增量（KB）
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CounterDelta {
+    pub read_kb: Option<f64>,
+    pub write_kb: Option<f64>,
+    pub syscr: Option<f64>,
+    pub syscw: Option<f64>,
+    pub rx_kb: Option<f64>,
+    pub tx_kb: Option<f64>,
+    pub voluntary_switches: Option<f64>,
+    pub involuntary_switches: Option<f64>,
+    pub minor_faults: Option<f64>,
+    pub major_faults: Option<f64>,
+}
+
+impl CounterDelta {
+    pub fn columns(&self) -> [(&'static str, Option<f64>); 10] {
+        [
+            ("Disk Read (KB)", self.read_kb),
+            ("Disk Write (KB)", self.write_kb),
+            ("Read Syscalls", self.syscr),
+            ("Write Syscalls", self.syscw),
+            ("Net Rx (KB)", self.rx_kb),
+            ("Net Tx (KB)", self.tx_kb),
+            ("Voluntary Switches", self.voluntary_switches),
+            ("Involuntary Switches", self.involuntary_switches),
+            ("Minor Faults", self.minor_faults),
+            ("Major Faults", self.major_faults),
+        ]
+    }
+}
+
+impl CumulativeCounters {
+    // 计数器回绕或进程重启导致变小时视为不可恢复
+    pub fn delta_since(&self, earlier: &CumulativeCounters) -> CounterDelta {
+        let delta = |now: Option<u64>, before: Option<u64>| match (now, before) {
+            (Some(now), Some(before)) if now >= before => Some((now - before) as f64),
+            _ => None,
+        };
+        let kb = |now, before| delta(now, before).map(|bytes| bytes / 1024.0);
+        CounterDelta {
+            read_kb: kb(self.read_bytes, earlier.read_bytes),
+            write_kb: kb(self.write_bytes, earlier.write_bytes),
+            syscr: delta(self.syscr, earlier.syscr),
+            syscw: delta(self.syscw, earlier.syscw),
+            rx_kb: kb(self.rx_bytes, earlier.rx_bytes),
+            tx_kb: kb(self.tx_bytes, earlier.tx_bytes),
+            voluntary_switches: delta(self.voluntary_switches, earlier.voluntary_switches),
+            involuntary_switches: delta(self.involuntary_switches, earlier.involuntary_switches),
+            minor_faults: delta(self.minor_faults, earlier.minor_faults),
+            major_faults: delta(self.major_faults, earlier.major_faults),
+        }
+    }
+}
+
+const SECTION_SEPARATOR: &str = "---";
+
+// 一次 adb 调用读取进程 IO、上下文切换次数、缺页次数和所在网络命名空间的收发字节数
+pub fn sample_counters(pid: &str) -> Result<CumulativeCounters> {
+    let output = utils::run_adb_command(&[
+        "shell",
+        &format!(
+            "cat /proc/{0}/io 2>/dev/null; echo {1}; cat /proc/{0}/net/dev 2>/dev/null; echo {1}; cat /proc/{0}/status 2>/dev/null; echo {1}; cat /proc/{0}/stat 2>/dev/null",
+            pid, SECTION_SEPARATOR
+        ),
+    ])?;
+    parse_counters(&output)
+}
+
+fn parse_counters(output: &str) -> Result<CumulativeCounters> {
+    let sections: Vec<&str> = output.splitn(4, SECTION_SEPARATOR).collect();
+    let [io, net, status, stat] = sections[..] else {
+        anyhow::bail!("Unexpected counter output: {}", output.trim());
+    };
+
+    let mut counters = CumulativeCounters::default();
+    for line in io.lines() {
+        match line.split_once(':') {
+            Some(("read_bytes", value)) => counters.read_bytes = value.trim().parse().ok(),
+            Some(("write_bytes", value)) => counters.write_bytes = value.trim().parse().ok(),
+            Some(("syscr", value)) => counters.syscr = value.trim().parse().ok(),
+            Some(("syscw", value)) => counters.syscw = value.trim().parse().ok(),
+            _ => {}
+        }
+    }
+    for line in status.lines() {
+        match line.split_once(':') {
+            Some(("voluntary_ctxt_switches", value)) => {
+                counters.voluntary_switches = value.trim().parse().ok()
+            }
+            Some(("nonvoluntary_ctxt_switches", value)) => {
+                counters.involuntary_switches = value.trim().parse().ok()
+            }
+            _ => {}
+        }
+    }
+
+    // stat 中 comm 可能含空格，从最后一个 ')' 之后开始，第一个字段是第 3 列（state），minflt 为第 10 列，majflt 为第 12 列
+    if let Some(close) = stat.rfind(')') {
+        let fields: Vec<&str> = stat[close + 1..].split_whitespace().collect();
+        counters.minor_faults = fields.get(7).and_then(|v| v.parse().ok());
+        counters.major_faults = fields.get(9).and_then(|v| v.parse().ok());
+    }
+
+    // net/dev：前两行为表头，每行 "iface: rx_bytes ... (8 列) tx_bytes ..."，忽略 lo
+    let mut rx = 0u64;
+    let mut tx = 0u64;
+    let mut found = false;
+    for line in net.lines().skip(2) {
+        let Some((iface, values)) = line.split_once(':') else {
+            continue;
+        };
+        if iface.trim() == "lo" {
+            continue;
+        }
+        let values: Vec<u64> = values
+            .split_whitespace()
+            .filter_map(|v| v.parse().ok())
+            .collect();
+        if values.len() >= 9 {
+            rx += values[0];
+            tx += values[8];
+            found = true;
+        }
+    }
+    if found {
+        counters.rx_bytes = Some(rx);
+        counters.tx_bytes = Some(tx);
+    }
+
+    Ok(counters)
+}
+
+// 一次计数器读数，以及与同一进程上一次读数之间的增量和间隔秒数
+#[derive(Debug, Clone, Copy)]
+pub struct CounterReading {
+    pub counters: CumulativeCounters,
+    pub delta: Option<CounterDelta>,
+    pub secs: f64,
+}
+
+// 保存上一次读数（附带读取时的 PID），进程重启后的第一次读数没有增量
+#[derive(Debug, Default)]
+pub struct CounterTracker {
+    last: Option<(String, CumulativeCounters, DateTime<Local>)>,
+}
+
+impl CounterTracker {
+    // 读取失败时返回 None，并保留上一次读数
+    pub fn sample(&mut self, pid: &str, timestamp: DateTime<Local>) -> Option<CounterReading> {
+        let counters = sample_counters(pid).ok()?;
+        let (delta, secs) = match &self.last {
+            Some((last_pid, before, at)) if last_pid == pid => (
+                Some(counters.delta_since(before)),
+                (timestamp - *at).num_milliseconds() as f64 / 1000.0,
+            ),
+            _ => (None, 0.0),
+        };
+        self.last = Some((pid.to_string(), counters, timestamp));
+        Some(CounterReading {
+            counters,
+            delta,
+            secs,
+        })
+    }
+}
+
+// 一个采样周期内的磁盘 IO 速率
+#[derive(Debug, Clone, Copy)]
+pub struct IoRate {
+    pub timestamp: DateTime<Local>,
+    pub read_kb_s: f64,
+    pub write_kb_s: f64,
+    pub syscr_s: f64,
+    pub syscw_s: f64,
+}
+
+impl IoRate {
+    // /proc/<pid>/io 不可读时返回 None
+    pub fn from_delta(timestamp: DateTime<Local>, delta: &CounterDelta, secs: f64) -> Option<Self> {
+        if secs <= 0.0 || (delta.read_kb.is_none() && delta.write_kb.is_none()) {
+            return None;
+        }
+        let rate = |value: Option<f64>| value.unwrap_or(0.0) / secs;
+        Some(Self {
+            timestamp,
+            read_kb_s: rate(delta.read_kb),
+            write_kb_s: rate(delta.write_kb),
+            syscr_s: rate(delta.syscr),
+            syscw_s: rate(delta.syscw),
+        })
+    }
+}
+
+// 导出磁盘 IO 速率 CSV 和读写吞吐、系统调用图表
+pub fn export_io_data(dir: &Path, package: &str, rates: &[IoRate]) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let mut paths = Vec::new();
+
+    let csv_path = dir.join(format!("{}_io_data.csv", package));
+    let mut file = std::fs::File::create(&csv_path)?;
+    writeln!(
+        file,
+        "Timestamp,Read (KB/s),Write (KB/s),Read Syscalls (/s),Write Syscalls (/s)"
+    )?;
+    for rate in rates {
+        writeln!(
+            file,
+            "{},{:.2},{:.2},{:.1},{:.1}",
+            rate.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            rate.read_kb_s,
+            rate.write_kb_s,
+            rate.syscr_s,
+            rate.syscw_s
+        )?;
+    }
+    paths.push(csv_path);
+
+    if rates.len() < 2 {
+        return Ok(paths);
+    }
+    let series = |f: fn(&IoRate) -> f64| -> Vec<(DateTime<Local>, f64)> {
+        rates.iter().map(|r| (r.timestamp, f(r))).collect()
+    };
+    for (file_name, title, y_desc, data) in [
+        (
+            "io_throughput.png",
+            "Disk I/O Throughput",
+            "KB/s",
+            [
+                TimeSeriesData::new("Read (KB/s)", series(|r| r.read_kb_s)),
+                TimeSeriesData::new("Write (KB/s)", series(|r| r.write_kb_s)),
+            ],
+        ),
+        (
+            "io_syscalls.png",
+            "I/O Syscalls",
+            "Calls/s",
+            [
+                TimeSeriesData::new("read syscalls/s", series(|r| r.syscr_s)),
+                TimeSeriesData::new("write syscalls/s", series(|r| r.syscw_s)),
+            ],
+        ),
+    ] {
+        let path = dir.join(chart::stamped_file_name(&format!(
+            "{}_{}",
+            package, file_name
+        )));
+        chart::draw_time_series_chart(&path, &format!("{} - {}", title, package), y_desc, &data)?;
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
+
+// --io：每个 Tick 的磁盘 IO 速率
+#[derive(Default)]
+pub struct IoCollector {
+    rates: Vec<IoRate>,
+    checked: bool,
+}
+
+impl Collector for IoCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        let Some(reading) = ctx.counters else {
+            return Ok(());
+        };
+        if !self.checked {
+            self.checked = true;
+            if reading.counters.read_bytes.is_none() {
+                console!(
+                    "{}",
+                    "/proc/<pid>/io is not readable (needs root or a debuggable app); no I/O data"
+                        .yellow()
+                );
+            }
+        }
+        // 缺口结束时的增量覆盖整个断开期间，不算作一个采样周期
+        if ctx.gap_started.is_some() {
+            return Ok(());
+        }
+        let Some(rate) = reading
+            .delta
+            .and_then(|delta| IoRate::from_delta(ctx.tick.timestamp, &delta, reading.secs))
+        else {
+            return Ok(());
+        };
+        console!(
+            "[{}] I/O: read {}, write {}, syscalls r/w {:.0}/{:.0} per s",
+            ctx.tick.timestamp.format("%H:%M:%S"),
+            format!("{:.1} KB/s", rate.read_kb_s).cyan(),
+            format!("{:.1} KB/s", rate.write_kb_s).yellow(),
+            rate.syscr_s,
+            rate.syscw_s
+        );
+        self.rates.push(rate);
+        Ok(())
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        let rates = &self.rates;
+        if rates.is_empty() {
+            return;
+        }
+        let total = |f: fn(&IoRate) -> f64| rates.iter().map(f).sum::<f64>();
+        console!(
+            "Disk I/O: peak read {:.1} KB/s, peak write {:.1} KB/s, mean read {:.1} KB/s, mean write {:.1} KB/s",
+            rates.iter().map(|r| r.read_kb_s).fold(0.0, f64::max),
+            rates.iter().map(|r| r.write_kb_s).fold(0.0, f64::max),
+            total(|r| r.read_kb_s) / rates.len() as f64,
+            total(|r| r.write_kb_s) / rates.len() as f64
+        );
+        match export_io_data(&ctx.layout.dir(Category::Io), ctx.package, rates) {
+            Ok(paths) => {
+                for path in paths {
+                    console!("✓ I/O data exported: {}", path.display());
+                }
+            }
+            Err(e) => console!("Failed to export I/O data: {}", e),
+        }
+    }
+}
+
+// ADB 断开造成的数据缺口，重连后用累计计数器补回期间的总量
+pub struct DataGap {
+    pub started: DateTime<Local>,
+    pub ended: DateTime<Local>,
+    pub backfill: CounterDelta,
+}
+
+// 记录每段缺口及补回的计数器总量
+#[derive(Default)]
+pub struct GapCollector {
+    gaps: Vec<DataGap>,
+}
+
+impl Collector for GapCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        let Some(started) = ctx.gap_started else {
+            return Ok(());
+        };
+        let backfill = ctx
+            .counters
+            .and_then(|reading| reading.delta)
+            .unwrap_or_default();
+        let recovered: Vec<String> = backfill
+            .columns()
+            .iter()
+            .filter_map(|(column, value)| value.map(|v| format!("{} {:.0}", column, v)))
+            .collect();
+        console!(
+            "[{}] Data gap of {}s closed, backfilled: {}",
+            ctx.tick.timestamp.format("%H:%M:%S").to_string().blue(),
+            (ctx.tick.timestamp - started).num_seconds(),
+            if recovered.is_empty() {
+                "nothing recoverable".to_string()
+            } else {
+                recovered.join(", ")
+            }
+        );
+        self.gaps.push(DataGap {
+            started,
+            ended: ctx.tick.timestamp,
+            backfill,
+        });
+        Ok(())
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        // 未关闭的缺口（断开后直接结束）也记录下来，补回量为空
+        if let Some(started) = ctx.open_gap {
+            self.gaps.push(DataGap {
+                started,
+                ended: Local::now(),
+                backfill: CounterDelta::default(),
+            });
+        }
+        if self.gaps.is_empty() {
+            return;
+        }
+        console!("Data Gaps: {}", self.gaps.len().to_string().red());
+        match export_data_gaps(ctx.layout.session_dir(), ctx.package, &self.gaps) {
+            Ok(path) => console!("✓ Data gaps exported to CSV: {}", path.display()),
+            Err(e) => console!("Failed to export data gaps: {}", e),
+        }
+    }
+}
+
+// 导出 ADB 断开造成的数据缺口，补不回的计数器留空
+pub fn export_data_gaps(dir: &Path, package: &str, gaps: &[DataGap]) -> Result<PathBuf> {
+    let path = dir.join(format!("{}_data_gaps.csv", package));
+    let mut file = std::fs::File::create(&path)?;
+    let columns: Vec<&str> = CounterDelta::default()
+        .columns()
+        .iter()
+        .map(|(column, _)| *column)
+        .collect();
+    writeln!(file, "Start,End,Duration (s),{}", columns.join(","))?;
+    for gap in gaps {
+        let values: Vec<String> = gap
+            .backfill
+            .columns()
+            .iter()
+            .map(|(_, value)| value.map(|v| format!("{:.1}", v)).unwrap_or_default())
+            .collect();
+        writeln!(
+            file,
+            "{},{},{},{}",
+            gap.started.format("%Y-%m-%d %H:%M:%S"),
+            gap.ended.format("%Y-%m-%d %H:%M:%S"),
+            (gap.ended - gap.started).num_seconds(),
+            values.join(",")
+        )?;
+    }
+    Ok(path)
+}
+
+// 一个采样周期内的上下文切换速率；非自愿切换（被抢占）偏高常是延迟的原因，单看 CPU 占用看不出来
+#[derive(Debug, Clone, Copy)]
+pub struct SwitchRate {
+    pub timestamp: DateTime<Local>,
+    pub voluntary_s: f64,
+    pub involuntary_s: f64,
+}
+
+impl SwitchRate {
+    pub fn from_delta(timestamp: DateTime<Local>, delta: &CounterDelta, secs: f64) -> Option<Self> {
+        if secs <= 0.0 {
+            return None;
+        }
+        Some(Self {
+            timestamp,
+            voluntary_s: delta.voluntary_switches? / secs,
+            involuntary_s: delta.involuntary_switches? / secs,
+        })
+    }
+
+    // 非自愿切换占全部切换的比例
+    pub fn involuntary_share(&self) -> f64 {
+        let total = self.voluntary_s + self.involuntary_s;
+        if total <= 0.0 {
+            return 0.0;
+        }
+        self.involuntary_s * 100.0 / total
+    }
+}
+
+// 导出上下文切换速率 CSV 和图表
+pub fn export_switch_data(dir: &Path, package: &str, rates: &[SwitchRate]) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let mut paths = Vec::new();
+
+    let csv_path = dir.join(format!("{}_ctx_switches.csv", package));
+    let mut file = std::fs::File::create(&csv_path)?;
+    writeln!(
+        file,
+        "Timestamp,Voluntary (/s),Involuntary (/s),Involuntary Share (%)"
+    )?;
+    for rate in rates {
+        writeln!(
+            file,
+            "{},{:.1},{:.1},{:.1}",
+            rate.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            rate.voluntary_s,
+            rate.involuntary_s,
+            rate.involuntary_share()
+        )?;
+    }
+    paths.push(csv_path);
+
+    if rates.len() > 1 {
+        let series = |f: fn(&SwitchRate) -> f64| -> Vec<(DateTime<Local>, f64)> {
+            rates.iter().map(|r| (r.timestamp, f(r))).collect()
+        };
+        let path = dir.join(chart::stamped_file_name(&format!(
+            "{}_ctx_switches.png",
+            package
+        )));
+        chart::draw_time_series_chart(
+            &path,
+            &format!("Context Switches - {}", package),
+            "Switches/s",
+            &[
+                TimeSeriesData::new("voluntary/s", series(|r| r.voluntary_s)),
+                TimeSeriesData::new("involuntary/s", series(|r| r.involuntary_s)),
+            ],
+        )?;
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
+
+// --ctx-switches：每个 Tick 的自愿和非自愿上下文切换速率
+#[derive(Default)]
+pub struct SwitchCollector {
+    rates: Vec<SwitchRate>,
+}
+
+impl Collector for SwitchCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        // 缺口结束时的增量覆盖整个断开期间，不算作一个采样周期
+        if ctx.gap_started.is_some() {
+            return Ok(());
+        }
+        let Some(rate) = ctx.counters.and_then(|reading| {
+            SwitchRate::from_delta(ctx.tick.timestamp, reading.delta.as_ref()?, reading.secs)
+        }) else {
+            return Ok(());
+        };
+        console!(
+            "[{}] Context switches: {:.0} voluntary, {} involuntary per s ({:.0}% involuntary)",
+            ctx.tick.timestamp.format("%H:%M:%S"),
+            rate.voluntary_s,
+            format!("{:.0}", rate.involuntary_s).yellow(),
+            rate.involuntary_share()
+        );
+        self.rates.push(rate);
+        Ok(())
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        let rates = &self.rates;
+        if rates.is_empty() {
+            return;
+        }
+        let mean =
+            |f: fn(&SwitchRate) -> f64| rates.iter().map(f).sum::<f64>() / rates.len() as f64;
+        console!(
+            "Context switches: mean {:.0} voluntary, {:.0} involuntary per s, peak involuntary {:.0} per s",
+            mean(|r| r.voluntary_s),
+            mean(|r| r.involuntary_s),
+            rates.iter().map(|r| r.involuntary_s).fold(0.0, f64::max)
+        );
+        match export_switch_data(&ctx.layout.dir(Category::Process), ctx.package, rates) {
+            Ok(paths) => {
+                for path in paths {
+                    console!("✓ Context switch data exported: {}", path.display());
+                }
+            }
+            Err(e) => console!("Failed to export context switch data: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COUNTERS: &str = "rchar: 5242880
+wchar: 1048576
+read_bytes: 409600
+write_bytes: 8192
+---
+Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+    lo:    1000      10    0    0    0     0          0         0     1000      10    0    0    0     0       0          0
+ wlan0: 2048000    1500    0    0    0     0          0         0   512000     900    0    0    0     0       0          0
+rmnet0:   10240      20    0    0    0     0          0         0     2048      10    0    0    0     0       0          0
+---
+Name:	com.example.app
+State:	S (sleeping)
+voluntary_ctxt_switches:	18342
+nonvoluntary_ctxt_switches:	2211
+---
+4321 (com.example app) S 612 612 0 0 -1 1077952832 52310 0 187 0 1520 640 0 0 10 -10 42 0 123456 2147483648 45210
+";
+
+    #[test]
+    fn sample_counters_parses_io_and_net_dev() {
+        let counters = parse_counters(COUNTERS).unwrap();
+        assert_eq!(counters.read_bytes, Some(409600));
+        assert_eq!(counters.write_bytes, Some(8192));
+        // lo 不计入
+        assert_eq!(counters.rx_bytes, Some(2058240));
+        assert_eq!(counters.tx_bytes, Some(514048));
+        assert_eq!(counters.voluntary_switches, Some(18342));
+        assert_eq!(counters.involuntary_switches, Some(2211));
+        // comm 含空格时仍按最后一个 ')' 定位字段
+        assert_eq!(counters.minor_faults, Some(52310));
+        assert_eq!(counters.major_faults, Some(187));
+    }
+
+    #[test]
+    fn sample_counters_without_permission() {
+        // 没有权限读取 /proc/<pid>/io 时只有网络计数
+        let counters = parse_counters(&COUNTERS[COUNTERS.find("---").unwrap()..]).unwrap();
+        assert_eq!(counters.read_bytes, None);
+        assert!(counters.rx_bytes.is_some());
+        assert!(parse_counters("error: device offline").is_err());
+    }
+
+    #[test]
+    fn delta_since_drops_counters_that_went_backwards() {
+        let before = parse_counters(COUNTERS).unwrap();
+        let after = CumulativeCounters {
+            read_bytes: Some(409600 + 2048),
+            write_bytes: Some(0),
+            ..before
+        };
+        let delta = after.delta_since(&before);
+        assert_eq!(delta.read_kb, Some(2.0));
+        assert_eq!(delta.write_kb, None);
+        assert_eq!(delta.rx_kb, Some(0.0));
+    }
+}
@@ -0,0 +1,258 @@
+use crate::chart::TimeSeriesData;
+use crate::layout::Category;
+use crate::recorder::{Collector, FinishContext, TickContext};
+use crate::utils::{self, CpuChartOverlay};
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use colored::*;
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// 每个 policy 一行：名称、当前频率、硬件最大频率、当前上限（kHz）、调频策略和所属核心
+const POLICY_COMMAND: &str = "cd /sys/devices/system/cpu/cpufreq && for p in policy*; do echo \"$p $(cat $p/scaling_cur_freq) $(cat $p/cpuinfo_max_freq) $(cat $p/scaling_max_freq) $(cat $p/scaling_governor) $(cat $p/related_cpus)\"; done";
+
+// 一个 cpufreq policy（簇）的频率读数
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyFreq {
+    pub policy: String,
+    pub cpus: String,
+    pub cur_khz: u64,
+    pub max_khz: u64,
+    pub scaling_max_khz: u64,
+    pub governor: String,
+}
+
+impl PolicyFreq {
+    // 当前频率占硬件最大频率的百分比
+    pub fn normalized(&self) -> f64 {
+        if self.max_khz == 0 {
+            return 0.0;
+        }
+        self.cur_khz as f64 * 100.0 / self.max_khz as f64
+    }
+
+    // 图例和 CSV 列名，如 policy4 (cpu4-6)
+    pub fn label(&self) -> String {
+        let cpus: Vec<&str> = self.cpus.split_whitespace().collect();
+        match cpus.as_slice() {
+            [] => self.policy.clone(),
+            [only] => format!("{} (cpu{})", self.policy, only),
+            [first, .., last] => format!("{} (cpu{}-{})", self.policy, first, last),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FreqSample {
+    pub timestamp: DateTime<Local>,
+    pub policies: Vec<PolicyFreq>,
+}
+
+pub fn parse_policies(output: &str) -> Vec<PolicyFreq> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let policy = fields
+                .next()
+                .filter(|name| name.starts_with("policy"))?
+                .to_string();
+            let cur_khz = fields.next()?.parse().ok()?;
+            let max_khz = fields.next()?.parse().ok()?;
+            let scaling_max_khz = fields.next()?.parse().ok()?;
+            let governor = fields.next()?.to_string();
+            Some(PolicyFreq {
+                policy,
+                cpus: fields.collect::<Vec<_>>().join(" "),
+                cur_khz,
+                max_khz,
+                scaling_max_khz,
+                governor,
+            })
+        })
+        .collect()
+}
+
+// 一次 adb 调用读取所有 policy 的频率
+pub fn sample(timestamp: DateTime<Local>) -> Result<FreqSample> {
+    let output = utils::run_adb_command(&["shell", POLICY_COMMAND])?;
+    let policies = parse_policies(&output);
+    if policies.is_empty() {
+        anyhow::bail!("No cpufreq policies readable: {}", output.trim());
+    }
+    Ok(FreqSample {
+        timestamp,
+        policies,
+    })
+}
+
+// 调频策略切换和频率上限变化（通常是温控限频），用于在 CPU 图表上标注
+pub fn policy_changes(samples: &[FreqSample]) -> Vec<(DateTime<Local>, String)> {
+    let mut changes = Vec::new();
+    for pair in samples.windows(2) {
+        for current in &pair[1].policies {
+            let Some(previous) = pair[0].policies.iter().find(|p| p.policy == current.policy)
+            else {
+                continue;
+            };
+            if current.governor != previous.governor {
+                changes.push((
+                    pair[1].timestamp,
+                    format!("{} {}", current.policy, current.governor),
+                ));
+            }
+            if current.scaling_max_khz != previous.scaling_max_khz {
+                changes.push((
+                    pair[1].timestamp,
+                    format!(
+                        "{} max {} MHz",
+                        current.policy,
+                        current.scaling_max_khz / 1000
+                    ),
+                ));
+            }
+        }
+    }
+    changes
+}
+
+// 每个 policy 的归一化频率（% of max），叠加在 CPU 图表的副坐标轴上
+pub fn normalized_series(samples: &[FreqSample]) -> Vec<TimeSeriesData> {
+    let policies: BTreeSet<(String, String)> = samples
+        .iter()
+        .flat_map(|s| s.policies.iter().map(|p| (p.policy.clone(), p.label())))
+        .collect();
+    policies
+        .into_iter()
+        .map(|(policy, label)| {
+            TimeSeriesData::new(
+                format!("{} freq", label),
+                samples
+                    .iter()
+                    .filter_map(|s| {
+                        let freq = s.policies.iter().find(|p| p.policy == policy)?;
+                        Some((s.timestamp, freq.normalized()))
+                    })
+                    .collect(),
+            )
+        })
+        .collect()
+}
+
+pub fn export_freq_csv(dir: &Path, package: &str, samples: &[FreqSample]) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{}_cpu_freq.csv", package));
+    let mut file = std::fs::File::create(&path)?;
+    writeln!(
+        file,
+        "Timestamp,Policy,CPUs,Frequency (MHz),Max (MHz),Limit (MHz),Frequency (% of max),Governor"
+    )?;
+    for sample in samples {
+        for freq in &sample.policies {
+            writeln!(
+                file,
+                "{},{},{},{},{},{},{:.1},{}",
+                sample.timestamp.format("%Y-%m-%d %H:%M:%S"),
+                freq.policy,
+                freq.cpus,
+                freq.cur_khz / 1000,
+                freq.max_khz / 1000,
+                freq.scaling_max_khz / 1000,
+                freq.normalized(),
+                freq.governor
+            )?;
+        }
+    }
+    Ok(path)
+}
+
+// --cpu-freq：各簇频率，图表上叠加归一化频率并标注调频策略和上限变化
+pub struct CpuFreqCollector {
+    verbose: bool,
+    samples: Vec<FreqSample>,
+}
+
+impl CpuFreqCollector {
+    pub fn new(verbose: bool) -> Self {
+        Self {
+            verbose,
+            samples: Vec::new(),
+        }
+    }
+}
+
+impl Collector for CpuFreqCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        let tick = ctx.tick;
+        let sample = match sample(tick.timestamp) {
+            Ok(sample) => sample,
+            Err(e) => {
+                if !utils::is_being_interrupted() {
+                    console!("Failed to sample CPU frequency: {}", e);
+                }
+                return Ok(());
+            }
+        };
+        let clusters: Vec<String> = sample
+            .policies
+            .iter()
+            .map(|freq| {
+                ctx.table.record(
+                    &tick,
+                    &format!("{} Freq (MHz)", freq.policy),
+                    (freq.cur_khz / 1000) as f64,
+                );
+                format!(
+                    "{} {} MHz ({:.0}%)",
+                    freq.policy,
+                    freq.cur_khz / 1000,
+                    freq.normalized()
+                )
+            })
+            .collect();
+        if self.verbose {
+            console!(
+                "[{}] CPU freq: {}",
+                tick.timestamp.format("%H:%M:%S").to_string().blue(),
+                clusters.join(", ")
+            );
+        }
+        ctx.cpu_freq = Some(sample.clone());
+        self.samples.push(sample);
+        Ok(())
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        if self.samples.is_empty() {
+            return;
+        }
+        for (time, change) in policy_changes(&self.samples) {
+            console!("[{}] CPU frequency: {}", time.format("%H:%M:%S"), change);
+        }
+        match export_freq_csv(&ctx.layout.dir(Category::Cpu), ctx.package, &self.samples) {
+            Ok(path) => console!("✓ CPU frequency exported to CSV: {}", path.display()),
+            Err(e) => console!("Failed to export CPU frequency: {}", e),
+        }
+    }
+
+    fn cpu_overlay(&self, overlay: &mut CpuChartOverlay) {
+        overlay.markers.extend(policy_changes(&self.samples));
+        overlay.frequency.extend(normalized_series(&self.samples));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_policies_reads_clusters() {
+        let output = "policy0 1804800 1804800 1804800 schedutil 0 1 2 3\npolicy4 710400 2419200 1497600 schedutil 4 5 6\npolicy7 No such file\n";
+        let policies = parse_policies(output);
+        assert_eq!(policies.len(), 2);
+        assert_eq!(policies[0].label(), "policy0 (cpu0-3)");
+        assert_eq!(policies[1].scaling_max_khz, 1497600);
+        assert!((policies[1].normalized() - 29.4).abs() < 0.1);
+    }
+}
@@ -0,0 +1,266 @@
+use crate::chart;
+use crate::layout::Category;
+use crate::logcat::{EventKind, LogcatEvent};
+use crate::recorder::{Collector, FinishContext, TickContext};
+use crate::regenerate::{self, CSV_TIMESTAMP_FORMAT};
+use crate::utils;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use colored::*;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// DropBoxManager 中的一条记录，如 data_app_crash、data_app_anr、data_app_wtf
+#[derive(Debug, Clone)]
+pub struct DropboxEntry {
+    pub timestamp: DateTime<Local>,
+    pub tag: String,
+    pub content: String,
+}
+
+impl DropboxEntry {
+    // 图表标记和事件中的类型
+    pub fn kind(&self) -> &'static str {
+        let tag = self.tag.to_lowercase();
+        if tag.contains("anr") {
+            "anr"
+        } else if tag.contains("wtf") {
+            "wtf"
+        } else {
+            "crash"
+        }
+    }
+
+    // 一行摘要：ANR 的 Subject，其余为头部之后的第一行（通常是异常）
+    pub fn headline(&self) -> String {
+        let mut lines = self.content.lines().map(str::trim);
+        if let Some(subject) = lines
+            .clone()
+            .find_map(|line| line.strip_prefix("Subject: "))
+        {
+            return subject.to_string();
+        }
+        // 头部以空行结束
+        lines.by_ref().find(|line| line.is_empty());
+        lines
+            .find(|line| !line.is_empty())
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    // 归档文件名，如 20261015_123456_data_app_crash.txt
+    pub fn file_name(&self) -> String {
+        format!(
+            "{}_{}.txt",
+            self.timestamp.format("%Y%m%d_%H%M%S"),
+            self.tag
+        )
+    }
+}
+
+// 崩溃、ANR 和 WTF 类记录，如 data_app_crash、system_app_anr、data_app_native_crash
+fn is_relevant_tag(tag: &str) -> bool {
+    let tag = tag.to_lowercase();
+    ["crash", "anr", "wtf"]
+        .iter()
+        .any(|kind| tag.contains(kind))
+}
+
+// 记录正文中的进程或包名：Java 崩溃和 ANR 为 "Process: <package>"，native crash 为 ">>> <package> <<<"
+fn involves_package(content: &str, package: &str) -> bool {
+    content.lines().map(str::trim).any(|line| {
+        line.strip_prefix("Process: ")
+            .or_else(|| line.strip_prefix("Package: "))
+            .and_then(|rest| rest.split_whitespace().next())
+            == Some(package)
+    }) || content.contains(&format!(">>> {} <<<", package))
+}
+
+// 解析 dumpsys dropbox --print，记录之间以一行等号分隔：
+//   ========================================
+//   2026-10-15 12:34:56 data_app_crash (text, 1234 bytes)
+//   Process: com.example.app
+//   ...
+pub fn parse_dropbox(output: &str, package: &str) -> Vec<DropboxEntry> {
+    output
+        .split("========================================")
+        .filter_map(|block| {
+            let block = block
+                .trim_start_matches('=')
+                .trim_start_matches(['\r', '\n']);
+            let (header, content) = block.split_once('\n')?;
+            let timestamp = regenerate::parse_timestamp(header.get(..19)?)?;
+            let tag = header.get(19..)?.split_whitespace().next()?;
+            (is_relevant_tag(tag) && involves_package(content, package)).then(|| DropboxEntry {
+                timestamp,
+                tag: tag.to_string(),
+                content: content.trim_end().to_string(),
+            })
+        })
+        .collect()
+}
+
+// since 之后的记录；dumpsys dropbox 按设备时间过滤，与其他事件一样假定设备与主机时钟一致
+pub fn query_entries(package: &str, since: DateTime<Local>) -> Result<Vec<DropboxEntry>> {
+    let output = utils::run_adb_command(&[
+        "shell",
+        "dumpsys",
+        "dropbox",
+        "--print",
+        &since.format("%Y-%m-%d").to_string(),
+        &since.format("%H:%M:%S").to_string(),
+    ])?;
+    Ok(parse_dropbox(&output, package)
+        .into_iter()
+        .filter(|entry| entry.timestamp >= since)
+        .collect())
+}
+
+// 每条记录保存为一个文本文件，并写出索引 CSV，返回索引路径
+pub fn archive_entries(dir: &Path, package: &str, entries: &[DropboxEntry]) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    for entry in entries {
+        std::fs::write(dir.join(entry.file_name()), &entry.content)?;
+    }
+
+    let index_path = dir.join(format!("{}_dropbox.csv", package));
+    let mut file = std::fs::File::create(&index_path)?;
+    writeln!(file, "Timestamp,Tag,Type,File,Headline")?;
+    for entry in entries {
+        writeln!(
+            file,
+            "{},{},{},{},\"{}\"",
+            entry.timestamp.format(CSV_TIMESTAMP_FORMAT),
+            entry.tag,
+            entry.kind(),
+            entry.file_name(),
+            entry.headline().replace('"', "'")
+        )?;
+    }
+    Ok(index_path)
+}
+
+// 会话期间系统记录的崩溃、ANR 和 WTF：结束时查询，在图表上标记并归档全文
+pub struct DropboxCollector {
+    package: String,
+    // 会话结束时按该时间查询 dumpsys dropbox
+    session_start: DateTime<Local>,
+    entries: Vec<DropboxEntry>,
+}
+
+impl DropboxCollector {
+    pub fn start(package: &str) -> Self {
+        Self {
+            package: package.to_string(),
+            session_start: Local::now(),
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl Collector for DropboxCollector {
+    fn sample(&mut self, _ctx: &mut TickContext) -> Result<()> {
+        Ok(())
+    }
+
+    // 在生成图表之前查询，标记才能画上
+    fn stop(&mut self) {
+        match query_entries(&self.package, self.session_start) {
+            Ok(entries) => self.entries = entries,
+            Err(e) => console!("Failed to read dumpsys dropbox: {}", e),
+        }
+        for entry in &self.entries {
+            chart::add_user_marker(entry.timestamp, entry.kind());
+        }
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        if self.entries.is_empty() {
+            return;
+        }
+        console!(
+            "Dropbox entries: {}",
+            self.entries.len().to_string().red().bold()
+        );
+        for entry in &self.entries {
+            console!(
+                "  [{}] {} {}",
+                entry.timestamp.format("%H:%M:%S").to_string().blue(),
+                entry.tag.red(),
+                entry.headline()
+            );
+        }
+        match archive_entries(
+            &ctx.layout.dir(Category::Dropbox),
+            ctx.package,
+            &self.entries,
+        ) {
+            Ok(index) => console!("✓ Dropbox entries archived: {}", index.display()),
+            Err(e) => console!("Failed to archive dropbox entries: {}", e),
+        }
+    }
+
+    fn events(&self, events: &mut Vec<LogcatEvent>) {
+        events.extend(self.entries.iter().map(|entry| LogcatEvent {
+            timestamp: entry.timestamp,
+            kind: EventKind::Dropbox,
+            pid: None,
+            message: format!(
+                "{}: {} (dropbox/{})",
+                entry.tag,
+                entry.headline(),
+                entry.file_name()
+            ),
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DROPBOX: &str = "Drop box contents: 3 entries
+Max entries: 1000
+
+========================================
+2026-10-15 12:34:56 data_app_crash (text, 512 bytes)
+Process: com.example.app
+PID: 1234
+Flags: 0x38c8bf46
+Package: com.example.app v42 (1.4.2)
+
+java.lang.IllegalStateException: boom
+\tat com.example.app.MainActivity.onCreate(MainActivity.java:42)
+
+========================================
+2026-10-15 12:35:10 data_app_anr (text, 2048 bytes)
+Process: com.example.app
+PID: 1234
+Subject: Input dispatching timed out (com.example.app/.MainActivity)
+
+========================================
+2026-10-15 12:35:20 data_app_crash (text, 256 bytes)
+Process: com.other.app
+PID: 5678
+
+java.lang.NullPointerException
+";
+
+    #[test]
+    fn parse_entries_for_package() {
+        let entries = parse_dropbox(DROPBOX, "com.example.app");
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].tag, "data_app_crash");
+        assert_eq!(entries[0].kind(), "crash");
+        assert_eq!(
+            entries[0].headline(),
+            "java.lang.IllegalStateException: boom"
+        );
+        assert_eq!(entries[0].file_name(), "20261015_123456_data_app_crash.txt");
+        assert_eq!(entries[1].kind(), "anr");
+        assert_eq!(
+            entries[1].headline(),
+            "Input dispatching timed out (com.example.app/.MainActivity)"
+        );
+    }
+}
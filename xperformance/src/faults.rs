@@ -0,0 +1,312 @@
+use crate::chart::{self, TimeSeriesData};
+use crate::counters::CounterDelta;
+use crate::layout::Category;
+use crate::memory::MemoryChartOverlay;
+use crate::recorder::{Collector, FinishContext, TickContext};
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use colored::*;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// 主缺页（需要读盘）速率持续达到该值视为缺页风暴，通常意味着内存压力和颠簸
+pub const MAJOR_STORM_RATE: f64 = 50.0;
+// 连续多少次采样超过阈值才算持续
+pub const STORM_SAMPLES: usize = 3;
+
+// 一个采样周期内的缺页速率
+#[derive(Debug, Clone, Copy)]
+pub struct FaultRate {
+    pub timestamp: DateTime<Local>,
+    pub minor_s: f64,
+    pub major_s: f64,
+}
+
+impl FaultRate {
+    pub fn from_delta(timestamp: DateTime<Local>, delta: &CounterDelta, secs: f64) -> Option<Self> {
+        if secs <= 0.0 {
+            return None;
+        }
+        Some(Self {
+            timestamp,
+            minor_s: delta.minor_faults? / secs,
+            major_s: delta.major_faults? / secs,
+        })
+    }
+}
+
+// 一段主缺页风暴，end 为 None 表示仍在持续
+#[derive(Debug, Clone)]
+pub struct FaultStorm {
+    pub start: DateTime<Local>,
+    pub end: Option<DateTime<Local>>,
+    pub peak_major_s: f64,
+}
+
+#[derive(Debug, Clone)]
+pub enum StormEvent {
+    Started(FaultStorm),
+    Ended(FaultStorm),
+}
+
+// 主缺页速率连续 STORM_SAMPLES 次达到 MAJOR_STORM_RATE 时进入风暴，回落到阈值以下时结束
+#[derive(Default)]
+pub struct FaultStormDetector {
+    // 尚未构成风暴的连续超限采样
+    pending: Vec<FaultRate>,
+    storms: Vec<FaultStorm>,
+}
+
+impl FaultStormDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn storms(&self) -> &[FaultStorm] {
+        &self.storms
+    }
+
+    fn in_storm(&self) -> bool {
+        self.storms.last().is_some_and(|s| s.end.is_none())
+    }
+
+    pub fn update(&mut self, rate: &FaultRate) -> Option<StormEvent> {
+        if rate.major_s < MAJOR_STORM_RATE {
+            self.pending.clear();
+            let storm = self.storms.last_mut().filter(|s| s.end.is_none())?;
+            storm.end = Some(rate.timestamp);
+            return Some(StormEvent::Ended(storm.clone()));
+        }
+        if self.in_storm() {
+            let storm = self.storms.last_mut()?;
+            storm.peak_major_s = storm.peak_major_s.max(rate.major_s);
+            return None;
+        }
+        self.pending.push(*rate);
+        if self.pending.len() < STORM_SAMPLES {
+            return None;
+        }
+        // 风暴从第一次超限的采样算起
+        let storm = FaultStorm {
+            start: self.pending[0].timestamp,
+            end: None,
+            peak_major_s: self.pending.iter().map(|r| r.major_s).fold(0.0, f64::max),
+        };
+        self.pending.clear();
+        self.storms.push(storm.clone());
+        Some(StormEvent::Started(storm))
+    }
+
+    // 会话结束时仍在风暴中则以结束时间收尾
+    pub fn finish(&mut self, timestamp: DateTime<Local>) {
+        if let Some(storm) = self.storms.last_mut().filter(|s| s.end.is_none()) {
+            storm.end = Some(timestamp);
+        }
+    }
+
+    // 图表底纹用的区间，未结束的区间延伸到 now
+    pub fn ranges(&self, now: DateTime<Local>) -> Vec<(DateTime<Local>, DateTime<Local>)> {
+        self.storms
+            .iter()
+            .map(|s| (s.start, s.end.unwrap_or(now)))
+            .collect()
+    }
+}
+
+// 导出缺页速率 CSV、折线图和风暴区间
+pub fn export_fault_data(
+    dir: &Path,
+    package: &str,
+    rates: &[FaultRate],
+    storms: &[FaultStorm],
+) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let mut paths = Vec::new();
+
+    let csv_path = dir.join(format!("{}_page_faults.csv", package));
+    let mut file = std::fs::File::create(&csv_path)?;
+    writeln!(file, "Timestamp,Minor Faults (/s),Major Faults (/s)")?;
+    for rate in rates {
+        writeln!(
+            file,
+            "{},{:.1},{:.1}",
+            rate.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            rate.minor_s,
+            rate.major_s
+        )?;
+    }
+    paths.push(csv_path);
+
+    if !storms.is_empty() {
+        let path = dir.join(format!("{}_fault_storms.csv", package));
+        let mut file = std::fs::File::create(&path)?;
+        writeln!(file, "Start,End,Duration (s),Peak Major Faults (/s)")?;
+        for storm in storms {
+            let end = storm.end.unwrap_or_else(Local::now);
+            writeln!(
+                file,
+                "{},{},{},{:.1}",
+                storm.start.format("%Y-%m-%d %H:%M:%S"),
+                end.format("%Y-%m-%d %H:%M:%S"),
+                (end - storm.start).num_seconds(),
+                storm.peak_major_s
+            )?;
+        }
+        paths.push(path);
+    }
+
+    if rates.len() > 1 {
+        let series = |f: fn(&FaultRate) -> f64| -> Vec<(DateTime<Local>, f64)> {
+            rates.iter().map(|r| (r.timestamp, f(r))).collect()
+        };
+        // 主缺页通常比次缺页少几个数量级，分开画
+        for (file_name, title, label, points) in [
+            (
+                "minor_faults.png",
+                "Minor Page Faults",
+                "minor faults/s",
+                series(|r| r.minor_s),
+            ),
+            (
+                "major_faults.png",
+                "Major Page Faults",
+                "major faults/s",
+                series(|r| r.major_s),
+            ),
+        ] {
+            let path = dir.join(chart::stamped_file_name(&format!(
+                "{}_{}",
+                package, file_name
+            )));
+            chart::draw_time_series_chart(
+                &path,
+                &format!("{} - {}", title, package),
+                "Faults/s",
+                &[TimeSeriesData::new(label, points)],
+            )?;
+            paths.push(path);
+        }
+    }
+
+    Ok(paths)
+}
+
+// 由计数器增量得到缺页速率，检测主缺页风暴，结束时导出到 memory 目录
+#[derive(Default)]
+pub struct FaultCollector {
+    rates: Vec<FaultRate>,
+    storms: FaultStormDetector,
+}
+
+impl Collector for FaultCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        // 缺口结束时的增量覆盖整个断开期间，不算作一个采样周期
+        if ctx.gap_started.is_some() {
+            return Ok(());
+        }
+        let Some(rate) = ctx.counters.and_then(|reading| {
+            FaultRate::from_delta(ctx.tick.timestamp, reading.delta.as_ref()?, reading.secs)
+        }) else {
+            return Ok(());
+        };
+        let time = ctx.tick.timestamp.format("%H:%M:%S");
+        console!(
+            "[{}] Page faults: {:.0} minor, {} major per s",
+            time,
+            rate.minor_s,
+            format!("{:.0}", rate.major_s).yellow()
+        );
+        match self.storms.update(&rate) {
+            Some(StormEvent::Started(storm)) => console!(
+                "[{}] {} major faults at {:.0}/s since {} (memory pressure)",
+                time.to_string().blue(),
+                "Page fault storm:".red().bold(),
+                storm.peak_major_s,
+                storm.start.format("%H:%M:%S")
+            ),
+            Some(StormEvent::Ended(storm)) => console!(
+                "[{}] {} after {}s, peak {:.0} major faults/s",
+                time.to_string().blue(),
+                "Page fault storm ended".green(),
+                (ctx.tick.timestamp - storm.start).num_seconds(),
+                storm.peak_major_s
+            ),
+            None => {}
+        }
+        self.rates.push(rate);
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        self.storms.finish(Local::now());
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        let rates = &self.rates;
+        if rates.is_empty() {
+            return;
+        }
+        let mean = |f: fn(&FaultRate) -> f64| rates.iter().map(f).sum::<f64>() / rates.len() as f64;
+        console!(
+            "Page faults: mean {:.0} minor, {:.1} major per s, major-fault storms: {}",
+            mean(|r| r.minor_s),
+            mean(|r| r.major_s),
+            self.storms.storms().len().to_string().red()
+        );
+        match export_fault_data(
+            &ctx.layout.dir(Category::Memory),
+            ctx.package,
+            rates,
+            self.storms.storms(),
+        ) {
+            Ok(paths) => {
+                for path in paths {
+                    console!("✓ Page fault data exported: {}", path.display());
+                }
+            }
+            Err(e) => console!("Failed to export page fault data: {}", e),
+        }
+    }
+
+    fn memory_overlay(&self, overlay: &mut MemoryChartOverlay) {
+        overlay
+            .fault_storms
+            .extend(self.storms.ranges(Local::now()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn detector_needs_sustained_major_faults() {
+        let start = Local::now();
+        let rate = |i: i64, major_s: f64| FaultRate {
+            timestamp: start + Duration::seconds(i),
+            minor_s: 1000.0,
+            major_s,
+        };
+        let mut detector = FaultStormDetector::new();
+        // 单次超限不算风暴
+        assert!(detector.update(&rate(0, 80.0)).is_none());
+        assert!(detector.update(&rate(1, 10.0)).is_none());
+
+        assert!(detector.update(&rate(2, 60.0)).is_none());
+        assert!(detector.update(&rate(3, 120.0)).is_none());
+        match detector.update(&rate(4, 70.0)) {
+            Some(StormEvent::Started(storm)) => {
+                assert_eq!(storm.start, rate(2, 0.0).timestamp);
+                assert_eq!(storm.peak_major_s, 120.0);
+            }
+            other => panic!("expected storm start, got {:?}", other),
+        }
+        assert!(detector.update(&rate(5, 200.0)).is_none());
+        match detector.update(&rate(6, 5.0)) {
+            Some(StormEvent::Ended(storm)) => assert_eq!(storm.peak_major_s, 200.0),
+            other => panic!("expected storm end, got {:?}", other),
+        }
+        assert_eq!(detector.storms().len(), 1);
+    }
+}
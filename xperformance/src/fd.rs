@@ -0,0 +1,269 @@
+use crate::chart::{self, TimeSeriesData};
+use crate::layout::Category;
+use crate::recorder::{Collector, FinishContext, TickContext};
+use crate::utils;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use colored::*;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const SECTION_SEPARATOR: &str = "---";
+// 打开的文件描述符达到软上限的该比例时告警
+pub const WARN_RATIO: f64 = 0.8;
+
+#[derive(Debug, Clone, Copy)]
+pub struct FdSample {
+    pub timestamp: DateTime<Local>,
+    pub count: u64,
+    // /proc/<pid>/limits 中 Max open files 的软上限，unlimited 时为 None
+    pub limit: Option<u64>,
+}
+
+impl FdSample {
+    pub fn usage_ratio(&self) -> Option<f64> {
+        self.limit
+            .filter(|limit| *limit > 0)
+            .map(|limit| self.count as f64 / limit as f64)
+    }
+}
+
+// "Max open files            32768                32768                files"
+pub fn parse_fd_limit(limits: &str) -> Option<u64> {
+    limits
+        .lines()
+        .find_map(|line| line.strip_prefix("Max open files"))?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+// 应用进程的 /proc/<pid>/fd 对 shell 用户不可读；依次尝试直接读取（adb root）、run-as、su，
+// 首次成功后固定使用该方式，全部失败后不再尝试
+pub struct FdReader {
+    package: String,
+    prefix: Option<String>,
+    unavailable: bool,
+}
+
+impl FdReader {
+    pub fn new(package: &str) -> Self {
+        Self {
+            package: package.to_string(),
+            prefix: None,
+            unavailable: false,
+        }
+    }
+
+    pub fn is_unavailable(&self) -> bool {
+        self.unavailable
+    }
+
+    // 一次 adb 调用统计 fd 数量并读取上限；无权限时 ls 没有输出，计数为 0 视为失败
+    fn read(prefix: &str, pid: &str, timestamp: DateTime<Local>) -> Option<FdSample> {
+        let output = utils::run_adb_command(&[
+            "shell",
+            &format!(
+                "{0}ls /proc/{1}/fd 2>/dev/null | wc -l; echo {2}; {0}cat /proc/{1}/limits 2>/dev/null",
+                prefix, pid, SECTION_SEPARATOR
+            ),
+        ])
+        .ok()?;
+        let (count, limits) = output.split_once(SECTION_SEPARATOR)?;
+        let count: u64 = count.trim().parse().ok().filter(|count| *count > 0)?;
+        Some(FdSample {
+            timestamp,
+            count,
+            limit: parse_fd_limit(limits),
+        })
+    }
+
+    pub fn sample(&mut self, pid: &str, timestamp: DateTime<Local>) -> Option<FdSample> {
+        if self.unavailable {
+            return None;
+        }
+        let prefixes = match &self.prefix {
+            Some(prefix) => vec![prefix.clone()],
+            None => vec![
+                String::new(),
+                format!("run-as {} ", self.package),
+                "su 0 ".to_string(),
+            ],
+        };
+        for prefix in prefixes {
+            if let Some(sample) = Self::read(&prefix, pid, timestamp) {
+                self.prefix = Some(prefix);
+                return Some(sample);
+            }
+        }
+        // 已确定的方式失败可能只是进程刚退出，下次重试
+        if self.prefix.is_none() {
+            self.unavailable = true;
+        }
+        None
+    }
+}
+
+// 导出 fd 数量 CSV 和折线图；峰值接近上限时图中画出告警线
+pub fn export_fd_data(dir: &Path, package: &str, samples: &[FdSample]) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let mut paths = Vec::new();
+
+    let csv_path = dir.join(format!("{}_fds.csv", package));
+    let mut file = std::fs::File::create(&csv_path)?;
+    writeln!(file, "Timestamp,Open FDs,Limit,Usage (%)")?;
+    for sample in samples {
+        writeln!(
+            file,
+            "{},{},{},{}",
+            sample.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            sample.count,
+            sample.limit.map(|l| l.to_string()).unwrap_or_default(),
+            sample
+                .usage_ratio()
+                .map(|r| format!("{:.1}", r * 100.0))
+                .unwrap_or_default()
+        )?;
+    }
+    paths.push(csv_path);
+
+    if samples.len() > 1 {
+        let mut series = vec![TimeSeriesData::new(
+            "Open FDs",
+            samples
+                .iter()
+                .map(|s| (s.timestamp, s.count as f64))
+                .collect(),
+        )];
+        // 上限通常远大于实际数量，只在峰值超过上限一半时画出，避免压扁曲线
+        let peak = samples.iter().map(|s| s.count).max().unwrap_or(0);
+        if let Some(limit) = samples.last().and_then(|s| s.limit) {
+            if peak * 2 > limit {
+                series.push(TimeSeriesData::new(
+                    format!("{:.0}% of limit ({})", WARN_RATIO * 100.0, limit),
+                    samples
+                        .iter()
+                        .map(|s| (s.timestamp, limit as f64 * WARN_RATIO))
+                        .collect(),
+                ));
+            }
+        }
+        let path = dir.join(chart::stamped_file_name(&format!("{}_fds.png", package)));
+        chart::draw_time_series_chart(
+            &path,
+            &format!("Open File Descriptors - {}", package),
+            "Open FDs",
+            &series,
+        )?;
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
+
+// --fds：打开的文件描述符，应用进程的 fd 目录同样需要 root 或 debuggable 应用
+pub struct FdCollector {
+    reader: FdReader,
+    samples: Vec<FdSample>,
+    // 超过告警比例时提示一次，回落后重新计数
+    warned: bool,
+}
+
+impl FdCollector {
+    pub fn new(package: &str) -> Self {
+        Self {
+            reader: FdReader::new(package),
+            samples: Vec::new(),
+            warned: false,
+        }
+    }
+}
+
+impl Collector for FdCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        if self.reader.is_unavailable() {
+            return Ok(());
+        }
+        let tick = ctx.tick;
+        let Some(sample) = self.reader.sample(&ctx.sample.pid, tick.timestamp) else {
+            if self.reader.is_unavailable() {
+                console!(
+                    "{}",
+                    "/proc/<pid>/fd is not readable (needs root or a debuggable app); no FD data"
+                        .yellow()
+                );
+            }
+            return Ok(());
+        };
+        ctx.table.record(&tick, "Open FDs", sample.count as f64);
+        let ratio = sample.usage_ratio();
+        if ratio.is_some_and(|r| r >= WARN_RATIO) {
+            if !self.warned {
+                console!(
+                    "[{}] {} {} open file descriptors, {:.0}% of the limit {}",
+                    tick.timestamp.format("%H:%M:%S").to_string().blue(),
+                    "WARNING:".red().bold(),
+                    sample.count.to_string().red(),
+                    ratio.unwrap_or_default() * 100.0,
+                    sample.limit.unwrap_or_default()
+                );
+            }
+            self.warned = true;
+        } else {
+            self.warned = false;
+        }
+        if ctx.verbose {
+            console!(
+                "[{}] Open FDs: {}",
+                tick.timestamp.format("%H:%M:%S").to_string().blue(),
+                sample.count
+            );
+        }
+        self.samples.push(sample);
+        Ok(())
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        let (Some(first), Some(last)) = (self.samples.first(), self.samples.last()) else {
+            return;
+        };
+        let peak = self.samples.iter().map(|s| s.count).max().unwrap_or(0);
+        console!(
+            "Open FDs: {} -> {} (peak {}, limit {})",
+            first.count,
+            last.count,
+            peak.to_string().yellow(),
+            last.limit
+                .map(|l| l.to_string())
+                .unwrap_or_else(|| "unlimited".to_string())
+        );
+        match export_fd_data(
+            &ctx.layout.dir(Category::Process),
+            ctx.package,
+            &self.samples,
+        ) {
+            Ok(paths) => {
+                for path in paths {
+                    console!("✓ FD data exported: {}", path.display());
+                }
+            }
+            Err(e) => console!("Failed to export FD data: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_fd_limit_reads_soft_limit() {
+        let limits = "Limit                     Soft Limit           Hard Limit           Units\nMax processes             21367                21367                processes\nMax open files            32768                32768                files\n";
+        assert_eq!(parse_fd_limit(limits), Some(32768));
+        assert_eq!(
+            parse_fd_limit("Max open files unlimited unlimited files"),
+            None
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::Path;
+
+const WIDTH: f64 = 1200.0;
+const FRAME_HEIGHT: f64 = 16.0;
+const PADDING: f64 = 10.0;
+const TITLE_HEIGHT: f64 = 30.0;
+// 字体 12px 时每个字符的大致宽度，用于截断函数名
+const CHAR_WIDTH: f64 = 7.0;
+// 窄于该宽度的帧不绘制
+const MIN_FRAME_WIDTH: f64 = 0.5;
+
+// 折叠栈：从根到叶以 ';' 连接的调用栈及其样本数
+pub type CollapsedStacks = BTreeMap<String, u64>;
+
+pub fn write_collapsed(path: &Path, stacks: &CollapsedStacks) -> Result<()> {
+    let mut file = std::fs::File::create(path)?;
+    for (stack, count) in stacks {
+        writeln!(file, "{} {}", stack, count)?;
+    }
+    Ok(())
+}
+
+#[derive(Default)]
+struct Frame {
+    count: u64,
+    children: BTreeMap<String, Frame>,
+}
+
+impl Frame {
+    fn depth(&self) -> usize {
+        1 + self.children.values().map(Frame::depth).max().unwrap_or(0)
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// 按函数名哈希取暖色，同名函数颜色一致
+fn color(name: &str) -> String {
+    let hash = name
+        .bytes()
+        .fold(5381u32, |h, b| h.wrapping_mul(33) ^ u32::from(b));
+    let r = 205 + hash % 50;
+    let g = (hash / 50) % 230;
+    let b = (hash / 11500) % 55;
+    format!("rgb({},{},{})", r, g, b)
+}
+
+// 生成与 flamegraph.pl 风格一致的 SVG，根在底部，宽度与样本数成正比
+pub fn write_svg(path: &Path, stacks: &CollapsedStacks, title: &str) -> Result<()> {
+    let mut root = Frame::default();
+    for (stack, count) in stacks {
+        root.count += count;
+        let mut node = &mut root;
+        for name in stack.split(';') {
+            node = node.children.entry(name.to_string()).or_default();
+            node.count += count;
+        }
+    }
+
+    let depth = root.depth();
+    let height = TITLE_HEIGHT + depth as f64 * FRAME_HEIGHT + PADDING * 2.0;
+    let scale = if root.count > 0 {
+        (WIDTH - PADDING * 2.0) / root.count as f64
+    } else {
+        0.0
+    };
+
+    let mut svg = String::new();
+    svg.push_str(&format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\" font-family=\"Verdana\" font-size=\"12\">\n",
+        WIDTH, height, WIDTH, height
+    ));
+    svg.push_str(&format!(
+        "<rect width=\"100%\" height=\"100%\" fill=\"#f8f8f8\"/>\n<text x=\"{}\" y=\"20\" text-anchor=\"middle\" font-size=\"16\">{} ({} samples)</text>\n",
+        WIDTH / 2.0,
+        escape(title),
+        root.count
+    ));
+
+    // 显式栈代替递归，记录每帧的名称、节点、起始 x 和层级
+    let mut pending: Vec<(&str, &Frame, f64, usize)> = vec![("all", &root, PADDING, 0)];
+    while let Some((name, frame, x, level)) = pending.pop() {
+        let width = frame.count as f64 * scale;
+        if width < MIN_FRAME_WIDTH {
+            continue;
+        }
+        let y = height - PADDING - (level + 1) as f64 * FRAME_HEIGHT;
+        let percent = frame.count as f64 * 100.0 / root.count.max(1) as f64;
+        svg.push_str(&format!(
+            "<g><title>{} ({} samples, {:.2}%)</title><rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{}\" fill=\"{}\" rx=\"2\"/>",
+            escape(name),
+            frame.count,
+            percent,
+            x,
+            y,
+            width,
+            FRAME_HEIGHT - 1.0,
+            color(name)
+        ));
+        let max_chars = ((width - 6.0) / CHAR_WIDTH) as usize;
+        if max_chars >= 3 {
+            let label = if name.chars().count() > max_chars {
+                format!("{}..", name.chars().take(max_chars - 2).collect::<String>())
+            } else {
+                name.to_string()
+            };
+            svg.push_str(&format!(
+                "<text x=\"{:.1}\" y=\"{:.1}\">{}</text>",
+                x + 3.0,
+                y + FRAME_HEIGHT - 4.0,
+                escape(&label)
+            ));
+        }
+        svg.push_str("</g>\n");
+
+        let mut child_x = x;
+        for (child_name, child) in &frame.children {
+            pending.push((child_name, child, child_x, level + 1));
+            child_x += child.count as f64 * scale;
+        }
+    }
+    svg.push_str("</svg>\n");
+
+    std::fs::write(path, svg)?;
+    Ok(())
+}
@@ -0,0 +1,421 @@
+use crate::chart::{self, TimeSeriesData};
+use crate::cpu::CpuScale;
+use crate::process;
+use crate::report::HtmlReport;
+use crate::tick::{Tick, TickTable};
+use crate::utils;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const SECTION_SEPARATOR: &str = "---";
+
+// 单个进程在一次采样中的资源占用
+#[derive(Debug, Clone)]
+pub struct ProcessUsage {
+    pub pid: String,
+    pub name: String,
+    pub cpu: f64,
+    pub rss_kb: f64,
+}
+
+// 全系统进程采样器，CPU 由两次采样之间 utime+stime 的增量计算
+pub struct FleetSampler {
+    scale: CpuScale,
+    cores: u32,
+    previous: HashMap<String, u64>,
+    last_sample: Option<DateTime<Local>>,
+}
+
+impl FleetSampler {
+    pub fn new(scale: CpuScale, cores: u32) -> Self {
+        Self {
+            scale,
+            cores,
+            previous: HashMap::new(),
+            last_sample: None,
+        }
+    }
+
+    // 一次 adb 调用读取所有进程的 stat 和完整进程名；首次采样没有基线，CPU 为 0
+    pub fn sample(&mut self) -> Result<Vec<ProcessUsage>> {
+        let output = utils::run_adb_command(&[
+            "shell",
+            &format!(
+                "cat /proc/[0-9]*/stat 2>/dev/null; echo {}; ps -A -o PID,NAME",
+                SECTION_SEPARATOR
+            ),
+        ])?;
+        // 回放时使用录制时间，CPU 计算与录制时一致
+        let now = crate::replay::now();
+        let (stats, ps) = output
+            .split_once(SECTION_SEPARATOR)
+            .ok_or_else(|| anyhow::format_err!("Unexpected process list output"))?;
+
+        let names: HashMap<&str, &str> = ps
+            .lines()
+            .skip(1)
+            .filter_map(|line| line.trim().split_once(char::is_whitespace))
+            .map(|(pid, name)| (pid, name.trim()))
+            .collect();
+
+        let elapsed = self
+            .last_sample
+            .map(|last| (now - last).num_milliseconds() as f64 / 1000.0)
+            .filter(|secs| *secs > 0.0);
+
+        let mut current = HashMap::new();
+        let mut usages = Vec::new();
+        for stat in stats.lines().filter_map(process::parse_proc_stat) {
+            // 内核线程没有用户态内存
+            if stat.rss_kb == 0.0 {
+                continue;
+            }
+            let cpu = elapsed
+                .zip(self.previous.get(&stat.pid))
+                .and_then(|(secs, &before)| process::cpu_percent(before, stat.cpu_ticks, secs))
+                .map(|per_core| self.scale.normalize(per_core as f32, self.cores) as f64)
+                .unwrap_or(0.0);
+            current.insert(stat.pid.clone(), stat.cpu_ticks);
+            usages.push(ProcessUsage {
+                // 进程名用作 CSV 列名，不能含逗号
+                name: names
+                    .get(stat.pid.as_str())
+                    .copied()
+                    .unwrap_or(&stat.comm)
+                    .replace(',', ";"),
+                pid: stat.pid,
+                cpu,
+                rss_kb: stat.rss_kb,
+            });
+        }
+
+        if usages.is_empty() {
+            anyhow::bail!("No processes found in /proc");
+        }
+        self.previous = current;
+        self.last_sample = Some(now);
+        Ok(usages)
+    }
+}
+
+// 按 key 降序取前 n 个
+pub fn top_by(
+    usages: &[ProcessUsage],
+    n: usize,
+    key: fn(&ProcessUsage) -> f64,
+) -> Vec<ProcessUsage> {
+    let mut sorted = usages.to_vec();
+    sorted.sort_by(|a, b| {
+        key(b)
+            .partial_cmp(&key(a))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    sorted.truncate(n);
+    sorted
+}
+
+// 排行榜中单个进程名的累计统计，同名多进程每次采样合并计算
+#[derive(Debug, Clone, Default)]
+pub struct OffenderStats {
+    pub samples: u32,
+    pub samples_in_top: u32,
+    pub cpu_sum: f64,
+    pub cpu_peak: f64,
+    pub rss_sum: f64,
+    pub rss_peak: f64,
+    pub pss_samples: u32,
+    pub pss_sum: f64,
+    pub pss_peak: f64,
+}
+
+impl OffenderStats {
+    pub fn mean_cpu(&self) -> f64 {
+        self.cpu_sum / self.samples.max(1) as f64
+    }
+
+    pub fn mean_rss(&self) -> f64 {
+        self.rss_sum / self.samples.max(1) as f64
+    }
+
+    pub fn mean_pss(&self) -> f64 {
+        self.pss_sum / self.pss_samples.max(1) as f64
+    }
+}
+
+// 记录每个 Tick 的全系统占用：所有进程参与排行榜统计，进入过前 N 的进程才保留时间序列
+pub struct FleetRecorder {
+    top: usize,
+    stats: HashMap<String, OffenderStats>,
+    tracked: HashSet<String>,
+    cpu_series: HashMap<String, Vec<(DateTime<Local>, f64)>>,
+    memory_series: HashMap<String, Vec<(DateTime<Local>, f64)>>,
+    pss_series: HashMap<String, Vec<(DateTime<Local>, f64)>>,
+    cpu_table: TickTable,
+    memory_table: TickTable,
+    pss_table: TickTable,
+}
+
+impl FleetRecorder {
+    pub fn new(top: usize) -> Self {
+        Self {
+            top: top.max(1),
+            stats: HashMap::new(),
+            tracked: HashSet::new(),
+            cpu_series: HashMap::new(),
+            memory_series: HashMap::new(),
+            pss_series: HashMap::new(),
+            cpu_table: TickTable::default(),
+            memory_table: TickTable::default(),
+            pss_table: TickTable::default(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stats.is_empty()
+    }
+
+    // 返回本次 CPU 和内存的前 N 个进程（同名进程已合并）
+    pub fn record(
+        &mut self,
+        tick: &Tick,
+        usages: &[ProcessUsage],
+    ) -> (Vec<ProcessUsage>, Vec<ProcessUsage>) {
+        let mut merged: HashMap<&str, ProcessUsage> = HashMap::new();
+        for usage in usages {
+            merged
+                .entry(usage.name.as_str())
+                .and_modify(|m| {
+                    m.cpu += usage.cpu;
+                    m.rss_kb += usage.rss_kb;
+                })
+                .or_insert_with(|| usage.clone());
+        }
+        let merged: Vec<ProcessUsage> = merged.into_values().collect();
+
+        let top_cpu = top_by(&merged, self.top, |u| u.cpu);
+        let top_memory = top_by(&merged, self.top, |u| u.rss_kb);
+        let in_top: HashSet<&str> = top_cpu
+            .iter()
+            .chain(top_memory.iter())
+            .map(|u| u.name.as_str())
+            .collect();
+
+        for usage in &merged {
+            let stats = self.stats.entry(usage.name.clone()).or_default();
+            stats.samples += 1;
+            stats.cpu_sum += usage.cpu;
+            stats.cpu_peak = stats.cpu_peak.max(usage.cpu);
+            stats.rss_sum += usage.rss_kb;
+            stats.rss_peak = stats.rss_peak.max(usage.rss_kb);
+            if in_top.contains(usage.name.as_str()) {
+                stats.samples_in_top += 1;
+                self.tracked.insert(usage.name.clone());
+            }
+            if self.tracked.contains(&usage.name) {
+                self.cpu_series
+                    .entry(usage.name.clone())
+                    .or_default()
+                    .push((tick.timestamp, usage.cpu));
+                self.memory_series
+                    .entry(usage.name.clone())
+                    .or_default()
+                    .push((tick.timestamp, usage.rss_kb));
+                self.cpu_table.record(tick, &usage.name, usage.cpu);
+                self.memory_table.record(tick, &usage.name, usage.rss_kb);
+            }
+        }
+        self.cpu_table
+            .record(tick, "Total CPU (%)", merged.iter().map(|u| u.cpu).sum());
+
+        (top_cpu, top_memory)
+    }
+
+    // 记录 dumpsys meminfo 的各进程 PSS，返回本次 PSS 前 N 个进程（同名进程已合并）
+    pub fn record_pss(&mut self, tick: &Tick, processes: &[(String, f64)]) -> Vec<(String, f64)> {
+        let mut merged: HashMap<&str, f64> = HashMap::new();
+        for (name, pss_kb) in processes {
+            *merged.entry(name.as_str()).or_default() += pss_kb;
+        }
+        let mut sorted: Vec<(String, f64)> = merged
+            .into_iter()
+            .map(|(name, pss_kb)| (name.to_string(), pss_kb))
+            .collect();
+        sorted.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+
+        for (rank, (name, pss_kb)) in sorted.iter().enumerate() {
+            let stats = self.stats.entry(name.clone()).or_default();
+            stats.pss_samples += 1;
+            stats.pss_sum += pss_kb;
+            stats.pss_peak = stats.pss_peak.max(*pss_kb);
+            if rank < self.top {
+                self.tracked.insert(name.clone());
+            }
+            if self.tracked.contains(name) {
+                self.pss_series
+                    .entry(name.clone())
+                    .or_default()
+                    .push((tick.timestamp, *pss_kb));
+                self.pss_table.record(tick, name, *pss_kb);
+            }
+        }
+        self.pss_table.record(
+            tick,
+            "Total PSS (KB)",
+            sorted.iter().map(|(_, pss)| pss).sum(),
+        );
+
+        sorted.truncate(self.top);
+        sorted
+    }
+
+    // 按 CPU 均值降序
+    pub fn leaderboard(&self) -> Vec<(&str, &OffenderStats)> {
+        let mut entries: Vec<(&str, &OffenderStats)> = self
+            .stats
+            .iter()
+            .filter(|(name, _)| self.tracked.contains(*name))
+            .map(|(name, stats)| (name.as_str(), stats))
+            .collect();
+        entries.sort_by(|a, b| {
+            b.1.mean_cpu()
+                .partial_cmp(&a.1.mean_cpu())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        entries
+    }
+
+    // 前 N 个进程的曲线，按指定统计量排序
+    fn top_series(
+        &self,
+        series: &HashMap<String, Vec<(DateTime<Local>, f64)>>,
+        key: fn(&OffenderStats) -> f64,
+    ) -> Vec<TimeSeriesData> {
+        let mut names: Vec<(&String, f64)> = series
+            .keys()
+            .filter_map(|name| self.stats.get(name).map(|stats| (name, key(stats))))
+            .collect();
+        names.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        names
+            .into_iter()
+            .take(self.top)
+            .filter_map(|(name, _)| {
+                series
+                    .get(name)
+                    .filter(|points| points.len() > 1)
+                    .map(|points| TimeSeriesData::new(name.clone(), points.clone()))
+            })
+            .collect()
+    }
+
+    // 导出排行榜、宽表 CSV、图表和 HTML 报告
+    pub fn write(&self, dir: &Path, scale: CpuScale) -> Result<Vec<PathBuf>> {
+        std::fs::create_dir_all(dir)?;
+        let mut paths = Vec::new();
+
+        // 采集了 PSS（--system）时排行榜增加 PSS 列
+        let with_pss = !self.pss_table.is_empty();
+        let mut headers = vec![
+            "Rank",
+            "Process",
+            "Samples",
+            "Samples In Top",
+            "Mean CPU (%)",
+            "Peak CPU (%)",
+            "Mean RSS (KB)",
+            "Peak RSS (KB)",
+        ];
+        if with_pss {
+            headers.extend(["Mean PSS (KB)", "Peak PSS (KB)"]);
+        }
+        let rows: Vec<Vec<String>> = self
+            .leaderboard()
+            .iter()
+            .enumerate()
+            .map(|(i, (name, stats))| {
+                let mut row = vec![
+                    (i + 1).to_string(),
+                    name.to_string(),
+                    stats.samples.to_string(),
+                    stats.samples_in_top.to_string(),
+                    format!("{:.1}", stats.mean_cpu()),
+                    format!("{:.1}", stats.cpu_peak),
+                    format!("{:.0}", stats.mean_rss()),
+                    format!("{:.0}", stats.rss_peak),
+                ];
+                if with_pss {
+                    row.push(format!("{:.0}", stats.mean_pss()));
+                    row.push(format!("{:.0}", stats.pss_peak));
+                }
+                row
+            })
+            .collect();
+
+        let leaderboard_path = dir.join("fleet_leaderboard.csv");
+        let mut file = std::fs::File::create(&leaderboard_path)?;
+        writeln!(file, "{}", headers.join(","))?;
+        for row in &rows {
+            writeln!(file, "{}", row.join(","))?;
+        }
+        paths.push(leaderboard_path);
+
+        for (table, file_name) in [
+            (&self.cpu_table, "fleet_cpu.csv"),
+            (&self.memory_table, "fleet_memory.csv"),
+            (&self.pss_table, "fleet_pss.csv"),
+        ] {
+            if !table.is_empty() {
+                let path = dir.join(file_name);
+                table.write_csv(&path)?;
+                paths.push(path);
+            }
+        }
+
+        let mut report = HtmlReport::new("Device Fleet Health");
+        report.add_paragraph(&format!(
+            "Processes that entered the top {} by CPU, RSS or PSS in at least one sample, ranked by mean CPU ({}).",
+            self.top,
+            scale.unit()
+        ));
+        report.add_table(&headers, &rows);
+
+        let cpu_y_desc = format!("CPU Usage ({})", scale.unit());
+        for (series, file_name, title, y_desc) in [
+            (
+                self.top_series(&self.cpu_series, OffenderStats::mean_cpu),
+                "fleet_cpu.png",
+                "Top Processes by CPU",
+                cpu_y_desc.as_str(),
+            ),
+            (
+                self.top_series(&self.memory_series, OffenderStats::mean_rss),
+                "fleet_memory.png",
+                "Top Processes by RSS",
+                "RSS (KB)",
+            ),
+            (
+                self.top_series(&self.pss_series, OffenderStats::mean_pss),
+                "fleet_pss.png",
+                "Top Processes by PSS",
+                "PSS (KB)",
+            ),
+        ] {
+            if series.is_empty() {
+                continue;
+            }
+            let file_name = chart::stamped_file_name(file_name);
+            let path = dir.join(&file_name);
+            chart::draw_time_series_chart(&path, title, y_desc, &series)?;
+            report.add_image(&file_name, title);
+            paths.push(path);
+        }
+
+        let report_path = dir.join("report.html");
+        report.write(&report_path)?;
+        paths.push(report_path);
+
+        Ok(paths)
+    }
+}
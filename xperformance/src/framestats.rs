@@ -0,0 +1,645 @@
+use crate::chart::{self, TimeSeriesData};
+use crate::layout::Category;
+use crate::logcat::{EventKind, LogcatEvent};
+use crate::recorder::{Collector, FinishContext, TickContext};
+use crate::stats::Summary;
+use crate::surfaceflinger::LatencySampler;
+use crate::utils;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use clap::ValueEnum;
+use colored::*;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// 默认 60Hz 帧预算（纳秒）
+const DEFAULT_FRAME_INTERVAL_NS: u64 = 16_666_667;
+
+// 卡顿类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum JankType {
+    None,
+    /// 帧开始时已错过预定的 vsync
+    MissedVsync,
+    /// UI 线程（输入、动画、measure/layout/draw）耗时过长
+    SlowUiThread,
+    /// RenderThread 同步和提交绘制命令耗时过长
+    SlowDraw,
+}
+
+impl JankType {
+    pub const ALL: [JankType; 3] = [
+        JankType::MissedVsync,
+        JankType::SlowUiThread,
+        JankType::SlowDraw,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JankType::None => "none",
+            JankType::MissedVsync => "missed_vsync",
+            JankType::SlowUiThread => "slow_ui_thread",
+            JankType::SlowDraw => "slow_draw",
+        }
+    }
+}
+
+// framestats 中的单帧时间戳（纳秒）
+#[derive(Debug, Clone, Default)]
+pub struct FrameTiming {
+    pub intended_vsync: u64,
+    pub vsync: u64,
+    pub sync_queued: u64,
+    pub sync_start: u64,
+    pub frame_completed: u64,
+    pub frame_interval: u64,
+}
+
+fn ns_to_ms(ns: u64) -> f64 {
+    ns as f64 / 1_000_000.0
+}
+
+impl FrameTiming {
+    pub fn total_ms(&self) -> f64 {
+        ns_to_ms(self.frame_completed.saturating_sub(self.intended_vsync))
+    }
+
+    pub fn ui_thread_ms(&self) -> f64 {
+        ns_to_ms(self.sync_queued.saturating_sub(self.vsync))
+    }
+
+    pub fn render_ms(&self) -> f64 {
+        ns_to_ms(self.frame_completed.saturating_sub(self.sync_start))
+    }
+
+    pub fn vsync_delay_ms(&self) -> f64 {
+        ns_to_ms(self.vsync.saturating_sub(self.intended_vsync))
+    }
+
+    pub fn budget_ms(&self) -> f64 {
+        ns_to_ms(self.frame_interval)
+    }
+
+    pub fn classify(&self) -> JankType {
+        let budget = self.budget_ms();
+        if self.total_ms() <= budget {
+            JankType::None
+        } else if self.vsync_delay_ms() > budget {
+            JankType::MissedVsync
+        } else if self.ui_thread_ms() >= self.render_ms() {
+            JankType::SlowUiThread
+        } else {
+            JankType::SlowDraw
+        }
+    }
+}
+
+// 解析 `dumpsys gfxinfo <package> framestats` 的 PROFILEDATA 段
+pub fn parse_framestats(output: &str) -> Vec<FrameTiming> {
+    let mut frames = Vec::new();
+    let mut columns: Option<HashMap<String, usize>> = None;
+    let mut in_profile = false;
+
+    for line in output.lines() {
+        let line = line.trim();
+        if line == "---PROFILEDATA---" {
+            in_profile = !in_profile;
+            columns = None;
+            continue;
+        }
+        if !in_profile || line.is_empty() {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.trim_end_matches(',').split(',').collect();
+        if fields[0] == "Flags" {
+            columns = Some(
+                fields
+                    .iter()
+                    .enumerate()
+                    .map(|(i, name)| (name.to_string(), i))
+                    .collect(),
+            );
+            continue;
+        }
+
+        let Some(columns) = &columns else {
+            continue;
+        };
+        let get = |name: &str| -> Option<u64> {
+            columns
+                .get(name)
+                .and_then(|&i| fields.get(i))
+                .and_then(|v| v.parse().ok())
+        };
+
+        // 非零 Flags 表示该帧不具备参考价值（如首帧、窗口变化）
+        if get("Flags") != Some(0) {
+            continue;
+        }
+
+        let frame = FrameTiming {
+            intended_vsync: get("IntendedVsync").unwrap_or(0),
+            vsync: get("Vsync").unwrap_or(0),
+            sync_queued: get("SyncQueued").unwrap_or(0),
+            sync_start: get("SyncStart").unwrap_or(0),
+            frame_completed: get("FrameCompleted").unwrap_or(0),
+            frame_interval: get("FrameInterval")
+                .filter(|&v| v > 0)
+                .unwrap_or(DEFAULT_FRAME_INTERVAL_NS),
+        };
+        if frame.intended_vsync > 0 && frame.frame_completed > frame.intended_vsync {
+            frames.push(frame);
+        }
+    }
+
+    frames
+}
+
+// 帧数据来源：gfxinfo 只覆盖 HWUI 绘制的帧，SurfaceView 游戏等需改用 SurfaceFlinger
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum FrameSource {
+    /// `dumpsys gfxinfo <package> framestats`
+    #[default]
+    Gfxinfo,
+    /// `dumpsys SurfaceFlinger --latency <layer>`，图层自动查找
+    Surfaceflinger,
+}
+
+// framestats 每次返回最近约 120 帧，按 IntendedVsync 去重，只保留上次读取之后的新帧
+#[derive(Debug, Default)]
+pub struct FrameTracker {
+    last_vsync: u64,
+}
+
+impl FrameTracker {
+    pub fn take_new(&mut self, frames: Vec<FrameTiming>) -> Vec<FrameTiming> {
+        let mut new_frames: Vec<FrameTiming> = frames
+            .into_iter()
+            .filter(|f| f.intended_vsync > self.last_vsync)
+            .collect();
+        new_frames.sort_by_key(|f| f.intended_vsync);
+        if let Some(last) = new_frames.last() {
+            self.last_vsync = last.intended_vsync;
+        }
+        new_frames
+    }
+}
+
+// 一个采样周期的帧率、卡顿率和帧耗时百分位
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FrameStats {
+    pub frames: usize,
+    pub fps: f64,
+    pub jank_percent: f64,
+    pub p90_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+}
+
+impl FrameStats {
+    pub fn from_frames(frames: &[FrameTiming], secs: f64) -> Self {
+        let janky = frames
+            .iter()
+            .filter(|f| f.classify() != JankType::None)
+            .count();
+        let totals: Vec<f64> = frames.iter().map(|f| f.total_ms()).collect();
+        Self::from_durations(&totals, janky, secs)
+    }
+
+    // 由各帧耗时（ms）和卡顿帧数计算，供不同帧来源共用
+    pub fn from_durations(durations_ms: &[f64], janky: usize, secs: f64) -> Self {
+        let summary = Summary::from_values(durations_ms).unwrap_or_default();
+        Self {
+            frames: durations_ms.len(),
+            fps: durations_ms.len() as f64 / secs.max(f64::EPSILON),
+            jank_percent: janky as f64 * 100.0 / durations_ms.len().max(1) as f64,
+            p90_ms: summary.p90,
+            p95_ms: summary.p95,
+            p99_ms: summary.p99,
+        }
+    }
+}
+
+// 帧率持续低于阈值的一段时间，end 为 None 表示仍在持续
+#[derive(Debug, Clone)]
+pub struct FpsDrop {
+    pub start: DateTime<Local>,
+    pub end: Option<DateTime<Local>>,
+    pub min_fps: f64,
+}
+
+impl FpsDrop {
+    pub fn duration_secs(&self, now: DateTime<Local>) -> f64 {
+        (self.end.unwrap_or(now) - self.start).num_milliseconds() as f64 / 1000.0
+    }
+}
+
+// 跟踪帧率低于阈值的连续周期，持续达到 min_duration 时告警一次
+pub struct FpsDropDetector {
+    threshold: f64,
+    min_duration: chrono::Duration,
+    current: Option<FpsDrop>,
+    alerted: bool,
+    drops: Vec<FpsDrop>,
+}
+
+impl FpsDropDetector {
+    pub fn new(threshold: f64, min_duration: std::time::Duration) -> Self {
+        Self {
+            threshold,
+            min_duration: chrono::Duration::from_std(min_duration).unwrap_or_default(),
+            current: None,
+            alerted: false,
+            drops: Vec::new(),
+        }
+    }
+
+    pub fn threshold(&self) -> f64 {
+        self.threshold
+    }
+
+    // 已告警的低帧率区间
+    pub fn drops(&self) -> &[FpsDrop] {
+        &self.drops
+    }
+
+    // secs 为本周期长度，区间从周期开始算起；刚达到持续时长时返回该区间
+    pub fn update(
+        &mut self,
+        timestamp: DateTime<Local>,
+        secs: f64,
+        stats: &FrameStats,
+    ) -> Option<FpsDrop> {
+        // 没有新帧说明画面静止，不算掉帧
+        if stats.frames == 0 || stats.fps >= self.threshold {
+            self.reset(timestamp);
+            return None;
+        }
+        let start = timestamp - chrono::Duration::milliseconds((secs * 1000.0) as i64);
+        let current = self.current.get_or_insert(FpsDrop {
+            start,
+            end: None,
+            min_fps: stats.fps,
+        });
+        current.min_fps = current.min_fps.min(stats.fps);
+        if self.alerted {
+            if let Some(drop) = self.drops.last_mut() {
+                drop.min_fps = current.min_fps;
+            }
+            return None;
+        }
+        if timestamp - current.start < self.min_duration {
+            return None;
+        }
+        self.alerted = true;
+        self.drops.push(current.clone());
+        self.drops.last().cloned()
+    }
+
+    // 帧率恢复或预热期间结束当前区间
+    pub fn reset(&mut self, timestamp: DateTime<Local>) {
+        if self.current.take().is_some() && self.alerted {
+            if let Some(drop) = self.drops.last_mut() {
+                drop.end = Some(timestamp);
+            }
+        }
+        self.alerted = false;
+    }
+}
+
+// 导出每个周期的帧统计 CSV 和 FPS/卡顿率图表
+pub fn export_fps_data(
+    dir: &Path,
+    package: &str,
+    intervals: &[(DateTime<Local>, FrameStats)],
+) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let mut paths = Vec::new();
+
+    let csv_path = dir.join(format!("{}_fps_data.csv", package));
+    let mut file = std::fs::File::create(&csv_path)?;
+    writeln!(
+        file,
+        "Timestamp,Frames,FPS,Jank (%),P90 (ms),P95 (ms),P99 (ms)"
+    )?;
+    for (timestamp, stats) in intervals {
+        writeln!(
+            file,
+            "{},{},{:.2},{:.2},{:.2},{:.2},{:.2}",
+            timestamp.format("%Y-%m-%d %H:%M:%S"),
+            stats.frames,
+            stats.fps,
+            stats.jank_percent,
+            stats.p90_ms,
+            stats.p95_ms,
+            stats.p99_ms
+        )?;
+    }
+    paths.push(csv_path);
+
+    if intervals.len() > 1 {
+        let path = dir.join(chart::stamped_file_name(&format!(
+            "{}_fps_chart.png",
+            package
+        )));
+        let series = |f: fn(&FrameStats) -> f64| -> Vec<(DateTime<Local>, f64)> {
+            intervals.iter().map(|(t, s)| (*t, f(s))).collect()
+        };
+        chart::draw_time_series_chart(
+            &path,
+            &format!("FPS / Jank - {}", package),
+            "FPS / Jank (%)",
+            &[
+                TimeSeriesData::new("FPS", series(|s| s.fps)),
+                TimeSeriesData::new("Jank (%)", series(|s| s.jank_percent)),
+            ],
+        )?;
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
+
+// 帧统计：丢弃启动前已存在的帧，之后每个 Tick 只统计新帧
+pub struct FrameCollector {
+    package: String,
+    tracker: FrameTracker,
+    latency: Option<LatencySampler>,
+    intervals: Vec<(DateTime<Local>, FrameStats)>,
+    // 预热之后所有帧的耗时和卡顿帧数
+    totals: Vec<f64>,
+    janky: usize,
+    last_sample: Option<DateTime<Local>>,
+    interval: u64,
+    // --min-fps：持续低帧率告警，同时记入 events.csv
+    drops: Option<FpsDropDetector>,
+    events: Vec<LogcatEvent>,
+}
+
+impl FrameCollector {
+    pub fn start(
+        package: &str,
+        source: FrameSource,
+        interval: u64,
+        drops: Option<FpsDropDetector>,
+    ) -> Self {
+        let latency = match source {
+            FrameSource::Gfxinfo => {
+                let _ = utils::run_adb_command(&["shell", "dumpsys", "gfxinfo", package, "reset"]);
+                None
+            }
+            FrameSource::Surfaceflinger => {
+                let sampler = LatencySampler::new(package);
+                match sampler.layer() {
+                    Some(layer) => console!("SurfaceFlinger layer: {}", layer.cyan()),
+                    None => console!(
+                        "{}",
+                        "No SurfaceFlinger layer found yet; retrying each sample".yellow()
+                    ),
+                }
+                Some(sampler)
+            }
+        };
+        Self {
+            package: package.to_string(),
+            tracker: FrameTracker::default(),
+            latency,
+            intervals: Vec::new(),
+            totals: Vec::new(),
+            janky: 0,
+            last_sample: None,
+            interval,
+            drops,
+            events: Vec::new(),
+        }
+    }
+
+    // 各帧来源统一为帧耗时（ms）和卡顿帧数
+    fn read_frames(&mut self) -> Result<(Vec<f64>, usize)> {
+        if let Some(sampler) = self.latency.as_mut() {
+            return sampler.sample();
+        }
+        let output =
+            utils::run_adb_command(&["shell", "dumpsys", "gfxinfo", &self.package, "framestats"])?;
+        let frames = self.tracker.take_new(parse_framestats(&output));
+        let janky = frames
+            .iter()
+            .filter(|f| f.classify() != JankType::None)
+            .count();
+        Ok((frames.iter().map(|f| f.total_ms()).collect(), janky))
+    }
+}
+
+impl Collector for FrameCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        let tick = ctx.tick;
+        let (durations, janky) = match self.read_frames() {
+            Ok(frames) => frames,
+            Err(e) => {
+                if !utils::is_being_interrupted() {
+                    console!("Failed to read frame stats: {}", e);
+                }
+                return Ok(());
+            }
+        };
+        let secs = self
+            .last_sample
+            .map(|last| (tick.timestamp - last).num_milliseconds() as f64 / 1000.0)
+            .filter(|secs| *secs > 0.0)
+            .unwrap_or(self.interval.max(1) as f64);
+        self.last_sample = Some(tick.timestamp);
+        let stats = FrameStats::from_durations(&durations, janky, secs);
+        ctx.table.record(&tick, "FPS", stats.fps);
+        ctx.table.record(&tick, "Jank (%)", stats.jank_percent);
+        if stats.frames > 0 {
+            ctx.table.record(&tick, "Frame P90 (ms)", stats.p90_ms);
+            ctx.table.record(&tick, "Frame P95 (ms)", stats.p95_ms);
+            ctx.table.record(&tick, "Frame P99 (ms)", stats.p99_ms);
+        }
+        console!(
+            "[{}] FPS: {}, frames: {}, janky: {}, p90/p95/p99: {:.1}/{:.1}/{:.1} ms",
+            tick.timestamp.format("%H:%M:%S"),
+            format!("{:.1}", stats.fps).blue(),
+            stats.frames,
+            format!("{:.1}%", stats.jank_percent).red(),
+            stats.p90_ms,
+            stats.p95_ms,
+            stats.p99_ms
+        );
+        // 预热期间不累计低帧率时长
+        let fps_drop = match self.drops.as_mut() {
+            Some(detector) if ctx.in_warmup => {
+                detector.reset(tick.timestamp);
+                None
+            }
+            Some(detector) => detector
+                .update(tick.timestamp, secs, &stats)
+                .map(|fps_drop| (detector.threshold(), fps_drop)),
+            None => None,
+        };
+        if let Some((threshold, fps_drop)) = fps_drop {
+            ctx.anomaly.get_or_insert_with(|| "FPS drop".to_string());
+            console!(
+                "[{}] {} below {:.0} FPS for {:.0}s (lowest {:.1})",
+                tick.timestamp.format("%H:%M:%S").to_string().blue(),
+                "FPS drop:".red().bold(),
+                threshold,
+                fps_drop.duration_secs(tick.timestamp),
+                fps_drop.min_fps
+            );
+            chart::add_user_marker(fps_drop.start, &format!("FPS < {:.0}", threshold));
+            self.events.push(LogcatEvent {
+                timestamp: fps_drop.start,
+                kind: EventKind::FpsDrop,
+                pid: Some(ctx.sample.pid.clone()).filter(|pid| !pid.is_empty()),
+                message: format!(
+                    "FPS below {:.0} for {:.0}s (lowest {:.1})",
+                    threshold,
+                    fps_drop.duration_secs(tick.timestamp),
+                    fps_drop.min_fps
+                ),
+            });
+        }
+        // 预热期间的帧不计入卡顿率和帧耗时分位数
+        if !ctx.in_warmup {
+            self.janky += janky;
+            self.totals.extend(durations);
+        }
+        self.intervals.push((tick.timestamp, stats));
+        Ok(())
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        if self.intervals.is_empty() {
+            return;
+        }
+        if let Some(summary) = Summary::from_values(&self.totals) {
+            console!(
+                "Frames: {}, janky: {}, frame time p90 {:.1} ms, p95 {:.1} ms, p99 {:.1} ms",
+                summary.count,
+                format!("{:.1}%", self.janky as f64 * 100.0 / summary.count as f64).red(),
+                summary.p90,
+                summary.p95,
+                summary.p99
+            );
+        }
+        if let Some(detector) = self.drops.as_mut() {
+            detector.reset(Local::now());
+            let drops = detector.drops();
+            let longest = drops
+                .iter()
+                .map(|d| d.duration_secs(Local::now()))
+                .fold(0.0, f64::max);
+            console!(
+                "FPS drops below {:.0}: {}{}",
+                detector.threshold(),
+                drops.len().to_string().red(),
+                if drops.is_empty() {
+                    String::new()
+                } else {
+                    format!(", longest {:.0}s", longest)
+                }
+            );
+        }
+        match export_fps_data(
+            &ctx.layout.dir(Category::Frames),
+            ctx.package,
+            &self.intervals,
+        ) {
+            Ok(paths) => {
+                for path in paths {
+                    console!("✓ FPS data exported: {}", path.display());
+                }
+            }
+            Err(e) => console!("Failed to export FPS data: {}", e),
+        }
+    }
+
+    fn events(&self, events: &mut Vec<LogcatEvent>) {
+        events.extend(self.events.iter().cloned());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAMESTATS: &str = include_str!("../testdata/gfxinfo_framestats.txt");
+
+    #[test]
+    fn parse_framestats_skips_flagged_frames() {
+        let frames = parse_framestats(FRAMESTATS);
+        // 首帧 Flags=1，不计入
+        assert_eq!(frames.len(), 4);
+        assert_eq!(frames[0].intended_vsync, 1_000_000_000);
+        assert_eq!(frames[0].frame_interval, 16_666_666);
+        assert!((frames[0].total_ms() - 12.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn parse_framestats_ignores_rows_outside_profile_data() {
+        assert!(parse_framestats("Total frames rendered: 5\n0,1,2,3\n").is_empty());
+        // 没有表头时无法定位列
+        assert!(
+            parse_framestats("---PROFILEDATA---\n0,2001,1000,1000,\n---PROFILEDATA---\n")
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn classify_frames() {
+        let kinds: Vec<JankType> = parse_framestats(FRAMESTATS)
+            .iter()
+            .map(FrameTiming::classify)
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                JankType::None,
+                JankType::SlowUiThread,
+                JankType::SlowDraw,
+                JankType::MissedVsync
+            ]
+        );
+    }
+
+    #[test]
+    fn frame_tracker_returns_only_new_frames() {
+        let mut tracker = FrameTracker::default();
+        assert_eq!(tracker.take_new(parse_framestats(FRAMESTATS)).len(), 4);
+        // 下一次 dumpsys 仍包含已读取的帧
+        assert!(tracker.take_new(parse_framestats(FRAMESTATS)).is_empty());
+    }
+
+    #[test]
+    fn frame_stats_from_frames() {
+        let stats = FrameStats::from_frames(&parse_framestats(FRAMESTATS), 2.0);
+        assert_eq!(stats.frames, 4);
+        assert!((stats.fps - 2.0).abs() < 1e-9);
+        assert!((stats.jank_percent - 75.0).abs() < 1e-9);
+        assert!(stats.p90_ms > 25.0 && stats.p99_ms <= 34.0);
+    }
+
+    #[test]
+    fn fps_drop_detector_alerts_once_per_drop() {
+        use chrono::TimeZone;
+        let at = |secs: u32| Local.with_ymd_and_hms(2026, 10, 15, 14, 0, secs).unwrap();
+        let stats = |fps: f64| FrameStats::from_durations(&vec![16.0; fps as usize], 0, 1.0);
+        let mut detector = FpsDropDetector::new(30.0, std::time::Duration::from_secs(3));
+        assert!(detector.update(at(1), 1.0, &stats(20.0)).is_none());
+        assert!(detector.update(at(2), 1.0, &stats(12.0)).is_none());
+        let drop = detector.update(at(3), 1.0, &stats(25.0)).unwrap();
+        assert_eq!(drop.start, at(0));
+        assert_eq!(drop.min_fps, 12.0);
+        // 同一段低帧率只告警一次
+        assert!(detector.update(at(4), 1.0, &stats(8.0)).is_none());
+        assert!(detector.update(at(5), 1.0, &stats(60.0)).is_none());
+        assert_eq!(detector.drops()[0].end, Some(at(5)));
+        assert_eq!(detector.drops()[0].min_fps, 8.0);
+        // 没有新帧（画面静止）不算掉帧
+        assert!(detector.update(at(9), 4.0, &stats(0.0)).is_none());
+        assert_eq!(detector.drops().len(), 1);
+    }
+}
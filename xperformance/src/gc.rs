@@ -0,0 +1,293 @@
+use crate::chart::{self, TimeSeriesData};
+use crate::layout::Category;
+use crate::memory::MemoryChartOverlay;
+use crate::recorder::{Collector, FinishContext, TickContext};
+use crate::stats::Summary;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use colored::*;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// ART/Dalvik 打印的一次 GC
+#[derive(Debug, Clone)]
+pub struct GcEvent {
+    pub timestamp: DateTime<Local>,
+    pub pid: String,
+    // GC 原因，如 Background、Alloc、Explicit、NativeAlloc
+    pub cause: String,
+    // 回收器，如 concurrent copying、young concurrent copying
+    pub collector: String,
+    pub freed_kb: f64,
+    // 所有暂停之和
+    pub pause_ms: f64,
+    pub total_ms: Option<f64>,
+    // GC 后的堆占用和堆大小
+    pub heap_used_kb: Option<f64>,
+    pub heap_total_kb: Option<f64>,
+}
+
+// "43KB"、"5MB"、"0B"、Dalvik 的 "2049K"
+fn parse_size_kb(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let split = text.find(|c: char| !c.is_ascii_digit() && c != '.')?;
+    let value: f64 = text[..split].parse().ok()?;
+    let kb = match &text[split..] {
+        "B" => value / 1024.0,
+        "K" | "KB" => value,
+        "M" | "MB" => value * 1024.0,
+        "G" | "GB" => value * 1024.0 * 1024.0,
+        _ => return None,
+    };
+    Some(kb)
+}
+
+// "28us"、"1.234ms"、"2.5s"
+fn parse_duration_ms(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let split = text.find(|c: char| !c.is_ascii_digit() && c != '.')?;
+    let value: f64 = text[..split].parse().ok()?;
+    let ms = match &text[split..] {
+        "ns" => value / 1_000_000.0,
+        "us" => value / 1000.0,
+        "ms" => value,
+        "s" => value * 1000.0,
+        _ => return None,
+    };
+    Some(ms)
+}
+
+// threadtime 格式的 GC 行，Android 8 起 tag 为进程名，更早为 art 或 dalvikvm：
+// "10-15 12:34:56.789  1234  1250 I com.example: Background concurrent copying GC freed 198(43KB) AllocSpace objects, 0(0B) LOS objects, 49% free, 3398KB/6796KB, paused 28us,22us total 102.126ms"
+// "10-15 12:34:56.789  1234  1250 D dalvikvm: GC_CONCURRENT freed 2049K, 65% free 3571K/9991K, paused 2ms+2ms, total 25ms"
+pub fn parse_gc_line(line: &str) -> Option<GcEvent> {
+    let (header, message) = line.split_once(": ")?;
+    let pid = header.split_whitespace().nth(2)?;
+    if !pid.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let message = message.trim();
+
+    let (cause, collector, rest) = if let Some((kind, rest)) = message.split_once(" GC freed ") {
+        let (cause, collector) = kind.split_once(' ').unwrap_or((kind, ""));
+        (cause.to_string(), collector.to_string(), rest)
+    } else {
+        let message = message.strip_prefix("GC_")?;
+        let (cause, rest) = message.split_once(" freed ")?;
+        (cause.to_string(), "dalvik".to_string(), rest)
+    };
+
+    let (freed, after) = rest.split_once("% free")?;
+    // ART 每类对象为 "数量(大小)"，Android 13 起 AllocSpace 为 "大小 AllocSpace bytes"；Dalvik 直接是大小
+    let freed_kb = freed
+        .split(',')
+        .filter_map(|part| match part.split_once('(') {
+            Some((_, size)) => parse_size_kb(size.split(')').next()?),
+            None => parse_size_kb(part.split_whitespace().next()?),
+        })
+        .sum();
+
+    let heap = after
+        .split([',', ' '])
+        .find(|token| token.contains('/'))
+        .and_then(|token| token.split_once('/'));
+    let paused = after.split_once("paused ")?.1;
+    let (pauses, total) = match paused.split_once("total ") {
+        Some((pauses, total)) => (pauses, parse_duration_ms(total.trim_end_matches(','))),
+        None => (paused, None),
+    };
+    let pause_ms = pauses
+        .split([',', '+', ' '])
+        .filter(|p| !p.is_empty())
+        .filter_map(parse_duration_ms)
+        .sum();
+
+    Some(GcEvent {
+        timestamp: Local::now(),
+        pid: pid.to_string(),
+        cause,
+        collector,
+        freed_kb,
+        pause_ms,
+        total_ms: total,
+        heap_used_kb: heap.and_then(|(used, _)| parse_size_kb(used)),
+        heap_total_kb: heap.and_then(|(_, total)| parse_size_kb(total)),
+    })
+}
+
+// 按原因汇总的暂停时间，最后一行为全部 GC
+pub fn pause_summary(events: &[GcEvent]) -> Vec<(String, Summary, f64)> {
+    let mut by_cause: BTreeMap<&str, Vec<&GcEvent>> = BTreeMap::new();
+    for event in events {
+        by_cause.entry(&event.cause).or_default().push(event);
+    }
+    let row = |name: String, events: &[&GcEvent]| {
+        let pauses: Vec<f64> = events.iter().map(|e| e.pause_ms).collect();
+        let freed: f64 = events.iter().map(|e| e.freed_kb).sum();
+        Summary::from_values(&pauses).map(|summary| (name, summary, freed))
+    };
+    let mut rows: Vec<(String, Summary, f64)> = by_cause
+        .iter()
+        .filter_map(|(cause, events)| row(cause.to_string(), events))
+        .collect();
+    rows.extend(row("All".to_string(), &events.iter().collect::<Vec<_>>()));
+    rows
+}
+
+// 导出 GC 事件 CSV、按原因汇总的暂停时间表和暂停时间图
+pub fn export_gc_data(dir: &Path, package: &str, events: &[GcEvent]) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let mut paths = Vec::new();
+
+    let csv_path = dir.join(format!("{}_gc_events.csv", package));
+    let mut file = std::fs::File::create(&csv_path)?;
+    writeln!(
+        file,
+        "Timestamp,PID,Cause,Collector,Freed (KB),Pause (ms),Total (ms),Heap Used (KB),Heap Size (KB)"
+    )?;
+    let optional = |value: Option<f64>| value.map(|v| format!("{:.1}", v)).unwrap_or_default();
+    for event in events {
+        writeln!(
+            file,
+            "{},{},{},{},{:.1},{:.3},{},{},{}",
+            event.timestamp.format("%Y-%m-%d %H:%M:%S%.3f"),
+            event.pid,
+            event.cause,
+            event.collector,
+            event.freed_kb,
+            event.pause_ms,
+            optional(event.total_ms),
+            optional(event.heap_used_kb),
+            optional(event.heap_total_kb)
+        )?;
+    }
+    paths.push(csv_path);
+
+    let summary_path = dir.join(format!("{}_gc_summary.csv", package));
+    let mut file = std::fs::File::create(&summary_path)?;
+    writeln!(
+        file,
+        "Cause,Freed (KB),Total Pause (ms),Pause {}",
+        Summary::CSV_HEADER.replace(',', ",Pause ")
+    )?;
+    for (cause, summary, freed) in pause_summary(events) {
+        writeln!(
+            file,
+            "{},{:.0},{:.2},{}",
+            cause,
+            freed,
+            summary.mean * summary.count as f64,
+            summary.to_csv_row()
+        )?;
+    }
+    paths.push(summary_path);
+
+    if events.len() > 1 {
+        let path = dir.join(chart::stamped_file_name(&format!(
+            "{}_gc_pauses.png",
+            package
+        )));
+        chart::draw_time_series_chart(
+            &path,
+            &format!("GC Pause Time - {}", package),
+            "Pause (ms)",
+            &[TimeSeriesData::new(
+                "GC pause (ms)",
+                events.iter().map(|e| (e.timestamp, e.pause_ms)).collect(),
+            )],
+        )?;
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
+
+// 汇总 LogcatCollector 解析到的应用 GC，逐 Tick 记录次数和暂停时间，结束时按原因统计并导出
+#[derive(Default)]
+pub struct GcCollector {
+    events: Vec<GcEvent>,
+}
+
+impl Collector for GcCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        let tick = ctx.tick;
+        let tick_gcs = std::mem::take(&mut ctx.gc_events);
+        let pause_ms: f64 = tick_gcs.iter().map(|e| e.pause_ms).sum();
+        ctx.table.record(&tick, "GC Count", tick_gcs.len() as f64);
+        ctx.table.record(&tick, "GC Pause (ms)", pause_ms);
+        if ctx.verbose && !tick_gcs.is_empty() {
+            let causes: BTreeSet<&str> = tick_gcs.iter().map(|e| e.cause.as_str()).collect();
+            console!(
+                "[{}] GC: {} ({}), paused {:.1} ms, freed {:.1} MB",
+                tick.timestamp.format("%H:%M:%S").to_string().blue(),
+                tick_gcs.len(),
+                causes.into_iter().collect::<Vec<_>>().join(", "),
+                pause_ms,
+                tick_gcs.iter().map(|e| e.freed_kb).sum::<f64>() / 1024.0
+            );
+        }
+        self.events.extend(tick_gcs);
+        Ok(())
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        if self.events.is_empty() {
+            return;
+        }
+        console!("GC pauses by cause:");
+        for (cause, summary, freed_kb) in pause_summary(&self.events) {
+            console!(
+                "  {}: {} GCs, pause mean {:.2} ms, p95 {:.2} ms, max {} ms, freed {:.1} MB",
+                cause.cyan(),
+                summary.count,
+                summary.mean,
+                summary.p95,
+                format!("{:.2}", summary.max).yellow(),
+                freed_kb / 1024.0
+            );
+        }
+        match export_gc_data(&ctx.layout.dir(Category::Memory), ctx.package, &self.events) {
+            Ok(paths) => {
+                for path in paths {
+                    console!("✓ GC data exported: {}", path.display());
+                }
+            }
+            Err(e) => console!("Failed to export GC data: {}", e),
+        }
+    }
+
+    fn memory_overlay(&self, overlay: &mut MemoryChartOverlay) {
+        overlay
+            .gc_times
+            .extend(self.events.iter().map(|e| e.timestamp));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_gc_line_reads_art_and_dalvik() {
+        let art = parse_gc_line("10-15 12:34:56.789  1234  1250 I com.example: Background concurrent copying GC freed 198(43KB) AllocSpace objects, 0(0B) LOS objects, 49% free, 3398KB/6796KB, paused 28us,22us total 102.126ms").unwrap();
+        assert_eq!(art.pid, "1234");
+        assert_eq!(art.cause, "Background");
+        assert_eq!(art.collector, "concurrent copying");
+        assert_eq!(art.freed_kb, 43.0);
+        assert!((art.pause_ms - 0.05).abs() < 1e-9);
+        assert_eq!(art.total_ms, Some(102.126));
+        assert_eq!(art.heap_used_kb, Some(3398.0));
+        assert_eq!(art.heap_total_kb, Some(6796.0));
+
+        let dalvik = parse_gc_line("10-15 12:34:56.789  1234  1250 D dalvikvm: GC_CONCURRENT freed 2049K, 65% free 3571K/9991K, paused 2ms+2ms, total 25ms").unwrap();
+        assert_eq!(dalvik.cause, "CONCURRENT");
+        assert_eq!(dalvik.freed_kb, 2049.0);
+        assert_eq!(dalvik.pause_ms, 4.0);
+        assert_eq!(dalvik.total_ms, Some(25.0));
+
+        assert!(
+            parse_gc_line("10-15 12:34:56.789  1234  1250 I ActivityManager: Start proc").is_none()
+        );
+    }
+}
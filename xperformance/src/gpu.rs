@@ -0,0 +1,446 @@
+use crate::chart::{self, TimeSeriesData};
+use crate::layout::Category;
+use crate::recorder::{Collector, FinishContext, TickContext};
+use crate::utils;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use colored::*;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// GPU 利用率的 sysfs 来源，不同厂商的驱动路径和格式不同
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GpuSource {
+    // Adreno：直接给出百分比，如 "45 %"
+    AdrenoPercentage,
+    // Adreno：上个窗口内的 "busy total" 计数
+    AdrenoBusy,
+    // Mali（Exynos、部分 Tensor）：百分比
+    MaliKernel,
+    // Mali：misc 设备下的百分比
+    MaliUtilization,
+}
+
+impl GpuSource {
+    const ALL: [GpuSource; 4] = [
+        GpuSource::AdrenoPercentage,
+        GpuSource::AdrenoBusy,
+        GpuSource::MaliKernel,
+        GpuSource::MaliUtilization,
+    ];
+
+    pub fn path(&self) -> &'static str {
+        match self {
+            GpuSource::AdrenoPercentage => "/sys/class/kgsl/kgsl-3d0/gpu_busy_percentage",
+            GpuSource::AdrenoBusy => "/sys/class/kgsl/kgsl-3d0/gpubusy",
+            GpuSource::MaliKernel => "/sys/kernel/gpu/gpu_busy",
+            GpuSource::MaliUtilization => "/sys/class/misc/mali0/device/utilization",
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GpuSource::AdrenoPercentage | GpuSource::AdrenoBusy => "Adreno",
+            GpuSource::MaliKernel | GpuSource::MaliUtilization => "Mali",
+        }
+    }
+
+    fn parse(&self, content: &str) -> Option<f64> {
+        let numbers: Vec<f64> = content
+            .split(|c: char| c.is_whitespace() || c == '%')
+            .filter_map(|v| v.parse().ok())
+            .collect();
+        match self {
+            GpuSource::AdrenoBusy => match numbers.as_slice() {
+                [_, total] if *total == 0.0 => Some(0.0),
+                [busy, total] => Some(busy / total * 100.0),
+                _ => None,
+            },
+            _ => numbers.first().copied(),
+        }
+    }
+}
+
+// 依次尝试已知路径，返回第一个可读且格式正确的来源
+pub fn detect_source() -> Option<GpuSource> {
+    GpuSource::ALL.into_iter().find(|source| {
+        utils::run_adb_command(&["shell", "cat", source.path()])
+            .ok()
+            .and_then(|content| source.parse(&content))
+            .is_some()
+    })
+}
+
+// 读取 GPU 利用率（%）
+pub fn sample_busy(source: GpuSource) -> Result<f64> {
+    let content = utils::run_adb_command(&["shell", "cat", source.path()])?;
+    source
+        .parse(&content)
+        .map(|busy| busy.clamp(0.0, 100.0))
+        .ok_or_else(|| {
+            anyhow::format_err!("Unexpected {} content: {}", source.path(), content.trim())
+        })
+}
+
+// 利用率达到该值且频率已到最高档时视为 GPU 瓶颈，而不是调频还没升上去
+pub const BOUND_BUSY: f64 = 90.0;
+// 频率达到最大频率的该比例视为最高档
+const AT_MAX_RATIO: f64 = 0.95;
+
+// 固定路径的 GPU 频率来源：(当前频率, 最大频率, 换算为 MHz 的系数)
+// Adreno kgsl 以 Hz 为单位，Exynos 的 Mali 以 MHz 为单位
+const FIXED_FREQ_SOURCES: [(&str, &str, f64); 2] = [
+    (
+        "/sys/class/kgsl/kgsl-3d0/gpuclk",
+        "/sys/class/kgsl/kgsl-3d0/max_gpuclk",
+        1e-6,
+    ),
+    (
+        "/sys/kernel/gpu/gpu_clock",
+        "/sys/kernel/gpu/gpu_max_clock",
+        1.0,
+    ),
+];
+
+// GPU 频率的 sysfs 来源
+#[derive(Debug, Clone)]
+pub struct GpuFreqSource {
+    pub path: String,
+    pub max_mhz: Option<f64>,
+    to_mhz: f64,
+}
+
+fn read_number(path: &str) -> Option<f64> {
+    utils::run_adb_command(&["shell", "cat", path])
+        .ok()?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+impl GpuFreqSource {
+    fn probe(path: &str, max_path: &str, to_mhz: f64) -> Option<Self> {
+        read_number(path)?;
+        Some(Self {
+            path: path.to_string(),
+            max_mhz: read_number(max_path).map(|max| max * to_mhz),
+            to_mhz,
+        })
+    }
+}
+
+// 先试固定路径，再找名称含 gpu、kgsl 或 mali 的 devfreq 设备（Hz）
+pub fn detect_freq_source() -> Option<GpuFreqSource> {
+    let fixed = FIXED_FREQ_SOURCES
+        .iter()
+        .find_map(|(path, max_path, to_mhz)| GpuFreqSource::probe(path, max_path, *to_mhz));
+    fixed.or_else(|| {
+        let devices = utils::run_adb_command(&["shell", "ls", "/sys/class/devfreq"]).ok()?;
+        devices
+            .split_whitespace()
+            .filter(|name| {
+                let name = name.to_lowercase();
+                name.contains("gpu") || name.contains("kgsl") || name.contains("mali")
+            })
+            .find_map(|name| {
+                let dir = format!("/sys/class/devfreq/{}", name);
+                GpuFreqSource::probe(
+                    &format!("{}/cur_freq", dir),
+                    &format!("{}/max_freq", dir),
+                    1e-6,
+                )
+            })
+    })
+}
+
+// 读取 GPU 当前频率（MHz）
+pub fn sample_frequency(source: &GpuFreqSource) -> Result<f64> {
+    read_number(&source.path)
+        .map(|value| value * source.to_mhz)
+        .ok_or_else(|| anyhow::format_err!("Failed to read {}", source.path))
+}
+
+// 频率汇总：频率在最高档的采样占比，以及其中利用率达到 BOUND_BUSY 的采样数
+pub fn frequency_summary(
+    busy: &[(DateTime<Local>, f64)],
+    frequency: &[(DateTime<Local>, f64)],
+    max_mhz: Option<f64>,
+) -> Option<(f64, usize)> {
+    if frequency.is_empty() {
+        return None;
+    }
+    let max_mhz = max_mhz.or_else(|| frequency.iter().map(|(_, f)| *f).reduce(f64::max))?;
+    let at_max: Vec<DateTime<Local>> = frequency
+        .iter()
+        .filter(|(_, mhz)| *mhz >= max_mhz * AT_MAX_RATIO)
+        .map(|(t, _)| *t)
+        .collect();
+    let bound = busy
+        .iter()
+        .filter(|(t, value)| *value >= BOUND_BUSY && at_max.contains(t))
+        .count();
+    let share = at_max.len() as f64 * 100.0 / frequency.len().max(1) as f64;
+    Some((share, bound))
+}
+
+// 进程占用的 GPU 内存（KB），来自 dumpsys gpu --gpumem（Android 12+）
+// 格式："Proc <pid> total: <bytes>"
+pub fn sample_process_memory(pid: &str) -> Option<f64> {
+    let output = utils::run_adb_command(&["shell", "dumpsys", "gpu", "--gpumem"]).ok()?;
+    let prefix = format!("Proc {} total:", pid);
+    output.lines().find_map(|line| {
+        let bytes: f64 = line.trim().strip_prefix(&prefix)?.trim().parse().ok()?;
+        Some(bytes / 1024.0)
+    })
+}
+
+// 导出 GPU 利用率、频率和 GPU 内存的 CSV 与图表
+pub fn export_gpu_data(
+    dir: &Path,
+    package: &str,
+    busy: &[(DateTime<Local>, f64)],
+    frequency: &[(DateTime<Local>, f64)],
+    memory: &[(DateTime<Local>, f64)],
+) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let mut paths = Vec::new();
+
+    let csv_path = dir.join(format!("{}_gpu_data.csv", package));
+    let mut file = std::fs::File::create(&csv_path)?;
+    writeln!(
+        file,
+        "Timestamp,GPU Busy (%),GPU Frequency (MHz),GPU Memory (KB)"
+    )?;
+    // 各列的时间戳取并集，没有利用率或频率来源时仍导出其余列
+    let mut rows: BTreeMap<DateTime<Local>, [Option<f64>; 3]> = BTreeMap::new();
+    for (column, points) in [busy, frequency, memory].into_iter().enumerate() {
+        for (timestamp, value) in points {
+            rows.entry(*timestamp).or_default()[column] = Some(*value);
+        }
+    }
+    for (timestamp, [busy, frequency, memory]) in rows {
+        writeln!(
+            file,
+            "{},{},{},{}",
+            timestamp.format("%Y-%m-%d %H:%M:%S"),
+            busy.map(|v| format!("{:.2}", v)).unwrap_or_default(),
+            frequency
+                .map(|mhz| format!("{:.0}", mhz))
+                .unwrap_or_default(),
+            memory.map(|kb| format!("{:.0}", kb)).unwrap_or_default()
+        )?;
+    }
+    paths.push(csv_path);
+
+    // 利用率和频率画在同一张双纵轴图上，区分调频和 GPU 瓶颈
+    let dual = busy.len() > 1 && frequency.len() > 1;
+    if dual {
+        let path = dir.join(chart::stamped_file_name(&format!(
+            "{}_gpu_busy.png",
+            package
+        )));
+        chart::draw_dual_axis_chart(
+            &path,
+            &format!("GPU Busy and Frequency - {}", package),
+            "GPU Busy (%)",
+            &[TimeSeriesData::new("GPU Busy", busy.to_vec())],
+            "GPU Frequency (MHz)",
+            &[TimeSeriesData::new("GPU Frequency", frequency.to_vec())],
+        )?;
+        paths.push(path);
+    }
+
+    // 已画在双纵轴图中的不再单独画
+    for (points, single, file_name, title, y_desc) in [
+        (busy, !dual, "gpu_busy.png", "GPU Busy", "GPU Busy (%)"),
+        (
+            frequency,
+            !dual,
+            "gpu_frequency.png",
+            "GPU Frequency",
+            "GPU Frequency (MHz)",
+        ),
+        (
+            memory,
+            true,
+            "gpu_memory.png",
+            "GPU Memory",
+            "GPU Memory (KB)",
+        ),
+    ] {
+        if single && points.len() > 1 {
+            let path = dir.join(chart::stamped_file_name(&format!(
+                "{}_{}",
+                package, file_name
+            )));
+            chart::draw_time_series_chart(
+                &path,
+                &format!("{} - {}", title, package),
+                y_desc,
+                &[TimeSeriesData::new(title, points.to_vec())],
+            )?;
+            paths.push(path);
+        }
+    }
+
+    Ok(paths)
+}
+
+// 每个 Tick 的 GPU 利用率和应用 GPU 内存
+pub struct GpuCollector {
+    source: Option<GpuSource>,
+    freq_source: Option<GpuFreqSource>,
+    busy: Vec<(DateTime<Local>, f64)>,
+    frequency: Vec<(DateTime<Local>, f64)>,
+    memory: Vec<(DateTime<Local>, f64)>,
+}
+
+impl GpuCollector {
+    // GPU 利用率来源因厂商而异，启动时探测一次
+    pub fn start() -> Self {
+        let source = detect_source();
+        match source {
+            Some(source) => console!("GPU: {} ({})", source.as_str().cyan(), source.path()),
+            None => console!(
+                "{}",
+                "GPU busy counters not readable on this device; recording GPU memory only".yellow()
+            ),
+        }
+        let freq_source = detect_freq_source();
+        if let Some(source) = &freq_source {
+            console!(
+                "GPU frequency: {}{}",
+                source.path,
+                source
+                    .max_mhz
+                    .map(|max| format!(" (max {:.0} MHz)", max))
+                    .unwrap_or_default()
+            );
+        }
+        Self {
+            source,
+            freq_source,
+            busy: Vec::new(),
+            frequency: Vec::new(),
+            memory: Vec::new(),
+        }
+    }
+}
+
+impl Collector for GpuCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        let tick = ctx.tick;
+        let busy = self.source.and_then(|source| sample_busy(source).ok());
+        let gpu_freq = self
+            .freq_source
+            .as_ref()
+            .and_then(|source| sample_frequency(source).ok());
+        let gpu_memory = sample_process_memory(&ctx.sample.pid);
+        if let Some(busy) = busy {
+            ctx.table.record(&tick, "GPU Busy (%)", busy);
+            self.busy.push((tick.timestamp, busy));
+        }
+        if let Some(mhz) = gpu_freq {
+            ctx.table.record(&tick, "GPU Frequency (MHz)", mhz);
+            self.frequency.push((tick.timestamp, mhz));
+        }
+        if let Some(kb) = gpu_memory {
+            ctx.table.record(&tick, "GPU Memory (KB)", kb);
+            self.memory.push((tick.timestamp, kb));
+        }
+        if busy.is_some() || gpu_freq.is_some() || gpu_memory.is_some() {
+            console!(
+                "[{}] GPU Busy: {}, GPU Freq: {}, GPU Memory: {}",
+                tick.timestamp.format("%H:%M:%S"),
+                busy.map(|b| format!("{:.1}%", b))
+                    .unwrap_or_else(|| "-".to_string())
+                    .magenta(),
+                gpu_freq
+                    .map(|mhz| format!("{:.0} MHz", mhz))
+                    .unwrap_or_else(|| "-".to_string()),
+                gpu_memory
+                    .map(|kb| format!("{:.0} KB", kb))
+                    .unwrap_or_else(|| "-".to_string())
+            );
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        if self.busy.is_empty() && self.frequency.is_empty() && self.memory.is_empty() {
+            return;
+        }
+        if let Some(peak) = self
+            .busy
+            .iter()
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+        {
+            let mean = self.busy.iter().map(|(_, v)| v).sum::<f64>() / self.busy.len() as f64;
+            console!(
+                "GPU Busy: mean {:.1}%, peak {} at {}",
+                mean,
+                format!("{:.1}%", peak.1).red(),
+                peak.0.format("%Y-%m-%d %H:%M:%S")
+            );
+        }
+        if let Some((at_max, bound)) = frequency_summary(
+            &self.busy,
+            &self.frequency,
+            self.freq_source.as_ref().and_then(|s| s.max_mhz),
+        ) {
+            let mean =
+                self.frequency.iter().map(|(_, v)| v).sum::<f64>() / self.frequency.len() as f64;
+            // 高利用率但频率没到顶说明调频偏保守；频率到顶仍满载才是 GPU 瓶颈
+            console!(
+                "GPU Frequency: mean {:.0} MHz, at max {:.0}% of samples, GPU-bound (busy >= {:.0}% at max) {} samples",
+                mean,
+                at_max,
+                BOUND_BUSY,
+                if bound > 0 {
+                    bound.to_string().red()
+                } else {
+                    bound.to_string().green()
+                }
+            );
+        }
+        match export_gpu_data(
+            &ctx.layout.dir(Category::Gpu),
+            ctx.package,
+            &self.busy,
+            &self.frequency,
+            &self.memory,
+        ) {
+            Ok(paths) => {
+                for path in paths {
+                    console!("✓ GPU data exported: {}", path.display());
+                }
+            }
+            Err(e) => console!("Failed to export GPU data: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn frequency_summary_counts_bound_samples_at_max() {
+        let at = |secs: u32| Local.with_ymd_and_hms(2026, 10, 15, 14, 0, secs).unwrap();
+        let frequency = [
+            (at(0), 315.0),
+            (at(1), 900.0),
+            (at(2), 900.0),
+            (at(3), 585.0),
+        ];
+        let busy = [(at(0), 97.0), (at(1), 96.0), (at(2), 40.0), (at(3), 99.0)];
+        let (share, bound) = frequency_summary(&busy, &frequency, Some(900.0)).unwrap();
+        assert_eq!(share, 50.0);
+        // 只有频率到顶时的高利用率才算 GPU 瓶颈
+        assert_eq!(bound, 1);
+        assert!(frequency_summary(&busy, &[], Some(900.0)).is_none());
+    }
+}
@@ -0,0 +1,136 @@
+use crate::utils;
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+// 设备端临时目录，shell 用户和应用进程均可写
+const REMOTE_DIR: &str = "/data/local/tmp";
+// am dumpheap 立即返回，文件由应用进程异步写入；大小连续不变视为写完
+const STABLE_CHECKS: u32 = 3;
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone)]
+pub struct HeapDump {
+    // 设备上原始格式的 hprof（native 堆为文本格式）
+    pub raw: PathBuf,
+    // hprof-conv 转换后的标准格式，可直接在 Android Studio / MAT 中打开
+    pub converted: Option<PathBuf>,
+}
+
+fn remote_size(path: &str) -> Option<u64> {
+    utils::run_adb_command(&["shell", "stat", "-c", "%s", path])
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+// 触发 am dumpheap 并等待文件写完，返回设备端路径
+pub fn dump_heap(package: &str, pid: &str, native: bool, timeout: Duration) -> Result<String> {
+    let suffix = if native { "native.txt" } else { "hprof" };
+    let remote_path = format!(
+        "{}/{}_{}.{}",
+        REMOTE_DIR,
+        package,
+        chrono::Local::now().format("%Y%m%d_%H%M%S"),
+        suffix
+    );
+
+    let mut args = vec!["shell", "am", "dumpheap"];
+    if native {
+        args.push("-n");
+    }
+    args.extend([pid, remote_path.as_str()]);
+    let output = utils::run_adb_command(&args)?;
+    if output.contains("Error") || output.contains("Exception") {
+        anyhow::bail!("am dumpheap failed: {}", output.trim());
+    }
+
+    let deadline = Instant::now() + timeout;
+    let mut last_size = None;
+    let mut stable = 0;
+    while stable < STABLE_CHECKS {
+        if Instant::now() >= deadline {
+            anyhow::bail!(
+                "Heap dump {} not complete after {}s",
+                remote_path,
+                timeout.as_secs()
+            );
+        }
+        if utils::is_being_interrupted() {
+            anyhow::bail!("Interrupted while waiting for the heap dump");
+        }
+        std::thread::sleep(POLL_INTERVAL);
+        let size = remote_size(&remote_path).filter(|size| *size > 0);
+        if size.is_some() && size == last_size {
+            stable += 1;
+        } else {
+            stable = 0;
+        }
+        last_size = size;
+    }
+    Ok(remote_path)
+}
+
+// 拉取到本地目录并删除设备端文件
+pub fn pull(remote_path: &str, output_dir: &Path) -> Result<PathBuf> {
+    std::fs::create_dir_all(output_dir)?;
+    let file_name = remote_path.rsplit('/').next().unwrap_or("heap.hprof");
+    let local_path = output_dir.join(file_name);
+    utils::run_adb_command(&["pull", remote_path, &local_path.to_string_lossy()])?;
+    let _ = utils::run_adb_command(&["shell", "rm", "-f", remote_path]);
+    Ok(local_path)
+}
+
+// ANDROID_HOME（或 ANDROID_SDK_ROOT）下 platform-tools 中的 hprof-conv
+pub fn find_hprof_conv() -> Option<PathBuf> {
+    let name = if cfg!(windows) {
+        "hprof-conv.exe"
+    } else {
+        "hprof-conv"
+    };
+    ["ANDROID_HOME", "ANDROID_SDK_ROOT"]
+        .iter()
+        .filter_map(std::env::var_os)
+        .map(|sdk| PathBuf::from(sdk).join("platform-tools").join(name))
+        .find(|path| path.is_file())
+}
+
+// 将 ART 格式的 hprof 转换为标准 J2SE 格式，输出为 <name>-conv.hprof
+pub fn convert(hprof_conv: &Path, raw: &Path) -> Result<PathBuf> {
+    let stem = raw
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "heap".to_string());
+    let converted = raw.with_file_name(format!("{}-conv.hprof", stem));
+    let output = Command::new(hprof_conv)
+        .arg(raw)
+        .arg(&converted)
+        .output()
+        .with_context(|| format!("Failed to execute {}", hprof_conv.display()))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "hprof-conv failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(converted)
+}
+
+// 完整流程：dump、拉取，Java 堆在找到 hprof-conv 时转换
+pub fn capture(
+    package: &str,
+    pid: &str,
+    native: bool,
+    timeout: Duration,
+    output_dir: &Path,
+) -> Result<HeapDump> {
+    let remote_path = dump_heap(package, pid, native, timeout)?;
+    let raw = pull(&remote_path, output_dir)?;
+    let converted = match find_hprof_conv().filter(|_| !native) {
+        Some(hprof_conv) => Some(convert(&hprof_conv, &raw)?),
+        None => None,
+    };
+    Ok(HeapDump { raw, converted })
+}
@@ -0,0 +1,507 @@
+use crate::chart::{self, Band, TimelineLane};
+use crate::layout::Category;
+use crate::memory::MemoryChartOverlay;
+use crate::recorder::{Collector, FinishContext, TickContext};
+use crate::regenerate::{self, CSV_TIMESTAMP_FORMAT};
+use crate::utils::{self, CpuChartOverlay};
+use crate::wakelock;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use colored::*;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// 时间线图中最多显示的作业数（按总运行时长）
+const MAX_TIMELINE_LANES: usize = 30;
+
+// dumpsys jobscheduler 的 "Active jobs:" 段中正在运行的一个作业
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunningJob {
+    // UID/作业 ID，如 u0a123/42
+    pub id: String,
+    // JobService 组件，如 com.foo/.SyncJobService
+    pub component: String,
+    pub running_ms: Option<i64>,
+}
+
+impl RunningJob {
+    // 图例和控制台中的名称：JobService 类名和作业 ID，如 SyncJobService #42
+    pub fn name(&self) -> String {
+        let class = self.component.rsplit(['/', '.']).next().unwrap_or_default();
+        let job_id = self.id.rsplit('/').next().unwrap_or_default();
+        format!("{} #{}", class, job_id)
+    }
+}
+
+// 解析正在运行的作业，各版本格式不同：
+//   Slot #0(ID=3): #u0a123/42 com.foo/.SyncJobService
+//   Slot #1: JobStatus{8e1a3b5 #u0a123/42 com.foo/.SyncJobService u=0 s=10123}
+//     Running for: +1s234ms, timeout at: +9m58s766ms
+// 空闲的槽位为 "Slot #2: inactive since ..."
+pub fn parse_active_jobs(output: &str, package: &str) -> Vec<RunningJob> {
+    let component_prefix = format!("{}/", package);
+    let mut jobs = Vec::new();
+    let mut section_indent: Option<usize> = None;
+    // 上一个槽位是否为该应用的作业，其后的 Running for 行属于它
+    let mut in_job = false;
+    for line in output.lines() {
+        let trimmed = line.trim();
+        let indent = line.len() - line.trim_start().len();
+        if trimmed == "Active jobs:" {
+            section_indent = Some(indent);
+            continue;
+        }
+        let Some(section) = section_indent else {
+            continue;
+        };
+        if trimmed.is_empty() {
+            continue;
+        }
+        if indent <= section {
+            break;
+        }
+        if trimmed.starts_with("Slot #") {
+            in_job = false;
+            if trimmed.contains("inactive") {
+                continue;
+            }
+            let tokens: Vec<&str> = trimmed
+                .split_whitespace()
+                .skip(2)
+                .map(|t| t.trim_end_matches([':', '}', ',']))
+                .collect();
+            let id = tokens
+                .iter()
+                .find(|t| t.starts_with('#') && t.contains('/'))
+                .map(|t| t.trim_start_matches('#'));
+            let component = tokens.iter().find(|t| t.starts_with(&component_prefix));
+            if let (Some(id), Some(component)) = (id, component) {
+                jobs.push(RunningJob {
+                    id: id.to_string(),
+                    component: component.to_string(),
+                    running_ms: None,
+                });
+                in_job = true;
+            }
+        } else if let Some(rest) = trimmed.strip_prefix("Running for: ").filter(|_| in_job) {
+            if let Some(job) = jobs.last_mut() {
+                job.running_ms = rest
+                    .split([',', ' '])
+                    .next()
+                    .and_then(wakelock::parse_dumpsys_duration);
+            }
+        }
+    }
+    jobs
+}
+
+pub fn sample_running_jobs(package: &str) -> Result<Vec<RunningJob>> {
+    let output = utils::run_adb_command(&["shell", "dumpsys", "jobscheduler", package])?;
+    if !output.contains("Active jobs:") {
+        anyhow::bail!("No 'Active jobs' section in dumpsys jobscheduler");
+    }
+    Ok(parse_active_jobs(&output, package))
+}
+
+// 一次作业执行：开始时间来自 Running for，结束时间为首次发现已停止的采样时间（上界）
+#[derive(Debug, Clone)]
+pub struct JobRun {
+    pub name: String,
+    pub component: String,
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    pub running_at_end: bool,
+}
+
+impl JobRun {
+    pub fn duration_secs(&self) -> f64 {
+        (self.end - self.start).num_milliseconds() as f64 / 1000.0
+    }
+}
+
+// 同一作业的汇总
+#[derive(Debug, Clone, Default)]
+pub struct JobStats {
+    pub name: String,
+    pub runs: u32,
+    pub total_secs: f64,
+    pub longest_secs: f64,
+}
+
+// 一次采样中发生的变化
+#[derive(Debug, Default)]
+pub struct JobChanges {
+    pub started: Vec<String>,
+    pub finished: Vec<JobRun>,
+}
+
+// 运行中的作业：名称、组件和开始时间
+type ActiveJob = (String, String, DateTime<Local>);
+
+// 跟踪作业的开始与结束。两次采样之间开始并结束的作业无法观察到；
+// Running for 算出的开始时间明显晚于已记录的时间时，视为上次已结束后重新调度
+#[derive(Debug, Default)]
+pub struct JobTracker {
+    start: Option<DateTime<Local>>,
+    active: HashMap<String, ActiveJob>,
+    pub runs: Vec<JobRun>,
+    pub samples: Vec<(DateTime<Local>, usize)>,
+}
+
+impl JobTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 由已导出的执行区间重建，用于重新生成图表
+    pub fn from_runs(runs: Vec<JobRun>) -> Self {
+        Self {
+            runs,
+            ..Self::default()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    fn close(&mut self, job: ActiveJob, end: DateTime<Local>, running_at_end: bool) -> JobRun {
+        let (name, component, start) = job;
+        let run = JobRun {
+            name,
+            component,
+            start,
+            end,
+            running_at_end,
+        };
+        self.runs.push(run.clone());
+        run
+    }
+
+    pub fn record(&mut self, timestamp: DateTime<Local>, jobs: &[RunningJob]) -> JobChanges {
+        // 开始监控之前已在运行的从开始监控时算起
+        let start = *self.start.get_or_insert(timestamp);
+        let mut changes = JobChanges::default();
+        let mut active = HashMap::new();
+        for job in jobs {
+            if active.contains_key(&job.id) {
+                continue;
+            }
+            let started = job
+                .running_ms
+                .map(|ms| timestamp - chrono::Duration::milliseconds(ms))
+                .unwrap_or(timestamp)
+                .max(start);
+            let started = match self.active.remove(&job.id) {
+                Some(previous)
+                    if started > previous.2 + chrono::Duration::seconds(1)
+                        && job.running_ms.is_some() =>
+                {
+                    let run = self.close(previous, started, false);
+                    changes.finished.push(run);
+                    changes.started.push(job.name());
+                    started
+                }
+                Some(previous) => previous.2,
+                None => {
+                    changes.started.push(job.name());
+                    started
+                }
+            };
+            active.insert(job.id.clone(), (job.name(), job.component.clone(), started));
+        }
+        for (_, job) in std::mem::take(&mut self.active) {
+            let run = self.close(job, timestamp, false);
+            changes.finished.push(run);
+        }
+        self.active = active;
+        self.samples.push((timestamp, self.active.len()));
+        changes
+    }
+
+    // 结束监控时仍在运行的记为运行到最后一次采样
+    pub fn finish(&mut self) {
+        let Some(end) = self.samples.last().map(|(t, _)| *t) else {
+            return;
+        };
+        for (_, job) in std::mem::take(&mut self.active) {
+            self.close(job, end, true);
+        }
+        self.runs.sort_by_key(|run| run.start);
+    }
+
+    // CPU 和内存图表上的作业执行区间底纹，仍在运行的画到 now
+    pub fn bands(&self, now: DateTime<Local>) -> Vec<Band> {
+        let running = self
+            .active
+            .values()
+            .map(|(name, _, start)| (name, *start, now));
+        self.runs
+            .iter()
+            .map(|run| (&run.name, run.start, run.end))
+            .chain(running)
+            .map(|(name, start, end)| Band {
+                start,
+                end,
+                label: format!("Job {}", name),
+                muted: false,
+            })
+            .collect()
+    }
+}
+
+// 按作业汇总，总运行时长降序
+pub fn job_stats(runs: &[JobRun]) -> Vec<JobStats> {
+    let mut stats: HashMap<&str, JobStats> = HashMap::new();
+    for run in runs {
+        let entry = stats.entry(&run.name).or_insert_with(|| JobStats {
+            name: run.name.clone(),
+            ..Default::default()
+        });
+        let secs = run.duration_secs();
+        entry.runs += 1;
+        entry.total_secs += secs;
+        entry.longest_secs = entry.longest_secs.max(secs);
+    }
+    let mut stats: Vec<JobStats> = stats.into_values().collect();
+    stats.sort_by(|a, b| {
+        b.total_secs
+            .partial_cmp(&a.total_secs)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    stats
+}
+
+pub fn write_runs_csv(path: &Path, runs: &[JobRun]) -> Result<()> {
+    let mut file = std::fs::File::create(path)?;
+    writeln!(file, "Job,Component,Start,End,Duration (s),Running At End")?;
+    for run in runs {
+        writeln!(
+            file,
+            "{},{},{},{},{:.1},{}",
+            run.name,
+            run.component,
+            run.start.format(CSV_TIMESTAMP_FORMAT),
+            run.end.format(CSV_TIMESTAMP_FORMAT),
+            run.duration_secs(),
+            run.running_at_end
+        )?;
+    }
+    Ok(())
+}
+
+// 读取 write_runs_csv 写出的文件，用于重新生成图表
+pub fn read_runs_csv(path: &Path) -> Result<Vec<JobRun>> {
+    let table = regenerate::read_csv(path)?;
+    Ok(table
+        .rows
+        .iter()
+        .filter_map(|row| {
+            Some(JobRun {
+                name: row.first()?.clone(),
+                component: row.get(1)?.clone(),
+                start: regenerate::parse_timestamp(row.get(2)?)?,
+                end: regenerate::parse_timestamp(row.get(3)?)?,
+                running_at_end: row.get(5).is_some_and(|v| v == "true"),
+            })
+        })
+        .collect())
+}
+
+// 导出每次采样的运行作业数 CSV、每次执行的区间 CSV 和时间线图
+pub fn export_job_data(dir: &Path, package: &str, tracker: &JobTracker) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let mut paths = Vec::new();
+
+    let csv_path = dir.join(format!("{}_job_data.csv", package));
+    let mut file = std::fs::File::create(&csv_path)?;
+    writeln!(file, "Timestamp,Running Jobs")?;
+    for (timestamp, running) in &tracker.samples {
+        writeln!(
+            file,
+            "{},{}",
+            timestamp.format(CSV_TIMESTAMP_FORMAT),
+            running
+        )?;
+    }
+    paths.push(csv_path);
+
+    let runs_path = dir.join(format!("{}_jobs.csv", package));
+    write_runs_csv(&runs_path, &tracker.runs)?;
+    paths.push(runs_path);
+
+    if !tracker.runs.is_empty() {
+        let lanes: Vec<TimelineLane> = job_stats(&tracker.runs)
+            .into_iter()
+            .take(MAX_TIMELINE_LANES)
+            .map(|stats| {
+                TimelineLane::new(
+                    &stats.name,
+                    tracker
+                        .runs
+                        .iter()
+                        .filter(|run| run.name == stats.name)
+                        .map(|run| (run.start, run.end))
+                        .collect(),
+                )
+            })
+            .collect();
+        let path = dir.join(chart::stamped_file_name(&format!(
+            "{}_job_timeline.png",
+            package
+        )));
+        chart::draw_timeline_chart(&path, &format!("Job Timeline - {}", package), &lanes)?;
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
+
+// --jobs：跟踪应用正在运行的 JobScheduler 作业，解释周期性的后台尖峰
+#[derive(Default)]
+pub struct JobCollector {
+    tracker: JobTracker,
+}
+
+impl Collector for JobCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        let tick = ctx.tick;
+        let running = match sample_running_jobs(ctx.package) {
+            Ok(running) => running,
+            Err(e) => {
+                if !utils::is_being_interrupted() {
+                    console!("Failed to sample jobs: {}", e);
+                }
+                return Ok(());
+            }
+        };
+        let changes = self.tracker.record(tick.timestamp, &running);
+        for name in &changes.started {
+            console!(
+                "[{}] {} {}",
+                tick.timestamp.format("%H:%M:%S").to_string().blue(),
+                "Job started:".yellow(),
+                name
+            );
+        }
+        for run in &changes.finished {
+            console!(
+                "[{}] {} {} ({:.1}s)",
+                tick.timestamp.format("%H:%M:%S").to_string().blue(),
+                "Job finished:".green(),
+                run.name,
+                run.duration_secs()
+            );
+        }
+        ctx.table
+            .record(&tick, "Running Jobs", running.len() as f64);
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        self.tracker.finish();
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        if self.tracker.is_empty() {
+            return;
+        }
+        let stats = job_stats(&self.tracker.runs);
+        console!(
+            "Jobs: {} runs, {:.1} s running in total",
+            self.tracker.runs.len(),
+            stats.iter().map(|s| s.total_secs).sum::<f64>()
+        );
+        for stats in stats.iter().take(5) {
+            console!(
+                "  {}: {} runs, total {} s, longest {:.1} s",
+                stats.name.cyan(),
+                stats.runs,
+                format!("{:.1}", stats.total_secs).red(),
+                stats.longest_secs
+            );
+        }
+        match export_job_data(
+            &ctx.layout.dir(Category::Process),
+            ctx.package,
+            &self.tracker,
+        ) {
+            Ok(paths) => {
+                for path in paths {
+                    console!("✓ Job data exported: {}", path.display());
+                }
+            }
+            Err(e) => console!("Failed to export job data: {}", e),
+        }
+    }
+
+    fn cpu_overlay(&self, overlay: &mut CpuChartOverlay) {
+        overlay.bands.extend(self.tracker.bands(Local::now()));
+    }
+
+    fn memory_overlay(&self, overlay: &mut MemoryChartOverlay) {
+        overlay.bands.extend(self.tracker.bands(Local::now()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_active_jobs_both_formats() {
+        let output = "\
+Active jobs:
+    Slot #0(ID=3): #u0a123/42 com.foo/.SyncJobService
+      Running for: +1s234ms, timeout at: +9m58s766ms
+    Slot #1: JobStatus{8e1a3b5 #u0a123/7 com.foo/com.foo.work.UploadService u=0 s=10123}
+    Slot #2: JobStatus{8e1a3b6 #u0a124/1 com.bar/.BarJob u=0 s=10124}
+      Running for: +5s, timeout at: +9m
+    Slot #3: inactive since -1m2s
+Pending queue:
+    Slot #4(ID=9): #u0a123/9 com.foo/.LaterJob
+";
+        let jobs = parse_active_jobs(output, "com.foo");
+        assert_eq!(
+            jobs,
+            vec![
+                RunningJob {
+                    id: "u0a123/42".into(),
+                    component: "com.foo/.SyncJobService".into(),
+                    running_ms: Some(1234),
+                },
+                RunningJob {
+                    id: "u0a123/7".into(),
+                    component: "com.foo/com.foo.work.UploadService".into(),
+                    running_ms: None,
+                },
+            ]
+        );
+        assert_eq!(jobs[0].name(), "SyncJobService #42");
+        assert_eq!(jobs[1].name(), "UploadService #7");
+    }
+
+    #[test]
+    fn tracker_closes_finished_jobs() {
+        let job = RunningJob {
+            id: "u0a123/42".into(),
+            component: "com.foo/.SyncJobService".into(),
+            running_ms: Some(2000),
+        };
+        let start = Local::now();
+        let mut tracker = JobTracker::new();
+        let changes = tracker.record(start, std::slice::from_ref(&job));
+        assert_eq!(changes.started, ["SyncJobService #42"]);
+        let changes = tracker.record(start + chrono::Duration::seconds(5), &[]);
+        assert_eq!(changes.finished.len(), 1);
+        assert_eq!(changes.finished[0].start, start);
+        assert_eq!(changes.finished[0].duration_secs(), 5.0);
+
+        tracker.record(start + chrono::Duration::seconds(10), &[job]);
+        tracker.finish();
+        assert_eq!(tracker.runs.len(), 2);
+        assert!(tracker.runs[1].running_at_end);
+        assert_eq!(job_stats(&tracker.runs)[0].runs, 2);
+    }
+}
@@ -0,0 +1,185 @@
+use crate::cpu::ThreadCpuInfo;
+use crate::layout::OutputLayout;
+use crate::memory::MemoryDetails;
+use crate::recorder::{Collector, FinishContext, TickContext};
+use crate::tick::Tick;
+use anyhow::Result;
+use serde::Serialize;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+// 每行一条采样记录，type 字段区分类型，同一 Tick 的记录共享 tick 和 timestamp
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SampleRecord<'a> {
+    Cpu {
+        tick: u64,
+        timestamp: String,
+        pid: &'a str,
+        cpu: f32,
+    },
+    Memory {
+        tick: u64,
+        timestamp: String,
+        pid: &'a str,
+        total_pss_kb: u64,
+        java_heap_kb: u64,
+        native_heap_kb: u64,
+        code_kb: u64,
+        stack_kb: u64,
+        graphics_kb: u64,
+        private_other_kb: u64,
+        system_kb: u64,
+        dalvik_heap_size_kb: u64,
+        dalvik_heap_alloc_kb: u64,
+        native_heap_size_kb: u64,
+        native_heap_alloc_kb: u64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        uss_kb: Option<u64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        rss_kb: Option<u64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        swap_kb: Option<u64>,
+    },
+    Thread {
+        tick: u64,
+        timestamp: String,
+        pid: &'a str,
+        tid: &'a str,
+        name: &'a str,
+        cpu: f32,
+    },
+}
+
+fn timestamp(tick: &Tick) -> String {
+    tick.timestamp.to_rfc3339()
+}
+
+impl<'a> SampleRecord<'a> {
+    pub fn cpu(tick: &Tick, pid: &'a str, cpu: f32) -> Self {
+        SampleRecord::Cpu {
+            tick: tick.id,
+            timestamp: timestamp(tick),
+            pid,
+            cpu,
+        }
+    }
+
+    pub fn memory(tick: &Tick, pid: &'a str, details: &MemoryDetails) -> Self {
+        SampleRecord::Memory {
+            tick: tick.id,
+            timestamp: timestamp(tick),
+            pid,
+            total_pss_kb: details.total_pss,
+            java_heap_kb: details.java_heap,
+            native_heap_kb: details.native_heap,
+            code_kb: details.code,
+            stack_kb: details.stack,
+            graphics_kb: details.graphics,
+            private_other_kb: details.private_other,
+            system_kb: details.system,
+            dalvik_heap_size_kb: details.dalvik_heap_detail.size,
+            dalvik_heap_alloc_kb: details.dalvik_heap_detail.alloc,
+            native_heap_size_kb: details.native_heap_detail.size,
+            native_heap_alloc_kb: details.native_heap_detail.alloc,
+            uss_kb: details.smaps.map(|s| s.uss),
+            rss_kb: details.smaps.map(|s| s.rss),
+            swap_kb: details.smaps.map(|s| s.swap),
+        }
+    }
+
+    pub fn thread(tick: &Tick, pid: &'a str, thread: &'a ThreadCpuInfo) -> Self {
+        SampleRecord::Thread {
+            tick: tick.id,
+            timestamp: timestamp(tick),
+            pid,
+            tid: &thread.tid,
+            name: &thread.name,
+            cpu: thread.cpu_usage,
+        }
+    }
+}
+
+// 采样过程中逐条追加写入，中途退出时已写入的记录仍然可用
+pub struct JsonlWriter {
+    path: PathBuf,
+    writer: BufWriter<std::fs::File>,
+}
+
+impl JsonlWriter {
+    pub fn create(dir: &Path, package: &str) -> Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(format!("{}_samples.jsonl", package));
+        let writer = BufWriter::new(std::fs::File::create(&path)?);
+        Ok(Self { path, writer })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn write(&mut self, record: &SampleRecord) -> Result<()> {
+        serde_json::to_writer(&mut self.writer, record)?;
+        writeln!(self.writer)?;
+        Ok(())
+    }
+
+    // 每个 Tick 结束时刷新
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+// --format json 时逐条写入 CPU、线程和内存记录，每个 Tick 等待前刷新
+pub struct JsonlCollector {
+    writer: JsonlWriter,
+    threads: bool,
+}
+
+impl JsonlCollector {
+    pub fn create(package: &str, threads: bool) -> Result<Self> {
+        Ok(Self {
+            writer: JsonlWriter::create(OutputLayout::session(package)?.session_dir(), package)?,
+            threads,
+        })
+    }
+}
+
+impl Collector for JsonlCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        let tick = ctx.tick;
+        let sample = ctx.sample;
+        let pid = sample.pid.as_str();
+        if let Some(cpu) = sample.cpu {
+            let _ = self.writer.write(&SampleRecord::cpu(&tick, pid, cpu));
+            if self.threads {
+                for thread in &sample.threads {
+                    let _ = self.writer.write(&SampleRecord::thread(&tick, pid, thread));
+                }
+            }
+        }
+        if let Some(details) = &sample.memory {
+            let _ = self
+                .writer
+                .write(&SampleRecord::memory(&tick, pid, details));
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) {
+        if let Err(e) = self.writer.flush() {
+            console!("Failed to write JSON samples: {}", e);
+        }
+    }
+
+    fn finish(&mut self, _ctx: &FinishContext) {
+        match self.writer.flush() {
+            Ok(()) => console!(
+                "✓ Samples exported to JSON: {}",
+                self.writer.path().display()
+            ),
+            Err(e) => console!("Failed to write JSON samples: {}", e),
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use crate::utils;
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+// 会话目录下按类别划分的子目录
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    Cpu,
+    Memory,
+    Thread,
+    Gpu,
+    Frames,
+    Binder,
+    Wakelocks,
+    Io,
+    Thermal,
+    Battery,
+    Companions,
+    Segments,
+    Probe,
+    Blocked,
+    Process,
+    Snapshots,
+    Tombstones,
+    Perfetto,
+    Simpleperf,
+    Atrace,
+    Heapdump,
+    Raw,
+    Device,
+    Fleet,
+    Compare,
+    Apk,
+    Startup,
+    Monkey,
+    Trace,
+    Dropbox,
+}
+
+impl Category {
+    pub fn dir_name(&self) -> &'static str {
+        match self {
+            Category::Cpu => "cpu",
+            Category::Memory => "memory",
+            Category::Thread => "thread",
+            Category::Gpu => "gpu",
+            Category::Frames => "frames",
+            Category::Binder => "binder",
+            Category::Wakelocks => "wakelocks",
+            Category::Io => "io",
+            Category::Thermal => "thermal",
+            Category::Battery => "battery",
+            Category::Companions => "companions",
+            Category::Segments => "segments",
+            Category::Probe => "probe",
+            Category::Blocked => "blocked",
+            Category::Process => "process",
+            Category::Snapshots => "snapshots",
+            Category::Tombstones => "tombstones",
+            Category::Perfetto => "perfetto",
+            Category::Simpleperf => "simpleperf",
+            Category::Atrace => "atrace",
+            Category::Heapdump => "heapdump",
+            Category::Raw => "raw",
+            Category::Device => "device",
+            Category::Fleet => "fleet",
+            Category::Compare => "compare",
+            Category::Apk => "apk",
+            Category::Startup => "startup",
+            Category::Monkey => "monkey",
+            Category::Trace => "trace",
+            Category::Dropbox => "dropbox",
+        }
+    }
+}
+
+// 一次会话的输出目录结构：<output-dir>/<name>/<timestamp>[/<device>]/<category>/，
+// 所有图表、CSV 和日志的路径都经由这里生成
+#[derive(Debug, Clone)]
+pub struct OutputLayout {
+    session_dir: PathBuf,
+}
+
+impl OutputLayout {
+    // 当前会话的目录（多设备时为当前设备的子目录），首次调用时创建
+    pub fn session(name: &str) -> Result<Self> {
+        Ok(Self {
+            session_dir: utils::create_timestamp_subdir(name)?,
+        })
+    }
+
+    pub fn session_dir(&self) -> &Path {
+        &self.session_dir
+    }
+
+    // 会话目录下的文件，如合并数据 CSV 和 session.json
+    pub fn file(&self, file_name: &str) -> PathBuf {
+        self.session_dir.join(file_name)
+    }
+
+    // 类别子目录的路径，不创建目录，由导出函数按需创建
+    pub fn dir(&self, category: Category) -> PathBuf {
+        self.session_dir.join(category.dir_name())
+    }
+
+    pub fn create_dir(&self, category: Category) -> Result<PathBuf> {
+        let dir = self.dir(category);
+        std::fs::create_dir_all(&dir)?;
+        Ok(dir)
+    }
+
+    // 类别子目录下的文件
+    pub fn category_file(&self, category: Category, file_name: &str) -> PathBuf {
+        self.dir(category).join(file_name)
+    }
+}
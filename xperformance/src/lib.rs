@@ -0,0 +1,75 @@
+pub use xperformance_core::{
+    chart, cpu, device, leak, memory, monitor, output, replay, tick, utils,
+};
+
+// 终端输出统一经过这里，--tui 时进入仪表盘的日志区
+#[macro_export]
+macro_rules! console {
+    () => {
+        $crate::tui::console_line(String::new())
+    };
+    ($($arg:tt)*) => {
+        $crate::tui::console_line(format!($($arg)*))
+    };
+}
+
+pub mod abtest;
+pub mod activity;
+pub mod alarm;
+pub mod atrace;
+pub mod binder;
+pub mod capture;
+pub mod ci;
+pub mod cluster;
+pub mod companion;
+pub mod config;
+pub mod counters;
+pub mod cpufreq;
+pub mod dropbox;
+pub mod faults;
+pub mod fd;
+pub mod flamegraph;
+pub mod fleet;
+pub mod framestats;
+pub mod gc;
+pub mod gpu;
+pub mod heapdump;
+pub mod jobs;
+pub mod jsonl;
+pub mod layout;
+pub mod logcat;
+pub mod markers;
+pub mod multiprocess;
+pub mod odpm;
+pub mod oomadj;
+pub mod package;
+pub mod pairing;
+pub mod percore;
+pub mod power;
+pub mod priority;
+pub mod probe;
+pub mod process;
+pub mod prometheus;
+pub mod recorder;
+pub mod regenerate;
+pub mod regression;
+pub mod report;
+pub mod scenario;
+pub mod schedule;
+pub mod segment;
+pub mod session;
+pub mod simpleperf;
+pub mod snapshot;
+pub mod stats;
+pub mod strictmode;
+pub mod surfaceflinger;
+pub mod system;
+pub mod thermal;
+pub mod threadstate;
+pub mod threshold;
+pub mod throttle;
+pub mod tombstone;
+pub mod trace;
+pub mod tui;
+pub mod wakelock;
+pub mod web;
@@ -0,0 +1,446 @@
+use crate::gc::{self, GcEvent};
+use crate::recorder::{Collector, FinishContext, TickContext};
+use crate::regenerate;
+use crate::strictmode::{self, StrictModeTracker, Violation};
+use crate::utils;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use colored::*;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+
+pub const EVENTS_FILE: &str = "events.csv";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    Crash,
+    Anr,
+    NativeCrash,
+    // 用户在监控过程中插入的标记
+    Marker,
+    // --blocked-threads 检测到长时间处于 D 状态的线程
+    BlockedThread,
+    // --min-fps 检测到的持续低帧率
+    FpsDrop,
+    // --object-growth 检测到回到桌面后 Activities 或 ViewRootImpl 持续增长
+    ObjectGrowth,
+    // 会话结束时从 dumpsys dropbox 归档的崩溃、ANR 和 WTF 记录
+    Dropbox,
+}
+
+impl EventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventKind::Crash => "crash",
+            EventKind::Anr => "anr",
+            EventKind::NativeCrash => "native_crash",
+            EventKind::Marker => "marker",
+            EventKind::BlockedThread => "blocked_thread",
+            EventKind::FpsDrop => "fps_drop",
+            EventKind::ObjectGrowth => "object_growth",
+            EventKind::Dropbox => "dropbox",
+        }
+    }
+
+    // 崩溃和 ANR，不含标记、阻塞线程等监控过程中记录的事件
+    pub fn is_crash(&self) -> bool {
+        matches!(
+            self,
+            EventKind::Crash | EventKind::Anr | EventKind::NativeCrash
+        )
+    }
+}
+
+// 从 logcat 检测到的应用事件
+#[derive(Debug, Clone)]
+pub struct LogcatEvent {
+    pub timestamp: DateTime<Local>,
+    pub kind: EventKind,
+    pub pid: Option<String>,
+    pub message: String,
+}
+
+// threadtime 格式："10-15 12:34:56.789  1234  1250 E AndroidRuntime: FATAL EXCEPTION: main"
+// 返回 (tag, message)
+fn split_line(line: &str) -> Option<(&str, &str)> {
+    let (header, message) = line.split_once(": ")?;
+    let tag = header.split_whitespace().last()?;
+    Some((tag, message.trim()))
+}
+
+// 按行解析 logcat，跨行的崩溃信息（进程名、异常原因）在后续行中补全
+pub struct LogcatParser {
+    package: String,
+    // FATAL EXCEPTION 的线程名，等待下一行 "Process: <package>, PID: <pid>"
+    fatal_thread: Option<String>,
+    // 已匹配应用、等待补充原因的事件
+    pending: Option<LogcatEvent>,
+}
+
+impl LogcatParser {
+    pub fn new(package: &str) -> Self {
+        Self {
+            package: package.to_string(),
+            fatal_thread: None,
+            pending: None,
+        }
+    }
+
+    // 返回已完整的事件；事件原因取自其后第一条有效行
+    pub fn feed(&mut self, line: &str) -> Option<LogcatEvent> {
+        let (tag, message) = split_line(line)?;
+
+        if let Some(mut event) = self.pending.take() {
+            let detail = match (event.kind, tag) {
+                (EventKind::Crash, "AndroidRuntime") => Some(message.to_string()),
+                (EventKind::Anr, "ActivityManager") => {
+                    if message.starts_with("PID:") {
+                        event.pid = message
+                            .strip_prefix("PID:")
+                            .map(|pid| pid.trim().to_string());
+                        self.pending = Some(event);
+                        return None;
+                    }
+                    message
+                        .strip_prefix("Reason:")
+                        .map(|r| r.trim().to_string())
+                }
+                _ => None,
+            };
+            if let Some(detail) = detail {
+                event.message = format!("{}: {}", event.message, detail);
+            }
+            return Some(event);
+        }
+
+        match tag {
+            "AndroidRuntime" => {
+                if let Some(thread) = message.strip_prefix("FATAL EXCEPTION:") {
+                    self.fatal_thread = Some(thread.trim().to_string());
+                    return None;
+                }
+                let thread = self.fatal_thread.take()?;
+                // Process: com.example.app, PID: 1234
+                let (process, pid) = message.strip_prefix("Process:")?.split_once(", PID:")?;
+                if process.trim() != self.package {
+                    return None;
+                }
+                self.pending = Some(LogcatEvent {
+                    timestamp: Local::now(),
+                    kind: EventKind::Crash,
+                    pid: Some(pid.trim().to_string()),
+                    message: format!("FATAL EXCEPTION in thread {}", thread),
+                });
+                None
+            }
+            "ActivityManager" => {
+                // ANR in com.example.app (com.example.app/.MainActivity)
+                let process = message.strip_prefix("ANR in ")?;
+                let name = process.split_whitespace().next()?;
+                if name != self.package {
+                    return None;
+                }
+                self.pending = Some(LogcatEvent {
+                    timestamp: Local::now(),
+                    kind: EventKind::Anr,
+                    pid: None,
+                    message: format!("ANR in {}", process),
+                });
+                None
+            }
+            "libc" => {
+                // Fatal signal 11 (SIGSEGV), code 1 (SEGV_MAPERR), fault addr 0x0 in tid 1250 (RenderThread), pid 1234 (com.example.app)
+                if !message.starts_with("Fatal signal") {
+                    return None;
+                }
+                let (_, process) = message.rsplit_once(", pid ")?;
+                let (pid, name) = process.split_once(' ')?;
+                if name.trim_matches(|c| c == '(' || c == ')') != self.package {
+                    return None;
+                }
+                Some(LogcatEvent {
+                    timestamp: Local::now(),
+                    kind: EventKind::NativeCrash,
+                    pid: Some(pid.to_string()),
+                    message: message.to_string(),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+// 后台运行 adb logcat，读取线程解析后通过通道传回事件；Drop 时结束 logcat
+pub struct LogcatWatcher {
+    child: Child,
+    receiver: Receiver<LogcatEvent>,
+    gc_receiver: Receiver<GcEvent>,
+    strict_mode_receiver: Receiver<Violation>,
+}
+
+impl LogcatWatcher {
+    // 只读取启动之后的日志；gc、strict_mode 为 true 时同时解析所有进程的 GC 行、StrictMode 违规，
+    // 由调用方按 PID 过滤
+    pub fn start(package: &str, gc: bool, strict_mode: bool) -> Result<Self> {
+        let mut command = Command::new("adb");
+        if let Some(serial) = utils::adb_serial() {
+            command.args(["-s", &serial]);
+        }
+        let mut child = command
+            .args([
+                "logcat",
+                "-v",
+                "threadtime",
+                "-b",
+                "main,system,crash",
+                "-T",
+                "1",
+            ])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .context("Failed to start adb logcat")?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow::format_err!("Failed to open logcat output"))?;
+
+        let (sender, receiver) = mpsc::channel();
+        let (gc_sender, gc_receiver) = mpsc::channel();
+        let (strict_mode_sender, strict_mode_receiver) = mpsc::channel();
+        let mut parser = LogcatParser::new(package);
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if let Some(event) = parser.feed(&line) {
+                    if sender.send(event).is_err() {
+                        break;
+                    }
+                }
+                if let Some(event) = gc.then(|| gc::parse_gc_line(&line)).flatten() {
+                    if gc_sender.send(event).is_err() {
+                        break;
+                    }
+                }
+                if let Some(violation) = strict_mode
+                    .then(|| strictmode::parse_violation_line(&line))
+                    .flatten()
+                {
+                    if strict_mode_sender.send(violation).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            child,
+            receiver,
+            gc_receiver,
+            strict_mode_receiver,
+        })
+    }
+
+    // 取出上次调用之后检测到的事件
+    pub fn poll(&self) -> Vec<LogcatEvent> {
+        self.receiver.try_iter().collect()
+    }
+
+    // 取出上次调用之后解析到的 GC
+    pub fn poll_gc(&self) -> Vec<GcEvent> {
+        self.gc_receiver.try_iter().collect()
+    }
+
+    // 取出上次调用之后解析到的 StrictMode 违规
+    pub fn poll_strict_mode(&self) -> Vec<Violation> {
+        self.strict_mode_receiver.try_iter().collect()
+    }
+}
+
+impl Drop for LogcatWatcher {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+pub fn write_events_csv(dir: &Path, events: &[LogcatEvent]) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(EVENTS_FILE);
+    let mut file = std::fs::File::create(&path)?;
+    writeln!(file, "Timestamp,Type,PID,Message")?;
+    for event in events {
+        writeln!(
+            file,
+            "{},{},{},\"{}\"",
+            event.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            event.kind.as_str(),
+            event.pid.as_deref().unwrap_or_default(),
+            event.message.replace('"', "'")
+        )?;
+    }
+    Ok(path)
+}
+
+// 会话目录 events.csv 中的用户标记，用于重新生成图表
+pub fn read_markers(session_dir: &Path) -> Vec<(DateTime<Local>, String)> {
+    let Ok(content) = std::fs::read_to_string(session_dir.join(EVENTS_FILE)) else {
+        return Vec::new();
+    };
+    content
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            // 消息在最后一列且可能含逗号，只拆前三列
+            let mut fields = line.splitn(4, ',');
+            let timestamp = regenerate::parse_timestamp(fields.next()?)?;
+            if fields.next()? != EventKind::Marker.as_str() {
+                return None;
+            }
+            let label = fields.nth(1)?.trim().trim_matches('"');
+            Some((timestamp, label.to_string()))
+        })
+        .collect()
+}
+
+// 会话期间检测到的崩溃、ANR 和 native crash，本 Tick 的事件同时交给后续采集器
+pub struct LogcatCollector {
+    watcher: LogcatWatcher,
+    events: Vec<LogcatEvent>,
+    // --strict-mode 时应用进程的 StrictMode 违规
+    strict_mode: Option<StrictModeTracker>,
+    // 上次采样时的进程，结束时的最后一次读取按它过滤
+    pid: String,
+}
+
+impl LogcatCollector {
+    // logcat 启动失败时只提示，不影响其他指标
+    pub fn start(package: &str, gc: bool, strict_mode: bool) -> Option<Self> {
+        match LogcatWatcher::start(package, gc, strict_mode) {
+            Ok(watcher) => Some(Self {
+                watcher,
+                events: Vec::new(),
+                strict_mode: strict_mode.then(StrictModeTracker::default),
+                pid: String::new(),
+            }),
+            Err(e) => {
+                console!("Failed to start logcat watcher: {}", e);
+                None
+            }
+        }
+    }
+}
+
+impl LogcatCollector {
+    // 所有进程的 StrictMode 行，只保留应用当前进程的
+    fn strict_mode_violations(&self) -> Vec<Violation> {
+        self.watcher
+            .poll_strict_mode()
+            .into_iter()
+            .filter(|violation| violation.pid == self.pid)
+            .collect()
+    }
+}
+
+impl Collector for LogcatCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        for event in self.watcher.poll() {
+            console!(
+                "[{}] {} {}",
+                event.timestamp.format("%H:%M:%S").to_string().blue(),
+                format!("{} detected:", event.kind.as_str().to_uppercase())
+                    .red()
+                    .bold(),
+                event.message
+            );
+            ctx.anomaly
+                .get_or_insert_with(|| event.kind.as_str().to_string());
+            ctx.events.push(event.clone());
+            self.events.push(event);
+        }
+        // 所有进程的 GC 行，只保留应用当前进程的
+        ctx.gc_events.extend(
+            self.watcher
+                .poll_gc()
+                .into_iter()
+                .filter(|event| event.pid == ctx.sample.pid),
+        );
+        self.pid = ctx.sample.pid.clone();
+        if self.strict_mode.is_some() {
+            let violations = self.strict_mode_violations();
+            ctx.table
+                .record(&ctx.tick, "StrictMode Violations", violations.len() as f64);
+            if let Some(strict_mode) = &mut self.strict_mode {
+                strict_mode.record(&ctx.tick, violations);
+            }
+        }
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        self.events.extend(self.watcher.poll());
+        let violations = self.strict_mode_violations();
+        if let Some(strict_mode) = &mut self.strict_mode {
+            strict_mode.extend(violations);
+        }
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        if let Some(strict_mode) = &self.strict_mode {
+            strict_mode.finish(ctx);
+        }
+    }
+
+    fn events(&self, events: &mut Vec<LogcatEvent>) {
+        events.extend(self.events.iter().cloned());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_markers_skips_crashes() {
+        let dir = std::env::temp_dir().join(format!("xperformance_events_{}", std::process::id()));
+        let at = |text: &str| regenerate::parse_timestamp(text).unwrap();
+        let event = |time: &str, kind: EventKind, message: &str| LogcatEvent {
+            timestamp: at(time),
+            kind,
+            pid: Some("12345".to_string()),
+            message: message.to_string(),
+        };
+        write_events_csv(
+            &dir,
+            &[
+                event(
+                    "2026-10-15 14:00:05",
+                    EventKind::Marker,
+                    "opened gallery, 200 photos",
+                ),
+                event(
+                    "2026-10-15 14:00:07",
+                    EventKind::Crash,
+                    "FATAL EXCEPTION: main",
+                ),
+                event("2026-10-15 14:00:09", EventKind::Marker, "marker 2"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            read_markers(&dir),
+            [
+                (
+                    at("2026-10-15 14:00:05"),
+                    "opened gallery, 200 photos".to_string()
+                ),
+                (at("2026-10-15 14:00:09"), "marker 2".to_string()),
+            ]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
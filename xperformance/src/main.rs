@@ -1,29 +1,85 @@
 #![deny(warnings)]
-use anyhow::{Context, Result};
-use chrono::{DateTime, Local, Timelike};
-use clap::Parser;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use clap::{CommandFactory, Parser, Subcommand};
 use colored::*;
-use std::collections::VecDeque;
 use std::io::Write;
-use std::path::PathBuf;
-use std::process::Command;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tokio::time::{sleep, Duration, Instant};
 
-mod cpu;
-mod memory;
-mod utils;
+use xperformance::abtest::{self, Variant};
+use xperformance::capture::PerfettoTrigger;
+use xperformance::chart::{self, ChartTheme};
+use xperformance::ci;
+use xperformance::config;
+use xperformance::console;
+use xperformance::cpu::CpuScale;
+use xperformance::device;
+use xperformance::fleet::{FleetRecorder, FleetSampler, ProcessUsage};
+use xperformance::framestats::FrameSource;
+use xperformance::heapdump;
+use xperformance::layout::{Category, OutputLayout};
+use xperformance::output;
+use xperformance::package;
+use xperformance::pairing;
+use xperformance::probe::ProbeMethod;
+use xperformance::prometheus;
+use xperformance::recorder::{OutputFormat, RecorderOptions, SessionRecorder, ThreadCsv};
+use xperformance::regenerate::{self, TimeWindow};
+use xperformance::regression::{self, Run};
+use xperformance::replay;
+use xperformance::scenario::Scenario;
+use xperformance::schedule::{self, Schedule};
+use xperformance::session::SessionInfo;
+use xperformance::system::{self, SystemSample, SystemSampler};
+use xperformance::threshold;
+use xperformance::tick::Tick;
+use xperformance::tui;
+use xperformance::utils;
+use xperformance::web;
+
+// --system 未指定 --top 时跟踪的进程数
+const DEFAULT_SYSTEM_TOP: usize = 10;
+
+#[derive(Parser, Debug, Clone)]
+#[command(
+    author,
+    version,
+    about,
+    long_about = None,
+    args_override_self = true,
+    subcommand_negates_reqs = true,
+    args_conflicts_with_subcommands = true
+)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Commands>,
+
+    /// Serial of the device to monitor when several are attached
+    #[arg(short, long)]
+    serial: Option<String>,
 
-use cpu::ThreadCpuInfo;
-use memory::MemoryTimeSeriesData;
+    /// Connect to a device over TCP (adb connect <ip:port>) and reconnect with backoff if the link drops
+    #[arg(long, value_name = "IP:PORT", conflicts_with_all = ["serial", "all_devices", "replay"])]
+    connect: Option<String>,
+
+    /// Monitor every connected device in parallel, each in its own output subdirectory
+    #[arg(long, conflicts_with_all = ["serial", "top", "system", "replay"])]
+    all_devices: bool,
 
-#[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
-struct Args {
     /// Package name to monitor
-    #[arg(short, long)]
-    package: String,
+    #[arg(short, long, required_unless_present_any = ["top", "system"])]
+    package: Option<String>,
+
+    /// System-wide mode: sample the top N processes by CPU and memory instead of one package
+    #[arg(long, value_name = "N", conflicts_with = "package")]
+    top: Option<usize>,
+
+    /// System-wide mode with device CPU (/proc/stat), memory (/proc/meminfo) and top processes by PSS; --top sets N (default: 10)
+    #[arg(long, conflicts_with = "package")]
+    system: bool,
 
     /// Monitor CPU usage
     #[arg(long)]
@@ -33,6 +89,106 @@ struct Args {
     #[arg(long)]
     memory: bool,
 
+    /// Monitor GPU busy % (Adreno kgsl or Mali sysfs) and the app's GPU memory
+    #[arg(long)]
+    gpu: bool,
+
+    /// Monitor battery level, voltage, current and temperature
+    #[arg(long)]
+    battery: bool,
+
+    /// Reset batterystats at start and report the app's estimated power use, CPU time, wakeups and radio time at the end
+    #[arg(long)]
+    battery_stats: bool,
+
+    /// Sample per-rail energy counters from the on-device power monitor (ODPM, Pixel 6 and later) and report per-rail power
+    #[arg(long)]
+    power_rails: bool,
+
+    /// Monitor FPS, janky frames and frame time percentiles from dumpsys gfxinfo framestats
+    #[arg(long)]
+    fps: bool,
+
+    /// Monitor CPU/GPU/skin temperatures and thermal throttling status
+    #[arg(long)]
+    thermal: bool,
+
+    /// Monitor disk I/O rates (read/write KB/s and syscalls/s) from /proc/<pid>/io
+    #[arg(long)]
+    io: bool,
+
+    /// Count open file descriptors (/proc/<pid>/fd) and warn when nearing the process limit
+    #[arg(long)]
+    fds: bool,
+
+    /// Chart voluntary and involuntary context switch rates from /proc/<pid>/status
+    #[arg(long)]
+    ctx_switches: bool,
+
+    /// Chart minor/major page fault rates from /proc/<pid>/stat and flag sustained major-fault storms
+    #[arg(long)]
+    page_faults: bool,
+
+    /// Parse the app's ART GC lines from logcat (cause, freed memory, pause time) and mark GCs on the memory chart
+    #[arg(long)]
+    gc: bool,
+
+    /// Count the app's StrictMode policy violations in logcat by type (e.g. DiskRead, LeakedClosable) and list them in the summary
+    #[arg(long)]
+    strict_mode: bool,
+
+    /// Track the top activity and whether the app is in the foreground, shown as bands on the CPU and memory charts
+    #[arg(long)]
+    activity: bool,
+
+    /// Track the app's JobScheduler jobs (dumpsys jobscheduler), shown as bands on the CPU and memory charts
+    #[arg(long)]
+    jobs: bool,
+
+    /// Track the process's oom_score_adj and process state (LMK kill priority) and relate them to memory peaks
+    #[arg(long)]
+    oom_adj: bool,
+
+    /// Alert when Activities or ViewRootImpl grow across N consecutive samples after the app goes back to the home screen
+    #[arg(long, value_name = "N", requires = "memory", requires = "activity")]
+    object_growth: Option<usize>,
+
+    /// Sample each core's utilization (/proc/stat) and record which cores the hottest threads ran on
+    #[arg(long, requires = "cpu")]
+    per_core: bool,
+
+    /// Sample each cluster's CPU frequency (scaling_cur_freq) and overlay it on the CPU chart
+    #[arg(long, requires = "cpu")]
+    cpu_freq: bool,
+
+    /// Monitor binder transactions and binder thread pool saturation
+    #[arg(long)]
+    binder: bool,
+
+    /// Track wakelocks held by the app (dumpsys power): acquire counts, hold durations and a timeline
+    #[arg(long)]
+    wakelocks: bool,
+
+    /// Count the app's wakeup alarms and most frequent alarm tags from dumpsys alarm
+    #[arg(long)]
+    alarms: bool,
+
+    /// How often to read dumpsys alarm for --alarms
+    #[arg(long, value_parser = utils::parse_duration, default_value = "1m", requires = "alarms")]
+    alarm_interval: Duration,
+
+    /// Frame data source for --fps; use surfaceflinger for SurfaceView apps such as games
+    #[arg(long, value_enum, default_value_t = FrameSource::Gfxinfo, requires = "fps")]
+    frame_source: FrameSource,
+
+    /// Alert when FPS stays below this value for --min-fps-duration: adds a chart marker and an event, and counts as a Perfetto anomaly
+    #[arg(long, value_name = "FPS", requires = "fps")]
+    min_fps: Option<f64>,
+
+    /// How long FPS must stay below --min-fps before alerting
+    #[arg(long, value_parser = utils::parse_duration, default_value = "3s", requires = "min_fps")]
+    min_fps_duration: Duration,
+
     /// Monitor thread activity
     #[arg(long)]
     thread: bool,
@@ -44,154 +200,563 @@ struct Args {
     /// Sampling interval in seconds (default: 1)
     #[arg(short, long, default_value_t = 1)]
     interval: u64,
-}
 
-#[derive(Default)]
-struct CpuTimeSeriesData {
-    timestamps: VecDeque<DateTime<Local>>,
-    process_cpu: VecDeque<f32>,
-    top_threads: VecDeque<Vec<ThreadCpuInfo>>,
-}
+    /// CPU normalization: per-core (100% = one core, may exceed 100%) or total (100% = all cores)
+    #[arg(long, value_enum, default_value_t = CpuScale::PerCore)]
+    cpu_scale: CpuScale,
 
-impl CpuTimeSeriesData {
-    fn add_data_point(
-        &mut self,
-        timestamp: DateTime<Local>,
-        process_cpu: f32,
-        top_threads: Vec<ThreadCpuInfo>,
-    ) {
-        self.timestamps.push_back(timestamp);
-        self.process_cpu.push_back(process_cpu);
-        self.top_threads.push_back(top_threads);
-    }
-}
+    /// Divide CPU by the device's core count (100% = all cores); shorthand for --cpu-scale total
+    #[arg(long)]
+    normalize_cpu: bool,
 
-#[derive(Default)]
-struct PeakStats {
-    cpu_usage: f32,
-    cpu_time: DateTime<Local>,
-    memory_usage: u64,
-    memory_time: DateTime<Local>,
-    restart_count: u32,
-    cpu_data: CpuTimeSeriesData,
-    memory_data: MemoryTimeSeriesData,
-}
+    /// Probe main-thread responsiveness each sample: activity (dumpsys activity dump) or broadcast
+    #[arg(long, value_enum)]
+    probe: Option<ProbeMethod>,
 
-impl PeakStats {
-    fn format_current_peaks(&self) -> String {
-        let timestamp = Local::now().format("%H:%M:%S").to_string();
-        let mut peaks = Vec::new();
-        if self.cpu_usage > 0.0 {
-            peaks.push(format!(
-                "[{}] Peak CPU: {}% at {}",
-                timestamp.blue(),
-                format!("{:.1}", self.cpu_usage).red(),
-                self.cpu_time.format("%H:%M:%S").to_string().blue()
-            ));
-        }
-        if self.memory_usage > 0 {
-            peaks.push(format!(
-                "[{}] Peak Memory: {} at {}",
-                timestamp.blue(),
-                format!("{} KB", self.memory_usage).red(),
-                self.memory_time.format("%H:%M:%S").to_string().blue()
-            ));
-        }
-        peaks.join("\n")
-    }
+    /// Broadcast action handled by the app's receiver (required by --probe broadcast)
+    #[arg(long)]
+    probe_action: Option<String>,
+
+    /// Main-thread latency in milliseconds reported as a stall (default: 200)
+    #[arg(long, default_value_t = 200.0)]
+    stall_threshold: f64,
+
+    /// Also sample CPU and memory of a system process (e.g. system_server, surfaceflinger); repeatable
+    #[arg(long, value_name = "PROCESS")]
+    also: Vec<String>,
+
+    /// Sample every process of a multi-process app (package and package:suffix) and chart per-process and total CPU/RSS
+    #[arg(long)]
+    all_processes: bool,
+
+    /// Track processes forked by the app (crash handlers, renderers, helpers) and add them to its CPU and RSS totals
+    #[arg(long)]
+    children: bool,
+
+    /// Capture labeled meminfo snapshots on demand: type a label and press Enter while monitoring
+    #[arg(long)]
+    snapshots: bool,
+
+    /// Insert labeled event markers while monitoring: type a label and press Enter (drawn on all charts, saved in events.csv)
+    #[arg(long, conflicts_with = "snapshots")]
+    markers: bool,
+
+    /// Also read event marker labels from this named pipe, one per line (created if missing), e.g. echo "started playback" > PATH
+    #[arg(long, value_name = "PATH")]
+    marker_pipe: Option<PathBuf>,
+
+    /// Alert and capture the kernel stack when a thread stays in D state for N consecutive samples
+    #[arg(long, value_name = "N")]
+    blocked_threads: Option<u32>,
+
+    /// Record a Perfetto trace during monitoring: built-in config (scheduling, memory, graphics) or a .pbtxt file
+    #[arg(long, value_name = "CONFIG")]
+    perfetto: Option<String>,
+
+    /// Length of each Perfetto trace window
+    #[arg(long, value_parser = utils::parse_duration, default_value = "30s", requires = "perfetto")]
+    perfetto_duration: Duration,
+
+    /// Start the Perfetto trace at session start, or on each anomaly (threshold alert, restart, crash/ANR, main-thread stall, FPS drop)
+    #[arg(long, value_enum, default_value_t = PerfettoTrigger::Start, requires = "perfetto")]
+    perfetto_trigger: PerfettoTrigger,
+
+    /// Record a simpleperf call-graph profile whenever process CPU (%) exceeds this value (up to 5 profiles)
+    #[arg(long, value_name = "PERCENT", requires = "cpu")]
+    profile_on_spike: Option<f64>,
+
+    /// Length of each simpleperf recording
+    #[arg(long, value_parser = utils::parse_duration, default_value = "10s", requires = "profile_on_spike")]
+    profile_duration: Duration,
+
+    /// Fold the call stacks of each simpleperf profile and draw a flamegraph SVG
+    #[arg(long, requires = "profile_on_spike")]
+    flamegraph: bool,
+
+    /// Capture an atrace window of the app when the session starts; in --tui, press t to capture another
+    #[arg(long)]
+    atrace: bool,
+
+    /// Length of each atrace window
+    #[arg(long, value_parser = utils::parse_duration, default_value = "10s")]
+    atrace_duration: Duration,
+
+    /// atrace categories, comma-separated (default: gfx,view,sched)
+    #[arg(long, value_delimiter = ',')]
+    atrace_categories: Vec<String>,
+
+    /// Initial window (e.g. 60s) charted but excluded from peaks, statistics and alerts
+    #[arg(long, value_parser = utils::parse_duration)]
+    warmup: Option<Duration>,
+
+    /// Daily monitoring window, e.g. 22:00-06:00; idles until the window opens
+    #[arg(long, conflicts_with_all = ["start_at", "stop_at"])]
+    schedule: Option<String>,
+
+    /// Time of day to start monitoring (HH:MM)
+    #[arg(long)]
+    start_at: Option<String>,
+
+    /// Time of day to stop monitoring and export (HH:MM)
+    #[arg(long)]
+    stop_at: Option<String>,
+
+    /// Stop after this long (e.g. 30m, 2h) and export as if Ctrl-C was pressed
+    #[arg(long, value_parser = utils::parse_duration)]
+    duration: Option<Duration>,
+
+    /// Stop after collecting N samples and export as if Ctrl-C was pressed
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    samples: Option<u64>,
+
+    /// After a window closes, wait for the next one instead of exiting
+    #[arg(long)]
+    repeat: bool,
+
+    /// Output format for CPU, memory and thread samples: csv, json (newline-delimited) or both
+    #[arg(long, value_enum, default_value_t = OutputFormat::Csv)]
+    format: OutputFormat,
+
+    /// Host a local web page on this port that streams live samples and draws charts in the browser
+    #[arg(long, value_name = "PORT", conflicts_with_all = ["top", "system"])]
+    serve: Option<u16>,
+
+    /// Live terminal dashboard with CPU/PSS graphs, top threads and peaks instead of scrolling output
+    #[arg(long, conflicts_with_all = ["all_devices", "top", "system", "snapshots", "markers"])]
+    tui: bool,
+
+    /// CI mode: progress goes to stderr without colors, and a single JSON object with peaks, percentiles, restarts, violations and artifact paths is printed to stdout at exit
+    #[arg(long, conflicts_with_all = ["tui", "all_devices", "top", "system", "repeat", "schedule", "start_at", "stop_at"])]
+    ci: bool,
+
+    /// Serve current CPU, memory and restart count as Prometheus gauges on this port (/metrics)
+    #[arg(long, value_name = "PORT", conflicts_with_all = ["top", "system"])]
+    prometheus_port: Option<u16>,
+
+    /// Alert and exit with code 2 when process CPU (%) exceeds this value after warmup
+    #[arg(long, value_name = "PERCENT", requires = "cpu")]
+    max_cpu: Option<f64>,
+
+    /// Alert and exit with code 3 when Total PSS (KB) exceeds this value after warmup
+    #[arg(long, value_name = "KB", requires = "memory")]
+    max_pss_kb: Option<u64>,
+
+    /// Alert and exit with code 4 when the process restarts more than this many times
+    #[arg(long, value_name = "N")]
+    max_restarts: Option<u32>,
+
+    /// Thread CSV layout: per-thread files, consolidated (wide + long) files, or all
+    #[arg(long, value_enum, default_value_t = ThreadCsv::All)]
+    thread_csv: ThreadCsv,
+
+    /// Number of top threads (by mean CPU) kept as columns in the wide thread CSV
+    #[arg(long, default_value_t = 20)]
+    top_threads: usize,
+
+    /// Also stamp device model, Android version and app version into chart file names
+    #[arg(long)]
+    stamp_filenames: bool,
+
+    /// Record every raw adb output with timestamps into the session's raw/ directory for --replay
+    #[arg(long, conflicts_with = "replay")]
+    record_raw: bool,
+
+    /// Replay a capture recorded with --record-raw (raw/ directory or raw_adb.jsonl) instead of a live device
+    #[arg(long, value_name = "CAPTURE", conflicts_with_all = ["schedule", "start_at", "stop_at"])]
+    replay: Option<PathBuf>,
+
+    /// Read options from a TOML file (default: ./xperf.toml if present); command-line flags override it
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+
+    /// Root directory for session output (default: log)
+    #[arg(long, value_name = "DIR")]
+    output_dir: Option<PathBuf>,
+
+    /// Chart image size (default: 1920x1080)
+    #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = chart::parse_chart_size)]
+    chart_size: Option<(u32, u32)>,
+
+    /// Downsample each chart line to at most this many points, keeping per-bucket min and max (0 = off)
+    #[arg(long, value_name = "N", default_value_t = chart::DEFAULT_MAX_POINTS)]
+    chart_max_points: usize,
+
+    /// Chart colors: light (default), dark, or a TOML file with background, foreground, grid and series colors
+    #[arg(long, value_name = "THEME", value_parser = chart::parse_chart_theme)]
+    chart_theme: Option<ChartTheme>,
+
+    /// How long to wait for the device after an ADB drop before ending the run (default: 60s)
+    #[arg(long, value_parser = utils::parse_duration, default_value = "60s")]
+    reconnect_timeout: Duration,
+
+    // run-scenario 加载的场景脚本
+    #[arg(skip)]
+    scenario: Option<Scenario>,
 }
 
-fn check_adb() -> Result<()> {
-    let output = Command::new("adb")
-        .arg("devices")
-        .output()
-        .context("Failed to execute adb command")?;
+#[derive(Subcommand, Debug, Clone)]
+enum Commands {
+    /// Compare two runs (session directories or CSVs) aligned by elapsed time and flag regressions
+    Compare {
+        /// Baseline session directory or CSV
+        baseline: PathBuf,
+
+        /// Candidate session directory or CSV
+        candidate: PathBuf,
+
+        /// Increase (%) in peak, mean or p95 CPU/PSS reported as a regression
+        #[arg(short, long, default_value_t = 10.0)]
+        threshold: f64,
+
+        /// Output directory (default: log/compare/<timestamp>/compare)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Dump the app's heap (am dumpheap), pull it and convert it with hprof-conv for Android Studio / MAT
+    Heapdump {
+        /// Package name of the app to dump
+        #[arg(short, long)]
+        package: String,
+
+        /// Serial of the device when several are attached
+        #[arg(short, long)]
+        serial: Option<String>,
+
+        /// Dump the native heap instead of the Java heap (no conversion needed)
+        #[arg(long)]
+        native: bool,
+
+        /// How long to wait for the app to finish writing the dump
+        #[arg(long, value_parser = utils::parse_duration, default_value = "120s")]
+        timeout: Duration,
+
+        /// Output directory (default: log/<package>/<timestamp>/heapdump)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Pair with an Android 11+ device over wireless debugging (adb pair); without an address, list devices found over mDNS
+    Pair {
+        /// Pairing address shown under Wireless debugging > Pair device with pairing code (ip:port)
+        address: Option<String>,
+
+        /// Six-digit pairing code (prompted for when omitted)
+        code: Option<String>,
+
+        /// After pairing, connect to the device's wireless debugging port found over mDNS
+        #[arg(long)]
+        connect: bool,
+    },
+    /// Regenerate the charts and HTML report of a captured session from its CSVs with the current chart options (no device needed)
+    Report {
+        /// Session directory (e.g. log/<package>/<timestamp>)
+        session_dir: PathBuf,
+
+        /// Output directory (default: <session_dir>/report)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Only use samples at or after this time (HH:MM[:SS] on the session's first day, or YYYY-MM-DD HH:MM[:SS])
+        #[arg(long)]
+        from: Option<String>,
+
+        /// Only use samples up to this time, including the whole minute for HH:MM (HH:MM[:SS] or YYYY-MM-DD HH:MM[:SS])
+        #[arg(long)]
+        to: Option<String>,
+
+        /// Chart image size (default: 1920x1080)
+        #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = chart::parse_chart_size)]
+        chart_size: Option<(u32, u32)>,
+
+        /// Downsample each chart line to at most this many points, keeping per-bucket min and max (0 = off)
+        #[arg(long, value_name = "N", default_value_t = chart::DEFAULT_MAX_POINTS)]
+        chart_max_points: usize,
+
+        /// Chart colors: light (default), dark, or a TOML file with background, foreground, grid and series colors
+        #[arg(long, value_name = "THEME", value_parser = chart::parse_chart_theme)]
+        chart_theme: Option<ChartTheme>,
+
+        /// Also stamp device model, Android version and app version into chart file names
+        #[arg(long)]
+        stamp_filenames: bool,
+    },
+    /// Install APK A and run a scenario with monitoring, then the same for APK B, and compare CPU/PSS with overlay charts and significance tests
+    AbTest {
+        /// APK of the baseline build
+        apk_a: PathBuf,
+
+        /// APK of the candidate build
+        apk_b: PathBuf,
+
+        /// Scenario run for each APK (see run-scenario); it should launch the app
+        script: PathBuf,
+
+        /// Scenario runs per APK; with 2 or more, significance is tested over per-run statistics
+        #[arg(long, default_value_t = 3)]
+        runs: usize,
+
+        /// Pause before each run after the first so the device cools down
+        #[arg(long, value_parser = utils::parse_duration, default_value = "30s")]
+        cooldown: Duration,
+
+        /// Significant increase (%) in B's peak, mean or p95 CPU/PSS reported as a regression
+        #[arg(short, long, default_value_t = 10.0)]
+        threshold: f64,
+
+        /// Output directory (default: log/ab-test/<timestamp>/compare)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Monitoring options (e.g. -p com.example --cpu --memory), overriding the scenario's [monitor] table
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        options: Vec<std::ffi::OsString>,
+    },
+    /// Run a TOML scenario (launch, sleep, tap, swipe, key, broadcast, force-stop) while monitoring, so the same workload can be replayed across builds
+    RunScenario {
+        /// Scenario file; its [monitor] table holds monitoring options in xperf.toml format
+        script: PathBuf,
+
+        /// Monitoring options (e.g. -p com.example --cpu --memory), overriding the scenario's [monitor] table
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        options: Vec<std::ffi::OsString>,
+    },
+}
 
-    if !output.status.success() {
-        anyhow::bail!("ADB command failed");
+// 确认目标设备已连接，之后所有 adb 调用都指向该设备
+// 多设备并行时设备由任务作用域决定，不修改全局设置
+fn check_adb(serial: Option<&str>) -> Result<()> {
+    if replay::is_replaying() {
+        return Ok(());
     }
-
-    let devices = String::from_utf8_lossy(&output.stdout);
-    if !devices.lines().skip(1).any(|line| !line.trim().is_empty()) {
-        anyhow::bail!("No Android devices connected");
+    let task_device = utils::current_task_device();
+    let serial = utils::select_device(task_device.as_deref().or(serial))?;
+    if task_device.is_none() {
+        utils::set_adb_serial(Some(&serial));
     }
-
+    console!("Device serial: {}", serial.cyan());
     Ok(())
 }
 
-async fn monitor_adb_connection(running: Arc<AtomicBool>) {
+// 短暂断开时只标记 connected，超过 reconnect_timeout 仍未恢复才结束本次监控
+// 指定了 --connect 时断开期间按指数退避重新执行 adb connect
+async fn monitor_adb_connection(
+    running: Arc<AtomicBool>,
+    connected: Arc<AtomicBool>,
+    reconnect_timeout: Duration,
+    connect: Option<String>,
+) {
     let check_interval = Duration::from_secs(1);
+    let max_backoff = Duration::from_secs(30);
+    let mut lost_at: Option<Instant> = None;
+    // 下一次重连的时间和当前退避时长
+    let mut retry = (Instant::now(), check_interval);
     while running.load(Ordering::SeqCst) {
-        if !utils::check_adb_connection() {
-            println!("\n{}", "ADB connection lost. Stopping...".red());
-            running.store(false, Ordering::SeqCst);
-            break;
+        let ok = utils::check_adb_connection();
+        match (ok, lost_at) {
+            (false, None) => {
+                console!(
+                    "\n{} Waiting up to {}s for the device...",
+                    "ADB connection lost.".red(),
+                    reconnect_timeout.as_secs()
+                );
+                connected.store(false, Ordering::SeqCst);
+                lost_at = Some(Instant::now());
+                retry = (Instant::now(), check_interval);
+            }
+            (false, Some(lost)) if lost.elapsed() >= reconnect_timeout => {
+                console!("\n{}", "ADB connection not restored. Stopping...".red());
+                running.store(false, Ordering::SeqCst);
+                break;
+            }
+            (false, Some(_)) => {
+                if let Some(address) = connect.as_deref().filter(|_| Instant::now() >= retry.0) {
+                    match utils::adb_reconnect(address) {
+                        Ok(()) => console!("Reconnected to {}", address.cyan()),
+                        Err(e) => console!(
+                            "Reconnect failed, retrying in {}s: {}",
+                            retry.1.as_secs(),
+                            e
+                        ),
+                    }
+                    retry = (Instant::now() + retry.1, (retry.1 * 2).min(max_backoff));
+                }
+            }
+            (true, Some(lost)) => {
+                console!(
+                    "{} after {:.0}s",
+                    "ADB connection restored".green(),
+                    lost.elapsed().as_secs_f64()
+                );
+                connected.store(true, Ordering::SeqCst);
+                lost_at = None;
+            }
+            _ => {}
         }
         sleep(check_interval).await;
     }
 }
 
-async fn monitor_process(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
-    let mut peak_stats = PeakStats::default();
+// 命令行参数中与监控会话有关的部分
+fn recorder_options(args: &Args, package: &str) -> RecorderOptions {
+    RecorderOptions {
+        package: package.to_string(),
+        interval: args.interval,
+        verbose: args.verbose,
+        cpu: args.cpu,
+        memory: args.memory,
+        thread: args.thread,
+        gpu: args.gpu,
+        battery: args.battery,
+        battery_stats: args.battery_stats,
+        power_rails: args.power_rails,
+        fps: args.fps,
+        frame_source: args.frame_source,
+        min_fps: args.min_fps,
+        min_fps_duration: args.min_fps_duration,
+        thermal: args.thermal,
+        io: args.io,
+        fds: args.fds,
+        ctx_switches: args.ctx_switches,
+        page_faults: args.page_faults,
+        gc: args.gc,
+        strict_mode: args.strict_mode,
+        activity: args.activity,
+        jobs: args.jobs,
+        oom_adj: args.oom_adj,
+        object_growth: args.object_growth,
+        per_core: args.per_core,
+        cpu_freq: args.cpu_freq,
+        binder: args.binder,
+        wakelocks: args.wakelocks,
+        alarms: args.alarms,
+        alarm_interval: args.alarm_interval,
+        cpu_scale: args.cpu_scale,
+        probe: args.probe,
+        probe_action: args.probe_action.clone(),
+        stall_threshold: args.stall_threshold,
+        also: args.also.clone(),
+        all_processes: args.all_processes,
+        children: args.children,
+        snapshots: args.snapshots,
+        markers: args.markers,
+        scenario: args.scenario.clone(),
+        marker_pipe: args.marker_pipe.clone(),
+        blocked_threads: args.blocked_threads,
+        perfetto: args.perfetto.clone(),
+        perfetto_duration: args.perfetto_duration,
+        perfetto_trigger: args.perfetto_trigger,
+        profile_on_spike: args.profile_on_spike,
+        profile_duration: args.profile_duration,
+        flamegraph: args.flamegraph,
+        atrace: args.atrace,
+        atrace_duration: args.atrace_duration,
+        atrace_categories: args.atrace_categories.clone(),
+        warmup: args.warmup,
+        format: args.format,
+        tui: args.tui,
+        ci: args.ci,
+        serve: args.serve.is_some(),
+        prometheus: args.prometheus_port.is_some(),
+        max_cpu: args.max_cpu,
+        max_pss_kb: args.max_pss_kb,
+        max_restarts: args.max_restarts,
+        thread_csv: args.thread_csv,
+        top_threads: args.top_threads,
+        stamp_filenames: args.stamp_filenames,
+    }
+}
+
+async fn monitor_process(
+    args: &Args,
+    running: &Arc<AtomicBool>,
+    stop_at: Option<DateTime<Local>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let package = args.package.as_deref().ok_or("--package is required")?;
+    let options = recorder_options(args, package);
 
-    println!("{}", "XPerformance Monitor".green().bold());
-    println!("Monitoring package: {}", args.package.cyan());
-    println!("Sampling interval: {} seconds", args.interval);
+    console!("{}", "XPerformance Monitor".green().bold());
+    console!("Monitoring package: {}", package.cyan());
+    console!("Sampling interval: {} seconds", args.interval);
 
-    check_adb()?;
+    check_adb(args.serial.as_deref())?;
 
-    if !args.cpu && !args.memory {
-        println!("No monitoring options selected. Use --cpu or --memory");
+    if !options.has_metrics() {
+        console!(
+            "No monitoring options selected. Use --cpu, --memory, --gpu, --battery, --battery-stats, --power-rails, --fps, --thermal, --io, --fds, --ctx-switches, --page-faults, --gc, --strict-mode, --activity, --jobs, --oom-adj, --binder, --wakelocks, --alarms, --probe, --blocked-threads, --also, --all-processes, --children, --snapshots, --perfetto or --atrace"
+        );
+        report_ci_error(args, "No monitoring options selected");
         return Ok(());
     }
 
-    // Set up signal handling
-    let running = Arc::new(AtomicBool::new(true));
-    let r = running.clone();
-    ctrlc::set_handler(move || {
-        r.store(false, Ordering::SeqCst);
-        // 设置中断标志
-        utils::set_interrupt_flag();
-        println!("\n程序正在退出...");
-    })?;
+    if let Some(stop_at) = stop_at {
+        console!("Monitoring until {}", stop_at.format("%Y-%m-%d %H:%M:%S"));
+    }
+
+    // 本次监控窗口是否仍在进行，ADB 断开或进程退出时置为 false
+    let active = Arc::new(AtomicBool::new(true));
+    // ADB 是否在线，短暂断开期间暂停采样
+    let connected = Arc::new(AtomicBool::new(true));
 
     // Start ADB connection monitoring
     let adb_monitor = {
-        let running = running.clone();
-        tokio::spawn(async move {
-            monitor_adb_connection(running).await;
-        })
+        let active = active.clone();
+        let connected = connected.clone();
+        let reconnect_timeout = args.reconnect_timeout;
+        let connect = args.connect.clone();
+        let device = utils::current_task_device();
+        tokio::spawn(utils::with_device(
+            device,
+            monitor_adb_connection(active, connected, reconnect_timeout, connect),
+        ))
     };
 
+    // 场景脚本：第一个 launch 及之前的步骤在采样前执行，等应用进程出现后再开始监控
+    if let Some(scenario) = &args.scenario {
+        console!(
+            "Scenario: {} ({} steps)",
+            scenario.name().cyan(),
+            scenario.steps.len()
+        );
+        let (prelude, _) = scenario.split_prelude();
+        for step in prelude {
+            console!("Scenario step: {}", step.describe());
+            step.run(package, running)?;
+        }
+        if !prelude.is_empty() {
+            let deadline = Instant::now() + Duration::from_secs(15);
+            while utils::get_process_info(package).is_err() {
+                if Instant::now() >= deadline || !running.load(Ordering::SeqCst) {
+                    return Err(
+                        format!("{} did not start after the scenario launch", package).into(),
+                    );
+                }
+                sleep(Duration::from_millis(500)).await;
+            }
+        }
+    }
+
     let interval = Duration::from_secs(args.interval);
+    let mut recorder = SessionRecorder::start(options, running)?;
 
     // 记录程序起始时间点，用于计算绝对采样时间
     let start_time = Instant::now();
     let mut sample_count: u64 = 0;
 
-    let mut last_process_info = utils::get_process_info(&args.package)?;
-    println!(
-        "Process started with PID {} at {}",
-        last_process_info.pid.yellow(),
-        last_process_info.start_time.blue()
-    );
-
-    // 添加变量以跟踪上次生成图表的小时
-    let mut last_chart_hour = -1i32;
-
-    // 添加变量用于跟踪每个线程的时间序列数据
-    let mut thread_time_series: std::collections::HashMap<String, Vec<ThreadCpuInfo>> =
-        std::collections::HashMap::new();
-
-    // 如果是verbose模式且开启了CPU监控，立即尝试导出一个初始线程数据文件
-    // 确保文件被创建但不预先创建空目录
-    if args.verbose && args.cpu {
-        println!(
-            "CPU monitoring enabled, but not creating files until actual thread data is available"
-        );
-    }
+    while running.load(Ordering::SeqCst) && active.load(Ordering::SeqCst) {
+        // 到达监控窗口结束时间
+        if stop_at.is_some_and(|stop| Local::now() >= stop) {
+            console!("\n{}", "Monitoring window closed".yellow());
+            break;
+        }
+        if let Some(reason) = session_limit_reached(args, start_time, recorder.collected()) {
+            console!("\n{}", reason.yellow());
+            break;
+        }
+        // 采集器要求结束，如场景脚本已执行完
+        if let Some(reason) = recorder.end_reason() {
+            console!("\n{}", reason.yellow());
+            break;
+        }
 
-    while running.load(Ordering::SeqCst) {
         // 计算当前应该在的绝对采样点
         sample_count += 1;
         // 使用Duration::from_secs代替直接乘法
@@ -207,7 +772,7 @@ async fn monitor_process(args: &Args) -> Result<(), Box<dyn std::error::Error>>
                 (time_behind.as_secs_f64() / interval.as_secs_f64()).ceil() as u64;
 
             if should_be_at_sample > sample_count && args.verbose {
-                println!(
+                console!(
                     "Warning: Sampling is taking longer than the interval. Skipped {} samples to catch up.",
                     should_be_at_sample - sample_count
                 );
@@ -225,614 +790,955 @@ async fn monitor_process(args: &Args) -> Result<(), Box<dyn std::error::Error>>
             }
         }
 
-        // 等待到达计划的采样时间点
-        if target_sample_time > now {
+        // 等待前刷新上一 Tick 的缓冲输出
+        recorder.flush();
+
+        // 等待到达计划的采样时间点，回放时不等待
+        if target_sample_time > now && !replay::is_replaying() {
             sleep(target_sample_time - now).await;
         }
 
-        // 检查当前是否为整小时，如果是则生成图表和CSV
-        let now = Local::now();
-        let current_hour = now.hour() as i32;
+        // 本轮所有指标共用同一个 Tick
+        let tick = Tick::new(sample_count);
 
-        // 如果进入了新的整小时且有足够的CPU数据，生成图表
-        if current_hour != last_chart_hour && !peak_stats.cpu_data.timestamps.is_empty() && args.cpu
-        {
-            last_chart_hour = current_hour;
+        // ADB 断开期间不采样，只记录缺口开始时间
+        if !connected.load(Ordering::SeqCst) {
+            recorder.record_gap(&tick);
+            continue;
+        }
 
-            // 只有在收集了数据后才生成图表
-            if peak_stats.cpu_data.timestamps.len() > 1 {
-                // 计算整小时标记（格式如 14:00）
-                let hour_mark = format!("{}:00", now.hour());
+        // 进程已退出
+        if !recorder.sample(tick).await? {
+            active.store(false, Ordering::SeqCst);
+            break;
+        }
+    }
 
-                println!(
-                    "{} Generating scheduled CPU chart at {}...",
-                    now.format("%H:%M:%S").to_string().blue(),
-                    hour_mark.green()
-                );
+    // Wait for ADB monitor to finish
+    active.store(false, Ordering::SeqCst);
+    let _ = adb_monitor.await;
 
-                // 使用预定义chart_hourly_intervals的时间执行图表生成
-                let chart_path = match utils::generate_cpu_chart(
-                    &args.package,
-                    &peak_stats.cpu_data.timestamps,
-                    &peak_stats.cpu_data.process_cpu,
-                    &last_process_info.pid,
-                ) {
-                    Ok(path) => path,
-                    Err(e) => {
-                        eprintln!("Error generating CPU chart: {}", e);
-                        continue;
-                    }
-                };
-
-                if args.verbose && args.cpu {
-                    // 为最新时间点的top线程创建CSV
-                    if peak_stats.cpu_data.timestamps.back().is_some()
-                        && peak_stats.cpu_data.top_threads.back().is_some()
-                    {
-                        println!("Thread data collection available");
-                    }
+    recorder.finish()?;
+    Ok(())
+}
 
-                    // 仅打印图表生成信息，不写入日志
-                    println!("Scheduled CPU chart generated: {}", chart_path.display());
+// --ci 时监控未能完成也输出一个 JSON 对象，并以退出码 1 结束
+fn report_ci_error(args: &Args, error: &str) {
+    if args.ci {
+        ci::report_error(args.package.as_deref().unwrap_or_default(), error);
+    }
+}
 
-                    // 添加CSV数据文件的信息
-                    let csv_path = chart_path.with_extension("csv");
-                    if csv_path.exists() {
-                        println!("Scheduled CPU data exported to CSV: {}", csv_path.display());
-                    }
-                }
+// 指定 --top 或 --system 时监控整机，否则监控单个应用
+async fn run_monitor(
+    args: &Args,
+    running: &Arc<AtomicBool>,
+    stop_at: Option<DateTime<Local>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // 每个监控窗口录制到各自的时间戳目录
+    if args.record_raw {
+        let name = args.package.as_deref().unwrap_or("system");
+        let path = replay::start_recording(&OutputLayout::session(name)?.dir(Category::Raw))?;
+        console!("Recording raw adb output to {}", path.display());
+    }
+    if args.all_devices {
+        return monitor_all_devices(args, running, stop_at).await;
+    }
+    match args.top {
+        Some(top) => monitor_system(args, top, running, stop_at).await,
+        None if args.system => monitor_system(args, DEFAULT_SYSTEM_TOP, running, stop_at).await,
+        None => monitor_process(args, running, stop_at).await,
+    }
+}
+
+// 每台已连接设备一个监控任务，峰值等状态各自独立，输出写入时间戳目录下以序列号命名的子目录
+async fn monitor_all_devices(
+    args: &Args,
+    running: &Arc<AtomicBool>,
+    stop_at: Option<DateTime<Local>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let devices = utils::list_devices()?;
+    if devices.is_empty() {
+        return Err("No Android devices connected".into());
+    }
+    console!(
+        "Monitoring {} devices in parallel: {}",
+        devices.len(),
+        devices.join(", ").cyan()
+    );
+
+    let tasks: Vec<_> = devices
+        .into_iter()
+        .map(|serial| {
+            let args = args.clone();
+            let running = running.clone();
+            let task = tokio::spawn(utils::with_device(Some(serial.clone()), async move {
+                monitor_process(&args, &running, stop_at)
+                    .await
+                    .map_err(|e| e.to_string())
+            }));
+            (serial, task)
+        })
+        .collect();
+
+    let mut failed = Vec::new();
+    for (serial, task) in tasks {
+        match task.await {
+            Ok(Ok(())) => console!("✓ Device {} finished", serial.cyan()),
+            Ok(Err(e)) => {
+                eprintln!("Monitor error on {}: {}", serial, e);
+                threshold::record_exit_code(1);
+                failed.push(serial);
+            }
+            Err(e) => {
+                eprintln!("Monitor task for {} failed: {}", serial, e);
+                threshold::record_exit_code(1);
+                failed.push(serial);
             }
         }
+    }
+    if !failed.is_empty() {
+        return Err(format!("Monitoring failed on {}", failed.join(", ")).into());
+    }
+    Ok(())
+}
 
-        // Check for process restart
-        match utils::get_process_info(&args.package) {
-            Ok(current_info) => {
-                if current_info.pid != last_process_info.pid {
-                    peak_stats.restart_count += 1;
-                    let timestamp = Local::now().format("%H:%M:%S").to_string();
-                    let restart_msg = format!(
-                        "[{}] Process restarted! New PID: {} (previous: {}), Start time: {}",
-                        timestamp.blue(),
-                        current_info.pid.yellow(),
-                        last_process_info.pid.red(),
-                        current_info.start_time
-                    );
+fn format_offenders(usages: &[ProcessUsage], value: fn(&ProcessUsage) -> String) -> String {
+    usages
+        .iter()
+        .take(3)
+        .map(|u| format!("{} {}", u.name, value(u)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
 
-                    let peaks = peak_stats.format_current_peaks();
-                    if !peaks.is_empty() {
-                        println!("{}\n\n{}", peaks, restart_msg);
-                    } else {
-                        println!("\n{}", restart_msg);
-                    }
+// 整机模式：每个 Tick 采样所有进程，输出前 N 名和排行榜，回答“是什么拖慢了设备”
+async fn monitor_system(
+    args: &Args,
+    top: usize,
+    running: &Arc<AtomicBool>,
+    stop_at: Option<DateTime<Local>>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    console!("{}", "XPerformance Monitor (system-wide)".green().bold());
+    console!("Tracking top {} processes by CPU and memory", top);
+    console!("Sampling interval: {} seconds", args.interval);
+
+    check_adb(args.serial.as_deref())?;
+
+    let cpu_cores = device::query_cpu_cores(None);
+    console!(
+        "CPU cores: {}, CPU scale: {} ({})",
+        cpu_cores,
+        args.cpu_scale.as_str(),
+        args.cpu_scale.unit()
+    );
+    if let Some(stop_at) = stop_at {
+        console!("Monitoring until {}", stop_at.format("%Y-%m-%d %H:%M:%S"));
+    }
 
-                    // 移除进程重启时的日志记录，只在整小时和退出时记录
-                    last_process_info = current_info;
-                }
+    let active = Arc::new(AtomicBool::new(true));
+    let connected = Arc::new(AtomicBool::new(true));
+    let adb_monitor = {
+        let active = active.clone();
+        let connected = connected.clone();
+        let reconnect_timeout = args.reconnect_timeout;
+        let connect = args.connect.clone();
+        let device = utils::current_task_device();
+        tokio::spawn(utils::with_device(
+            device,
+            monitor_adb_connection(active, connected, reconnect_timeout, connect),
+        ))
+    };
+
+    let mut sampler = FleetSampler::new(args.cpu_scale, cpu_cores);
+    let mut recorder = FleetRecorder::new(top);
+    let interval = Duration::from_secs(args.interval);
+    let mut sample_count: u64 = 0;
+    // --system：整机 CPU、内存和各进程 PSS
+    let mut system_sampler = SystemSampler::new();
+    let mut system_samples: Vec<SystemSample> = Vec::new();
+
+    // 首次采样只建立 CPU 基线
+    if let Err(e) = sampler.sample() {
+        console!("Failed to sample processes: {}", e);
+    }
+    if args.system {
+        if let Err(e) = system_sampler.sample(Local::now()) {
+            console!("Failed to sample system CPU and memory: {}", e);
+        }
+    }
+
+    let started = Instant::now();
+
+    while running.load(Ordering::SeqCst) && active.load(Ordering::SeqCst) {
+        if stop_at.is_some_and(|stop| Local::now() >= stop) {
+            console!("\n{}", "Monitoring window closed".yellow());
+            break;
+        }
+        if let Some(reason) = session_limit_reached(args, started, sample_count) {
+            console!("\n{}", reason.yellow());
+            break;
+        }
+        if !replay::is_replaying() {
+            sleep(interval).await;
+        }
+        if !connected.load(Ordering::SeqCst) {
+            continue;
+        }
+
+        sample_count += 1;
+        let tick = Tick::new(sample_count);
+        match sampler.sample() {
+            Ok(usages) => {
+                let (top_cpu, top_memory) = recorder.record(&tick, &usages);
+                console!(
+                    "[{}] Top CPU: {} | Top RSS: {}",
+                    tick.timestamp.format("%H:%M:%S").to_string().blue(),
+                    format_offenders(&top_cpu, |u| format!("{:.1}%", u.cpu)),
+                    format_offenders(&top_memory, |u| format!("{:.0} MB", u.rss_kb / 1024.0))
+                );
             }
             Err(e) => {
-                println!("\n{}: {}", "Process not found".red(), e);
-                running.store(false, Ordering::SeqCst);
-                break;
+                if args.verbose {
+                    console!("Failed to sample processes: {}", e);
+                }
             }
         }
 
-        if args.cpu {
-            if let Ok((cpu_usage, timestamp, top_threads)) = cpu::sample_cpu(&args.package).await {
-                if cpu_usage > peak_stats.cpu_usage {
-                    peak_stats.cpu_usage = cpu_usage;
-                    peak_stats.cpu_time = timestamp;
+        if args.system {
+            match system_sampler.sample(tick.timestamp) {
+                Ok(sample) => {
+                    console!(
+                        "[{}] Device CPU: {} | Memory used: {:.0} MB / {:.0} MB (available {:.0} MB)",
+                        tick.timestamp.format("%H:%M:%S").to_string().blue(),
+                        sample
+                            .cpu
+                            .map(|cpu| format!("{:.1}%", cpu))
+                            .unwrap_or_else(|| "-".to_string())
+                            .red(),
+                        sample.memory.used_kb() as f64 / 1024.0,
+                        sample.memory.total_kb as f64 / 1024.0,
+                        sample.memory.available_kb as f64 / 1024.0
+                    );
+                    system_samples.push(sample);
                 }
-                peak_stats
-                    .cpu_data
-                    .add_data_point(timestamp, cpu_usage, top_threads.clone());
-
-                // 将线程数据添加到时间序列跟踪
-                if args.thread {
-                    // 打印CPU占用最高的线程信息
-                    println!("Top CPU threads:");
-
-                    // 只显示最多5个线程，避免输出过多
-                    let display_count = std::cmp::min(5, top_threads.len());
-                    for (i, thread) in top_threads.iter().take(display_count).enumerate() {
-                        println!(
-                            "  {}: {} (TID: {}) - {:.1}%",
-                            i + 1,
-                            thread.name.cyan(),
-                            thread.tid.yellow(),
-                            thread.cpu_usage
-                        );
-                    }
-
-                    // 如果有更多线程，显示总数
-                    if top_threads.len() > display_count {
-                        println!(
-                            "  ... and {} more threads",
-                            top_threads.len() - display_count
-                        );
-                    }
-                    println!(); // 空行分隔
-
-                    for thread in &top_threads {
-                        let entry = thread_time_series
-                            .entry(thread.tid.clone())
-                            .or_insert_with(Vec::new);
-                        entry.push(thread.clone());
+                Err(e) => {
+                    if args.verbose {
+                        console!("Failed to sample system CPU and memory: {}", e);
                     }
                 }
             }
-        }
-
-        if args.memory {
-            if let Ok((memory_kb, timestamp, memory_details)) =
-                memory::sample_memory(&args.package, args.verbose).await
-            {
-                if memory_kb > peak_stats.memory_usage {
-                    peak_stats.memory_usage = memory_kb;
-                    peak_stats.memory_time = timestamp;
+            match system::sample_pss_by_process() {
+                Ok(processes) => {
+                    let top_pss = recorder.record_pss(&tick, &processes);
+                    console!(
+                        "[{}] Top PSS: {}",
+                        tick.timestamp.format("%H:%M:%S").to_string().blue(),
+                        top_pss
+                            .iter()
+                            .take(3)
+                            .map(|(name, pss_kb)| format!("{} {:.0} MB", name, pss_kb / 1024.0))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    );
                 }
-
-                // 添加内存数据点到时间序列
-                peak_stats
-                    .memory_data
-                    .add_data_point(timestamp, memory_details);
-
-                // 如果开启了详细模式并且已收集了足够的数据点，生成内存图表
-                if args.verbose && peak_stats.memory_data.timestamps.len() >= 5 {
-                    if let Ok(timestamp_dir) = utils::create_timestamp_subdir(&args.package) {
-                        // 创建memory子目录
-                        let memory_dir = timestamp_dir.join("memory");
-                        if !memory_dir.exists() {
-                            if let Err(e) = std::fs::create_dir_all(&memory_dir) {
-                                println!("Failed to create memory directory: {}", e);
-                                continue;
-                            }
-                            println!("Created memory directory: {}", memory_dir.display());
-                        }
-
-                        // 生成内存图表
-                        let memory_charts = generate_memory_charts(
-                            &memory_dir,
-                            &args.package,
-                            &peak_stats.memory_data,
-                        );
-                        if let Ok(chart_paths) = memory_charts {
-                            for path in chart_paths {
-                                if path.to_string_lossy().ends_with(".png") {
-                                    println!("✓ Memory chart generated: {}", path.display());
-                                } else if path.to_string_lossy().ends_with(".csv") {
-                                    println!("✓ Memory data exported to CSV: {}", path.display());
-                                }
-                            }
-                        } else {
-                            println!("Failed to generate memory charts");
-                        }
+                Err(e) => {
+                    if args.verbose {
+                        console!("Failed to sample process PSS: {}", e);
                     }
                 }
             }
         }
     }
 
-    // Wait for ADB monitor to finish
+    active.store(false, Ordering::SeqCst);
     let _ = adb_monitor.await;
 
-    // 在结束前生成最终的线程时间序列图表
-    if args.thread && args.cpu && !thread_time_series.is_empty() {
-        println!("Program ending, generating final thread time series chart...");
-        if let Ok(timestamp_dir) = utils::create_timestamp_subdir(&args.package) {
-            // 创建thread子目录
-            let thread_dir = timestamp_dir.join("thread");
-            if !thread_dir.exists() {
-                if let Err(e) = std::fs::create_dir_all(&thread_dir) {
-                    println!("Failed to create thread directory: {}", e);
-                    return Ok(());
-                }
-                println!("Created thread directory: {}", thread_dir.display());
-            }
+    if recorder.is_empty() {
+        return Ok(());
+    }
+
+    console!("\n{}", "Top Offenders (by mean CPU):".green().bold());
+    for (rank, (name, stats)) in recorder.leaderboard().iter().take(top).enumerate() {
+        console!(
+            "{:>3}. {} - CPU mean {:.1}% / peak {:.1}%, RSS mean {:.0} MB / peak {:.0} MB",
+            rank + 1,
+            name.cyan(),
+            stats.mean_cpu(),
+            stats.cpu_peak,
+            stats.mean_rss() / 1024.0,
+            stats.rss_peak / 1024.0
+        );
+    }
 
-            // 导出最终的线程数据
-            match utils::export_thread_data_to_csv(
-                thread_dir.clone(),
-                &last_process_info.pid,
-                &thread_time_series
-                    .values()
-                    .flat_map(|v| v.iter().cloned())
-                    .collect::<Vec<_>>(),
-                false,
-            ) {
-                Ok(filenames) => {
-                    println!(
-                        "✓ Final thread data exported to {} CSV files",
-                        filenames.len()
+    if !system_samples.is_empty() {
+        let system_dir = OutputLayout::session("system")?.dir(Category::Device);
+        match system::export_system_data(&system_dir, &system_samples) {
+            Ok((paths, cpu, used)) => {
+                if let Some(cpu) = cpu {
+                    console!(
+                        "Device CPU: mean {:.1}% / peak {}",
+                        cpu.mean,
+                        format!("{:.1}%", cpu.max).red()
                     );
                 }
-                Err(e) => {
-                    println!("Failed to export final thread data to CSV: {}", e);
+                if let Some(used) = used {
+                    let total = system_samples[0].memory.total_kb as f64;
+                    console!(
+                        "Device Memory Used: mean {:.0} MB / peak {} of {:.0} MB",
+                        used.mean / 1024.0,
+                        format!("{:.0} MB", used.max / 1024.0).red(),
+                        total / 1024.0
+                    );
+                }
+                for path in paths {
+                    console!("✓ Device data exported: {}", path.display());
                 }
             }
+            Err(e) => console!("Failed to export device data: {}", e),
+        }
+    }
 
-            // 生成最终的线程时间序列图表
-            match utils::generate_thread_time_series_chart(
-                thread_dir,
-                &args.package,
-                &last_process_info.pid,
-                &thread_time_series,
-            ) {
-                Ok(chart_filename) => {
-                    if !chart_filename.is_empty() {
-                        println!(
-                            "✓ Final thread time series chart generated: {}",
-                            chart_filename
-                        );
-                    }
-                }
-                Err(e) => {
-                    println!("Failed to generate final thread time series chart: {}", e);
-                }
+    let fleet_dir = OutputLayout::session("system")?.dir(Category::Fleet);
+    match recorder.write(&fleet_dir, args.cpu_scale) {
+        Ok(paths) => {
+            for path in paths {
+                console!("✓ Fleet data exported: {}", path.display());
             }
         }
+        Err(e) => console!("Failed to export fleet data: {}", e),
     }
 
-    // 创建时间戳目录
-    let timestamp_dir = if let Ok(dir) = utils::create_timestamp_subdir(&args.package) {
-        dir
-    } else {
-        println!("Warning: Could not create timestamp directory.");
-        return Ok(());
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // 公共库的输出同样进入仪表盘日志区或 --ci 的 stderr
+    output::set_sink(tui::console_line);
+    // 配置文件中的选项放在命令行参数之前，命令行指定的值优先
+    let mut argv: Vec<std::ffi::OsString> = std::env::args_os().collect();
+    // 子命令不使用配置文件
+    let is_subcommand = argv.get(1).is_some_and(|arg| {
+        Args::command()
+            .find_subcommand(arg.to_string_lossy().as_ref())
+            .is_some()
+    });
+    let config_path = (!is_subcommand).then(|| config::locate(&argv)).flatten();
+    if let Some(path) = &config_path {
+        let options = config::to_args(path, &Args::command())?;
+        argv.splice(1..1, options);
+    }
+    let mut args = Args::parse_from(&argv);
+    if args.ci {
+        tui::set_ci_mode();
+    }
+    if let Some(path) = &config_path {
+        console!("Using config: {}", path.display());
+    }
+    let ab_test = match &args.command {
+        Some(Commands::AbTest {
+            apk_a,
+            apk_b,
+            runs,
+            cooldown,
+            threshold,
+            output,
+            ..
+        }) => Some(AbTestPlan {
+            apks: [apk_a.clone(), apk_b.clone()],
+            runs: *runs,
+            cooldown: *cooldown,
+            threshold: *threshold,
+            output: output.clone(),
+        }),
+        _ => None,
     };
+    // 场景的 [monitor] 表和其后的命令行选项重新解析为监控参数
+    if let Some(Commands::RunScenario { script, options })
+    | Some(Commands::AbTest {
+        script, options, ..
+    }) = &args.command
+    {
+        let scenario = Scenario::load(script)?;
+        let mut monitor_argv = argv[..1].to_vec();
+        monitor_argv.extend(config::table_to_args(
+            scenario.monitor.clone(),
+            script,
+            &Args::command(),
+        )?);
+        monitor_argv.extend(options.iter().cloned());
+        args = Args::parse_from(monitor_argv);
+        if args.command.is_some() {
+            anyhow::bail!("Scenario options cannot contain another subcommand");
+        }
+        if args.package.is_none() || args.all_devices || args.system || args.top.is_some() {
+            anyhow::bail!(
+                "A scenario monitors a single app: set package in [monitor] or pass -p <package>"
+            );
+        }
+        args.scenario = Some(scenario);
+    }
+    if args.normalize_cpu {
+        args.cpu_scale = CpuScale::Total;
+    }
+    utils::set_output_root(args.output_dir.as_deref());
+    if let Some(size) = args.chart_size {
+        chart::set_chart_size(size);
+    }
+    chart::set_max_points(args.chart_max_points);
+    if let Some(theme) = &args.chart_theme {
+        chart::set_theme(theme.clone());
+    }
 
-    // 程序结束时生成CPU图表
-    if args.cpu && peak_stats.cpu_data.timestamps.len() > 1 {
-        // 创建CPU子目录
-        let cpu_dir = timestamp_dir.join("cpu");
-        if !cpu_dir.exists() {
-            if let Err(e) = std::fs::create_dir_all(&cpu_dir) {
-                println!("Failed to create CPU directory: {}", e);
-                return Ok(());
+    match &args.command {
+        Some(Commands::Compare {
+            baseline,
+            candidate,
+            threshold,
+            output,
+        }) => return compare_runs(baseline, candidate, *threshold, output.as_deref()),
+        Some(Commands::Heapdump {
+            package,
+            serial,
+            native,
+            timeout,
+            output,
+        }) => {
+            return dump_heap(
+                package,
+                serial.as_deref(),
+                *native,
+                *timeout,
+                output.as_deref(),
+            )
+        }
+        Some(Commands::Pair {
+            address,
+            code,
+            connect,
+        }) => return pair_device(address.as_deref(), code.as_deref(), *connect),
+        Some(Commands::Report {
+            session_dir,
+            output,
+            from,
+            to,
+            chart_size,
+            chart_max_points,
+            chart_theme,
+            stamp_filenames,
+        }) => {
+            if let Some(size) = chart_size {
+                chart::set_chart_size(*size);
             }
-            println!("Created CPU directory: {}", cpu_dir.display());
+            chart::set_max_points(*chart_max_points);
+            if let Some(theme) = chart_theme {
+                chart::set_theme(theme.clone());
+            }
+            let window = TimeWindow::parse(from.as_deref(), to.as_deref())?;
+            return report_session(session_dir, output.as_deref(), &window, *stamp_filenames);
         }
+        // 已在上面转换为监控参数
+        Some(Commands::RunScenario { .. }) | Some(Commands::AbTest { .. }) | None => {}
+    }
+
+    // --connect：先连接 TCP 设备，之后以该地址作为设备序列号
+    if let Some(address) = &args.connect {
+        utils::adb_connect(address)?;
+        console!("Connected to {}", address.cyan());
+        args.serial = Some(address.clone());
+    }
+
+    // 不再调用init_logging初始化日志文件
+    // if args.verbose {
+    //     utils::init_logging(&args.package, args.cpu, args.memory)?;
+    // }
+
+    // Set up signal handling
+    let running = Arc::new(AtomicBool::new(true));
+    let r = running.clone();
+    ctrlc::set_handler(move || {
+        r.store(false, Ordering::SeqCst);
+        // 设置中断标志
+        utils::set_interrupt_flag();
+        console!("\n程序正在退出...");
+    })?;
 
-        println!(
-            "Peak CPU Usage: {} at {}",
-            format!("{:.1}%", peak_stats.cpu_usage).red(),
-            peak_stats.cpu_time.format("%Y-%m-%d %H:%M:%S")
+    if let Some(capture) = &args.replay {
+        let count = replay::start_replay(capture, args.serial.as_deref())?;
+        console!(
+            "Replaying {} recorded adb outputs from {}",
+            count,
+            capture.display()
         );
+    }
 
-        // 生成CPU图表
-        let chart_path = match utils::generate_cpu_chart(
-            &args.package,
-            &peak_stats.cpu_data.timestamps,
-            &peak_stats.cpu_data.process_cpu,
-            &last_process_info.pid,
-        ) {
-            Ok(path) => path,
-            Err(e) => {
-                println!("Failed to generate CPU chart: {}", e);
-                return Ok(());
+    // 指标服务在整个运行期间保持，计划窗口之间继续提供最后的值
+    if let Some(port) = args.prometheus_port {
+        prometheus::serve(port).await?;
+        console!(
+            "Prometheus metrics: {}",
+            format!("http://localhost:{}/metrics", port).cyan()
+        );
+    }
+
+    if let Some(port) = args.serve {
+        web::serve(port).await?;
+        console!(
+            "Web dashboard: {}",
+            format!("http://localhost:{}/", port).cyan()
+        );
+    }
+
+    if let Some(plan) = &ab_test {
+        // 每次运行各自结束监控，--ci 无法只输出一个 JSON 对象
+        if args.ci {
+            anyhow::bail!("--ci is not supported by ab-test");
+        }
+        return run_ab_test(&args, plan, &running).await;
+    }
+
+    let schedule = match (&args.schedule, &args.start_at, &args.stop_at) {
+        (Some(window), _, _) => Some(Schedule::parse_window(window)?),
+        (None, None, None) => None,
+        (None, start, stop) => Some(Schedule {
+            start: start.as_deref().map(schedule::parse_time).transpose()?,
+            stop: stop.as_deref().map(schedule::parse_time).transpose()?,
+        }),
+    };
+
+    // 直接调用monitor_process函数
+    let Some(schedule) = schedule else {
+        if let Err(e) = run_monitor(&args, &running, None).await {
+            eprintln!("Monitor error: {}", e);
+            report_ci_error(&args, &e.to_string());
+            threshold::record_exit_code(1);
+        }
+        return exit_on_threshold_violation();
+    };
+
+    // 按计划窗口监控：等待窗口开始，监控并导出，然后退出或等待下一个窗口
+    while running.load(Ordering::SeqCst) {
+        let (open, close) = schedule.next_window(Local::now());
+        if open > Local::now() {
+            console!(
+                "Waiting for monitoring window: {} - {}",
+                open.format("%Y-%m-%d %H:%M:%S").to_string().green(),
+                close
+                    .map(|c| c.format("%Y-%m-%d %H:%M:%S").to_string())
+                    .unwrap_or_else(|| "Ctrl-C".to_string())
+            );
+            while running.load(Ordering::SeqCst) && Local::now() < open {
+                sleep(Duration::from_secs(1)).await;
             }
-        };
+            if !running.load(Ordering::SeqCst) {
+                break;
+            }
+        }
 
-        // 复制CPU图表到输出目录
-        let target_path = cpu_dir.join(chart_path.file_name().unwrap());
-        if let Err(e) = std::fs::copy(&chart_path, &target_path) {
-            println!("Failed to copy CPU chart to output directory: {}", e);
-        } else {
-            println!("✓ CPU chart generated: {}", target_path.display());
+        // 每个窗口输出到单独的时间戳目录
+        utils::reset_timestamp_subdir();
+        // --ci 与计划窗口互斥，这里出错时继续后面的窗口
+        if let Err(e) = run_monitor(&args, &running, close).await {
+            eprintln!("Monitor error: {}", e);
+            threshold::record_exit_code(1);
         }
 
-        // 导出CPU数据到CSV
-        let csv_path = cpu_dir.join(format!("{}_cpu_data.csv", args.package));
-        if let Ok(_) = utils::export_cpu_data_to_csv(
-            &csv_path,
-            &peak_stats.cpu_data.timestamps,
-            &peak_stats.cpu_data.process_cpu,
-        ) {
-            println!("✓ CPU data exported to CSV: {}", csv_path.display());
+        let Some(close) = close.filter(|_| args.repeat) else {
+            break;
+        };
+
+        // 窗口提前结束（如进程退出）时等到窗口关闭，避免在同一窗口内反复重试
+        while running.load(Ordering::SeqCst) && Local::now() < close {
+            sleep(Duration::from_secs(1)).await;
         }
     }
 
-    if args.memory {
-        println!(
-            "Peak Memory Usage: {} at {}",
-            format!("{} KB", peak_stats.memory_usage).red(),
-            peak_stats.memory_time.format("%Y-%m-%d %H:%M:%S")
-        );
+    exit_on_threshold_violation()
+}
 
-        // 如果收集了足够的内存数据点，生成内存图表
-        if peak_stats.memory_data.timestamps.len() > 1 {
-            // 在时间戳目录下创建memory子目录
-            let memory_dir = timestamp_dir.join("memory");
-            if !memory_dir.exists() {
-                if let Err(e) = std::fs::create_dir_all(&memory_dir) {
-                    println!("Failed to create memory directory: {}", e);
-                    return Ok(());
-                }
-                println!("Created memory directory: {}", memory_dir.display());
-            }
+// --duration 或 --samples 到达时返回提示，采样循环随之结束并照常导出
+fn session_limit_reached(args: &Args, started: Instant, collected: u64) -> Option<String> {
+    if let Some(duration) = args.duration.filter(|d| started.elapsed() >= *d) {
+        return Some(format!(
+            "Session duration reached ({}s)",
+            duration.as_secs()
+        ));
+    }
+    args.samples
+        .filter(|n| collected >= *n)
+        .map(|n| format!("Collected {} samples", n))
+}
 
-            // 生成内存图表
-            let memory_charts =
-                generate_memory_charts(&memory_dir, &args.package, &peak_stats.memory_data);
-            if let Ok(chart_paths) = memory_charts {
-                for path in chart_paths {
-                    if path.to_string_lossy().ends_with(".png") {
-                        println!("✓ Memory chart generated: {}", path.display());
-                    } else if path.to_string_lossy().ends_with(".csv") {
-                        println!("✓ Memory data exported to CSV: {}", path.display());
-                    }
-                }
+// 监控期间有 --max-* 阈值超限或监控失败时以对应的退出码结束，供 CI 判定性能门禁
+fn exit_on_threshold_violation() -> Result<()> {
+    let code = threshold::exit_code();
+    if code != 0 {
+        console!("{}", format!("Exiting with code {}", code).red().bold());
+        std::process::exit(code);
+    }
+    Ok(())
+}
+
+// 无线调试配对：未给出地址时列出 mDNS 发现的设备，配对后可直接连接
+fn pair_device(address: Option<&str>, code: Option<&str>, connect: bool) -> Result<()> {
+    console!("{}", "XPerformance Wireless Pairing".green().bold());
+    let Some(address) = address else {
+        let services = pairing::discover()?;
+        if services.is_empty() {
+            console!(
+                "No wireless debugging devices found over mDNS. Open Developer options > Wireless debugging > Pair device with pairing code on the device"
+            );
+            return Ok(());
+        }
+        for service in &services {
+            let kind = if service.is_pairing() {
+                "pairable".yellow()
+            } else if service.is_connect() {
+                "paired".green()
             } else {
-                println!("Failed to generate memory charts");
-            }
+                service.service_type.normal()
+            };
+            console!(
+                "{:<10} {:<24} {}",
+                kind,
+                service.address.cyan(),
+                service.name
+            );
+        }
+        console!("Pair with: xperformance pair <ip:port> <code>");
+        return Ok(());
+    };
+
+    let code = match code {
+        Some(code) => code.to_string(),
+        None => {
+            print!("Pairing code: ");
+            std::io::stdout().flush()?;
+            let mut line = String::new();
+            std::io::stdin().read_line(&mut line)?;
+            line.trim().to_string()
+        }
+    };
+    pairing::pair(address, &code)?;
+    console!("✓ Paired with {}", address.cyan());
+
+    let ip = address.rsplit_once(':').map_or(address, |(ip, _)| ip);
+    // 配对后设备需要片刻才会发布连接服务
+    let mut connect_address = None;
+    for _ in 0..5 {
+        connect_address = pairing::find_connect_address(&pairing::discover()?, ip);
+        if connect_address.is_some() {
+            break;
+        }
+        std::thread::sleep(Duration::from_secs(1));
+    }
+    match connect_address {
+        Some(target) if connect => {
+            utils::adb_connect(&target)?;
+            console!("✓ Connected to {}", target.cyan());
         }
+        Some(target) => console!(
+            "Connect with: xperformance --connect {} --package <package> ...",
+            target
+        ),
+        None => console!(
+            "{}",
+            "Wireless debugging port not found over mDNS; use the IP address and port shown on the device with --connect"
+                .yellow()
+        ),
     }
-    println!(
-        "Process Restarts: {}",
-        peak_stats.restart_count.to_string().red()
+    Ok(())
+}
+
+// 抓取应用堆转储并拉取到本地，Java 堆在设置了 ANDROID_HOME 时转换为标准 hprof
+fn dump_heap(
+    package: &str,
+    serial: Option<&str>,
+    native: bool,
+    timeout: Duration,
+    output: Option<&Path>,
+) -> Result<()> {
+    console!("{}", "XPerformance Heap Dump".green().bold());
+    check_adb(serial)?;
+    let process = utils::get_process_info(package)?;
+    console!(
+        "Dumping {} heap of {} (PID {})...",
+        if native { "native" } else { "Java" },
+        package.cyan(),
+        process.pid.yellow()
     );
 
+    let output_dir = match output {
+        Some(dir) => dir.to_path_buf(),
+        None => OutputLayout::session(package)?.dir(Category::Heapdump),
+    };
+    let dump = heapdump::capture(package, &process.pid, native, timeout, &output_dir)?;
+    console!("✓ Heap dump pulled: {}", dump.raw.display());
+    match &dump.converted {
+        Some(path) => console!("✓ Converted with hprof-conv: {}", path.display()),
+        None if !native => console!(
+            "{}",
+            "hprof-conv not found (set ANDROID_HOME); Android Studio opens the raw dump, MAT needs it converted"
+                .yellow()
+        ),
+        None => {}
+    }
     Ok(())
 }
 
-// 生成内存图表的函数
-fn generate_memory_charts(
-    output_dir: &PathBuf,
-    package: &str,
-    memory_data: &MemoryTimeSeriesData,
-) -> Result<Vec<PathBuf>> {
-    use plotters::prelude::*;
-
-    // 创建一个单一的内存图表文件
-    let mut chart_paths = Vec::new();
-    let file_name = format!("{}_memory_chart.png", package);
-    let path = output_dir.join(file_name);
-
-    // 检查数据是否足够
-    if memory_data.timestamps.is_empty() || memory_data.memory_details.is_empty() {
-        return Err(anyhow::format_err!("No memory data to chart"));
-    }
-
-    // 创建图表
-    let root = BitMapBackend::new(&path, (1920, 1080)).into_drawing_area();
-    root.fill(&WHITE)?;
-
-    // 创建图表标题
-    let title = format!("Memory Usage - {}", package);
-
-    // 分割绘图区域为标题、图表和图例
-    let (title_area, rest_area) = root.split_vertically(50);
-
-    // 绘制标题
-    title_area.titled(&title, ("sans-serif", 20))?;
-
-    // 查找最大内存使用量以设置Y轴范围
-    let mut max_memory = 0.1f32;
-    for detail in &memory_data.memory_details {
-        max_memory = max_memory.max(detail.total_pss as f32);
-        max_memory = max_memory.max(detail.java_heap as f32);
-        max_memory = max_memory.max(detail.native_heap as f32);
-        max_memory = max_memory.max(detail.code as f32);
-        max_memory = max_memory.max(detail.stack as f32);
-        max_memory = max_memory.max(detail.graphics as f32);
-        max_memory = max_memory.max(detail.private_other as f32);
-        max_memory = max_memory.max(detail.system as f32);
-    }
-
-    // 添加一些填充到最大内存使用量
-    max_memory = max_memory * 1.1;
-
-    // 获取时间范围
-    let min_time = *memory_data.timestamps.front().unwrap();
-    let max_time = *memory_data.timestamps.back().unwrap();
-
-    // 定义内存类型和对应的名称
-    let memory_types = [
-        "Total PSS",
-        "Java Heap",
-        "Native Heap",
-        "Code",
-        "Stack",
-        "Graphics",
-        "Private Other",
-        "System",
-    ];
-
-    // 定义颜色
-    let colors = [
-        &RED,
-        &BLUE,
-        &GREEN,
-        &YELLOW,
-        &MAGENTA,
-        &CYAN,
-        &RGBColor(128, 0, 0),
-        &RGBColor(0, 128, 0),
-    ];
-
-    // 创建图表上下文
-    let mut chart = ChartBuilder::on(&rest_area)
-        .margin(10)
-        .margin_right(35) // 增加右侧边距为图例留出空间
-        .x_label_area_size(40)
-        .y_label_area_size(60)
-        .build_cartesian_2d(min_time..max_time, 0f32..max_memory)?;
-
-    // 配置网格
-    chart
-        .configure_mesh()
-        .x_labels(8)
-        .x_label_formatter(&|x| x.format("%H:%M:%S").to_string())
-        .y_desc("Memory Usage (KB)")
-        .x_desc("Time")
-        .draw()?;
-
-    // 为每种内存类型绘制数据线
-    for (i, &memory_type) in memory_types.iter().enumerate() {
-        let color = colors[i];
-
-        // 根据内存类型获取对应的数据
-        let values: Vec<(DateTime<Local>, f32)> = memory_data
-            .timestamps
-            .iter()
-            .zip(memory_data.memory_details.iter())
-            .map(|(t, d)| {
-                let value = match i {
-                    0 => d.total_pss as f32,
-                    1 => d.java_heap as f32,
-                    2 => d.native_heap as f32,
-                    3 => d.code as f32,
-                    4 => d.stack as f32,
-                    5 => d.graphics as f32,
-                    6 => d.private_other as f32,
-                    7 => d.system as f32,
-                    _ => 0.0,
-                };
-                (t.to_owned(), value)
-            })
-            .collect();
-
-        // 绘制数据线
-        chart
-            .draw_series(LineSeries::new(values, color.clone()))?
-            .label(memory_type.to_string())
-            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
-    }
-
-    // 添加图例配置
-    chart
-        .configure_series_labels()
-        .background_style(WHITE.mix(0.8))
-        .border_style(BLACK)
-        .position(SeriesLabelPosition::UpperRight)
-        .margin(10)
-        .legend_area_size(35) // 增加图例区域大小
-        .label_font(("sans-serif", 15)) // 增加字体大小
-        .draw()?;
-
-    // 保存图表
-    root.present()?;
-
-    chart_paths.push(path.clone());
-    // 移除输出，由调用者处理输出
-    // println!("✓ Memory chart generated: {}", path.display());
-
-    // 导出内存数据到CSV
-    let csv_path = output_dir.join(format!("{}_memory_data.csv", package));
-    if let Ok(file) = std::fs::File::create(&csv_path) {
-        let mut writer = std::io::BufWriter::new(file);
-
-        // 写入CSV头
-        writeln!(
-            &mut writer,
-            "Timestamp,Total PSS,Java Heap,Native Heap,Code,Stack,Graphics,Private Other,System"
-        )?;
-
-        // 写入每个数据点
-        for i in 0..memory_data.timestamps.len() {
-            let timestamp = &memory_data.timestamps[i];
-            let details = &memory_data.memory_details[i];
-
-            writeln!(
-                &mut writer,
-                "{},{},{},{},{},{},{},{},{}",
-                timestamp.format("%Y-%m-%d %H:%M:%S"),
-                details.total_pss,
-                details.java_heap,
-                details.native_heap,
-                details.code,
-                details.stack,
-                details.graphics,
-                details.private_other,
-                details.system
-            )?;
-        }
-
-        // 添加CSV文件路径到返回结果
-        chart_paths.push(csv_path.clone());
-        // 移除输出，由调用者处理输出
-        // println!("✓ Memory data exported to CSV: {}", csv_path.display());
-    }
-
-    Ok(chart_paths)
+// 根据会话目录中的 CSV 重新生成所有图表和报告，使用当前的图表选项（尺寸、主题）
+fn report_session(
+    session_dir: &Path,
+    output: Option<&Path>,
+    window: &TimeWindow,
+    stamp_filenames: bool,
+) -> Result<()> {
+    console!("{}", "XPerformance Report".green().bold());
+    console!("Session: {}", session_dir.display().to_string().cyan());
+    if !session_dir.is_dir() {
+        anyhow::bail!("Session directory not found: {}", session_dir.display());
+    }
+
+    if let Some(info) = SessionInfo::load(session_dir)? {
+        chart::set_stamp(&info.stamp_label(), stamp_filenames);
+    }
+
+    let output_dir = output
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| regenerate::default_output_dir(session_dir, window));
+    if !window.is_full() {
+        console!("Time window: {}", window.label().yellow());
+    }
+
+    let generated = regenerate::regenerate_session(session_dir, &output_dir, window)?;
+    for path in generated {
+        console!("✓ Generated: {}", path.display());
+    }
+
+    // 进程 CPU 图连同活动底纹和限频区间按监控时的样式重画
+    match regenerate::redraw_cpu_chart(session_dir, &output_dir, window) {
+        Ok(Some(path)) => console!("✓ Generated: {}", path.display()),
+        Ok(None) => {}
+        Err(e) => console!("Failed to regenerate CPU chart: {}", e),
+    }
+
+    Ok(())
 }
 
-// 保留原始的单个内存指标图表函数，但它不会被直接调用
-#[allow(dead_code)]
-fn generate_single_memory_chart(
-    output_dir: &PathBuf,
-    package: &str,
-    metric_name: &str,
-    timestamps: &VecDeque<DateTime<Local>>,
-    values: &Vec<f32>,
-) -> Result<PathBuf> {
-    use plotters::prelude::*;
-
-    // 创建文件名，用下划线替换空格
-    let file_name = format!("{}_{}.png", package, metric_name.replace(" ", "_"));
-    let path = output_dir.join(file_name);
-    let path_copy = path.clone();
-
-    // 创建图表
-    let root = BitMapBackend::new(&path, (1920, 1080)).into_drawing_area();
-    root.fill(&WHITE)?;
-
-    // 找到最大值
-    let max_value = values.iter().fold(0.0f32, |a, &b| a.max(b)) * 1.1;
-
-    // 获取开始和结束时间
-    let first_timestamp = timestamps.front().unwrap();
-    let last_timestamp = timestamps.back().unwrap();
-
-    // 定义图表区域
-    let mut chart = ChartBuilder::on(&root)
-        .caption(
-            format!("{} - {}", package, metric_name),
-            ("sans-serif", 22).into_font(),
-        )
-        .margin(10)
-        .x_label_area_size(40)
-        .y_label_area_size(60)
-        .build_cartesian_2d(
-            first_timestamp.to_owned()..last_timestamp.to_owned(),
-            0.0..max_value,
-        )?;
-
-    // 配置网格和标签
-    chart
-        .configure_mesh()
-        .x_labels(10)
-        .x_label_formatter(&|x| x.format("%H:%M:%S").to_string())
-        .y_desc(format!("{} (KB)", metric_name))
-        .draw()?;
-
-    // 绘制折线
-    chart.draw_series(LineSeries::new(
-        timestamps
-            .iter()
-            .zip(values.iter())
-            .map(|(t, &v)| (t.to_owned(), v)),
-        &RED,
-    ))?;
-
-    // 保存图表
-    root.present()?;
-
-    Ok(path_copy)
+// ab-test 的参数，监控参数与 run-scenario 一样另行解析
+struct AbTestPlan {
+    apks: [PathBuf; 2],
+    runs: usize,
+    cooldown: Duration,
+    threshold: f64,
+    output: Option<PathBuf>,
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    let args = Args::parse();
+// 依次安装 A、B 两个 APK，各执行 runs 次场景并监控，最后对比两组运行的 CPU 和 PSS
+async fn run_ab_test(args: &Args, plan: &AbTestPlan, running: &Arc<AtomicBool>) -> Result<()> {
+    console!("{}", "XPerformance A/B Test".green().bold());
+    let package = args.package.as_deref().unwrap_or_default();
+    check_adb(args.serial.as_deref())?;
 
-    // 不再调用init_logging初始化日志文件
-    // if args.verbose {
-    //     utils::init_logging(&args.package, args.cpu, args.memory)?;
-    // }
+    let runs = plan.runs.max(1);
+    let mut variants: Vec<Variant> = Vec::new();
+    for (name, apk) in ["A", "B"].into_iter().zip(&plan.apks) {
+        if !running.load(Ordering::SeqCst) {
+            break;
+        }
+        let tag = format!("[{}]", name).green().bold();
+        console!("\n{} Installing {}", tag, apk.display());
+        abtest::install_apk(apk)?;
+        let info = package::query_package_info(None, package).unwrap_or_default();
+        let mut variant = Variant {
+            name: name.to_string(),
+            apk: apk.clone(),
+            version: format!("{} ({})", info.version_name, info.version_code),
+            runs: Vec::new(),
+        };
+        console!("{} Version {}", tag, variant.version.cyan());
+
+        for run in 1..=runs {
+            // 第一次运行之外先等待设备降温，减少温度对后一个 APK 的影响
+            if variant.name != "A" || run > 1 {
+                let resume = Instant::now() + plan.cooldown;
+                while running.load(Ordering::SeqCst) && Instant::now() < resume {
+                    sleep(Duration::from_millis(500)).await;
+                }
+            }
+            if !running.load(Ordering::SeqCst) {
+                break;
+            }
+            console!("\n{} Run {}/{}", tag, run, runs);
+            // 每次运行输出到单独的时间戳目录
+            utils::reset_timestamp_subdir();
+            if let Err(e) = monitor_process(args, running, None).await {
+                eprintln!("Monitor error: {}", e);
+                threshold::record_exit_code(1);
+            }
+            let session_dir = OutputLayout::session(package)?.session_dir().to_path_buf();
+            match Run::load(&session_dir) {
+                Ok(result) => variant.runs.push(result),
+                Err(e) => console!("{} Run {} has no usable data: {}", tag, run, e),
+            }
+        }
+        variants.push(variant);
+    }
+    utils::reset_timestamp_subdir();
 
-    // 直接调用monitor_process函数
-    if let Err(e) = monitor_process(&args).await {
-        eprintln!("Monitor error: {}", e);
+    let [a, b] = variants.as_slice() else {
+        anyhow::bail!("A/B test interrupted before both APKs were measured");
+    };
+    if a.runs.is_empty() || b.runs.is_empty() {
+        anyhow::bail!("No CPU or PSS data for both APKs");
+    }
+    let deltas = abtest::compare_variants(&a.runs, &b.runs, plan.threshold);
+    if deltas.is_empty() {
+        anyhow::bail!("No CPU or PSS data present in both APKs' runs");
+    }
+    console!("\nA: {}", a.label().cyan());
+    console!("B: {}", b.label().cyan());
+    console!(
+        "\n{:<10} {:<6} {:>14} {:>14} {:>10} {:>8}",
+        "Metric",
+        "Stat",
+        "A",
+        "B",
+        "Delta",
+        "P"
+    );
+    for delta in &deltas {
+        let percent = delta
+            .percent
+            .map(|p| format!("{:+.1}%", p))
+            .unwrap_or_else(|| "-".to_string());
+        let p_value = delta
+            .p_value
+            .map(|p| format!("{:.3}", p))
+            .unwrap_or_else(|| "-".to_string());
+        console!(
+            "{:<10} {:<6} {:>14.2} {:>14.2} {:>10} {:>8}",
+            delta.metric,
+            delta.statistic.as_str(),
+            delta.a,
+            delta.b,
+            if delta.regression {
+                percent.red().bold().to_string()
+            } else {
+                percent
+            },
+            if delta.is_significant() {
+                p_value.yellow().to_string()
+            } else {
+                p_value
+            }
+        );
     }
 
-    Ok(())
+    let output_dir = match &plan.output {
+        Some(dir) => dir.clone(),
+        None => OutputLayout::session("ab-test")?.dir(Category::Compare),
+    };
+    for path in abtest::export_ab_test(&output_dir, a, b, &deltas, plan.threshold)? {
+        console!("✓ A/B comparison exported: {}", path.display());
+    }
+
+    let regressions = deltas.iter().filter(|d| d.regression).count();
+    if regressions == 0 {
+        console!(
+            "\n{}",
+            format!(
+                "No significant regressions above {:.0}% in B",
+                plan.threshold
+            )
+            .green()
+        );
+        return exit_on_threshold_violation();
+    }
+    console!(
+        "\n{}",
+        format!(
+            "{} significant regression(s) above {:.0}% in B",
+            regressions, plan.threshold
+        )
+        .red()
+        .bold()
+    );
+    std::process::exit(1);
+}
+
+// 对比两次运行，存在回归时以退出码 1 结束
+
+fn compare_runs(
+    baseline: &Path,
+    candidate: &Path,
+    threshold: f64,
+    output: Option<&Path>,
+) -> Result<()> {
+    console!("{}", "XPerformance Compare".green().bold());
+    let baseline = Run::load(baseline)?;
+    let candidate = Run::load(candidate)?;
+    console!("Baseline:  {}", baseline.label.cyan());
+    console!("Candidate: {}", candidate.label.cyan());
+
+    let deltas = regression::compare(&baseline, &candidate, threshold);
+    if deltas.is_empty() {
+        anyhow::bail!("No CPU or PSS data present in both runs");
+    }
+    console!(
+        "\n{:<10} {:<6} {:>14} {:>14} {:>10}",
+        "Metric",
+        "Stat",
+        "Baseline",
+        "Candidate",
+        "Delta"
+    );
+    for delta in &deltas {
+        let percent = delta
+            .percent
+            .map(|p| format!("{:+.1}%", p))
+            .unwrap_or_else(|| "-".to_string());
+        console!(
+            "{:<10} {:<6} {:>14.2} {:>14.2} {:>10}",
+            delta.metric,
+            delta.statistic.as_str(),
+            delta.baseline,
+            delta.candidate,
+            if delta.regression {
+                percent.red().bold().to_string()
+            } else {
+                percent
+            }
+        );
+    }
+
+    let output_dir = match output {
+        Some(dir) => dir.to_path_buf(),
+        None => OutputLayout::session("compare")?.dir(Category::Compare),
+    };
+    for path in
+        regression::export_comparison(&output_dir, &baseline, &candidate, &deltas, threshold)?
+    {
+        console!("✓ Comparison exported: {}", path.display());
+    }
+
+    let regressions = deltas.iter().filter(|d| d.regression).count();
+    if regressions == 0 {
+        console!(
+            "\n{}",
+            format!("No regressions above {:.0}%", threshold).green()
+        );
+        return Ok(());
+    }
+    console!(
+        "\n{}",
+        format!(
+            "{} regression(s) above {:.0}% in the candidate run",
+            regressions, threshold
+        )
+        .red()
+        .bold()
+    );
+    std::process::exit(1);
 }
@@ -0,0 +1,157 @@
+use crate::logcat::{EventKind, LogcatEvent};
+use crate::recorder::{Collector, FinishContext, TickContext};
+use crate::{chart, tui};
+use anyhow::{Context, Result};
+use colored::*;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+// 监控过程中用户插入的事件标记：标准输入或命名管道中每行一个标签
+pub struct MarkerInput {
+    receiver: Receiver<String>,
+    // 由本工具创建的命名管道，结束时删除
+    created_pipe: Option<PathBuf>,
+}
+
+fn forward_lines(reader: impl BufRead, sender: &Sender<String>) -> bool {
+    for line in reader.lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if sender.send(line.trim().to_string()).is_err() {
+            return false;
+        }
+    }
+    true
+}
+
+impl MarkerInput {
+    pub fn start(stdin: bool, pipe: Option<&Path>) -> Result<Self> {
+        let (sender, receiver) = mpsc::channel::<String>();
+        if stdin {
+            let sender = sender.clone();
+            std::thread::spawn(move || forward_lines(std::io::stdin().lock(), &sender));
+        }
+
+        let mut created_pipe = None;
+        if let Some(path) = pipe {
+            if !path.exists() {
+                let status = Command::new("mkfifo")
+                    .arg(path)
+                    .status()
+                    .context("Failed to run mkfifo")?;
+                if !status.success() {
+                    anyhow::bail!("Failed to create named pipe {}", path.display());
+                }
+                created_pipe = Some(path.to_path_buf());
+            }
+            // 打开管道会阻塞到有写入方；写入方关闭后重新打开，等待下一次写入
+            let path = path.to_path_buf();
+            std::thread::spawn(move || loop {
+                let Ok(file) = std::fs::File::open(&path) else {
+                    break;
+                };
+                if !forward_lines(BufReader::new(file), &sender) {
+                    break;
+                }
+            });
+        }
+
+        Ok(Self {
+            receiver,
+            created_pipe,
+        })
+    }
+
+    // 自上次调用以来输入的标签
+    pub fn poll(&self) -> Vec<String> {
+        self.receiver.try_iter().collect()
+    }
+}
+
+impl Drop for MarkerInput {
+    fn drop(&mut self) {
+        if let Some(path) = &self.created_pipe {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+// 把用户标记记入 events.csv 并画在所有图表上
+pub struct MarkerCollector {
+    input: Option<MarkerInput>,
+    events: Vec<LogcatEvent>,
+}
+
+impl MarkerCollector {
+    pub fn start(stdin: bool, pipe: Option<&Path>) -> Self {
+        let input = match MarkerInput::start(stdin, pipe) {
+            Ok(input) => {
+                if stdin {
+                    console!(
+                        "{}",
+                        "Event markers: type a label (e.g. started video playback) and press Enter"
+                            .green()
+                    );
+                }
+                if let Some(path) = pipe {
+                    console!(
+                        "{}",
+                        format!("Event markers: echo \"<label>\" > {}", path.display()).green()
+                    );
+                }
+                Some(input)
+            }
+            Err(e) => {
+                console!("Failed to start event marker input: {}", e);
+                None
+            }
+        };
+        Self {
+            input,
+            events: Vec::new(),
+        }
+    }
+}
+
+impl Collector for MarkerCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        let mut labels: Vec<String> = self.input.iter().flat_map(MarkerInput::poll).collect();
+        if tui::take_marker_request() {
+            labels.push(String::new());
+        }
+        for label in labels {
+            let label = if label.is_empty() {
+                format!("marker {}", self.events.len() + 1)
+            } else {
+                label
+            };
+            console!(
+                "[{}] {} {}",
+                ctx.tick.timestamp.format("%H:%M:%S").to_string().blue(),
+                "Marker:".magenta().bold(),
+                label
+            );
+            chart::add_user_marker(ctx.tick.timestamp, &label);
+            self.events.push(LogcatEvent {
+                timestamp: ctx.tick.timestamp,
+                kind: EventKind::Marker,
+                pid: Some(ctx.sample.pid.clone()).filter(|pid| !pid.is_empty()),
+                message: label,
+            });
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, _ctx: &FinishContext) {
+        if !self.events.is_empty() {
+            console!("Event markers: {}", self.events.len().to_string().magenta());
+        }
+    }
+
+    fn events(&self, events: &mut Vec<LogcatEvent>) {
+        events.extend(self.events.iter().cloned());
+    }
+}
@@ -0,0 +1,536 @@
+use crate::chart::{self, TimeSeriesData};
+use crate::cpu::CpuScale;
+use crate::layout::Category;
+use crate::process::{self, ProcStat};
+use crate::recorder::{Collector, FinishContext, TickContext};
+use crate::utils;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use colored::*;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const SECTION_SEPARATOR: &str = "---";
+
+// 应用的一个进程在一次采样中的占用，首次采样或重启后 CPU 为 None
+#[derive(Debug, Clone)]
+pub struct AppProcess {
+    pub pid: String,
+    pub ppid: String,
+    pub name: String,
+    // 由应用进程派生的子进程（崩溃处理、渲染进程等）
+    pub child: bool,
+    pub cpu: Option<f64>,
+    pub rss_kb: f64,
+}
+
+impl AppProcess {
+    // 控制台中的简称：主进程为 main，其他应用进程为 ":后缀"，子进程为其进程名
+    pub fn label<'a>(&'a self, package: &str) -> &'a str {
+        match self.name.strip_prefix(package) {
+            Some("") => "main",
+            Some(suffix) if suffix.starts_with(':') => suffix,
+            _ => &self.name,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ProcessGroupSample {
+    pub timestamp: DateTime<Local>,
+    pub processes: Vec<AppProcess>,
+    // 两次采样之间启动又退出的子进程的 CPU，来自父进程 cutime + cstime 的增量
+    pub exited_children_cpu: Option<f64>,
+    // 本次新出现的子进程，"名称 (PID)"
+    pub new_children: Vec<String>,
+}
+
+impl ProcessGroupSample {
+    // 所有进程 CPU 之和，仅统计有基线的进程
+    pub fn total_cpu(&self) -> Option<f64> {
+        self.processes
+            .iter()
+            .filter_map(|p| p.cpu)
+            .chain(self.exited_children_cpu)
+            .reduce(|a, b| a + b)
+    }
+
+    pub fn total_rss_kb(&self) -> f64 {
+        self.processes.iter().map(|p| p.rss_kb).sum()
+    }
+}
+
+// 进程名（即 cmdline 的 argv[0]）为包名本身或 "<包名>:<后缀>" 的都属于该应用
+pub fn belongs_to(name: &str, package: &str) -> bool {
+    name.strip_prefix(package)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(':'))
+}
+
+// ps -A -o PID,NAME 的 PID 到进程名
+pub fn parse_process_names(ps: &str) -> HashMap<String, String> {
+    ps.lines()
+        .skip(1)
+        .filter_map(|line| line.trim().split_once(char::is_whitespace))
+        .map(|(pid, name)| (pid.to_string(), name.trim().to_string()))
+        .collect()
+}
+
+// 从根进程出发沿 ppid 找出所有后代进程
+pub fn descendants(stats: &[ProcStat], roots: &HashSet<String>) -> HashSet<String> {
+    let mut found: HashSet<String> = HashSet::new();
+    let mut frontier: Vec<&str> = roots.iter().map(String::as_str).collect();
+    while let Some(parent) = frontier.pop() {
+        for stat in stats.iter().filter(|s| s.ppid == parent) {
+            if !roots.contains(&stat.pid) && found.insert(stat.pid.clone()) {
+                frontier.push(&stat.pid);
+            }
+        }
+    }
+    found
+}
+
+// 多进程应用（:remote、:service 等）的所有进程及其子进程，CPU 由两次采样之间 utime+stime 的增量计算
+pub struct ProcessGroupSampler {
+    package: String,
+    scale: CpuScale,
+    cores: u32,
+    // false 时只以主进程为根
+    all_processes: bool,
+    children: bool,
+    // PID 到上次的 utime+stime 和 cutime+cstime
+    previous: HashMap<String, (u64, u64)>,
+    previous_children: HashSet<String>,
+    last_sample: Option<DateTime<Local>>,
+}
+
+impl ProcessGroupSampler {
+    pub fn new(
+        package: &str,
+        scale: CpuScale,
+        cores: u32,
+        all_processes: bool,
+        children: bool,
+    ) -> Self {
+        Self {
+            package: package.to_string(),
+            scale,
+            cores,
+            all_processes,
+            children,
+            previous: HashMap::new(),
+            previous_children: HashSet::new(),
+            last_sample: None,
+        }
+    }
+
+    fn is_root(&self, name: &str) -> bool {
+        if self.all_processes {
+            belongs_to(name, &self.package)
+        } else {
+            name == self.package
+        }
+    }
+
+    // 读取进程列表和 stat；跟踪子进程时需要所有进程的 ppid，一次读取全部 stat，否则只读应用进程的
+    fn read_stats(&self) -> Result<(HashMap<String, String>, Vec<ProcStat>)> {
+        if self.children {
+            let output = utils::run_adb_command(&[
+                "shell",
+                &format!(
+                    "ps -A -o PID,NAME; echo {}; cat /proc/[0-9]*/stat 2>/dev/null",
+                    SECTION_SEPARATOR
+                ),
+            ])?;
+            let (ps, stats) = output
+                .split_once(SECTION_SEPARATOR)
+                .ok_or_else(|| anyhow::format_err!("Unexpected process list output"))?;
+            return Ok((
+                parse_process_names(ps),
+                stats.lines().filter_map(process::parse_proc_stat).collect(),
+            ));
+        }
+
+        let ps = utils::run_adb_command(&["shell", "ps -A -o PID,NAME"])?;
+        let names: HashMap<String, String> = parse_process_names(&ps)
+            .into_iter()
+            .filter(|(_, name)| self.is_root(name))
+            .collect();
+        if names.is_empty() {
+            return Ok((names, Vec::new()));
+        }
+        let paths: Vec<String> = names
+            .keys()
+            .map(|pid| format!("/proc/{}/stat", pid))
+            .collect();
+        let output =
+            utils::run_adb_command(&["shell", &format!("cat {} 2>/dev/null", paths.join(" "))])?;
+        Ok((
+            names,
+            output
+                .lines()
+                .filter_map(process::parse_proc_stat)
+                .collect(),
+        ))
+    }
+
+    pub fn sample(&mut self, timestamp: DateTime<Local>) -> Result<ProcessGroupSample> {
+        let (names, stats) = self.read_stats()?;
+        let now = crate::replay::now();
+        let name_of = |stat: &ProcStat| {
+            names
+                .get(&stat.pid)
+                .cloned()
+                .unwrap_or_else(|| stat.comm.clone())
+        };
+
+        let roots: HashSet<String> = stats
+            .iter()
+            .filter(|stat| self.is_root(&name_of(stat)))
+            .map(|stat| stat.pid.clone())
+            .collect();
+        if roots.is_empty() {
+            anyhow::bail!("No processes found for {}", self.package);
+        }
+        let children = if self.children {
+            descendants(&stats, &roots)
+        } else {
+            HashSet::new()
+        };
+
+        let elapsed = self
+            .last_sample
+            .map(|last| (now - last).num_milliseconds() as f64 / 1000.0)
+            .filter(|secs| *secs > 0.0);
+        let normalize = |per_core: f64| self.scale.normalize(per_core as f32, self.cores) as f64;
+
+        let mut current = HashMap::new();
+        let mut processes = Vec::new();
+        let mut reaped_ticks = 0;
+        for stat in stats
+            .iter()
+            .filter(|s| roots.contains(&s.pid) || children.contains(&s.pid))
+        {
+            let previous = self.previous.get(&stat.pid);
+            let cpu = elapsed
+                .zip(previous)
+                .and_then(|(secs, (ticks, _))| process::cpu_percent(*ticks, stat.cpu_ticks, secs))
+                .map(normalize);
+            if let Some((_, child_ticks)) = previous {
+                reaped_ticks += stat.child_cpu_ticks.saturating_sub(*child_ticks);
+            }
+            current.insert(stat.pid.clone(), (stat.cpu_ticks, stat.child_cpu_ticks));
+            processes.push(AppProcess {
+                pid: stat.pid.clone(),
+                ppid: stat.ppid.clone(),
+                name: name_of(stat),
+                child: children.contains(&stat.pid),
+                cpu,
+                rss_kb: stat.rss_kb,
+            });
+        }
+
+        // 已退出的子进程在存活期间已计入，回收后其全部 CPU 时间又并入父进程的 cutime，扣除已计入的部分
+        let exited_children_cpu = if self.children {
+            let counted: u64 = self
+                .previous_children
+                .iter()
+                .filter(|pid| !children.contains(*pid))
+                .filter_map(|pid| self.previous.get(pid))
+                .map(|(ticks, _)| ticks)
+                .sum();
+            elapsed
+                .and_then(|secs| {
+                    process::cpu_percent(0, reaped_ticks.saturating_sub(counted), secs)
+                })
+                .map(normalize)
+        } else {
+            None
+        };
+        let new_children = processes
+            .iter()
+            .filter(|p| p.child && !self.previous_children.contains(&p.pid))
+            .map(|p| format!("{} ({})", p.name, p.pid))
+            .collect();
+
+        // 已退出的进程不再保留基线，PID 复用时重新计算
+        self.previous = current;
+        self.previous_children = children;
+        self.last_sample = Some(now);
+
+        Ok(ProcessGroupSample {
+            timestamp,
+            processes,
+            exited_children_cpu,
+            new_children,
+        })
+    }
+}
+
+// 会话中出现过的进程名，PID 变化（重启）时仍归为同一条曲线
+pub fn process_names(samples: &[ProcessGroupSample]) -> BTreeSet<String> {
+    samples
+        .iter()
+        .flat_map(|s| s.processes.iter().map(|p| p.name.clone()))
+        .collect()
+}
+
+// 导出每个进程和合计的 CPU、RSS 明细 CSV 及图表
+pub fn export_process_group(
+    dir: &Path,
+    package: &str,
+    samples: &[ProcessGroupSample],
+    scale: CpuScale,
+) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let mut paths = Vec::new();
+
+    let csv_path = dir.join(format!("{}_processes.csv", package));
+    let mut file = std::fs::File::create(&csv_path)?;
+    writeln!(file, "Timestamp,PID,PPID,Process,Child,CPU (%),RSS (KB)")?;
+    for sample in samples {
+        let timestamp = sample.timestamp.format("%Y-%m-%d %H:%M:%S");
+        for process in &sample.processes {
+            writeln!(
+                file,
+                "{},{},{},{},{},{},{:.0}",
+                timestamp,
+                process.pid,
+                process.ppid,
+                process.name.replace(',', ";"),
+                process.child,
+                process.cpu.map(|c| format!("{:.1}", c)).unwrap_or_default(),
+                process.rss_kb
+            )?;
+        }
+        if let Some(cpu) = sample.exited_children_cpu {
+            writeln!(file, "{},,,Exited children,true,{:.1},", timestamp, cpu)?;
+        }
+        writeln!(
+            file,
+            "{},,,All processes,,{},{:.0}",
+            timestamp,
+            sample
+                .total_cpu()
+                .map(|c| format!("{:.1}", c))
+                .unwrap_or_default(),
+            sample.total_rss_kb()
+        )?;
+    }
+    paths.push(csv_path);
+
+    let names = process_names(samples);
+    let cpu_y_desc = format!("CPU Usage ({})", scale.unit());
+    for (file_name, title, y_desc, is_cpu) in [
+        (
+            "processes_cpu.png",
+            "CPU Usage by Process",
+            cpu_y_desc.as_str(),
+            true,
+        ),
+        ("processes_memory.png", "RSS by Process", "RSS (KB)", false),
+    ] {
+        let value = |p: &AppProcess| if is_cpu { p.cpu } else { Some(p.rss_kb) };
+        let total = TimeSeriesData::new(
+            "All processes",
+            samples
+                .iter()
+                .filter_map(|s| {
+                    let total = if is_cpu {
+                        s.total_cpu()?
+                    } else {
+                        s.total_rss_kb()
+                    };
+                    Some((s.timestamp, total))
+                })
+                .collect(),
+        );
+        let series: Vec<TimeSeriesData> = std::iter::once(total)
+            .chain(names.iter().map(|name| {
+                TimeSeriesData::new(
+                    name.clone(),
+                    samples
+                        .iter()
+                        .filter_map(|s| {
+                            // 同名进程短暂并存（重启交替）时取合计
+                            let values: Vec<f64> = s
+                                .processes
+                                .iter()
+                                .filter(|p| p.name == *name)
+                                .filter_map(value)
+                                .collect();
+                            (!values.is_empty()).then(|| (s.timestamp, values.iter().sum()))
+                        })
+                        .collect(),
+                )
+            }))
+            .filter(|series| series.points.len() > 1)
+            .collect();
+        if series.is_empty() {
+            continue;
+        }
+        let path = dir.join(chart::stamped_file_name(&format!(
+            "{}_{}",
+            package, file_name
+        )));
+        chart::draw_time_series_chart(&path, &format!("{} - {}", title, package), y_desc, &series)?;
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
+
+// --all-processes、--children：多进程应用的所有进程，以及应用派生的子进程
+pub struct ProcessGroupCollector {
+    sampler: ProcessGroupSampler,
+    scale: CpuScale,
+    samples: Vec<ProcessGroupSample>,
+}
+
+impl ProcessGroupCollector {
+    pub fn new(
+        package: &str,
+        scale: CpuScale,
+        cores: u32,
+        all_processes: bool,
+        children: bool,
+    ) -> Self {
+        Self {
+            sampler: ProcessGroupSampler::new(package, scale, cores, all_processes, children),
+            scale,
+            samples: Vec::new(),
+        }
+    }
+}
+
+impl Collector for ProcessGroupCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        let tick = ctx.tick;
+        let sample = match self.sampler.sample(tick.timestamp) {
+            Ok(sample) => sample,
+            Err(e) => {
+                if !utils::is_being_interrupted() {
+                    console!("Failed to sample app processes: {}", e);
+                }
+                return Ok(());
+            }
+        };
+        let total_cpu = sample.total_cpu();
+        if let Some(cpu) = total_cpu {
+            ctx.table.record(&tick, "All Processes CPU (%)", cpu);
+        }
+        ctx.table
+            .record(&tick, "All Processes RSS (KB)", sample.total_rss_kb());
+        let mut notes = Vec::new();
+        for process in &sample.processes {
+            if let Some(cpu) = process.cpu {
+                ctx.table
+                    .record(&tick, &format!("{} CPU (%)", process.name), cpu);
+            }
+            ctx.table
+                .record(&tick, &format!("{} RSS (KB)", process.name), process.rss_kb);
+            notes.push(format!(
+                "{} {}, {:.0} MB",
+                process.label(ctx.package).cyan(),
+                process
+                    .cpu
+                    .map(|c| format!("{:.1}%", c))
+                    .unwrap_or_else(|| "-".to_string()),
+                process.rss_kb / 1024.0
+            ));
+        }
+        console!(
+            "[{}] {} processes: {}, {:.0} MB ({})",
+            tick.timestamp.format("%H:%M:%S").to_string().blue(),
+            sample.processes.len(),
+            total_cpu
+                .map(|c| format!("{:.1}%", c))
+                .unwrap_or_else(|| "-".to_string()),
+            sample.total_rss_kb() / 1024.0,
+            notes.join(" | ")
+        );
+        if let Some(cpu) = sample.exited_children_cpu.filter(|c| *c > 0.0) {
+            ctx.table.record(&tick, "Exited Children CPU (%)", cpu);
+            if ctx.verbose {
+                console!("Exited child processes used {:.1}% CPU", cpu);
+            }
+        }
+        for child in &sample.new_children {
+            console!("{} {}", "New child process:".yellow(), child);
+        }
+        self.samples.push(sample);
+        Ok(())
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        if self.samples.is_empty() {
+            return;
+        }
+        // 会话中出现过的应用进程和子进程
+        let names = |child: bool| {
+            self.samples
+                .iter()
+                .flat_map(|s| &s.processes)
+                .filter(|p| p.child == child)
+                .map(|p| p.name.as_str())
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        console!("App processes seen: {}", names(false).cyan());
+        let children = names(true);
+        if !children.is_empty() {
+            console!("Child processes seen: {}", children.yellow());
+        }
+        match export_process_group(
+            &ctx.layout.dir(Category::Process),
+            ctx.package,
+            &self.samples,
+            self.scale,
+        ) {
+            Ok(paths) => {
+                for path in paths {
+                    console!("✓ Per-process data exported: {}", path.display());
+                }
+            }
+            Err(e) => console!("Failed to export per-process data: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn belongs_to_matches_package_and_suffixes() {
+        assert!(belongs_to("com.example.app", "com.example.app"));
+        assert!(belongs_to("com.example.app:remote", "com.example.app"));
+        assert!(!belongs_to("com.example.appstore", "com.example.app"));
+    }
+
+    #[test]
+    fn descendants_follow_ppid_chain() {
+        let stat = |pid: &str, ppid: &str| ProcStat {
+            pid: pid.to_string(),
+            ppid: ppid.to_string(),
+            comm: String::new(),
+            cpu_ticks: 0,
+            child_cpu_ticks: 0,
+            rss_kb: 0.0,
+        };
+        let stats = [
+            stat("4012", "612"),
+            stat("4500", "4012"),
+            stat("4501", "4500"),
+            stat("4600", "612"),
+        ];
+        let roots = HashSet::from(["4012".to_string()]);
+        let found = descendants(&stats, &roots);
+        assert_eq!(
+            found,
+            HashSet::from(["4500".to_string(), "4501".to_string()])
+        );
+    }
+}
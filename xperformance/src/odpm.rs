@@ -0,0 +1,414 @@
+use crate::chart::{self, TimeSeriesData};
+use crate::layout::Category;
+use crate::recorder::{Collector, FinishContext, TickContext};
+use crate::stats::Summary;
+use crate::utils;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use colored::*;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// ODPM（On Device Power Monitor，Pixel 6 及以后）的能量计数器，可能分布在多个 iio 设备上
+const ENERGY_COMMAND: &str = "cat /sys/bus/iio/devices/iio:device*/energy_value 2>/dev/null; true";
+
+// 一个电源轨的累计能量读数
+#[derive(Debug, Clone, PartialEq)]
+pub struct RailReading {
+    pub rail: String,
+    // 计数器更新时的开机时间（毫秒）
+    pub timestamp_ms: u64,
+    // 累计能量（微瓦秒）
+    pub energy_uws: u64,
+}
+
+// 解析 energy_value：
+//   t=2462306
+//   CH0(T=2462306)[S10M_VDD_TPU], 10785537383
+// 通道行没有 T= 时使用上方 t= 的时间
+pub fn parse_energy_values(output: &str) -> Vec<RailReading> {
+    let mut readings = Vec::new();
+    let mut device_ms = None;
+    for line in output.lines() {
+        let line = line.trim();
+        if let Some(ms) = line.strip_prefix("t=") {
+            device_ms = ms.trim().parse().ok();
+            continue;
+        }
+        if !line.starts_with("CH") {
+            continue;
+        }
+        let (Some(open), Some(close)) = (line.find('['), line.rfind(']')) else {
+            continue;
+        };
+        if close < open {
+            continue;
+        }
+        let timestamp_ms = line[..open]
+            .split_once("(T=")
+            .and_then(|(_, rest)| rest.trim_end_matches(')').parse().ok())
+            .or(device_ms);
+        let energy = line[close + 1..]
+            .trim_start_matches(',')
+            .trim()
+            .parse()
+            .ok();
+        if let (Some(timestamp_ms), Some(energy_uws)) = (timestamp_ms, energy) {
+            readings.push(RailReading {
+                rail: line[open + 1..close].to_string(),
+                timestamp_ms,
+                energy_uws,
+            });
+        }
+    }
+    readings
+}
+
+pub fn read_energy_values() -> Result<Vec<RailReading>> {
+    let output = utils::run_adb_command(&["shell", ENERGY_COMMAND])?;
+    let readings = parse_energy_values(&output);
+    if readings.is_empty() {
+        anyhow::bail!(
+            "No ODPM energy counters found under /sys/bus/iio/devices (Pixel 6 or later required)"
+        );
+    }
+    Ok(readings)
+}
+
+// 一次采样中各电源轨的平均功率（mW），顺序同 RailTracker::rails，计数器未更新的轨为 None
+#[derive(Debug, Clone)]
+pub struct RailSample {
+    pub timestamp: DateTime<Local>,
+    pub power_mw: Vec<Option<f64>>,
+}
+
+// 整个会话中一个电源轨的能耗
+#[derive(Debug, Clone)]
+pub struct RailEnergy {
+    pub rail: String,
+    pub energy_mj: f64,
+    pub average_mw: f64,
+    pub peak_mw: f64,
+}
+
+// 由相邻两次读数的能量差和计数器时间差计算功率：uWs / ms = mW
+fn power_mw(from: &RailReading, to: &RailReading) -> Option<f64> {
+    let elapsed_ms = to.timestamp_ms.checked_sub(from.timestamp_ms)?;
+    let energy_uws = to.energy_uws.checked_sub(from.energy_uws)?;
+    (elapsed_ms > 0).then(|| energy_uws as f64 / elapsed_ms as f64)
+}
+
+// 保存首次和上次读数，每次采样计算各电源轨自上次以来的功率
+pub struct RailTracker {
+    rails: Vec<String>,
+    first: HashMap<String, RailReading>,
+    last: HashMap<String, RailReading>,
+    samples: Vec<RailSample>,
+}
+
+impl RailTracker {
+    // 读取一次作为基准，电源轨按设备给出的顺序固定下来
+    pub fn detect() -> Result<Self> {
+        let readings = read_energy_values()?;
+        let rails = readings.iter().map(|r| r.rail.clone()).collect();
+        let first: HashMap<String, RailReading> =
+            readings.into_iter().map(|r| (r.rail.clone(), r)).collect();
+        Ok(Self {
+            rails,
+            last: first.clone(),
+            first,
+            samples: Vec::new(),
+        })
+    }
+
+    pub fn rails(&self) -> &[String] {
+        &self.rails
+    }
+
+    pub fn samples(&self) -> &[RailSample] {
+        &self.samples
+    }
+
+    pub fn sample(&mut self, timestamp: DateTime<Local>) -> Result<RailSample> {
+        let readings = read_energy_values()?;
+        let mut power = vec![None; self.rails.len()];
+        for reading in readings {
+            let Some(i) = self.rails.iter().position(|rail| *rail == reading.rail) else {
+                continue;
+            };
+            power[i] = self
+                .last
+                .get(&reading.rail)
+                .and_then(|last| power_mw(last, &reading));
+            // 计数器未前进时保留旧读数，下次按更长的间隔计算
+            if power[i].is_some() {
+                self.last.insert(reading.rail.clone(), reading);
+            }
+        }
+        let sample = RailSample {
+            timestamp,
+            power_mw: power,
+        };
+        self.samples.push(sample.clone());
+        Ok(sample)
+    }
+
+    // 预热结束后各电源轨功率的统计摘要，用于会话摘要
+    pub fn summaries(&self, warmup_end: Option<DateTime<Local>>) -> Vec<(String, Summary)> {
+        self.rails
+            .iter()
+            .enumerate()
+            .filter_map(|(i, rail)| {
+                let values: Vec<f64> = self
+                    .samples
+                    .iter()
+                    .filter(|s| warmup_end.is_none_or(|end| s.timestamp >= end))
+                    .filter_map(|s| s.power_mw[i])
+                    .collect();
+                Summary::from_values(&values).map(|s| (format!("Rail {} (mW)", rail), s))
+            })
+            .collect()
+    }
+
+    // 按首末读数计算的整段能耗，按能耗降序
+    pub fn energy(&self) -> Vec<RailEnergy> {
+        let mut energy: Vec<RailEnergy> = self
+            .rails
+            .iter()
+            .enumerate()
+            .filter_map(|(i, rail)| {
+                let (first, last) = (self.first.get(rail)?, self.last.get(rail)?);
+                let elapsed_ms = last.timestamp_ms.saturating_sub(first.timestamp_ms);
+                let energy_uws = last.energy_uws.saturating_sub(first.energy_uws);
+                Some(RailEnergy {
+                    rail: rail.clone(),
+                    energy_mj: energy_uws as f64 / 1000.0,
+                    average_mw: energy_uws as f64 / elapsed_ms.max(1) as f64,
+                    peak_mw: self
+                        .samples
+                        .iter()
+                        .filter_map(|s| s.power_mw[i])
+                        .fold(0.0, f64::max),
+                })
+            })
+            .collect();
+        energy.sort_by(|a, b| {
+            b.energy_mj
+                .partial_cmp(&a.energy_mj)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        energy
+    }
+}
+
+// 导出各电源轨功率的逐次采样 CSV、整段能耗 CSV 和功率堆叠面积图（面积即能耗）
+pub fn export_rail_data(dir: &Path, package: &str, tracker: &RailTracker) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let mut paths = Vec::new();
+
+    let csv_path = dir.join(format!("{}_power_rails.csv", package));
+    let mut file = std::fs::File::create(&csv_path)?;
+    let header: Vec<String> = tracker
+        .rails
+        .iter()
+        .map(|rail| format!("{} (mW)", rail))
+        .collect();
+    writeln!(file, "Timestamp,{}", header.join(","))?;
+    for sample in &tracker.samples {
+        let values: Vec<String> = sample
+            .power_mw
+            .iter()
+            .map(|v| v.map_or(String::new(), |mw| format!("{:.1}", mw)))
+            .collect();
+        writeln!(
+            file,
+            "{},{}",
+            sample.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            values.join(",")
+        )?;
+    }
+    paths.push(csv_path);
+
+    let energy = tracker.energy();
+    let energy_path = dir.join(format!("{}_rail_energy.csv", package));
+    let mut file = std::fs::File::create(&energy_path)?;
+    writeln!(file, "Rail,Energy (mJ),Average Power (mW),Peak Power (mW)")?;
+    for rail in &energy {
+        writeln!(
+            file,
+            "{},{:.1},{:.1},{:.1}",
+            rail.rail, rail.energy_mj, rail.average_mw, rail.peak_mw
+        )?;
+    }
+    paths.push(energy_path);
+
+    if tracker.samples.len() > 1 {
+        // 自下而上按能耗从大到小，没有耗电的轨不画；计数器未更新的点按 0 计
+        let series: Vec<TimeSeriesData> = energy
+            .iter()
+            .filter(|rail| rail.energy_mj > 0.0)
+            .filter_map(|rail| {
+                let i = tracker.rails.iter().position(|r| *r == rail.rail)?;
+                Some(TimeSeriesData::new(
+                    rail.rail.as_str(),
+                    tracker
+                        .samples
+                        .iter()
+                        .map(|s| (s.timestamp, s.power_mw[i].unwrap_or(0.0)))
+                        .collect(),
+                ))
+            })
+            .collect();
+        if !series.is_empty() {
+            let path = dir.join(chart::stamped_file_name(&format!(
+                "{}_power_rails.png",
+                package
+            )));
+            chart::draw_stacked_area_chart(
+                &path,
+                &format!("Power Rails (ODPM) - {}", package),
+                "Power (mW)",
+                &series,
+            )?;
+            paths.push(path);
+        }
+    }
+
+    Ok(paths)
+}
+
+// --power-rails：每个 Tick 读取 ODPM 计数器，结束时输出各电源轨的能耗并导出
+pub struct RailCollector {
+    tracker: RailTracker,
+    warmup_end: Option<DateTime<Local>>,
+}
+
+impl RailCollector {
+    // 设备不支持 ODPM 时只提示一次，不加入采集器
+    pub fn start(warmup_end: Option<DateTime<Local>>) -> Option<Self> {
+        match RailTracker::detect() {
+            Ok(tracker) => {
+                console!("Power rails (ODPM): {}", tracker.rails().join(", "));
+                Some(Self {
+                    tracker,
+                    warmup_end,
+                })
+            }
+            Err(e) => {
+                console!("{}", format!("Power rails unavailable: {}", e).yellow());
+                None
+            }
+        }
+    }
+}
+
+impl Collector for RailCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        let tick = ctx.tick;
+        let sample = match self.tracker.sample(tick.timestamp) {
+            Ok(sample) => sample,
+            Err(e) => {
+                if !utils::is_being_interrupted() {
+                    console!("Failed to sample power rails: {}", e);
+                }
+                return Ok(());
+            }
+        };
+        let mut total = 0.0;
+        for (rail, power) in self.tracker.rails().iter().zip(&sample.power_mw) {
+            if let Some(mw) = power {
+                ctx.table.record(&tick, &format!("Rail {} (mW)", rail), *mw);
+                total += mw;
+            }
+        }
+        console!(
+            "[{}] Power rails: {} mW total",
+            tick.timestamp.format("%H:%M:%S"),
+            format!("{:.0}", total).yellow()
+        );
+        Ok(())
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        if self.tracker.samples().is_empty() {
+            return;
+        }
+        let energy = self.tracker.energy();
+        console!(
+            "Power rails: {:.0} mJ total",
+            energy.iter().map(|rail| rail.energy_mj).sum::<f64>()
+        );
+        for rail in &energy {
+            console!(
+                "  {}: {:.0} mJ, average {} mW, peak {:.0} mW",
+                rail.rail,
+                rail.energy_mj,
+                format!("{:.1}", rail.average_mw).yellow(),
+                rail.peak_mw
+            );
+        }
+        match export_rail_data(
+            &ctx.layout.dir(Category::Battery),
+            ctx.package,
+            &self.tracker,
+        ) {
+            Ok(paths) => {
+                for path in paths {
+                    console!("✓ Power rail data exported: {}", path.display());
+                }
+            }
+            Err(e) => console!("Failed to export power rail data: {}", e),
+        }
+    }
+
+    fn summaries(&self, summaries: &mut Vec<(String, Summary)>) {
+        summaries.extend(self.tracker.summaries(self.warmup_end));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_energy_values_uses_device_time_fallback() {
+        let output = "t=2462306\n\
+                      CH0(T=2462310)[S10M_VDD_TPU], 10785537383\n\
+                      CH1[VSYS_PWR_DISPLAY], 4200\n\
+                      garbage line\n";
+        let readings = parse_energy_values(output);
+        assert_eq!(
+            readings,
+            vec![
+                RailReading {
+                    rail: "S10M_VDD_TPU".into(),
+                    timestamp_ms: 2462310,
+                    energy_uws: 10785537383,
+                },
+                RailReading {
+                    rail: "VSYS_PWR_DISPLAY".into(),
+                    timestamp_ms: 2462306,
+                    energy_uws: 4200,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn power_from_counter_deltas() {
+        let reading = |timestamp_ms, energy_uws| RailReading {
+            rail: "VDD_CPUCL0".into(),
+            timestamp_ms,
+            energy_uws,
+        };
+        // 500000 uWs / 1000 ms = 500 mW
+        assert_eq!(
+            power_mw(&reading(1000, 100_000), &reading(2000, 600_000)),
+            Some(500.0)
+        );
+        // 计数器未前进或回绕时没有功率
+        assert_eq!(power_mw(&reading(1000, 1), &reading(1000, 2)), None);
+        assert_eq!(power_mw(&reading(1000, 5), &reading(2000, 1)), None);
+    }
+}
@@ -0,0 +1,404 @@
+use crate::chart::{self, TimeSeriesData};
+use crate::layout::Category;
+use crate::recorder::{Collector, FinishContext, TickContext};
+use crate::regenerate::CSV_TIMESTAMP_FORMAT;
+use crate::utils;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use colored::*;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// ProcessList 中的 oom_adj 分档：PERCEPTIBLE_LOW_APP_ADJ 以下为前台和用户可感知，
+// SERVICE_B_ADJ 及以上（旧服务、缓存进程）在内存不足时最先被 LMK 杀死
+const PERCEPTIBLE_MAX_ADJ: i32 = 250;
+const SERVICE_B_ADJ: i32 = 800;
+
+// 被 LMK 杀死的风险
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LmkRisk {
+    Low,
+    Medium,
+    High,
+}
+
+impl LmkRisk {
+    pub fn from_score_adj(score_adj: i32) -> Self {
+        if score_adj <= PERCEPTIBLE_MAX_ADJ {
+            LmkRisk::Low
+        } else if score_adj < SERVICE_B_ADJ {
+            LmkRisk::Medium
+        } else {
+            LmkRisk::High
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LmkRisk::Low => "low",
+            LmkRisk::Medium => "medium",
+            LmkRisk::High => "high",
+        }
+    }
+}
+
+// 一次采样的 oom_score_adj 和 AMS 中的进程状态
+#[derive(Debug, Clone)]
+pub struct OomSample {
+    pub timestamp: DateTime<Local>,
+    pub score_adj: i32,
+    // LRU 列表中的 adj 分档，如 fg、vis、svcb、cch+75
+    pub adj_label: Option<String>,
+    // LRU 列表中的进程状态缩写，如 TOP、FGS、SVC、CEM
+    pub proc_state: Option<String>,
+}
+
+impl OomSample {
+    pub fn risk(&self) -> LmkRisk {
+        LmkRisk::from_score_adj(self.score_adj)
+    }
+
+    // 如 "900 (cch+75 CEM)"
+    pub fn describe(&self) -> String {
+        let state: Vec<&str> = [self.adj_label.as_deref(), self.proc_state.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect();
+        if state.is_empty() {
+            self.score_adj.to_string()
+        } else {
+            format!("{} ({})", self.score_adj, state.join(" "))
+        }
+    }
+}
+
+// 第一行为 /proc/<pid>/oom_score_adj，其后为 `dumpsys activity lru` 中该进程的一行：
+//   #57: cch+75 CEM  ---- 1234:com.foo/u0a123
+// 旧版本在 adj 前还有 "Proc"、"PERS" 等列时按 "<pid>:" 定位
+pub fn parse_oom_state(output: &str, pid: &str, timestamp: DateTime<Local>) -> Option<OomSample> {
+    let mut lines = output.lines().map(str::trim).filter(|l| !l.is_empty());
+    let score_adj = lines.next()?.parse().ok()?;
+    let process = format!("{}:", pid);
+    let lru: Option<Vec<&str>> = lines
+        .map(|line| line.split_whitespace().collect::<Vec<&str>>())
+        .find(|tokens| tokens.iter().any(|t| t.starts_with(&process)));
+    let (adj_label, proc_state) = lru
+        .and_then(|tokens| {
+            let start = tokens.iter().position(|t| t.starts_with('#'))? + 1;
+            Some((
+                tokens.get(start).map(|t| t.to_string()),
+                tokens.get(start + 1).map(|t| t.to_string()),
+            ))
+        })
+        .unwrap_or_default();
+    Some(OomSample {
+        timestamp,
+        score_adj,
+        adj_label,
+        proc_state,
+    })
+}
+
+// 一次 adb 调用读取 oom_score_adj 和 LRU 列表中的进程状态
+pub fn sample_oom_state(pid: &str, timestamp: DateTime<Local>) -> Result<OomSample> {
+    let command = format!(
+        "cat /proc/{pid}/oom_score_adj; dumpsys activity lru | grep ' {pid}:'; true",
+        pid = pid
+    );
+    let output = utils::run_adb_command(&["shell", &command])?;
+    parse_oom_state(&output, pid, timestamp)
+        .ok_or_else(|| anyhow::format_err!("Failed to read oom_score_adj for pid {}", pid))
+}
+
+// 内存峰值与当时的 oom_score_adj
+#[derive(Debug, Clone)]
+pub struct MemoryPeak {
+    pub timestamp: DateTime<Local>,
+    pub pss_kb: f64,
+    pub oom: OomSample,
+}
+
+#[derive(Debug, Default)]
+pub struct OomTracker {
+    samples: Vec<OomSample>,
+}
+
+impl OomTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn samples(&self) -> &[OomSample] {
+        &self.samples
+    }
+
+    pub fn last(&self) -> Option<&OomSample> {
+        self.samples.last()
+    }
+
+    // oom_score_adj 或进程状态变化时返回变化前的采样
+    pub fn record(&mut self, sample: OomSample) -> Option<OomSample> {
+        let previous = self
+            .samples
+            .last()
+            .filter(|last| {
+                last.score_adj != sample.score_adj || last.proc_state != sample.proc_state
+            })
+            .cloned();
+        self.samples.push(sample);
+        previous
+    }
+
+    // timestamp 时的状态：不晚于该时刻的最后一次采样
+    pub fn at(&self, timestamp: DateTime<Local>) -> Option<&OomSample> {
+        let index = self.samples.partition_point(|s| s.timestamp <= timestamp);
+        index.checked_sub(1).map(|i| &self.samples[i])
+    }
+
+    // 处于 risk 及以上风险的总时长（秒），每次采样的状态持续到下一次采样
+    pub fn secs_at_risk(&self, risk: LmkRisk) -> f64 {
+        self.samples
+            .windows(2)
+            .filter(|pair| pair[0].risk() >= risk)
+            .map(|pair| (pair[1].timestamp - pair[0].timestamp).num_milliseconds() as f64 / 1000.0)
+            .sum()
+    }
+
+    // 整段的 PSS 峰值，以及高风险期间的 PSS 峰值：缓存状态下占用内存越大越先被杀
+    pub fn memory_peaks(
+        &self,
+        pss: &[(DateTime<Local>, f64)],
+    ) -> (Option<MemoryPeak>, Option<MemoryPeak>) {
+        let peaks: Vec<MemoryPeak> = pss
+            .iter()
+            .filter_map(|(t, kb)| {
+                Some(MemoryPeak {
+                    timestamp: *t,
+                    pss_kb: *kb,
+                    oom: self.at(*t)?.clone(),
+                })
+            })
+            .collect();
+        let max = |peaks: Vec<&MemoryPeak>| {
+            peaks
+                .into_iter()
+                .max_by(|a, b| {
+                    a.pss_kb
+                        .partial_cmp(&b.pss_kb)
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                })
+                .cloned()
+        };
+        (
+            max(peaks.iter().collect()),
+            max(peaks
+                .iter()
+                .filter(|p| p.oom.risk() == LmkRisk::High)
+                .collect()),
+        )
+    }
+
+    // 阶梯序列：每次变化时先画旧值再画新值
+    fn step_points(&self) -> Vec<(DateTime<Local>, f64)> {
+        let mut points: Vec<(DateTime<Local>, f64)> = Vec::new();
+        for sample in &self.samples {
+            let value = sample.score_adj as f64;
+            if let Some(&(_, last)) = points.last() {
+                if last != value {
+                    points.push((sample.timestamp, last));
+                }
+            }
+            points.push((sample.timestamp, value));
+        }
+        points
+    }
+}
+
+// 导出 oom_score_adj 和进程状态 CSV，以及 oom_score_adj 阶梯图（有 PSS 时画在右轴对照）
+pub fn export_oom_data(
+    dir: &Path,
+    package: &str,
+    tracker: &OomTracker,
+    pss: &[(DateTime<Local>, f64)],
+) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let mut paths = Vec::new();
+
+    let csv_path = dir.join(format!("{}_oom_adj.csv", package));
+    let mut file = std::fs::File::create(&csv_path)?;
+    writeln!(file, "Timestamp,OOM Score Adj,Adj,Proc State,LMK Risk")?;
+    for sample in &tracker.samples {
+        writeln!(
+            file,
+            "{},{},{},{},{}",
+            sample.timestamp.format(CSV_TIMESTAMP_FORMAT),
+            sample.score_adj,
+            sample.adj_label.as_deref().unwrap_or_default(),
+            sample.proc_state.as_deref().unwrap_or_default(),
+            sample.risk().as_str()
+        )?;
+    }
+    paths.push(csv_path);
+
+    if tracker.samples.len() > 1 {
+        let path = dir.join(chart::stamped_file_name(&format!(
+            "{}_oom_adj.png",
+            package
+        )));
+        let title = format!("OOM Score Adj - {}", package);
+        let adj = [TimeSeriesData::new("oom_score_adj", tracker.step_points())];
+        if pss.is_empty() {
+            chart::draw_time_series_chart(
+                &path,
+                &title,
+                "oom_score_adj (higher is killed first)",
+                &adj,
+            )?;
+        } else {
+            chart::draw_dual_axis_chart(
+                &path,
+                &title,
+                "oom_score_adj (higher is killed first)",
+                &adj,
+                "Total PSS (MB)",
+                &[TimeSeriesData::new(
+                    "Total PSS",
+                    pss.iter().map(|(t, kb)| (*t, kb / 1024.0)).collect(),
+                )],
+            )?;
+        }
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
+
+// --oom-adj：跟踪 oom_score_adj 和进程状态，进程被杀时可据此判断原因
+#[derive(Default)]
+pub struct OomCollector {
+    tracker: OomTracker,
+    // 每个 Tick 的 Total PSS，结束时找出高风险状态下的内存峰值
+    pss: Vec<(DateTime<Local>, f64)>,
+}
+
+impl Collector for OomCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        let tick = ctx.tick;
+        // 被杀前的最后状态：缓存或旧服务状态下通常是被 LMK 回收
+        if ctx.sample.restarted {
+            if let Some(last) = self.tracker.last() {
+                console!(
+                    "Last state before restart: oom_score_adj {}, LMK risk {}",
+                    last.describe(),
+                    last.risk().as_str()
+                );
+            }
+        }
+        if let Some(memory) = &ctx.sample.memory {
+            self.pss.push((tick.timestamp, memory.total_pss as f64));
+        }
+        let mut sample = match sample_oom_state(&ctx.sample.pid, tick.timestamp) {
+            Ok(sample) => sample,
+            Err(e) => {
+                if !utils::is_being_interrupted() {
+                    console!("Failed to sample oom_score_adj: {}", e);
+                }
+                return Ok(());
+            }
+        };
+        // OOM Score Adj 列只由进程状态记录，本 Tick 的 oom_score_adj 统一使用该值
+        if let Some(status) = ctx.process_status {
+            sample.score_adj = status.oom_score_adj;
+        }
+        let current = sample.describe();
+        let risk = sample.risk();
+        if let Some(previous) = self.tracker.record(sample) {
+            console!(
+                "[{}] {} {} -> {}, LMK risk {}",
+                tick.timestamp.format("%H:%M:%S").to_string().blue(),
+                "OOM adj:".yellow(),
+                previous.describe(),
+                current,
+                match risk {
+                    LmkRisk::High => risk.as_str().red(),
+                    _ => risk.as_str().normal(),
+                }
+            );
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        let samples = self.tracker.samples();
+        if samples.is_empty() {
+            return;
+        }
+        let adj = samples.iter().map(|s| s.score_adj);
+        console!(
+            "OOM adj: min {}, max {}, {:.0}s at high LMK risk (oom_score_adj >= 800)",
+            adj.clone().min().unwrap_or_default(),
+            adj.max().unwrap_or_default(),
+            self.tracker.secs_at_risk(LmkRisk::High)
+        );
+        let (peak, killable_peak) = self.tracker.memory_peaks(&self.pss);
+        if let Some(peak) = peak {
+            console!(
+                "  Peak PSS {} KB at {} with oom_score_adj {}",
+                peak.pss_kb,
+                peak.timestamp.format("%H:%M:%S"),
+                peak.oom.describe()
+            );
+        }
+        if let Some(peak) = killable_peak {
+            console!(
+                "  {} {} KB at {} with oom_score_adj {}",
+                "Peak PSS at high LMK risk:".red(),
+                peak.pss_kb,
+                peak.timestamp.format("%H:%M:%S"),
+                peak.oom.describe()
+            );
+        }
+        match export_oom_data(
+            &ctx.layout.dir(Category::Memory),
+            ctx.package,
+            &self.tracker,
+            &self.pss,
+        ) {
+            Ok(paths) => {
+                for path in paths {
+                    console!("✓ OOM adj data exported: {}", path.display());
+                }
+            }
+            Err(e) => console!("Failed to export OOM adj data: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_oom_state_with_lru_line() {
+        let output = "905\n  #57: cch+75 CEM  ---- 12345:com.example.app/u0a123\n";
+        let sample = parse_oom_state(output, "12345", Local::now()).unwrap();
+        assert_eq!(sample.score_adj, 905);
+        assert_eq!(sample.adj_label.as_deref(), Some("cch+75"));
+        assert_eq!(sample.proc_state.as_deref(), Some("CEM"));
+    }
+
+    #[test]
+    fn parse_oom_state_without_lru_line() {
+        // grep 匹配到了 pid 相近的其他进程
+        let output = "0\n  #3: fg     TOP  LCM  123456:com.other/u0a99\n";
+        let sample = parse_oom_state(output, "12345", Local::now()).unwrap();
+        assert_eq!(sample.score_adj, 0);
+        assert!(sample.adj_label.is_none() && sample.proc_state.is_none());
+        assert!(parse_oom_state(
+            "cat: /proc/12345/oom_score_adj: No such file",
+            "12345",
+            Local::now()
+        )
+        .is_none());
+    }
+}
@@ -0,0 +1,69 @@
+use crate::utils;
+use anyhow::Result;
+
+// 通过 dumpsys package 获取的应用信息
+#[derive(Debug, Clone, Default)]
+pub struct PackageInfo {
+    pub version_name: String,
+    pub version_code: String,
+    pub target_sdk: String,
+    pub uid: String,
+    pub debuggable: bool,
+    // 首次安装和最近一次安装（更新）的时间，如 2026-10-15 09:12:33
+    pub first_install_time: String,
+    pub last_update_time: String,
+}
+
+impl PackageInfo {
+    pub fn build_type(&self) -> &'static str {
+        if self.debuggable {
+            "debug"
+        } else {
+            "release"
+        }
+    }
+}
+
+pub fn query_package_info(serial: Option<&str>, package: &str) -> Result<PackageInfo> {
+    let output = utils::run_adb_command_on(serial, &["shell", "dumpsys", "package", package])?;
+    Ok(parse_package_info(&output))
+}
+
+fn parse_package_info(output: &str) -> PackageInfo {
+    let mut info = PackageInfo::default();
+
+    for token in output.split_whitespace() {
+        let Some((key, value)) = token.split_once('=') else {
+            continue;
+        };
+        // 多个安装版本时只取第一个（当前生效的版本）
+        match key {
+            "versionName" if info.version_name.is_empty() => info.version_name = value.to_string(),
+            "versionCode" if info.version_code.is_empty() => info.version_code = value.to_string(),
+            "targetSdk" if info.target_sdk.is_empty() => info.target_sdk = value.to_string(),
+            // 新版本系统为 appId，旧版本为 userId
+            "userId" | "appId" if info.uid.is_empty() => info.uid = value.to_string(),
+            _ => {}
+        }
+    }
+
+    // 时间值含空格，按行匹配；Android 14 起 firstInstallTime 在每个用户的小节中，取第一个
+    let line_value = |key: &str| {
+        output
+            .lines()
+            .find_map(|line| line.trim().strip_prefix(key)?.strip_prefix('='))
+            .map(|value| value.trim().to_string())
+            .unwrap_or_default()
+    };
+    info.first_install_time = line_value("firstInstallTime");
+    info.last_update_time = line_value("lastUpdateTime");
+
+    // flags=[ DEBUGGABLE HAS_CODE ... ] 或 pkgFlags=[ ... ]
+    info.debuggable = output
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with("flags=[") || line.starts_with("pkgFlags=["))
+        .any(|line| line.split_whitespace().any(|flag| flag == "DEBUGGABLE"));
+
+    info
+}
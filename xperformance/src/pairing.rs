@@ -0,0 +1,87 @@
+use anyhow::{Context, Result};
+use std::process::Command;
+
+// 无线调试在 mDNS 上发布的服务类型：配对码配对和配对后的连接端口
+pub const PAIRING_SERVICE: &str = "_adb-tls-pairing._tcp";
+pub const CONNECT_SERVICE: &str = "_adb-tls-connect._tcp";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MdnsService {
+    pub name: String,
+    pub service_type: String,
+    // ip:port
+    pub address: String,
+}
+
+impl MdnsService {
+    pub fn is_pairing(&self) -> bool {
+        self.service_type.starts_with(PAIRING_SERVICE)
+    }
+
+    pub fn is_connect(&self) -> bool {
+        self.service_type.starts_with(CONNECT_SERVICE)
+    }
+
+    pub fn ip(&self) -> &str {
+        self.address
+            .rsplit_once(':')
+            .map_or(self.address.as_str(), |(ip, _)| ip)
+    }
+}
+
+// 配对和 mDNS 都是 adb server 的命令，不指定设备；失败时 adb 也可能返回 0，输出合并后由调用方判断
+fn run_adb_host(args: &[&str]) -> Result<String> {
+    let output = Command::new("adb")
+        .args(args)
+        .output()
+        .context("Failed to execute adb command")?;
+    let text = String::from_utf8_lossy(&output.stdout).to_string()
+        + &String::from_utf8_lossy(&output.stderr);
+    if !output.status.success() {
+        anyhow::bail!("adb {} failed: {}", args.join(" "), text.trim());
+    }
+    Ok(text)
+}
+
+// 解析 adb mdns services 的输出，每行为 "<名称>\t<服务类型>\t<ip:port>"
+pub fn parse_mdns_services(output: &str) -> Vec<MdnsService> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                [name, service_type, address] if service_type.starts_with('_') => {
+                    Some(MdnsService {
+                        name: name.to_string(),
+                        service_type: service_type.trim_end_matches('.').to_string(),
+                        address: address.to_string(),
+                    })
+                }
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+// 列出 adb server 通过 mDNS 发现的无线调试设备
+pub fn discover() -> Result<Vec<MdnsService>> {
+    let output = run_adb_host(&["mdns", "services"])?;
+    Ok(parse_mdns_services(&output))
+}
+
+// adb pair <ip:port> <配对码>，成功时输出 "Successfully paired to ..."
+pub fn pair(address: &str, code: &str) -> Result<()> {
+    let output = run_adb_host(&["pair", address, code])?;
+    if !output.contains("Successfully paired") {
+        anyhow::bail!("adb pair {} failed: {}", address, output.trim());
+    }
+    Ok(())
+}
+
+// 配对后设备以新端口发布连接服务，按 IP 找到对应的 ip:port
+pub fn find_connect_address(services: &[MdnsService], ip: &str) -> Option<String> {
+    services
+        .iter()
+        .find(|service| service.is_connect() && service.ip() == ip)
+        .map(|service| service.address.clone())
+}
@@ -0,0 +1,369 @@
+use crate::chart::{self, TimeSeriesData};
+use crate::cpu::ThreadCpuInfo;
+use crate::layout::Category;
+use crate::recorder::{Collector, FinishContext, TickContext};
+use crate::regenerate;
+use crate::system::{self, CpuTimes};
+use crate::utils::{self, CpuChartOverlay};
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use colored::*;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const SECTION_SEPARATOR: &str = "---";
+// 每次采样记录运行核心的热点线程数
+pub const HOT_THREADS: usize = 5;
+
+// 采样时热点线程所在的核心（/proc/<pid>/task/<tid>/stat 的 processor 字段，即最近一次运行的核心）
+#[derive(Debug, Clone)]
+pub struct ThreadPlacement {
+    pub tid: String,
+    pub name: String,
+    pub core: u32,
+}
+
+// 一次采样中每个核心的占用（0-100%），离线核心不出现；首次采样没有基线，usage 为空
+#[derive(Debug, Clone)]
+pub struct PerCoreSample {
+    pub timestamp: DateTime<Local>,
+    pub usage: BTreeMap<u32, f64>,
+    pub placements: Vec<ThreadPlacement>,
+}
+
+// /proc/stat 中 "cpu0 ..."、"cpu1 ..." 等每核心的行
+pub fn parse_core_times(stat: &str) -> BTreeMap<u32, CpuTimes> {
+    stat.lines()
+        .filter_map(|line| {
+            let core = line
+                .split_whitespace()
+                .next()?
+                .strip_prefix("cpu")?
+                .parse()
+                .ok()?;
+            Some((core, system::parse_cpu_line(line)?))
+        })
+        .collect()
+}
+
+// stat 行 `tid (comm) S ...`，processor 为第 39 列，comm 中可能含空格，以最后一个 ')' 为界
+pub fn parse_placement(line: &str) -> Option<ThreadPlacement> {
+    let open = line.find('(')?;
+    let close = line.rfind(')')?;
+    if close < open {
+        return None;
+    }
+    let tid = line[..open].trim();
+    let core = line[close + 1..].split_whitespace().nth(36)?.parse().ok()?;
+    (!tid.is_empty()).then(|| ThreadPlacement {
+        tid: tid.to_string(),
+        name: line[open + 1..close].to_string(),
+        core,
+    })
+}
+
+#[derive(Default)]
+pub struct PerCoreSampler {
+    previous: BTreeMap<u32, CpuTimes>,
+}
+
+impl PerCoreSampler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 一次 adb 调用读取 /proc/stat 和热点线程的 stat
+    pub fn sample(
+        &mut self,
+        pid: &str,
+        hot_threads: &[ThreadCpuInfo],
+        timestamp: DateTime<Local>,
+    ) -> Result<PerCoreSample> {
+        let thread_stats: Vec<String> = hot_threads
+            .iter()
+            .take(HOT_THREADS)
+            .map(|thread| format!("/proc/{}/task/{}/stat", pid, thread.tid))
+            .collect();
+        let mut command = format!("cat /proc/stat; echo {}", SECTION_SEPARATOR);
+        if !thread_stats.is_empty() {
+            command.push_str(&format!("; cat {} 2>/dev/null", thread_stats.join(" ")));
+        }
+        let output = utils::run_adb_command(&["shell", &command])?;
+        let (stat, threads) = output
+            .split_once(SECTION_SEPARATOR)
+            .ok_or_else(|| anyhow::format_err!("Unexpected per-core output: {}", output.trim()))?;
+
+        let times = parse_core_times(stat);
+        if times.is_empty() {
+            anyhow::bail!("No per-core lines in /proc/stat");
+        }
+        let usage = times
+            .iter()
+            .filter_map(|(core, now)| {
+                let previous = self.previous.get(core)?;
+                Some((*core, now.usage_since(previous)?))
+            })
+            .collect();
+        self.previous.extend(times);
+
+        Ok(PerCoreSample {
+            timestamp,
+            usage,
+            placements: threads.lines().filter_map(parse_placement).collect(),
+        })
+    }
+}
+
+// 每个热点线程在各核心上被采到的次数，按线程总次数降序
+pub fn placement_counts(samples: &[PerCoreSample]) -> Vec<(String, String, BTreeMap<u32, u32>)> {
+    let mut counts: BTreeMap<(String, String), BTreeMap<u32, u32>> = BTreeMap::new();
+    for placement in samples.iter().flat_map(|s| &s.placements) {
+        *counts
+            .entry((placement.tid.clone(), placement.name.clone()))
+            .or_default()
+            .entry(placement.core)
+            .or_insert(0) += 1;
+    }
+    let mut counts: Vec<(String, String, BTreeMap<u32, u32>)> = counts
+        .into_iter()
+        .map(|((tid, name), cores)| (tid, name, cores))
+        .collect();
+    counts.sort_by_key(|(_, _, cores)| std::cmp::Reverse(cores.values().sum::<u32>()));
+    counts
+}
+
+// 每个核心的占用曲线，少于两个点的核心不画
+pub fn core_series(samples: &[PerCoreSample]) -> Vec<TimeSeriesData> {
+    let cores: BTreeSet<u32> = samples
+        .iter()
+        .flat_map(|s| s.usage.keys().copied())
+        .collect();
+    cores
+        .iter()
+        .map(|core| {
+            TimeSeriesData::new(
+                format!("CPU{}", core),
+                samples
+                    .iter()
+                    .filter_map(|s| s.usage.get(core).map(|v| (s.timestamp, *v)))
+                    .collect(),
+            )
+        })
+        .filter(|series| series.points.len() > 1)
+        .collect()
+}
+
+// 读取 export_per_core_data 写出的每核心 CSV，用于重新生成 CPU 图表
+pub fn read_per_core_csv(path: &Path) -> Result<Vec<TimeSeriesData>> {
+    let table = regenerate::read_csv(path)?;
+    let timestamps: Vec<Option<DateTime<Local>>> = table
+        .rows
+        .iter()
+        .map(|row| row.first().and_then(|t| regenerate::parse_timestamp(t)))
+        .collect();
+    Ok(table
+        .headers
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(index, header)| {
+            TimeSeriesData::new(
+                header.trim_end_matches(" (%)"),
+                table
+                    .rows
+                    .iter()
+                    .zip(&timestamps)
+                    .filter_map(|(row, timestamp)| {
+                        Some(((*timestamp)?, row.get(index)?.parse().ok()?))
+                    })
+                    .collect(),
+            )
+        })
+        .filter(|series| series.points.len() > 1)
+        .collect())
+}
+
+// 导出每核心占用 CSV 和折线图，以及热点线程的核心分布 CSV
+pub fn export_per_core_data(
+    dir: &Path,
+    package: &str,
+    samples: &[PerCoreSample],
+) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let mut paths = Vec::new();
+
+    let cores: BTreeSet<u32> = samples
+        .iter()
+        .flat_map(|s| s.usage.keys().copied())
+        .collect();
+    let csv_path = dir.join(format!("{}_per_core.csv", package));
+    let mut file = std::fs::File::create(&csv_path)?;
+    let header: Vec<String> = cores.iter().map(|c| format!("CPU{} (%)", c)).collect();
+    writeln!(file, "Timestamp,{}", header.join(","))?;
+    for sample in samples.iter().filter(|s| !s.usage.is_empty()) {
+        let values: Vec<String> = cores
+            .iter()
+            .map(|core| {
+                sample
+                    .usage
+                    .get(core)
+                    .map(|v| format!("{:.1}", v))
+                    .unwrap_or_default()
+            })
+            .collect();
+        writeln!(
+            file,
+            "{},{}",
+            sample.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            values.join(",")
+        )?;
+    }
+    paths.push(csv_path);
+
+    let series = core_series(samples);
+    if !series.is_empty() {
+        let path = dir.join(chart::stamped_file_name(&format!(
+            "{}_per_core.png",
+            package
+        )));
+        chart::draw_time_series_chart(
+            &path,
+            &format!("Per-core CPU Usage - {}", package),
+            "Core busy (%)",
+            &series,
+        )?;
+        paths.push(path);
+    }
+
+    let counts = placement_counts(samples);
+    if !counts.is_empty() {
+        let path = dir.join(format!("{}_thread_cores.csv", package));
+        let mut file = std::fs::File::create(&path)?;
+        writeln!(file, "TID,Thread,Core,Samples,Share (%)")?;
+        for (tid, name, cores) in &counts {
+            let total = cores.values().sum::<u32>().max(1);
+            for (core, count) in cores {
+                writeln!(
+                    file,
+                    "{},{},{},{},{:.1}",
+                    tid,
+                    name.replace(',', " "),
+                    core,
+                    count,
+                    *count as f64 * 100.0 / total as f64
+                )?;
+            }
+        }
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
+
+// --per-core：每核心占用和热点线程所在核心
+#[derive(Default)]
+pub struct PerCoreCollector {
+    sampler: PerCoreSampler,
+    samples: Vec<PerCoreSample>,
+}
+
+impl Collector for PerCoreCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        let tick = ctx.tick;
+        let sample = match self
+            .sampler
+            .sample(&ctx.sample.pid, &ctx.sample.threads, tick.timestamp)
+        {
+            Ok(sample) => sample,
+            Err(e) => {
+                if !utils::is_being_interrupted() {
+                    console!("Failed to sample per-core CPU: {}", e);
+                }
+                return Ok(());
+            }
+        };
+        for (core, usage) in &sample.usage {
+            ctx.table.record(&tick, &format!("CPU{} (%)", core), *usage);
+        }
+        if !sample.usage.is_empty() {
+            let cores: Vec<String> = sample
+                .usage
+                .iter()
+                .map(|(core, usage)| format!("{}:{:.0}%", core, usage))
+                .collect();
+            console!(
+                "[{}] Cores: {}",
+                tick.timestamp.format("%H:%M:%S").to_string().blue(),
+                cores.join(" ")
+            );
+        }
+        self.samples.push(sample);
+        Ok(())
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        if self.samples.is_empty() {
+            return;
+        }
+        console!("Hot thread placement (share of samples per core):");
+        for (tid, name, cores) in placement_counts(&self.samples).iter().take(HOT_THREADS) {
+            let total = cores.values().sum::<u32>().max(1);
+            let mut shares: Vec<(&u32, &u32)> = cores.iter().collect();
+            shares.sort_by_key(|(_, count)| std::cmp::Reverse(**count));
+            let shares: Vec<String> = shares
+                .iter()
+                .map(|(core, count)| {
+                    format!("cpu{} {:.0}%", core, **count as f64 * 100.0 / total as f64)
+                })
+                .collect();
+            console!(
+                "  {} (TID: {}): {}",
+                name.cyan(),
+                tid.yellow(),
+                shares.join(", ")
+            );
+        }
+        match export_per_core_data(&ctx.layout.dir(Category::Cpu), ctx.package, &self.samples) {
+            Ok(paths) => {
+                for path in paths {
+                    console!("✓ Per-core CPU data exported: {}", path.display());
+                }
+            }
+            Err(e) => console!("Failed to export per-core CPU data: {}", e),
+        }
+    }
+
+    fn cpu_overlay(&self, overlay: &mut CpuChartOverlay) {
+        overlay.per_core = core_series(&self.samples);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TASK_STAT: &str = include_str!("../testdata/task_stat.txt");
+
+    #[test]
+    fn parse_placement_reads_processor_field() {
+        let placements: Vec<ThreadPlacement> =
+            TASK_STAT.lines().filter_map(parse_placement).collect();
+        assert_eq!(placements.len(), 5);
+        assert_eq!(placements[0].tid, "12345");
+        assert_eq!(placements[0].core, 5);
+        assert_eq!(placements[2].name, "RenderThread");
+        assert_eq!(placements[2].core, 7);
+        // 线程名中含空格和括号
+        assert_eq!(placements[4].name, "AudioTrack (x)");
+        assert_eq!(placements[4].core, 2);
+        assert!(parse_placement("4321 (binder:4321_2) S 612 612 0").is_none());
+    }
+
+    #[test]
+    fn parse_core_times_skips_aggregate_line() {
+        let stat = "cpu  100 0 50 800 0 0 0 0 0 0\ncpu0 60 0 30 400 0 0 0 0 0 0\ncpu3 40 0 20 400 0 0 0 0 0 0\nintr 12345\n";
+        let times = parse_core_times(stat);
+        assert_eq!(times.keys().copied().collect::<Vec<_>>(), vec![0, 3]);
+    }
+}
@@ -0,0 +1,568 @@
+use crate::chart::{self, TimeSeriesData};
+use crate::layout::Category;
+use crate::recorder::{Collector, FinishContext, TickContext};
+use crate::replay;
+use crate::utils;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use colored::*;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// 电池采样点
+#[derive(Debug, Clone)]
+pub struct BatterySample {
+    pub timestamp: DateTime<Local>,
+    pub level: u32,
+    pub voltage_mv: u32,
+    pub temperature_c: f32,
+    pub current_ma: Option<f32>,
+    pub charging: bool,
+}
+
+// 读取 dumpsys battery 和 current_now
+pub fn sample_battery(serial: Option<&str>) -> Result<BatterySample> {
+    let timestamp = Local::now();
+    let output = utils::run_adb_command_on(serial, &["shell", "dumpsys", "battery"])?;
+
+    let mut sample = BatterySample {
+        timestamp,
+        level: 0,
+        voltage_mv: 0,
+        temperature_c: 0.0,
+        current_ma: None,
+        charging: false,
+    };
+
+    for line in output.lines() {
+        let Some((key, value)) = line.trim().split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "level" => sample.level = value.parse().unwrap_or(0),
+            "voltage" => sample.voltage_mv = value.parse().unwrap_or(0),
+            // 温度单位为 0.1 摄氏度
+            "temperature" => sample.temperature_c = value.parse::<f32>().unwrap_or(0.0) / 10.0,
+            "AC powered" | "USB powered" | "Wireless powered" if value == "true" => {
+                sample.charging = true
+            }
+            _ => {}
+        }
+    }
+
+    sample.current_ma = read_current_now(serial);
+    Ok(sample)
+}
+
+// current_now 多数设备单位为微安，少数厂商直接报告毫安
+fn read_current_now(serial: Option<&str>) -> Option<f32> {
+    let output = utils::run_adb_command_on(
+        serial,
+        &[
+            "shell",
+            "cat",
+            "/sys/class/power_supply/battery/current_now",
+        ],
+    )
+    .ok()?;
+    let raw: f32 = output.trim().parse().ok()?;
+    Some(if raw.abs() > 10_000.0 {
+        raw / 1000.0
+    } else {
+        raw
+    })
+}
+
+// 电池采样明细 CSV
+pub fn write_samples_csv(path: &Path, samples: &[BatterySample]) -> Result<()> {
+    let mut file = std::fs::File::create(path)?;
+    writeln!(
+        file,
+        "Timestamp,Level (%),Voltage (mV),Temperature (C),Current (mA)"
+    )?;
+    for s in samples {
+        writeln!(
+            file,
+            "{},{},{},{:.1},{}",
+            s.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            s.level,
+            s.voltage_mv,
+            s.temperature_c,
+            s.current_ma
+                .map(|c| format!("{:.1}", c))
+                .unwrap_or_default()
+        )?;
+    }
+    file.flush()?;
+    Ok(())
+}
+
+// 导出监控期间的电池 CSV 和电量/温度、电压、电流图表
+pub fn export_battery_data(
+    dir: &Path,
+    package: &str,
+    samples: &[BatterySample],
+) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let mut paths = Vec::new();
+
+    let csv_path = dir.join(format!("{}_battery.csv", package));
+    write_samples_csv(&csv_path, samples)?;
+    paths.push(csv_path);
+
+    if samples.len() < 2 {
+        return Ok(paths);
+    }
+    let series = |f: fn(&BatterySample) -> Option<f64>| -> Vec<(DateTime<Local>, f64)> {
+        samples
+            .iter()
+            .filter_map(|s| f(s).map(|v| (s.timestamp, v)))
+            .collect()
+    };
+    let currents = series(|s| s.current_ma.map(|c| c as f64));
+    for (file_name, title, y_desc, data) in [
+        (
+            "battery_level.png",
+            "Battery Level / Temperature",
+            "Level (%) / Temperature (°C)",
+            vec![
+                TimeSeriesData::new("Level (%)", series(|s| Some(s.level as f64))),
+                TimeSeriesData::new("Temperature (°C)", series(|s| Some(s.temperature_c as f64))),
+            ],
+        ),
+        (
+            "battery_voltage.png",
+            "Battery Voltage",
+            "Voltage (mV)",
+            vec![TimeSeriesData::new(
+                "Voltage (mV)",
+                series(|s| Some(s.voltage_mv as f64)),
+            )],
+        ),
+        (
+            "battery_current.png",
+            "Battery Current",
+            "Current (mA)",
+            if currents.len() > 1 {
+                vec![TimeSeriesData::new("Current (mA)", currents.clone())]
+            } else {
+                Vec::new()
+            },
+        ),
+    ] {
+        if data.is_empty() {
+            continue;
+        }
+        let path = dir.join(chart::stamped_file_name(&format!(
+            "{}_{}",
+            package, file_name
+        )));
+        chart::draw_time_series_chart(&path, &format!("{} - {}", title, package), y_desc, &data)?;
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
+
+pub fn reset_batterystats(serial: Option<&str>) -> Result<()> {
+    utils::run_adb_command_on(serial, &["shell", "dumpsys", "batterystats", "--reset"])?;
+    Ok(())
+}
+
+// 模拟拔出 USB，让设备在连接 adb 时也处于放电状态
+pub fn set_unplugged(serial: Option<&str>, unplugged: bool) -> Result<()> {
+    let action = if unplugged { "unplug" } else { "reset" };
+    utils::run_adb_command_on(serial, &["shell", "dumpsys", "battery", action])?;
+    Ok(())
+}
+
+// batterystats 中按 UID 归属的耗电
+#[derive(Debug, Clone)]
+pub struct UidPowerUse {
+    pub uid: String,
+    pub label: String,
+    pub packages: Vec<String>,
+    pub mah: f64,
+}
+
+// 解析 `dumpsys batterystats --checkin` 的 pwi（power use item）和 uid 行
+pub fn parse_power_use(checkin: &str) -> Vec<UidPowerUse> {
+    let mut packages: HashMap<String, Vec<String>> = HashMap::new();
+    let mut items: Vec<UidPowerUse> = Vec::new();
+
+    for line in checkin.lines() {
+        let fields: Vec<&str> = line.trim().split(',').collect();
+        if fields.len() < 6 {
+            continue;
+        }
+        match (fields[2], fields[3]) {
+            // 9,0,i,uid,<uid>,<package>
+            ("i", "uid") => packages
+                .entry(fields[4].to_string())
+                .or_default()
+                .push(fields[5].to_string()),
+            // 9,<uid>,l,pwi,<label>,<mAh>,...
+            ("l", "pwi") => {
+                if let Ok(mah) = fields[5].parse::<f64>() {
+                    items.push(UidPowerUse {
+                        uid: fields[1].to_string(),
+                        label: fields[4].to_string(),
+                        packages: Vec::new(),
+                        mah,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for item in &mut items {
+        if let Some(pkgs) = packages.get(&item.uid) {
+            item.packages = pkgs.clone();
+        }
+    }
+    items.sort_by(|a, b| {
+        b.mah
+            .partial_cmp(&a.mah)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    items
+}
+
+pub fn query_power_use(serial: Option<&str>) -> Result<Vec<UidPowerUse>> {
+    let output =
+        utils::run_adb_command_on(serial, &["shell", "dumpsys", "batterystats", "--checkin"])?;
+    Ok(parse_power_use(&output))
+}
+
+// 会话期间 batterystats 归属该应用的能耗（自会话开始时重置起）
+#[derive(Debug, Clone, Default)]
+pub struct EnergyAttribution {
+    pub power_mah: Option<f64>,
+    pub cpu_user_ms: Option<u64>,
+    pub cpu_system_ms: Option<u64>,
+    pub wakeups: u32,
+    pub mobile_radio_ms: Option<u64>,
+    pub wifi_ms: Option<u64>,
+}
+
+impl EnergyAttribution {
+    pub fn cpu_ms(&self) -> Option<u64> {
+        match (self.cpu_user_ms, self.cpu_system_ms) {
+            (None, None) => None,
+            (user, system) => Some(user.unwrap_or(0) + system.unwrap_or(0)),
+        }
+    }
+
+    pub fn rows(&self) -> Vec<(&'static str, String)> {
+        let secs = |ms: Option<u64>| {
+            ms.map(|ms| format!("{:.1}", ms as f64 / 1000.0))
+                .unwrap_or_default()
+        };
+        vec![
+            (
+                "Estimated Power (mAh)",
+                self.power_mah
+                    .map(|mah| format!("{:.2}", mah))
+                    .unwrap_or_default(),
+            ),
+            ("CPU User (s)", secs(self.cpu_user_ms)),
+            ("CPU System (s)", secs(self.cpu_system_ms)),
+            ("Wakeup Alarms", self.wakeups.to_string()),
+            ("Mobile Radio Active (s)", secs(self.mobile_radio_ms)),
+            ("Wifi Running (s)", secs(self.wifi_ms)),
+        ]
+    }
+}
+
+// batterystats 的 UID 显示形式，如 10123 -> u0a123，1000 -> u0s1000
+pub fn format_uid(uid: &str) -> Option<String> {
+    let uid: u32 = uid.trim().parse().ok()?;
+    let (user, app_id) = (uid / 100_000, uid % 100_000);
+    Some(if app_id >= 10_000 {
+        format!("u{}a{}", user, app_id - 10_000)
+    } else {
+        format!("u{}s{}", user, app_id)
+    })
+}
+
+// "1h 2m 3s 100ms" 转为毫秒，遇到第一个不是时长的词为止
+fn parse_stats_duration(text: &str) -> Option<u64> {
+    let mut total = None;
+    for token in text.split_whitespace() {
+        let unit_start = token
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(token.len());
+        let Ok(value) = token[..unit_start].parse::<u64>() else {
+            break;
+        };
+        let ms = match &token[unit_start..] {
+            "d" => value * 86_400_000,
+            "h" => value * 3_600_000,
+            "m" => value * 60_000,
+            "s" => value * 1000,
+            "ms" => value,
+            _ => break,
+        };
+        total = Some(total.unwrap_or(0) + ms);
+    }
+    total
+}
+
+// 解析 `dumpsys batterystats <package>`：Estimated power use 段中的 "Uid u0a123: 1.23"，
+// 以及该 UID 段内的 CPU 时间、唤醒闹钟次数和移动网络/Wi-Fi 时长
+pub fn parse_energy_attribution(output: &str, uid: &str) -> EnergyAttribution {
+    let mut energy = EnergyAttribution::default();
+    let Some(label) = format_uid(uid) else {
+        return energy;
+    };
+    let header = format!("{}:", label);
+    // UID 段的缩进，遇到同级或更浅的非空行结束
+    let mut section_indent: Option<usize> = None;
+
+    for line in output.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        let indent = line.len() - line.trim_start().len();
+
+        // 旧版本为 "Uid u0a123: 1.23 ( cpu=... )"，Android 12+ 为 "UID u0a123: 1.23 fg: ..."
+        if let Some(rest) = trimmed
+            .get(..4)
+            .filter(|prefix| prefix.eq_ignore_ascii_case("uid "))
+            .and_then(|_| trimmed[4..].strip_prefix(&header))
+        {
+            if let Some(mah) = rest
+                .split_whitespace()
+                .next()
+                .and_then(|v| v.parse::<f64>().ok())
+            {
+                *energy.power_mah.get_or_insert(0.0) += mah;
+            }
+            continue;
+        }
+
+        match section_indent {
+            None if trimmed == header => {
+                section_indent = Some(indent);
+                continue;
+            }
+            Some(section) if indent <= section => section_indent = None,
+            _ => {}
+        }
+        if section_indent.is_none() {
+            continue;
+        }
+
+        if let Some(rest) = trimmed.strip_prefix("Total cpu time:") {
+            // "u=1s 200ms s=500ms"，新版本可能再跟 "p=..."
+            if let Some(user) = rest.trim().strip_prefix("u=") {
+                let (user, system) = user.split_once(" s=").unwrap_or((user, ""));
+                energy.cpu_user_ms = parse_stats_duration(user);
+                energy.cpu_system_ms = parse_stats_duration(system);
+            }
+        } else if let Some(rest) = trimmed.strip_prefix("Mobile radio active:") {
+            energy.mobile_radio_ms = parse_stats_duration(rest);
+        } else if let Some((key, rest)) = trimmed.split_once(':') {
+            if key.eq_ignore_ascii_case("wifi running") {
+                energy.wifi_ms = parse_stats_duration(rest);
+            }
+        }
+
+        // 旧版本在 Apk 段内为 "3 wakeup alarms"，新版本为每个闹钟一行 "Wakeup alarm *walarm*:...: 0ms realtime (3 times)"
+        if let Some(count) = trimmed
+            .strip_suffix(" wakeup alarms")
+            .or_else(|| trimmed.strip_suffix(" wakeup alarm"))
+            .and_then(|count| count.parse::<u32>().ok())
+        {
+            energy.wakeups += count;
+        } else if trimmed.starts_with("Wakeup alarm ") {
+            if let Some(count) = trimmed
+                .rsplit_once('(')
+                .and_then(|(_, times)| times.split_whitespace().next())
+                .and_then(|count| count.parse::<u32>().ok())
+            {
+                energy.wakeups += count;
+            }
+        }
+    }
+    energy
+}
+
+pub fn query_energy_attribution(
+    serial: Option<&str>,
+    package: &str,
+    uid: &str,
+) -> Result<(EnergyAttribution, String)> {
+    let output = utils::run_adb_command_on(serial, &["shell", "dumpsys", "batterystats", package])?;
+    Ok((parse_energy_attribution(&output, uid), output))
+}
+
+// 导出能耗归因 CSV，并保存 batterystats 原始输出以便查看完整明细
+pub fn export_energy_attribution(
+    dir: &Path,
+    package: &str,
+    energy: &EnergyAttribution,
+    raw: &str,
+) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+
+    let csv_path = dir.join(format!("{}_batterystats.csv", package));
+    let mut file = std::fs::File::create(&csv_path)?;
+    writeln!(file, "Metric,Value")?;
+    for (metric, value) in energy.rows() {
+        writeln!(file, "{},{}", metric, value)?;
+    }
+
+    let raw_path = dir.join(format!("{}_batterystats.txt", package));
+    std::fs::write(&raw_path, raw)?;
+
+    Ok(vec![csv_path, raw_path])
+}
+
+// 每个 Tick 的电量、电压、电池温度和电流
+#[derive(Default)]
+pub struct BatteryCollector {
+    samples: Vec<BatterySample>,
+}
+
+impl Collector for BatteryCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        let tick = ctx.tick;
+        let mut sample = match sample_battery(None) {
+            Ok(sample) => sample,
+            Err(e) => {
+                if !utils::is_being_interrupted() {
+                    console!("Failed to sample battery: {}", e);
+                }
+                return Ok(());
+            }
+        };
+        sample.timestamp = tick.timestamp;
+        ctx.table
+            .record(&tick, "Battery Level (%)", sample.level as f64);
+        ctx.table
+            .record(&tick, "Battery Voltage (mV)", sample.voltage_mv as f64);
+        ctx.table.record(
+            &tick,
+            "Battery Temperature (C)",
+            sample.temperature_c as f64,
+        );
+        if let Some(current) = sample.current_ma {
+            ctx.table
+                .record(&tick, "Battery Current (mA)", current as f64);
+        }
+        console!(
+            "[{}] Battery: {}%, {} mV, {:.1}°C, current: {}",
+            tick.timestamp.format("%H:%M:%S"),
+            sample.level.to_string().green(),
+            sample.voltage_mv,
+            sample.temperature_c,
+            sample
+                .current_ma
+                .map_or("-".to_string(), |c| format!("{:.0} mA", c))
+                .yellow()
+        );
+        self.samples.push(sample);
+        Ok(())
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        let (Some(first), Some(last)) = (self.samples.first(), self.samples.last()) else {
+            return;
+        };
+        console!(
+            "Battery: {}% -> {}%, temperature {:.1}°C -> {:.1}°C",
+            first.level,
+            last.level,
+            first.temperature_c,
+            last.temperature_c
+        );
+        match export_battery_data(
+            &ctx.layout.dir(Category::Battery),
+            ctx.package,
+            &self.samples,
+        ) {
+            Ok(paths) => {
+                for path in paths {
+                    console!("✓ Battery data exported: {}", path.display());
+                }
+            }
+            Err(e) => console!("Failed to export battery data: {}", e),
+        }
+    }
+}
+
+// 会话开始时重置 batterystats，结束时报告归属该应用的能耗
+pub struct BatteryStatsCollector {
+    uid: String,
+}
+
+impl BatteryStatsCollector {
+    pub fn start(uid: &str) -> Self {
+        // 重置后结束时读取的能耗即为本次会话期间的累计值；回放时没有设备
+        if !replay::is_replaying() {
+            match reset_batterystats(None) {
+                Ok(()) => console!("Batterystats reset"),
+                Err(e) => console!("Failed to reset batterystats: {}", e),
+            }
+            // 充电时 batterystats 不累计耗电
+            if sample_battery(None).is_ok_and(|sample| sample.charging) {
+                console!(
+                    "{}",
+                    "Device is charging; batterystats does not accumulate power use while plugged in (try `adb shell dumpsys battery unplug`)"
+                        .yellow()
+                );
+            }
+        }
+        Self {
+            uid: uid.to_string(),
+        }
+    }
+}
+
+impl Collector for BatteryStatsCollector {
+    fn sample(&mut self, _ctx: &mut TickContext) -> Result<()> {
+        Ok(())
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        let (energy, raw) = match query_energy_attribution(None, ctx.package, &self.uid) {
+            Ok(result) => result,
+            Err(e) => {
+                console!("Failed to read batterystats: {}", e);
+                return;
+            }
+        };
+        let seconds = |ms: Option<u64>| {
+            ms.map_or("-".to_string(), |ms| format!("{:.1} s", ms as f64 / 1000.0))
+        };
+        console!(
+            "Batterystats: estimated power {}, CPU time {}, wakeup alarms {}, mobile radio {}, Wi-Fi {}",
+            energy
+                .power_mah
+                .map_or("-".to_string(), |mah| format!("{:.2} mAh", mah))
+                .red(),
+            seconds(energy.cpu_ms()),
+            energy.wakeups,
+            seconds(energy.mobile_radio_ms),
+            seconds(energy.wifi_ms)
+        );
+        match export_energy_attribution(
+            &ctx.layout.dir(Category::Battery),
+            ctx.package,
+            &energy,
+            &raw,
+        ) {
+            Ok(paths) => {
+                for path in paths {
+                    console!("✓ Batterystats exported: {}", path.display());
+                }
+            }
+            Err(e) => console!("Failed to export batterystats: {}", e),
+        }
+    }
+}
@@ -0,0 +1,286 @@
+use crate::chart::{self, TimeSeriesData};
+use crate::layout::Category;
+use crate::recorder::{Collector, FinishContext, TickContext};
+use crate::threadstate::{SchedParams, ThreadState};
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use colored::*;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// 图表中最多画出的线程数，按变化次数排序
+const MAX_CHART_THREADS: usize = 12;
+
+// 一个线程的调度参数变化
+#[derive(Debug, Clone)]
+pub struct PriorityChange {
+    pub timestamp: DateTime<Local>,
+    pub tid: String,
+    pub name: String,
+    pub from: SchedParams,
+    pub to: SchedParams,
+}
+
+impl PriorityChange {
+    // 从实时降为普通策略、实时优先级降低、改为 batch/idle 或 nice 变大都算降级，常见于应用退到后台
+    pub fn is_demotion(&self) -> bool {
+        match (self.from.is_realtime(), self.to.is_realtime()) {
+            (true, false) => true,
+            (false, true) => false,
+            (true, true) => self.to.rt_priority < self.from.rt_priority,
+            (false, false) => {
+                (policy_rank(&self.to), self.to.priority)
+                    > (policy_rank(&self.from), self.from.priority)
+            }
+        }
+    }
+}
+
+// 普通策略之间的先后：normal 优先于 batch，batch 优先于 idle
+fn policy_rank(sched: &SchedParams) -> u8 {
+    match sched.policy_name() {
+        "batch" => 1,
+        "idle" => 2,
+        _ => 0,
+    }
+}
+
+// 线程首次出现时的调度参数
+#[derive(Debug, Clone)]
+struct FirstSeen {
+    timestamp: DateTime<Local>,
+    name: String,
+    sched: SchedParams,
+}
+
+// 跟踪每个线程的 nice、优先级和调度策略，只保留初始值和变化，不逐次保存
+#[derive(Default)]
+pub struct PriorityTracker {
+    current: HashMap<String, SchedParams>,
+    first_seen: HashMap<String, FirstSeen>,
+    changes: Vec<PriorityChange>,
+    last_sample: Option<DateTime<Local>>,
+}
+
+impl PriorityTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn changes(&self) -> &[PriorityChange] {
+        &self.changes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.first_seen.is_empty()
+    }
+
+    // 返回本次采样中调度参数变化的线程；新出现的线程只记录初始值
+    pub fn update(
+        &mut self,
+        timestamp: DateTime<Local>,
+        states: &[ThreadState],
+    ) -> Vec<PriorityChange> {
+        let mut changes = Vec::new();
+        let mut current = HashMap::new();
+        for state in states {
+            let Some(sched) = state.sched else {
+                continue;
+            };
+            match self.current.get(&state.tid) {
+                Some(previous) if *previous != sched => changes.push(PriorityChange {
+                    timestamp,
+                    tid: state.tid.clone(),
+                    name: state.name.clone(),
+                    from: *previous,
+                    to: sched,
+                }),
+                Some(_) => {}
+                None => {
+                    self.first_seen
+                        .entry(state.tid.clone())
+                        .or_insert_with(|| FirstSeen {
+                            timestamp,
+                            name: state.name.clone(),
+                            sched,
+                        });
+                }
+            }
+            current.insert(state.tid.clone(), sched);
+        }
+        // 已退出的线程不再比较
+        self.current = current;
+        self.last_sample = Some(timestamp);
+        self.changes.extend(changes.iter().cloned());
+        changes
+    }
+
+    // 发生过变化的线程的阶梯序列：初始值、每次变化前后的值、最后一次采样的值
+    fn step_series(&self) -> Vec<TimeSeriesData> {
+        let mut by_thread: HashMap<&str, Vec<&PriorityChange>> = HashMap::new();
+        for change in &self.changes {
+            by_thread.entry(&change.tid).or_default().push(change);
+        }
+        let mut threads: Vec<(&str, Vec<&PriorityChange>)> = by_thread.into_iter().collect();
+        threads.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then(a.0.cmp(b.0)));
+
+        threads
+            .into_iter()
+            .take(MAX_CHART_THREADS)
+            .filter_map(|(tid, changes)| {
+                let first = self.first_seen.get(tid)?;
+                let mut points = vec![(first.timestamp, first.sched.priority as f64)];
+                for change in &changes {
+                    points.push((change.timestamp, change.from.priority as f64));
+                    points.push((change.timestamp, change.to.priority as f64));
+                }
+                let last = changes.last()?;
+                if let Some(end) = self.last_sample.filter(|end| *end > last.timestamp) {
+                    points.push((end, last.to.priority as f64));
+                }
+                Some(TimeSeriesData::new(
+                    format!("{} ({})", last.name, tid),
+                    points,
+                ))
+            })
+            .collect()
+    }
+}
+
+// 导出调度参数 CSV（每个线程的初始值和每次变化）和优先级变化图
+pub fn export_priority_data(
+    dir: &Path,
+    package: &str,
+    tracker: &PriorityTracker,
+) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let mut paths = Vec::new();
+
+    let csv_path = dir.join(format!("{}_thread_priorities.csv", package));
+    let mut file = std::fs::File::create(&csv_path)?;
+    writeln!(
+        file,
+        "Timestamp,TID,Name,Change,Policy,Nice,RT Priority,Priority"
+    )?;
+    let mut initial: Vec<(&String, &FirstSeen)> = tracker.first_seen.iter().collect();
+    initial.sort_by(|a, b| a.1.timestamp.cmp(&b.1.timestamp).then(a.0.cmp(b.0)));
+    let rows = initial
+        .into_iter()
+        .map(|(tid, first)| (first.timestamp, tid, &first.name, "initial", first.sched))
+        .chain(tracker.changes.iter().map(|change| {
+            (
+                change.timestamp,
+                &change.tid,
+                &change.name,
+                if change.is_demotion() {
+                    "demoted"
+                } else {
+                    "promoted"
+                },
+                change.to,
+            )
+        }));
+    for (timestamp, tid, name, change, sched) in rows {
+        writeln!(
+            file,
+            "{},{},{},{},{},{},{},{}",
+            timestamp.format("%Y-%m-%d %H:%M:%S"),
+            tid,
+            name.replace(',', " "),
+            change,
+            sched.policy_name(),
+            sched.nice,
+            sched.rt_priority,
+            sched.priority
+        )?;
+    }
+    paths.push(csv_path);
+
+    let series = tracker.step_series();
+    if !series.is_empty() {
+        let path = dir.join(chart::stamped_file_name(&format!(
+            "{}_thread_priorities.png",
+            package
+        )));
+        // 内核优先级：普通线程为 20 + nice，实时线程为负数，越小越优先
+        chart::draw_time_series_chart(
+            &path,
+            &format!("Thread Priority Changes - {}", package),
+            "Priority (lower runs first)",
+            &series,
+        )?;
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
+
+// --thread 时跟踪每个线程的 nice、优先级和调度策略变化，线程状态由 ThreadStateCollector 读取
+#[derive(Default)]
+pub struct PriorityCollector {
+    tracker: PriorityTracker,
+}
+
+impl Collector for PriorityCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        let tick = ctx.tick;
+        let Some(states) = &ctx.thread_states else {
+            return Ok(());
+        };
+        // 主线程 TID 与 PID 相同，切到后台时通常被降低优先级
+        if let Some(sched) = states
+            .iter()
+            .find(|s| s.tid == ctx.sample.pid)
+            .and_then(|s| s.sched)
+        {
+            ctx.table
+                .record(&tick, "Main Thread Nice", sched.nice as f64);
+        }
+        for change in self.tracker.update(tick.timestamp, states) {
+            let to = change.to.describe();
+            console!(
+                "[{}] Thread priority: {} (TID: {}) {} -> {}",
+                tick.timestamp.format("%H:%M:%S").to_string().blue(),
+                change.name.cyan(),
+                change.tid.yellow(),
+                change.from.describe(),
+                if change.is_demotion() {
+                    to.red()
+                } else {
+                    to.green()
+                }
+            );
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        if self.tracker.is_empty() {
+            return;
+        }
+        let changes = self.tracker.changes();
+        let demotions = changes.iter().filter(|c| c.is_demotion()).count();
+        console!(
+            "Thread priority changes: {} ({} demotions)",
+            changes.len(),
+            if demotions > 0 {
+                demotions.to_string().red()
+            } else {
+                demotions.to_string().green()
+            }
+        );
+        match export_priority_data(
+            &ctx.layout.dir(Category::Thread),
+            ctx.package,
+            &self.tracker,
+        ) {
+            Ok(paths) => {
+                for path in paths {
+                    console!("✓ Thread priorities exported: {}", path.display());
+                }
+            }
+            Err(e) => console!("Failed to export thread priorities: {}", e),
+        }
+    }
+}
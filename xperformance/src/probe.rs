@@ -0,0 +1,245 @@
+use crate::chart::{self, TimeSeriesData};
+use crate::layout::Category;
+use crate::recorder::{Collector, FinishContext, TickContext};
+use crate::utils;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use clap::ValueEnum;
+use colored::*;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+// 主线程响应探测方式，两种方式都需要应用主线程处理一条消息后才会返回
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ProbeMethod {
+    /// `dumpsys activity activity <package>`，由主线程执行 Activity dump
+    Activity,
+    /// `am broadcast`，需要应用注册接收该 action 的 receiver（适用于 debug 构建）
+    Broadcast,
+}
+
+impl ProbeMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProbeMethod::Activity => "activity",
+            ProbeMethod::Broadcast => "broadcast",
+        }
+    }
+}
+
+// 主线程响应探测，返回扣除 adb 往返开销后的延迟
+pub struct ResponsivenessProbe {
+    method: ProbeMethod,
+    package: String,
+    action: Option<String>,
+    overhead_ms: f64,
+}
+
+fn timed_adb(args: &[&str]) -> Result<(String, f64)> {
+    let start = Instant::now();
+    let output = utils::run_adb_command(args)?;
+    Ok((output, start.elapsed().as_secs_f64() * 1000.0))
+}
+
+impl ResponsivenessProbe {
+    pub fn new(method: ProbeMethod, package: &str, action: Option<&str>) -> Result<Self> {
+        if method == ProbeMethod::Broadcast && action.is_none() {
+            anyhow::bail!("The broadcast probe requires --probe-action");
+        }
+
+        // 用空命令的往返时间中位数作为 adb 开销基线
+        let mut samples = Vec::new();
+        for _ in 0..3 {
+            samples.push(timed_adb(&["shell", "true"])?.1);
+        }
+        samples.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+        Ok(Self {
+            method,
+            package: package.to_string(),
+            action: action.map(str::to_string),
+            overhead_ms: samples[1],
+        })
+    }
+
+    pub fn overhead_ms(&self) -> f64 {
+        self.overhead_ms
+    }
+
+    pub fn measure(&self) -> Result<f64> {
+        let (output, elapsed) = match self.method {
+            ProbeMethod::Activity => {
+                timed_adb(&["shell", "dumpsys", "activity", "activity", &self.package])?
+            }
+            ProbeMethod::Broadcast => timed_adb(&[
+                "shell",
+                "am",
+                "broadcast",
+                "-a",
+                self.action.as_deref().unwrap_or_default(),
+                "-p",
+                &self.package,
+            ])?,
+        };
+
+        if self.method == ProbeMethod::Activity && !output.contains(&self.package) {
+            anyhow::bail!("No activity of {} to probe", self.package);
+        }
+        if self.method == ProbeMethod::Broadcast && !output.contains("Broadcast completed") {
+            anyhow::bail!("Broadcast was not delivered: {}", output.trim());
+        }
+
+        Ok((elapsed - self.overhead_ms).max(0.0))
+    }
+}
+
+// 每个 Tick 探测一次主线程响应，超过阈值记为卡顿
+pub struct ProbeCollector {
+    probe: ResponsivenessProbe,
+    stall_threshold: f64,
+    warmup_end: Option<DateTime<Local>>,
+    points: Vec<(DateTime<Local>, f64)>,
+    stalls: u32,
+}
+
+impl ProbeCollector {
+    // 先测量 adb 往返开销作为基线
+    pub fn start(
+        method: ProbeMethod,
+        package: &str,
+        action: Option<&str>,
+        stall_threshold: f64,
+        warmup_end: Option<DateTime<Local>>,
+    ) -> Result<Self> {
+        let probe = ResponsivenessProbe::new(method, package, action)?;
+        console!(
+            "Main-thread probe: {} (adb overhead: {:.1} ms, stall threshold: {:.0} ms)",
+            method.as_str(),
+            probe.overhead_ms(),
+            stall_threshold
+        );
+        Ok(Self {
+            probe,
+            stall_threshold,
+            warmup_end,
+            points: Vec::new(),
+            stalls: 0,
+        })
+    }
+}
+
+impl Collector for ProbeCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        let tick = ctx.tick;
+        let latency = match self.probe.measure() {
+            Ok(latency) => latency,
+            Err(e) => {
+                if ctx.verbose {
+                    console!("Main-thread probe failed: {}", e);
+                }
+                return Ok(());
+            }
+        };
+        ctx.table.record(&tick, "Main Thread Latency (ms)", latency);
+        self.points.push((tick.timestamp, latency));
+        if !ctx.in_warmup && latency >= self.stall_threshold {
+            self.stalls += 1;
+            ctx.anomaly
+                .get_or_insert_with(|| "Main thread stall".to_string());
+            // CPU 不高时的卡顿多为锁等待、IO 或 binder 阻塞
+            let cpu_note = match ctx.sample.cpu {
+                Some(cpu) => format!(" (process CPU: {:.1}%)", cpu),
+                None => String::new(),
+            };
+            console!(
+                "[{}] {} {:.0} ms{}",
+                tick.timestamp.format("%H:%M:%S").to_string().blue(),
+                "Main thread stall:".red(),
+                latency,
+                cpu_note
+            );
+        } else if ctx.verbose {
+            console!(
+                "[{}] Main thread latency: {:.1} ms",
+                tick.timestamp.format("%H:%M:%S"),
+                latency
+            );
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        if self.points.is_empty() {
+            return;
+        }
+        console!(
+            "Main Thread Stalls: {} (>= {:.0} ms)",
+            self.stalls.to_string().red(),
+            self.stall_threshold
+        );
+        match export_probe_data(
+            &ctx.layout.dir(Category::Probe),
+            ctx.package,
+            &self.points,
+            self.stall_threshold,
+            self.warmup_end,
+        ) {
+            Ok((csv_path, chart_path)) => {
+                console!(
+                    "✓ Main-thread latency exported to CSV: {}",
+                    csv_path.display()
+                );
+                console!(
+                    "✓ Main-thread latency chart generated: {}",
+                    chart_path.display()
+                );
+            }
+            Err(e) => console!("Failed to export main-thread latency: {}", e),
+        }
+    }
+}
+
+// 导出主线程延迟 CSV 和图表，图表中附带卡顿阈值线
+fn export_probe_data(
+    probe_dir: &Path,
+    package: &str,
+    points: &[(DateTime<Local>, f64)],
+    stall_threshold: f64,
+    warmup_end: Option<DateTime<Local>>,
+) -> Result<(PathBuf, PathBuf)> {
+    std::fs::create_dir_all(probe_dir)?;
+
+    let csv_path = probe_dir.join(format!("{}_main_thread_latency.csv", package));
+    let mut file = std::fs::File::create(&csv_path)?;
+    writeln!(file, "Timestamp,Latency (ms),Stall")?;
+    for (timestamp, latency) in points {
+        writeln!(
+            file,
+            "{},{:.1},{}",
+            timestamp.format("%Y-%m-%d %H:%M:%S"),
+            latency,
+            (*latency >= stall_threshold && warmup_end.is_none_or(|end| *timestamp >= end)) as u8
+        )?;
+    }
+
+    let chart_path = probe_dir.join(chart::stamped_file_name(&format!(
+        "{}_main_thread_latency.png",
+        package
+    )));
+    let threshold = points
+        .iter()
+        .map(|(timestamp, _)| (*timestamp, stall_threshold))
+        .collect();
+    chart::draw_time_series_chart(
+        &chart_path,
+        &format!("Main Thread Latency - {}", package),
+        "Latency (ms)",
+        &[
+            TimeSeriesData::new("Main thread latency", points.to_vec()),
+            TimeSeriesData::new("Stall threshold", threshold),
+        ],
+    )?;
+
+    Ok((csv_path, chart_path))
+}
@@ -0,0 +1,218 @@
+use crate::layout::Category;
+use crate::recorder::{Collector, FinishContext, TickContext};
+use crate::utils;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use colored::*;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// Android 内核的 USER_HZ，/proc/<pid>/stat 中的时间以此为单位
+const CLOCK_TICKS_PER_SEC: f64 = 100.0;
+
+// 进程运行时长和调度优先级
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ProcessStatus {
+    pub uptime_secs: f64,
+    pub nice: i32,
+    pub oom_score: i32,
+    pub oom_score_adj: i32,
+}
+
+impl ProcessStatus {
+    // nice 或 oom_score_adj 变化视为优先级变化（如前后台切换）
+    pub fn priority_changed(&self, previous: &ProcessStatus) -> bool {
+        self.nice != previous.nice || self.oom_score_adj != previous.oom_score_adj
+    }
+}
+
+// /proc/<pid>/stat 中 rss 以页为单位
+const PAGE_SIZE_KB: f64 = 4.0;
+
+// /proc/<pid>/stat 中计算 CPU 和内存所需的字段
+#[derive(Debug, Clone)]
+pub struct ProcStat {
+    pub pid: String,
+    pub ppid: String,
+    pub comm: String,
+    // utime + stime，单位为 CLOCK_TICKS_PER_SEC
+    pub cpu_ticks: u64,
+    // cutime + cstime，已退出并被回收的子进程的 CPU 时间
+    pub child_cpu_ticks: u64,
+    pub rss_kb: f64,
+}
+
+// comm 可能含空格和括号，以最后一个 ')' 为界，之后第一个字段是第 3 列（state）
+pub fn parse_proc_stat(line: &str) -> Option<ProcStat> {
+    let open = line.find('(')?;
+    let close = line.rfind(')')?;
+    if close < open {
+        return None;
+    }
+    let fields: Vec<&str> = line[close + 1..].split_whitespace().collect();
+    let field = |column: usize| -> Option<u64> { fields.get(column - 3)?.parse().ok() };
+    Some(ProcStat {
+        pid: line[..open].trim().to_string(),
+        ppid: fields.get(1)?.to_string(),
+        comm: line[open + 1..close].to_string(),
+        cpu_ticks: field(14)? + field(15)?,
+        child_cpu_ticks: field(16)? + field(17)?,
+        rss_kb: field(24)? as f64 * PAGE_SIZE_KB,
+    })
+}
+
+// 两次读数之间的 CPU 占用（单核为 100%）
+pub fn cpu_percent(ticks_before: u64, ticks_now: u64, elapsed_secs: f64) -> Option<f64> {
+    if ticks_now < ticks_before || elapsed_secs <= 0.0 {
+        return None;
+    }
+    Some((ticks_now - ticks_before) as f64 / CLOCK_TICKS_PER_SEC / elapsed_secs * 100.0)
+}
+
+// 一次 adb 调用读取 stat、系统 uptime 和 oom 分数
+pub fn sample_process_status(pid: &str) -> Result<ProcessStatus> {
+    let output = utils::run_adb_command(&[
+        "shell",
+        &format!(
+            "cat /proc/{0}/stat /proc/uptime /proc/{0}/oom_score /proc/{0}/oom_score_adj",
+            pid
+        ),
+    ])?;
+    let lines: Vec<&str> = output.lines().map(str::trim).collect();
+    if lines.len() < 4 {
+        anyhow::bail!("Unexpected /proc output for pid {}: {}", pid, output.trim());
+    }
+
+    // stat 中 comm 可能含空格，从最后一个 ')' 之后开始，第一个字段是第 3 列（state）
+    let stat = lines[0];
+    let fields: Vec<&str> = stat[stat.rfind(')').map_or(0, |i| i + 1)..]
+        .split_whitespace()
+        .collect();
+    let field = |column: usize| -> Result<&str> {
+        fields
+            .get(column - 3)
+            .copied()
+            .ok_or_else(|| anyhow::format_err!("Missing field {} in /proc/{}/stat", column, pid))
+    };
+    let nice: i32 = field(19)?.parse()?;
+    let start_ticks: f64 = field(22)?.parse()?;
+
+    let system_uptime: f64 = lines[1]
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .parse()?;
+
+    Ok(ProcessStatus {
+        uptime_secs: (system_uptime - start_ticks / CLOCK_TICKS_PER_SEC).max(0.0),
+        nice,
+        oom_score: lines[2].parse()?,
+        oom_score_adj: lines[3].parse()?,
+    })
+}
+
+// 每个 Tick 记录进程运行时长和优先级，以及优先级变化
+#[derive(Default)]
+pub struct StatusCollector {
+    statuses: Vec<ProcessStatus>,
+    changes: Vec<(DateTime<Local>, ProcessStatus, ProcessStatus)>,
+}
+
+impl Collector for StatusCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        let status = match sample_process_status(&ctx.sample.pid) {
+            Ok(status) => status,
+            Err(e) => {
+                if ctx.verbose {
+                    console!("Failed to read process status: {}", e);
+                }
+                return Ok(());
+            }
+        };
+        for (column, value) in [
+            ("Uptime (s)", status.uptime_secs),
+            ("Nice", status.nice as f64),
+            ("OOM Score", status.oom_score as f64),
+            ("OOM Score Adj", status.oom_score_adj as f64),
+        ] {
+            ctx.table.record(&ctx.tick, column, value);
+        }
+        // 运行时长变小说明进程已重启，不算优先级变化
+        if let Some(previous) = self.statuses.last().copied() {
+            if status.uptime_secs >= previous.uptime_secs && status.priority_changed(&previous) {
+                console!(
+                    "[{}] {} nice {} -> {}, oom_score_adj {} -> {}",
+                    ctx.tick.timestamp.format("%H:%M:%S").to_string().blue(),
+                    "Priority changed:".yellow(),
+                    previous.nice,
+                    status.nice,
+                    previous.oom_score_adj,
+                    status.oom_score_adj
+                );
+                self.changes.push((ctx.tick.timestamp, previous, status));
+            }
+        }
+        self.statuses.push(status);
+        ctx.process_status = Some(status);
+        Ok(())
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        let statuses = &self.statuses;
+        let Some(last) = statuses.last() else {
+            return;
+        };
+        let nice_min = statuses.iter().map(|s| s.nice).min().unwrap_or(0);
+        let nice_max = statuses.iter().map(|s| s.nice).max().unwrap_or(0);
+        let oom_min = statuses.iter().map(|s| s.oom_score).min().unwrap_or(0);
+        let oom_max = statuses.iter().map(|s| s.oom_score).max().unwrap_or(0);
+        console!(
+            "Process Uptime: {:.0} s, nice: {}..{}, oom_score: {}..{}, priority changes: {}",
+            last.uptime_secs,
+            nice_min,
+            nice_max,
+            oom_min,
+            oom_max,
+            self.changes.len().to_string().red()
+        );
+
+        if !self.changes.is_empty() {
+            match export_priority_changes(
+                &ctx.layout.dir(Category::Process),
+                ctx.package,
+                &self.changes,
+            ) {
+                Ok(path) => console!("✓ Priority changes exported to CSV: {}", path.display()),
+                Err(e) => console!("Failed to export priority changes: {}", e),
+            }
+        }
+    }
+}
+
+// 导出优先级变化记录
+pub fn export_priority_changes(
+    process_dir: &Path,
+    package: &str,
+    changes: &[(DateTime<Local>, ProcessStatus, ProcessStatus)],
+) -> Result<PathBuf> {
+    std::fs::create_dir_all(process_dir)?;
+    let path = process_dir.join(format!("{}_priority_changes.csv", package));
+    let mut file = std::fs::File::create(&path)?;
+    writeln!(
+        file,
+        "Timestamp,Uptime (s),Nice Before,Nice After,OOM Score Adj Before,OOM Score Adj After"
+    )?;
+    for (timestamp, before, after) in changes {
+        writeln!(
+            file,
+            "{},{:.0},{},{},{},{}",
+            timestamp.format("%Y-%m-%d %H:%M:%S"),
+            after.uptime_secs,
+            before.nice,
+            after.nice,
+            before.oom_score_adj,
+            after.oom_score_adj
+        )?;
+    }
+    Ok(path)
+}
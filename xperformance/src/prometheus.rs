@@ -0,0 +1,184 @@
+use crate::memory::MemoryDetails;
+use crate::recorder::{Collector, TickContext};
+use crate::utils;
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::Mutex;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+// 分内存类别导出的字段，名称用作 category 标签
+const MEMORY_CATEGORIES: [&str; 7] = [
+    "java_heap",
+    "native_heap",
+    "code",
+    "stack",
+    "graphics",
+    "private_other",
+    "system",
+];
+
+// 某个应用（及设备）最近一次采样的指标
+#[derive(Debug, Clone, Default)]
+struct AppMetrics {
+    cpu: Option<f64>,
+    memory: Option<MemoryDetails>,
+    restarts: u32,
+}
+
+// 按 (包名, 设备序列号) 保存，多设备并行监控时各自一组；None 表示未启动服务
+type MetricsMap = BTreeMap<(String, Option<String>), AppMetrics>;
+static METRICS: Mutex<Option<MetricsMap>> = Mutex::new(None);
+
+// 绑定端口并在后台提供 /metrics，进程结束前一直运行
+pub async fn serve(port: u16) -> Result<()> {
+    let listener = TcpListener::bind(("0.0.0.0", port))
+        .await
+        .with_context(|| format!("Failed to listen on port {}", port))?;
+    METRICS.lock().unwrap().get_or_insert_with(BTreeMap::new);
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut buf = [0u8; 1024];
+                let Ok(n) = stream.read(&mut buf).await else {
+                    return;
+                };
+                // 只看请求行 "GET /metrics HTTP/1.1"
+                let request = String::from_utf8_lossy(&buf[..n]);
+                let path = request.split_whitespace().nth(1).unwrap_or_default();
+                let response = if path == "/metrics" || path == "/" {
+                    let body = render();
+                    format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    )
+                } else {
+                    "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                        .to_string()
+                };
+                let _ = stream.write_all(response.as_bytes()).await;
+                let _ = stream.shutdown().await;
+            });
+        }
+    });
+    Ok(())
+}
+
+// 每个采样周期更新一次；本周期未采到的指标保留上次的值。未启动服务时不做任何事
+pub fn update(package: &str, cpu: Option<f64>, memory: Option<&MemoryDetails>, restarts: u32) {
+    let Ok(mut metrics) = METRICS.lock() else {
+        return;
+    };
+    let Some(metrics) = metrics.as_mut() else {
+        return;
+    };
+    let entry = metrics
+        .entry((package.to_string(), utils::adb_serial()))
+        .or_default();
+    if cpu.is_some() {
+        entry.cpu = cpu;
+    }
+    if let Some(memory) = memory {
+        entry.memory = Some(memory.clone());
+    }
+    entry.restarts = restarts;
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn memory_value(details: &MemoryDetails, category: &str) -> u64 {
+    match category {
+        "java_heap" => details.java_heap,
+        "native_heap" => details.native_heap,
+        "code" => details.code,
+        "stack" => details.stack,
+        "graphics" => details.graphics,
+        "private_other" => details.private_other,
+        _ => details.system,
+    }
+}
+
+// Prometheus 文本格式，每个指标一组 HELP/TYPE
+fn render() -> String {
+    let metrics = METRICS.lock().unwrap().clone().unwrap_or_default();
+    let mut out = String::new();
+    let labels = |(package, serial): &(String, Option<String>)| match serial {
+        Some(serial) => format!(
+            "package=\"{}\",device=\"{}\"",
+            escape(package),
+            escape(serial)
+        ),
+        None => format!("package=\"{}\"", escape(package)),
+    };
+    let mut gauge = |name: &str, help: &str, values: Vec<(String, f64)>| {
+        if values.is_empty() {
+            return;
+        }
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} gauge", name);
+        for (labels, value) in values {
+            let _ = writeln!(out, "{}{{{}}} {}", name, labels, value);
+        }
+    };
+
+    gauge(
+        "xperformance_cpu_usage_percent",
+        "App CPU usage in percent.",
+        metrics
+            .iter()
+            .filter_map(|(key, m)| m.cpu.map(|cpu| (labels(key), cpu)))
+            .collect(),
+    );
+    gauge(
+        "xperformance_memory_total_pss_kb",
+        "App total PSS in KB.",
+        metrics
+            .iter()
+            .filter_map(|(key, m)| m.memory.as_ref().map(|d| (labels(key), d.total_pss as f64)))
+            .collect(),
+    );
+    gauge(
+        "xperformance_memory_kb",
+        "App PSS per memory category in KB.",
+        metrics
+            .iter()
+            .filter_map(|(key, m)| m.memory.as_ref().map(|d| (key, d)))
+            .flat_map(|(key, details)| {
+                MEMORY_CATEGORIES.iter().map(move |category| {
+                    (
+                        format!("{},category=\"{}\"", labels(key), category),
+                        memory_value(details, category) as f64,
+                    )
+                })
+            })
+            .collect(),
+    );
+    gauge(
+        "xperformance_restart_count",
+        "Number of app process restarts since monitoring started.",
+        metrics
+            .iter()
+            .map(|(key, m)| (labels(key), m.restarts as f64))
+            .collect(),
+    );
+    out
+}
+
+// 每个 Tick 更新 CPU、内存和重启次数指标
+pub struct MetricsPublisher;
+
+impl Collector for MetricsPublisher {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        update(
+            ctx.package,
+            ctx.sample.cpu.map(f64::from),
+            ctx.sample.memory.as_ref(),
+            ctx.peaks.restarts,
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,1515 @@
+use crate::activity::ActivityCollector;
+use crate::alarm::AlarmCollector;
+use crate::atrace::AtraceCollector;
+use crate::binder::BinderCollector;
+use crate::capture::{PerfettoCollector, PerfettoTrigger};
+use crate::chart;
+use crate::ci::{self, CiReport, Peaks};
+use crate::cluster::ClusterCollector;
+use crate::companion::CompanionCollector;
+use crate::counters::{CounterReading, CounterTracker, GapCollector, IoCollector, SwitchCollector};
+use crate::cpu::{CpuScale, ThreadCpuInfo, ThreadRole};
+use crate::cpufreq::{CpuFreqCollector, FreqSample};
+use crate::dropbox::DropboxCollector;
+use crate::faults::FaultCollector;
+use crate::fd::FdCollector;
+use crate::framestats::{FpsDropDetector, FrameCollector, FrameSource};
+use crate::gc::{GcCollector, GcEvent};
+use crate::gpu::GpuCollector;
+use crate::jobs::JobCollector;
+use crate::jsonl::JsonlCollector;
+use crate::layout::{Category, OutputLayout};
+use crate::leak::{self, Trend};
+use crate::logcat::{self, LogcatCollector, LogcatEvent};
+use crate::markers::MarkerCollector;
+use crate::memory::{self, MemoryChartOverlay, MemoryDetails, MemoryTimeSeriesData};
+use crate::monitor::{MonitorConfig, MonitorPeaks, MonitorSample, Sampler};
+use crate::multiprocess::ProcessGroupCollector;
+use crate::odpm::RailCollector;
+use crate::oomadj::OomCollector;
+use crate::percore::PerCoreCollector;
+use crate::power::{BatteryCollector, BatteryStatsCollector};
+use crate::priority::PriorityCollector;
+use crate::probe::{ProbeCollector, ProbeMethod};
+use crate::process::{ProcessStatus, StatusCollector};
+use crate::prometheus::MetricsPublisher;
+use crate::replay;
+use crate::scenario::{Scenario, ScenarioCollector};
+use crate::segment::SegmentCollector;
+use crate::session::SessionInfo;
+use crate::simpleperf::ProfileCollector;
+use crate::snapshot::SnapshotCollector;
+use crate::stats::{self, Summary};
+use crate::system::{self, HeadroomCollector, MemoryRanking};
+use crate::thermal::ThermalCollector;
+use crate::threadstate::{
+    BlockedThreadCollector, ThreadCountCollector, ThreadState, ThreadStateCollector,
+};
+use crate::threshold::{self, ThresholdCollector};
+use crate::throttle::ThrottleCollector;
+use crate::tick::{Tick, TickTable};
+use crate::tombstone::TombstoneCollector;
+use crate::tui::{self, Dashboard, DashboardPublisher};
+use crate::utils::{self, CpuChartOverlay};
+use crate::wakelock::WakelockCollector;
+use crate::web::WebPublisher;
+use anyhow::Result;
+use chrono::{DateTime, Local, Timelike};
+use clap::ValueEnum;
+use colored::*;
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Duration;
+
+// 线程 CPU 数据的导出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ThreadCsv {
+    /// 每个线程一个 CSV
+    PerThread,
+    /// 宽表（每个 Tick 一行）和长表（每个 Tick 每个线程一行）
+    Consolidated,
+    /// 两种都导出
+    All,
+}
+
+// CPU、内存和线程采样的输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// 按指标导出 CSV
+    Csv,
+    /// 每条采样一行 JSON（<package>_samples.jsonl），不导出这些指标的 CSV
+    Json,
+    /// 两种都导出
+    Both,
+}
+
+impl OutputFormat {
+    pub fn csv(self) -> bool {
+        self != OutputFormat::Json
+    }
+
+    pub fn json(self) -> bool {
+        self != OutputFormat::Csv
+    }
+}
+
+// 一次监控会话要采集的指标和导出方式，命令行参数和配置文件都归结到这里
+#[derive(Debug, Clone)]
+pub struct RecorderOptions {
+    pub package: String,
+    pub interval: u64,
+    pub verbose: bool,
+    pub cpu: bool,
+    pub memory: bool,
+    pub thread: bool,
+    pub gpu: bool,
+    pub battery: bool,
+    pub battery_stats: bool,
+    pub power_rails: bool,
+    pub fps: bool,
+    pub frame_source: FrameSource,
+    // --min-fps：帧率持续低于阈值的时长达到 min_fps_duration 时告警
+    pub min_fps: Option<f64>,
+    pub min_fps_duration: Duration,
+    pub thermal: bool,
+    pub io: bool,
+    pub fds: bool,
+    pub ctx_switches: bool,
+    pub page_faults: bool,
+    pub gc: bool,
+    pub strict_mode: bool,
+    pub activity: bool,
+    pub jobs: bool,
+    pub oom_adj: bool,
+    // --object-growth：回到桌面后连续增长多少次采样时告警
+    pub object_growth: Option<usize>,
+    pub per_core: bool,
+    pub cpu_freq: bool,
+    pub binder: bool,
+    pub wakelocks: bool,
+    // --alarms：dumpsys alarm 输出较大，按 alarm_interval 间隔读取
+    pub alarms: bool,
+    pub alarm_interval: Duration,
+    pub cpu_scale: CpuScale,
+    pub probe: Option<ProbeMethod>,
+    pub probe_action: Option<String>,
+    pub stall_threshold: f64,
+    pub also: Vec<String>,
+    pub all_processes: bool,
+    pub children: bool,
+    pub snapshots: bool,
+    // 从标准输入和/或命名管道读取用户事件标记
+    pub markers: bool,
+    // run-scenario 的场景脚本，第一个 launch 及之前的步骤已在会话开始前执行
+    pub scenario: Option<Scenario>,
+    pub marker_pipe: Option<PathBuf>,
+    pub blocked_threads: Option<u32>,
+    pub perfetto: Option<String>,
+    pub perfetto_duration: Duration,
+    pub perfetto_trigger: PerfettoTrigger,
+    pub profile_on_spike: Option<f64>,
+    pub profile_duration: Duration,
+    pub flamegraph: bool,
+    pub atrace: bool,
+    pub atrace_duration: Duration,
+    pub atrace_categories: Vec<String>,
+    pub warmup: Option<Duration>,
+    pub format: OutputFormat,
+    pub tui: bool,
+    pub ci: bool,
+    pub serve: bool,
+    pub prometheus: bool,
+    pub max_cpu: Option<f64>,
+    pub max_pss_kb: Option<u64>,
+    pub max_restarts: Option<u32>,
+    pub thread_csv: ThreadCsv,
+    pub top_threads: usize,
+    pub stamp_filenames: bool,
+}
+
+impl RecorderOptions {
+    // 没有选择任何指标时不启动会话
+    pub fn has_metrics(&self) -> bool {
+        self.cpu
+            || self.memory
+            || self.gpu
+            || self.battery
+            || self.battery_stats
+            || self.power_rails
+            || self.fps
+            || self.thermal
+            || self.io
+            || self.fds
+            || self.ctx_switches
+            || self.page_faults
+            || self.gc
+            || self.strict_mode
+            || self.activity
+            || self.jobs
+            || self.oom_adj
+            || self.binder
+            || self.wakelocks
+            || self.alarms
+            || self.probe.is_some()
+            || self.blocked_threads.is_some()
+            || !self.also.is_empty()
+            || self.all_processes
+            || self.children
+            || self.snapshots
+            || self.perfetto.is_some()
+            || self.atrace
+    }
+}
+
+// 一个 Tick 内各采集器共用的数据
+pub struct TickContext<'a> {
+    pub tick: Tick,
+    pub package: &'a str,
+    // 本 Tick 的进程、CPU 和内存采样
+    pub sample: &'a MonitorSample,
+    // 不含预热区间的峰值和重启次数
+    pub peaks: &'a MonitorPeaks,
+    pub in_warmup: bool,
+    pub verbose: bool,
+    // IO、网络等累计计数器的读数，读取失败时为 None
+    pub counters: Option<&'a CounterReading>,
+    // 本 Tick 结束了一段 ADB 断开造成的缺口，值为缺口开始时间；此时计数器增量是整个缺口的总量
+    pub gap_started: Option<DateTime<Local>>,
+    // 本 Tick 从 logcat 读到的应用事件和 GC，由 LogcatCollector 填入
+    pub events: Vec<LogcatEvent>,
+    pub gc_events: Vec<GcEvent>,
+    // 本 Tick 检测到的第一个异常（重启、阈值告警、崩溃/ANR、主线程卡顿），用于触发 perfetto 抓取
+    pub anomaly: Option<String>,
+    // 本 Tick 的各簇频率和温控状态，由 CpuFreqCollector 和 ThermalCollector 填入，用于判断限频
+    pub cpu_freq: Option<FreqSample>,
+    pub thermal_status: Option<u32>,
+    // 本 Tick 各线程的调度状态，由 ThreadStateCollector 填入
+    pub thread_states: Option<Vec<ThreadState>>,
+    // 本 Tick 的 nice 和 oom 分数，由 StatusCollector 填入
+    pub process_status: Option<ProcessStatus>,
+    pub table: &'a mut TickTable,
+}
+
+// 会话结束时各采集器共用的数据
+pub struct FinishContext<'a> {
+    pub package: &'a str,
+    // 最后一次采样时的进程
+    pub pid: &'a str,
+    // 本次会话的输出目录结构
+    pub layout: &'a OutputLayout,
+    // 结束时仍未恢复的缺口开始时间
+    pub open_gap: Option<DateTime<Local>>,
+}
+
+// 一项可选指标的采集和导出，状态由各采集器自己持有
+pub trait Collector: Send {
+    // 每个 Tick 在进程、CPU 和内存采样之后调用
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()>;
+
+    // 采样结束后、导出之前调用，用于停止仍在进行的后台抓取
+    fn stop(&mut self) {}
+
+    // 会话结束时输出汇总并导出数据
+    fn finish(&mut self, _ctx: &FinishContext) {}
+
+    // 向 CPU 图表添加的事件标记和叠加曲线
+    fn cpu_overlay(&self, _overlay: &mut CpuChartOverlay) {}
+
+    // 向内存图表添加的风暴区间和 GC 标记
+    fn memory_overlay(&self, _overlay: &mut MemoryChartOverlay) {}
+
+    // 会话期间记录的事件（崩溃、用户标记等），合并写入 events.csv
+    fn events(&self, _events: &mut Vec<LogcatEvent>) {}
+
+    // 追加到会话统计摘要的指标，如各电源轨的功率
+    fn summaries(&self, _summaries: &mut Vec<(String, Summary)>) {}
+
+    // 填入 --ci 报告中属于自己的部分，如阈值超限
+    fn ci_report(&self, _report: &mut CiReport) {}
+
+    // 需要提前结束会话时返回原因，如场景脚本已执行完
+    fn end_reason(&self) -> Option<String> {
+        None
+    }
+
+    // 等待下一个 Tick 前调用，用于刷新缓冲的输出
+    fn flush(&mut self) {}
+}
+
+#[derive(Default)]
+struct CpuTimeSeriesData {
+    timestamps: VecDeque<DateTime<Local>>,
+    process_cpu: VecDeque<f32>,
+    top_threads: VecDeque<Vec<ThreadCpuInfo>>,
+}
+
+impl CpuTimeSeriesData {
+    fn add_data_point(
+        &mut self,
+        timestamp: DateTime<Local>,
+        process_cpu: f32,
+        top_threads: Vec<ThreadCpuInfo>,
+    ) {
+        self.timestamps.push_back(timestamp);
+        self.process_cpu.push_back(process_cpu);
+        self.top_threads.push_back(top_threads);
+    }
+}
+
+// 一个应用的监控会话：进程、CPU、内存和线程由 Sampler 采样，其余指标交给各采集器
+pub struct SessionRecorder {
+    options: RecorderOptions,
+    sampler: Sampler,
+    session_info: SessionInfo,
+    warmup_end: Option<DateTime<Local>>,
+    // 会话开始时间，用于 --ci 报告的时长
+    started: DateTime<Local>,
+    peaks: MonitorPeaks,
+    cpu_data: CpuTimeSeriesData,
+    memory_data: MemoryTimeSeriesData,
+    // 完整的内存明细历史，用于堆大小与已分配量的对比图
+    heap_history: Vec<(DateTime<Local>, MemoryDetails)>,
+    // 开始时整机进程的内存排名，结束时与之对比
+    start_ranking: Option<MemoryRanking>,
+    // 按 Tick 保存的线程数据，用于导出合并的宽表和长表
+    thread_samples: Vec<(Tick, Vec<ThreadCpuInfo>)>,
+    thread_time_series: HashMap<String, Vec<ThreadCpuInfo>>,
+    counters: CounterTracker,
+    gap_start: Option<DateTime<Local>>,
+    // 所有指标按 Tick 对齐的宽表
+    table: TickTable,
+    last_chart_hour: i32,
+    // 统计摘要每小时输出一次，首次在开始后的下一个整点
+    last_summary_hour: u32,
+    // 暂停以外实际进行的采样数，用于 --samples
+    collected: u64,
+    collectors: Vec<Box<dyn Collector>>,
+    // 仪表盘接管终端，结束采样后恢复，汇总照常打印
+    dashboard: Option<Dashboard>,
+}
+
+impl SessionRecorder {
+    // 记录应用和设备信息并启动各采集器；进程不存在时返回错误
+    pub fn start(options: RecorderOptions, running: &Arc<AtomicBool>) -> Result<Self> {
+        let package = options.package.as_str();
+
+        // 启动时记录应用和设备信息，便于日后对比归档数据
+        let mut session_info = SessionInfo::capture(None, package);
+        if let Some(scenario) = &options.scenario {
+            session_info.scenario = scenario.name();
+        }
+        console!(
+            "App version: {} ({}), build: {}, targetSdk: {}, UID: {}",
+            session_info.version_name.cyan(),
+            session_info.version_code,
+            session_info.build_type().yellow(),
+            session_info.target_sdk,
+            session_info.uid
+        );
+        if !session_info.last_update_time.is_empty() {
+            console!(
+                "Installed: {}, last updated: {}",
+                session_info.first_install_time,
+                session_info.last_update_time.cyan()
+            );
+        }
+        console!(
+            "Device: {} {} (Android {}, SDK {})",
+            session_info.device.manufacturer,
+            session_info.device.model.cyan(),
+            session_info.device.android_version,
+            session_info.device.sdk
+        );
+
+        // 图表标题附带设备和构建信息
+        chart::set_stamp(&session_info.stamp_label(), options.stamp_filenames);
+
+        // 预热区间：样本照常记录和绘图（加底纹），但不计入峰值、统计和告警
+        let warmup_end = options
+            .warmup
+            .map(|warmup| Local::now() + chrono::Duration::from_std(warmup).unwrap_or_default());
+        chart::set_warmup_end(warmup_end);
+        if let Some(end) = warmup_end {
+            session_info.warmup_end = end.format("%Y-%m-%d %H:%M:%S").to_string();
+            console!(
+                "Warmup until {}, excluded from peaks, statistics and alerts",
+                end.format("%H:%M:%S").to_string().yellow()
+            );
+        }
+        session_info.cpu_scale = options.cpu_scale.as_str().to_string();
+
+        let sampler = Sampler::new(MonitorConfig {
+            package: package.to_string(),
+            interval: Duration::from_secs(options.interval),
+            cpu: options.cpu,
+            memory: options.memory,
+            cpu_scale: options.cpu_scale,
+            verbose: options.verbose,
+        })?;
+        // 核心数用于 CPU 归一化和图表坐标范围
+        if options.cpu {
+            console!(
+                "CPU cores: {}, CPU scale: {} ({})",
+                sampler.cores(),
+                options.cpu_scale.as_str(),
+                options.cpu_scale.unit()
+            );
+        }
+
+        // 开始和结束时整机进程的内存排名，查看同时竞争内存的其他进程
+        let start_ranking = if options.memory {
+            capture_memory_ranking(package, "start")
+        } else {
+            None
+        };
+
+        let collectors = start_collectors(&options, &session_info, &sampler, warmup_end)?;
+
+        let process = sampler.process();
+        console!(
+            "Process started with PID {} at {}",
+            process.pid.yellow(),
+            process.start_time.blue()
+        );
+
+        // 如果是verbose模式且开启了CPU监控，立即尝试导出一个初始线程数据文件
+        // 确保文件被创建但不预先创建空目录
+        if options.verbose && options.cpu {
+            console!(
+                "CPU monitoring enabled, but not creating files until actual thread data is available"
+            );
+        }
+
+        let dashboard = if options.tui {
+            Some(Dashboard::start(package, running.clone())?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            options,
+            sampler,
+            session_info,
+            warmup_end,
+            started: Local::now(),
+            peaks: MonitorPeaks::default(),
+            cpu_data: CpuTimeSeriesData::default(),
+            memory_data: MemoryTimeSeriesData::default(),
+            heap_history: Vec::new(),
+            start_ranking,
+            thread_samples: Vec::new(),
+            thread_time_series: HashMap::new(),
+            counters: CounterTracker::default(),
+            gap_start: None,
+            table: TickTable::default(),
+            last_chart_hour: -1,
+            last_summary_hour: Local::now().hour(),
+            collected: 0,
+            collectors,
+            dashboard,
+        })
+    }
+
+    // ADB 断开期间不采样，只记录缺口开始时间
+    pub fn record_gap(&mut self, tick: &Tick) {
+        self.gap_start.get_or_insert(tick.timestamp);
+    }
+
+    pub fn collected(&self) -> u64 {
+        self.collected
+    }
+
+    // 某个采集器要求结束会话时的原因
+    pub fn end_reason(&self) -> Option<String> {
+        self.collectors.iter().find_map(|c| c.end_reason())
+    }
+
+    // 等待下一个 Tick 前刷新各采集器的缓冲输出
+    pub fn flush(&mut self) {
+        for collector in &mut self.collectors {
+            collector.flush();
+        }
+    }
+
+    // 采样一个 Tick；进程已退出时返回 false，监控应当结束
+    pub async fn sample(&mut self, tick: Tick) -> Result<bool> {
+        // 仪表盘中按 e 时先写出目前为止的合并数据
+        if tui::take_export_request() && !self.table.is_empty() {
+            let layout = OutputLayout::session(&self.options.package)?;
+            self.export_combined(&layout);
+        }
+        if tui::is_paused() {
+            return Ok(true);
+        }
+        self.collected += 1;
+
+        let in_warmup = self.warmup_end.is_some_and(|end| tick.timestamp < end);
+        self.hourly_cpu_chart(&tick);
+        if tick.timestamp.hour() != self.last_summary_hour {
+            self.last_summary_hour = tick.timestamp.hour();
+            report_summary(
+                &OutputLayout::session(&self.options.package)?,
+                &self.session_summary(),
+            );
+        }
+
+        let previous_pid = self.sampler.process().pid.clone();
+        let smaps_was_available = !self.sampler.smaps_unavailable();
+        let sample = match self.sampler.sample(tick).await {
+            Ok(sample) => sample,
+            Err(e) => {
+                // 设备掉线导致的失败按数据缺口处理，等待重连
+                if !utils::check_adb_connection() {
+                    self.record_gap(&tick);
+                    return Ok(true);
+                }
+                console!("\n{}: {}", "Process not found".red(), e);
+                return Ok(false);
+            }
+        };
+        if smaps_was_available && self.sampler.smaps_unavailable() {
+            console!(
+                "{}",
+                "/proc/<pid>/smaps_rollup is not readable (needs root or a debuggable app); no USS/RSS/Swap data"
+                    .yellow()
+            );
+        }
+
+        if sample.restarted {
+            let timestamp = Local::now().format("%H:%M:%S").to_string();
+            let restart_msg = format!(
+                "[{}] Process restarted! New PID: {} (previous: {}), Start time: {}",
+                timestamp.blue(),
+                sample.pid.yellow(),
+                previous_pid.red(),
+                self.sampler.process().start_time
+            );
+            let peaks = format_current_peaks(&self.peaks);
+            if !peaks.is_empty() {
+                console!("{}\n\n{}", peaks, restart_msg);
+            } else {
+                console!("\n{}", restart_msg);
+            }
+        }
+        // 预热期间只计重启次数
+        if in_warmup {
+            self.peaks.restarts += u32::from(sample.restarted);
+        } else {
+            self.peaks.record(&sample);
+        }
+
+        // 累计计数器：正常时记录每个 Tick 的增量，缺口结束时由 GapCollector 记为补回量
+        let counters = self.counters.sample(&sample.pid, tick.timestamp);
+        let gap_started = self.gap_start.take();
+        if gap_started.is_none() {
+            if let Some(delta) = counters.as_ref().and_then(|c| c.delta.as_ref()) {
+                for (column, value) in delta.columns() {
+                    if let Some(value) = value {
+                        self.table.record(&tick, column, value);
+                    }
+                }
+            }
+        }
+
+        if let Some(cpu_usage) = sample.cpu {
+            self.table.record(&tick, "CPU Usage (%)", cpu_usage as f64);
+            self.cpu_data
+                .add_data_point(tick.timestamp, cpu_usage, sample.threads.clone());
+            if self.options.thread {
+                self.record_threads(&tick, &sample);
+            }
+        }
+
+        if let Some(details) = &sample.memory {
+            if let Some(objects) = details.objects {
+                for (column, value) in objects.columns() {
+                    self.table.record(&tick, column, value as f64);
+                }
+            }
+            if let Some(smaps) = details.smaps {
+                for (column, value) in [
+                    ("RSS (KB)", smaps.rss),
+                    ("USS (KB)", smaps.uss),
+                    ("Swap (KB)", smaps.swap),
+                    ("Swap PSS (KB)", smaps.swap_pss),
+                ] {
+                    self.table.record(&tick, column, value as f64);
+                }
+            }
+            for (column, value) in memory::detail_columns(details) {
+                self.table.record(&tick, column, value as f64);
+            }
+            self.heap_history.push((tick.timestamp, details.clone()));
+            self.memory_data
+                .add_data_point(tick.timestamp, details.clone());
+
+            // 如果开启了详细模式并且已收集了足够的数据点，生成内存图表
+            if self.options.verbose && self.memory_data.timestamps.len() >= 5 {
+                if let Ok(layout) = OutputLayout::session(&self.options.package) {
+                    self.write_memory_charts(&layout, &[]);
+                }
+            }
+        }
+
+        let mut ctx = TickContext {
+            tick,
+            package: &self.options.package,
+            sample: &sample,
+            peaks: &self.peaks,
+            in_warmup,
+            verbose: self.options.verbose,
+            counters: counters.as_ref(),
+            gap_started,
+            events: Vec::new(),
+            gc_events: Vec::new(),
+            anomaly: sample.restarted.then(|| "Process restart".to_string()),
+            cpu_freq: None,
+            thermal_status: None,
+            thread_states: None,
+            process_status: None,
+            table: &mut self.table,
+        };
+        for collector in &mut self.collectors {
+            collector.sample(&mut ctx)?;
+        }
+
+        Ok(true)
+    }
+
+    // 结束会话：关闭仪表盘，输出汇总并导出所有数据
+    pub fn finish(mut self) -> Result<()> {
+        drop(self.dashboard.take());
+        for collector in &mut self.collectors {
+            collector.stop();
+        }
+
+        // 在结束前生成最终的线程时间序列图表
+        if self.options.thread && self.options.cpu && !self.thread_time_series.is_empty() {
+            console!("Program ending, generating final thread time series chart...");
+            if let Ok(layout) = OutputLayout::session(&self.options.package) {
+                self.export_threads(&layout);
+            }
+        }
+
+        // 创建时间戳目录
+        let layout = if let Ok(layout) = OutputLayout::session(&self.options.package) {
+            layout
+        } else {
+            console!("Warning: Could not create timestamp directory.");
+            if self.options.ci {
+                ci::report_error(
+                    &self.options.package,
+                    "Could not create the session directory",
+                );
+            }
+            return Ok(());
+        };
+
+        // 记录会话元数据，供 xcompare 按设备档位和版本对比
+        if let Err(e) = self.session_info.save(layout.session_dir()) {
+            console!("Failed to save session info: {}", e);
+        }
+
+        // 导出按 Tick 对齐的合并数据
+        if !self.table.is_empty() {
+            self.export_combined(&layout);
+        }
+
+        if self.options.cpu && self.cpu_data.timestamps.len() > 1 {
+            self.export_cpu(&layout);
+        }
+        if self.options.memory {
+            self.export_memory(&layout);
+        }
+        report_summary(&layout, &self.session_summary());
+        console!(
+            "Process Restarts: {}",
+            self.peaks.restarts.to_string().red()
+        );
+
+        let ctx = FinishContext {
+            package: &self.options.package,
+            pid: &self.sampler.process().pid,
+            layout: &layout,
+            open_gap: self.gap_start,
+        };
+        for collector in &mut self.collectors {
+            collector.finish(&ctx);
+        }
+        self.export_events(&layout);
+        if self.options.ci {
+            self.ci_report(&layout).print();
+        }
+
+        Ok(())
+    }
+
+    // --ci 退出时输出的 JSON 对象：峰值、预热后的统计、重启次数、超限和输出文件
+    fn ci_report(&self, layout: &OutputLayout) -> CiReport {
+        let mut report = CiReport::new(&self.options.package, Some(self.started));
+        report.device = utils::adb_serial();
+        report.samples = self.peaks.samples;
+        report.peaks = Peaks {
+            cpu_percent: self.peaks.cpu_time.map(|_| f64::from(self.peaks.cpu)),
+            cpu_at: self.peaks.cpu_time.map(|t| t.to_rfc3339()),
+            pss_kb: self.peaks.memory_time.map(|_| self.peaks.memory_kb),
+            pss_at: self.peaks.memory_time.map(|t| t.to_rfc3339()),
+        };
+        report.metrics = self.session_summary().into_iter().collect();
+        report.restart_count = self.peaks.restarts;
+        for collector in &self.collectors {
+            collector.ci_report(&mut report);
+        }
+        report.exit_code = threshold::exit_code();
+        report.set_session_dir(layout.session_dir());
+        report
+    }
+
+    // 各采集器记录的事件按时间合并写入 events.csv，崩溃和 ANR 单独计数
+    fn export_events(&self, layout: &OutputLayout) {
+        let mut events = Vec::new();
+        for collector in &self.collectors {
+            collector.events(&mut events);
+        }
+        if events.is_empty() {
+            return;
+        }
+        events.sort_by_key(|event| event.timestamp);
+        let crashes = events.iter().filter(|event| event.kind.is_crash()).count();
+        if crashes > 0 {
+            console!("Crash/ANR events: {}", crashes.to_string().red().bold());
+        }
+        match logcat::write_events_csv(layout.session_dir(), &events) {
+            Ok(path) => console!("✓ Events exported to CSV: {}", path.display()),
+            Err(e) => console!("Failed to export events: {}", e),
+        }
+    }
+
+    // 各采集器提供的 CPU 图表叠加内容，标记按时间排序
+    fn cpu_overlay(&self) -> CpuChartOverlay {
+        let mut overlay = CpuChartOverlay::default();
+        for collector in &self.collectors {
+            collector.cpu_overlay(&mut overlay);
+        }
+        overlay.markers.sort_by_key(|(time, _)| *time);
+        overlay
+    }
+
+    // 各采集器提供的内存图表叠加内容
+    fn memory_overlay(&self) -> MemoryChartOverlay {
+        let mut overlay = MemoryChartOverlay::default();
+        for collector in &self.collectors {
+            collector.memory_overlay(&mut overlay);
+        }
+        overlay
+    }
+
+    // 预热之后进程 CPU 和 Total PSS 的统计摘要，峰值之外用于判断长时间测试的整体水平
+    fn session_summary(&self) -> Vec<(String, Summary)> {
+        let steady = |t: &DateTime<Local>| self.warmup_end.is_none_or(|end| *t >= end);
+        let cpu: Vec<f64> = self
+            .cpu_data
+            .timestamps
+            .iter()
+            .zip(&self.cpu_data.process_cpu)
+            .filter(|(t, _)| steady(t))
+            .map(|(_, cpu)| f64::from(*cpu))
+            .collect();
+        let pss: Vec<f64> = self
+            .heap_history
+            .iter()
+            .filter(|(t, _)| steady(t))
+            .map(|(_, details)| details.total_pss as f64)
+            .collect();
+        let mut summaries: Vec<(String, Summary)> =
+            [("Process CPU (%)", cpu), ("Total PSS (KB)", pss)]
+                .into_iter()
+                .filter_map(|(metric, values)| {
+                    Summary::from_values(&values).map(|s| (metric.to_string(), s))
+                })
+                .collect();
+        for collector in &self.collectors {
+            collector.summaries(&mut summaries);
+        }
+        summaries
+    }
+
+    // 进入新的整小时且有足够的 CPU 数据时生成一次图表
+    fn hourly_cpu_chart(&mut self, tick: &Tick) {
+        let now = tick.timestamp;
+        let current_hour = now.hour() as i32;
+        if current_hour == self.last_chart_hour
+            || self.cpu_data.timestamps.is_empty()
+            || !self.options.cpu
+        {
+            return;
+        }
+        self.last_chart_hour = current_hour;
+
+        // 只有在收集了数据后才生成图表
+        if self.cpu_data.timestamps.len() <= 1 {
+            return;
+        }
+        // 计算整小时标记（格式如 14:00）
+        let hour_mark = format!("{}:00", now.hour());
+        console!(
+            "{} Generating scheduled CPU chart at {}...",
+            now.format("%H:%M:%S").to_string().blue(),
+            hour_mark.green()
+        );
+
+        let chart_path = match utils::generate_cpu_chart(
+            &self.options.package,
+            &self.cpu_data.timestamps,
+            &self.cpu_data.process_cpu,
+            &self.sampler.process().pid,
+            self.options.cpu_scale,
+            self.sampler.cores(),
+            &self.cpu_overlay(),
+        ) {
+            Ok(path) => path,
+            Err(e) => {
+                eprintln!("Error generating CPU chart: {}", e);
+                return;
+            }
+        };
+
+        if self.options.verbose {
+            // 为最新时间点的top线程创建CSV
+            if self.cpu_data.timestamps.back().is_some()
+                && self.cpu_data.top_threads.back().is_some()
+            {
+                console!("Thread data collection available");
+            }
+
+            // 仅打印图表生成信息，不写入日志
+            console!("Scheduled CPU chart generated: {}", chart_path.display());
+
+            // 添加CSV数据文件的信息
+            let csv_path = chart_path.with_extension("csv");
+            if csv_path.exists() {
+                console!("Scheduled CPU data exported to CSV: {}", csv_path.display());
+            }
+        }
+    }
+
+    // 打印 CPU 占用最高的线程，并加入线程时间序列
+    fn record_threads(&mut self, tick: &Tick, sample: &MonitorSample) {
+        let top_threads = &sample.threads;
+        console!("Top CPU threads:");
+
+        // 只显示最多5个线程，避免输出过多
+        let display_count = std::cmp::min(5, top_threads.len());
+        for (i, thread) in top_threads.iter().take(display_count).enumerate() {
+            // 标出渲染相关线程
+            let role = match ThreadRole::classify(thread, &sample.pid) {
+                ThreadRole::Other => String::new(),
+                role => format!(" [{}]", role.as_str()).magenta().to_string(),
+            };
+            console!(
+                "  {}: {} (TID: {}) - {:.1}%{}",
+                i + 1,
+                thread.name.cyan(),
+                thread.tid.yellow(),
+                thread.cpu_usage,
+                role
+            );
+        }
+
+        // 如果有更多线程，显示总数
+        if top_threads.len() > display_count {
+            console!(
+                "  ... and {} more threads",
+                top_threads.len() - display_count
+            );
+        }
+        console!(); // 空行分隔
+
+        for thread in top_threads {
+            let entry = self
+                .thread_time_series
+                .entry(thread.tid.clone())
+                .or_default();
+            entry.push(thread.clone());
+        }
+        self.thread_samples.push((*tick, top_threads.clone()));
+    }
+
+    fn export_combined(&self, layout: &OutputLayout) {
+        let combined_path = layout.file(&format!("{}_combined.csv", self.options.package));
+        match self.table.write_csv(&combined_path) {
+            Ok(()) => console!(
+                "✓ Combined data exported to CSV: {}",
+                combined_path.display()
+            ),
+            Err(e) => console!("Failed to export combined data to CSV: {}", e),
+        }
+    }
+
+    fn export_threads(&self, layout: &OutputLayout) {
+        let package = self.options.package.as_str();
+        let pid = self.sampler.process().pid.as_str();
+
+        // 创建thread子目录
+        let thread_dir = layout.dir(Category::Thread);
+        if !thread_dir.exists() {
+            if let Err(e) = std::fs::create_dir_all(&thread_dir) {
+                console!("Failed to create thread directory: {}", e);
+                return;
+            }
+            console!("Created thread directory: {}", thread_dir.display());
+        }
+
+        // 导出合并的宽表和长表
+        if self.options.format.csv() && self.options.thread_csv != ThreadCsv::PerThread {
+            match utils::export_thread_tables(
+                &thread_dir,
+                package,
+                &self.thread_samples,
+                self.options.top_threads,
+            ) {
+                Ok((wide_path, long_path)) => {
+                    console!("✓ Thread data exported to CSV: {}", wide_path.display());
+                    console!("✓ Thread data exported to CSV: {}", long_path.display());
+                }
+                Err(e) => console!("Failed to export consolidated thread data: {}", e),
+            }
+        }
+
+        // 导出最终的线程数据
+        if self.options.format.csv() && self.options.thread_csv != ThreadCsv::Consolidated {
+            match utils::export_thread_data_to_csv(
+                thread_dir.clone(),
+                pid,
+                &self
+                    .thread_time_series
+                    .values()
+                    .flat_map(|v| v.iter().cloned())
+                    .collect::<Vec<_>>(),
+                false,
+            ) {
+                Ok(filenames) => {
+                    console!(
+                        "✓ Final thread data exported to {} CSV files",
+                        filenames.len()
+                    );
+                }
+                Err(e) => {
+                    console!("Failed to export final thread data to CSV: {}", e);
+                }
+            }
+        }
+
+        // 生成最终的线程时间序列图表
+        match utils::generate_thread_time_series_chart(
+            thread_dir,
+            package,
+            pid,
+            &self.thread_time_series,
+        ) {
+            Ok(chart_filenames) => {
+                for chart_filename in chart_filenames {
+                    console!(
+                        "✓ Final thread time series chart generated: {}",
+                        chart_filename
+                    );
+                }
+            }
+            Err(e) => {
+                console!("Failed to generate final thread time series chart: {}", e);
+            }
+        }
+    }
+
+    // 程序结束时生成CPU图表
+    fn export_cpu(&self, layout: &OutputLayout) {
+        let package = self.options.package.as_str();
+
+        // 创建CPU子目录
+        let cpu_dir = layout.dir(Category::Cpu);
+        if !cpu_dir.exists() {
+            if let Err(e) = std::fs::create_dir_all(&cpu_dir) {
+                console!("Failed to create CPU directory: {}", e);
+                return;
+            }
+            console!("Created CPU directory: {}", cpu_dir.display());
+        }
+
+        console!(
+            "Peak CPU Usage: {} at {}",
+            format!("{:.1}%", self.peaks.cpu).red(),
+            self.peaks
+                .cpu_time
+                .unwrap_or_default()
+                .format("%Y-%m-%d %H:%M:%S")
+        );
+
+        // 生成CPU图表
+        match utils::generate_cpu_chart(
+            package,
+            &self.cpu_data.timestamps,
+            &self.cpu_data.process_cpu,
+            &self.sampler.process().pid,
+            self.options.cpu_scale,
+            self.sampler.cores(),
+            &self.cpu_overlay(),
+        ) {
+            Ok(path) => console!("✓ CPU chart generated: {}", path.display()),
+            Err(e) => {
+                console!("Failed to generate CPU chart: {}", e);
+                return;
+            }
+        }
+
+        // 导出CPU数据到CSV
+        let csv_path = cpu_dir.join(format!("{}_cpu_data.csv", package));
+        if self.options.format.csv()
+            && utils::export_cpu_data_to_csv(
+                &csv_path,
+                &self.cpu_data.timestamps,
+                &self.cpu_data.process_cpu,
+            )
+            .is_ok()
+        {
+            console!("✓ CPU data exported to CSV: {}", csv_path.display());
+        }
+    }
+
+    fn export_memory(&self, layout: &OutputLayout) {
+        console!(
+            "Peak Memory Usage: {} at {}",
+            format!("{} KB", self.peaks.memory_kb).red(),
+            self.peaks
+                .memory_time
+                .unwrap_or_default()
+                .format("%Y-%m-%d %H:%M:%S")
+        );
+
+        if let Some(start) = &self.start_ranking {
+            if let Some(end) = capture_memory_ranking(&self.options.package, "end") {
+                let diff = system::diff_rankings(start, &end);
+                console!("Top memory consumers at end (PSS, change since start):");
+                for (name, start_kb, end_kb) in diff.iter().take(5) {
+                    console!(
+                        "  {} {:.0} MB ({:+.0} MB)",
+                        if *name == self.options.package {
+                            name.green().to_string()
+                        } else {
+                            name.cyan().to_string()
+                        },
+                        end_kb / 1024.0,
+                        (end_kb - start_kb) / 1024.0
+                    );
+                }
+                let path = layout.category_file(Category::Memory, "system_memory_diff.csv");
+                match system::write_ranking_diff(&path, &diff) {
+                    Ok(()) => console!("✓ System memory diff exported to CSV: {}", path.display()),
+                    Err(e) => console!("Failed to export system memory diff: {}", e),
+                }
+            }
+        }
+
+        // 预热之后的 PSS 和 Java 堆做线性回归，持续增长视为疑似泄漏
+        let after_warmup = |f: fn(&MemoryDetails) -> u64| -> Vec<(DateTime<Local>, f64)> {
+            self.heap_history
+                .iter()
+                .filter(|(t, _)| self.warmup_end.is_none_or(|end| *t >= end))
+                .map(|(t, details)| (*t, f(details) as f64))
+                .collect()
+        };
+        let trends: Vec<(&str, Trend)> = [
+            ("Total PSS", after_warmup(|d| d.total_pss)),
+            ("Java Heap", after_warmup(|d| d.java_heap)),
+        ]
+        .into_iter()
+        .filter_map(|(metric, points)| Trend::fit(&points).map(|trend| (metric, trend)))
+        .collect();
+        for (metric, trend) in &trends {
+            if trend.is_probable_leak() {
+                console!(
+                    "{} {} growing {}",
+                    "PROBABLE LEAK:".red().bold(),
+                    metric,
+                    trend.describe().red()
+                );
+            } else {
+                console!("{} trend: {}", metric, trend.describe());
+            }
+        }
+        if !trends.is_empty() {
+            match leak::write_trends_csv(
+                &layout.dir(Category::Memory),
+                &self.options.package,
+                &trends,
+            ) {
+                Ok(path) => console!("✓ Memory trend exported to CSV: {}", path.display()),
+                Err(e) => console!("Failed to export memory trend: {}", e),
+            }
+        }
+
+        // 如果收集了足够的内存数据点，生成内存图表
+        if self.memory_data.timestamps.len() > 1 {
+            self.write_memory_charts(layout, &trends);
+        }
+
+        // Objects 段的首末计数，泄漏指标一直没有回到基线时告警
+        let mut objects = self.heap_history.iter().filter_map(|(_, d)| d.objects);
+        if let (Some(first), Some(last)) = (objects.next(), objects.next_back()) {
+            let changes: Vec<String> = first
+                .columns()
+                .iter()
+                .zip(last.columns())
+                .take(5)
+                .map(|((name, from), (_, to))| format!("{} {} -> {}", name, from, to))
+                .collect();
+            console!("Objects: {}", changes.join(", "));
+            for leak in memory::object_leaks(&self.heap_history, self.warmup_end) {
+                console!(
+                    "{} {} never returned to baseline ({} at start, at least {} in the second half, {} at end)",
+                    "POSSIBLE LEAK:".red().bold(),
+                    leak.name,
+                    leak.baseline,
+                    leak.floor.to_string().red(),
+                    leak.last
+                );
+            }
+        }
+
+        // 堆碎片：已提交但未使用的堆占比
+        if let Some((_, last)) = self.heap_history.last() {
+            for (name, heap) in [
+                ("Dalvik", last.dalvik_heap_detail),
+                ("Native", last.native_heap_detail),
+            ] {
+                if let Some(ratio) = heap.free_ratio() {
+                    console!(
+                        "{} Heap: size {} KB, alloc {} KB, free {} KB ({:.0}% of committed)",
+                        name,
+                        heap.size,
+                        heap.alloc,
+                        heap.free.to_string().yellow(),
+                        ratio * 100.0
+                    );
+                }
+            }
+        }
+        if self.heap_history.len() > 1 {
+            match memory::write_heap_charts(
+                &layout.dir(Category::Memory),
+                &self.options.package,
+                &self.heap_history,
+            ) {
+                Ok(paths) => {
+                    for path in paths {
+                        console!("✓ Heap chart generated: {}", path.display());
+                    }
+                }
+                Err(e) => console!("Failed to generate heap charts: {}", e),
+            }
+            match memory::write_smaps_chart(
+                &layout.dir(Category::Memory),
+                &self.options.package,
+                &self.heap_history,
+            ) {
+                Ok(Some(path)) => console!("✓ RSS/PSS/USS chart generated: {}", path.display()),
+                Ok(None) => {}
+                Err(e) => console!("Failed to generate RSS/PSS/USS chart: {}", e),
+            }
+            match memory::write_object_charts(
+                &layout.dir(Category::Memory),
+                &self.options.package,
+                &self.heap_history,
+            ) {
+                Ok(paths) => {
+                    for path in paths {
+                        console!("✓ Objects chart generated: {}", path.display());
+                    }
+                }
+                Err(e) => console!("Failed to generate objects charts: {}", e),
+            }
+        }
+    }
+
+    fn write_memory_charts(&self, layout: &OutputLayout, trends: &[(&str, Trend)]) {
+        // 创建memory子目录
+        let memory_dir = layout.dir(Category::Memory);
+        if !memory_dir.exists() {
+            if let Err(e) = std::fs::create_dir_all(&memory_dir) {
+                console!("Failed to create memory directory: {}", e);
+                return;
+            }
+            console!("Created memory directory: {}", memory_dir.display());
+        }
+
+        // 生成内存图表
+        let memory_charts = memory::generate_memory_charts(
+            &memory_dir,
+            &self.options.package,
+            &self.memory_data,
+            self.options.format.csv(),
+            trends,
+            &self.memory_overlay(),
+        );
+        if let Ok(chart_paths) = memory_charts {
+            for path in chart_paths {
+                if path.to_string_lossy().ends_with(".png") {
+                    console!("✓ Memory chart generated: {}", path.display());
+                } else if path.to_string_lossy().ends_with(".csv") {
+                    console!("✓ Memory data exported to CSV: {}", path.display());
+                }
+            }
+        } else {
+            console!("Failed to generate memory charts");
+        }
+    }
+}
+
+// 打印统计摘要并写出（覆盖）会话目录下的 summary.csv
+fn report_summary(layout: &OutputLayout, summaries: &[(String, Summary)]) {
+    if summaries.is_empty() {
+        return;
+    }
+    for (metric, summary) in summaries {
+        console!("{}: {}", metric, summary.describe());
+    }
+    let path = layout.file("summary.csv");
+    match stats::write_summary_csv(&path, summaries) {
+        Ok(()) => console!("✓ Summary exported to CSV: {}", path.display()),
+        Err(e) => console!("Failed to export summary: {}", e),
+    }
+}
+
+// 抓取整机进程内存排名并写入会话目录的 memory/system_memory_<label>.csv
+fn capture_memory_ranking(package: &str, label: &str) -> Option<MemoryRanking> {
+    let ranking = match system::capture_memory_ranking() {
+        Ok(ranking) => ranking,
+        Err(e) => {
+            console!("Failed to capture system memory ranking: {}", e);
+            return None;
+        }
+    };
+    let path = match OutputLayout::session(package) {
+        Ok(layout) => {
+            layout.category_file(Category::Memory, &format!("system_memory_{}.csv", label))
+        }
+        Err(e) => {
+            console!("Failed to create memory directory: {}", e);
+            return Some(ranking);
+        }
+    };
+    match system::write_memory_ranking(&path, &ranking) {
+        Ok(()) => console!(
+            "✓ System memory ranking ({} processes, {:.0} MB total PSS, via {}) exported: {}",
+            ranking.processes.len(),
+            ranking.total_pss_kb() / 1024.0,
+            ranking.source,
+            path.display()
+        ),
+        Err(e) => console!("Failed to export system memory ranking: {}", e),
+    }
+    Some(ranking)
+}
+
+// 按选项启动各采集器；启动提示按启动顺序输出，采样和导出按加入列表的顺序进行
+fn start_collectors(
+    options: &RecorderOptions,
+    session_info: &SessionInfo,
+    sampler: &Sampler,
+    warmup_end: Option<DateTime<Local>>,
+) -> Result<Vec<Box<dyn Collector>>> {
+    let package = options.package.as_str();
+    let pid = sampler.process().pid.as_str();
+
+    // 主线程响应探测，先测量 adb 往返开销作为基线
+    let probe = match options.probe {
+        Some(method) => Some(ProbeCollector::start(
+            method,
+            package,
+            options.probe_action.as_deref(),
+            options.stall_threshold,
+            warmup_end,
+        )?),
+        None => None,
+    };
+    let gpu = options.gpu.then(GpuCollector::start);
+    let battery_stats = options
+        .battery_stats
+        .then(|| BatteryStatsCollector::start(&session_info.uid));
+    // ODPM 电源轨，设备不支持时只提示
+    let rails = options
+        .power_rails
+        .then(|| RailCollector::start(warmup_end))
+        .flatten();
+    // 后台读取 logcat，检测应用的崩溃、ANR 和 native crash；回放时没有设备
+    let logcat = if replay::is_replaying() {
+        None
+    } else {
+        LogcatCollector::start(package, options.gc, options.strict_mode)
+    };
+    if options.gc && logcat.is_none() {
+        console!("{}", "GC events need logcat; --gc is ignored".yellow());
+    }
+    if options.strict_mode && logcat.is_none() {
+        console!(
+            "{}",
+            "StrictMode violations need logcat; --strict-mode is ignored".yellow()
+        );
+    }
+    // JSON 记录在采样时逐条写入
+    let jsonl = if options.format.json() {
+        Some(JsonlCollector::create(package, options.thread)?)
+    } else {
+        None
+    };
+    // native crash 或进程意外重启后拉取 tombstone
+    let tombstones = TombstoneCollector::start(package);
+    let frames = options.fps.then(|| {
+        FrameCollector::start(
+            package,
+            options.frame_source,
+            options.interval,
+            options
+                .min_fps
+                .map(|threshold| FpsDropDetector::new(threshold, options.min_fps_duration)),
+        )
+    });
+    let companions = (!options.also.is_empty())
+        .then(|| CompanionCollector::new(&options.also, options.cpu_scale, sampler.cores()));
+    let snapshots = options.snapshots.then(SnapshotCollector::start);
+    let markers = (options.markers || options.marker_pipe.is_some())
+        .then(|| MarkerCollector::start(options.markers, options.marker_pipe.as_deref()));
+    // perfetto trace 抓取，回放时没有设备
+    let perfetto = match &options.perfetto {
+        Some(config) if !replay::is_replaying() => Some(PerfettoCollector::start(
+            package,
+            config,
+            options.perfetto_duration,
+            options.perfetto_trigger,
+        )?),
+        _ => None,
+    };
+
+    // 按进程存活期分段，重启时关闭当前段
+    let mut collectors: Vec<Box<dyn Collector>> = vec![
+        Box::new(SegmentCollector::new(pid, warmup_end)),
+        Box::new(GapCollector::default()),
+        Box::new(ThreadCountCollector::new(options.thread)),
+    ];
+    // --thread 统计各状态的线程数，--blocked-threads 检测 D 状态，共用一次读取
+    if options.thread || options.blocked_threads.is_some() {
+        collectors.push(Box::new(ThreadStateCollector::new(options.thread)));
+    }
+    if options.thread {
+        collectors.push(Box::new(PriorityCollector::default()));
+    }
+    if options.max_cpu.is_some() || options.max_pss_kb.is_some() || options.max_restarts.is_some() {
+        collectors.push(Box::new(ThresholdCollector::new(
+            options.max_cpu,
+            options.max_pss_kb,
+            options.max_restarts,
+        )));
+    }
+    if options.memory {
+        collectors.push(Box::new(HeadroomCollector::default()));
+    }
+    if options.fds {
+        collectors.push(Box::new(FdCollector::new(package)));
+    }
+    if options.cpu_freq {
+        collectors.push(Box::new(CpuFreqCollector::new(options.verbose)));
+    }
+    if options.per_core {
+        collectors.push(Box::new(PerCoreCollector::default()));
+        if let Some(clusters) = ClusterCollector::start(options.verbose) {
+            collectors.push(Box::new(clusters));
+        }
+    }
+    if options.io {
+        collectors.push(Box::new(IoCollector::default()));
+    }
+    if options.ctx_switches {
+        collectors.push(Box::new(SwitchCollector::default()));
+    }
+    if options.page_faults {
+        collectors.push(Box::new(FaultCollector::default()));
+    }
+    if options.activity {
+        collectors.push(Box::new(ActivityCollector::new(options.object_growth)));
+    }
+    if options.jobs {
+        collectors.push(Box::new(JobCollector::default()));
+    }
+    collectors.push(Box::new(StatusCollector::default()));
+    // 使用 StatusCollector 本 Tick 读到的 oom_score_adj
+    if options.oom_adj {
+        collectors.push(Box::new(OomCollector::default()));
+    }
+    if let Some(jsonl) = jsonl {
+        collectors.push(Box::new(jsonl));
+    }
+    if options.tui {
+        collectors.push(Box::new(DashboardPublisher));
+    }
+    if options.prometheus {
+        collectors.push(Box::new(MetricsPublisher));
+    }
+    if let Some(snapshots) = snapshots {
+        collectors.push(Box::new(snapshots));
+    }
+    if let Some(markers) = markers {
+        collectors.push(Box::new(markers));
+    }
+    if let Some(gpu) = gpu {
+        collectors.push(Box::new(gpu));
+    }
+    if let Some(frames) = frames {
+        collectors.push(Box::new(frames));
+    }
+    if options.thermal {
+        collectors.push(Box::new(ThermalCollector::default()));
+    }
+    // 由 --cpu-freq 的频率上限或 --thermal 的状态判断限频区间
+    if options.cpu_freq || options.thermal {
+        collectors.push(Box::new(ThrottleCollector::default()));
+    }
+    // logcat 事件先于 tombstone 处理，native crash 在同一 Tick 拉取
+    if let Some(logcat) = logcat {
+        collectors.push(Box::new(logcat));
+        if options.gc {
+            collectors.push(Box::new(GcCollector::default()));
+        }
+    }
+    collectors.push(Box::new(tombstones));
+    // dumpsys dropbox 中的崩溃、ANR 和 WTF，回放时没有设备
+    if !replay::is_replaying() {
+        collectors.push(Box::new(DropboxCollector::start(package)));
+    }
+    if let Some(scenario) = &options.scenario {
+        collectors.push(Box::new(ScenarioCollector::start(scenario, package)));
+    }
+    if options.binder {
+        collectors.push(Box::new(BinderCollector::new(&session_info.uid)));
+    }
+    if options.wakelocks {
+        collectors.push(Box::new(WakelockCollector::new(&session_info.uid)));
+    }
+    if options.alarms {
+        collectors.push(Box::new(AlarmCollector::new(options.alarm_interval)));
+    }
+    if options.battery {
+        collectors.push(Box::new(BatteryCollector::default()));
+    }
+    if let Some(battery_stats) = battery_stats {
+        collectors.push(Box::new(battery_stats));
+    }
+    if let Some(rails) = rails {
+        collectors.push(Box::new(rails));
+    }
+    if let Some(companions) = companions {
+        collectors.push(Box::new(companions));
+    }
+    if options.all_processes || options.children {
+        collectors.push(Box::new(ProcessGroupCollector::new(
+            package,
+            options.cpu_scale,
+            sampler.cores(),
+            options.all_processes,
+            options.children,
+        )));
+    }
+    if let Some(probe) = probe {
+        collectors.push(Box::new(probe));
+    }
+    if let Some(threshold) = options.blocked_threads {
+        collectors.push(Box::new(BlockedThreadCollector::new(threshold)));
+    }
+    if let Some(perfetto) = perfetto {
+        collectors.push(Box::new(perfetto));
+    }
+    if let Some(threshold) = options.profile_on_spike {
+        collectors.push(Box::new(ProfileCollector::new(
+            package,
+            threshold,
+            options.profile_duration,
+            options.flamegraph,
+        )));
+    }
+    // --tui 中随时可以按 t 抓取
+    if options.atrace || options.tui {
+        collectors.push(Box::new(AtraceCollector::new(
+            package,
+            &options.atrace_categories,
+            options.atrace_duration,
+            options.atrace,
+        )));
+    }
+    // 网页仪表盘发送本 Tick 的整行数据，放在最后
+    if options.serve {
+        collectors.push(Box::new(WebPublisher));
+    }
+    Ok(collectors)
+}
+
+fn format_current_peaks(peaks: &MonitorPeaks) -> String {
+    let timestamp = Local::now().format("%H:%M:%S").to_string();
+    let mut lines = Vec::new();
+    if let Some(time) = peaks.cpu_time {
+        lines.push(format!(
+            "[{}] Peak CPU: {}% at {}",
+            timestamp.blue(),
+            format!("{:.1}", peaks.cpu).red(),
+            time.format("%H:%M:%S").to_string().blue()
+        ));
+    }
+    if let Some(time) = peaks.memory_time {
+        lines.push(format!(
+            "[{}] Peak Memory: {} at {}",
+            timestamp.blue(),
+            format!("{} KB", peaks.memory_kb).red(),
+            time.format("%H:%M:%S").to_string().blue()
+        ));
+    }
+    lines.join("\n")
+}
@@ -0,0 +1,922 @@
+use crate::activity::{self, ActivityTracker};
+use crate::chart::{self, LineSeriesData, TimeSeriesData};
+use crate::cpu::CpuScale;
+use crate::jobs::{self, JobTracker};
+use crate::layout::Category;
+use crate::logcat;
+use crate::percore;
+use crate::regression;
+use crate::report::HtmlReport;
+use crate::schedule;
+use crate::session::SessionInfo;
+use crate::stats::Summary;
+use crate::throttle;
+use crate::utils::{self, CpuChartOverlay};
+use crate::wakelock;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use clap::ValueEnum;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+pub use crate::tick::CSV_TIMESTAMP_FORMAT;
+
+// 标识类的列，不作为指标绘图或统计
+const IGNORED_COLUMNS: [&str; 6] = ["IntendedVsync", "Launch", "PID", "PPID", "Tick", "TID"];
+
+// 逐次采样的表格以采样时间或重复测量（启动、安装）的序号开头；
+// 汇总、区间、前后对比等表格的第一列不同，不绘图也不参与统计
+const SAMPLE_KEY_COLUMNS: [&str; 3] = ["Timestamp", "Launch", "Run"];
+
+// 长表每个时间戳有多行（如每线程一行），不适合直接绘图和统计
+fn is_long_format(path: &Path) -> bool {
+    file_stem(path).ends_with("_long")
+}
+
+// 从磁盘读取的一个 CSV 文件
+#[derive(Debug, Clone)]
+pub struct CsvTable {
+    pub path: PathBuf,
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+pub fn read_csv(path: &Path) -> Result<CsvTable> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut lines = content.lines().filter(|line| !line.trim().is_empty());
+    let headers = lines.next().map(split_csv_line).unwrap_or_default();
+    let rows = lines.map(split_csv_line).collect();
+
+    Ok(CsvTable {
+        path: path.to_path_buf(),
+        headers,
+        rows,
+    })
+}
+
+// 按逗号拆分一行，双引号内的逗号不拆分，"" 表示字段中的一个引号
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field).trim().to_string()),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field.trim().to_string());
+    fields
+}
+
+pub fn parse_timestamp(text: &str) -> Option<DateTime<Local>> {
+    let naive = NaiveDateTime::parse_from_str(text, CSV_TIMESTAMP_FORMAT).ok()?;
+    Local.from_local_datetime(&naive).single()
+}
+
+impl CsvTable {
+    pub fn is_time_series(&self) -> bool {
+        self.headers.first().map(String::as_str) == Some("Timestamp")
+    }
+
+    // 每行一次采样，且至少有一个数值指标列
+    pub fn is_samples(&self) -> bool {
+        self.headers
+            .first()
+            .is_some_and(|key| SAMPLE_KEY_COLUMNS.contains(&key.as_str()))
+            && !self.metric_columns().is_empty()
+    }
+
+    // 时间戳早于预热结束时间的行
+    fn is_warmup_row(&self, row: &[String], warmup_end: Option<DateTime<Local>>) -> bool {
+        match warmup_end {
+            Some(end) if self.is_time_series() => row
+                .first()
+                .and_then(|t| parse_timestamp(t))
+                .is_some_and(|t| t < end),
+            _ => false,
+        }
+    }
+
+    // 所有可解析为数值的指标列索引
+    fn metric_columns(&self) -> Vec<usize> {
+        (1..self.headers.len())
+            .filter(|&i| !IGNORED_COLUMNS.contains(&self.headers[i].as_str()))
+            .filter(|&i| {
+                self.rows
+                    .iter()
+                    .filter_map(|row| row.get(i))
+                    .filter(|v| !v.is_empty())
+                    .all(|v| v.parse::<f64>().is_ok())
+            })
+            .filter(|&i| {
+                self.rows
+                    .iter()
+                    .any(|row| row.get(i).is_some_and(|v| !v.is_empty()))
+            })
+            .collect()
+    }
+
+    pub fn time_series(&self) -> Vec<TimeSeriesData> {
+        let timestamps: Vec<Option<DateTime<Local>>> = self
+            .rows
+            .iter()
+            .map(|row| row.first().and_then(|t| parse_timestamp(t)))
+            .collect();
+
+        self.metric_columns()
+            .into_iter()
+            .map(|i| {
+                let points = self
+                    .rows
+                    .iter()
+                    .zip(&timestamps)
+                    .filter_map(|(row, t)| Some(((*t)?, row.get(i)?.parse().ok()?)))
+                    .collect();
+                TimeSeriesData::new(self.headers[i].clone(), points)
+            })
+            .collect()
+    }
+
+    // 非时间序列表格以第一列（如启动序号）为X轴
+    pub fn indexed_series(&self) -> Vec<LineSeriesData> {
+        self.metric_columns()
+            .into_iter()
+            .map(|i| {
+                let points = self
+                    .rows
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(n, row)| {
+                        let x = row.first().and_then(|v| v.parse().ok()).unwrap_or(n as f64);
+                        Some((x, row.get(i)?.parse().ok()?))
+                    })
+                    .collect();
+                LineSeriesData::new(self.headers[i].clone(), points)
+            })
+            .collect()
+    }
+}
+
+// 递归查找会话目录中的所有 CSV 文件（跳过报告输出目录）
+fn find_csv_files(dir: &Path, skip: &[&Path], files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if skip.contains(&path.as_path()) {
+            continue;
+        }
+        if path.is_dir() {
+            find_csv_files(&path, skip, files)?;
+        } else if path.extension().is_some_and(|e| e == "csv") && !is_long_format(&path) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn relative_name(path: &Path, base: &Path) -> String {
+    path.strip_prefix(base)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .to_string()
+}
+
+// 线程 CSV 为每线程一个文件，合并为一组曲线：thread_<name>_<tid>_<pid>.csv
+fn thread_series(tables: &[CsvTable]) -> Vec<TimeSeriesData> {
+    let mut series: Vec<TimeSeriesData> = tables
+        .iter()
+        .filter_map(|table| {
+            let stem = file_stem(&table.path);
+            let label = stem
+                .strip_prefix("thread_")
+                .and_then(|s| s.rsplit_once('_'))
+                .map(|(name_tid, _pid)| name_tid.to_string())
+                .unwrap_or(stem);
+            let mut s = table.time_series().into_iter().next()?;
+            s.label = label;
+            Some(s)
+        })
+        .collect();
+
+    // 按总CPU排序，最热的线程优先
+    let total = |s: &TimeSeriesData| s.points.iter().map(|&(_, v)| v).sum::<f64>();
+    series.sort_by(|a, b| {
+        total(b)
+            .partial_cmp(&total(a))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    series
+}
+
+fn summary_row(label: &str, summary: &Summary) -> Vec<String> {
+    vec![
+        label.to_string(),
+        summary.count.to_string(),
+        format!("{:.2}", summary.min),
+        format!("{:.2}", summary.mean),
+        format!("{:.2}", summary.median),
+        format!("{:.2}", summary.p90),
+        format!("{:.2}", summary.p95),
+        format!("{:.2}", summary.p99),
+        format!("{:.2}", summary.max),
+    ]
+}
+
+const SUMMARY_HEADERS: [&str; 9] = [
+    "Metric", "Count", "Min", "Mean", "Median", "P90", "P95", "P99", "Max",
+];
+
+// 时间窗口的一端：HH:MM[:SS] 为一天中的时刻，按会话开始时间定位到具体日期；
+// 也可以写完整的 YYYY-MM-DD HH:MM[:SS]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WindowBound {
+    pub date: Option<NaiveDate>,
+    pub time: NaiveTime,
+    // 只写到分钟，作为结束时间时包含这一整分钟
+    pub minute_only: bool,
+}
+
+impl WindowBound {
+    pub fn parse(text: &str) -> Result<Self> {
+        let text = text.trim();
+        let (date, time) = match text.split_once(' ') {
+            Some((date, time)) => (
+                Some(
+                    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                        .map_err(|_| anyhow::format_err!("Invalid date: {}", date))?,
+                ),
+                time.trim(),
+            ),
+            None => (None, text),
+        };
+        Ok(Self {
+            date,
+            time: schedule::parse_time(time)?,
+            minute_only: time.len() <= "HH:MM".len(),
+        })
+    }
+
+    // 不早于 after 的第一个对应时刻
+    fn resolve(&self, after: NaiveDateTime) -> NaiveDateTime {
+        match self.date {
+            Some(date) => date.and_time(self.time),
+            None => {
+                let candidate = after.date().and_time(self.time);
+                if candidate < after {
+                    candidate + Duration::days(1)
+                } else {
+                    candidate
+                }
+            }
+        }
+    }
+
+    fn label(&self) -> String {
+        match self.date {
+            Some(date) => date.and_time(self.time).format("%Y%m%d%H%M").to_string(),
+            None => self.time.format("%H%M").to_string(),
+        }
+    }
+}
+
+// 重新生成报告时选取的时间窗口；from 晚于 to 的时刻时表示跨越午夜
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TimeWindow {
+    pub from: Option<WindowBound>,
+    pub to: Option<WindowBound>,
+}
+
+// 定位到会话日期后的窗口，结束时间不包含
+#[derive(Debug, Clone, Copy)]
+struct ResolvedWindow {
+    from: Option<NaiveDateTime>,
+    until: Option<NaiveDateTime>,
+}
+
+impl ResolvedWindow {
+    fn contains(&self, timestamp: DateTime<Local>) -> bool {
+        let time = timestamp.naive_local();
+        self.from.is_none_or(|from| time >= from) && self.until.is_none_or(|until| time < until)
+    }
+}
+
+impl TimeWindow {
+    pub fn parse(from: Option<&str>, to: Option<&str>) -> Result<Self> {
+        Ok(Self {
+            from: from.map(WindowBound::parse).transpose()?,
+            to: to.map(WindowBound::parse).transpose()?,
+        })
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.from.is_none() && self.to.is_none()
+    }
+
+    // 时刻取会话开始后第一次出现的那一天，to 取 from 之后第一次出现的时刻，
+    // 超过 24 小时的会话不会把每天的同一时段合并在一起
+    fn resolve(&self, session_start: NaiveDateTime) -> ResolvedWindow {
+        let from = self.from.map(|from| from.resolve(session_start));
+        let until = self.to.map(|to| {
+            let end = to.resolve(from.unwrap_or(session_start));
+            end + if to.minute_only {
+                Duration::minutes(1)
+            } else {
+                Duration::seconds(1)
+            }
+        });
+        ResolvedWindow { from, until }
+    }
+
+    // 用于报告标题和目录名，如 1405-1420
+    pub fn label(&self) -> String {
+        let format = |bound: Option<WindowBound>| bound.map(|b| b.label());
+        format!(
+            "{}-{}",
+            format(self.from).unwrap_or_else(|| "start".to_string()),
+            format(self.to).unwrap_or_else(|| "end".to_string())
+        )
+    }
+}
+
+impl CsvTable {
+    // 第一行样本的时间
+    fn first_timestamp(&self) -> Option<DateTime<Local>> {
+        if !self.is_time_series() {
+            return None;
+        }
+        self.rows
+            .iter()
+            .find_map(|row| row.first().and_then(|t| parse_timestamp(t)))
+    }
+
+    // 只保留窗口内的行，非时间序列表格不受影响
+    fn retain_window(&mut self, window: &ResolvedWindow) {
+        if !self.is_time_series() {
+            return;
+        }
+        self.rows.retain(|row| {
+            row.first()
+                .and_then(|t| parse_timestamp(t))
+                .is_some_and(|t| window.contains(t))
+        });
+    }
+}
+
+// 会话开始时间：session.json 中的记录，旧会话没有时取 CSV 中最早的样本
+fn session_start(session_dir: &Path, csv_files: &[PathBuf]) -> Option<NaiveDateTime> {
+    if let Some(started) = SessionInfo::load(session_dir)
+        .ok()
+        .flatten()
+        .and_then(|info| parse_timestamp(&info.started))
+    {
+        return Some(started.naive_local());
+    }
+    csv_files
+        .iter()
+        .filter_map(|path| read_csv(path).ok()?.first_timestamp())
+        .min()
+        .map(|t| t.naive_local())
+}
+
+// session.json 中记录的预热结束时间
+fn session_warmup_end(session_dir: &Path) -> Option<DateTime<Local>> {
+    let info = SessionInfo::load(session_dir).ok()??;
+    parse_timestamp(&info.warmup_end)
+}
+
+// 去掉预热区间的样本后的数值
+fn steady_values(
+    points: &[(DateTime<Local>, f64)],
+    warmup_end: Option<DateTime<Local>>,
+) -> Vec<f64> {
+    points
+        .iter()
+        .filter(|(t, _)| warmup_end.is_none_or(|end| *t >= end))
+        .map(|&(_, v)| v)
+        .collect()
+}
+
+// 报告的默认输出目录；时间窗口的报告放在 report 下的子目录中，不覆盖完整报告
+pub fn default_output_dir(session_dir: &Path, window: &TimeWindow) -> PathBuf {
+    if window.is_full() {
+        session_dir.join("report")
+    } else {
+        session_dir
+            .join("report")
+            .join(format!("window_{}", window.label()))
+    }
+}
+
+// 根据已保存的会话 CSV 重新生成图表、统计摘要和 HTML 报告
+// window 不为全量时只使用窗口内的数据
+pub fn regenerate_session(
+    session_dir: &Path,
+    output_dir: &Path,
+    window: &TimeWindow,
+) -> Result<Vec<PathBuf>> {
+    let mut csv_files = Vec::new();
+    find_csv_files(
+        session_dir,
+        &[output_dir, &session_dir.join("report")],
+        &mut csv_files,
+    )?;
+    csv_files.sort();
+    if csv_files.is_empty() {
+        anyhow::bail!("No CSV files found in {}", session_dir.display());
+    }
+
+    std::fs::create_dir_all(output_dir)?;
+    let mut generated = Vec::new();
+
+    let resolved = if window.is_full() {
+        None
+    } else {
+        let start = session_start(session_dir, &csv_files)
+            .context("No timestamps found to place the time window")?;
+        Some(window.resolve(start))
+    };
+
+    // 预热区间在图表中加底纹，并从统计中排除
+    let warmup_end = session_warmup_end(session_dir);
+    chart::set_warmup_end(warmup_end);
+    // 监控时插入的用户标记
+    chart::set_user_markers(logcat::read_markers(session_dir));
+
+    let mut title = format!(
+        "Session Report - {}",
+        relative_name(session_dir, &utils::output_root())
+    );
+    if !window.is_full() {
+        title = format!("{} ({})", title, window.label());
+    }
+    let mut report = HtmlReport::new(&title);
+    let summary_path = output_dir.join("summary.csv");
+    let mut summary_file = std::fs::File::create(&summary_path)?;
+    writeln!(summary_file, "Source,Metric,{}", Summary::CSV_HEADER)?;
+
+    // wakelock 区间单独画成时间线
+    let (wakelock_files, csv_files): (Vec<PathBuf>, Vec<PathBuf>) = csv_files
+        .into_iter()
+        .partition(|p| wakelock::is_intervals_csv(p));
+    let (thread_files, other_files): (Vec<PathBuf>, Vec<PathBuf>) = csv_files
+        .into_iter()
+        .partition(|p| file_stem(p).starts_with("thread_"));
+
+    for path in &other_files {
+        let mut table = match read_csv(path) {
+            Ok(table) => table,
+            Err(e) => {
+                console!("Skipping {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        if !table.is_samples() {
+            continue;
+        }
+        if let Some(resolved) = &resolved {
+            table.retain_window(resolved);
+        }
+        if table.rows.is_empty() {
+            continue;
+        }
+        let source = relative_name(path, session_dir);
+        let chart_path = output_dir.join(format!("{}.png", file_stem(path)));
+
+        let (values, chart_result): (Vec<(String, Vec<f64>)>, Result<()>) = if table
+            .is_time_series()
+        {
+            let series = table.time_series();
+            let values = series
+                .iter()
+                .map(|s| (s.label.clone(), steady_values(&s.points, warmup_end)))
+                .collect();
+            let result = chart::draw_time_series_chart(&chart_path, &source, "Value", &series);
+            (values, result)
+        } else {
+            let series = table.indexed_series();
+            let values = series
+                .iter()
+                .map(|s| (s.label.clone(), s.points.iter().map(|&(_, v)| v).collect()))
+                .collect();
+            let x_desc = table.headers.first().cloned().unwrap_or_default();
+            let result = chart::draw_line_chart(&chart_path, &source, &x_desc, "Value", &series);
+            (values, result)
+        };
+
+        report.add_heading(&source);
+        let rows: Vec<Vec<String>> = values
+            .iter()
+            .filter_map(|(label, v)| {
+                let summary = Summary::from_values(v)?;
+                writeln!(
+                    summary_file,
+                    "{},{},{}",
+                    source,
+                    label,
+                    summary.to_csv_row()
+                )
+                .ok()?;
+                Some(summary_row(label, &summary))
+            })
+            .collect();
+        report.add_table(&SUMMARY_HEADERS, &rows);
+
+        match chart_result {
+            Ok(()) => {
+                report.add_image(&relative_name(&chart_path, output_dir), &source);
+                generated.push(chart_path);
+            }
+            Err(e) => console!("Failed to chart {}: {}", source, e),
+        }
+    }
+
+    // 线程数据合并成一张图
+    let thread_tables: Vec<CsvTable> = thread_files
+        .iter()
+        .filter_map(|p| read_csv(p).ok())
+        .filter(CsvTable::is_samples)
+        .map(|mut table| {
+            if let Some(resolved) = &resolved {
+                table.retain_window(resolved);
+            }
+            table
+        })
+        .collect();
+    let threads = thread_series(&thread_tables);
+    if !threads.is_empty() {
+        report.add_heading("Threads");
+        let rows: Vec<Vec<String>> = threads
+            .iter()
+            .filter_map(|s| {
+                let values = steady_values(&s.points, warmup_end);
+                let summary = Summary::from_values(&values)?;
+                writeln!(summary_file, "thread,{},{}", s.label, summary.to_csv_row()).ok()?;
+                Some(summary_row(&s.label, &summary))
+            })
+            .collect();
+        report.add_table(&SUMMARY_HEADERS, &rows);
+
+        // 每页最多一个调色板的线程，按总CPU分页，所有活跃线程都会出现在图中
+        let pages: Vec<&[TimeSeriesData]> = threads.chunks(chart::theme().series.len()).collect();
+        for (idx, page) in pages.iter().enumerate() {
+            let (file_name, title) = if pages.len() == 1 {
+                (
+                    "thread_time_series.png".to_string(),
+                    "Thread CPU".to_string(),
+                )
+            } else {
+                (
+                    format!("thread_time_series_page{}.png", idx + 1),
+                    format!("Thread CPU - page {}/{}", idx + 1, pages.len()),
+                )
+            };
+            let chart_path = output_dir.join(file_name);
+            match chart::draw_time_series_chart(&chart_path, &title, "CPU Usage (%)", page) {
+                Ok(()) => {
+                    report.add_image(&relative_name(&chart_path, output_dir), &title);
+                    generated.push(chart_path);
+                }
+                Err(e) => console!("Failed to chart threads: {}", e),
+            }
+        }
+    }
+
+    for path in &wakelock_files {
+        let intervals: Vec<wakelock::WakelockInterval> = match wakelock::read_intervals_csv(path) {
+            Ok(intervals) => intervals
+                .into_iter()
+                .filter(|i| {
+                    resolved.is_none_or(|w| w.contains(i.acquired) || w.contains(i.released))
+                })
+                .collect(),
+            Err(e) => {
+                console!("Skipping {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        if intervals.is_empty() {
+            continue;
+        }
+        let source = relative_name(path, session_dir);
+        report.add_heading(&source);
+        let rows: Vec<Vec<String>> = wakelock::tag_stats(&intervals)
+            .iter()
+            .map(|stats| {
+                vec![
+                    stats.tag.clone(),
+                    stats.level.clone(),
+                    stats.acquires.to_string(),
+                    format!("{:.1}", stats.total_secs),
+                    format!("{:.1}", stats.longest_secs),
+                ]
+            })
+            .collect();
+        report.add_table(
+            &[
+                "Tag",
+                "Level",
+                "Acquires",
+                "Total Hold (s)",
+                "Longest Hold (s)",
+            ],
+            &rows,
+        );
+        let chart_path = output_dir.join(format!("{}.png", file_stem(path)));
+        match wakelock::draw_timeline(&chart_path, "Wakelock Timeline", &intervals) {
+            Ok(()) => {
+                report.add_image(&relative_name(&chart_path, output_dir), "Wakelock Timeline");
+                generated.push(chart_path);
+            }
+            Err(e) => console!("Failed to chart {}: {}", source, e),
+        }
+    }
+
+    summary_file.flush()?;
+    generated.push(summary_path);
+
+    let report_path = output_dir.join("report.html");
+    report.write(&report_path)?;
+    generated.push(report_path);
+
+    Ok(generated)
+}
+
+// 会话中每个指标列的统计摘要，用于跨会话对比
+// 跳过逐线程 CSV（线程 ID 在会话间不同）、摘要等非采样表格和 report 输出目录
+pub fn session_metrics(session_dir: &Path) -> Result<Vec<(String, String, Summary)>> {
+    let mut csv_files = Vec::new();
+    find_csv_files(session_dir, &[&session_dir.join("report")], &mut csv_files)?;
+    csv_files.sort();
+
+    let warmup_end = session_warmup_end(session_dir);
+    let mut metrics = Vec::new();
+    for path in &csv_files {
+        let stem = file_stem(path);
+        // 宽表的线程列名含 TID，同样无法跨会话对比
+        if stem.starts_with("thread_") || stem.ends_with("_threads_wide") {
+            continue;
+        }
+        let Ok(table) = read_csv(path) else {
+            continue;
+        };
+        if !table.is_samples() {
+            continue;
+        }
+        let source = relative_name(path, session_dir);
+        for i in table.metric_columns() {
+            let values: Vec<f64> = table
+                .rows
+                .iter()
+                .filter(|row| !table.is_warmup_row(row, warmup_end))
+                .filter_map(|row| row.get(i)?.parse().ok())
+                .collect();
+            if let Some(summary) = Summary::from_values(&values) {
+                metrics.push((source.clone(), table.headers[i].clone(), summary));
+            }
+        }
+    }
+    Ok(metrics)
+}
+
+// 根据合并 CSV 按监控时的样式重画进程 CPU 图，叠加 --activity 的底纹、限频区间和每核心占用
+// 会话没有合并 CSV 或其中没有 CPU 列时返回 None
+pub fn redraw_cpu_chart(
+    session_dir: &Path,
+    output_dir: &Path,
+    window: &TimeWindow,
+) -> Result<Option<PathBuf>> {
+    let Ok(combined_path) = regression::combined_csv(session_dir) else {
+        return Ok(None);
+    };
+    let mut table = read_csv(&combined_path)?;
+    let Some(index) = table.headers.iter().position(|h| h == "CPU Usage (%)") else {
+        return Ok(None);
+    };
+    if !window.is_full() {
+        let start = session_start(session_dir, std::slice::from_ref(&combined_path))
+            .context("No timestamps found to place the time window")?;
+        table.retain_window(&window.resolve(start));
+    }
+    let points: Vec<(DateTime<Local>, f32)> = table
+        .rows
+        .iter()
+        .filter_map(|row| {
+            Some((
+                parse_timestamp(row.first()?)?,
+                row.get(index)?.parse().ok()?,
+            ))
+        })
+        .collect();
+    let Some(&(end, _)) = points.last() else {
+        return Ok(None);
+    };
+
+    let info = SessionInfo::load(session_dir)?;
+    let package = match &info {
+        Some(info) => info.package.clone(),
+        None => file_stem(&combined_path).replace("_combined", ""),
+    };
+    let scale = info
+        .as_ref()
+        .and_then(|info| CpuScale::from_str(&info.cpu_scale, true).ok())
+        .unwrap_or_default();
+    let cores = info.as_ref().map_or(0, |info| info.device.cpu_cores);
+
+    let spans_path = session_dir
+        .join(Category::Process.dir_name())
+        .join(format!("{}_activities.csv", package));
+    let jobs_path = session_dir
+        .join(Category::Process.dir_name())
+        .join(format!("{}_jobs.csv", package));
+    let periods_path = session_dir
+        .join(Category::Thermal.dir_name())
+        .join(format!("{}_throttling.csv", package));
+    let per_core_path = session_dir
+        .join(Category::Cpu.dir_name())
+        .join(format!("{}_per_core.csv", package));
+    let overlay = CpuChartOverlay {
+        throttled: throttle::read_periods_csv(&periods_path)
+            .map(|periods| {
+                periods
+                    .iter()
+                    .map(|p| (p.start, p.end.unwrap_or(end)))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        bands: activity::read_spans_csv(&spans_path)
+            .map(|spans| {
+                let mut tracker = ActivityTracker::from_spans(spans);
+                tracker.finish(end);
+                tracker.bands(end)
+            })
+            .unwrap_or_default()
+            .into_iter()
+            .chain(
+                jobs::read_runs_csv(&jobs_path)
+                    .map(|runs| JobTracker::from_runs(runs).bands(end))
+                    .unwrap_or_default(),
+            )
+            .collect(),
+        per_core: percore::read_per_core_csv(&per_core_path).unwrap_or_default(),
+        ..Default::default()
+    };
+
+    std::fs::create_dir_all(output_dir)?;
+    let path = output_dir.join(chart::stamped_file_name(&format!(
+        "{}_cpu_chart.png",
+        package
+    )));
+    utils::draw_cpu_chart(&path, &package, &points, "", scale, cores, &overlay)?;
+    Ok(Some(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_csv_line_honours_quotes() {
+        assert_eq!(split_csv_line("a, b,c"), ["a", "b", "c"]);
+        assert_eq!(
+            split_csv_line(r#"2024-01-01 10:00:00,"Binder:1234_1, pool","say ""hi""",3"#),
+            [
+                "2024-01-01 10:00:00",
+                "Binder:1234_1, pool",
+                r#"say "hi""#,
+                "3"
+            ]
+        );
+        assert_eq!(split_csv_line("a,,"), ["a", "", ""]);
+    }
+
+    fn at(text: &str) -> DateTime<Local> {
+        parse_timestamp(text).unwrap()
+    }
+
+    fn window(from: Option<&str>, to: Option<&str>) -> ResolvedWindow {
+        let start = at("2026-10-15 13:00:00").naive_local();
+        TimeWindow::parse(from, to).unwrap().resolve(start)
+    }
+
+    #[test]
+    fn time_window_contains() {
+        let w = window(Some("14:05"), Some("14:20"));
+        assert!(w.contains(at("2026-10-15 14:05:00")));
+        assert!(w.contains(at("2026-10-15 14:12:30")));
+        // 只写到分钟时包含这一整分钟
+        assert!(w.contains(at("2026-10-15 14:20:59")));
+        assert!(!w.contains(at("2026-10-15 14:21:00")));
+        assert!(!w.contains(at("2026-10-15 09:00:00")));
+        // 超过一天的会话只取第一次出现的时段
+        assert!(!w.contains(at("2026-10-16 14:10:00")));
+
+        let w = window(Some("14:05"), Some("14:20:00"));
+        assert!(w.contains(at("2026-10-15 14:20:00")));
+        assert!(!w.contains(at("2026-10-15 14:20:01")));
+    }
+
+    #[test]
+    fn time_window_across_midnight() {
+        let w = window(Some("23:00"), Some("01:00"));
+        assert!(w.contains(at("2026-10-15 23:30:00")));
+        assert!(w.contains(at("2026-10-16 00:30:00")));
+        assert!(!w.contains(at("2026-10-15 12:00:00")));
+        assert!(!w.contains(at("2026-10-16 23:30:00")));
+    }
+
+    #[test]
+    fn time_window_full_dates() {
+        let w = window(Some("2026-10-16 14:05"), Some("2026-10-16 14:20"));
+        assert!(!w.contains(at("2026-10-15 14:10:00")));
+        assert!(w.contains(at("2026-10-16 14:10:00")));
+        // 会话开始前的时刻落到第二天
+        let w = window(Some("09:00"), None);
+        assert!(!w.contains(at("2026-10-15 09:30:00")));
+        assert!(w.contains(at("2026-10-16 09:30:00")));
+        assert!(TimeWindow::parse(Some("2026-13-01 10:00"), None).is_err());
+    }
+
+    #[test]
+    fn time_window_open_ends() {
+        assert!(TimeWindow::default().is_full());
+        assert!(window(None, None).contains(at("2026-10-15 03:00:00")));
+        assert!(window(Some("14:00"), None).contains(at("2026-10-17 23:59:59")));
+        assert!(!window(Some("14:00"), None).contains(at("2026-10-15 13:59:59")));
+        assert!(window(None, Some("14:00")).contains(at("2026-10-15 13:00:00")));
+        assert!(!window(None, Some("14:00")).contains(at("2026-10-15 14:01:00")));
+        let w = TimeWindow::parse(Some("14:05"), Some("2026-10-16 08:00")).unwrap();
+        assert_eq!(w.label(), "1405-202610160800");
+    }
+
+    #[test]
+    fn default_output_dir_per_window() {
+        let session = Path::new("log/com.example.app/20261015_140000");
+        assert_eq!(
+            default_output_dir(session, &TimeWindow::default()),
+            session.join("report")
+        );
+        let w = TimeWindow::parse(Some("14:05"), None).unwrap();
+        assert_eq!(
+            default_output_dir(session, &w),
+            session.join("report").join("window_1405-end")
+        );
+    }
+
+    #[test]
+    fn only_sample_tables_are_charted() {
+        let table = |header: &str, row: &str| CsvTable {
+            path: PathBuf::new(),
+            headers: split_csv_line(header),
+            rows: vec![split_csv_line(row)],
+        };
+        assert!(table("Timestamp,Open FDs,Limit", "2026-10-15 14:00:00,128,32768").is_samples());
+        assert!(table("Launch,TotalTime", "1,420").is_samples());
+        assert!(table("Run,Install Time (ms)", "1,3100").is_samples());
+        // 汇总、区间和前后对比
+        assert!(!table("Metric,Count,Mean", "CPU Usage (%),60,12.5").is_samples());
+        assert!(!table(
+            "Start,End,Duration (s),Reason",
+            "2026-10-15 14:00:00,2026-10-15 14:01:00,60,x"
+        )
+        .is_samples());
+        assert!(!table("Process,Start PSS (KB),End PSS (KB)", "system_server,1,2").is_samples());
+        // 没有数值指标的事件记录
+        assert!(!table(
+            "Timestamp,Type,PID,Message",
+            "2026-10-15 14:00:00,crash,1234,boom"
+        )
+        .is_samples());
+    }
+
+    #[test]
+    fn retain_window_filters_time_series_rows() {
+        let row = |t: &str, v: &str| vec![t.to_string(), v.to_string()];
+        let mut table = CsvTable {
+            path: PathBuf::from("com.example.app_cpu_usage.csv"),
+            headers: vec!["Timestamp".to_string(), "CPU (%)".to_string()],
+            rows: vec![
+                row("2026-10-15 14:00:00", "12.5"),
+                row("2026-10-15 14:10:00", "48.0"),
+                row("2026-10-15 14:30:00", "7.25"),
+            ],
+        };
+        assert_eq!(table.first_timestamp(), Some(at("2026-10-15 14:00:00")));
+        table.retain_window(&window(Some("14:05"), Some("14:20")));
+        let series = table.time_series();
+        assert_eq!(series.len(), 1);
+        assert_eq!(series[0].points, vec![(at("2026-10-15 14:10:00"), 48.0)]);
+    }
+}
@@ -0,0 +1,302 @@
+use crate::chart::{self, LineSeriesData};
+use crate::regenerate::{self, CsvTable};
+use crate::report::HtmlReport;
+use crate::session::SessionInfo;
+use crate::stats::Summary;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// 合并 CSV 与 CPU/内存 CSV 中的列名
+const CPU_COLUMNS: [&str; 2] = ["CPU Usage (%)", "Process CPU (%)"];
+const PSS_COLUMNS: [&str; 2] = ["Total PSS (KB)", "Total PSS"];
+
+// 一次运行的 CPU 和 PSS，X 为距第一个样本的秒数
+#[derive(Debug, Clone)]
+pub struct Run {
+    pub label: String,
+    pub cpu: Vec<(f64, f64)>,
+    pub pss_kb: Vec<(f64, f64)>,
+}
+
+fn column_points(
+    table: &CsvTable,
+    names: &[&str],
+    start: DateTime<Local>,
+    warmup_end: Option<DateTime<Local>>,
+) -> Vec<(f64, f64)> {
+    let Some(index) = table
+        .headers
+        .iter()
+        .position(|h| names.contains(&h.as_str()))
+    else {
+        return Vec::new();
+    };
+    table
+        .rows
+        .iter()
+        .filter_map(|row| {
+            let timestamp = regenerate::parse_timestamp(row.first()?)?;
+            let value: f64 = row.get(index)?.parse().ok()?;
+            Some((timestamp, value))
+        })
+        // 预热区间的样本不参与对比
+        .filter(|(timestamp, _)| warmup_end.is_none_or(|end| *timestamp >= end))
+        .map(|(timestamp, value)| {
+            let elapsed = (timestamp - start).num_milliseconds() as f64 / 1000.0;
+            (elapsed, value)
+        })
+        .collect()
+}
+
+// 会话目录中的合并 CSV（<package>_combined.csv）
+pub fn combined_csv(dir: &Path) -> Result<PathBuf> {
+    std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .find(|path| {
+            path.file_name()
+                .is_some_and(|name| name.to_string_lossy().ends_with("_combined.csv"))
+        })
+        .ok_or_else(|| anyhow::format_err!("No *_combined.csv in {}", dir.display()))
+}
+
+impl Run {
+    // 会话目录或单个 CSV（合并 CSV、CPU CSV 或内存 CSV）
+    pub fn load(path: &Path) -> Result<Self> {
+        let (csv_path, warmup_end) = if path.is_dir() {
+            let warmup_end = SessionInfo::load(path)
+                .ok()
+                .flatten()
+                .and_then(|info| regenerate::parse_timestamp(&info.warmup_end));
+            (combined_csv(path)?, warmup_end)
+        } else {
+            (path.to_path_buf(), None)
+        };
+        let table = regenerate::read_csv(&csv_path)?;
+        if !table.is_time_series() {
+            anyhow::bail!("{} has no Timestamp column", csv_path.display());
+        }
+        let start = table
+            .rows
+            .iter()
+            .filter_map(|row| regenerate::parse_timestamp(row.first()?))
+            .filter(|timestamp| warmup_end.is_none_or(|end| *timestamp >= end))
+            .min()
+            .ok_or_else(|| anyhow::format_err!("No samples in {}", csv_path.display()))?;
+
+        let run = Self {
+            label: path.display().to_string(),
+            cpu: column_points(&table, &CPU_COLUMNS, start, warmup_end),
+            pss_kb: column_points(&table, &PSS_COLUMNS, start, warmup_end),
+        };
+        if run.cpu.is_empty() && run.pss_kb.is_empty() {
+            anyhow::bail!("No CPU or PSS column in {}", csv_path.display());
+        }
+        Ok(run)
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.cpu
+            .iter()
+            .chain(&self.pss_kb)
+            .map(|(elapsed, _)| *elapsed)
+            .fold(0.0, f64::max)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Statistic {
+    Peak,
+    Mean,
+    P95,
+}
+
+impl Statistic {
+    pub const ALL: [Statistic; 3] = [Statistic::Peak, Statistic::Mean, Statistic::P95];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Statistic::Peak => "peak",
+            Statistic::Mean => "mean",
+            Statistic::P95 => "p95",
+        }
+    }
+
+    pub fn value(&self, summary: &Summary) -> f64 {
+        match self {
+            Statistic::Peak => summary.max,
+            Statistic::Mean => summary.mean,
+            Statistic::P95 => summary.p95,
+        }
+    }
+}
+
+// 对比表的一行；数值越大越差，增幅超过阈值即为回归
+#[derive(Debug, Clone)]
+pub struct Delta {
+    pub metric: &'static str,
+    pub statistic: Statistic,
+    pub baseline: f64,
+    pub candidate: f64,
+    pub percent: Option<f64>,
+    pub regression: bool,
+}
+
+// 只对比两次运行重叠的时长，避免较长的一次包含对方没有的阶段
+pub fn compare(baseline: &Run, candidate: &Run, threshold: f64) -> Vec<Delta> {
+    let overlap = baseline.duration().min(candidate.duration());
+    let within = |points: &[(f64, f64)]| -> Vec<f64> {
+        points
+            .iter()
+            .filter(|(elapsed, _)| *elapsed <= overlap)
+            .map(|(_, value)| *value)
+            .collect()
+    };
+
+    let mut deltas = Vec::new();
+    for (metric, base, cand) in [
+        ("CPU (%)", &baseline.cpu, &candidate.cpu),
+        ("PSS (KB)", &baseline.pss_kb, &candidate.pss_kb),
+    ] {
+        let (Some(base), Some(cand)) = (
+            Summary::from_values(&within(base)),
+            Summary::from_values(&within(cand)),
+        ) else {
+            continue;
+        };
+        for statistic in Statistic::ALL {
+            let (base, cand) = (statistic.value(&base), statistic.value(&cand));
+            let percent = (base.abs() > f64::EPSILON).then(|| (cand - base) * 100.0 / base.abs());
+            deltas.push(Delta {
+                metric,
+                statistic,
+                baseline: base,
+                candidate: cand,
+                percent,
+                regression: percent.is_some_and(|p| p > threshold),
+            });
+        }
+    }
+    deltas
+}
+
+// 导出对比 CSV、CPU/PSS 叠加图和 HTML 报告
+pub fn export_comparison(
+    dir: &Path,
+    baseline: &Run,
+    candidate: &Run,
+    deltas: &[Delta],
+    threshold: f64,
+) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let mut paths = Vec::new();
+
+    let csv_path = dir.join("comparison.csv");
+    let mut file = std::fs::File::create(&csv_path)?;
+    writeln!(
+        file,
+        "Metric,Statistic,Baseline,Candidate,Delta (%),Regression"
+    )?;
+    for delta in deltas {
+        writeln!(
+            file,
+            "{},{},{:.2},{:.2},{},{}",
+            delta.metric,
+            delta.statistic.as_str(),
+            delta.baseline,
+            delta.candidate,
+            delta
+                .percent
+                .map(|p| format!("{:.1}", p))
+                .unwrap_or_default(),
+            delta.regression
+        )?;
+    }
+    paths.push(csv_path);
+
+    let mut charts = Vec::new();
+    for (file_name, title, y_desc, base, cand) in [
+        (
+            "cpu_overlay.png",
+            "CPU Usage",
+            "CPU (%)",
+            &baseline.cpu,
+            &candidate.cpu,
+        ),
+        (
+            "pss_overlay.png",
+            "Total PSS",
+            "PSS (MB)",
+            &baseline.pss_kb,
+            &candidate.pss_kb,
+        ),
+    ] {
+        let to_mb = file_name.starts_with("pss");
+        let series: Vec<LineSeriesData> = [("Baseline", base), ("Candidate", cand)]
+            .into_iter()
+            .filter(|(_, points)| !points.is_empty())
+            .map(|(label, points)| {
+                LineSeriesData::new(
+                    label,
+                    points
+                        .iter()
+                        .map(|&(x, y)| (x, if to_mb { y / 1024.0 } else { y }))
+                        .collect(),
+                )
+            })
+            .collect();
+        if series.len() < 2 {
+            continue;
+        }
+        let path = dir.join(file_name);
+        chart::draw_line_chart(
+            &path,
+            &format!("{} - Baseline vs Candidate", title),
+            "Elapsed (s)",
+            y_desc,
+            &series,
+        )?;
+        charts.push((file_name, title));
+        paths.push(path);
+    }
+
+    let mut report = HtmlReport::new("Run Comparison");
+    report.add_paragraph(&format!(
+        "Baseline: {}. Candidate: {}. Runs are aligned by elapsed time and compared over their overlapping duration; increases above {:.0}% are flagged as regressions.",
+        baseline.label, candidate.label, threshold
+    ));
+    let rows: Vec<Vec<String>> = deltas
+        .iter()
+        .map(|delta| {
+            vec![
+                delta.metric.to_string(),
+                delta.statistic.as_str().to_string(),
+                format!("{:.2}", delta.baseline),
+                format!("{:.2}", delta.candidate),
+                delta
+                    .percent
+                    .map(|p| format!("{:+.1}%", p))
+                    .unwrap_or_else(|| "-".to_string()),
+            ]
+        })
+        .collect();
+    let highlights: Vec<Vec<bool>> = deltas
+        .iter()
+        .map(|delta| vec![false, false, false, false, delta.regression])
+        .collect();
+    report.add_highlighted_table(
+        &["Metric", "Statistic", "Baseline", "Candidate", "Delta"],
+        &rows,
+        &highlights,
+    );
+    for (file_name, title) in charts {
+        report.add_image(file_name, title);
+    }
+    let report_path = dir.join("report.html");
+    report.write(&report_path)?;
+    paths.push(report_path);
+
+    Ok(paths)
+}
@@ -0,0 +1,128 @@
+use anyhow::Result;
+use std::io::Write;
+use std::path::Path;
+
+// 简单的自包含 HTML 报告，图片以相对路径引用
+pub struct HtmlReport {
+    title: String,
+    body: String,
+}
+
+impl HtmlReport {
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            body: String::new(),
+        }
+    }
+
+    pub fn add_heading(&mut self, text: &str) {
+        self.body
+            .push_str(&format!("<h2>{}</h2>\n", escape_html(text)));
+    }
+
+    pub fn add_paragraph(&mut self, text: &str) {
+        self.body
+            .push_str(&format!("<p>{}</p>\n", escape_html(text)));
+    }
+
+    pub fn add_table(&mut self, headers: &[&str], rows: &[Vec<String>]) {
+        self.body.push_str("<table>\n<tr>");
+        for header in headers {
+            self.body
+                .push_str(&format!("<th>{}</th>", escape_html(header)));
+        }
+        self.body.push_str("</tr>\n");
+        for row in rows {
+            self.body.push_str("<tr>");
+            for cell in row {
+                self.body
+                    .push_str(&format!("<td>{}</td>", escape_html(cell)));
+            }
+            self.body.push_str("</tr>\n");
+        }
+        self.body.push_str("</table>\n");
+    }
+
+    // 与 add_table 相同，highlights[row][col] 为 true 的单元格高亮显示
+    pub fn add_highlighted_table(
+        &mut self,
+        headers: &[&str],
+        rows: &[Vec<String>],
+        highlights: &[Vec<bool>],
+    ) {
+        self.body.push_str("<table>\n<tr>");
+        for header in headers {
+            self.body
+                .push_str(&format!("<th>{}</th>", escape_html(header)));
+        }
+        self.body.push_str("</tr>\n");
+        for (r, row) in rows.iter().enumerate() {
+            self.body.push_str("<tr>");
+            for (c, cell) in row.iter().enumerate() {
+                let highlighted = highlights
+                    .get(r)
+                    .and_then(|h| h.get(c))
+                    .copied()
+                    .unwrap_or(false);
+                let class = if highlighted {
+                    " class=\"highlight\""
+                } else {
+                    ""
+                };
+                self.body
+                    .push_str(&format!("<td{}>{}</td>", class, escape_html(cell)));
+            }
+            self.body.push_str("</tr>\n");
+        }
+        self.body.push_str("</table>\n");
+    }
+
+    // 图片路径应相对于报告所在目录
+    pub fn add_image(&mut self, relative_path: &str, caption: &str) {
+        self.body.push_str(&format!(
+            "<figure><img src=\"{}\" alt=\"{}\"><figcaption>{}</figcaption></figure>\n",
+            escape_html(relative_path),
+            escape_html(caption),
+            escape_html(caption)
+        ));
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let mut file = std::fs::File::create(path)?;
+        write!(
+            file,
+            r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+body {{ font-family: sans-serif; margin: 2em; }}
+table {{ border-collapse: collapse; margin-bottom: 1.5em; }}
+th, td {{ border: 1px solid #ccc; padding: 4px 10px; text-align: right; }}
+th {{ background: #f0f0f0; }}
+td.highlight {{ background: #ffe0b0; font-weight: bold; }}
+td:first-child, th:first-child {{ text-align: left; }}
+img {{ max-width: 100%; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+{body}</body>
+</html>
+"#,
+            title = escape_html(&self.title),
+            body = self.body
+        )?;
+        file.flush()?;
+        Ok(())
+    }
+}
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,456 @@
+use crate::chart;
+use crate::logcat::{EventKind, LogcatEvent};
+use crate::recorder::{Collector, FinishContext, TickContext};
+use crate::utils;
+use anyhow::{Context, Result};
+use colored::*;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::time::Duration;
+
+// 脚本中的一步操作，action 为操作类型，其余键为参数
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "action", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum Step {
+    // 启动 Activity，未指定时启动桌面入口
+    Launch {
+        activity: Option<String>,
+    },
+    // 等待，时长格式同 --duration，如 "5s"
+    Sleep {
+        duration: String,
+    },
+    Tap {
+        x: u32,
+        y: u32,
+    },
+    Swipe {
+        from: [u32; 2],
+        to: [u32; 2],
+        // 滑动耗时（毫秒），越短越接近快速滑动
+        duration_ms: Option<u32>,
+    },
+    // input keyevent，如 KEYCODE_BACK、KEYCODE_HOME 或键码数字
+    Key {
+        code: String,
+    },
+    // 发给应用的广播，extras 中字符串、整数、浮点数和布尔值分别对应 --es、--ei/--el、--ef 和 --ez
+    Broadcast {
+        intent: String,
+        #[serde(default)]
+        extras: BTreeMap<String, toml::Value>,
+    },
+    ForceStop,
+}
+
+impl Step {
+    pub fn is_launch(&self) -> bool {
+        matches!(self, Step::Launch { .. })
+    }
+
+    // 控制台输出和事件标记中的描述
+    pub fn describe(&self) -> String {
+        match self {
+            Step::Launch { activity } => format!(
+                "launch {}",
+                activity.as_deref().unwrap_or("(launcher activity)")
+            ),
+            Step::Sleep { duration } => format!("sleep {}", duration),
+            Step::Tap { x, y } => format!("tap {},{}", x, y),
+            Step::Swipe { from, to, .. } => {
+                format!("swipe {},{} -> {},{}", from[0], from[1], to[0], to[1])
+            }
+            Step::Key { code } => format!("key {}", code),
+            Step::Broadcast { intent, .. } => format!("broadcast {}", intent),
+            Step::ForceStop => "force-stop".to_string(),
+        }
+    }
+
+    fn sleep_duration(&self) -> Result<Option<Duration>> {
+        match self {
+            Step::Sleep { duration } => utils::parse_duration(duration).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    // 对应的 adb shell 参数；sleep 不需要 adb
+    fn command(&self, package: &str) -> Result<Vec<String>> {
+        let args: Vec<String> = match self {
+            Step::Launch {
+                activity: Some(activity),
+            } => {
+                let component = if activity.contains('/') {
+                    activity.clone()
+                } else {
+                    format!("{}/{}", package, activity)
+                };
+                vec!["am".into(), "start".into(), "-n".into(), component]
+            }
+            Step::Launch { activity: None } => vec![
+                "monkey".into(),
+                "-p".into(),
+                package.into(),
+                "-c".into(),
+                "android.intent.category.LAUNCHER".into(),
+                "1".into(),
+            ],
+            Step::Sleep { .. } => Vec::new(),
+            Step::Tap { x, y } => vec!["input".into(), "tap".into(), x.to_string(), y.to_string()],
+            Step::Swipe {
+                from,
+                to,
+                duration_ms,
+            } => {
+                let mut args = vec!["input".to_string(), "swipe".to_string()];
+                args.extend([from[0], from[1], to[0], to[1]].map(|v| v.to_string()));
+                args.extend(duration_ms.map(|ms| ms.to_string()));
+                args
+            }
+            Step::Key { code } => vec!["input".into(), "keyevent".into(), code.clone()],
+            Step::Broadcast { intent, extras } => {
+                let mut args = vec![
+                    "am".to_string(),
+                    "broadcast".to_string(),
+                    "-a".to_string(),
+                    intent.clone(),
+                    "-p".to_string(),
+                    package.to_string(),
+                ];
+                for (key, value) in extras {
+                    let (flag, text) = match value {
+                        toml::Value::String(text) => ("--es", text.clone()),
+                        toml::Value::Integer(n) if i32::try_from(*n).is_ok() => {
+                            ("--ei", n.to_string())
+                        }
+                        toml::Value::Integer(n) => ("--el", n.to_string()),
+                        toml::Value::Float(n) => ("--ef", n.to_string()),
+                        toml::Value::Boolean(b) => ("--ez", b.to_string()),
+                        other => anyhow::bail!(
+                            "Unsupported extra '{}' = {} (use a string, number or boolean)",
+                            key,
+                            other
+                        ),
+                    };
+                    args.extend([flag.to_string(), key.clone(), text]);
+                }
+                args
+            }
+            Step::ForceStop => vec!["am".into(), "force-stop".into(), package.into()],
+        };
+        Ok(args)
+    }
+
+    // 执行一步；sleep 期间每 100ms 检查一次 running，被清除时提前返回
+    pub fn run(&self, package: &str, running: &AtomicBool) -> Result<()> {
+        if let Some(duration) = self.sleep_duration()? {
+            let deadline = std::time::Instant::now() + duration;
+            while std::time::Instant::now() < deadline && running.load(Ordering::SeqCst) {
+                std::thread::sleep(
+                    deadline
+                        .saturating_duration_since(std::time::Instant::now())
+                        .min(Duration::from_millis(100)),
+                );
+            }
+            return Ok(());
+        }
+        let mut args = vec!["shell".to_string()];
+        args.extend(self.command(package)?);
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let output = utils::run_adb_command(&args)?;
+        // am 和 input 失败时退出码仍可能为 0，错误信息在输出中
+        if output.contains("Error:") || output.contains("Exception") {
+            anyhow::bail!("{} failed: {}", self.describe(), output.trim());
+        }
+        Ok(())
+    }
+}
+
+// 场景脚本（TOML）：
+//   name = "scroll feed"
+//   [monitor]
+//   package = "com.example"
+//   cpu = true
+//   [[step]]
+//   action = "launch"
+//   [[step]]
+//   action = "sleep"
+//   duration = "5s"
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    name: Option<String>,
+    // 监控选项，格式同 xperf.toml，命令行中的选项优先
+    #[serde(default)]
+    pub monitor: toml::Table,
+    #[serde(rename = "step", default)]
+    pub steps: Vec<Step>,
+    #[serde(skip)]
+    pub path: PathBuf,
+}
+
+impl Scenario {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read scenario {}", path.display()))?;
+        let mut scenario: Scenario = toml::from_str(&text)
+            .with_context(|| format!("Invalid scenario {}", path.display()))?;
+        if scenario.steps.is_empty() {
+            anyhow::bail!("Scenario {} has no [[step]] entries", path.display());
+        }
+        // 启动前检查参数，避免执行到一半才失败
+        for (i, step) in scenario.steps.iter().enumerate() {
+            step.sleep_duration()
+                .and_then(|_| step.command(""))
+                .with_context(|| format!("Invalid step {} ({})", i + 1, step.describe()))?;
+        }
+        scenario.path = path.to_path_buf();
+        Ok(scenario)
+    }
+
+    // 未指定 name 时为文件名
+    pub fn name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| {
+            self.path
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default()
+        })
+    }
+
+    // 第一个 launch 及其之前的步骤（如 force-stop）在采样开始前执行，其余步骤在监控期间执行
+    pub fn split_prelude(&self) -> (&[Step], &[Step]) {
+        let end = self
+            .steps
+            .iter()
+            .position(Step::is_launch)
+            .map_or(0, |i| i + 1);
+        self.steps.split_at(end)
+    }
+}
+
+// 在后台线程中依次执行步骤，每步完成时通过通道通知监控循环
+pub struct ScenarioRunner {
+    receiver: Receiver<String>,
+    finished: Arc<AtomicBool>,
+    // 监控结束时清除，剩余步骤不再执行
+    running: Arc<AtomicBool>,
+}
+
+impl ScenarioRunner {
+    pub fn start(steps: Vec<Step>, package: &str) -> Self {
+        let (sender, receiver) = mpsc::channel::<String>();
+        let finished = Arc::new(AtomicBool::new(false));
+        let running = Arc::new(AtomicBool::new(true));
+        let package = package.to_string();
+        {
+            let finished = finished.clone();
+            let running = running.clone();
+            std::thread::spawn(move || {
+                for step in &steps {
+                    if !running.load(Ordering::SeqCst) || utils::is_being_interrupted() {
+                        break;
+                    }
+                    // sleep 不单独标记
+                    let message = match step.run(&package, &running) {
+                        Ok(()) if matches!(step, Step::Sleep { .. }) => continue,
+                        Ok(()) => step.describe(),
+                        Err(e) => format!("{} (failed: {})", step.describe(), e),
+                    };
+                    if sender.send(message).is_err() {
+                        break;
+                    }
+                }
+                finished.store(true, Ordering::SeqCst);
+            });
+        }
+        Self {
+            receiver,
+            finished,
+            running,
+        }
+    }
+
+    // 自上次调用以来执行完的步骤
+    pub fn poll(&self) -> Vec<String> {
+        self.receiver.try_iter().collect()
+    }
+
+    // 所有步骤已执行；先检查再 poll 可取到全部步骤
+    pub fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for ScenarioRunner {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+    }
+}
+
+// run-scenario：第一个 launch 之后的步骤与采样同时执行，每一步记为一个标记，全部执行完后结束会话
+pub struct ScenarioCollector {
+    runner: ScenarioRunner,
+    path: PathBuf,
+    finished: bool,
+    events: Vec<LogcatEvent>,
+}
+
+impl ScenarioCollector {
+    pub fn start(scenario: &Scenario, package: &str) -> Self {
+        let (_, body) = scenario.split_prelude();
+        Self {
+            runner: ScenarioRunner::start(body.to_vec(), package),
+            path: scenario.path.clone(),
+            finished: false,
+            events: Vec::new(),
+        }
+    }
+}
+
+impl Collector for ScenarioCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        self.finished = self.runner.is_finished();
+        // 标记便于不同构建之间对照同一操作
+        for step in self.runner.poll() {
+            let label = format!("scenario: {}", step);
+            console!(
+                "[{}] {} {}",
+                ctx.tick.timestamp.format("%H:%M:%S").to_string().blue(),
+                "Marker:".magenta().bold(),
+                label
+            );
+            chart::add_user_marker(ctx.tick.timestamp, &label);
+            self.events.push(LogcatEvent {
+                timestamp: ctx.tick.timestamp,
+                kind: EventKind::Marker,
+                pid: Some(ctx.sample.pid.clone()).filter(|pid| !pid.is_empty()),
+                message: label,
+            });
+        }
+        Ok(())
+    }
+
+    // 保存本次执行的场景脚本，便于在其他构建上重放
+    fn finish(&mut self, ctx: &FinishContext) {
+        let path = ctx.layout.file("scenario.toml");
+        match std::fs::copy(&self.path, &path) {
+            Ok(_) => console!("✓ Scenario saved: {}", path.display()),
+            Err(e) => console!("Failed to save scenario: {}", e),
+        }
+    }
+
+    fn events(&self, events: &mut Vec<LogcatEvent>) {
+        events.extend(self.events.iter().cloned());
+    }
+
+    fn end_reason(&self) -> Option<String> {
+        self.finished.then(|| "Scenario finished".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Scenario {
+        toml::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn split_prelude_ends_at_first_launch() {
+        let scenario = parse(
+            r#"
+            [[step]]
+            action = "force-stop"
+            [[step]]
+            action = "launch"
+            [[step]]
+            action = "tap"
+            x = 10
+            y = 20
+            [[step]]
+            action = "launch"
+            activity = ".Main"
+            "#,
+        );
+        let (prelude, rest) = scenario.split_prelude();
+        assert_eq!(prelude.len(), 2);
+        assert!(prelude[1].is_launch());
+        assert_eq!(rest.len(), 2);
+        assert_eq!(rest[0].describe(), "tap 10,20");
+    }
+
+    #[test]
+    fn split_prelude_without_launch_is_empty() {
+        let scenario = parse(
+            r#"
+            [[step]]
+            action = "key"
+            code = "KEYCODE_BACK"
+            "#,
+        );
+        let (prelude, rest) = scenario.split_prelude();
+        assert!(prelude.is_empty());
+        assert_eq!(rest.len(), 1);
+    }
+
+    #[test]
+    fn step_commands() {
+        let launch = Step::Launch {
+            activity: Some(".Main".into()),
+        };
+        assert_eq!(
+            launch.command("com.example").unwrap(),
+            ["am", "start", "-n", "com.example/.Main"]
+        );
+        let scenario = parse(
+            r#"
+            [[step]]
+            action = "broadcast"
+            intent = "com.example.RELOAD"
+            extras = { id = 3, big = 5000000000, name = "a", on = true }
+            [[step]]
+            action = "swipe"
+            from = [1, 2]
+            to = [3, 4]
+            duration_ms = 80
+            "#,
+        );
+        assert_eq!(
+            scenario.steps[0].command("com.example").unwrap(),
+            [
+                "am",
+                "broadcast",
+                "-a",
+                "com.example.RELOAD",
+                "-p",
+                "com.example",
+                "--el",
+                "big",
+                "5000000000",
+                "--ei",
+                "id",
+                "3",
+                "--es",
+                "name",
+                "a",
+                "--ez",
+                "on",
+                "true"
+            ]
+        );
+        assert_eq!(
+            scenario.steps[1].command("com.example").unwrap(),
+            ["input", "swipe", "1", "2", "3", "4", "80"]
+        );
+    }
+
+    #[test]
+    fn unknown_action_is_rejected() {
+        assert!(toml::from_str::<Scenario>("[[step]]\naction = \"jump\"").is_err());
+    }
+}
@@ -0,0 +1,66 @@
+use anyhow::Result;
+use chrono::{DateTime, Days, Local, NaiveTime, TimeZone};
+
+// 解析 "22:00" 或 "22:00:30" 形式的时刻
+pub fn parse_time(text: &str) -> Result<NaiveTime> {
+    let text = text.trim();
+    NaiveTime::parse_from_str(text, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(text, "%H:%M"))
+        .map_err(|_| anyhow::format_err!("Invalid time: {} (expected HH:MM or HH:MM:SS)", text))
+}
+
+// 每日监控窗口；stop 早于 start 时表示跨越午夜，如 22:00-06:00
+#[derive(Debug, Clone, Copy)]
+pub struct Schedule {
+    pub start: Option<NaiveTime>,
+    pub stop: Option<NaiveTime>,
+}
+
+impl Schedule {
+    // 解析 "22:00-06:00" 形式的窗口
+    pub fn parse_window(text: &str) -> Result<Self> {
+        let (start, stop) = text.split_once('-').ok_or_else(|| {
+            anyhow::format_err!("Invalid window: {} (expected HH:MM-HH:MM)", text)
+        })?;
+        Ok(Self {
+            start: Some(parse_time(start)?),
+            stop: Some(parse_time(stop)?),
+        })
+    }
+
+    fn contains(&self, time: NaiveTime) -> bool {
+        match (self.start, self.stop) {
+            (Some(start), Some(stop)) if start <= stop => start <= time && time < stop,
+            (Some(start), Some(stop)) => time >= start || time < stop,
+            _ => false,
+        }
+    }
+
+    // 下一个窗口的开始和结束时间；当前处于窗口内时立即开始，没有结束时间时运行到 Ctrl-C
+    pub fn next_window(&self, now: DateTime<Local>) -> (DateTime<Local>, Option<DateTime<Local>>) {
+        let open = match self.start {
+            Some(_) if self.contains(now.time()) => now,
+            Some(start) => next_occurrence(now, start),
+            None => now,
+        };
+        let close = self.stop.map(|stop| next_occurrence(open, stop));
+        (open, close)
+    }
+}
+
+// now 之后（含当天）第一次到达该时刻的时间
+fn next_occurrence(now: DateTime<Local>, time: NaiveTime) -> DateTime<Local> {
+    let today = now.date_naive();
+    for date in [Some(today), today.checked_add_days(Days::new(1))]
+        .into_iter()
+        .flatten()
+    {
+        // 夏令时切换导致的不存在时刻跳过
+        if let Some(candidate) = Local.from_local_datetime(&date.and_time(time)).earliest() {
+            if candidate > now {
+                return candidate;
+            }
+        }
+    }
+    now
+}
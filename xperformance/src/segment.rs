@@ -0,0 +1,368 @@
+use crate::chart::{self, TimeSeriesData};
+use crate::layout::{Category, OutputLayout};
+use crate::recorder::{Collector, FinishContext, TickContext};
+use crate::report::HtmlReport;
+use crate::stats::Summary;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use colored::*;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// 进程一次存活期间的数据段，进程重启时关闭当前段并开始新段
+#[derive(Debug, Clone)]
+pub struct Segment {
+    pub index: usize,
+    pub pid: String,
+    pub started: DateTime<Local>,
+    pub ended: DateTime<Local>,
+    pub cpu: Vec<(DateTime<Local>, f64)>,
+    pub memory: Vec<(DateTime<Local>, f64)>,
+    // 预热结束时间，之前的样本不计入峰值和统计
+    pub warmup_end: Option<DateTime<Local>>,
+}
+
+impl Segment {
+    pub fn new(
+        index: usize,
+        pid: &str,
+        started: DateTime<Local>,
+        warmup_end: Option<DateTime<Local>>,
+    ) -> Self {
+        Self {
+            index,
+            pid: pid.to_string(),
+            started,
+            ended: started,
+            cpu: Vec::new(),
+            memory: Vec::new(),
+            warmup_end,
+        }
+    }
+
+    fn steady<'a>(
+        &self,
+        points: &'a [(DateTime<Local>, f64)],
+    ) -> impl Iterator<Item = (DateTime<Local>, f64)> + 'a {
+        let warmup_end = self.warmup_end;
+        points
+            .iter()
+            .copied()
+            .filter(move |(t, _)| warmup_end.is_none_or(|end| *t >= end))
+    }
+
+    pub fn label(&self) -> String {
+        format!("Segment {} (pid {})", self.index, self.pid)
+    }
+
+    pub fn dir_name(&self) -> String {
+        format!("segment_{}", self.index)
+    }
+
+    pub fn record_cpu(&mut self, timestamp: DateTime<Local>, value: f64) {
+        self.cpu.push((timestamp, value));
+        self.ended = timestamp;
+    }
+
+    pub fn record_memory(&mut self, timestamp: DateTime<Local>, total_pss_kb: f64) {
+        self.memory.push((timestamp, total_pss_kb));
+        self.ended = timestamp;
+    }
+
+    pub fn cpu_summary(&self) -> Option<Summary> {
+        Summary::from_values(&self.steady(&self.cpu).map(|(_, v)| v).collect::<Vec<_>>())
+    }
+
+    pub fn memory_summary(&self) -> Option<Summary> {
+        Summary::from_values(
+            &self
+                .steady(&self.memory)
+                .map(|(_, v)| v)
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    // 峰值及出现时间
+    fn peak(&self, points: &[(DateTime<Local>, f64)]) -> Option<(DateTime<Local>, f64)> {
+        self.steady(points)
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+    }
+
+    pub fn peak_cpu(&self) -> Option<(DateTime<Local>, f64)> {
+        self.peak(&self.cpu)
+    }
+
+    pub fn peak_memory(&self) -> Option<(DateTime<Local>, f64)> {
+        self.peak(&self.memory)
+    }
+
+    // 写出本段的数据、统计和图表，返回生成的文件
+    pub fn write(&self, dir: &Path, package: &str) -> Result<Vec<PathBuf>> {
+        std::fs::create_dir_all(dir)?;
+        let mut paths = Vec::new();
+
+        for (points, file_name, column) in [
+            (&self.cpu, "segment_cpu.csv", "CPU Usage (%)"),
+            (&self.memory, "segment_memory.csv", "Total PSS (KB)"),
+        ] {
+            if points.is_empty() {
+                continue;
+            }
+            let csv_path = dir.join(file_name);
+            let mut file = std::fs::File::create(&csv_path)?;
+            writeln!(file, "Timestamp,{}", column)?;
+            for (timestamp, value) in points {
+                writeln!(
+                    file,
+                    "{},{:.2}",
+                    timestamp.format("%Y-%m-%d %H:%M:%S"),
+                    value
+                )?;
+            }
+            paths.push(csv_path);
+        }
+
+        let summary_path = dir.join("segment_summary.csv");
+        let mut file = std::fs::File::create(&summary_path)?;
+        writeln!(file, "Metric,{}", Summary::CSV_HEADER)?;
+        if let Some(summary) = self.cpu_summary() {
+            writeln!(file, "CPU Usage (%),{}", summary.to_csv_row())?;
+        }
+        if let Some(summary) = self.memory_summary() {
+            writeln!(file, "Total PSS (KB),{}", summary.to_csv_row())?;
+        }
+        paths.push(summary_path);
+
+        for (points, file_name, title, y_desc) in [
+            (&self.cpu, "segment_cpu.png", "CPU Usage", "CPU Usage (%)"),
+            (&self.memory, "segment_memory.png", "Total PSS", "PSS (KB)"),
+        ] {
+            if points.len() > 1 {
+                let path = dir.join(chart::stamped_file_name(file_name));
+                chart::draw_time_series_chart(
+                    &path,
+                    &format!("{} - {} - {}", title, package, self.label()),
+                    y_desc,
+                    &[TimeSeriesData::new(self.label(), points.clone())],
+                )?;
+                paths.push(path);
+            }
+        }
+
+        Ok(paths)
+    }
+}
+
+fn format_peak(peak: Option<(DateTime<Local>, f64)>, precision: usize) -> String {
+    match peak {
+        Some((timestamp, value)) => {
+            format!(
+                "{:.*} at {}",
+                precision,
+                value,
+                timestamp.format("%H:%M:%S")
+            )
+        }
+        None => "-".to_string(),
+    }
+}
+
+fn format_mean(summary: Option<Summary>, precision: usize) -> String {
+    summary
+        .map(|s| format!("{:.*}", precision, s.mean))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+// 各段并排对比：汇总表、每段一条曲线的图表和 HTML 报告
+pub fn write_comparison(dir: &Path, package: &str, segments: &[Segment]) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let mut paths = Vec::new();
+
+    let headers = [
+        "Segment",
+        "PID",
+        "Start",
+        "End",
+        "Duration (s)",
+        "Mean CPU (%)",
+        "Peak CPU (%)",
+        "Mean PSS (KB)",
+        "Peak PSS (KB)",
+    ];
+    let rows: Vec<Vec<String>> = segments
+        .iter()
+        .map(|segment| {
+            vec![
+                segment.index.to_string(),
+                segment.pid.clone(),
+                segment.started.format("%Y-%m-%d %H:%M:%S").to_string(),
+                segment.ended.format("%Y-%m-%d %H:%M:%S").to_string(),
+                (segment.ended - segment.started).num_seconds().to_string(),
+                format_mean(segment.cpu_summary(), 1),
+                format_peak(segment.peak_cpu(), 1),
+                format_mean(segment.memory_summary(), 0),
+                format_peak(segment.peak_memory(), 0),
+            ]
+        })
+        .collect();
+
+    let csv_path = dir.join("segments_summary.csv");
+    let mut file = std::fs::File::create(&csv_path)?;
+    writeln!(file, "{}", headers.join(","))?;
+    for row in &rows {
+        writeln!(file, "{}", row.join(","))?;
+    }
+    paths.push(csv_path);
+
+    let mut report = HtmlReport::new(&format!("Process Segments - {}", package));
+    report.add_paragraph(&format!(
+        "The process restarted {} time(s). Each segment covers one process lifetime, so data from before and after a restart is not blended.",
+        segments.len().saturating_sub(1)
+    ));
+    report.add_table(&headers, &rows);
+
+    for (file_name, title, y_desc, is_cpu) in [
+        (
+            "segments_cpu.png",
+            "CPU Usage by Segment",
+            "CPU Usage (%)",
+            true,
+        ),
+        (
+            "segments_memory.png",
+            "Total PSS by Segment",
+            "PSS (KB)",
+            false,
+        ),
+    ] {
+        let series: Vec<TimeSeriesData> = segments
+            .iter()
+            .map(|segment| {
+                let points = if is_cpu {
+                    &segment.cpu
+                } else {
+                    &segment.memory
+                };
+                TimeSeriesData::new(segment.label(), points.clone())
+            })
+            .filter(|series| !series.points.is_empty())
+            .collect();
+        if series.is_empty() {
+            continue;
+        }
+        let file_name = chart::stamped_file_name(file_name);
+        let path = dir.join(&file_name);
+        chart::draw_time_series_chart(&path, &format!("{} - {}", title, package), y_desc, &series)?;
+        report.add_image(&file_name, title);
+        paths.push(path);
+    }
+
+    let report_path = dir.join("report.html");
+    report.write(&report_path)?;
+    paths.push(report_path);
+
+    Ok(paths)
+}
+
+// 按进程存活期分段，重启时关闭当前段并输出其数据
+pub struct SegmentCollector {
+    segment: Segment,
+    closed: Vec<Segment>,
+    warmup_end: Option<DateTime<Local>>,
+}
+
+impl SegmentCollector {
+    pub fn new(pid: &str, warmup_end: Option<DateTime<Local>>) -> Self {
+        Self {
+            segment: Segment::new(1, pid, Local::now(), warmup_end),
+            closed: Vec::new(),
+            warmup_end,
+        }
+    }
+}
+
+impl Collector for SegmentCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        let sample = ctx.sample;
+        // 关闭当前段并输出其数据，新进程开始新段
+        if sample.restarted {
+            let next = Segment::new(
+                self.segment.index + 1,
+                &sample.pid,
+                Local::now(),
+                self.warmup_end,
+            );
+            let closed = std::mem::replace(&mut self.segment, next);
+            close_segment(ctx.package, &closed);
+            self.closed.push(closed);
+        }
+        let timestamp = sample.tick.timestamp;
+        if let Some(cpu) = sample.cpu {
+            self.segment.record_cpu(timestamp, cpu as f64);
+        }
+        if let Some(details) = &sample.memory {
+            self.segment
+                .record_memory(timestamp, details.total_pss as f64);
+        }
+        Ok(())
+    }
+
+    // 发生过重启时，关闭最后一段并并排输出各段对比
+    fn finish(&mut self, ctx: &FinishContext) {
+        if self.closed.is_empty() {
+            return;
+        }
+        close_segment(ctx.package, &self.segment);
+        self.closed.push(self.segment.clone());
+        let segments_dir = ctx.layout.dir(Category::Segments);
+        match write_comparison(&segments_dir, ctx.package, &self.closed) {
+            Ok(_) => console!(
+                "✓ Segment comparison generated: {}",
+                segments_dir.join("report.html").display()
+            ),
+            Err(e) => console!("Failed to generate segment comparison: {}", e),
+        }
+    }
+}
+
+// 输出已关闭段的峰值，并写出其数据和图表
+fn close_segment(package: &str, segment: &Segment) {
+    let mut peaks = Vec::new();
+    if let Some((timestamp, value)) = segment.peak_cpu() {
+        peaks.push(format!(
+            "peak CPU {:.1}% at {}",
+            value,
+            timestamp.format("%H:%M:%S")
+        ));
+    }
+    if let Some((timestamp, value)) = segment.peak_memory() {
+        peaks.push(format!(
+            "peak memory {:.0} KB at {}",
+            value,
+            timestamp.format("%H:%M:%S")
+        ));
+    }
+    console!(
+        "{} closed: {}",
+        segment.label().green(),
+        if peaks.is_empty() {
+            "no data".to_string()
+        } else {
+            peaks.join(", ")
+        }
+    );
+
+    if segment.cpu.is_empty() && segment.memory.is_empty() {
+        return;
+    }
+    match OutputLayout::session(package) {
+        Ok(layout) => {
+            let segment_dir = layout.dir(Category::Segments).join(segment.dir_name());
+            match segment.write(&segment_dir, package) {
+                Ok(_) => console!("✓ Segment data exported: {}", segment_dir.display()),
+                Err(e) => console!("Failed to export segment data: {}", e),
+            }
+        }
+        Err(e) => console!("Failed to create segment directory: {}", e),
+    }
+}
@@ -0,0 +1,116 @@
+use crate::device::{self, DeviceInfo};
+use crate::package;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+// 会话元数据，保存在会话目录的 session.json 中，供跨会话/跨设备对比使用
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionInfo {
+    pub package: String,
+    pub version_name: String,
+    pub version_code: String,
+    #[serde(default)]
+    pub target_sdk: String,
+    #[serde(default)]
+    pub uid: String,
+    #[serde(default)]
+    pub debuggable: bool,
+    // 首次安装和最近一次安装的时间，区分版本号相同的不同构建
+    #[serde(default)]
+    pub first_install_time: String,
+    #[serde(default)]
+    pub last_update_time: String,
+    pub started: String,
+    pub device: DeviceInfo,
+    // CPU 数据的归一化方式（total / per-core）
+    #[serde(default)]
+    pub cpu_scale: String,
+    // 预热结束时间，之前的样本只绘图、不计入统计
+    #[serde(default)]
+    pub warmup_end: String,
+    // run-scenario 的场景名，相同场景的会话才适合对比
+    #[serde(default)]
+    pub scenario: String,
+}
+
+impl SessionInfo {
+    pub const FILE_NAME: &'static str = "session.json";
+
+    pub fn capture(serial: Option<&str>, package: &str) -> Self {
+        let info = package::query_package_info(serial, package).unwrap_or_default();
+        Self {
+            package: package.to_string(),
+            version_name: info.version_name,
+            version_code: info.version_code,
+            target_sdk: info.target_sdk,
+            uid: info.uid,
+            debuggable: info.debuggable,
+            first_install_time: info.first_install_time,
+            last_update_time: info.last_update_time,
+            started: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            device: device::query_device_info(serial),
+            cpu_scale: String::new(),
+            warmup_end: String::new(),
+            scenario: String::new(),
+        }
+    }
+
+    pub fn build_type(&self) -> &'static str {
+        if self.debuggable {
+            "debug"
+        } else {
+            "release"
+        }
+    }
+
+    // 应用版本，如 1.2.3 (10203)
+    pub fn version_label(&self) -> String {
+        match (self.version_name.is_empty(), self.version_code.is_empty()) {
+            (false, false) => format!("{} ({})", self.version_name, self.version_code),
+            (false, true) => self.version_name.clone(),
+            (true, false) => format!("({})", self.version_code),
+            (true, true) => String::new(),
+        }
+    }
+
+    // 图表标签：设备型号、Android 版本、应用版本和安装时间
+    pub fn stamp_label(&self) -> String {
+        let mut parts = Vec::new();
+        if !self.device.model.is_empty() {
+            parts.push(self.device.model.clone());
+        }
+        if !self.device.android_version.is_empty() {
+            parts.push(format!("Android {}", self.device.android_version));
+        }
+        let version = self.version_label();
+        if !version.is_empty() {
+            parts.push(format!("v{}", version));
+        }
+        // 精确到分钟，重装同一版本号的构建也能区分
+        if let Some(installed) = self.last_update_time.get(..16) {
+            parts.push(format!("installed {}", installed));
+        }
+        parts.join(" | ")
+    }
+
+    pub fn save(&self, session_dir: &Path) -> Result<()> {
+        std::fs::create_dir_all(session_dir)?;
+        std::fs::write(
+            session_dir.join(Self::FILE_NAME),
+            serde_json::to_string_pretty(self)?,
+        )?;
+        Ok(())
+    }
+
+    pub fn load(session_dir: &Path) -> Result<Option<Self>> {
+        let path = session_dir.join(Self::FILE_NAME);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)?;
+        serde_json::from_str(&content)
+            .map(Some)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+}
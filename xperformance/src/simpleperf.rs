@@ -0,0 +1,320 @@
+use crate::capture::{self, CaptureWindow};
+use crate::flamegraph::{self, CollapsedStacks};
+use crate::layout::{Category, OutputLayout};
+use crate::recorder::{Collector, FinishContext, TickContext};
+use crate::replay;
+use crate::utils::{self, CpuChartOverlay};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use colored::*;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+
+// 设备端临时目录，simpleperf 以 shell 用户运行时可写
+const REMOTE_DIR: &str = "/data/local/tmp";
+
+// 后台运行的 simpleperf record，由 adb 子进程承载，结束时随之退出
+pub struct Recording {
+    child: Child,
+    pub remote_path: String,
+    pub start: DateTime<Local>,
+    pub trigger: String,
+}
+
+// 以调用栈采样指定进程，持续 duration 后自行结束
+pub fn start_record(
+    package: &str,
+    pid: &str,
+    duration: Duration,
+    trigger: &str,
+) -> Result<Recording> {
+    let start = Local::now();
+    let remote_path = format!(
+        "{}/{}_{}.perf.data",
+        REMOTE_DIR,
+        package,
+        start.format("%Y%m%d_%H%M%S")
+    );
+
+    let mut command = Command::new("adb");
+    if let Some(serial) = utils::adb_serial() {
+        command.args(["-s", &serial]);
+    }
+    let child = command
+        .args([
+            "shell",
+            "simpleperf",
+            "record",
+            "-p",
+            pid,
+            "-g",
+            "--duration",
+            &duration.as_secs().max(1).to_string(),
+            "-o",
+            &remote_path,
+        ])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to execute adb command")?;
+
+    Ok(Recording {
+        child,
+        remote_path,
+        start,
+        trigger: trigger.to_string(),
+    })
+}
+
+impl Recording {
+    // 已结束时检查退出状态，失败时返回 simpleperf 的错误输出
+    pub fn is_finished(&mut self) -> Result<bool> {
+        let Some(status) = self.child.try_wait()? else {
+            return Ok(false);
+        };
+        if !status.success() {
+            let mut stderr = String::new();
+            if let Some(mut pipe) = self.child.stderr.take() {
+                let _ = pipe.read_to_string(&mut stderr);
+            }
+            anyhow::bail!("simpleperf record failed: {}", stderr.trim());
+        }
+        Ok(true)
+    }
+
+    // 提前结束时发送 SIGINT，simpleperf 收到后写完 perf.data 再退出
+    pub fn stop(&mut self) -> Result<()> {
+        if self.child.try_wait()?.is_none() {
+            let _ = utils::run_adb_command(&["shell", "pkill", "-INT", "-x", "simpleperf"]);
+            self.child.wait()?;
+        }
+        Ok(())
+    }
+}
+
+// 解析 simpleperf report-sample --show-callchain 的文本输出，按线程名为根折叠调用栈
+pub fn parse_report_sample(output: &str) -> CollapsedStacks {
+    let mut stacks = CollapsedStacks::new();
+    let mut thread = String::new();
+    let mut frames: Vec<String> = Vec::new();
+    let mut flush = |thread: &str, frames: &mut Vec<String>| {
+        if frames.is_empty() {
+            return;
+        }
+        // 输出中叶子函数在前，折叠格式要求根在前
+        let mut stack = vec![if thread.is_empty() {
+            "[unknown]"
+        } else {
+            thread
+        }
+        .to_string()];
+        stack.extend(frames.drain(..).rev());
+        *stacks.entry(stack.join(";")).or_insert(0) += 1;
+    };
+
+    for line in output.lines() {
+        let line = line.trim();
+        if line == "sample:" {
+            flush(&thread, &mut frames);
+            thread.clear();
+        } else if let Some(name) = line.strip_prefix("thread_name:") {
+            thread = name.trim().to_string();
+        } else if let Some(symbol) = line.strip_prefix("symbol:") {
+            frames.push(symbol.trim().replace(';', ":"));
+        }
+    }
+    flush(&thread, &mut frames);
+    stacks
+}
+
+// 在设备端用 simpleperf report-sample 符号化并折叠调用栈
+pub fn collapse(remote_path: &str) -> Result<CollapsedStacks> {
+    let output = utils::run_adb_command(&[
+        "shell",
+        "simpleperf",
+        "report-sample",
+        "--show-callchain",
+        "-i",
+        remote_path,
+    ])?;
+    let stacks = parse_report_sample(&output);
+    if stacks.is_empty() {
+        anyhow::bail!("No samples in {}", remote_path);
+    }
+    Ok(stacks)
+}
+
+fn file_name(recording: &Recording) -> &str {
+    recording
+        .remote_path
+        .rsplit('/')
+        .next()
+        .unwrap_or("perf.data")
+}
+
+// 在拉取前生成折叠栈（.folded）和火焰图 SVG，返回两者的路径
+pub fn render_flamegraph(recording: &Recording, output_dir: &Path) -> Result<(PathBuf, PathBuf)> {
+    std::fs::create_dir_all(output_dir)?;
+    let stacks = collapse(&recording.remote_path)?;
+    let stem = file_name(recording).trim_end_matches(".perf.data");
+    let collapsed = output_dir.join(format!("{}.folded", stem));
+    flamegraph::write_collapsed(&collapsed, &stacks)?;
+    let svg = output_dir.join(format!("{}_flamegraph.svg", stem));
+    flamegraph::write_svg(&svg, &stacks, &format!("{} ({})", stem, recording.trigger))?;
+    Ok((collapsed, svg))
+}
+
+// 拉取 perf.data 到本地目录并删除设备端文件
+pub fn pull(recording: &Recording, output_dir: &Path) -> Result<PathBuf> {
+    std::fs::create_dir_all(output_dir)?;
+    let local_path = output_dir.join(file_name(recording));
+    utils::run_adb_command(&[
+        "pull",
+        &recording.remote_path,
+        &local_path.to_string_lossy(),
+    ])?;
+    let _ = utils::run_adb_command(&["shell", "rm", "-f", &recording.remote_path]);
+    Ok(local_path)
+}
+
+// 拉取 simpleperf 采样（按需先生成火焰图），返回其时间窗口
+fn finish_profile(
+    package: &str,
+    recording: &Recording,
+    end: DateTime<Local>,
+    flamegraph: bool,
+) -> Result<CaptureWindow> {
+    let dir = OutputLayout::session(package)?.dir(Category::Simpleperf);
+    if flamegraph {
+        match render_flamegraph(recording, &dir) {
+            Ok((_, svg)) => console!("✓ Flamegraph generated: {}", svg.display()),
+            Err(e) => console!("Failed to generate flamegraph: {}", e),
+        }
+    }
+    let path = match pull(recording, &dir) {
+        Ok(path) => {
+            console!("✓ simpleperf profile pulled: {}", path.display());
+            Some(path)
+        }
+        Err(e) => {
+            console!("Failed to pull simpleperf profile: {}", e);
+            None
+        }
+    };
+    Ok(CaptureWindow {
+        kind: "Simpleperf",
+        start: recording.start,
+        end,
+        trigger: recording.trigger.clone(),
+        path,
+    })
+}
+
+// --profile-on-spike：CPU 超过阈值时的 simpleperf 采样，同一时间最多一个
+pub struct ProfileCollector {
+    package: String,
+    threshold: f64,
+    duration: Duration,
+    flamegraph: bool,
+    profiling: Option<Recording>,
+    attempts: usize,
+    windows: Vec<CaptureWindow>,
+}
+
+impl ProfileCollector {
+    pub fn new(package: &str, threshold: f64, duration: Duration, flamegraph: bool) -> Self {
+        Self {
+            package: package.to_string(),
+            threshold,
+            duration,
+            flamegraph,
+            profiling: None,
+            attempts: 0,
+            windows: Vec::new(),
+        }
+    }
+}
+
+impl Collector for ProfileCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        let tick = ctx.tick;
+        let finished = match self.profiling.as_mut().map(Recording::is_finished) {
+            Some(Ok(finished)) => finished,
+            Some(Err(e)) => {
+                console!("{}", e);
+                self.profiling = None;
+                false
+            }
+            None => false,
+        };
+        if let Some(recording) = self.profiling.take_if(|_| finished) {
+            let end = tick.timestamp.min(
+                recording.start + chrono::Duration::from_std(self.duration).unwrap_or_default(),
+            );
+            self.windows.push(finish_profile(
+                &self.package,
+                &recording,
+                end,
+                self.flamegraph,
+            )?);
+        }
+
+        let spike = ctx
+            .sample
+            .cpu
+            .filter(|cpu| f64::from(*cpu) >= self.threshold);
+        if let Some(cpu) = spike.filter(|_| {
+            !ctx.in_warmup
+                && self.profiling.is_none()
+                && self.attempts < capture::MAX_TRIGGERED_TRACES
+                && !replay::is_replaying()
+        }) {
+            self.attempts += 1;
+            let trigger = format!("CPU {:.1}%", cpu);
+            match start_record(&self.package, &ctx.sample.pid, self.duration, &trigger) {
+                Ok(recording) => {
+                    console!(
+                        "[{}] simpleperf recording started ({}s): {}",
+                        tick.timestamp.format("%H:%M:%S").to_string().blue(),
+                        self.duration.as_secs(),
+                        trigger.yellow()
+                    );
+                    self.profiling = Some(recording);
+                }
+                Err(e) => console!("Failed to start simpleperf: {}", e),
+            }
+        }
+        Ok(())
+    }
+
+    // 提前结束时 simpleperf 仍在采样，停止后照常拉取
+    fn stop(&mut self) {
+        let Some(mut recording) = self.profiling.take() else {
+            return;
+        };
+        if let Err(e) = recording.stop() {
+            console!("Failed to stop simpleperf: {}", e);
+            return;
+        }
+        match finish_profile(&self.package, &recording, Local::now(), self.flamegraph) {
+            Ok(window) => self.windows.push(window),
+            Err(e) => console!("Failed to pull simpleperf profile: {}", e),
+        }
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        capture::report_windows(
+            "simpleperf profiles:",
+            Category::Simpleperf,
+            &self.windows,
+            ctx,
+        );
+    }
+
+    fn cpu_overlay(&self, overlay: &mut CpuChartOverlay) {
+        overlay.markers.extend(capture::markers(&self.windows));
+    }
+}
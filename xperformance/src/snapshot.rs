@@ -0,0 +1,277 @@
+use crate::layout::{Category, OutputLayout};
+use crate::recorder::{Collector, TickContext};
+use crate::utils;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use colored::*;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+
+// App Summary 类别前缀，与明细表中的同名行区分
+const SUMMARY_PREFIX: &str = "Summary: ";
+
+// 带标签的完整内存快照，类别按 dumpsys meminfo 中出现的顺序保存
+#[derive(Debug, Clone)]
+pub struct MemorySnapshot {
+    pub index: usize,
+    pub label: String,
+    pub timestamp: DateTime<Local>,
+    pub categories: Vec<(String, u64)>,
+    pub raw: String,
+}
+
+// 单个类别在两次快照之间的变化
+#[derive(Debug, Clone)]
+pub struct CategoryDelta {
+    pub category: String,
+    pub before_kb: u64,
+    pub after_kb: u64,
+}
+
+impl CategoryDelta {
+    pub fn delta_kb(&self) -> i64 {
+        self.after_kb as i64 - self.before_kb as i64
+    }
+}
+
+// 解析 dumpsys meminfo：App Summary 之前的明细表取 Pss Total 列，App Summary 取 Pss 列
+pub fn parse_meminfo(output: &str) -> Vec<(String, u64)> {
+    let mut categories = Vec::new();
+    let mut in_summary = false;
+    for line in output.lines() {
+        let line = line.trim();
+        if line.starts_with("App Summary") {
+            in_summary = true;
+            continue;
+        }
+        if in_summary {
+            // Objects 等后续部分每行有多对键值，不属于内存类别
+            if line.starts_with("Objects") {
+                break;
+            }
+            if let Some((name, values)) = line.split_once(':') {
+                if let Some(kb) = values
+                    .split_whitespace()
+                    .next()
+                    .and_then(|v| v.parse().ok())
+                {
+                    categories.push((format!("{}{}", SUMMARY_PREFIX, name.trim()), kb));
+                }
+            }
+            continue;
+        }
+
+        // 明细行形如 "Native Heap    12345    12000 ..."，名称为第一个数字之前的部分
+        // "** MEMINFO in pid ... **" 标题行跳过
+        if line.starts_with('*') {
+            continue;
+        }
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let Some(first_number) = tokens.iter().position(|t| t.parse::<u64>().is_ok()) else {
+            continue;
+        };
+        if first_number == 0 {
+            continue;
+        }
+        let name = tokens[..first_number].join(" ");
+        if name.ends_with(':') || categories.iter().any(|(n, _)| *n == name) {
+            continue;
+        }
+        if let Ok(kb) = tokens[first_number].parse() {
+            categories.push((name, kb));
+        }
+    }
+    categories
+}
+
+// 抓取应用当前的完整内存快照
+pub fn capture(package: &str, index: usize, label: &str) -> Result<MemorySnapshot> {
+    let timestamp = Local::now();
+    let raw = utils::run_adb_command(&["shell", "dumpsys", "meminfo", package])?;
+    let categories = parse_meminfo(&raw);
+    if categories.is_empty() {
+        anyhow::bail!("No meminfo categories found for {}", package);
+    }
+    Ok(MemorySnapshot {
+        index,
+        label: label.to_string(),
+        timestamp,
+        categories,
+        raw,
+    })
+}
+
+impl MemorySnapshot {
+    fn get(&self, category: &str) -> u64 {
+        self.categories
+            .iter()
+            .find(|(name, _)| name == category)
+            .map(|(_, kb)| *kb)
+            .unwrap_or(0)
+    }
+
+    // 文件名中只保留字母数字，其余替换为下划线
+    pub fn file_stem(&self) -> String {
+        let label: String = self
+            .label
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '_' })
+            .collect();
+        format!("{:02}_{}", self.index, label)
+    }
+
+    // 与更早的快照逐类别对比，按变化量绝对值降序
+    pub fn diff(&self, before: &MemorySnapshot) -> Vec<CategoryDelta> {
+        let mut names: Vec<&str> = before.categories.iter().map(|(n, _)| n.as_str()).collect();
+        for (name, _) in &self.categories {
+            if !names.contains(&name.as_str()) {
+                names.push(name);
+            }
+        }
+        let mut deltas: Vec<CategoryDelta> = names
+            .into_iter()
+            .map(|name| CategoryDelta {
+                category: name.to_string(),
+                before_kb: before.get(name),
+                after_kb: self.get(name),
+            })
+            .collect();
+        deltas.sort_by_key(|d| std::cmp::Reverse(d.delta_kb().abs()));
+        deltas
+    }
+}
+
+// 一行摘要，如 "after opening gallery: +42.0MB Graphics, +8.0MB Native Heap"
+// 只列 App Summary 类别（不含 TOTAL），忽略 1MB 以内的变化
+pub fn summarize(label: &str, deltas: &[CategoryDelta]) -> String {
+    let parts: Vec<String> = deltas
+        .iter()
+        .filter_map(|d| {
+            let name = d.category.strip_prefix(SUMMARY_PREFIX)?;
+            (!name.starts_with("TOTAL") && d.delta_kb().abs() >= 1024)
+                .then(|| format!("{:+.1}MB {}", d.delta_kb() as f64 / 1024.0, name))
+        })
+        .collect();
+    let total = deltas
+        .iter()
+        .find(|d| d.category.starts_with(&format!("{}TOTAL", SUMMARY_PREFIX)))
+        .map(|d| format!(" (total {:+.1}MB)", d.delta_kb() as f64 / 1024.0))
+        .unwrap_or_default();
+    if parts.is_empty() {
+        format!("{}: no category changed by 1MB or more{}", label, total)
+    } else {
+        format!("{}: {}{}", label, parts.join(", "), total)
+    }
+}
+
+// 保存快照原文，以及与上一快照的逐类别差异
+pub fn write_snapshot(
+    dir: &Path,
+    snapshot: &MemorySnapshot,
+    previous: Option<&MemorySnapshot>,
+) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let mut paths = Vec::new();
+
+    let raw_path = dir.join(format!("{}.txt", snapshot.file_stem()));
+    std::fs::write(&raw_path, &snapshot.raw)?;
+    paths.push(raw_path);
+
+    if let Some(previous) = previous {
+        let diff_path = dir.join(format!(
+            "{}_vs_{}_diff.csv",
+            snapshot.file_stem(),
+            previous.file_stem()
+        ));
+        let mut file = std::fs::File::create(&diff_path)?;
+        writeln!(file, "Category,Before (KB),After (KB),Delta (KB)")?;
+        for delta in snapshot.diff(previous) {
+            writeln!(
+                file,
+                "{},{},{},{}",
+                delta.category.replace(',', ";"),
+                delta.before_kb,
+                delta.after_kb,
+                delta.delta_kb()
+            )?;
+        }
+        paths.push(diff_path);
+    }
+
+    Ok(paths)
+}
+
+// 按需内存快照：后台线程读取标准输入，每输入一行（标签）抓取一次快照，与上一快照逐类别对比
+pub struct SnapshotCollector {
+    requests: Receiver<String>,
+    snapshots: Vec<MemorySnapshot>,
+}
+
+impl SnapshotCollector {
+    pub fn start() -> Self {
+        let (sender, requests) = mpsc::channel::<String>();
+        std::thread::spawn(move || {
+            for line in std::io::stdin().lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if sender.send(line.trim().to_string()).is_err() {
+                    break;
+                }
+            }
+        });
+        console!(
+            "{}",
+            "Memory snapshots: type a label (e.g. after opening gallery) and press Enter".green()
+        );
+        Self {
+            requests,
+            snapshots: Vec::new(),
+        }
+    }
+}
+
+impl Collector for SnapshotCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        while let Ok(label) = self.requests.try_recv() {
+            let index = self.snapshots.len() + 1;
+            let label = if label.is_empty() {
+                format!("snapshot {}", index)
+            } else {
+                label
+            };
+            let snapshot = match capture(ctx.package, index, &label) {
+                Ok(snapshot) => snapshot,
+                Err(e) => {
+                    console!("Failed to capture memory snapshot: {}", e);
+                    continue;
+                }
+            };
+            let previous = self.snapshots.last();
+            match previous {
+                Some(previous) => console!(
+                    "[{}] {} {}",
+                    snapshot.timestamp.format("%H:%M:%S").to_string().blue(),
+                    "Memory snapshot".green(),
+                    summarize(
+                        &format!("{} (vs {})", label, previous.label),
+                        &snapshot.diff(previous)
+                    )
+                ),
+                None => console!(
+                    "[{}] {} '{}' captured as baseline",
+                    snapshot.timestamp.format("%H:%M:%S").to_string().blue(),
+                    "Memory snapshot".green(),
+                    label
+                ),
+            }
+            let snapshots_dir = OutputLayout::session(ctx.package)?.dir(Category::Snapshots);
+            if let Err(e) = write_snapshot(&snapshots_dir, &snapshot, previous) {
+                console!("Failed to save memory snapshot: {}", e);
+            }
+            self.snapshots.push(snapshot);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,230 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::io::Write;
+use std::path::Path;
+
+// 数值序列的统计摘要
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Summary {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub median: f64,
+    pub p90: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub stddev: f64,
+}
+
+impl Summary {
+    pub fn from_values(values: &[f64]) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+
+        let mut sorted = values.to_vec();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+        let count = sorted.len();
+        let mean = sorted.iter().sum::<f64>() / count as f64;
+        let variance = sorted.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / count as f64;
+
+        Some(Self {
+            count,
+            min: sorted[0],
+            max: sorted[count - 1],
+            mean,
+            median: percentile(&sorted, 50.0),
+            p90: percentile(&sorted, 90.0),
+            p95: percentile(&sorted, 95.0),
+            p99: percentile(&sorted, 99.0),
+            stddev: variance.sqrt(),
+        })
+    }
+
+    // 终端输出用的一行摘要
+    pub fn describe(&self) -> String {
+        format!(
+            "mean {:.1}, median {:.1}, p90 {:.1}, p95 {:.1}, p99 {:.1}, stddev {:.1} ({} samples)",
+            self.mean, self.median, self.p90, self.p95, self.p99, self.stddev, self.count
+        )
+    }
+
+    pub const CSV_HEADER: &'static str = "Count,Min,Max,Mean,Median,P90,P95,P99,StdDev";
+
+    pub fn to_csv_row(&self) -> String {
+        format!(
+            "{},{:.2},{:.2},{:.2},{:.2},{:.2},{:.2},{:.2},{:.2}",
+            self.count,
+            self.min,
+            self.max,
+            self.mean,
+            self.median,
+            self.p90,
+            self.p95,
+            self.p99,
+            self.stddev
+        )
+    }
+}
+
+// 每个指标一行的统计摘要 CSV
+pub fn write_summary_csv(path: &Path, rows: &[(String, Summary)]) -> Result<()> {
+    let mut file = std::fs::File::create(path)?;
+    writeln!(file, "Metric,{}", Summary::CSV_HEADER)?;
+    for (metric, summary) in rows {
+        writeln!(file, "{},{}", metric, summary.to_csv_row())?;
+    }
+    Ok(())
+}
+
+// 对已排序的数据计算百分位数（线性插值）
+pub fn percentile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (p / 100.0).clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    let weight = rank - lower as f64;
+    sorted[lower] * (1.0 - weight) + sorted[upper] * weight
+}
+
+// Welch t 检验的双侧 p 值，不要求两组方差相等；任一组少于两个值时为 None
+pub fn welch_t_test(a: &[f64], b: &[f64]) -> Option<f64> {
+    if a.len() < 2 || b.len() < 2 {
+        return None;
+    }
+    // 样本方差（n - 1）
+    let moments = |values: &[f64]| {
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        (n, mean, variance)
+    };
+    let (na, mean_a, var_a) = moments(a);
+    let (nb, mean_b, var_b) = moments(b);
+    let (se_a, se_b) = (var_a / na, var_b / nb);
+    let se = se_a + se_b;
+    // 两组都没有波动时，均值不同即为显著
+    if se <= f64::EPSILON {
+        return Some(if (mean_a - mean_b).abs() <= f64::EPSILON {
+            1.0
+        } else {
+            0.0
+        });
+    }
+    let t = (mean_b - mean_a) / se.sqrt();
+    let df = se.powi(2) / (se_a.powi(2) / (na - 1.0) + se_b.powi(2) / (nb - 1.0));
+    Some(incomplete_beta(df / 2.0, 0.5, df / (df + t * t)).clamp(0.0, 1.0))
+}
+
+// Lanczos 近似的 ln Γ(x)
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.180_091_729_471_46,
+        -86.505_320_329_416_77,
+        24.014_098_240_830_91,
+        -1.231_739_572_450_155,
+        0.001_208_650_973_866_179,
+        -0.000_005_395_239_384_953,
+    ];
+    let tmp = x + 5.5 - (x + 0.5) * (x + 5.5).ln();
+    let series = COEFFICIENTS
+        .iter()
+        .enumerate()
+        .fold(1.000_000_000_190_015, |sum, (i, c)| {
+            sum + c / (x + 1.0 + i as f64)
+        });
+    -tmp + (2.506_628_274_631_000_5 * series / x).ln()
+}
+
+// 正则化不完全 Beta 函数 I_x(a, b)，连分式展开
+fn incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let front =
+        (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+    // 连分式在 x < (a + 1) / (a + b + 2) 时收敛较快，否则用对称关系
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_fraction(a, b, x) / a
+    } else {
+        1.0 - front * beta_fraction(b, a, 1.0 - x) / b
+    }
+}
+
+fn beta_fraction(a: f64, b: f64, x: f64) -> f64 {
+    const TINY: f64 = 1e-30;
+    let clamp = |v: f64| if v.abs() < TINY { TINY } else { v };
+    let mut c = 1.0;
+    let mut d = 1.0 / clamp(1.0 - (a + b) * x / (a + 1.0));
+    let mut result = d;
+    for m in 1..200 {
+        let m = m as f64;
+        let even = m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m));
+        d = 1.0 / clamp(1.0 + even * d);
+        c = clamp(1.0 + even / c);
+        result *= d * c;
+        let odd = -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0));
+        d = 1.0 / clamp(1.0 + odd * d);
+        c = clamp(1.0 + odd / c);
+        let step = d * c;
+        result *= step;
+        if (step - 1.0).abs() < 1e-12 {
+            break;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentile_interpolates_between_ranks() {
+        let sorted = [1.0, 2.0, 3.0, 4.0];
+        assert_eq!(percentile(&sorted, 0.0), 1.0);
+        assert_eq!(percentile(&sorted, 50.0), 2.5);
+        assert_eq!(percentile(&sorted, 100.0), 4.0);
+        let sorted: Vec<f64> = (1..=10).map(f64::from).collect();
+        assert!((percentile(&sorted, 90.0) - 9.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn percentile_edge_cases() {
+        assert_eq!(percentile(&[], 50.0), 0.0);
+        assert_eq!(percentile(&[7.0], 99.0), 7.0);
+        // 超出 0..=100 时取首尾
+        assert_eq!(percentile(&[1.0, 2.0], 150.0), 2.0);
+        assert_eq!(percentile(&[1.0, 2.0], -10.0), 1.0);
+    }
+
+    #[test]
+    fn summary_sorts_values() {
+        assert!(Summary::from_values(&[]).is_none());
+        let summary = Summary::from_values(&[4.0, 1.0, 3.0, 2.0]).unwrap();
+        assert_eq!(summary.count, 4);
+        assert_eq!(summary.min, 1.0);
+        assert_eq!(summary.max, 4.0);
+        assert_eq!(summary.mean, 2.5);
+        assert_eq!(summary.median, 2.5);
+        assert!((summary.stddev - 1.25f64.sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn welch_t_test_p_values() {
+        assert!(welch_t_test(&[1.0], &[1.0, 2.0]).is_none());
+        assert_eq!(welch_t_test(&[5.0, 5.0], &[5.0, 5.0]), Some(1.0));
+        assert_eq!(welch_t_test(&[5.0, 5.0], &[6.0, 6.0]), Some(0.0));
+        let a = [10.1, 9.8, 10.0, 10.3, 9.9, 10.2];
+        let b = [12.0, 12.4, 11.8, 12.1, 12.3, 11.9];
+        assert!(welch_t_test(&a, &b).unwrap() < 0.001);
+        assert!(welch_t_test(&a, &a).unwrap() > 0.99);
+    }
+}
@@ -0,0 +1,295 @@
+use crate::layout::Category;
+use crate::recorder::FinishContext;
+use crate::regenerate::CSV_TIMESTAMP_FORMAT;
+use crate::tick::Tick;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use colored::*;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// 线程策略的违规类型（通常在主线程上检测），其余为 VM 策略（泄漏、URI 暴露等）
+const THREAD_POLICY: [&str; 7] = [
+    "DiskRead",
+    "DiskWrite",
+    "Network",
+    "Custom",
+    "ResourceMismatch",
+    "UnbufferedIo",
+    "ExplicitGc",
+];
+
+// logcat 中的一次 StrictMode 违规
+#[derive(Debug, Clone)]
+pub struct Violation {
+    pub timestamp: DateTime<Local>,
+    pub pid: String,
+    // 违规类名去掉前后缀，如 DiskRead、LeakedClosable
+    pub kind: String,
+    // 线程策略违规的耗时（~duration）
+    pub duration_ms: Option<f64>,
+}
+
+impl Violation {
+    pub fn policy(&self) -> &'static str {
+        policy(&self.kind)
+    }
+}
+
+pub fn policy(kind: &str) -> &'static str {
+    if THREAD_POLICY.contains(&kind) {
+        "thread"
+    } else {
+        "vm"
+    }
+}
+
+// 违规类名：android.os.strictmode.DiskReadViolation -> DiskRead，
+// Android 9 之前为 android.os.StrictMode$StrictModeDiskReadViolation
+fn violation_kind(class: &str) -> Option<String> {
+    let name = class.trim().rsplit(['.', '$']).next()?;
+    let name = name.strip_suffix("Violation")?;
+    let name = name.strip_prefix("StrictMode").unwrap_or(name);
+    (!name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric())).then(|| name.to_string())
+}
+
+// threadtime 格式的 StrictMode 行，调用栈在其后的行中：
+// "10-15 12:34:56.789  1234  1234 D StrictMode: StrictMode policy violation; ~duration=33 ms: android.os.strictmode.DiskReadViolation"
+// "10-15 12:34:56.789  1234  1250 D StrictMode: StrictMode policy violation: android.os.strictmode.LeakedClosableViolation: A resource was acquired ..."
+pub fn parse_violation_line(line: &str) -> Option<Violation> {
+    let (header, message) = line.split_once(": ")?;
+    let mut fields = header.split_whitespace();
+    let pid = fields.nth(2)?;
+    if fields.last()? != "StrictMode" || !pid.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let rest = message.trim().strip_prefix("StrictMode policy violation")?;
+    let duration_ms = rest
+        .split_once("~duration=")
+        .and_then(|(_, d)| d.split_whitespace().next()?.parse().ok());
+    let kind = rest.split(": ").find_map(violation_kind)?;
+    Some(Violation {
+        timestamp: Local::now(),
+        pid: pid.to_string(),
+        kind,
+        duration_ms,
+    })
+}
+
+// 同一类型违规的汇总
+#[derive(Debug, Clone, Default)]
+pub struct ViolationStats {
+    pub kind: String,
+    pub count: usize,
+    pub total_ms: f64,
+    pub max_ms: f64,
+    pub first: Option<DateTime<Local>>,
+}
+
+// 按类型汇总，次数降序
+pub fn violation_stats(violations: &[Violation]) -> Vec<ViolationStats> {
+    let mut by_kind: BTreeMap<&str, ViolationStats> = BTreeMap::new();
+    for violation in violations {
+        let stats = by_kind
+            .entry(&violation.kind)
+            .or_insert_with(|| ViolationStats {
+                kind: violation.kind.clone(),
+                first: Some(violation.timestamp),
+                ..Default::default()
+            });
+        let ms = violation.duration_ms.unwrap_or(0.0);
+        stats.count += 1;
+        stats.total_ms += ms;
+        stats.max_ms = stats.max_ms.max(ms);
+    }
+    let mut stats: Vec<ViolationStats> = by_kind.into_values().collect();
+    stats.sort_by_key(|s| std::cmp::Reverse(s.count));
+    stats
+}
+
+// 导出逐条违规 CSV 和按类型汇总的 CSV
+pub fn export_violations(
+    dir: &Path,
+    package: &str,
+    violations: &[Violation],
+) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let mut paths = Vec::new();
+
+    let csv_path = dir.join(format!("{}_strictmode.csv", package));
+    let mut file = std::fs::File::create(&csv_path)?;
+    writeln!(file, "Timestamp,PID,Type,Policy,Duration (ms)")?;
+    for violation in violations {
+        writeln!(
+            file,
+            "{},{},{},{},{}",
+            violation.timestamp.format(CSV_TIMESTAMP_FORMAT),
+            violation.pid,
+            violation.kind,
+            violation.policy(),
+            violation
+                .duration_ms
+                .map(|ms| format!("{:.0}", ms))
+                .unwrap_or_default()
+        )?;
+    }
+    paths.push(csv_path);
+
+    let summary_path = dir.join(format!("{}_strictmode_summary.csv", package));
+    let mut file = std::fs::File::create(&summary_path)?;
+    writeln!(
+        file,
+        "Type,Policy,Count,Total Duration (ms),Max Duration (ms),First Seen"
+    )?;
+    for stats in violation_stats(violations) {
+        writeln!(
+            file,
+            "{},{},{},{:.0},{:.0},{}",
+            stats.kind,
+            policy(&stats.kind),
+            stats.count,
+            stats.total_ms,
+            stats.max_ms,
+            stats
+                .first
+                .map(|t| t.format(CSV_TIMESTAMP_FORMAT).to_string())
+                .unwrap_or_default()
+        )?;
+    }
+    paths.push(summary_path);
+
+    Ok(paths)
+}
+
+// --strict-mode：应用进程的违规由 LogcatCollector 从同一 logcat 读取后交给这里计数
+#[derive(Default)]
+pub struct StrictModeTracker {
+    violations: Vec<Violation>,
+}
+
+impl StrictModeTracker {
+    // 每种类型首次出现时提示，之后只计数
+    pub fn record(&mut self, tick: &Tick, violations: Vec<Violation>) {
+        for violation in violations {
+            if !self
+                .violations
+                .iter()
+                .any(|seen| seen.kind == violation.kind)
+            {
+                console!(
+                    "[{}] {} {} ({} policy{})",
+                    tick.timestamp.format("%H:%M:%S").to_string().blue(),
+                    "StrictMode:".yellow().bold(),
+                    violation.kind,
+                    violation.policy(),
+                    violation
+                        .duration_ms
+                        .map(|ms| format!(", {:.0} ms", ms))
+                        .unwrap_or_default()
+                );
+            }
+            self.violations.push(violation);
+        }
+    }
+
+    pub fn extend(&mut self, violations: Vec<Violation>) {
+        self.violations.extend(violations);
+    }
+
+    // 按类型汇总的表格，并导出
+    pub fn finish(&self, ctx: &FinishContext) {
+        if self.violations.is_empty() {
+            console!("StrictMode violations: {}", "none".green());
+            return;
+        }
+        console!(
+            "StrictMode violations: {}",
+            self.violations.len().to_string().red().bold()
+        );
+        console!(
+            "  {:<28} {:<6} {:>6} {:>12} {:>10}  {}",
+            "Type",
+            "Policy",
+            "Count",
+            "Total (ms)",
+            "Max (ms)",
+            "First seen"
+        );
+        for stats in violation_stats(&self.violations) {
+            console!(
+                "  {:<28} {:<6} {:>6} {:>12.0} {:>10.0}  {}",
+                stats.kind,
+                policy(&stats.kind),
+                stats.count,
+                stats.total_ms,
+                stats.max_ms,
+                stats
+                    .first
+                    .map(|t| t.format("%H:%M:%S").to_string())
+                    .unwrap_or_default()
+            );
+        }
+        match export_violations(
+            &ctx.layout.dir(Category::Process),
+            ctx.package,
+            &self.violations,
+        ) {
+            Ok(paths) => {
+                for path in paths {
+                    console!("✓ StrictMode violations exported: {}", path.display());
+                }
+            }
+            Err(e) => console!("Failed to export StrictMode violations: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_violation_lines() {
+        let violation = parse_violation_line(
+            "10-15 12:34:56.789  1234  1234 D StrictMode: StrictMode policy violation; ~duration=33 ms: android.os.strictmode.DiskReadViolation",
+        )
+        .unwrap();
+        assert_eq!(violation.pid, "1234");
+        assert_eq!(violation.kind, "DiskRead");
+        assert_eq!(violation.duration_ms, Some(33.0));
+        assert_eq!(violation.policy(), "thread");
+
+        let violation = parse_violation_line(
+            "10-15 12:34:56.789  1234  1250 D StrictMode: StrictMode policy violation: android.os.StrictMode$StrictModeLeakedClosableViolation: A resource was acquired at attached stack trace but never released.",
+        )
+        .unwrap();
+        assert_eq!(violation.kind, "LeakedClosable");
+        assert_eq!(violation.duration_ms, None);
+        assert_eq!(violation.policy(), "vm");
+
+        assert!(parse_violation_line(
+            "10-15 12:34:56.789  1234  1234 D StrictMode: \tat java.io.FileInputStream.<init>(FileInputStream.java:159)"
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn violation_stats_by_count() {
+        let violation = |kind: &str, duration_ms| Violation {
+            timestamp: Local::now(),
+            pid: "1234".into(),
+            kind: kind.into(),
+            duration_ms,
+        };
+        let stats = violation_stats(&[
+            violation("LeakedClosable", None),
+            violation("DiskRead", Some(10.0)),
+            violation("DiskRead", Some(30.0)),
+        ]);
+        assert_eq!(stats[0].kind, "DiskRead");
+        assert_eq!(stats[0].count, 2);
+        assert_eq!((stats[0].total_ms, stats[0].max_ms), (40.0, 30.0));
+        assert_eq!(stats[1].count, 1);
+    }
+}
@@ -0,0 +1,111 @@
+use crate::utils;
+use anyhow::Result;
+
+// 尚未显示的帧，actualPresent 为 INT64_MAX
+const PENDING_FENCE: u64 = i64::MAX as u64;
+
+// 相邻两帧间隔超过该值视为画面静止，而非卡顿
+const IDLE_GAP_MS: f64 = 1000.0;
+
+// `dumpsys SurfaceFlinger --latency <layer>` 的输出：首行为刷新周期（ns），
+// 之后每行为 desiredPresent、actualPresent、frameReady 三个时间戳（ns），最多约 128 帧
+#[derive(Debug, Clone, Default)]
+pub struct LayerLatency {
+    pub refresh_period_ns: u64,
+    pub present_times: Vec<u64>,
+}
+
+pub fn parse_latency(output: &str) -> LayerLatency {
+    let mut lines = output.lines().map(str::trim).filter(|l| !l.is_empty());
+    let refresh_period_ns = lines.next().and_then(|l| l.parse().ok()).unwrap_or(0);
+    let mut present_times: Vec<u64> = lines
+        .filter_map(|line| {
+            let fields: Vec<u64> = line
+                .split_whitespace()
+                .filter_map(|v| v.parse().ok())
+                .collect();
+            match fields.as_slice() {
+                [_, actual, _] if *actual != 0 && *actual < PENDING_FENCE => Some(*actual),
+                _ => None,
+            }
+        })
+        .collect();
+    present_times.sort_unstable();
+    LayerLatency {
+        refresh_period_ns,
+        present_times,
+    }
+}
+
+// 在 `dumpsys SurfaceFlinger --list` 中查找应用的图层，优先 SurfaceView（游戏和视频的实际绘制图层）
+pub fn discover_layer(package: &str) -> Option<String> {
+    let output = utils::run_adb_command(&["shell", "dumpsys", "SurfaceFlinger", "--list"]).ok()?;
+    let layers: Vec<&str> = output
+        .lines()
+        .map(str::trim)
+        .filter(|l| l.contains(package) && !l.contains("Background"))
+        .collect();
+    layers
+        .iter()
+        .find(|l| l.starts_with("SurfaceView"))
+        .or_else(|| layers.iter().find(|l| l.contains('/')))
+        .or_else(|| layers.first())
+        .map(|l| l.to_string())
+}
+
+// 按图层采样帧显示时间；图层未找到或被重建（如切换 Activity）时每次采样重新查找
+pub struct LatencySampler {
+    package: String,
+    layer: Option<String>,
+    last_present: u64,
+}
+
+impl LatencySampler {
+    pub fn new(package: &str) -> Self {
+        Self {
+            package: package.to_string(),
+            layer: discover_layer(package),
+            last_present: 0,
+        }
+    }
+
+    pub fn layer(&self) -> Option<&str> {
+        self.layer.as_deref()
+    }
+
+    // 返回新帧的帧间隔（ms）和卡顿帧数；帧间隔超过 1.5 个刷新周期（错过至少一个 vsync）记为卡顿
+    pub fn sample(&mut self) -> Result<(Vec<f64>, usize)> {
+        if self.layer.is_none() {
+            self.layer = discover_layer(&self.package);
+        }
+        let Some(layer) = self.layer.clone() else {
+            anyhow::bail!("No SurfaceFlinger layer found for {}", self.package);
+        };
+        let output =
+            utils::run_adb_command(&["shell", "dumpsys", "SurfaceFlinger", "--latency", &layer])?;
+        let latency = parse_latency(&output);
+        if latency.refresh_period_ns == 0 {
+            self.layer = None;
+            anyhow::bail!("Layer {} is gone", layer);
+        }
+
+        let budget_ms = latency.refresh_period_ns as f64 / 1_000_000.0;
+        let mut durations = Vec::new();
+        let mut janky = 0;
+        for present in latency.present_times {
+            if present <= self.last_present {
+                continue;
+            }
+            // 首帧没有上一帧作为基准，只作为起点；画面静止后恢复刷新的间隔不计入
+            let interval_ms = (present - self.last_present) as f64 / 1_000_000.0;
+            if self.last_present > 0 && interval_ms <= IDLE_GAP_MS {
+                if interval_ms > budget_ms * 1.5 {
+                    janky += 1;
+                }
+                durations.push(interval_ms);
+            }
+            self.last_present = present;
+        }
+        Ok((durations, janky))
+    }
+}
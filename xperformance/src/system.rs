@@ -0,0 +1,615 @@
+use crate::chart::{self, TimeSeriesData};
+use crate::layout::Category;
+use crate::recorder::{Collector, FinishContext, TickContext};
+use crate::stats::Summary;
+use crate::utils;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use colored::*;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const SECTION_SEPARATOR: &str = "---";
+
+// /proc/stat 第一行 "cpu" 的累计 jiffies，idle 包含 iowait
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CpuTimes {
+    pub busy: u64,
+    pub total: u64,
+}
+
+impl CpuTimes {
+    // 两次读数之间整机 CPU 占用（0-100%，所有核心合计）
+    pub fn usage_since(&self, earlier: &CpuTimes) -> Option<f64> {
+        let total = self.total.checked_sub(earlier.total)?;
+        let busy = self.busy.checked_sub(earlier.busy)?;
+        (total > 0).then(|| busy as f64 * 100.0 / total as f64)
+    }
+}
+
+// "cpu  user nice system idle iowait irq softirq steal ..."
+pub fn parse_cpu_times(stat: &str) -> Option<CpuTimes> {
+    let line = stat.lines().find(|line| line.starts_with("cpu "))?;
+    parse_cpu_line(line)
+}
+
+// 单行 "cpu" 或 "cpuN" 的 jiffies
+pub fn parse_cpu_line(line: &str) -> Option<CpuTimes> {
+    let values: Vec<u64> = line
+        .split_whitespace()
+        .skip(1)
+        .take(8)
+        .filter_map(|v| v.parse().ok())
+        .collect();
+    if values.len() < 4 {
+        return None;
+    }
+    let idle = values[3] + values.get(4).copied().unwrap_or(0);
+    let total: u64 = values.iter().sum();
+    Some(CpuTimes {
+        busy: total.saturating_sub(idle),
+        total,
+    })
+}
+
+// /proc/meminfo 中的整机内存（KB）
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MemInfo {
+    pub total_kb: u64,
+    pub available_kb: u64,
+    pub free_kb: u64,
+    pub cached_kb: u64,
+    pub swap_total_kb: u64,
+    pub swap_free_kb: u64,
+}
+
+impl MemInfo {
+    pub fn used_kb(&self) -> u64 {
+        self.total_kb.saturating_sub(self.available_kb)
+    }
+
+    pub fn swap_used_kb(&self) -> u64 {
+        self.swap_total_kb.saturating_sub(self.swap_free_kb)
+    }
+}
+
+// 没有 MemTotal 时返回 None；旧内核没有 MemAvailable，用 MemFree + Cached 代替
+pub fn parse_proc_meminfo(text: &str) -> Option<MemInfo> {
+    let mut info = MemInfo::default();
+    let mut available = None;
+    for line in text.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let Some(kb) = value
+            .split_whitespace()
+            .next()
+            .and_then(|v| v.parse::<u64>().ok())
+        else {
+            continue;
+        };
+        match key.trim() {
+            "MemTotal" => info.total_kb = kb,
+            "MemAvailable" => available = Some(kb),
+            "MemFree" => info.free_kb = kb,
+            "Cached" => info.cached_kb = kb,
+            "SwapTotal" => info.swap_total_kb = kb,
+            "SwapFree" => info.swap_free_kb = kb,
+            _ => {}
+        }
+    }
+    if info.total_kb == 0 {
+        return None;
+    }
+    info.available_kb = available.unwrap_or(info.free_kb + info.cached_kb);
+    Some(info)
+}
+
+pub fn sample_meminfo() -> Result<MemInfo> {
+    let output = utils::run_adb_command(&["shell", "cat", "/proc/meminfo"])?;
+    parse_proc_meminfo(&output).ok_or_else(|| anyhow::format_err!("No MemTotal in /proc/meminfo"))
+}
+
+// 一次采样的整机 CPU 和内存，首次采样没有基线，CPU 为 None
+#[derive(Debug, Clone, Copy)]
+pub struct SystemSample {
+    pub timestamp: DateTime<Local>,
+    pub cpu: Option<f64>,
+    pub memory: MemInfo,
+}
+
+#[derive(Default)]
+pub struct SystemSampler {
+    previous: Option<CpuTimes>,
+}
+
+impl SystemSampler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 一次 adb 调用读取 /proc/stat 和 /proc/meminfo
+    pub fn sample(&mut self, timestamp: DateTime<Local>) -> Result<SystemSample> {
+        let output = utils::run_adb_command(&[
+            "shell",
+            &format!(
+                "cat /proc/stat; echo {}; cat /proc/meminfo",
+                SECTION_SEPARATOR
+            ),
+        ])?;
+        let (stat, meminfo) = output
+            .split_once(SECTION_SEPARATOR)
+            .ok_or_else(|| anyhow::format_err!("Unexpected system output: {}", output.trim()))?;
+        let times = parse_cpu_times(stat)
+            .ok_or_else(|| anyhow::format_err!("No cpu line in /proc/stat"))?;
+        let memory = parse_proc_meminfo(meminfo)
+            .ok_or_else(|| anyhow::format_err!("No MemTotal in /proc/meminfo"))?;
+        let cpu = self
+            .previous
+            .and_then(|previous| times.usage_since(&previous));
+        self.previous = Some(times);
+        Ok(SystemSample {
+            timestamp,
+            cpu,
+            memory,
+        })
+    }
+}
+
+// 解析 dumpsys meminfo 的 "Total PSS by process:" 段：
+// "    312,345K: system (pid 1234)"、"    98,765K: com.foo (pid 2345 / activities)"
+pub fn parse_pss_by_process(output: &str) -> Vec<(String, f64)> {
+    let mut processes = Vec::new();
+    let mut in_section = false;
+    for line in output.lines() {
+        let line = line.trim();
+        if line.starts_with("Total PSS by process") {
+            in_section = true;
+            continue;
+        }
+        if !in_section {
+            continue;
+        }
+        if line.is_empty() {
+            break;
+        }
+        let Some((value, rest)) = line.split_once("K: ") else {
+            continue;
+        };
+        let Ok(pss_kb) = value.replace(',', "").trim().parse::<f64>() else {
+            continue;
+        };
+        let name = rest.split(" (pid").next().unwrap_or(rest).trim();
+        // 进程名用作 CSV 列名，不能含逗号
+        processes.push((name.replace(',', ";"), pss_kb));
+    }
+    processes
+}
+
+// 整机进程内存排名中的一项（procrank 风格），dumpsys meminfo 来源只有 PSS
+#[derive(Debug, Clone)]
+pub struct RankedProcess {
+    pub pid: String,
+    pub name: String,
+    pub pss_kb: f64,
+    pub rss_kb: Option<f64>,
+    pub uss_kb: Option<f64>,
+    pub swap_kb: Option<f64>,
+}
+
+// 一次整机内存排名，按 PSS 降序
+#[derive(Debug, Clone)]
+pub struct MemoryRanking {
+    pub timestamp: DateTime<Local>,
+    pub source: &'static str,
+    pub processes: Vec<RankedProcess>,
+}
+
+impl MemoryRanking {
+    pub fn total_pss_kb(&self) -> f64 {
+        self.processes.iter().map(|p| p.pss_kb).sum()
+    }
+}
+
+// 解析 procrank 输出，按表头定位列（新版本多出 Swap/PSwap/USwap/ZSwap 列）：
+// "  PID       Vss      Rss      Pss      Uss  cmdline"
+// " 1234  123456K   98765K   45678K   40000K  com.foo"
+pub fn parse_procrank(output: &str) -> Vec<RankedProcess> {
+    let mut lines = output.lines();
+    let Some(header) = lines.find(|line| line.trim_start().starts_with("PID")) else {
+        return Vec::new();
+    };
+    let columns: Vec<&str> = header.split_whitespace().collect();
+    let index = |name: &str| columns.iter().position(|c| *c == name);
+    let (Some(pss), Some(cmdline)) = (index("Pss"), index("cmdline")) else {
+        return Vec::new();
+    };
+    let kb = |fields: &[&str], i: Option<usize>| -> Option<f64> {
+        fields.get(i?)?.trim_end_matches('K').parse().ok()
+    };
+
+    let mut processes = Vec::new();
+    for line in lines {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        // 末尾的汇总行（"------"、"TOTAL"）不以 PID 开头
+        if fields.len() <= cmdline || fields[0].parse::<u32>().is_err() {
+            continue;
+        }
+        let Some(pss_kb) = kb(&fields, Some(pss)) else {
+            continue;
+        };
+        processes.push(RankedProcess {
+            pid: fields[0].to_string(),
+            name: fields[cmdline..].join(" ").replace(',', ";"),
+            pss_kb,
+            rss_kb: kb(&fields, index("Rss")),
+            uss_kb: kb(&fields, index("Uss")),
+            swap_kb: kb(&fields, index("Swap")),
+        });
+    }
+    processes
+}
+
+// 解析 dumpsys meminfo 的 "Total PSS by process:" 段，保留 PID
+pub fn parse_pss_ranking(output: &str) -> Vec<RankedProcess> {
+    let mut processes = Vec::new();
+    let mut in_section = false;
+    for line in output.lines() {
+        let line = line.trim();
+        if line.starts_with("Total PSS by process") {
+            in_section = true;
+            continue;
+        }
+        if !in_section {
+            continue;
+        }
+        if line.is_empty() {
+            break;
+        }
+        let Some((value, rest)) = line.split_once("K: ") else {
+            continue;
+        };
+        let Ok(pss_kb) = value.replace(',', "").trim().parse::<f64>() else {
+            continue;
+        };
+        let (name, pid) = match rest.split_once(" (pid ") {
+            Some((name, pid)) => (name, pid.split([' ', ')']).next().unwrap_or_default()),
+            None => (rest, ""),
+        };
+        processes.push(RankedProcess {
+            pid: pid.to_string(),
+            name: name.trim().replace(',', ";"),
+            pss_kb,
+            rss_kb: None,
+            uss_kb: None,
+            swap_kb: None,
+        });
+    }
+    processes
+}
+
+// 优先使用 procrank（需要 root，带 RSS/USS），否则退回 dumpsys meminfo
+pub fn capture_memory_ranking() -> Result<MemoryRanking> {
+    let timestamp = Local::now();
+    for args in [
+        vec!["shell", "procrank"],
+        vec!["shell", "su", "0", "procrank"],
+    ] {
+        let Ok(output) = utils::run_adb_command(&args) else {
+            continue;
+        };
+        let processes = parse_procrank(&output);
+        if !processes.is_empty() {
+            return Ok(MemoryRanking {
+                timestamp,
+                source: "procrank",
+                processes: sorted_by_pss(processes),
+            });
+        }
+    }
+
+    let output = utils::run_adb_command(&["shell", "dumpsys", "meminfo"])?;
+    let processes = parse_pss_ranking(&output);
+    if processes.is_empty() {
+        anyhow::bail!("No 'Total PSS by process' section in dumpsys meminfo");
+    }
+    Ok(MemoryRanking {
+        timestamp,
+        source: "dumpsys meminfo",
+        processes: sorted_by_pss(processes),
+    })
+}
+
+fn sorted_by_pss(mut processes: Vec<RankedProcess>) -> Vec<RankedProcess> {
+    processes.sort_by(|a, b| {
+        b.pss_kb
+            .partial_cmp(&a.pss_kb)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    processes
+}
+
+pub fn write_memory_ranking(path: &Path, ranking: &MemoryRanking) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut file = std::fs::File::create(path)?;
+    let optional = |kb: Option<f64>| kb.map(|kb| format!("{:.0}", kb)).unwrap_or_default();
+    writeln!(
+        file,
+        "Rank,PID,Process,PSS (KB),RSS (KB),USS (KB),Swap (KB)"
+    )?;
+    for (rank, process) in ranking.processes.iter().enumerate() {
+        writeln!(
+            file,
+            "{},{},{},{:.0},{},{},{}",
+            rank + 1,
+            process.pid,
+            process.name,
+            process.pss_kb,
+            optional(process.rss_kb),
+            optional(process.uss_kb),
+            optional(process.swap_kb)
+        )?;
+    }
+    Ok(())
+}
+
+// 开始与结束两次排名按进程名对比（同名进程合计），按结束时 PSS 降序
+pub fn diff_rankings(start: &MemoryRanking, end: &MemoryRanking) -> Vec<(String, f64, f64)> {
+    let mut totals: Vec<(String, f64, f64)> = Vec::new();
+    let mut add = |name: &str, start_kb: f64, end_kb: f64| match totals
+        .iter_mut()
+        .find(|(n, _, _)| n == name)
+    {
+        Some(entry) => {
+            entry.1 += start_kb;
+            entry.2 += end_kb;
+        }
+        None => totals.push((name.to_string(), start_kb, end_kb)),
+    };
+    for process in &start.processes {
+        add(&process.name, process.pss_kb, 0.0);
+    }
+    for process in &end.processes {
+        add(&process.name, 0.0, process.pss_kb);
+    }
+    totals.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap_or(std::cmp::Ordering::Equal));
+    totals
+}
+
+pub fn write_ranking_diff(path: &Path, diff: &[(String, f64, f64)]) -> Result<()> {
+    let mut file = std::fs::File::create(path)?;
+    writeln!(file, "Process,Start PSS (KB),End PSS (KB),Delta (KB)")?;
+    for (name, start_kb, end_kb) in diff {
+        writeln!(
+            file,
+            "{},{:.0},{:.0},{:+.0}",
+            name,
+            start_kb,
+            end_kb,
+            end_kb - start_kb
+        )?;
+    }
+    Ok(())
+}
+
+// dumpsys meminfo 遍历所有进程，通常需要数秒
+pub fn sample_pss_by_process() -> Result<Vec<(String, f64)>> {
+    let output = utils::run_adb_command(&["shell", "dumpsys", "meminfo"])?;
+    let processes = parse_pss_by_process(&output);
+    if processes.is_empty() {
+        anyhow::bail!("No 'Total PSS by process' section in dumpsys meminfo");
+    }
+    Ok(processes)
+}
+
+// 导出整机 CPU/内存 CSV 和图表，并返回 CPU 与已用内存的统计
+pub fn export_system_data(
+    dir: &Path,
+    samples: &[SystemSample],
+) -> Result<(Vec<PathBuf>, Option<Summary>, Option<Summary>)> {
+    std::fs::create_dir_all(dir)?;
+    let mut paths = Vec::new();
+
+    let csv_path = dir.join("system_data.csv");
+    let mut file = std::fs::File::create(&csv_path)?;
+    writeln!(
+        file,
+        "Timestamp,Device CPU (%),Memory Total (KB),Memory Used (KB),Memory Available (KB),Memory Free (KB),Cached (KB),Swap Used (KB)"
+    )?;
+    for sample in samples {
+        writeln!(
+            file,
+            "{},{},{},{},{},{},{},{}",
+            sample.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            sample
+                .cpu
+                .map(|cpu| format!("{:.2}", cpu))
+                .unwrap_or_default(),
+            sample.memory.total_kb,
+            sample.memory.used_kb(),
+            sample.memory.available_kb,
+            sample.memory.free_kb,
+            sample.memory.cached_kb,
+            sample.memory.swap_used_kb()
+        )?;
+    }
+    paths.push(csv_path);
+
+    let cpu: Vec<(DateTime<Local>, f64)> = samples
+        .iter()
+        .filter_map(|s| s.cpu.map(|cpu| (s.timestamp, cpu)))
+        .collect();
+    let memory_mb = |f: fn(&MemInfo) -> u64| -> Vec<(DateTime<Local>, f64)> {
+        samples
+            .iter()
+            .map(|s| (s.timestamp, f(&s.memory) as f64 / 1024.0))
+            .collect()
+    };
+    let cpu_summary = Summary::from_values(&cpu.iter().map(|(_, v)| *v).collect::<Vec<_>>());
+    let used_summary = Summary::from_values(
+        &samples
+            .iter()
+            .map(|s| s.memory.used_kb() as f64)
+            .collect::<Vec<_>>(),
+    );
+
+    let mut charts = vec![(
+        "system_cpu.png",
+        "Device CPU Usage",
+        "CPU (% of all cores)",
+        vec![TimeSeriesData::new("Device CPU (%)", cpu)],
+    )];
+    let mut memory = vec![
+        TimeSeriesData::new("Used (MB)", memory_mb(MemInfo::used_kb)),
+        TimeSeriesData::new("Available (MB)", memory_mb(|m| m.available_kb)),
+        TimeSeriesData::new("Cached (MB)", memory_mb(|m| m.cached_kb)),
+    ];
+    if samples.iter().any(|s| s.memory.swap_total_kb > 0) {
+        memory.push(TimeSeriesData::new(
+            "Swap Used (MB)",
+            memory_mb(MemInfo::swap_used_kb),
+        ));
+    }
+    charts.push(("system_memory.png", "Device Memory", "Memory (MB)", memory));
+
+    for (file_name, title, y_desc, series) in charts {
+        let series: Vec<TimeSeriesData> =
+            series.into_iter().filter(|s| s.points.len() > 1).collect();
+        if series.is_empty() {
+            continue;
+        }
+        let path = dir.join(chart::stamped_file_name(file_name));
+        chart::draw_time_series_chart(&path, title, y_desc, &series)?;
+        paths.push(path);
+    }
+
+    Ok((paths, cpu_summary, used_summary))
+}
+
+// 整机可用内存 CSV，以及应用 PSS（左轴）与整机 MemAvailable、MemFree（右轴）的对照图：
+// 应用内存增长的同时系统余量缩小，LMK 开始回收其他进程
+pub fn write_headroom_data(
+    dir: &Path,
+    package: &str,
+    pss: &[(DateTime<Local>, u64)],
+    device: &[(DateTime<Local>, MemInfo)],
+) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let mut paths = Vec::new();
+
+    let csv_path = dir.join(format!("{}_device_memory.csv", package));
+    let mut file = std::fs::File::create(&csv_path)?;
+    writeln!(
+        file,
+        "Timestamp,MemTotal (KB),MemAvailable (KB),MemFree (KB),Cached (KB)"
+    )?;
+    for (timestamp, info) in device {
+        writeln!(
+            file,
+            "{},{},{},{},{}",
+            timestamp.format("%Y-%m-%d %H:%M:%S"),
+            info.total_kb,
+            info.available_kb,
+            info.free_kb,
+            info.cached_kb
+        )?;
+    }
+    paths.push(csv_path);
+
+    if pss.len() > 1 && device.len() > 1 {
+        let mb = |kb: u64| kb as f64 / 1024.0;
+        let device_series = |value: fn(&MemInfo) -> u64| -> Vec<(DateTime<Local>, f64)> {
+            device
+                .iter()
+                .map(|(t, info)| (*t, mb(value(info))))
+                .collect()
+        };
+        let path = dir.join(chart::stamped_file_name(&format!(
+            "{}_pss_vs_available.png",
+            package
+        )));
+        chart::draw_dual_axis_chart(
+            &path,
+            &format!("App PSS vs Device Available Memory - {}", package),
+            "App PSS (MB)",
+            &[TimeSeriesData::new(
+                "App PSS",
+                pss.iter().map(|(t, kb)| (*t, mb(*kb))).collect(),
+            )],
+            "Device Memory (MB)",
+            &[
+                TimeSeriesData::new("MemAvailable", device_series(|i| i.available_kb)),
+                TimeSeriesData::new("MemFree", device_series(|i| i.free_kb)),
+            ],
+        )?;
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
+
+// --memory 时与 PSS 同时读取整机内存，对照应用增长与系统余量
+#[derive(Default)]
+pub struct HeadroomCollector {
+    pss: Vec<(DateTime<Local>, u64)>,
+    device: Vec<(DateTime<Local>, MemInfo)>,
+}
+
+impl Collector for HeadroomCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        let Some(memory) = &ctx.sample.memory else {
+            return Ok(());
+        };
+        let tick = ctx.tick;
+        self.pss.push((tick.timestamp, memory.total_pss));
+        match sample_meminfo() {
+            Ok(info) => {
+                ctx.table
+                    .record(&tick, "Device MemAvailable (KB)", info.available_kb as f64);
+                ctx.table
+                    .record(&tick, "Device MemFree (KB)", info.free_kb as f64);
+                self.device.push((tick.timestamp, info));
+            }
+            Err(e) => {
+                if !utils::is_being_interrupted() {
+                    console!("Failed to read /proc/meminfo: {}", e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        let (Some((_, first)), Some((_, last))) = (self.device.first(), self.device.last()) else {
+            return;
+        };
+        let min_available = self
+            .device
+            .iter()
+            .map(|(_, info)| info.available_kb)
+            .min()
+            .unwrap_or_default();
+        console!(
+            "Device MemAvailable: {} MB -> {} MB (min {} MB of {} MB)",
+            first.available_kb / 1024,
+            last.available_kb / 1024,
+            (min_available / 1024).to_string().yellow(),
+            last.total_kb / 1024
+        );
+        match write_headroom_data(
+            &ctx.layout.dir(Category::Memory),
+            ctx.package,
+            &self.pss,
+            &self.device,
+        ) {
+            Ok(paths) => {
+                for path in paths {
+                    console!("✓ Device memory data exported: {}", path.display());
+                }
+            }
+            Err(e) => console!("Failed to export device memory data: {}", e),
+        }
+    }
+}
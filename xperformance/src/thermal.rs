@@ -0,0 +1,402 @@
+use crate::chart::{self, TimeSeriesData};
+use crate::layout::Category;
+use crate::recorder::{Collector, FinishContext, TickContext};
+use crate::utils::{self, CpuChartOverlay};
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use colored::*;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// 温度类别，对应 thermalservice 中 Temperature 的 mType
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThermalKind {
+    Cpu,
+    Gpu,
+    Skin,
+}
+
+impl ThermalKind {
+    pub const ALL: [ThermalKind; 3] = [ThermalKind::Cpu, ThermalKind::Gpu, ThermalKind::Skin];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ThermalKind::Cpu => "CPU",
+            ThermalKind::Gpu => "GPU",
+            ThermalKind::Skin => "Skin",
+        }
+    }
+
+    fn from_hal_type(value: u32) -> Option<Self> {
+        match value {
+            0 => Some(ThermalKind::Cpu),
+            1 => Some(ThermalKind::Gpu),
+            3 => Some(ThermalKind::Skin),
+            _ => None,
+        }
+    }
+
+    // sysfs thermal_zone 的 type 因厂商而异，按常见命名归类
+    fn from_zone_type(zone_type: &str) -> Option<Self> {
+        let zone_type = zone_type.to_lowercase();
+        if zone_type.contains("gpu") {
+            Some(ThermalKind::Gpu)
+        } else if zone_type.contains("cpu") || zone_type.starts_with("tsens") {
+            Some(ThermalKind::Cpu)
+        } else if zone_type.contains("skin") || zone_type.contains("quiet") {
+            Some(ThermalKind::Skin)
+        } else {
+            None
+        }
+    }
+}
+
+// PowerManager.THERMAL_STATUS_* 的名称
+pub fn status_name(status: u32) -> &'static str {
+    match status {
+        0 => "NONE",
+        1 => "LIGHT",
+        2 => "MODERATE",
+        3 => "SEVERE",
+        4 => "CRITICAL",
+        5 => "EMERGENCY",
+        6 => "SHUTDOWN",
+        _ => "UNKNOWN",
+    }
+}
+
+// 一次采样：各类别取所有对应传感器的最高温度（摄氏度）
+#[derive(Debug, Clone)]
+pub struct ThermalSample {
+    pub timestamp: DateTime<Local>,
+    pub status: Option<u32>,
+    pub cpu: Option<f64>,
+    pub gpu: Option<f64>,
+    pub skin: Option<f64>,
+    pub zones: Vec<(String, f64)>,
+}
+
+impl ThermalSample {
+    pub fn get(&self, kind: ThermalKind) -> Option<f64> {
+        match kind {
+            ThermalKind::Cpu => self.cpu,
+            ThermalKind::Gpu => self.gpu,
+            ThermalKind::Skin => self.skin,
+        }
+    }
+
+    fn update(&mut self, kind: ThermalKind, celsius: f64) {
+        let slot = match kind {
+            ThermalKind::Cpu => &mut self.cpu,
+            ThermalKind::Gpu => &mut self.gpu,
+            ThermalKind::Skin => &mut self.skin,
+        };
+        *slot = Some(slot.map_or(celsius, |v| v.max(celsius)));
+    }
+}
+
+// 解析 dumpsys thermalservice：
+// "Thermal Status: 0" 和 "Current temperatures from HAL:" 之后的
+// "Temperature{mValue=38.2, mType=0, mName=CPU0, mStatus=0}"
+pub fn parse_thermalservice(output: &str) -> (Option<u32>, Vec<(ThermalKind, String, f64)>) {
+    let mut status = None;
+    let mut temperatures = Vec::new();
+    let mut in_current = false;
+    for line in output.lines() {
+        let line = line.trim();
+        if let Some(value) = line.strip_prefix("Thermal Status:") {
+            status = value.trim().parse().ok();
+        } else if line.starts_with("Current temperatures from HAL") {
+            in_current = true;
+        } else if line.ends_with(':') {
+            // 其后的 "Cached temperatures" 等段落不是当前读数
+            in_current = false;
+        } else if in_current && line.starts_with("Temperature{") {
+            let field = |name: &str| {
+                line.split([',', '{', '}'])
+                    .find_map(|part| part.trim().strip_prefix(name))
+                    .map(str::trim)
+            };
+            let value = field("mValue=").and_then(|v| v.parse::<f64>().ok());
+            let kind = field("mType=")
+                .and_then(|v| v.parse().ok())
+                .and_then(ThermalKind::from_hal_type);
+            if let (Some(value), Some(kind)) = (value, kind) {
+                let name = field("mName=").unwrap_or_default().to_string();
+                temperatures.push((kind, name, value));
+            }
+        }
+    }
+    (status, temperatures)
+}
+
+// sysfs 温度多数以毫摄氏度为单位，少数直接为摄氏度
+fn zone_celsius(raw: f64) -> f64 {
+    if raw.abs() > 1000.0 {
+        raw / 1000.0
+    } else {
+        raw
+    }
+}
+
+// 一次 adb 调用读取所有 thermal_zone 的 type 和 temp
+pub fn read_zones() -> Result<Vec<(String, f64)>> {
+    let output = utils::run_adb_command(&[
+        "shell",
+        "for z in /sys/class/thermal/thermal_zone*; do echo \"$(cat $z/type) $(cat $z/temp)\"; done 2>/dev/null",
+    ])?;
+    Ok(output
+        .lines()
+        .filter_map(|line| {
+            let (zone_type, temp) = line.trim().rsplit_once(' ')?;
+            let raw: f64 = temp.parse().ok()?;
+            Some((zone_type.to_string(), zone_celsius(raw)))
+        })
+        // 未接入的传感器常报告负值或 0
+        .filter(|(_, celsius)| *celsius > 0.0)
+        .collect())
+}
+
+// 优先使用 thermal HAL 的分类温度，HAL 不可用时按 thermal_zone 名称归类
+pub fn sample(timestamp: DateTime<Local>) -> Result<ThermalSample> {
+    let (status, temperatures) = utils::run_adb_command(&["shell", "dumpsys", "thermalservice"])
+        .map(|output| parse_thermalservice(&output))
+        .unwrap_or_default();
+    let zones = read_zones().unwrap_or_default();
+    if status.is_none() && temperatures.is_empty() && zones.is_empty() {
+        anyhow::bail!("No thermal data available");
+    }
+
+    let mut sample = ThermalSample {
+        timestamp,
+        status,
+        cpu: None,
+        gpu: None,
+        skin: None,
+        zones,
+    };
+    if temperatures.is_empty() {
+        let zones = sample.zones.clone();
+        for (zone_type, celsius) in zones {
+            if let Some(kind) = ThermalKind::from_zone_type(&zone_type) {
+                sample.update(kind, celsius);
+            }
+        }
+    } else {
+        for (kind, _, celsius) in temperatures {
+            sample.update(kind, celsius);
+        }
+    }
+    Ok(sample)
+}
+
+// 限频状态发生变化的时间点及说明，如 "Thermal: NONE -> MODERATE"
+pub fn status_changes(samples: &[ThermalSample]) -> Vec<(DateTime<Local>, String)> {
+    let mut changes = Vec::new();
+    let mut previous: Option<u32> = None;
+    for sample in samples {
+        let Some(status) = sample.status else {
+            continue;
+        };
+        if let Some(before) = previous.filter(|before| *before != status) {
+            changes.push((
+                sample.timestamp,
+                format!(
+                    "Thermal: {} -> {}",
+                    status_name(before),
+                    status_name(status)
+                ),
+            ));
+        }
+        previous = Some(status);
+    }
+    changes
+}
+
+// 导出温度 CSV（含各 thermal_zone 原始读数）和 CPU/GPU/Skin 温度图表
+pub fn export_thermal_data(
+    dir: &Path,
+    package: &str,
+    samples: &[ThermalSample],
+) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let mut paths = Vec::new();
+
+    let csv_path = dir.join(format!("{}_thermal.csv", package));
+    let mut file = std::fs::File::create(&csv_path)?;
+    writeln!(file, "Timestamp,Thermal Status,CPU (C),GPU (C),Skin (C)")?;
+    let format = |value: Option<f64>| value.map(|v| format!("{:.1}", v)).unwrap_or_default();
+    for sample in samples {
+        writeln!(
+            file,
+            "{},{},{},{},{}",
+            sample.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            sample.status.map(status_name).unwrap_or_default(),
+            format(sample.cpu),
+            format(sample.gpu),
+            format(sample.skin)
+        )?;
+    }
+    paths.push(csv_path);
+
+    // thermal_zone 名称可能重复，按出现顺序加序号作为列名
+    let zone_names: Vec<String> = samples
+        .iter()
+        .find(|s| !s.zones.is_empty())
+        .map(|s| {
+            s.zones
+                .iter()
+                .enumerate()
+                .map(|(i, (name, _))| format!("{}:{}", i, name.replace(',', ";")))
+                .collect()
+        })
+        .unwrap_or_default();
+    if !zone_names.is_empty() {
+        let zones_path = dir.join(format!("{}_thermal_zones.csv", package));
+        let mut file = std::fs::File::create(&zones_path)?;
+        writeln!(file, "Timestamp,{}", zone_names.join(","))?;
+        for sample in samples.iter().filter(|s| s.zones.len() == zone_names.len()) {
+            let values: Vec<String> = sample
+                .zones
+                .iter()
+                .map(|(_, celsius)| format!("{:.1}", celsius))
+                .collect();
+            writeln!(
+                file,
+                "{},{}",
+                sample.timestamp.format("%Y-%m-%d %H:%M:%S"),
+                values.join(",")
+            )?;
+        }
+        paths.push(zones_path);
+    }
+
+    let series: Vec<TimeSeriesData> = ThermalKind::ALL
+        .iter()
+        .map(|kind| {
+            TimeSeriesData::new(
+                format!("{} (°C)", kind.as_str()),
+                samples
+                    .iter()
+                    .filter_map(|s| s.get(*kind).map(|v| (s.timestamp, v)))
+                    .collect(),
+            )
+        })
+        .filter(|series| series.points.len() > 1)
+        .collect();
+    if !series.is_empty() {
+        let path = dir.join(chart::stamped_file_name(&format!(
+            "{}_thermal_chart.png",
+            package
+        )));
+        chart::draw_time_series_chart(
+            &path,
+            &format!("Temperature - {}", package),
+            "Temperature (°C)",
+            &series,
+        )?;
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
+
+// 每个 Tick 的各类温度和系统温控状态，状态变化同时标在 CPU 图表上
+#[derive(Default)]
+pub struct ThermalCollector {
+    samples: Vec<ThermalSample>,
+}
+
+impl Collector for ThermalCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        let tick = ctx.tick;
+        let sample = match sample(tick.timestamp) {
+            Ok(sample) => sample,
+            Err(e) => {
+                if !utils::is_being_interrupted() {
+                    console!("Failed to sample thermal data: {}", e);
+                }
+                return Ok(());
+            }
+        };
+        for kind in ThermalKind::ALL {
+            if let Some(celsius) = sample.get(kind) {
+                ctx.table
+                    .record(&tick, &format!("{} Temp (C)", kind.as_str()), celsius);
+            }
+        }
+        if let Some(status) = sample.status {
+            ctx.table.record(&tick, "Thermal Status", status as f64);
+        }
+        let temps: Vec<String> = ThermalKind::ALL
+            .iter()
+            .filter_map(|kind| {
+                sample
+                    .get(*kind)
+                    .map(|c| format!("{} {:.1}°C", kind.as_str(), c))
+            })
+            .collect();
+        console!(
+            "[{}] Thermal: {}{}",
+            tick.timestamp.format("%H:%M:%S"),
+            temps.join(", "),
+            sample
+                .status
+                .map(|s| format!(", status {}", status_name(s)))
+                .unwrap_or_default()
+        );
+        let previous = self.samples.iter().rev().find_map(|s| s.status);
+        if let (Some(before), Some(now)) = (previous, sample.status) {
+            if before != now {
+                console!(
+                    "{}",
+                    format!(
+                        "Thermal status changed: {} -> {}",
+                        status_name(before),
+                        status_name(now)
+                    )
+                    .red()
+                    .bold()
+                );
+            }
+        }
+        ctx.thermal_status = sample.status;
+        self.samples.push(sample);
+        Ok(())
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        if self.samples.is_empty() {
+            return;
+        }
+        for kind in ThermalKind::ALL {
+            if let Some(peak) = self
+                .samples
+                .iter()
+                .filter_map(|s| s.get(kind))
+                .reduce(f64::max)
+            {
+                console!("Peak {} temperature: {:.1}°C", kind.as_str(), peak);
+            }
+        }
+        for (time, change) in status_changes(&self.samples) {
+            console!("  {} {}", time.format("%H:%M:%S"), change.yellow());
+        }
+        match export_thermal_data(
+            &ctx.layout.dir(Category::Thermal),
+            ctx.package,
+            &self.samples,
+        ) {
+            Ok(paths) => {
+                for path in paths {
+                    console!("✓ Thermal data exported: {}", path.display());
+                }
+            }
+            Err(e) => console!("Failed to export thermal data: {}", e),
+        }
+    }
+
+    fn cpu_overlay(&self, overlay: &mut CpuChartOverlay) {
+        overlay.markers.extend(status_changes(&self.samples));
+    }
+}
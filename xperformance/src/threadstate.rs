@@ -0,0 +1,743 @@
+use crate::chart::{self, TimeSeriesData};
+use crate::layout::Category;
+use crate::logcat::{EventKind, LogcatEvent};
+use crate::recorder::{Collector, FinishContext, TickContext};
+use crate::utils;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use colored::*;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// 线程调度状态，来自 /proc/<pid>/task/<tid>/stat 的第三列
+#[derive(Debug, Clone)]
+pub struct ThreadState {
+    pub tid: String,
+    pub name: String,
+    pub state: char,
+    // 调度参数，stat 字段不全时为 None
+    pub sched: Option<SchedParams>,
+}
+
+// stat 第 18、19、40、41 列：内核优先级、nice、实时优先级和调度策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SchedParams {
+    pub priority: i32,
+    pub nice: i32,
+    pub rt_priority: u32,
+    pub policy: u32,
+}
+
+impl SchedParams {
+    // include/uapi/linux/sched.h 中的 SCHED_* 常量
+    pub fn policy_name(&self) -> &'static str {
+        match self.policy {
+            0 => "normal",
+            1 => "fifo",
+            2 => "rr",
+            3 => "batch",
+            5 => "idle",
+            6 => "deadline",
+            _ => "unknown",
+        }
+    }
+
+    pub fn is_realtime(&self) -> bool {
+        matches!(self.policy, 1 | 2)
+    }
+
+    // 实时线程显示策略和实时优先级，普通线程显示 nice
+    pub fn describe(&self) -> String {
+        if self.is_realtime() {
+            format!("{} {}", self.policy_name(), self.rt_priority)
+        } else {
+            format!("{} nice {}", self.policy_name(), self.nice)
+        }
+    }
+}
+
+impl ThreadState {
+    // D 状态：不可中断睡眠，通常在等待 IO 或内核锁
+    pub fn is_blocked(&self) -> bool {
+        self.state == 'D'
+    }
+}
+
+// 解析 stat 行：`tid (comm) S ...`，comm 中可能含空格和括号，以最后一个 ')' 为界
+fn parse_stat_line(line: &str) -> Option<ThreadState> {
+    let open = line.find('(')?;
+    let close = line.rfind(')')?;
+    let tid = line[..open].trim();
+    if tid.is_empty() || close < open {
+        return None;
+    }
+    // 从第三列（状态）开始的各列
+    let fields: Vec<&str> = line[close + 1..].split_whitespace().collect();
+    let state = fields.first()?.chars().next()?;
+    let field = |column: usize| fields.get(column - 3)?.parse::<i64>().ok();
+    let sched = (|| {
+        Some(SchedParams {
+            priority: field(18)? as i32,
+            nice: field(19)? as i32,
+            rt_priority: field(40)? as u32,
+            policy: field(41)? as u32,
+        })
+    })();
+    Some(ThreadState {
+        tid: tid.to_string(),
+        name: line[open + 1..close].to_string(),
+        state,
+        sched,
+    })
+}
+
+// 一次 adb 调用读取进程所有线程的状态
+pub fn sample_thread_states(pid: &str) -> Result<Vec<ThreadState>> {
+    let output = utils::run_adb_command(&[
+        "shell",
+        &format!("cat /proc/{}/task/*/stat 2>/dev/null", pid),
+    ])?;
+    let states: Vec<ThreadState> = output.lines().filter_map(parse_stat_line).collect();
+    if states.is_empty() {
+        anyhow::bail!("No thread states found for pid {}", pid);
+    }
+    Ok(states)
+}
+
+// 进程的线程总数，即 /proc/<pid>/task 下的目录数
+pub fn sample_thread_count(pid: &str) -> Result<u32> {
+    let output = utils::run_adb_command(&["shell", &format!("ls /proc/{}/task", pid)])?;
+    let count = parse_task_list(&output);
+    if count == 0 {
+        anyhow::bail!("No threads found for pid {}", pid);
+    }
+    Ok(count)
+}
+
+fn parse_task_list(output: &str) -> u32 {
+    output
+        .split_whitespace()
+        .filter(|entry| entry.chars().all(|c| c.is_ascii_digit()))
+        .count() as u32
+}
+
+// 导出线程数 CSV 和折线图，线程泄漏时即使单个线程 CPU 很低也能看出
+pub fn export_thread_count(
+    dir: &Path,
+    package: &str,
+    points: &[(DateTime<Local>, u32)],
+) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let mut paths = Vec::new();
+
+    let csv_path = dir.join(format!("{}_thread_count.csv", package));
+    let mut file = std::fs::File::create(&csv_path)?;
+    writeln!(file, "Timestamp,Threads")?;
+    for (timestamp, count) in points {
+        writeln!(file, "{},{}", timestamp.format("%Y-%m-%d %H:%M:%S"), count)?;
+    }
+    paths.push(csv_path);
+
+    if points.len() > 1 {
+        let path = dir.join(chart::stamped_file_name(&format!(
+            "{}_thread_count.png",
+            package
+        )));
+        chart::draw_time_series_chart(
+            &path,
+            &format!("Thread Count - {}", package),
+            "Threads",
+            &[TimeSeriesData::new(
+                "Threads",
+                points.iter().map(|(t, c)| (*t, *c as f64)).collect(),
+            )],
+        )?;
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
+
+// 一次采样中各调度状态的线程数
+#[derive(Debug, Clone, Copy)]
+pub struct StateBreakdown {
+    pub timestamp: DateTime<Local>,
+    // R：运行或就绪
+    pub running: u32,
+    // S：可中断睡眠，如等待锁、消息或定时器
+    pub sleeping: u32,
+    // D：不可中断睡眠，通常在等待 IO
+    pub blocked: u32,
+    // Z：已退出但未被回收
+    pub zombie: u32,
+    // 其他状态，如 T（停止）、t（被跟踪）
+    pub other: u32,
+}
+
+impl StateBreakdown {
+    pub fn from_states(timestamp: DateTime<Local>, states: &[ThreadState]) -> Self {
+        let mut breakdown = Self {
+            timestamp,
+            running: 0,
+            sleeping: 0,
+            blocked: 0,
+            zombie: 0,
+            other: 0,
+        };
+        for state in states {
+            match state.state {
+                'R' => breakdown.running += 1,
+                'S' => breakdown.sleeping += 1,
+                'D' => breakdown.blocked += 1,
+                'Z' => breakdown.zombie += 1,
+                _ => breakdown.other += 1,
+            }
+        }
+        breakdown
+    }
+
+    pub fn total(&self) -> u32 {
+        self.running + self.sleeping + self.blocked + self.zombie + self.other
+    }
+
+    // (列名, 数量)，用于合并 CSV 和图表
+    pub fn columns(&self) -> [(&'static str, u32); 5] {
+        [
+            ("Running Threads", self.running),
+            ("Blocked Threads", self.blocked),
+            ("Sleeping Threads", self.sleeping),
+            ("Zombie Threads", self.zombie),
+            ("Other Threads", self.other),
+        ]
+    }
+}
+
+// 导出各状态线程数 CSV 和堆叠面积图，D 状态线程增多通常意味着 IO 阻塞
+pub fn export_state_breakdown(
+    dir: &Path,
+    package: &str,
+    breakdowns: &[StateBreakdown],
+) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let mut paths = Vec::new();
+
+    let csv_path = dir.join(format!("{}_thread_states.csv", package));
+    let mut file = std::fs::File::create(&csv_path)?;
+    writeln!(
+        file,
+        "Timestamp,Running (R),Sleeping (S),Uninterruptible (D),Zombie (Z),Other"
+    )?;
+    for b in breakdowns {
+        writeln!(
+            file,
+            "{},{},{},{},{},{}",
+            b.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            b.running,
+            b.sleeping,
+            b.blocked,
+            b.zombie,
+            b.other
+        )?;
+    }
+    paths.push(csv_path);
+
+    if breakdowns.len() > 1 {
+        // 自下而上：运行、不可中断、睡眠，便于看出 D 状态的变化；全为 0 的状态不画
+        const LABELS: [&str; 5] = [
+            "Running (R)",
+            "Uninterruptible (D)",
+            "Sleeping (S)",
+            "Zombie (Z)",
+            "Other",
+        ];
+        let series: Vec<TimeSeriesData> = LABELS
+            .iter()
+            .enumerate()
+            .filter_map(|(i, label)| {
+                let points: Vec<(DateTime<Local>, f64)> = breakdowns
+                    .iter()
+                    .map(|b| (b.timestamp, b.columns()[i].1 as f64))
+                    .collect();
+                points
+                    .iter()
+                    .any(|(_, v)| *v > 0.0)
+                    .then(|| TimeSeriesData::new(*label, points))
+            })
+            .collect();
+        let path = dir.join(chart::stamped_file_name(&format!(
+            "{}_thread_states.png",
+            package
+        )));
+        chart::draw_stacked_area_chart(
+            &path,
+            &format!("Thread States - {}", package),
+            "Threads",
+            &series,
+        )?;
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
+
+// 读取线程内核栈，通常需要 root 权限；无权限时返回 None
+pub fn read_kernel_stack(pid: &str, tid: &str) -> Option<String> {
+    let path = format!("/proc/{}/task/{}/stack", pid, tid);
+    for args in [
+        vec!["shell", "cat", path.as_str()],
+        vec!["shell", "su", "0", "cat", path.as_str()],
+    ] {
+        if let Ok(output) = utils::run_adb_command(&args) {
+            let output = output.trim();
+            if !output.is_empty()
+                && !output.contains("Permission denied")
+                && !output.contains("not found")
+                && !output.contains("No such file")
+            {
+                return Some(output.to_string());
+            }
+        }
+    }
+    None
+}
+
+// 调度相关的栈帧，每个阻塞线程都有，不能说明阻塞原因
+const SCHEDULER_FRAMES: [&str; 5] = [
+    "__switch_to",
+    "__schedule",
+    "schedule",
+    "schedule_timeout",
+    "schedule_preempt_disabled",
+];
+
+// 内核栈中第一个非调度函数："[<0>] io_schedule+0x4c/0x70" -> io_schedule
+pub fn top_stack_frame(stack: &str) -> Option<&str> {
+    stack
+        .lines()
+        .filter_map(|line| {
+            let frame = line.trim();
+            let frame = frame.split_once("] ").map_or(frame, |(_, f)| f);
+            frame.split('+').next().filter(|f| !f.is_empty())
+        })
+        .find(|frame| !SCHEDULER_FRAMES.contains(frame))
+}
+
+// 线程持续处于 D 状态的告警
+#[derive(Debug, Clone)]
+pub struct BlockedAlert {
+    pub tid: String,
+    pub name: String,
+    pub samples: u32,
+}
+
+// 跟踪每个线程连续处于 D 状态的采样次数，达到阈值时告警一次
+pub struct BlockedThreadTracker {
+    threshold: u32,
+    consecutive: HashMap<String, u32>,
+}
+
+impl BlockedThreadTracker {
+    pub fn new(threshold: u32) -> Self {
+        Self {
+            threshold: threshold.max(1),
+            consecutive: HashMap::new(),
+        }
+    }
+
+    pub fn update(&mut self, states: &[ThreadState]) -> Vec<BlockedAlert> {
+        let mut alerts = Vec::new();
+        let mut consecutive = HashMap::new();
+        for state in states.iter().filter(|s| s.is_blocked()) {
+            let count = self.consecutive.get(&state.tid).copied().unwrap_or(0) + 1;
+            if count == self.threshold {
+                alerts.push(BlockedAlert {
+                    tid: state.tid.clone(),
+                    name: state.name.clone(),
+                    samples: count,
+                });
+            }
+            consecutive.insert(state.tid.clone(), count);
+        }
+        // 离开 D 状态的线程重新计数
+        self.consecutive = consecutive;
+        alerts
+    }
+}
+
+// 线程持续 D 状态的告警记录
+struct BlockedRecord {
+    timestamp: DateTime<Local>,
+    tid: String,
+    name: String,
+    samples: u32,
+    stack: Option<String>,
+}
+
+// 线程总数每次采样都记录，不依赖 --thread，线程泄漏时即使单个线程 CPU 很低也能看出
+pub struct ThreadCountCollector {
+    // --thread 时输出线程数的变化
+    print_changes: bool,
+    counts: Vec<(DateTime<Local>, u32)>,
+}
+
+impl ThreadCountCollector {
+    pub fn new(print_changes: bool) -> Self {
+        Self {
+            print_changes,
+            counts: Vec::new(),
+        }
+    }
+}
+
+impl Collector for ThreadCountCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        let tick = ctx.tick;
+        let count = match sample_thread_count(&ctx.sample.pid) {
+            Ok(count) => count,
+            Err(e) => {
+                if ctx.verbose {
+                    console!("Failed to count threads: {}", e);
+                }
+                return Ok(());
+            }
+        };
+        ctx.table.record(&tick, "Threads", count as f64);
+        if let Some((_, previous)) = self
+            .counts
+            .last()
+            .filter(|(_, c)| *c != count && self.print_changes)
+        {
+            console!(
+                "[{}] Threads: {} -> {}",
+                tick.timestamp.format("%H:%M:%S").to_string().blue(),
+                previous,
+                if count > *previous {
+                    count.to_string().yellow()
+                } else {
+                    count.to_string().green()
+                }
+            );
+        }
+        self.counts.push((tick.timestamp, count));
+        Ok(())
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        let (Some((_, first)), Some((_, last))) = (self.counts.first(), self.counts.last()) else {
+            return;
+        };
+        let peak = self.counts.iter().map(|(_, c)| *c).max().unwrap_or(0);
+        console!(
+            "Threads: {} -> {} (peak {})",
+            first,
+            last,
+            peak.to_string().yellow()
+        );
+        match export_thread_count(&ctx.layout.dir(Category::Thread), ctx.package, &self.counts) {
+            Ok(paths) => {
+                for path in paths {
+                    console!("✓ Thread count exported: {}", path.display());
+                }
+            }
+            Err(e) => console!("Failed to export thread count: {}", e),
+        }
+    }
+}
+
+// 一次 adb 调用读取所有线程的状态，交给 BlockedThreadCollector；--thread 时统计各状态的线程数
+pub struct ThreadStateCollector {
+    breakdown: bool,
+    breakdowns: Vec<StateBreakdown>,
+}
+
+impl ThreadStateCollector {
+    pub fn new(breakdown: bool) -> Self {
+        Self {
+            breakdown,
+            breakdowns: Vec::new(),
+        }
+    }
+}
+
+impl Collector for ThreadStateCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        let tick = ctx.tick;
+        let states = match sample_thread_states(&ctx.sample.pid) {
+            Ok(states) => states,
+            Err(e) => {
+                if ctx.verbose {
+                    console!("Failed to sample thread states: {}", e);
+                }
+                return Ok(());
+            }
+        };
+        if self.breakdown {
+            let breakdown = StateBreakdown::from_states(tick.timestamp, &states);
+            for (column, value) in breakdown.columns() {
+                ctx.table.record(&tick, column, value as f64);
+            }
+            // 不可中断（D）状态的线程数变化时输出
+            let previous_blocked = self.breakdowns.last().map_or(0, |b| b.blocked);
+            if breakdown.blocked != previous_blocked {
+                console!(
+                    "[{}] Threads in D state: {} -> {}",
+                    tick.timestamp.format("%H:%M:%S").to_string().blue(),
+                    previous_blocked,
+                    if breakdown.blocked > previous_blocked {
+                        breakdown.blocked.to_string().red()
+                    } else {
+                        breakdown.blocked.to_string().green()
+                    }
+                );
+            }
+            self.breakdowns.push(breakdown);
+        }
+        ctx.thread_states = Some(states);
+        Ok(())
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        if self.breakdowns.is_empty() {
+            return;
+        }
+        let mean = |f: fn(&StateBreakdown) -> u32| {
+            self.breakdowns.iter().map(|b| f(b) as f64).sum::<f64>() / self.breakdowns.len() as f64
+        };
+        let peak_blocked = self.breakdowns.iter().map(|b| b.blocked).max().unwrap_or(0);
+        console!(
+            "Thread states (mean): running {:.1}, sleeping {:.1}, uninterruptible {:.1} (peak {})",
+            mean(|b| b.running),
+            mean(|b| b.sleeping),
+            mean(|b| b.blocked),
+            if peak_blocked > 0 {
+                peak_blocked.to_string().red()
+            } else {
+                peak_blocked.to_string().green()
+            }
+        );
+        match export_state_breakdown(
+            &ctx.layout.dir(Category::Thread),
+            ctx.package,
+            &self.breakdowns,
+        ) {
+            Ok(paths) => {
+                for path in paths {
+                    console!("✓ Thread states exported: {}", path.display());
+                }
+            }
+            Err(e) => console!("Failed to export thread states: {}", e),
+        }
+    }
+}
+
+// 线程 D 状态跟踪，告警时附带内核栈
+pub struct BlockedThreadCollector {
+    tracker: BlockedThreadTracker,
+    records: Vec<BlockedRecord>,
+    // 告警同时记入 events.csv
+    events: Vec<LogcatEvent>,
+}
+
+impl BlockedThreadCollector {
+    pub fn new(threshold: u32) -> Self {
+        Self {
+            tracker: BlockedThreadTracker::new(threshold),
+            records: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+}
+
+impl Collector for BlockedThreadCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        let tick = ctx.tick;
+        let pid = ctx.sample.pid.as_str();
+        // 状态读取失败时本 Tick 不检测
+        let Some(states) = &ctx.thread_states else {
+            return Ok(());
+        };
+        let blocked = states.iter().filter(|s| s.is_blocked()).count();
+        ctx.table.record(&tick, "Blocked Threads", blocked as f64);
+        // 预热期间不累计 D 状态计数
+        let alerts = if ctx.in_warmup {
+            Vec::new()
+        } else {
+            self.tracker.update(states)
+        };
+        for alert in alerts {
+            let stack = read_kernel_stack(pid, &alert.tid);
+            console!(
+                "[{}] {} {} (TID: {}) in D state for {} samples",
+                tick.timestamp.format("%H:%M:%S").to_string().blue(),
+                "Blocked thread:".red().bold(),
+                alert.name.cyan(),
+                alert.tid.yellow(),
+                alert.samples
+            );
+            match &stack {
+                Some(stack) => console!("{}", stack),
+                None => console!("  Kernel stack not available (requires root)"),
+            }
+            self.events.push(LogcatEvent {
+                timestamp: tick.timestamp,
+                kind: EventKind::BlockedThread,
+                pid: Some(pid.to_string()).filter(|pid| !pid.is_empty()),
+                message: format!(
+                    "{} (TID: {}) in D state for {} samples{}",
+                    alert.name,
+                    alert.tid,
+                    alert.samples,
+                    // 内核栈顶部的函数，如 io_schedule、binder_thread_read
+                    stack
+                        .as_deref()
+                        .and_then(top_stack_frame)
+                        .map(|frame| format!(", in {}", frame))
+                        .unwrap_or_default()
+                ),
+            });
+            self.records.push(BlockedRecord {
+                timestamp: tick.timestamp,
+                tid: alert.tid,
+                name: alert.name,
+                samples: alert.samples,
+                stack,
+            });
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        console!(
+            "Blocked Thread Alerts: {}",
+            self.records.len().to_string().red()
+        );
+        if self.records.is_empty() {
+            return;
+        }
+        match export_blocked_threads(
+            &ctx.layout.dir(Category::Blocked),
+            ctx.package,
+            &self.records,
+        ) {
+            Ok(path) => console!(
+                "✓ Blocked thread alerts exported to CSV: {}",
+                path.display()
+            ),
+            Err(e) => console!("Failed to export blocked thread alerts: {}", e),
+        }
+    }
+
+    fn events(&self, events: &mut Vec<LogcatEvent>) {
+        events.extend(self.events.iter().cloned());
+    }
+}
+
+// 导出 D 状态告警，内核栈单独保存为文本文件
+fn export_blocked_threads(
+    blocked_dir: &Path,
+    package: &str,
+    records: &[BlockedRecord],
+) -> Result<PathBuf> {
+    std::fs::create_dir_all(blocked_dir)?;
+
+    let csv_path = blocked_dir.join(format!("{}_blocked_threads.csv", package));
+    let mut file = std::fs::File::create(&csv_path)?;
+    writeln!(file, "Timestamp,TID,Thread,Samples,Stack File")?;
+    for record in records {
+        let stack_file = match &record.stack {
+            Some(stack) => {
+                let name = format!(
+                    "stack_{}_{}.txt",
+                    record.tid,
+                    record.timestamp.format("%H%M%S")
+                );
+                std::fs::write(blocked_dir.join(&name), stack)?;
+                name
+            }
+            None => String::new(),
+        };
+        writeln!(
+            file,
+            "{},{},\"{}\",{},{}",
+            record.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            record.tid,
+            record.name.replace('"', "\"\""),
+            record.samples,
+            stack_file
+        )?;
+    }
+
+    Ok(csv_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TASK_STAT: &str = include_str!("../testdata/task_stat.txt");
+
+    #[test]
+    fn parse_stat_lines() {
+        let states: Vec<ThreadState> = TASK_STAT.lines().filter_map(parse_stat_line).collect();
+        assert_eq!(states.len(), 5);
+        assert_eq!(states[0].tid, "12345");
+        assert_eq!(states[0].name, "com.example.app");
+        assert_eq!(states[3].state, 'D');
+        assert!(states[3].is_blocked());
+        // 线程名中含空格和括号
+        assert_eq!(states[4].name, "AudioTrack (x)");
+
+        let render = states[2].sched.unwrap();
+        assert_eq!((render.priority, render.nice), (16, -16));
+        assert_eq!(render.describe(), "normal nice -16");
+        let audio = states[4].sched.unwrap();
+        assert!(audio.is_realtime());
+        assert_eq!(audio.describe(), "fifo 2");
+    }
+
+    #[test]
+    fn parse_stat_line_without_sched_fields() {
+        let state = parse_stat_line("4321 (binder:4321_2) S 612 612 0").unwrap();
+        assert_eq!(state.name, "binder:4321_2");
+        assert!(state.sched.is_none());
+        assert!(parse_stat_line("cat: /proc/4321/task/4322/stat: No such file").is_none());
+    }
+
+    #[test]
+    fn state_breakdown_counts() {
+        let states: Vec<ThreadState> = TASK_STAT.lines().filter_map(parse_stat_line).collect();
+        let breakdown = StateBreakdown::from_states(Local::now(), &states);
+        assert_eq!(
+            (breakdown.running, breakdown.sleeping, breakdown.blocked),
+            (1, 3, 1)
+        );
+        assert_eq!(breakdown.total(), 5);
+    }
+
+    #[test]
+    fn parse_task_list_counts_tids() {
+        assert_eq!(parse_task_list("12345\n12351\n12360\n12388\n12402\n"), 5);
+        assert_eq!(
+            parse_task_list("ls: /proc/99999/task: No such file or directory"),
+            0
+        );
+    }
+
+    #[test]
+    fn blocked_thread_tracker_alerts_once() {
+        let blocked = parse_stat_line(TASK_STAT.lines().nth(3).unwrap()).unwrap();
+        let mut tracker = BlockedThreadTracker::new(2);
+        assert!(tracker.update(std::slice::from_ref(&blocked)).is_empty());
+        let alerts = tracker.update(std::slice::from_ref(&blocked));
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].name, "pool-2-thread-1");
+        assert!(tracker.update(std::slice::from_ref(&blocked)).is_empty());
+    }
+
+    #[test]
+    fn top_stack_frame_skips_scheduler() {
+        let stack = "[<0>] __switch_to+0x104/0x170\n[<0>] __schedule+0x4f8/0x7c4\n[<0>] io_schedule+0x4c/0x70\n[<0>] wait_on_page_bit+0x18c/0x2a0\n";
+        assert_eq!(top_stack_frame(stack), Some("io_schedule"));
+    }
+}
@@ -0,0 +1,310 @@
+use crate::ci::{CiReport, CiViolation};
+use crate::recorder::{Collector, FinishContext, TickContext};
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use colored::*;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicI32, Ordering};
+
+// 超限时的退出码，每种限制各不相同；1 保留给一般错误
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+    Cpu,
+    Pss,
+    Restarts,
+}
+
+impl Limit {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Limit::Cpu => "CPU",
+            Limit::Pss => "PSS",
+            Limit::Restarts => "Restarts",
+        }
+    }
+
+    pub fn flag(&self) -> &'static str {
+        match self {
+            Limit::Cpu => "--max-cpu",
+            Limit::Pss => "--max-pss-kb",
+            Limit::Restarts => "--max-restarts",
+        }
+    }
+
+    pub fn format(&self, value: f64) -> String {
+        match self {
+            Limit::Cpu => format!("{:.1}%", value),
+            Limit::Pss => format!("{:.0} KB", value),
+            Limit::Restarts => format!("{:.0}", value),
+        }
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Limit::Cpu => 2,
+            Limit::Pss => 3,
+            Limit::Restarts => 4,
+        }
+    }
+}
+
+// 某项限制的超限记录
+#[derive(Debug, Clone)]
+pub struct Violation {
+    pub limit: Limit,
+    pub max: f64,
+    pub first_at: DateTime<Local>,
+    pub worst: f64,
+    pub worst_at: DateTime<Local>,
+    pub samples: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ThresholdChecker {
+    max_cpu: Option<f64>,
+    max_pss_kb: Option<f64>,
+    max_restarts: Option<f64>,
+    pub violations: Vec<Violation>,
+}
+
+impl ThresholdChecker {
+    pub fn new(max_cpu: Option<f64>, max_pss_kb: Option<u64>, max_restarts: Option<u32>) -> Self {
+        Self {
+            max_cpu,
+            max_pss_kb: max_pss_kb.map(|kb| kb as f64),
+            max_restarts: max_restarts.map(f64::from),
+            violations: Vec::new(),
+        }
+    }
+
+    fn max(&self, limit: Limit) -> Option<f64> {
+        match limit {
+            Limit::Cpu => self.max_cpu,
+            Limit::Pss => self.max_pss_kb,
+            Limit::Restarts => self.max_restarts,
+        }
+    }
+
+    // 超限时更新记录；该限制首次超限时返回记录，用于打印告警
+    pub fn check(
+        &mut self,
+        limit: Limit,
+        value: f64,
+        timestamp: DateTime<Local>,
+    ) -> Option<&Violation> {
+        let max = self.max(limit).filter(|max| value > *max)?;
+        match self.violations.iter().position(|v| v.limit == limit) {
+            Some(index) => {
+                let violation = &mut self.violations[index];
+                violation.samples += 1;
+                if value > violation.worst {
+                    violation.worst = value;
+                    violation.worst_at = timestamp;
+                }
+                None
+            }
+            None => {
+                self.violations.push(Violation {
+                    limit,
+                    max,
+                    first_at: timestamp,
+                    worst: value,
+                    worst_at: timestamp,
+                    samples: 1,
+                });
+                self.violations.last()
+            }
+        }
+    }
+
+    // 多项超限时取最先设置的限制（CPU、PSS、重启次数）对应的退出码
+    pub fn exit_code(&self) -> i32 {
+        self.violations
+            .iter()
+            .map(|v| v.limit.exit_code())
+            .min()
+            .unwrap_or(0)
+    }
+}
+
+pub fn write_violations_csv(
+    dir: &Path,
+    package: &str,
+    violations: &[Violation],
+) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{}_threshold_violations.csv", package));
+    let mut file = std::fs::File::create(&path)?;
+    writeln!(
+        file,
+        "Limit,Max,First Exceeded,Worst,Worst At,Samples Over Limit,Exit Code"
+    )?;
+    for v in violations {
+        writeln!(
+            file,
+            "{},{},{},{},{},{},{}",
+            v.limit.as_str(),
+            v.max,
+            v.first_at.format("%Y-%m-%d %H:%M:%S"),
+            v.worst,
+            v.worst_at.format("%Y-%m-%d %H:%M:%S"),
+            v.samples,
+            v.limit.exit_code()
+        )?;
+    }
+    Ok(path)
+}
+
+// 整个运行期间（多个监控窗口或设备）的退出码，取最小的非零值
+static EXIT_CODE: AtomicI32 = AtomicI32::new(0);
+
+pub fn record_exit_code(code: i32) {
+    if code == 0 {
+        return;
+    }
+    let _ = EXIT_CODE.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |current| {
+        (current == 0 || code < current).then_some(code)
+    });
+}
+
+pub fn exit_code() -> i32 {
+    EXIT_CODE.load(Ordering::SeqCst)
+}
+
+// --max-cpu / --max-pss-kb / --max-restarts：首次超限时打印醒目的告警并记录进程退出码
+pub struct ThresholdCollector {
+    checker: ThresholdChecker,
+}
+
+impl ThresholdCollector {
+    pub fn new(max_cpu: Option<f64>, max_pss_kb: Option<u64>, max_restarts: Option<u32>) -> Self {
+        Self {
+            checker: ThresholdChecker::new(max_cpu, max_pss_kb, max_restarts),
+        }
+    }
+
+    // 返回本次是否触发告警
+    fn alert(&mut self, limit: Limit, value: f64, timestamp: DateTime<Local>) -> bool {
+        let Some(v) = self.checker.check(limit, value, timestamp) else {
+            return false;
+        };
+        console!(
+            "[{}] {} {} {} exceeds {} {}",
+            timestamp.format("%H:%M:%S").to_string().blue(),
+            "ALERT:".red().bold(),
+            v.limit.as_str(),
+            v.limit.format(value).red().bold(),
+            v.limit.flag(),
+            v.limit.format(v.max)
+        );
+        record_exit_code(v.limit.exit_code());
+        true
+    }
+}
+
+impl Collector for ThresholdCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        let sample = ctx.sample;
+        let timestamp = ctx.tick.timestamp;
+        if sample.restarted {
+            self.alert(Limit::Restarts, ctx.peaks.restarts as f64, Local::now());
+        }
+        // 预热期间只检查重启次数
+        if ctx.in_warmup {
+            return Ok(());
+        }
+        if let Some(cpu) = sample.cpu {
+            if self.alert(Limit::Cpu, cpu as f64, timestamp) {
+                ctx.anomaly.get_or_insert_with(|| "CPU alert".to_string());
+            }
+        }
+        if let Some(details) = &sample.memory {
+            if self.alert(Limit::Pss, details.total_pss as f64, timestamp) {
+                ctx.anomaly.get_or_insert_with(|| "PSS alert".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        let violations = &self.checker.violations;
+        if violations.is_empty() {
+            return;
+        }
+        for v in violations {
+            console!(
+                "{} {} exceeded {} {} from {}: worst {} at {}, {} samples over (exit code {})",
+                "THRESHOLD VIOLATION:".red().bold(),
+                v.limit.as_str(),
+                v.limit.flag(),
+                v.limit.format(v.max),
+                v.first_at.format("%Y-%m-%d %H:%M:%S"),
+                v.limit.format(v.worst).red(),
+                v.worst_at.format("%Y-%m-%d %H:%M:%S"),
+                v.samples,
+                v.limit.exit_code()
+            );
+        }
+        match write_violations_csv(ctx.layout.session_dir(), ctx.package, violations) {
+            Ok(path) => console!("✓ Threshold violations exported to CSV: {}", path.display()),
+            Err(e) => console!("Failed to export threshold violations: {}", e),
+        }
+    }
+
+    fn ci_report(&self, report: &mut CiReport) {
+        report.violations = self
+            .checker
+            .violations
+            .iter()
+            .map(CiViolation::from)
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(secs: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2026, 10, 15, 14, 0, secs).unwrap()
+    }
+
+    #[test]
+    fn check_reports_first_violation_only() {
+        let mut checker = ThresholdChecker::new(Some(80.0), Some(200_000), None);
+        assert!(checker.check(Limit::Cpu, 80.0, at(0)).is_none());
+        assert!(checker.violations.is_empty());
+
+        let first = checker.check(Limit::Cpu, 90.0, at(1)).unwrap();
+        assert_eq!(first.max, 80.0);
+        assert_eq!(first.samples, 1);
+        assert!(checker.check(Limit::Cpu, 120.0, at(2)).is_none());
+        assert!(checker.check(Limit::Cpu, 85.0, at(3)).is_none());
+
+        let cpu = &checker.violations[0];
+        assert_eq!(cpu.samples, 3);
+        assert_eq!(cpu.first_at, at(1));
+        assert_eq!(cpu.worst, 120.0);
+        assert_eq!(cpu.worst_at, at(2));
+    }
+
+    #[test]
+    fn check_ignores_unset_limits() {
+        let mut checker = ThresholdChecker::new(None, None, None);
+        assert!(checker.check(Limit::Restarts, 5.0, at(0)).is_none());
+        assert_eq!(checker.exit_code(), 0);
+    }
+
+    #[test]
+    fn exit_code_prefers_first_limit() {
+        let mut checker = ThresholdChecker::new(Some(50.0), Some(100_000), Some(0));
+        checker.check(Limit::Restarts, 1.0, at(0));
+        assert_eq!(checker.exit_code(), 4);
+        checker.check(Limit::Pss, 150_000.0, at(1));
+        checker.check(Limit::Cpu, 75.0, at(2));
+        assert_eq!(checker.exit_code(), 2);
+        assert_eq!(checker.violations.len(), 3);
+    }
+}
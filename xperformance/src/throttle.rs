@@ -0,0 +1,256 @@
+use crate::cpufreq::FreqSample;
+use crate::layout::Category;
+use crate::recorder::{Collector, FinishContext, TickContext};
+use crate::regenerate;
+use crate::thermal;
+use crate::utils::CpuChartOverlay;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use colored::*;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// thermalservice 的状态达到 LIGHT 即表示系统已开始限频
+const THROTTLING_STATUS: u32 = 1;
+
+// 一段限频区间，end 为 None 表示仍在限频
+#[derive(Debug, Clone)]
+pub struct ThrottlePeriod {
+    pub start: DateTime<Local>,
+    pub end: Option<DateTime<Local>>,
+    pub reason: String,
+}
+
+impl ThrottlePeriod {
+    pub fn duration_secs(&self, now: DateTime<Local>) -> f64 {
+        (self.end.unwrap_or(now) - self.start).num_milliseconds() as f64 / 1000.0
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ThrottleEvent {
+    Started(String),
+    Ended(ThrottlePeriod),
+}
+
+// 某个簇的频率上限（scaling_max_freq）低于会话中出现过的最高上限，或 thermalservice 报告限频时视为限频
+#[derive(Default)]
+pub struct ThrottleDetector {
+    highest_limit: HashMap<String, u64>,
+    periods: Vec<ThrottlePeriod>,
+}
+
+impl ThrottleDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn periods(&self) -> &[ThrottlePeriod] {
+        &self.periods
+    }
+
+    pub fn is_throttled(&self) -> bool {
+        self.periods.last().is_some_and(|p| p.end.is_none())
+    }
+
+    // 本次采样的限频原因，未限频时返回 None
+    fn reasons(
+        &mut self,
+        freq: Option<&FreqSample>,
+        thermal_status: Option<u32>,
+    ) -> Option<String> {
+        let mut reasons = Vec::new();
+        for policy in freq.iter().flat_map(|sample| &sample.policies) {
+            let highest = self
+                .highest_limit
+                .entry(policy.policy.clone())
+                .or_insert(policy.scaling_max_khz);
+            *highest = (*highest).max(policy.scaling_max_khz);
+            if policy.scaling_max_khz < *highest {
+                reasons.push(format!(
+                    "{} capped at {} MHz (was {} MHz)",
+                    policy.policy,
+                    policy.scaling_max_khz / 1000,
+                    *highest / 1000
+                ));
+            }
+        }
+        if let Some(status) = thermal_status.filter(|s| *s >= THROTTLING_STATUS) {
+            reasons.push(format!("thermal status {}", thermal::status_name(status)));
+        }
+        (!reasons.is_empty()).then(|| reasons.join("; "))
+    }
+
+    // 每个 Tick 调用一次，进入或离开限频时返回事件
+    pub fn update(
+        &mut self,
+        timestamp: DateTime<Local>,
+        freq: Option<&FreqSample>,
+        thermal_status: Option<u32>,
+    ) -> Option<ThrottleEvent> {
+        if freq.is_none() && thermal_status.is_none() {
+            return None;
+        }
+        match (self.reasons(freq, thermal_status), self.is_throttled()) {
+            (Some(reason), false) => {
+                self.periods.push(ThrottlePeriod {
+                    start: timestamp,
+                    end: None,
+                    reason: reason.clone(),
+                });
+                Some(ThrottleEvent::Started(reason))
+            }
+            (None, true) => {
+                let period = self.periods.last_mut()?;
+                period.end = Some(timestamp);
+                Some(ThrottleEvent::Ended(period.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    // 会话结束时仍在限频则以结束时间收尾
+    pub fn finish(&mut self, timestamp: DateTime<Local>) {
+        if let Some(period) = self.periods.last_mut().filter(|p| p.end.is_none()) {
+            period.end = Some(timestamp);
+        }
+    }
+
+    // 图表底纹用的区间，未结束的区间延伸到 now
+    pub fn ranges(&self, now: DateTime<Local>) -> Vec<(DateTime<Local>, DateTime<Local>)> {
+        self.periods
+            .iter()
+            .map(|p| (p.start, p.end.unwrap_or(now)))
+            .collect()
+    }
+}
+
+pub fn write_periods_csv(dir: &Path, package: &str, periods: &[ThrottlePeriod]) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{}_throttling.csv", package));
+    let mut file = std::fs::File::create(&path)?;
+    writeln!(file, "Start,End,Duration (s),Reason")?;
+    let now = Local::now();
+    for period in periods {
+        writeln!(
+            file,
+            "{},{},{:.0},{}",
+            period.start.format("%Y-%m-%d %H:%M:%S"),
+            period
+                .end
+                .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_default(),
+            period.duration_secs(now),
+            period.reason.replace(',', ";")
+        )?;
+    }
+    Ok(path)
+}
+
+// 读取 write_periods_csv 写出的文件，用于重新生成图表
+pub fn read_periods_csv(path: &Path) -> Result<Vec<ThrottlePeriod>> {
+    let table = regenerate::read_csv(path)?;
+    Ok(table
+        .rows
+        .iter()
+        .filter_map(|row| {
+            Some(ThrottlePeriod {
+                start: regenerate::parse_timestamp(row.first()?)?,
+                end: row.get(1).and_then(|t| regenerate::parse_timestamp(t)),
+                reason: row.get(3).cloned().unwrap_or_default(),
+            })
+        })
+        .collect())
+}
+
+#[derive(Default)]
+pub struct ThrottleCollector {
+    detector: ThrottleDetector,
+}
+
+impl Collector for ThrottleCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        let tick = ctx.tick;
+        match self
+            .detector
+            .update(tick.timestamp, ctx.cpu_freq.as_ref(), ctx.thermal_status)
+        {
+            Some(ThrottleEvent::Started(reason)) => console!(
+                "[{}] {} {}",
+                tick.timestamp.format("%H:%M:%S").to_string().blue(),
+                "Throttling started:".red().bold(),
+                reason
+            ),
+            Some(ThrottleEvent::Ended(period)) => console!(
+                "[{}] {} after {:.0}s ({})",
+                tick.timestamp.format("%H:%M:%S").to_string().blue(),
+                "Throttling ended".green(),
+                period.duration_secs(tick.timestamp),
+                period.reason
+            ),
+            None => {}
+        }
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        self.detector.finish(Local::now());
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        let periods = self.detector.periods();
+        if periods.is_empty() {
+            return;
+        }
+        let total: f64 = periods.iter().map(|p| p.duration_secs(Local::now())).sum();
+        console!(
+            "Throttled: {} periods, {} in total",
+            periods.len(),
+            format!("{:.0}s", total).red()
+        );
+        match write_periods_csv(&ctx.layout.dir(Category::Thermal), ctx.package, periods) {
+            Ok(path) => console!("✓ Throttling periods exported to CSV: {}", path.display()),
+            Err(e) => console!("Failed to export throttling periods: {}", e),
+        }
+    }
+
+    fn cpu_overlay(&self, overlay: &mut CpuChartOverlay) {
+        overlay.throttled.extend(self.detector.ranges(Local::now()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpufreq::PolicyFreq;
+    use chrono::TimeZone;
+
+    fn freq(secs: u32, scaling_max_khz: u64) -> FreqSample {
+        FreqSample {
+            timestamp: Local.with_ymd_and_hms(2024, 1, 1, 12, 0, secs).unwrap(),
+            policies: vec![PolicyFreq {
+                policy: "policy7".to_string(),
+                cpus: "7".to_string(),
+                cur_khz: scaling_max_khz,
+                max_khz: 3187200,
+                scaling_max_khz,
+                governor: "schedutil".to_string(),
+            }],
+        }
+    }
+
+    #[test]
+    fn detector_opens_and_closes_period_on_limit_change() {
+        let mut detector = ThrottleDetector::new();
+        for (secs, limit) in [(0, 3187200), (1, 2188800), (2, 2188800), (3, 3187200)] {
+            let sample = freq(secs, limit);
+            detector.update(sample.timestamp, Some(&sample), None);
+        }
+        let periods = detector.periods();
+        assert_eq!(periods.len(), 1);
+        assert_eq!(periods[0].duration_secs(Local::now()), 2.0);
+        assert!(periods[0].reason.contains("capped at 2188 MHz"));
+        assert!(!detector.is_throttled());
+    }
+}
@@ -0,0 +1,148 @@
+use crate::layout::{Category, OutputLayout};
+use crate::logcat::EventKind;
+use crate::recorder::{Collector, FinishContext, TickContext};
+use crate::replay;
+use crate::utils;
+use anyhow::Result;
+use colored::*;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+const TOMBSTONE_DIR: &str = "/data/tombstones";
+
+// /data/tombstones 通常只有 root 可读；依次尝试直接读取（adb root）、run-as、su
+fn access_prefixes(package: &str) -> [Vec<String>; 3] {
+    [
+        Vec::new(),
+        vec!["run-as".to_string(), package.to_string()],
+        vec!["su".to_string(), "0".to_string()],
+    ]
+}
+
+fn run_with_prefix(prefix: &[String], command: &[&str]) -> Result<String> {
+    let mut args: Vec<&str> = vec!["shell"];
+    args.extend(prefix.iter().map(String::as_str));
+    args.extend_from_slice(command);
+    utils::run_adb_command(&args)
+}
+
+// 发生 native crash 或进程意外重启时拉取最新的 tombstone，同一文件只拉取一次
+pub struct TombstoneCollector {
+    package: String,
+    // 可用的访问方式，首次成功后固定使用
+    prefix: Option<Vec<String>>,
+    seen: HashSet<String>,
+    pub pulled: Vec<PathBuf>,
+    // 本 Tick 发生了 native crash 或重启，下一次采样时统一拉取
+    pending: bool,
+}
+
+impl TombstoneCollector {
+    pub fn new(package: &str) -> Self {
+        Self {
+            package: package.to_string(),
+            prefix: None,
+            seen: HashSet::new(),
+            pulled: Vec::new(),
+            pending: false,
+        }
+    }
+
+    // 回放时没有设备，不读取已有的 tombstone
+    pub fn start(package: &str) -> Self {
+        let mut collector = Self::new(package);
+        if !replay::is_replaying() {
+            collector.mark_existing();
+        }
+        collector
+    }
+
+    // 最新的文本 tombstone（Android 12+ 同时生成的 .pb 跳过）
+    fn latest(&mut self) -> Result<String> {
+        let prefixes = match &self.prefix {
+            Some(prefix) => vec![prefix.clone()],
+            None => access_prefixes(&self.package).to_vec(),
+        };
+        for prefix in prefixes {
+            let Ok(output) = run_with_prefix(&prefix, &["ls", "-t", TOMBSTONE_DIR]) else {
+                continue;
+            };
+            let Some(name) = output
+                .lines()
+                .map(str::trim)
+                .find(|name| name.starts_with("tombstone_") && !name.ends_with(".pb"))
+            else {
+                continue;
+            };
+            self.prefix = Some(prefix);
+            return Ok(name.to_string());
+        }
+        anyhow::bail!("No readable tombstone in {} (needs root)", TOMBSTONE_DIR)
+    }
+
+    // 监控开始前已存在的最新 tombstone 不属于本次会话
+    pub fn mark_existing(&mut self) {
+        if let Ok(name) = self.latest() {
+            self.seen.insert(name);
+        }
+    }
+
+    // 返回新拉取的文件；最新 tombstone 已拉取过或不属于该应用时返回 None
+    pub fn collect(&mut self, dir: &Path) -> Result<Option<PathBuf>> {
+        let name = self.latest()?;
+        if !self.seen.insert(name.clone()) {
+            return Ok(None);
+        }
+        let prefix = self.prefix.clone().unwrap_or_default();
+        let content = run_with_prefix(&prefix, &["cat", &format!("{}/{}", TOMBSTONE_DIR, name)])?;
+        // 头部形如 "pid: 1234, tid: 1250, name: RenderThread  >>> com.example.app <<<"
+        if !content.contains(&format!(">>> {} <<<", self.package)) {
+            return Ok(None);
+        }
+
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(format!("{}.txt", name));
+        std::fs::write(&path, content)?;
+        self.pulled.push(path.clone());
+        Ok(Some(path))
+    }
+}
+
+impl Collector for TombstoneCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        self.pending |= ctx.sample.restarted
+            || ctx
+                .events
+                .iter()
+                .any(|event| event.kind == EventKind::NativeCrash);
+        if !self.pending || replay::is_replaying() {
+            return Ok(());
+        }
+        self.pending = false;
+        let tombstones_dir = OutputLayout::session(ctx.package)?.dir(Category::Tombstones);
+        match self.collect(&tombstones_dir) {
+            Ok(Some(path)) => console!(
+                "[{}] {} {}",
+                ctx.tick.timestamp.format("%H:%M:%S").to_string().blue(),
+                "Tombstone pulled:".red(),
+                path.display()
+            ),
+            Ok(None) => {}
+            Err(e) => console!("Failed to pull tombstone: {}", e),
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, _ctx: &FinishContext) {
+        if self.pulled.is_empty() {
+            return;
+        }
+        console!(
+            "Native crash tombstones: {}",
+            self.pulled.len().to_string().red().bold()
+        );
+        for path in &self.pulled {
+            console!("  {}", path.display());
+        }
+    }
+}
@@ -0,0 +1,362 @@
+use crate::utils;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+// 设备端 trace 输出目录，shell 用户可写（Android 9+）
+pub const REMOTE_TRACE_DIR: &str = "/data/misc/perfetto-traces";
+
+// 内置的 perfetto 配置，只包含 data_sources，buffers 和 duration 由 build_config 追加
+pub struct TraceConfig {
+    pub name: &'static str,
+    pub description: &'static str,
+    data_sources: &'static str,
+}
+
+pub const CONFIGS: &[TraceConfig] = &[
+    TraceConfig {
+        name: "scheduling",
+        description: "CPU scheduling, wakeups, frequency and idle states",
+        data_sources: r#"data_sources: {
+  config {
+    name: "linux.ftrace"
+    ftrace_config {
+      ftrace_events: "sched/sched_switch"
+      ftrace_events: "sched/sched_wakeup"
+      ftrace_events: "sched/sched_wakeup_new"
+      ftrace_events: "sched/sched_waking"
+      ftrace_events: "sched/sched_process_exit"
+      ftrace_events: "sched/sched_process_free"
+      ftrace_events: "task/task_newtask"
+      ftrace_events: "task/task_rename"
+      ftrace_events: "power/cpu_frequency"
+      ftrace_events: "power/cpu_idle"
+      ftrace_events: "power/suspend_resume"
+      atrace_categories: "sched"
+      atrace_categories: "freq"
+      atrace_categories: "idle"
+    }
+  }
+}
+data_sources: {
+  config {
+    name: "linux.process_stats"
+    process_stats_config {
+      scan_all_processes_on_start: true
+    }
+  }
+}
+"#,
+    },
+    TraceConfig {
+        name: "memory",
+        description: "Process RSS, meminfo/vmstat counters, LMK and ION events",
+        data_sources: r#"data_sources: {
+  config {
+    name: "linux.process_stats"
+    process_stats_config {
+      scan_all_processes_on_start: true
+      proc_stats_poll_ms: 1000
+    }
+  }
+}
+data_sources: {
+  config {
+    name: "linux.sys_stats"
+    sys_stats_config {
+      meminfo_period_ms: 1000
+      vmstat_period_ms: 1000
+    }
+  }
+}
+data_sources: {
+  config {
+    name: "linux.ftrace"
+    ftrace_config {
+      ftrace_events: "kmem/rss_stat"
+      ftrace_events: "kmem/ion_heap_grow"
+      ftrace_events: "kmem/ion_heap_shrink"
+      ftrace_events: "mm_event/mm_event_record"
+      ftrace_events: "lowmemorykiller/lowmemory_kill"
+      ftrace_events: "oom/oom_score_adj_update"
+      ftrace_events: "sched/sched_process_exit"
+      ftrace_events: "task/task_newtask"
+      ftrace_events: "task/task_rename"
+      atrace_apps: "lmkd"
+    }
+  }
+}
+"#,
+    },
+    TraceConfig {
+        name: "graphics",
+        description: "Frame timeline, SurfaceFlinger, RenderThread and input slices",
+        data_sources: r#"data_sources: {
+  config {
+    name: "linux.ftrace"
+    ftrace_config {
+      ftrace_events: "sched/sched_switch"
+      ftrace_events: "sched/sched_wakeup"
+      ftrace_events: "power/cpu_frequency"
+      atrace_categories: "gfx"
+      atrace_categories: "view"
+      atrace_categories: "input"
+      atrace_categories: "wm"
+      atrace_categories: "am"
+      atrace_categories: "hal"
+      atrace_categories: "dalvik"
+      atrace_apps: "*"
+    }
+  }
+}
+data_sources: {
+  config {
+    name: "android.surfaceflinger.frametimeline"
+  }
+}
+data_sources: {
+  config {
+    name: "linux.process_stats"
+    process_stats_config {
+      scan_all_processes_on_start: true
+    }
+  }
+}
+"#,
+    },
+];
+
+pub fn find_config(name: &str) -> Option<&'static TraceConfig> {
+    CONFIGS.iter().find(|c| c.name == name)
+}
+
+// trace 选项：内置配置名或本地 .pbtxt 文件，以及附加的 atrace 类别和应用
+#[derive(Debug, Clone, Default)]
+pub struct TraceOptions {
+    pub config: String,
+    pub duration: Option<Duration>,
+    pub buffer_mb: u32,
+    pub categories: Vec<String>,
+    pub apps: Vec<String>,
+}
+
+impl TraceOptions {
+    // 用于输出文件名的配置标签
+    pub fn label(&self) -> String {
+        Path::new(&self.config)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| self.config.clone())
+    }
+}
+
+// 生成完整的文本格式 perfetto 配置
+pub fn build_config(options: &TraceOptions) -> Result<String> {
+    let mut text = String::new();
+
+    if let Some(config) = find_config(&options.config) {
+        text.push_str(&format!(
+            "buffers: {{\n  size_kb: {}\n  fill_policy: RING_BUFFER\n}}\n",
+            options.buffer_mb.max(1) * 1024
+        ));
+        if let Some(duration) = options.duration {
+            text.push_str(&format!("duration_ms: {}\n", duration.as_millis()));
+        }
+        text.push_str(config.data_sources);
+    } else {
+        // 自定义配置文件原样使用，由文件自行指定 buffers 和 duration
+        let path = Path::new(&options.config);
+        if !path.is_file() {
+            anyhow::bail!(
+                "Unknown trace config: {} (expected one of: {}, or a .pbtxt file)",
+                options.config,
+                CONFIGS
+                    .iter()
+                    .map(|c| c.name)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+        text.push_str(
+            &std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?,
+        );
+    }
+
+    // 额外的 atrace 类别和应用作为独立的 ftrace 数据源追加
+    if !options.categories.is_empty() || !options.apps.is_empty() {
+        text.push_str(
+            "data_sources: {\n  config {\n    name: \"linux.ftrace\"\n    ftrace_config {\n",
+        );
+        for category in &options.categories {
+            text.push_str(&format!("      atrace_categories: \"{}\"\n", category));
+        }
+        for app in &options.apps {
+            text.push_str(&format!("      atrace_apps: \"{}\"\n", app));
+        }
+        text.push_str("    }\n  }\n}\n");
+    }
+
+    Ok(text)
+}
+
+// 正在进行的后台 trace，保存在 log/xtrace/active_trace.json，供 stop 使用
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActiveTrace {
+    pub serial: Option<String>,
+    pub pid: String,
+    pub label: String,
+    pub remote_path: String,
+    pub started: String,
+}
+
+impl ActiveTrace {
+    pub fn path() -> PathBuf {
+        PathBuf::from("log")
+            .join("xtrace")
+            .join("active_trace.json")
+    }
+
+    pub fn load() -> Result<Option<Self>> {
+        let path = Self::path();
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)?;
+        serde_json::from_str(&content)
+            .map(Some)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn clear() -> Result<()> {
+        let path = Self::path();
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+// 以后台模式启动 perfetto，配置通过 stdin 传入以绕过 SELinux 对配置文件路径的限制
+pub fn start_trace(serial: Option<&str>, options: &TraceOptions) -> Result<ActiveTrace> {
+    let config = build_config(options)?;
+    let label = options.label();
+    let started = chrono::Local::now();
+    let remote_path = format!(
+        "{}/{}_{}.perfetto-trace",
+        REMOTE_TRACE_DIR,
+        label,
+        started.format("%Y%m%d_%H%M%S")
+    );
+
+    // Android 9/10 默认未启用 traced
+    let _ = utils::run_adb_command_on(serial, &["shell", "setprop", "persist.traced.enable", "1"]);
+
+    let mut command = Command::new("adb");
+    if let Some(serial) = serial.map(str::to_string).or_else(utils::adb_serial) {
+        command.args(["-s", &serial]);
+    }
+    let mut child = command
+        .args([
+            "shell",
+            "perfetto",
+            "--background",
+            "--txt",
+            "-c",
+            "-",
+            "-o",
+            &remote_path,
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to execute adb command")?;
+    child
+        .stdin
+        .take()
+        .ok_or_else(|| anyhow::format_err!("Failed to open perfetto stdin"))?
+        .write_all(config.as_bytes())?;
+    let output = child.wait_with_output()?;
+
+    // --background 模式下 perfetto 将守护进程的 PID 输出到 stdout
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let pid = stdout
+        .lines()
+        .map(str::trim)
+        .rfind(|l| !l.is_empty() && l.chars().all(|c| c.is_ascii_digit()))
+        .map(str::to_string);
+    let Some(pid) = pid else {
+        anyhow::bail!(
+            "Failed to start perfetto: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    };
+
+    Ok(ActiveTrace {
+        serial: serial.map(str::to_string),
+        pid,
+        label,
+        remote_path,
+        started: started.format("%Y-%m-%d %H:%M:%S").to_string(),
+    })
+}
+
+pub fn is_running(trace: &ActiveTrace) -> bool {
+    utils::run_adb_command_on(
+        trace.serial.as_deref(),
+        &["shell", "ls", &format!("/proc/{}", trace.pid)],
+    )
+    .is_ok()
+}
+
+// 发送 SIGTERM 让 perfetto 写完缓冲区后退出
+pub fn stop_trace(trace: &ActiveTrace) -> Result<()> {
+    if is_running(trace) {
+        utils::run_adb_command_on(
+            trace.serial.as_deref(),
+            &["shell", "kill", "-TERM", &trace.pid],
+        )?;
+    }
+
+    let deadline = Instant::now() + Duration::from_secs(30);
+    while is_running(trace) {
+        if Instant::now() >= deadline {
+            anyhow::bail!("perfetto (pid {}) did not exit in time", trace.pid);
+        }
+        std::thread::sleep(Duration::from_millis(500));
+    }
+    Ok(())
+}
+
+// 拉取 trace 文件到本地目录并删除设备端文件
+pub fn pull_trace(trace: &ActiveTrace, output_dir: &Path) -> Result<PathBuf> {
+    std::fs::create_dir_all(output_dir)?;
+    let file_name = trace
+        .remote_path
+        .rsplit('/')
+        .next()
+        .unwrap_or("trace.perfetto-trace");
+    let local_path = output_dir.join(file_name);
+    utils::run_adb_command_on(
+        trace.serial.as_deref(),
+        &["pull", &trace.remote_path, &local_path.to_string_lossy()],
+    )?;
+    let _ = utils::run_adb_command_on(
+        trace.serial.as_deref(),
+        &["shell", "rm", "-f", &trace.remote_path],
+    );
+    Ok(local_path)
+}
@@ -0,0 +1,441 @@
+use crate::cpu::ThreadCpuInfo;
+use crate::recorder::{Collector, TickContext};
+use crate::utils;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph, Row, Sparkline, Table};
+use ratatui::Frame;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+// 折线图保留的采样点数和日志区保留的行数
+const HISTORY_LEN: usize = 300;
+const LOG_LEN: usize = 200;
+
+// 线程表的排序方式，按 s 循环切换
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ThreadSort {
+    Cpu,
+    Name,
+    Tid,
+}
+
+impl ThreadSort {
+    fn next(self) -> Self {
+        match self {
+            ThreadSort::Cpu => ThreadSort::Name,
+            ThreadSort::Name => ThreadSort::Tid,
+            ThreadSort::Tid => ThreadSort::Cpu,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            ThreadSort::Cpu => "CPU",
+            ThreadSort::Name => "name",
+            ThreadSort::Tid => "TID",
+        }
+    }
+}
+
+// 每个 Tick 结束时从监控循环传入的数据
+#[derive(Debug, Clone)]
+pub struct DashboardUpdate {
+    pub pid: String,
+    pub cpu: Option<f32>,
+    pub pss_kb: Option<u64>,
+    pub threads: Vec<ThreadCpuInfo>,
+    pub peak_cpu: f32,
+    pub peak_cpu_time: DateTime<Local>,
+    pub peak_memory_kb: u64,
+    pub peak_memory_time: DateTime<Local>,
+    pub restarts: u32,
+}
+
+struct DashboardState {
+    package: String,
+    started: DateTime<Local>,
+    samples: u64,
+    cpu: VecDeque<f32>,
+    pss_kb: VecDeque<u64>,
+    latest: Option<DashboardUpdate>,
+    log: VecDeque<String>,
+    sort: ThreadSort,
+}
+
+// 仪表盘运行期间为 Some，console_line 据此决定输出到日志区还是终端
+static STATE: Mutex<Option<DashboardState>> = Mutex::new(None);
+static PAUSED: AtomicBool = AtomicBool::new(false);
+static EXPORT_REQUESTED: AtomicBool = AtomicBool::new(false);
+static ATRACE_REQUESTED: AtomicBool = AtomicBool::new(false);
+static MARKER_REQUESTED: AtomicBool = AtomicBool::new(false);
+// --ci 时 stdout 只留给最终的 JSON，其余输出不带颜色写到 stderr
+static CI_MODE: AtomicBool = AtomicBool::new(false);
+
+pub fn set_ci_mode() {
+    CI_MODE.store(true, Ordering::SeqCst);
+    colored::control::set_override(false);
+}
+
+pub fn is_paused() -> bool {
+    PAUSED.load(Ordering::SeqCst)
+}
+
+// 按 e 请求立即导出，取出后清除
+pub fn take_export_request() -> bool {
+    EXPORT_REQUESTED.swap(false, Ordering::SeqCst)
+}
+
+// 按 t 请求抓取一段 atrace，取出后清除
+pub fn take_atrace_request() -> bool {
+    ATRACE_REQUESTED.swap(false, Ordering::SeqCst)
+}
+
+// 按 m 插入一个事件标记，取出后清除
+pub fn take_marker_request() -> bool {
+    MARKER_REQUESTED.swap(false, Ordering::SeqCst)
+}
+
+fn push_log(state: &mut DashboardState, text: &str) {
+    for line in text.lines() {
+        if state.log.len() == LOG_LEN {
+            state.log.pop_front();
+        }
+        state.log.push_back(line.to_string());
+    }
+}
+
+// 仪表盘运行时输出进入日志区，否则直接打印
+pub fn console_line(text: String) {
+    if let Ok(mut state) = STATE.lock() {
+        if let Some(state) = state.as_mut() {
+            push_log(state, &text);
+            return;
+        }
+    }
+    if CI_MODE.load(Ordering::SeqCst) {
+        eprintln!("{}", text);
+    } else {
+        println!("{}", text);
+    }
+}
+
+pub fn update(update: DashboardUpdate) {
+    let Ok(mut state) = STATE.lock() else {
+        return;
+    };
+    let Some(state) = state.as_mut() else {
+        return;
+    };
+    state.samples += 1;
+    if let Some(cpu) = update.cpu {
+        if state.cpu.len() == HISTORY_LEN {
+            state.cpu.pop_front();
+        }
+        state.cpu.push_back(cpu);
+    }
+    if let Some(pss_kb) = update.pss_kb {
+        if state.pss_kb.len() == HISTORY_LEN {
+            state.pss_kb.pop_front();
+        }
+        state.pss_kb.push_back(pss_kb);
+    }
+    state.latest = Some(update);
+}
+
+// 终端仪表盘，Drop 时结束绘制线程并恢复终端
+pub struct Dashboard {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Dashboard {
+    // running 为监控循环的运行标志，按 q 或 Ctrl-C 时置为 false
+    pub fn start(package: &str, running: Arc<AtomicBool>) -> Result<Self> {
+        let mut terminal = ratatui::try_init()?;
+        // 日志区按纯文本显示，关闭终端颜色码
+        colored::control::set_override(false);
+        PAUSED.store(false, Ordering::SeqCst);
+        EXPORT_REQUESTED.store(false, Ordering::SeqCst);
+        ATRACE_REQUESTED.store(false, Ordering::SeqCst);
+        MARKER_REQUESTED.store(false, Ordering::SeqCst);
+        *STATE.lock().unwrap() = Some(DashboardState {
+            package: package.to_string(),
+            started: Local::now(),
+            samples: 0,
+            cpu: VecDeque::new(),
+            pss_kb: VecDeque::new(),
+            latest: None,
+            log: VecDeque::new(),
+            sort: ThreadSort::Cpu,
+        });
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let stop = stop.clone();
+            std::thread::spawn(move || {
+                while !stop.load(Ordering::SeqCst) {
+                    if let Ok(state) = STATE.lock() {
+                        if let Some(state) = state.as_ref() {
+                            let _ = terminal.draw(|frame| render(frame, state));
+                        }
+                    }
+                    if !event::poll(Duration::from_millis(200)).unwrap_or(false) {
+                        continue;
+                    }
+                    let Ok(Event::Key(key)) = event::read() else {
+                        continue;
+                    };
+                    if key.kind != KeyEventKind::Press {
+                        continue;
+                    }
+                    let mut state = STATE.lock().unwrap();
+                    let Some(state) = state.as_mut() else {
+                        break;
+                    };
+                    match key.code {
+                        // 原始模式下 Ctrl-C 不会产生信号，在这里处理
+                        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            running.store(false, Ordering::SeqCst);
+                            utils::set_interrupt_flag();
+                        }
+                        KeyCode::Char('q') | KeyCode::Esc => {
+                            running.store(false, Ordering::SeqCst);
+                            utils::set_interrupt_flag();
+                            push_log(state, "Stopping...");
+                        }
+                        KeyCode::Char('p') => {
+                            let paused = !PAUSED.fetch_xor(true, Ordering::SeqCst);
+                            push_log(
+                                state,
+                                if paused {
+                                    "Sampling paused"
+                                } else {
+                                    "Sampling resumed"
+                                },
+                            );
+                        }
+                        KeyCode::Char('e') => {
+                            EXPORT_REQUESTED.store(true, Ordering::SeqCst);
+                            push_log(state, "Export requested, writing at the next sample");
+                        }
+                        KeyCode::Char('t') => {
+                            ATRACE_REQUESTED.store(true, Ordering::SeqCst);
+                            push_log(state, "atrace capture requested");
+                        }
+                        KeyCode::Char('m') => {
+                            MARKER_REQUESTED.store(true, Ordering::SeqCst);
+                        }
+                        KeyCode::Char('s') => state.sort = state.sort.next(),
+                        _ => {}
+                    }
+                }
+                ratatui::restore();
+            })
+        };
+
+        Ok(Self {
+            stop,
+            handle: Some(handle),
+        })
+    }
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+        *STATE.lock().unwrap() = None;
+        PAUSED.store(false, Ordering::SeqCst);
+        colored::control::unset_override();
+    }
+}
+
+fn format_mb(kb: u64) -> String {
+    format!("{:.1} MB", kb as f64 / 1024.0)
+}
+
+fn render(frame: &mut Frame, state: &DashboardState) {
+    let [header, graphs, middle, log, footer] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Length(8),
+        Constraint::Min(6),
+        Constraint::Length(8),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    let latest = state.latest.as_ref();
+    let elapsed = (Local::now() - state.started).num_seconds();
+    let mut title = format!(
+        " XPerformance  {}  PID {}  {:02}:{:02}:{:02}  {} samples",
+        state.package,
+        latest.map(|l| l.pid.as_str()).unwrap_or("-"),
+        elapsed / 3600,
+        elapsed % 3600 / 60,
+        elapsed % 60,
+        state.samples
+    );
+    if is_paused() {
+        title.push_str("  [PAUSED]");
+    }
+    frame.render_widget(
+        Paragraph::new(title).style(Style::default().add_modifier(Modifier::BOLD)),
+        header,
+    );
+
+    let [cpu_area, pss_area] =
+        Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(graphs);
+    render_sparkline(
+        frame,
+        cpu_area,
+        &format!(
+            " CPU {} ",
+            state
+                .cpu
+                .back()
+                .map(|cpu| format!("{:.1}%", cpu))
+                .unwrap_or_else(|| "-".to_string())
+        ),
+        // 保留一位小数的精度
+        &state
+            .cpu
+            .iter()
+            .map(|cpu| (cpu * 10.0).round().max(0.0) as u64)
+            .collect::<Vec<_>>(),
+        Color::Green,
+    );
+    render_sparkline(
+        frame,
+        pss_area,
+        &format!(
+            " PSS {} ",
+            state
+                .pss_kb
+                .back()
+                .map(|kb| format_mb(*kb))
+                .unwrap_or_else(|| "-".to_string())
+        ),
+        &state.pss_kb.iter().copied().collect::<Vec<_>>(),
+        Color::Cyan,
+    );
+
+    let [threads_area, peaks_area] =
+        Layout::horizontal([Constraint::Percentage(65), Constraint::Percentage(35)]).areas(middle);
+    let mut threads = latest.map(|l| l.threads.clone()).unwrap_or_default();
+    match state.sort {
+        ThreadSort::Cpu => threads.sort_by(|a, b| b.cpu_usage.total_cmp(&a.cpu_usage)),
+        ThreadSort::Name => threads.sort_by(|a, b| a.name.cmp(&b.name)),
+        ThreadSort::Tid => threads.sort_by_key(|t| t.tid.parse::<u64>().unwrap_or(u64::MAX)),
+    }
+    let rows = threads.iter().map(|thread| {
+        Row::new(vec![
+            thread.tid.clone(),
+            thread.name.clone(),
+            format!("{:.1}", thread.cpu_usage),
+        ])
+    });
+    frame.render_widget(
+        Table::new(
+            rows,
+            [
+                Constraint::Length(8),
+                Constraint::Min(16),
+                Constraint::Length(8),
+            ],
+        )
+        .header(
+            Row::new(vec!["TID", "Name", "CPU %"])
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .block(
+            Block::bordered().title(format!(" Top threads (sorted by {}) ", state.sort.as_str())),
+        ),
+        threads_area,
+    );
+
+    let peaks = match latest {
+        Some(l) => vec![
+            Line::from(format!(
+                "CPU:      {:.1}% at {}",
+                l.peak_cpu,
+                l.peak_cpu_time.format("%H:%M:%S")
+            )),
+            Line::from(format!(
+                "Memory:   {} at {}",
+                format_mb(l.peak_memory_kb),
+                l.peak_memory_time.format("%H:%M:%S")
+            )),
+            Line::from(format!("Restarts: {}", l.restarts)),
+        ],
+        None => vec![Line::from("Waiting for the first sample...")],
+    };
+    frame.render_widget(
+        Paragraph::new(peaks).block(Block::bordered().title(" Peaks ")),
+        peaks_area,
+    );
+
+    // 日志区只显示最后几行
+    let visible = log.height.saturating_sub(2) as usize;
+    let lines: Vec<Line> = state
+        .log
+        .iter()
+        .skip(state.log.len().saturating_sub(visible))
+        .map(|line| Line::from(line.as_str()))
+        .collect();
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title(" Log ")),
+        log,
+    );
+
+    frame.render_widget(
+        Paragraph::new(
+            " p pause/resume   e export CSV now   t capture atrace   m add marker   s sort threads   q quit",
+        )
+        .style(Style::default().fg(Color::DarkGray)),
+        footer,
+    );
+}
+
+fn render_sparkline(frame: &mut Frame, area: Rect, title: &str, data: &[u64], color: Color) {
+    // 只显示能放下的最近数据
+    let width = area.width.saturating_sub(2) as usize;
+    let data = &data[data.len().saturating_sub(width)..];
+    frame.render_widget(
+        Sparkline::default()
+            .block(Block::bordered().title(title.to_string()))
+            .data(data)
+            .style(Style::default().fg(color)),
+        area,
+    );
+}
+
+// 每个 Tick 把采样和峰值送到仪表盘
+pub struct DashboardPublisher;
+
+impl Collector for DashboardPublisher {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        let sample = ctx.sample;
+        update(DashboardUpdate {
+            pid: sample.pid.clone(),
+            cpu: sample.cpu,
+            pss_kb: sample.memory.as_ref().map(|details| details.total_pss),
+            threads: sample.threads.clone(),
+            peak_cpu: ctx.peaks.cpu,
+            peak_cpu_time: ctx.peaks.cpu_time.unwrap_or_default(),
+            peak_memory_kb: ctx.peaks.memory_kb,
+            peak_memory_time: ctx.peaks.memory_time.unwrap_or_default(),
+            restarts: ctx.peaks.restarts,
+        });
+        Ok(())
+    }
+}
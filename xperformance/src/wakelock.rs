@@ -0,0 +1,496 @@
+use crate::chart::{self, TimelineLane};
+use crate::layout::Category;
+use crate::recorder::{Collector, FinishContext, TickContext};
+use crate::regenerate::{self, CSV_TIMESTAMP_FORMAT};
+use crate::utils;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use colored::*;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// 时间线图中最多显示的 wakelock 数（按总持有时长）
+const MAX_TIMELINE_LANES: usize = 30;
+
+// dumpsys power 中 "Wake Locks: size=N" 段的一行
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeldWakelock {
+    pub level: String,
+    pub tag: String,
+    pub acquired_ago_ms: Option<i64>,
+    pub uid: Option<String>,
+    pub pid: Option<String>,
+    pub work_source: Option<String>,
+}
+
+impl HeldWakelock {
+    // 持有者 UID/PID、WorkSource 中的 UID 或标签中的包名（如 "*job*/com.foo/.Job"）任一匹配即归属该应用
+    pub fn belongs_to(&self, package: &str, uid: &str, pid: &str) -> bool {
+        let uid = (!uid.is_empty()).then_some(uid);
+        self.uid.as_deref().is_some_and(|u| Some(u) == uid)
+            || self.pid.as_deref() == Some(pid)
+            || self.work_source.as_deref().is_some_and(|ws| {
+                ws.split(|c: char| !c.is_ascii_alphanumeric() && c != '.' && c != '_')
+                    .any(|token| Some(token) == uid || token == package)
+            })
+            || self.tag.contains(package)
+    }
+}
+
+// dumpsys 中 TimeUtils.formatDuration 的格式，如 "-1h2m3s456ms"、"+5s"，转为毫秒（不含符号）
+pub fn parse_dumpsys_duration(text: &str) -> Option<i64> {
+    let text = text.trim_start_matches(['-', '+']);
+    let mut total = 0i64;
+    let mut number = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let value: i64 = number.parse().ok()?;
+        number.clear();
+        total += match c {
+            'd' => value * 86_400_000,
+            'h' => value * 3_600_000,
+            'm' if chars.peek() == Some(&'s') => {
+                chars.next();
+                value
+            }
+            'm' => value * 60_000,
+            's' => value * 1000,
+            _ => return None,
+        };
+    }
+    number.is_empty().then_some(total)
+}
+
+// 形如 "  PARTIAL_WAKE_LOCK  'tag' ACQ=-2m3s66ms (uid=10123 pid=4567 ws=WorkSource{10123})"，
+// 旧版本为 "(uid=1000, pid=123, ws=null)"，且可能没有 ACQ
+fn parse_wakelock_line(line: &str) -> Option<HeldWakelock> {
+    let level = line.split_whitespace().next()?.to_string();
+    let start = line.find('\'')?;
+    let end = line.rfind('\'')?;
+    if end <= start {
+        return None;
+    }
+    let tag = line[start + 1..end].to_string();
+    let details = &line[end + 1..];
+
+    let mut wakelock = HeldWakelock {
+        level,
+        tag,
+        acquired_ago_ms: None,
+        uid: None,
+        pid: None,
+        work_source: None,
+    };
+    for field in details.split(|c: char| c.is_whitespace() || c == ',' || c == '(' || c == ')') {
+        if let Some(value) = field.strip_prefix("ACQ=") {
+            wakelock.acquired_ago_ms = parse_dumpsys_duration(value);
+        } else if let Some(value) = field.strip_prefix("uid=") {
+            wakelock.uid = Some(value.to_string());
+        } else if let Some(value) = field.strip_prefix("pid=") {
+            wakelock.pid = Some(value.to_string());
+        }
+    }
+    // WorkSource{...} 中可能含空格，单独截取
+    if let Some(ws) = details.find("ws=") {
+        let ws = &details[ws + 3..];
+        let ws = match ws.find('}') {
+            Some(close) => &ws[..=close],
+            None => ws.split_whitespace().next().unwrap_or_default(),
+        };
+        if ws != "null" {
+            wakelock.work_source = Some(ws.trim_end_matches([',', ')']).to_string());
+        }
+    }
+    Some(wakelock)
+}
+
+// 解析 dumpsys power 中当前持有的所有 wakelock
+pub fn parse_wake_locks(output: &str) -> Vec<HeldWakelock> {
+    let mut wakelocks = Vec::new();
+    let mut in_section = false;
+    for line in output.lines() {
+        if line.trim_start().starts_with("Wake Locks:") {
+            in_section = true;
+            continue;
+        }
+        if !in_section {
+            continue;
+        }
+        // 段内各行有缩进，遇到空行或下一个段落结束
+        if line.trim().is_empty() || !line.starts_with(' ') {
+            break;
+        }
+        if let Some(wakelock) = parse_wakelock_line(line.trim()) {
+            wakelocks.push(wakelock);
+        }
+    }
+    wakelocks
+}
+
+pub fn sample_wakelocks(package: &str, uid: &str, pid: &str) -> Result<Vec<HeldWakelock>> {
+    let output = utils::run_adb_command(&["shell", "dumpsys", "power"])?;
+    if !output.contains("Wake Locks:") {
+        anyhow::bail!("No 'Wake Locks' section in dumpsys power");
+    }
+    Ok(parse_wake_locks(&output)
+        .into_iter()
+        .filter(|w| w.belongs_to(package, uid, pid))
+        .collect())
+}
+
+// 一次持有：获取时间来自 ACQ，释放时间为首次发现已释放的采样时间（上界）
+#[derive(Debug, Clone)]
+pub struct WakelockInterval {
+    pub tag: String,
+    pub level: String,
+    pub acquired: DateTime<Local>,
+    pub released: DateTime<Local>,
+    pub held_at_end: bool,
+}
+
+impl WakelockInterval {
+    pub fn duration_secs(&self) -> f64 {
+        (self.released - self.acquired).num_milliseconds() as f64 / 1000.0
+    }
+}
+
+// 每次采样时该应用持有的 wakelock 数和累计获取次数、持有时长
+#[derive(Debug, Clone, Copy)]
+pub struct WakelockSample {
+    pub timestamp: DateTime<Local>,
+    pub held: usize,
+    pub acquires: u32,
+    pub hold_secs: f64,
+}
+
+// 同一标签的汇总
+#[derive(Debug, Clone, Default)]
+pub struct WakelockStats {
+    pub tag: String,
+    pub level: String,
+    pub acquires: u32,
+    pub total_secs: f64,
+    pub longest_secs: f64,
+}
+
+// 跟踪 wakelock 的获取与释放。两次采样之间的短暂持有无法观察到；
+// ACQ 时间明显晚于已记录的获取时间时，视为已释放后重新获取
+#[derive(Debug, Default)]
+pub struct WakelockTracker {
+    start: Option<DateTime<Local>>,
+    held: HashMap<(String, String), DateTime<Local>>,
+    pub intervals: Vec<WakelockInterval>,
+    pub samples: Vec<WakelockSample>,
+    acquires: u32,
+}
+
+impl WakelockTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    fn close(
+        &mut self,
+        key: (String, String),
+        acquired: DateTime<Local>,
+        released: DateTime<Local>,
+    ) {
+        self.intervals.push(WakelockInterval {
+            level: key.0,
+            tag: key.1,
+            acquired,
+            released,
+            held_at_end: false,
+        });
+    }
+
+    // 返回本次新获取的 wakelock 标签
+    pub fn record(&mut self, timestamp: DateTime<Local>, held: &[HeldWakelock]) -> Vec<String> {
+        // 开始监控之前获取的从开始监控时算起
+        let start = *self.start.get_or_insert(timestamp);
+        let mut acquired_now = Vec::new();
+        let mut current = HashMap::new();
+        for wakelock in held {
+            let key = (wakelock.level.clone(), wakelock.tag.clone());
+            // 同一标签同时持有多个时按一个计
+            if current.contains_key(&key) {
+                continue;
+            }
+            let acquired = wakelock
+                .acquired_ago_ms
+                .map(|ms| timestamp - chrono::Duration::milliseconds(ms))
+                .unwrap_or(timestamp)
+                .max(start);
+            let acquired = match self.held.remove(&key) {
+                Some(previous)
+                    if acquired > previous + chrono::Duration::seconds(1)
+                        && wakelock.acquired_ago_ms.is_some() =>
+                {
+                    self.close(key.clone(), previous, acquired);
+                    self.acquires += 1;
+                    acquired_now.push(wakelock.tag.clone());
+                    acquired
+                }
+                Some(previous) => previous,
+                None => {
+                    self.acquires += 1;
+                    acquired_now.push(wakelock.tag.clone());
+                    acquired
+                }
+            };
+            current.insert(key, acquired);
+        }
+        for (key, acquired) in std::mem::take(&mut self.held) {
+            self.close(key, acquired, timestamp);
+        }
+        self.held = current;
+
+        let hold_secs = self
+            .intervals
+            .iter()
+            .map(WakelockInterval::duration_secs)
+            .sum::<f64>()
+            + self
+                .held
+                .values()
+                .map(|acquired| (timestamp - *acquired).num_milliseconds() as f64 / 1000.0)
+                .sum::<f64>();
+        self.samples.push(WakelockSample {
+            timestamp,
+            held: self.held.len(),
+            acquires: self.acquires,
+            hold_secs,
+        });
+        acquired_now
+    }
+
+    // 结束监控时仍持有的记为持有到最后一次采样
+    pub fn finish(&mut self) {
+        let Some(end) = self.samples.last().map(|s| s.timestamp) else {
+            return;
+        };
+        for ((level, tag), acquired) in std::mem::take(&mut self.held) {
+            self.intervals.push(WakelockInterval {
+                tag,
+                level,
+                acquired,
+                released: end,
+                held_at_end: true,
+            });
+        }
+        self.intervals.sort_by_key(|i| i.acquired);
+    }
+}
+
+// 按标签汇总，总持有时长降序
+pub fn tag_stats(intervals: &[WakelockInterval]) -> Vec<WakelockStats> {
+    let mut stats: HashMap<(&str, &str), WakelockStats> = HashMap::new();
+    for interval in intervals {
+        let entry = stats
+            .entry((&interval.level, &interval.tag))
+            .or_insert_with(|| WakelockStats {
+                tag: interval.tag.clone(),
+                level: interval.level.clone(),
+                ..Default::default()
+            });
+        let secs = interval.duration_secs();
+        entry.acquires += 1;
+        entry.total_secs += secs;
+        entry.longest_secs = entry.longest_secs.max(secs);
+    }
+    let mut stats: Vec<WakelockStats> = stats.into_values().collect();
+    stats.sort_by(|a, b| {
+        b.total_secs
+            .partial_cmp(&a.total_secs)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    stats
+}
+
+// 每个标签一行的时间线图
+pub fn draw_timeline(path: &Path, title: &str, intervals: &[WakelockInterval]) -> Result<()> {
+    let lanes: Vec<TimelineLane> = tag_stats(intervals)
+        .into_iter()
+        .take(MAX_TIMELINE_LANES)
+        .map(|stats| {
+            TimelineLane::new(
+                &stats.tag,
+                intervals
+                    .iter()
+                    .filter(|i| i.tag == stats.tag && i.level == stats.level)
+                    .map(|i| (i.acquired, i.released))
+                    .collect(),
+            )
+        })
+        .collect();
+    chart::draw_timeline_chart(path, title, &lanes)
+}
+
+pub fn write_intervals_csv(path: &Path, intervals: &[WakelockInterval]) -> Result<()> {
+    let mut file = std::fs::File::create(path)?;
+    writeln!(file, "Tag,Level,Acquired,Released,Duration (s),Held At End")?;
+    for interval in intervals {
+        writeln!(
+            file,
+            "{},{},{},{},{:.1},{}",
+            interval.tag.replace(',', ";"),
+            interval.level,
+            interval.acquired.format(CSV_TIMESTAMP_FORMAT),
+            interval.released.format(CSV_TIMESTAMP_FORMAT),
+            interval.duration_secs(),
+            interval.held_at_end
+        )?;
+    }
+    Ok(())
+}
+
+// 读取 write_intervals_csv 写出的文件，用于重新生成报告
+pub fn read_intervals_csv(path: &Path) -> Result<Vec<WakelockInterval>> {
+    let table = regenerate::read_csv(path)?;
+    Ok(table
+        .rows
+        .iter()
+        .filter_map(|row| {
+            Some(WakelockInterval {
+                tag: row.first()?.clone(),
+                level: row.get(1)?.clone(),
+                acquired: regenerate::parse_timestamp(row.get(2)?)?,
+                released: regenerate::parse_timestamp(row.get(3)?)?,
+                held_at_end: row.get(5).is_some_and(|v| v == "true"),
+            })
+        })
+        .collect())
+}
+
+pub fn is_intervals_csv(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().ends_with("_wakelock_intervals.csv"))
+}
+
+// 导出每次采样的持有数 CSV、每次持有的区间 CSV 和时间线图
+pub fn export_wakelock_data(
+    dir: &Path,
+    package: &str,
+    tracker: &WakelockTracker,
+) -> Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir)?;
+    let mut paths = Vec::new();
+
+    let csv_path = dir.join(format!("{}_wakelock_data.csv", package));
+    let mut file = std::fs::File::create(&csv_path)?;
+    writeln!(file, "Timestamp,Held Wakelocks,Acquires,Hold Time (s)")?;
+    for sample in &tracker.samples {
+        writeln!(
+            file,
+            "{},{},{},{:.1}",
+            sample.timestamp.format(CSV_TIMESTAMP_FORMAT),
+            sample.held,
+            sample.acquires,
+            sample.hold_secs
+        )?;
+    }
+    paths.push(csv_path);
+
+    let intervals_path = dir.join(format!("{}_wakelock_intervals.csv", package));
+    write_intervals_csv(&intervals_path, &tracker.intervals)?;
+    paths.push(intervals_path);
+
+    if !tracker.intervals.is_empty() {
+        let path = dir.join(chart::stamped_file_name(&format!(
+            "{}_wakelock_timeline.png",
+            package
+        )));
+        draw_timeline(
+            &path,
+            &format!("Wakelock Timeline - {}", package),
+            &tracker.intervals,
+        )?;
+        paths.push(path);
+    }
+
+    Ok(paths)
+}
+
+// 应用持有的 wakelock，按 UID、PID、WorkSource 或标签中的包名归属
+pub struct WakelockCollector {
+    uid: String,
+    tracker: WakelockTracker,
+}
+
+impl WakelockCollector {
+    pub fn new(uid: &str) -> Self {
+        Self {
+            uid: uid.to_string(),
+            tracker: WakelockTracker::new(),
+        }
+    }
+}
+
+impl Collector for WakelockCollector {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        let tick = ctx.tick;
+        match sample_wakelocks(ctx.package, &self.uid, &ctx.sample.pid) {
+            Ok(held) => {
+                for tag in self.tracker.record(tick.timestamp, &held) {
+                    console!(
+                        "[{}] {} {}",
+                        tick.timestamp.format("%H:%M:%S").to_string().blue(),
+                        "Wakelock acquired:".yellow(),
+                        tag
+                    );
+                }
+                ctx.table.record(&tick, "Held Wakelocks", held.len() as f64);
+            }
+            Err(e) => {
+                if !utils::is_being_interrupted() {
+                    console!("Failed to sample wakelocks: {}", e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, ctx: &FinishContext) {
+        if self.tracker.is_empty() {
+            return;
+        }
+        self.tracker.finish();
+        let stats = tag_stats(&self.tracker.intervals);
+        console!(
+            "Wakelocks: {} acquires, {:.1} s held in total",
+            stats.iter().map(|s| s.acquires).sum::<u32>(),
+            stats.iter().map(|s| s.total_secs).sum::<f64>()
+        );
+        for stats in stats.iter().take(5) {
+            console!(
+                "  {} ({}): {} acquires, total {} s, longest {:.1} s",
+                stats.tag.cyan(),
+                stats.level,
+                stats.acquires,
+                format!("{:.1}", stats.total_secs).red(),
+                stats.longest_secs
+            );
+        }
+        match export_wakelock_data(
+            &ctx.layout.dir(Category::Wakelocks),
+            ctx.package,
+            &self.tracker,
+        ) {
+            Ok(paths) => {
+                for path in paths {
+                    console!("✓ Wakelock data exported: {}", path.display());
+                }
+            }
+            Err(e) => console!("Failed to export wakelock data: {}", e),
+        }
+    }
+}
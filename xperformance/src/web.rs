@@ -0,0 +1,147 @@
+use crate::recorder::{Collector, TickContext};
+use crate::tick::Tick;
+use crate::utils;
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+// 新连接的页面先收到已有的历史，超过上限时丢弃最早的
+const HISTORY_LEN: usize = 20000;
+// 长时间无数据时发送注释行，避免代理断开连接
+const KEEPALIVE: Duration = Duration::from_secs(15);
+
+const PAGE: &str = include_str!("web_dashboard.html");
+
+// 一个 Tick 的所有指标，作为一条 SSE 消息
+#[derive(Debug, Serialize)]
+struct LiveSample<'a> {
+    package: &'a str,
+    device: Option<String>,
+    tick: u64,
+    timestamp: String,
+    values: BTreeMap<&'a str, f64>,
+}
+
+struct Hub {
+    history: VecDeque<String>,
+    sender: broadcast::Sender<String>,
+}
+
+// None 表示未启动服务
+static HUB: Mutex<Option<Hub>> = Mutex::new(None);
+
+// 绑定端口并在后台提供页面（/）和实时数据流（/events），进程结束前一直运行
+pub async fn serve(port: u16) -> Result<()> {
+    let listener = TcpListener::bind(("0.0.0.0", port))
+        .await
+        .with_context(|| format!("Failed to listen on port {}", port))?;
+    HUB.lock().unwrap().get_or_insert_with(|| Hub {
+        history: VecDeque::new(),
+        sender: broadcast::channel(1024).0,
+    });
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(handle(stream));
+        }
+    });
+    Ok(())
+}
+
+async fn handle(mut stream: TcpStream) {
+    let mut buf = [0u8; 1024];
+    let Ok(n) = stream.read(&mut buf).await else {
+        return;
+    };
+    // 只看请求行 "GET /events HTTP/1.1"
+    let request = String::from_utf8_lossy(&buf[..n]);
+    let path = request.split_whitespace().nth(1).unwrap_or_default();
+    let response = match path {
+        "/" | "/index.html" => format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            PAGE.len(),
+            PAGE
+        ),
+        "/events" => {
+            let _ = stream_events(stream).await;
+            return;
+        }
+        _ => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+            .to_string(),
+    };
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+// 先发送历史再转发新数据，页面关闭（写入失败）时结束
+async fn stream_events(mut stream: TcpStream) -> Result<()> {
+    // 在同一次加锁中取历史并订阅，保证不漏也不重复
+    let (history, mut receiver) = {
+        let hub = HUB.lock().unwrap();
+        let hub = hub.as_ref().context("Web dashboard not started")?;
+        (hub.history.clone(), hub.sender.subscribe())
+    };
+    stream
+        .write_all(
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\n\r\n",
+        )
+        .await?;
+    for message in history {
+        stream
+            .write_all(format!("data: {}\n\n", message).as_bytes())
+            .await?;
+    }
+    loop {
+        match tokio::time::timeout(KEEPALIVE, receiver.recv()).await {
+            Ok(Ok(message)) => {
+                stream
+                    .write_all(format!("data: {}\n\n", message).as_bytes())
+                    .await?
+            }
+            // 页面处理太慢时跳过积压的数据
+            Ok(Err(RecvError::Lagged(_))) => continue,
+            Ok(Err(RecvError::Closed)) => return Ok(()),
+            Err(_) => stream.write_all(b": keepalive\n\n").await?,
+        }
+    }
+}
+
+// 每个 Tick 结束时推送该 Tick 的全部指标。未启动服务时不做任何事
+pub fn publish(package: &str, tick: &Tick, values: &[(&str, f64)]) {
+    let Ok(mut hub) = HUB.lock() else {
+        return;
+    };
+    let Some(hub) = hub.as_mut() else {
+        return;
+    };
+    let sample = LiveSample {
+        package,
+        device: utils::adb_serial(),
+        tick: tick.id,
+        timestamp: tick.timestamp.to_rfc3339(),
+        values: values.iter().copied().collect(),
+    };
+    let Ok(message) = serde_json::to_string(&sample) else {
+        return;
+    };
+    if hub.history.len() == HISTORY_LEN {
+        hub.history.pop_front();
+    }
+    hub.history.push_back(message.clone());
+    // 没有页面连接时发送失败，忽略
+    let _ = hub.sender.send(message);
+}
+
+// 每个 Tick 把合并宽表中本 Tick 的整行发给网页仪表盘
+pub struct WebPublisher;
+
+impl Collector for WebPublisher {
+    fn sample(&mut self, ctx: &mut TickContext) -> Result<()> {
+        publish(ctx.package, &ctx.tick, &ctx.table.row(ctx.tick.id));
+        Ok(())
+    }
+}
@@ -0,0 +1,56 @@
+#![deny(warnings)]
+use anyhow::Result;
+use clap::Parser;
+use colored::*;
+use std::path::PathBuf;
+use xperformance::regenerate::{self, TimeWindow};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Session directory (e.g. log/<package>/<timestamp>) containing CSV exports
+    session_dir: PathBuf,
+
+    /// Output directory for regenerated reports (default: <session_dir>/report)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Only use samples at or after this time (HH:MM[:SS] on the session's first day, or YYYY-MM-DD HH:MM[:SS])
+    #[arg(long)]
+    from: Option<String>,
+
+    /// Only use samples up to this time, including the whole minute for HH:MM (HH:MM[:SS] or YYYY-MM-DD HH:MM[:SS])
+    #[arg(long)]
+    to: Option<String>,
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    println!("{}", "XReport".green().bold());
+    println!("Session: {}", args.session_dir.display().to_string().cyan());
+
+    if !args.session_dir.is_dir() {
+        anyhow::bail!(
+            "Session directory not found: {}",
+            args.session_dir.display()
+        );
+    }
+
+    let window = TimeWindow::parse(args.from.as_deref(), args.to.as_deref())?;
+
+    let output_dir = args
+        .output
+        .clone()
+        .unwrap_or_else(|| regenerate::default_output_dir(&args.session_dir, &window));
+    if !window.is_full() {
+        println!("Time window: {}", window.label().yellow());
+    }
+
+    let generated = regenerate::regenerate_session(&args.session_dir, &output_dir, &window)?;
+    for path in generated {
+        println!("✓ Generated: {}", path.display());
+    }
+
+    Ok(())
+}
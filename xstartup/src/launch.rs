@@ -0,0 +1,249 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use regex::Regex;
+use xperformance::utils;
+
+// 启动类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, ValueEnum)]
+pub enum LaunchMode {
+    /// 进程不存在，每次启动前 force-stop
+    #[default]
+    Cold,
+    /// 进程存活但 Activity 已销毁（启动前按返回键）
+    Warm,
+    /// 进程和 Activity 都存活（启动前按 Home 键）
+    Hot,
+}
+
+impl LaunchMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LaunchMode::Cold => "cold",
+            LaunchMode::Warm => "warm",
+            LaunchMode::Hot => "hot",
+        }
+    }
+
+    // am start -W 输出的 LaunchState（Android 10 起），用于无法读取启动前状态时
+    pub fn from_launch_state(state: &str) -> Option<Self> {
+        match state {
+            "cold" => Some(LaunchMode::Cold),
+            "warm" => Some(LaunchMode::Warm),
+            "hot" => Some(LaunchMode::Hot),
+            _ => None,
+        }
+    }
+}
+
+// 单次启动的测量结果，时间单位均为毫秒
+#[derive(Debug, Clone, Default)]
+pub struct LaunchResult {
+    pub index: usize,
+    pub launch_state: String,
+    // 按启动前进程和 Activity 状态判定的实际启动类型
+    pub category: LaunchMode,
+    pub this_time: Option<u64>,
+    pub total_time: Option<u64>,
+    pub wait_time: Option<u64>,
+    pub displayed: Option<u64>,
+    pub fully_drawn: Option<u64>,
+}
+
+// 通过包管理器解析启动 Activity
+pub fn resolve_launch_activity(package: &str) -> Result<String> {
+    let output = utils::run_adb_command(&[
+        "shell",
+        "cmd",
+        "package",
+        "resolve-activity",
+        "--brief",
+        package,
+    ])?;
+
+    output
+        .lines()
+        .map(|line| line.trim())
+        .rfind(|line| line.starts_with(package) && line.contains('/'))
+        .map(|line| line.to_string())
+        .ok_or_else(|| anyhow::format_err!("Could not resolve launch activity for {}", package))
+}
+
+// 按启动类型在每次测量前准备设备状态
+pub fn prepare_launch(package: &str, mode: LaunchMode) -> Result<()> {
+    match mode {
+        LaunchMode::Cold => {
+            utils::run_adb_command(&["shell", "am", "force-stop", package])?;
+        }
+        LaunchMode::Warm => {
+            utils::run_adb_command(&["shell", "input", "keyevent", "KEYCODE_BACK"])?;
+        }
+        LaunchMode::Hot => {
+            utils::run_adb_command(&["shell", "input", "keyevent", "KEYCODE_HOME"])?;
+        }
+    }
+    Ok(())
+}
+
+// 第一行为 pidof 的输出，第二行为任务栈中目标应用的 Activity 数量（"* Hist #0: ActivityRecord{...}" 行，
+// 不含 mLastPausedActivity 等已销毁 Activity 的引用）
+fn prelaunch_command(package: &str) -> String {
+    format!(
+        "pidof {pkg}; dumpsys activity activities | grep 'Hist.*ActivityRecord{{' | grep -c ' {pkg}/'; true",
+        pkg = package
+    )
+}
+
+// 进程不存在为冷启动；进程存活但没有 Activity 实例为温启动；Activity 仍在任务栈中为热启动
+pub fn parse_prelaunch_state(output: &str) -> Option<LaunchMode> {
+    let mut lines = output.lines().map(str::trim);
+    let first = lines.next()?;
+    // 进程不存在时 pidof 没有输出，第一行即为 Activity 数量
+    let (pid, records) = match lines.next() {
+        Some(records) => (first, records),
+        None => ("", first),
+    };
+    let records: u32 = records.parse().ok()?;
+    let alive = pid.split_whitespace().any(|p| p.parse::<u32>().is_ok());
+    Some(match (alive, records > 0) {
+        (false, _) => LaunchMode::Cold,
+        (true, false) => LaunchMode::Warm,
+        (true, true) => LaunchMode::Hot,
+    })
+}
+
+// 在 prepare_launch 之后、启动之前读取进程和 Activity 状态
+pub fn detect_launch_mode(package: &str) -> Result<LaunchMode> {
+    let output = utils::run_adb_command(&["shell", &prelaunch_command(package)])?;
+    parse_prelaunch_state(&output)
+        .ok_or_else(|| anyhow::format_err!("Unexpected pre-launch state: {}", output.trim()))
+}
+
+// 执行 am start -W 并解析其输出
+pub fn start_activity(component: &str, index: usize) -> Result<LaunchResult> {
+    let output = utils::run_adb_command(&["shell", "am", "start", "-W", "-n", component])?;
+
+    if output.contains("Error:") {
+        anyhow::bail!("am start failed: {}", output.trim());
+    }
+
+    let mut result = LaunchResult {
+        index,
+        ..Default::default()
+    };
+
+    for line in output.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "LaunchState" => result.launch_state = value.to_lowercase(),
+            "ThisTime" => result.this_time = value.parse().ok(),
+            "TotalTime" => result.total_time = value.parse().ok(),
+            "WaitTime" => result.wait_time = value.parse().ok(),
+            _ => {}
+        }
+    }
+
+    Ok(result)
+}
+
+// 从 logcat 中提取 Displayed 与 Fully drawn 时间
+pub fn collect_logcat_timings(component: &str, result: &mut LaunchResult) -> Result<()> {
+    let output = utils::run_adb_command(&[
+        "logcat",
+        "-d",
+        "-s",
+        "ActivityTaskManager:I",
+        "ActivityManager:I",
+    ])?;
+
+    let displayed = Regex::new(r"Displayed (\S+): \+(\S+?)(\s|$)")?;
+    let fully_drawn = Regex::new(r"Fully drawn (\S+): \+(\S+?)(\s|$)")?;
+
+    for line in output.lines() {
+        if let Some(caps) = displayed.captures(line) {
+            if same_component(&caps[1], component) {
+                result.displayed = parse_logcat_duration(&caps[2]);
+            }
+        } else if let Some(caps) = fully_drawn.captures(line) {
+            if same_component(&caps[1], component) {
+                result.fully_drawn = parse_logcat_duration(&caps[2]);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// logcat 中的组件名可能是完整类名，也可能是 .MainActivity 缩写
+fn same_component(logged: &str, component: &str) -> bool {
+    let expand = |c: &str| -> String {
+        match c.split_once('/') {
+            Some((pkg, cls)) if cls.starts_with('.') => format!("{}/{}{}", pkg, pkg, cls),
+            _ => c.to_string(),
+        }
+    };
+    expand(logged) == expand(component)
+}
+
+// 解析 "+1s234ms" / "+512ms" 格式的时长
+pub fn parse_logcat_duration(text: &str) -> Option<u64> {
+    let text = text.trim_start_matches('+');
+    let mut total = 0u64;
+    let mut number = String::new();
+    let mut chars = text.chars().peekable();
+    let mut matched = false;
+
+    while let Some(c) = chars.next() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let value: u64 = number.parse().ok()?;
+        number.clear();
+        match c {
+            'm' if chars.peek() == Some(&'s') => {
+                chars.next();
+                total += value;
+            }
+            's' => total += value * 1000,
+            _ => return None,
+        }
+        matched = true;
+    }
+
+    if matched && number.is_empty() {
+        Some(total)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_prelaunch_state_modes() {
+        // pidof 输出 + 任务栈中的 Activity 数量
+        assert_eq!(parse_prelaunch_state("12345\n2\n"), Some(LaunchMode::Hot));
+        assert_eq!(parse_prelaunch_state("12345\n0\n"), Some(LaunchMode::Warm));
+        // 进程不存在时 pidof 没有输出
+        assert_eq!(parse_prelaunch_state("0\n"), Some(LaunchMode::Cold));
+        // 多进程应用 pidof 返回多个 pid
+        assert_eq!(
+            parse_prelaunch_state("12345 12399\n1\n"),
+            Some(LaunchMode::Hot)
+        );
+    }
+
+    #[test]
+    fn parse_prelaunch_state_rejects_unexpected_output() {
+        assert_eq!(parse_prelaunch_state(""), None);
+        assert_eq!(
+            parse_prelaunch_state("/system/bin/sh: pidof: inaccessible or not found\n"),
+            None
+        );
+    }
+}
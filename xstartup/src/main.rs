@@ -0,0 +1,318 @@
+#![deny(warnings)]
+use anyhow::Result;
+use clap::Parser;
+use colored::*;
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::time::{sleep, Duration};
+use xperformance::chart::{self, LineSeriesData};
+use xperformance::layout::{Category, OutputLayout};
+use xperformance::session::SessionInfo;
+use xperformance::stats::Summary;
+use xperformance::utils;
+
+mod launch;
+
+use launch::{LaunchMode, LaunchResult};
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    /// Package name to launch
+    #[arg(short, long)]
+    package: String,
+
+    /// Activity to launch (default: resolved launcher activity)
+    #[arg(short, long)]
+    activity: Option<String>,
+
+    /// Number of measured launches
+    #[arg(short = 'n', long, default_value_t = 10)]
+    count: usize,
+
+    /// Launch type to measure
+    #[arg(long, value_enum, default_value_t = LaunchMode::Cold)]
+    mode: LaunchMode,
+
+    /// Seconds to wait after each launch for reportFullyDrawn (default: 3)
+    #[arg(long, default_value_t = 3)]
+    settle: u64,
+}
+
+// 每个启动指标的名称及取值方式
+type MetricGetter = fn(&LaunchResult) -> Option<u64>;
+
+const METRICS: [(&str, MetricGetter); 4] = [
+    ("TotalTime", |r| r.total_time),
+    ("WaitTime", |r| r.wait_time),
+    ("Displayed", |r| r.displayed),
+    ("FullyDrawn", |r| r.fully_drawn),
+];
+
+fn format_ms(value: Option<u64>) -> String {
+    value.map_or("-".to_string(), |v| format!("{} ms", v))
+}
+
+async fn run_launches(args: &Args, running: &AtomicBool) -> Result<Vec<LaunchResult>> {
+    let component = match &args.activity {
+        Some(activity) if activity.contains('/') => activity.clone(),
+        Some(activity) => format!("{}/{}", args.package, activity),
+        None => launch::resolve_launch_activity(&args.package)?,
+    };
+    println!("Launch component: {}", component.cyan());
+
+    // 温启动和热启动需要进程预先存活
+    if args.mode != LaunchMode::Cold {
+        println!("Priming {} launch...", args.mode.as_str());
+        launch::start_activity(&component, 0)?;
+        sleep(Duration::from_secs(args.settle)).await;
+    }
+
+    let mut results = Vec::new();
+    for index in 1..=args.count {
+        if !running.load(Ordering::SeqCst) {
+            break;
+        }
+
+        launch::prepare_launch(&args.package, args.mode)?;
+        sleep(Duration::from_secs(1)).await;
+        // 返回键可能让系统回收进程，Home 键后 Activity 也可能被销毁，以实际状态归类
+        let detected = launch::detect_launch_mode(&args.package);
+
+        utils::run_adb_command(&["logcat", "-c"])?;
+        let mut result = launch::start_activity(&component, index)?;
+        if result.launch_state.is_empty() {
+            result.launch_state = args.mode.as_str().to_string();
+        }
+        result.category = match detected {
+            Ok(mode) => mode,
+            Err(e) => {
+                eprintln!("Failed to read pre-launch state: {}", e);
+                LaunchMode::from_launch_state(&result.launch_state).unwrap_or(args.mode)
+            }
+        };
+
+        sleep(Duration::from_secs(args.settle)).await;
+        if let Err(e) = launch::collect_logcat_timings(&component, &mut result) {
+            eprintln!("Failed to read launch timings from logcat: {}", e);
+        }
+
+        if result.category != args.mode {
+            println!(
+                "{}",
+                format!(
+                    "Launch {} was {} instead of {}, reported separately",
+                    index,
+                    result.category.as_str(),
+                    args.mode.as_str()
+                )
+                .yellow()
+            );
+        }
+        println!(
+            "[{}/{}] {} TotalTime: {}, WaitTime: {}, Displayed: {}, FullyDrawn: {}",
+            index,
+            args.count,
+            result.category.as_str().yellow(),
+            format_ms(result.total_time).blue(),
+            format_ms(result.wait_time),
+            format_ms(result.displayed).green(),
+            format_ms(result.fully_drawn).magenta()
+        );
+        results.push(result);
+    }
+
+    Ok(results)
+}
+
+fn export_results_to_csv(path: &Path, results: &[LaunchResult]) -> Result<()> {
+    let mut file = std::fs::File::create(path)?;
+    writeln!(
+        file,
+        "Launch,Category,LaunchState,ThisTime,TotalTime,WaitTime,Displayed,FullyDrawn"
+    )?;
+
+    let cell = |v: Option<u64>| v.map(|v| v.to_string()).unwrap_or_default();
+    for r in results {
+        writeln!(
+            file,
+            "{},{},{},{},{},{},{},{}",
+            r.index,
+            r.category.as_str(),
+            r.launch_state,
+            cell(r.this_time),
+            cell(r.total_time),
+            cell(r.wait_time),
+            cell(r.displayed),
+            cell(r.fully_drawn)
+        )?;
+    }
+
+    file.flush()?;
+    Ok(())
+}
+
+fn write_report(args: &Args, results: &[LaunchResult]) -> Result<()> {
+    let startup_dir = OutputLayout::session(&args.package)?.dir(Category::Startup);
+    std::fs::create_dir_all(&startup_dir)?;
+
+    let csv_path = startup_dir.join(format!("{}_startup_data.csv", args.package));
+    export_results_to_csv(&csv_path, results)?;
+    println!("✓ Startup data exported to CSV: {}", csv_path.display());
+
+    // 冷、温、热启动耗时相差一个数量级，按实际启动类型分别统计
+    let mut categories: Vec<LaunchMode> = results.iter().map(|r| r.category).collect();
+    categories.sort();
+    categories.dedup();
+    let mixed = categories.len() > 1;
+
+    let summary_path = startup_dir.join(format!("{}_startup_summary.csv", args.package));
+    let mut summary_file = std::fs::File::create(&summary_path)?;
+    writeln!(summary_file, "Category,Metric,{}", Summary::CSV_HEADER)?;
+
+    let mut series = Vec::new();
+    // 每种启动类型第一个可用指标（通常为 TotalTime）的取值，用于直方图
+    let mut primaries = Vec::new();
+    for category in &categories {
+        let launches: Vec<&LaunchResult> =
+            results.iter().filter(|r| r.category == *category).collect();
+        println!(
+            "\n{} ({} launches)",
+            format!("{} start", category.as_str()).green().bold(),
+            launches.len()
+        );
+        println!(
+            "{:<12} {:>6} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8}",
+            "Metric", "Count", "Min", "Mean", "Median", "P90", "P95", "Max", "StdDev"
+        );
+        for (name, getter) in METRICS {
+            let points: Vec<(f64, f64)> = launches
+                .iter()
+                .filter_map(|r| getter(r).map(|v| (r.index as f64, v as f64)))
+                .collect();
+            let values: Vec<f64> = points.iter().map(|&(_, v)| v).collect();
+            let Some(summary) = Summary::from_values(&values) else {
+                continue;
+            };
+
+            println!(
+                "{:<12} {:>6} {:>8.0} {:>8.1} {:>8.1} {:>8.1} {:>8.1} {:>8.0} {:>8.1}",
+                name.cyan(),
+                summary.count,
+                summary.min,
+                summary.mean,
+                summary.median,
+                summary.p90,
+                summary.p95,
+                summary.max,
+                summary.stddev
+            );
+            writeln!(
+                summary_file,
+                "{},{},{}",
+                category.as_str(),
+                name,
+                summary.to_csv_row()
+            )?;
+            if !primaries.iter().any(|(c, _, _)| c == category) {
+                primaries.push((*category, name, values));
+            }
+            let label = if mixed {
+                format!("{} ({})", name, category.as_str())
+            } else {
+                name.to_string()
+            };
+            series.push(LineSeriesData::new(label, points));
+        }
+    }
+    println!();
+    println!(
+        "✓ Startup summary exported to CSV: {}",
+        summary_path.display()
+    );
+
+    if series.is_empty() {
+        println!("No startup timings collected, skipping charts");
+        return Ok(());
+    }
+
+    let chart_path = startup_dir.join(format!("{}_startup_chart.png", args.package));
+    let modes: Vec<&str> = categories.iter().map(LaunchMode::as_str).collect();
+    let title = format!(
+        "{} Startup Time - {} ({} launches)",
+        modes.join("/"),
+        args.package,
+        results.len()
+    );
+    chart::draw_line_chart(&chart_path, &title, "Launch", "Time (ms)", &series)?;
+    println!("✓ Startup chart generated: {}", chart_path.display());
+
+    // 每种启动类型各一张直方图
+    for (category, name, values) in primaries {
+        let file_name = if mixed {
+            format!(
+                "{}_startup_{}_histogram.png",
+                args.package,
+                category.as_str()
+            )
+        } else {
+            format!("{}_startup_histogram.png", args.package)
+        };
+        let histogram_path = startup_dir.join(file_name);
+        chart::draw_histogram(
+            &histogram_path,
+            &format!(
+                "{} {} Distribution - {}",
+                category.as_str(),
+                name,
+                args.package
+            ),
+            &format!("{} (ms)", name),
+            &values,
+            10,
+        )?;
+        println!(
+            "✓ Startup histogram generated: {}",
+            histogram_path.display()
+        );
+    }
+
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = Args::parse();
+
+    println!("{}", "XStartup Benchmark".green().bold());
+    println!("Package: {}", args.package.cyan());
+    println!("Mode: {}, launches: {}", args.mode.as_str(), args.count);
+
+    if !utils::check_adb_connection() {
+        anyhow::bail!("No Android devices connected");
+    }
+
+    // 图表标题附带设备和构建信息
+    chart::set_stamp(
+        &SessionInfo::capture(None, &args.package).stamp_label(),
+        false,
+    );
+
+    let running = Arc::new(AtomicBool::new(true));
+    let r = running.clone();
+    ctrlc::set_handler(move || {
+        r.store(false, Ordering::SeqCst);
+        utils::set_interrupt_flag();
+        println!("\n程序正在退出...");
+    })?;
+
+    let results = run_launches(&args, &running).await?;
+    if results.is_empty() {
+        println!("No launches completed");
+        return Ok(());
+    }
+
+    write_report(&args, &results)
+}
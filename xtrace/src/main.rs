@@ -0,0 +1,174 @@
+#![deny(warnings)]
+use anyhow::Result;
+use clap::{Parser, Subcommand};
+use colored::*;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use xperformance::layout::{Category, OutputLayout};
+use xperformance::trace::{self, ActiveTrace, TraceOptions};
+use xperformance::utils;
+
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+struct Args {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(clap::Args, Debug)]
+struct TraceArgs {
+    /// Built-in config name (see `xtrace list`) or path to a text-format perfetto config
+    config: String,
+
+    /// Trace duration, e.g. 10s or 2m
+    #[arg(short, long, value_parser = utils::parse_duration)]
+    duration: Option<Duration>,
+
+    /// Ring buffer size in MB for built-in configs
+    #[arg(long, default_value_t = 64)]
+    buffer_mb: u32,
+
+    /// Extra atrace categories to record, e.g. --category binder_driver
+    #[arg(long = "category")]
+    categories: Vec<String>,
+
+    /// Apps to enable app-level atrace for, e.g. --app com.example.app
+    #[arg(long = "app")]
+    apps: Vec<String>,
+}
+
+impl TraceArgs {
+    fn options(&self) -> TraceOptions {
+        TraceOptions {
+            config: self.config.clone(),
+            duration: self.duration,
+            buffer_mb: self.buffer_mb,
+            categories: self.categories.clone(),
+            apps: self.apps.clone(),
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// List built-in trace configs
+    List,
+    /// Print the perfetto config that would be used
+    Show(TraceArgs),
+    /// Start a background trace
+    Start(TraceArgs),
+    /// Stop the background trace and pull the result
+    Stop {
+        /// Directory to save the trace to (default: log/xtrace/<timestamp>/trace)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Record a trace for a fixed duration (default: 10s) and pull the result
+    Record {
+        #[command(flatten)]
+        trace: TraceArgs,
+
+        /// Directory to save the trace to (default: log/xtrace/<timestamp>/trace)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+fn output_dir(output: Option<PathBuf>) -> Result<PathBuf> {
+    match output {
+        Some(dir) => Ok(dir),
+        None => Ok(OutputLayout::session("xtrace")?.dir(Category::Trace)),
+    }
+}
+
+fn start(args: &TraceArgs) -> Result<ActiveTrace> {
+    if let Some(active) = ActiveTrace::load()? {
+        if trace::is_running(&active) {
+            anyhow::bail!(
+                "A trace is already running (pid {}), run `xtrace stop` first",
+                active.pid
+            );
+        }
+    }
+
+    let active = trace::start_trace(None, &args.options())?;
+    active.save()?;
+    println!(
+        "Trace {} started (pid {}): {}",
+        active.label.cyan(),
+        active.pid,
+        active.remote_path
+    );
+    Ok(active)
+}
+
+fn stop(active: &ActiveTrace, output: Option<PathBuf>) -> Result<()> {
+    println!("Stopping trace {}...", active.label.cyan());
+    trace::stop_trace(active)?;
+    let path = trace::pull_trace(active, &output_dir(output)?)?;
+    ActiveTrace::clear()?;
+    println!("✓ Trace saved: {}", path.display());
+    println!("  Open it at https://ui.perfetto.dev");
+    Ok(())
+}
+
+fn record(args: &TraceArgs, output: Option<PathBuf>) -> Result<()> {
+    let duration = args.duration.unwrap_or(Duration::from_secs(10));
+    let mut options = args.options();
+    // 由 perfetto 自行结束，Ctrl-C 时提前停止
+    options.duration = Some(duration);
+
+    let running = Arc::new(AtomicBool::new(true));
+    let r = running.clone();
+    ctrlc::set_handler(move || {
+        r.store(false, Ordering::SeqCst);
+        utils::set_interrupt_flag();
+        println!("\n程序正在退出...");
+    })?;
+
+    let active = trace::start_trace(None, &options)?;
+    active.save()?;
+    println!(
+        "Recording {} for {:.0}s (pid {})",
+        active.label.cyan(),
+        duration.as_secs_f64(),
+        active.pid
+    );
+
+    let deadline = Instant::now() + duration;
+    while running.load(Ordering::SeqCst) && Instant::now() < deadline {
+        std::thread::sleep(Duration::from_millis(500));
+    }
+
+    stop(&active, output)
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+
+    if !matches!(args.command, Commands::List | Commands::Show(_)) && !utils::check_adb_connection()
+    {
+        anyhow::bail!("No Android devices connected");
+    }
+
+    match args.command {
+        Commands::List => {
+            for config in trace::CONFIGS {
+                println!("{:<12} {}", config.name.green(), config.description);
+            }
+            Ok(())
+        }
+        Commands::Show(trace_args) => {
+            print!("{}", trace::build_config(&trace_args.options())?);
+            Ok(())
+        }
+        Commands::Start(trace_args) => start(&trace_args).map(|_| ()),
+        Commands::Stop { output } => match ActiveTrace::load()? {
+            Some(active) => stop(&active, output),
+            None => anyhow::bail!("No active trace, start one with `xtrace start <config>`"),
+        },
+        Commands::Record { trace, output } => record(&trace, output),
+    }
+}